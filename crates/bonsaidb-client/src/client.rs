@@ -4,7 +4,7 @@ use std::fmt::Debug;
 use std::ops::Deref;
 #[cfg(feature = "test-util")]
 use std::sync::atomic::AtomicBool;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -16,15 +16,18 @@ use bonsaidb_core::arc_bytes::OwnedBytes;
 use bonsaidb_core::connection::{
     AsyncStorageConnection, Database, HasSession, IdentityReference, Session,
 };
+use bonsaidb_core::keyvalue::{KeyWatchEvent, Timestamp};
 use bonsaidb_core::networking::{
     AlterUserPermissionGroupMembership, AlterUserRoleMembership, AssumeIdentity, CreateDatabase,
-    CreateUser, DeleteDatabase, DeleteUser, ListAvailableSchemas, ListDatabases, LogOutSession,
-    MessageReceived, Payload, UnregisterSubscriber, CURRENT_PROTOCOL_VERSION,
+    CreateUser, DeleteDatabase, DeleteUser, KeyChanged, ListAvailableSchemas, ListDatabases,
+    LogOutSession, MessageReceived, Payload, QueryChunk, UnregisterSubscriber, UnwatchKeys,
+    CURRENT_PROTOCOL_VERSION,
 };
 use bonsaidb_core::permissions::Permissions;
+use bonsaidb_core::schema::view::map;
 use bonsaidb_core::schema::{Nameable, Schema, SchemaName, SchemaSummary, Schematic};
 use bonsaidb_utils::fast_async_lock;
-use flume::Sender;
+use flume::{Sender, TrySendError};
 use futures::future::BoxFuture;
 use futures::{Future, FutureExt};
 use parking_lot::Mutex;
@@ -32,7 +35,9 @@ use parking_lot::Mutex;
 use tokio::{runtime::Handle, task::JoinHandle};
 use url::Url;
 
-pub use self::remote_database::{AsyncRemoteDatabase, AsyncRemoteSubscriber};
+pub use self::remote_database::{
+    AsyncRemoteDatabase, AsyncRemoteKeyWatcher, AsyncRemoteSubscriber,
+};
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::sync::{BlockingClient, BlockingRemoteDatabase, BlockingRemoteSubscriber};
 use crate::builder::Async;
@@ -67,6 +72,133 @@ impl Deref for SubscriberMap {
     }
 }
 
+/// The maximum number of chunks that can be waiting to be received by a
+/// stream created with [`AsyncRemoteDatabase::query_by_name_streamed()`]. Once
+/// a stream falls this far behind, the client stops reading from its
+/// connection until the stream receives a chunk.
+pub const QUERY_STREAM_BUFFER_SIZE: usize = 16;
+
+/// The chunk receivers for queries being streamed from the server.
+#[derive(Debug, Clone, Default)]
+pub struct QueryStreamMap {
+    last_id: Arc<AtomicU64>,
+    streams: Arc<Mutex<HashMap<u64, flume::Sender<Vec<map::Serialized>>>>>,
+}
+
+impl QueryStreamMap {
+    pub fn register(&self) -> (u64, flume::Receiver<Vec<map::Serialized>>) {
+        let (sender, receiver) = flume::bounded(QUERY_STREAM_BUFFER_SIZE);
+        let id = self.last_id.fetch_add(1, Ordering::SeqCst);
+        let mut streams = self.streams.lock();
+        streams.insert(id, sender);
+        (id, receiver)
+    }
+
+    pub fn unregister(&self, id: u64) {
+        let mut streams = self.streams.lock();
+        streams.remove(&id);
+    }
+
+    /// Delivers `chunk` to its stream, waiting for room if the stream's
+    /// buffer is full.
+    async fn chunk_received(&self, chunk: QueryChunk) {
+        let sender = self.streams.lock().get(&chunk.stream_id).cloned();
+        if let Some(sender) = sender {
+            drop(sender.send_async(chunk.mappings).await);
+        }
+    }
+}
+
+/// The maximum number of events that can be waiting to be received by a key
+/// watcher created with [`AsyncRemoteDatabase::watch_keys()`]. Once a watcher
+/// falls this far behind, new events are discarded until there is room to
+/// deliver a [`KeyWatchEvent::Lagged`] event.
+pub const KEY_WATCHER_BUFFER_SIZE: usize = 256;
+
+/// The event receivers for key-value watchers registered with the server.
+#[derive(Debug, Clone, Default)]
+pub struct KeyWatcherMap {
+    last_id: Arc<AtomicU64>,
+    watchers: Arc<Mutex<HashMap<u64, KeyWatcherSender>>>,
+}
+
+#[derive(Debug)]
+struct KeyWatcherSender {
+    sender: flume::Sender<KeyWatchEvent>,
+    dropped_keys: u64,
+}
+
+impl KeyWatcherSender {
+    /// Sends `event`, returning false if the receiver has been dropped. If
+    /// the receiver is full, the event is counted as dropped and will be
+    /// reported in a [`KeyWatchEvent::Lagged`] event once there is room.
+    fn send(&mut self, event: KeyWatchEvent) -> bool {
+        if self.dropped_keys > 0 {
+            match self.sender.try_send(KeyWatchEvent::Lagged {
+                dropped_keys: self.dropped_keys,
+            }) {
+                Ok(()) => self.dropped_keys = 0,
+                Err(TrySendError::Full(_)) => {
+                    self.dropped_keys = self.dropped_keys.saturating_add(dropped_keys(&event));
+                    return true;
+                }
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+
+        match self.sender.try_send(event) {
+            Ok(()) => true,
+            Err(TrySendError::Full(event)) => {
+                self.dropped_keys = self.dropped_keys.saturating_add(dropped_keys(&event));
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+fn dropped_keys(event: &KeyWatchEvent) -> u64 {
+    match event {
+        KeyWatchEvent::Changed(_) => 1,
+        KeyWatchEvent::Lagged { dropped_keys } => *dropped_keys,
+    }
+}
+
+impl KeyWatcherMap {
+    pub fn register(&self) -> (u64, flume::Receiver<KeyWatchEvent>) {
+        let (sender, receiver) = flume::bounded(KEY_WATCHER_BUFFER_SIZE);
+        let id = self.last_id.fetch_add(1, Ordering::SeqCst);
+        let mut watchers = self.watchers.lock();
+        watchers.insert(
+            id,
+            KeyWatcherSender {
+                sender,
+                dropped_keys: 0,
+            },
+        );
+        (id, receiver)
+    }
+
+    pub fn unregister(&self, id: u64) {
+        let mut watchers = self.watchers.lock();
+        watchers.remove(&id);
+    }
+
+    pub fn clear(&self) {
+        let mut watchers = self.watchers.lock();
+        watchers.clear();
+    }
+
+    fn event_received(&self, event: KeyChanged) {
+        let mut watchers = self.watchers.lock();
+        if let Some(watcher) = watchers.get_mut(&event.watch_id) {
+            if !watcher.send(event.event) {
+                watchers.remove(&event.watch_id);
+            }
+        }
+    }
+}
+
 use bonsaidb_core::circulate::Message;
 
 #[cfg(all(feature = "websockets", not(target_arch = "wasm32")))]
@@ -230,6 +362,7 @@ pub struct AsyncClient {
     pub(crate) data: Arc<Data>,
     session: ClientSession,
     request_timeout: Duration,
+    request_deadline: Option<Timestamp>,
 }
 
 impl Drop for AsyncClient {
@@ -259,6 +392,8 @@ pub struct Data {
     connection_counter: Arc<AtomicU32>,
     request_id: AtomicU32,
     subscribers: SubscriberMap,
+    pub(crate) query_streams: QueryStreamMap,
+    pub(crate) key_watchers: KeyWatcherMap,
     #[cfg(feature = "test-util")]
     background_task_running: Arc<AtomicBool>,
 }
@@ -345,10 +480,34 @@ impl AsyncClient {
                 },
             ))),
         );
+        let query_streams = QueryStreamMap::default();
+        let callback_query_streams = query_streams.clone();
+        custom_apis.insert(
+            QueryChunk::name(),
+            Some(Arc::new(ApiCallback::<QueryChunk>::new(
+                move |chunk: QueryChunk| {
+                    let query_streams = callback_query_streams.clone();
+                    async move { query_streams.chunk_received(chunk).await }
+                },
+            ))),
+        );
+        let key_watchers = KeyWatcherMap::default();
+        let callback_key_watchers = key_watchers.clone();
+        custom_apis.insert(
+            KeyChanged::name(),
+            Some(Arc::new(ApiCallback::<KeyChanged>::new(
+                move |event: KeyChanged| {
+                    callback_key_watchers.event_received(event);
+                    async {}
+                },
+            ))),
+        );
         // Default timeouts to 1 minute.
         let connection = ConnectionInfo {
             url,
             subscribers,
+            query_streams,
+            key_watchers,
             connect_timeout: connect_timeout.unwrap_or(Duration::from_secs(60)),
             request_timeout: request_timeout.unwrap_or(Duration::from_secs(60)),
         };
@@ -385,6 +544,8 @@ impl AsyncClient {
         let connection_counter = Arc::new(AtomicU32::default());
         let request_timeout = server.request_timeout;
         let subscribers = server.subscribers.clone();
+        let query_streams = server.query_streams.clone();
+        let key_watchers = server.key_watchers.clone();
 
         let worker = sync::spawn_client(
             quic_worker::reconnecting_client_loop(
@@ -414,11 +575,14 @@ impl AsyncClient {
                 request_id: AtomicU32::default(),
                 effective_permissions: Mutex::default(),
                 subscribers,
+                query_streams,
+                key_watchers,
                 #[cfg(feature = "test-util")]
                 background_task_running,
             }),
             session: ClientSession::default(),
             request_timeout,
+            request_deadline: None,
         }
     }
 
@@ -433,6 +597,8 @@ impl AsyncClient {
         let connection_counter = Arc::new(AtomicU32::default());
         let request_timeout = server.request_timeout;
         let subscribers = server.subscribers.clone();
+        let query_streams = server.query_streams.clone();
+        let key_watchers = server.key_watchers.clone();
 
         let worker = sync::spawn_client(
             tungstenite_worker::reconnecting_client_loop(
@@ -462,11 +628,14 @@ impl AsyncClient {
                 connection_counter,
                 effective_permissions: Mutex::default(),
                 subscribers,
+                query_streams,
+                key_watchers,
                 #[cfg(feature = "test-util")]
                 background_task_running,
             }),
             session: ClientSession::default(),
             request_timeout,
+            request_deadline: None,
         }
     }
 
@@ -485,6 +654,7 @@ impl AsyncClient {
             request_receiver,
             Arc::new(custom_apis),
             server.subscribers.clone(),
+            server.key_watchers.clone(),
             connection_counter.clone(),
             None,
             server.connect_timeout,
@@ -507,11 +677,14 @@ impl AsyncClient {
                 connection_counter,
                 effective_permissions: Mutex::default(),
                 subscribers: server.subscribers,
+                query_streams: server.query_streams,
+                key_watchers: server.key_watchers,
                 #[cfg(feature = "test-util")]
                 background_task_running,
             }),
             session: ClientSession::default(),
             request_timeout: server.request_timeout,
+            request_deadline: None,
         }
     }
 
@@ -528,6 +701,7 @@ impl AsyncClient {
                 id: Some(id),
                 name,
                 value: Ok(bytes),
+                deadline: self.request_deadline,
             },
             responder: result_sender,
         })?;
@@ -656,6 +830,26 @@ impl AsyncClient {
         subscribers.remove(&id);
     }
 
+    pub(crate) async fn unwatch_keys_async(&self, database: String, id: u64) {
+        self.data.key_watchers.unregister(id);
+        drop(
+            self.send_api_request(&UnwatchKeys {
+                database,
+                watch_id: id,
+            })
+            .await,
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn unwatch_keys(&self, database: String, id: u64) {
+        self.data.key_watchers.unregister(id);
+        drop(self.send_blocking_api_request(&UnwatchKeys {
+            database,
+            watch_id: id,
+        }));
+    }
+
     fn remote_database<DB: bonsaidb_core::schema::Schema>(
         &self,
         name: &str,
@@ -688,6 +882,17 @@ impl AsyncClient {
     pub fn set_request_timeout(&mut self, timeout: impl Into<Duration>) {
         self.request_timeout = timeout.into();
     }
+
+    /// Sets the deadline sent to the server with each request from this
+    /// instance. The server stops working on requests whose deadline has
+    /// passed, returning [`bonsaidb_core::Error::DeadlineExceeded`] instead.
+    /// The deadline is compared against the server's clock.
+    ///
+    /// Each client has its own deadline. When cloning a client, this deadline
+    /// setting will be copied to the clone.
+    pub fn set_request_deadline(&mut self, deadline: impl Into<Option<Timestamp>>) {
+        self.request_deadline = deadline.into();
+    }
 }
 
 impl HasSession for AsyncClient {
@@ -793,6 +998,7 @@ impl AsyncStorageConnection for AsyncClient {
                 connection_id: self.data.connection_counter.load(Ordering::SeqCst),
             },
             request_timeout: self.request_timeout,
+            request_deadline: self.request_deadline,
         })
     }
 
@@ -810,6 +1016,7 @@ impl AsyncStorageConnection for AsyncClient {
                 connection_id: self.data.connection_counter.load(Ordering::SeqCst),
             },
             request_timeout: self.request_timeout,
+            request_deadline: self.request_deadline,
         })
     }
 
@@ -1049,6 +1256,8 @@ async fn disconnect_pending_requests(
 struct ConnectionInfo {
     pub url: Url,
     pub subscribers: SubscriberMap,
+    pub query_streams: QueryStreamMap,
+    pub key_watchers: KeyWatcherMap,
     #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
     pub connect_timeout: Duration,
     pub request_timeout: Duration,
@@ -33,6 +33,7 @@ pub(super) async fn reconnecting_client_loop(
     }
 
     server.subscribers.clear();
+    server.key_watchers.clear();
     let mut pending_error = None;
     while let Ok(request) = request_receiver.recv_async().await {
         if let Some(pending_error) = pending_error.take() {
@@ -4,18 +4,25 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use bonsaidb_core::connection::{
     AccessPolicy, AsyncConnection, AsyncLowLevelConnection, HasSchema, HasSession, Range,
-    SerializedQueryKey, Session, Sort,
+    SerializedQueryKey, Session, Sort, ViewIndexState,
 };
 use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
+use bonsaidb_core::keyvalue::{KeyWatchEvent, Timestamp};
 use bonsaidb_core::networking::{
-    ApplyTransaction, Compact, CompactCollection, CompactKeyValueStore, Count, DeleteDocs, Get,
-    GetMultiple, LastTransactionId, List, ListExecutedTransactions, ListHeaders, Query,
-    QueryWithDocs, Reduce, ReduceGrouped,
+    ApplyTransaction, ApplyTransactionWithViews, Compact, CompactCollection, CompactKeyValueStore,
+    Count, CountGrouped, DeleteAll, DeleteDocs, Exists, Get, GetMultiple, GetTombstone,
+    GetViewIndexState, LastTransactionId, List, ListExecutedTransactions, ListHeaders,
+    PurgeTombstones, Query, QueryKeys, QueryWithDocs, RebuildView, Reduce, ReduceGrouped,
+    StreamQuery, WatchKeys,
 };
-use bonsaidb_core::schema::view::map::MappedSerializedValue;
+use bonsaidb_core::schema::view::map::{MappedSerializedCount, MappedSerializedValue};
 use bonsaidb_core::schema::{self, CollectionName, Schematic, ViewName};
-use bonsaidb_core::transaction::{Executed, OperationResult, Transaction};
+use bonsaidb_core::transaction::{Executed, OperationResult, Transaction, ViewChanges};
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::BoxStream;
+use futures::{FutureExt, Stream, StreamExt};
 
+use crate::client::QueryStreamMap;
 use crate::AsyncClient;
 
 mod pubsub;
@@ -56,6 +63,211 @@ impl AsyncRemoteDatabase {
     }
 }
 
+impl AsyncRemoteDatabase {
+    /// Queries for view entries matching [`View`](schema::View), receiving
+    /// the results in chunks of at most `chunk_size` mappings. Unlike
+    /// [`AsyncLowLevelConnection::query_by_name()`], the server never needs to
+    /// send the entire result set in a single response, which allows querying
+    /// views whose results would otherwise exceed the maximum payload size.
+    ///
+    /// The chunks are returned in the same order as the results of
+    /// [`AsyncLowLevelConnection::query_by_name()`].
+    #[must_use]
+    pub fn query_by_name_streamed(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+        chunk_size: u32,
+    ) -> BoxStream<'static, Result<Vec<schema::view::map::Serialized>, bonsaidb_core::Error>> {
+        let query_streams = self.client.data.query_streams.clone();
+        let (stream_id, chunks) = query_streams.register();
+        let client = self.client.clone();
+        let request = StreamQuery {
+            stream_id,
+            chunk_size,
+            query: Query {
+                database: self.name.to_string(),
+                view: view.clone(),
+                key,
+                order,
+                limit,
+                access_policy,
+            },
+        };
+        let completion = async move { Ok(client.send_api_request(&request).await?) }.boxed();
+        let stream = QueryStream {
+            stream_id,
+            query_streams,
+            chunks,
+            completion: Some(completion),
+            expected_chunks: 0,
+            received_chunks: 0,
+        };
+        futures::stream::unfold(stream, |mut stream| async move {
+            stream
+                .next_chunk()
+                .await
+                .transpose()
+                .map(|chunk| (chunk, stream))
+        })
+        .boxed()
+    }
+
+    /// Watches the key-value store for persisted changes to keys in
+    /// `namespace`. If `prefix` is provided, only changes to keys starting
+    /// with `prefix` are delivered. Only changes persisted after this function
+    /// returns are delivered.
+    ///
+    /// Events are buffered by the client. If the returned watcher falls more
+    /// than [`KEY_WATCHER_BUFFER_SIZE`](crate::KEY_WATCHER_BUFFER_SIZE) events
+    /// behind, new changes are discarded and a [`KeyWatchEvent::Lagged`]
+    /// event is delivered once there is room. The stream ends if the client
+    /// is disconnected from the server.
+    pub async fn watch_keys(
+        &self,
+        namespace: Option<String>,
+        prefix: Option<String>,
+    ) -> Result<AsyncRemoteKeyWatcher, bonsaidb_core::Error> {
+        let (watch_id, events) = self.client.data.key_watchers.register();
+        let result = self
+            .client
+            .send_api_request(&WatchKeys {
+                database: self.name.to_string(),
+                watch_id,
+                namespace,
+                prefix,
+            })
+            .await;
+        if let Err(err) = result {
+            self.client.data.key_watchers.unregister(watch_id);
+            return Err(err.into());
+        }
+
+        Ok(AsyncRemoteKeyWatcher {
+            client: self.client.clone(),
+            database: self.name.clone(),
+            id: watch_id,
+            events: events.into_stream().boxed(),
+            #[cfg(not(target_arch = "wasm32"))]
+            tokio: tokio::runtime::Handle::try_current().ok().map(Arc::new),
+        })
+    }
+}
+
+/// A stream of [`KeyWatchEvent`]s from a remote server, created with
+/// [`AsyncRemoteDatabase::watch_keys()`]. Dropping this type stops watching.
+#[must_use = "the key watcher stops watching when dropped"]
+pub struct AsyncRemoteKeyWatcher {
+    client: AsyncClient,
+    database: Arc<String>,
+    id: u64,
+    events: BoxStream<'static, KeyWatchEvent>,
+    #[cfg(not(target_arch = "wasm32"))]
+    tokio: Option<Arc<tokio::runtime::Handle>>,
+}
+
+impl std::fmt::Debug for AsyncRemoteKeyWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncRemoteKeyWatcher")
+            .field("database", &self.database)
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for AsyncRemoteKeyWatcher {
+    type Item = KeyWatchEvent;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for AsyncRemoteKeyWatcher {
+    fn drop(&mut self) {
+        let client = self.client.clone();
+        let database = self.database.to_string();
+        let watch_id = self.id;
+        wasm_bindgen_futures::spawn_local(async move {
+            client.unwatch_keys_async(database, watch_id).await;
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for AsyncRemoteKeyWatcher {
+    fn drop(&mut self) {
+        if let Some(tokio) = &self.tokio {
+            let client = self.client.clone();
+            let database = self.database.to_string();
+            let watch_id = self.id;
+            tokio.spawn(async move {
+                client.unwatch_keys_async(database, watch_id).await;
+            });
+        } else {
+            self.client.unwatch_keys(self.database.to_string(), self.id);
+        }
+    }
+}
+
+struct QueryStream {
+    stream_id: u64,
+    query_streams: QueryStreamMap,
+    chunks: flume::Receiver<Vec<schema::view::map::Serialized>>,
+    completion: Option<BoxFuture<'static, Result<u64, bonsaidb_core::Error>>>,
+    expected_chunks: u64,
+    received_chunks: u64,
+}
+
+impl QueryStream {
+    async fn next_chunk(
+        &mut self,
+    ) -> Result<Option<Vec<schema::view::map::Serialized>>, bonsaidb_core::Error> {
+        if let Some(completion) = &mut self.completion {
+            match future::select(self.chunks.recv_async(), completion).await {
+                Either::Left((chunk, _)) => {
+                    self.received_chunks += 1;
+                    return chunk.map(Some).map_err(|_| stream_closed());
+                }
+                Either::Right((result, _)) => {
+                    self.completion = None;
+                    self.expected_chunks = result?;
+                }
+            }
+        }
+
+        // The chunks are delivered independently of the response, so some
+        // chunks may still be in flight after the response has arrived.
+        if self.received_chunks < self.expected_chunks {
+            self.received_chunks += 1;
+            self.chunks
+                .recv_async()
+                .await
+                .map(Some)
+                .map_err(|_| stream_closed())
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Drop for QueryStream {
+    fn drop(&mut self) {
+        self.query_streams.unregister(self.stream_id);
+    }
+}
+
+fn stream_closed() -> bonsaidb_core::Error {
+    bonsaidb_core::Error::other("bonsaidb-client", "query stream closed unexpectedly")
+}
+
 impl HasSession for AsyncRemoteDatabase {
     fn session(&self) -> Option<&Session> {
         self.client.session()
@@ -126,6 +338,19 @@ impl AsyncLowLevelConnection for AsyncRemoteDatabase {
             .await?)
     }
 
+    async fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&ApplyTransactionWithViews {
+                database: self.name.to_string(),
+                transaction,
+            })
+            .await?)
+    }
+
     async fn get_from_collection(
         &self,
         id: DocumentId,
@@ -141,6 +366,46 @@ impl AsyncLowLevelConnection for AsyncRemoteDatabase {
             .await?)
     }
 
+    async fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&Exists {
+                database: self.name.to_string(),
+                collection: collection.clone(),
+                id,
+            })
+            .await?)
+    }
+
+    async fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&GetTombstone {
+                database: self.name.to_string(),
+                collection: collection.clone(),
+                id,
+            })
+            .await?)
+    }
+
+    async fn purge_tombstones(&self, before: Timestamp) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&PurgeTombstones {
+                database: self.name.to_string(),
+                before,
+            })
+            .await?)
+    }
+
     async fn get_multiple_from_collection(
         &self,
         ids: &[DocumentId],
@@ -209,6 +474,19 @@ impl AsyncLowLevelConnection for AsyncRemoteDatabase {
             .await?)
     }
 
+    async fn delete_all_from_collection(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&DeleteAll {
+                database: self.name.to_string(),
+                collection: collection.clone(),
+            })
+            .await?)
+    }
+
     async fn compact_collection_by_name(
         &self,
         collection: CollectionName,
@@ -242,6 +520,27 @@ impl AsyncLowLevelConnection for AsyncRemoteDatabase {
             .await?)
     }
 
+    async fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<schema::view::map::MappedSerializedKey>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&QueryKeys(Query {
+                database: self.name.to_string(),
+                view: view.clone(),
+                key,
+                order,
+                limit,
+                access_policy,
+            }))
+            .await?)
+    }
+
     async fn query_by_name_with_docs(
         &self,
         view: &ViewName,
@@ -298,6 +597,46 @@ impl AsyncLowLevelConnection for AsyncRemoteDatabase {
             .await?)
     }
 
+    async fn count_grouped_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&CountGrouped(Reduce {
+                database: self.name.to_string(),
+                view: view.clone(),
+                key,
+                access_policy,
+            }))
+            .await?)
+    }
+
+    async fn view_index_state_by_name(
+        &self,
+        view: &ViewName,
+    ) -> Result<ViewIndexState, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&GetViewIndexState {
+                database: self.name.to_string(),
+                view: view.clone(),
+            })
+            .await?)
+    }
+
+    async fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), bonsaidb_core::Error> {
+        self.client
+            .send_api_request(&RebuildView {
+                database: self.name.to_string(),
+                view: view.clone(),
+            })
+            .await?;
+        Ok(())
+    }
+
     async fn delete_docs_by_name(
         &self,
         view: &ViewName,
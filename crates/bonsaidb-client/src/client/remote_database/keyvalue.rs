@@ -1,6 +1,9 @@
 use async_trait::async_trait;
-use bonsaidb_core::keyvalue::AsyncKeyValue;
-use bonsaidb_core::networking::ExecuteKeyOperation;
+use bonsaidb_core::keyvalue::{AsyncKeyValue, ExpiringKey, Output, Pipeline, Timestamp, Value};
+use bonsaidb_core::networking::{
+    ExecuteKeyOperation, ExecuteKeyPipeline, GetKeyExpirations, KeysExpiringBetween,
+    RenameKeyNamespace, RestoreKeyNamespace, ScanKeyNamespace,
+};
 
 #[async_trait]
 impl AsyncKeyValue for super::AsyncRemoteDatabase {
@@ -17,4 +20,96 @@ impl AsyncKeyValue for super::AsyncRemoteDatabase {
             })
             .await?)
     }
+
+    async fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&KeysExpiringBetween {
+                database: self.name.to_string(),
+                start,
+                end,
+            })
+            .await?)
+    }
+
+    async fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&GetKeyExpirations {
+                database: self.name.to_string(),
+                namespace,
+                keys,
+            })
+            .await?)
+    }
+
+    async fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&ScanKeyNamespace {
+                database: self.name.to_string(),
+                namespace,
+                after,
+                limit,
+            })
+            .await?)
+    }
+
+    async fn rename_namespace(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&RenameKeyNamespace {
+                database: self.name.to_string(),
+                from: from.map(String::from),
+                to: to.map(String::from),
+            })
+            .await?)
+    }
+
+    async fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&RestoreKeyNamespace {
+                database: self.name.to_string(),
+                namespace: namespace.map(String::from),
+                timestamp,
+                target: target.map(String::from),
+            })
+            .await?)
+    }
+
+    async fn execute_key_pipeline(
+        &self,
+        pipeline: Pipeline,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        Ok(self
+            .client
+            .send_api_request(&ExecuteKeyPipeline {
+                database: self.name.to_string(),
+                pipeline,
+            })
+            .await?)
+    }
 }
@@ -8,17 +8,20 @@ use bonsaidb_core::api;
 use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::connection::{
     AccessPolicy, Connection, Database, HasSchema, HasSession, IdentityReference,
-    LowLevelConnection, Range, SerializedQueryKey, Sort, StorageConnection,
+    LowLevelConnection, Range, SerializedQueryKey, Sort, StorageConnection, ViewIndexState,
 };
 use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
-use bonsaidb_core::keyvalue::KeyValue;
+use bonsaidb_core::keyvalue::{ExpiringKey, KeyValue, Output, Pipeline, Timestamp, Value};
 use bonsaidb_core::networking::{
-    AlterUserPermissionGroupMembership, AlterUserRoleMembership, ApplyTransaction, AssumeIdentity,
-    Compact, CompactCollection, CompactKeyValueStore, Count, CreateDatabase, CreateSubscriber,
-    CreateUser, DeleteDatabase, DeleteDocs, DeleteUser, ExecuteKeyOperation, Get, GetMultiple,
+    AlterUserPermissionGroupMembership, AlterUserRoleMembership, ApplyTransaction,
+    ApplyTransactionWithViews, AssumeIdentity, Compact, CompactCollection, CompactKeyValueStore,
+    Count, CountGrouped, CreateDatabase, CreateSubscriber, CreateUser, DeleteAll, DeleteDatabase,
+    DeleteDocs, DeleteUser, ExecuteKeyOperation, ExecuteKeyPipeline, Exists, Get,
+    GetKeyExpirations, GetMultiple, GetTombstone, GetViewIndexState, KeysExpiringBetween,
     LastTransactionId, List, ListAvailableSchemas, ListDatabases, ListExecutedTransactions,
-    ListHeaders, Publish, PublishToAll, Query, QueryWithDocs, Reduce, ReduceGrouped, SubscribeTo,
-    UnsubscribeFrom, CURRENT_PROTOCOL_VERSION,
+    ListHeaders, Publish, PublishToAll, PurgeTombstones, Query, QueryKeys, QueryWithDocs,
+    RebuildView, Reduce, ReduceGrouped, RenameKeyNamespace, RestoreKeyNamespace, ScanKeyNamespace,
+    SubscribeTo, UnsubscribeFrom, CURRENT_PROTOCOL_VERSION,
 };
 use bonsaidb_core::pubsub::{AsyncSubscriber, PubSub, Receiver, Subscriber};
 use bonsaidb_core::schema::view::map;
@@ -102,6 +105,17 @@ impl BlockingClient {
     pub fn set_request_timeout(&mut self, timeout: impl Into<Duration>) {
         self.0.request_timeout = timeout.into();
     }
+
+    /// Sets the deadline sent to the server with each request from this
+    /// instance. The server stops working on requests whose deadline has
+    /// passed, returning [`bonsaidb_core::Error::DeadlineExceeded`] instead.
+    /// The deadline is compared against the server's clock.
+    ///
+    /// Each client has its own deadline. When cloning a client, this deadline
+    /// setting will be copied to the clone.
+    pub fn set_request_deadline(&mut self, deadline: impl Into<Option<Timestamp>>) {
+        self.0.request_deadline = deadline.into();
+    }
 }
 
 impl From<AsyncClient> for BlockingClient {
@@ -215,6 +229,7 @@ impl StorageConnection for BlockingClient {
                 connection_id: self.0.data.connection_counter.load(Ordering::SeqCst),
             },
             request_timeout: self.0.request_timeout,
+            request_deadline: self.0.request_deadline,
         }))
     }
 
@@ -230,6 +245,7 @@ impl StorageConnection for BlockingClient {
                 connection_id: self.0.data.connection_counter.load(Ordering::SeqCst),
             },
             request_timeout: self.0.request_timeout,
+            request_deadline: self.0.request_deadline,
         }))
     }
 
@@ -374,6 +390,25 @@ impl LowLevelConnection for BlockingRemoteDatabase {
         })?)
     }
 
+    fn apply_transaction_with_views(
+        &self,
+        transaction: bonsaidb_core::transaction::Transaction,
+    ) -> Result<
+        (
+            Vec<bonsaidb_core::transaction::OperationResult>,
+            bonsaidb_core::transaction::ViewChanges,
+        ),
+        bonsaidb_core::Error,
+    > {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&ApplyTransactionWithViews {
+                database: self.0.name.to_string(),
+                transaction,
+            })?)
+    }
+
     fn get_from_collection(
         &self,
         id: bonsaidb_core::document::DocumentId,
@@ -386,6 +421,37 @@ impl LowLevelConnection for BlockingRemoteDatabase {
         })?)
     }
 
+    fn exists_in_collection(
+        &self,
+        id: bonsaidb_core::document::DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, bonsaidb_core::Error> {
+        Ok(self.0.client.send_blocking_api_request(&Exists {
+            database: self.0.name.to_string(),
+            collection: collection.clone(),
+            id,
+        })?)
+    }
+
+    fn get_tombstone_from_collection(
+        &self,
+        id: bonsaidb_core::document::DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        Ok(self.0.client.send_blocking_api_request(&GetTombstone {
+            database: self.0.name.to_string(),
+            collection: collection.clone(),
+            id,
+        })?)
+    }
+
+    fn purge_tombstones(&self, before: Timestamp) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self.0.client.send_blocking_api_request(&PurgeTombstones {
+            database: self.0.name.to_string(),
+            before,
+        })?)
+    }
+
     fn get_multiple_from_collection(
         &self,
         ids: &[bonsaidb_core::document::DocumentId],
@@ -442,6 +508,16 @@ impl LowLevelConnection for BlockingRemoteDatabase {
         })?)
     }
 
+    fn delete_all_from_collection(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self.0.client.send_blocking_api_request(&DeleteAll {
+            database: self.0.name.to_string(),
+            collection: collection.clone(),
+        })?)
+    }
+
     fn compact_collection_by_name(
         &self,
         collection: CollectionName,
@@ -471,6 +547,24 @@ impl LowLevelConnection for BlockingRemoteDatabase {
         })?)
     }
 
+    fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<map::MappedSerializedKey>, bonsaidb_core::Error> {
+        Ok(self.0.client.send_blocking_api_request(&QueryKeys(Query {
+            database: self.0.name.to_string(),
+            view: view.clone(),
+            key,
+            order,
+            limit,
+            access_policy,
+        }))?)
+    }
+
     fn query_by_name_with_docs(
         &self,
         view: &bonsaidb_core::schema::ViewName,
@@ -529,6 +623,48 @@ impl LowLevelConnection for BlockingRemoteDatabase {
             }))?)
     }
 
+    fn count_grouped_by_name(
+        &self,
+        view: &bonsaidb_core::schema::ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<bonsaidb_core::schema::view::map::MappedSerializedCount>, bonsaidb_core::Error>
+    {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&CountGrouped(Reduce {
+                database: self.0.name.to_string(),
+                view: view.clone(),
+                key,
+                access_policy,
+            }))?)
+    }
+
+    fn view_index_state_by_name(
+        &self,
+        view: &bonsaidb_core::schema::ViewName,
+    ) -> Result<ViewIndexState, bonsaidb_core::Error> {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&GetViewIndexState {
+                database: self.0.name.to_string(),
+                view: view.clone(),
+            })?)
+    }
+
+    fn rebuild_view_by_name(
+        &self,
+        view: &bonsaidb_core::schema::ViewName,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.0.client.send_blocking_api_request(&RebuildView {
+            database: self.0.name.to_string(),
+            view: view.clone(),
+        })?;
+        Ok(())
+    }
+
     fn delete_docs_by_name(
         &self,
         view: &bonsaidb_core::schema::ViewName,
@@ -642,6 +778,95 @@ impl KeyValue for BlockingRemoteDatabase {
                 op,
             })?)
     }
+
+    fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, bonsaidb_core::Error> {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&KeysExpiringBetween {
+                database: self.0.name.to_string(),
+                start,
+                end,
+            })?)
+    }
+
+    fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, bonsaidb_core::Error> {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&GetKeyExpirations {
+                database: self.0.name.to_string(),
+                namespace,
+                keys,
+            })?)
+    }
+
+    fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, bonsaidb_core::Error> {
+        Ok(self.0.client.send_blocking_api_request(&ScanKeyNamespace {
+            database: self.0.name.to_string(),
+            namespace,
+            after,
+            limit,
+        })?)
+    }
+
+    fn rename_namespace(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&RenameKeyNamespace {
+                database: self.0.name.to_string(),
+                from: from.map(String::from),
+                to: to.map(String::from),
+            })?)
+    }
+
+    fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&RestoreKeyNamespace {
+                database: self.0.name.to_string(),
+                namespace: namespace.map(String::from),
+                timestamp,
+                target: target.map(String::from),
+            })?)
+    }
+
+    fn execute_key_pipeline(
+        &self,
+        pipeline: Pipeline,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        Ok(self
+            .0
+            .client
+            .send_blocking_api_request(&ExecuteKeyPipeline {
+                database: self.0.name.to_string(),
+                pipeline,
+            })?)
+    }
 }
 
 pub enum Tokio {
@@ -29,6 +29,7 @@ pub(super) async fn reconnecting_client_loop(
     let mut pending_error = None;
     while let Ok(request) = {
         server.subscribers.clear();
+        server.key_watchers.clear();
         request_receiver.recv_async().await
     } {
         if let Some(pending_error) = pending_error.take() {
@@ -13,8 +13,8 @@ use wasm_bindgen::{JsCast, JsValue};
 use web_sys::{CloseEvent, ErrorEvent, MessageEvent, WebSocket};
 
 use crate::client::{
-    disconnect_pending_requests, AnyApiCallback, OutstandingRequestMapHandle, PendingRequest,
-    SubscriberMap,
+    disconnect_pending_requests, AnyApiCallback, KeyWatcherMap, OutstandingRequestMapHandle,
+    PendingRequest, SubscriberMap,
 };
 use crate::Error;
 
@@ -25,6 +25,7 @@ pub fn spawn_client(
     request_receiver: Receiver<PendingRequest>,
     custom_apis: Arc<HashMap<ApiName, Option<Arc<dyn AnyApiCallback>>>>,
     subscribers: SubscriberMap,
+    key_watchers: KeyWatcherMap,
     connection_counter: Arc<AtomicU32>,
     pending_error: Option<Error>,
     connect_timeout: Duration,
@@ -35,6 +36,7 @@ pub fn spawn_client(
         request_receiver,
         custom_apis,
         subscribers,
+        key_watchers,
         connection_counter,
         pending_error,
         connect_timeout,
@@ -48,11 +50,13 @@ async fn create_websocket(
     request_receiver: Receiver<PendingRequest>,
     custom_apis: Arc<HashMap<ApiName, Option<Arc<dyn AnyApiCallback>>>>,
     subscribers: SubscriberMap,
+    key_watchers: KeyWatcherMap,
     connection_counter: Arc<AtomicU32>,
     pending_error: Option<Error>,
     connect_timeout: Duration,
 ) {
     subscribers.clear();
+    key_watchers.clear();
 
     // Receive the next/initial request when we are reconnecting.
     let Ok(mut initial_request) = request_receiver.recv_async().await else { return };
@@ -80,6 +84,7 @@ async fn create_websocket(
                 request_receiver,
                 custom_apis.clone(),
                 subscribers,
+                key_watchers,
                 connection_counter,
                 None,
                 connect_timeout,
@@ -134,6 +139,7 @@ async fn create_websocket(
         outstanding_requests,
         custom_apis.clone(),
         subscribers.clone(),
+        key_watchers.clone(),
         connection_counter.clone(),
         connect_timeout,
     );
@@ -316,6 +322,7 @@ fn on_close_callback(
     outstanding_requests: OutstandingRequestMapHandle,
     custom_apis: Arc<HashMap<ApiName, Option<Arc<dyn AnyApiCallback>>>>,
     subscribers: SubscriberMap,
+    key_watchers: KeyWatcherMap,
     connection_counter: Arc<AtomicU32>,
     connect_timeout: Duration,
 ) -> JsValue {
@@ -346,6 +353,7 @@ fn on_close_callback(
                 request_receiver,
                 custom_apis.clone(),
                 subscribers,
+                key_watchers,
                 connection_counter,
                 pending_error,
                 connect_timeout,
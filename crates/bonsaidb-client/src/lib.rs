@@ -25,7 +25,10 @@ mod error;
 pub use fabruic;
 
 pub use self::builder::Builder;
-pub use self::client::{ApiCallback, AsyncClient, AsyncRemoteDatabase, AsyncRemoteSubscriber};
+pub use self::client::{
+    ApiCallback, AsyncClient, AsyncRemoteDatabase, AsyncRemoteKeyWatcher, AsyncRemoteSubscriber,
+    KEY_WATCHER_BUFFER_SIZE, QUERY_STREAM_BUFFER_SIZE,
+};
 #[cfg(not(target_arch = "wasm32"))]
 pub use self::client::{BlockingClient, BlockingRemoteDatabase, BlockingRemoteSubscriber};
 pub use self::error::{ApiError, Error};
@@ -15,22 +15,28 @@ use zeroize::Zeroize;
 
 use crate::admin::{Role, User};
 use crate::document::{
-    CollectionDocument, CollectionHeader, Document, HasHeader, Header, OwnedDocument,
+    CollectionDocument, CollectionHeader, Document, DocumentId, HasHeader, Header, OwnedDocument,
 };
 use crate::key::{ByteSource, IntoPrefixRange, Key, KeyEncoding, KeyKind, KeyVisitor};
 use crate::permissions::Permissions;
 use crate::schema::view::map::MappedDocuments;
 use crate::schema::{
-    self, Map, MappedValue, Nameable, NamedReference, Schema, SchemaName, SchemaSummary,
-    SerializedCollection,
+    self, CollectionName, Map, MappedValue, Nameable, NamedReference, Schema, SchemaName,
+    SchemaSummary, SerializedCollection, ViewName,
 };
 use crate::{transaction, Error};
 
+mod collection_transaction;
+mod event_log;
 mod has_session;
 mod lowlevel;
+mod transaction_stream;
 
+pub use self::collection_transaction::{AsyncCollectionTransaction, CollectionTransaction};
+pub use self::event_log::{AsyncEventLog, EventLog, EventReplay, LoggedEvent};
 pub use self::has_session::HasSession;
 pub use self::lowlevel::{AsyncLowLevelConnection, HasSchema, LowLevelConnection};
+pub use self::transaction_stream::TransactionStream;
 
 /// A connection to a database's [`Schema`](schema::Schema), giving access to
 /// [`Collection`s](crate::schema::Collection) and
@@ -54,10 +60,49 @@ pub trait Connection: LowLevelConnection + Sized + Send + Sync {
         View::new(self)
     }
 
+    /// Accesses an append-only [`EventLog`] whose events are stored in the
+    /// collection `C`.
+    fn event_log<C: schema::SerializedCollection>(&self) -> EventLog<'_, Self, C> {
+        EventLog::new(self)
+    }
+
+    /// Returns the names of the collections defined by this database's
+    /// [`Schema`](schema::Schema), in ascending order.
+    fn collection_names(&self) -> Vec<CollectionName> {
+        let mut names = self.schematic().collections().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Returns the names of the views defined for `collection` by this
+    /// database's [`Schema`](schema::Schema), in ascending order. If the
+    /// schema does not contain `collection`, no names are returned.
+    fn view_names(&self, collection: &CollectionName) -> Vec<ViewName> {
+        let mut names = self
+            .schematic()
+            .views_in_collection(collection)
+            .map(|view| view.view_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Returns a summary of this database's [`Schema`](schema::Schema),
+    /// including the primary key of each collection and the key and update
+    /// policy of each view. The summary can be used to query the database
+    /// without access to the Rust types that define the schema.
+    fn schema_summary(&self) -> SchemaSummary {
+        SchemaSummary::from(self.schematic())
+    }
+
     /// Lists [executed transactions](transaction::Executed) from this
-    /// [`Schema`](schema::Schema). By default, a maximum of 1000 entries will
-    /// be returned, but that limit can be overridden by setting `result_limit`.
-    /// A hard limit of 100,000 results will be returned. To begin listing after
+    /// [`Schema`](schema::Schema). By default, a maximum of
+    /// [`LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT`](crate::limits::LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT)
+    /// entries will be returned, but that limit can be overridden by setting
+    /// `result_limit`. The storage enforces a hard limit on the number of
+    /// results, which defaults to
+    /// [`LIST_TRANSACTIONS_MAX_RESULTS`](crate::limits::LIST_TRANSACTIONS_MAX_RESULTS)
+    /// and can be configured when opening the storage. To begin listing after
     /// another known `transaction_id`, pass `transaction_id + 1` into
     /// `starting_id`.
     fn list_executed_transactions(
@@ -69,6 +114,39 @@ pub trait Connection: LowLevelConnection + Sized + Send + Sync {
     /// Fetches the last transaction id that has been committed, if any.
     fn last_transaction_id(&self) -> Result<Option<u64>, Error>;
 
+    /// Builds and applies a transaction, retrying on conflict.
+    ///
+    /// `build` is invoked with an empty [`Transaction`](transaction::Transaction)
+    /// to populate. It should read any documents it needs from this connection
+    /// each time it is invoked. If applying the transaction fails with
+    /// [`Error::DocumentConflict`], `build` is invoked again with a new
+    /// transaction, up to `retry_limit` additional times. Retries are delayed
+    /// by an exponential backoff starting at 1 millisecond, doubling up to 100
+    /// milliseconds.
+    ///
+    /// On success, the value returned from `build` is returned. If every
+    /// attempt conflicts, the last [`Error::DocumentConflict`] is returned.
+    /// Errors returned from `build` are returned immediately.
+    fn transaction<T, F>(&self, mut retry_limit: usize, mut build: F) -> Result<T, Error>
+    where
+        F: FnMut(&mut transaction::Transaction) -> Result<T, Error>,
+    {
+        let mut backoff = std::time::Duration::from_millis(1);
+        loop {
+            let mut tx = transaction::Transaction::new();
+            let value = build(&mut tx)?;
+            match tx.apply(self) {
+                Ok(_) => return Ok(value),
+                Err(Error::DocumentConflict(..)) if retry_limit > 0 => {
+                    retry_limit -= 1;
+                    std::thread::sleep(backoff);
+                    backoff = (backoff * 2).min(std::time::Duration::from_millis(100));
+                }
+                Err(other) => return Err(other),
+            }
+        }
+    }
+
     /// Compacts the entire database to reclaim unused disk space.
     ///
     /// This process is done by writing data to a new file and swapping the file
@@ -96,6 +174,28 @@ pub trait Connection: LowLevelConnection + Sized + Send + Sync {
         self.compact_collection_by_name(C::collection_name())
     }
 
+    /// Returns how up-to-date the index of the view `V` is.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::ViewNotFound`]: `V` is not part of this database's schema.
+    fn view_index_state<V: schema::SerializedView>(&self) -> Result<ViewIndexState, Error> {
+        let view = self.schematic().view::<V>()?;
+        self.view_index_state_by_name(&view.view_name())
+    }
+
+    /// Discards the index of the view `V` and regenerates it from every
+    /// document in its collection. This function returns once the index has
+    /// been rebuilt.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::ViewNotFound`]: `V` is not part of this database's schema.
+    fn rebuild_view<V: schema::SerializedView>(&self) -> Result<(), Error> {
+        let view = self.schematic().view::<V>()?;
+        self.rebuild_view_by_name(&view.view_name())
+    }
+
     /// Compacts the key value store to reclaim unused disk space.
     ///
     /// This process is done by writing data to a new file and swapping the file
@@ -221,6 +321,13 @@ where
 
     /// Adds a new `Document<Cl>` with the given `id` and contents `item`.
     ///
+    /// Automatic id assignment chooses an id after the largest id ever stored
+    /// in the collection, so it will not collide with ids inserted this way.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DocumentConflict`]: a document with `id` already exists.
+    ///
     /// ```rust
     /// # bonsaidb_core::__doctest_prelude!();
     /// # use bonsaidb_core::connection::Connection;
@@ -273,6 +380,58 @@ where
         self.connection.insert::<Cl, _, B>(Some(id), contents)
     }
 
+    /// Adds a new `Document<Cl>` with the caller-chosen `id` and contents
+    /// `item`, ignoring [`SerializedCollection::natural_id()`].
+    ///
+    /// This can be used to preserve ids when importing documents from another
+    /// system. The storage backend never automatically assigns `id` to another
+    /// document, even after the inserted document is deleted.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DocumentConflict`]: a document with `id` already exists.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: &C) -> Result<(), Error> {
+    /// let inserted_header = db
+    ///     .collection::<MyCollection>()
+    ///     .push_with_id(&42, &MyCollection::default())?;
+    /// println!(
+    ///     "Inserted id {} with revision {}",
+    ///     inserted_header.id, inserted_header.revision
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn push_with_id<PrimaryKey>(
+        &self,
+        id: &PrimaryKey,
+        item: &<Cl as SerializedCollection>::Contents,
+    ) -> Result<CollectionHeader<Cl::PrimaryKey>, crate::Error>
+    where
+        Cl: schema::SerializedCollection,
+        PrimaryKey: KeyEncoding<Cl::PrimaryKey> + ?Sized,
+    {
+        let contents = Cl::serialize(item)?;
+        let tx = transaction::Transaction::insert_with_id(
+            Cl::collection_name(),
+            DocumentId::new(id)?,
+            contents,
+        );
+        let results = self.connection.apply_transaction(tx)?;
+        if let Some(transaction::OperationResult::DocumentUpdated { header, .. }) =
+            results.into_iter().next()
+        {
+            CollectionHeader::try_from(header)
+        } else {
+            unreachable!(
+                "apply_transaction on a single insert should yield a single DocumentUpdated entry"
+            )
+        }
+    }
+
     /// Updates an existing document. Upon success, `doc.revision` will be
     /// updated with the new revision.
     ///
@@ -336,6 +495,26 @@ where
         self.connection.get::<Cl, _>(id)
     }
 
+    /// Returns true if a `Document<Cl>` with `id` is stored. Unlike
+    /// [`get()`](Self::get), the document's contents are not retrieved.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: &C) -> Result<(), Error> {
+    /// if db.collection::<MyCollection>().exists(&42)? {
+    ///     println!("Document 42 exists");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exists<PrimaryKey>(&self, id: &PrimaryKey) -> Result<bool, Error>
+    where
+        PrimaryKey: KeyEncoding<Cl::PrimaryKey> + ?Sized,
+    {
+        self.connection.exists::<Cl, _>(id)
+    }
+
     /// Retrieves all documents matching `ids`. Documents that are not found
     /// are not returned, but no error will be generated.
     ///
@@ -460,6 +639,47 @@ where
     pub fn delete<H: HasHeader + Send + Sync>(&self, doc: &H) -> Result<(), Error> {
         self.connection.delete::<Cl, H>(doc)
     }
+
+    /// Removes every document from this collection in a single transaction.
+    /// Returns the number of documents removed.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: &C) -> Result<(), Error> {
+    /// let removed = db.collection::<MyCollection>().delete_all()?;
+    /// println!("Removed {} documents", removed);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn delete_all(&self) -> Result<u64, Error> {
+        self.connection.delete_all::<Cl>()
+    }
+
+    /// Returns a builder for a transaction containing multiple operations on
+    /// this collection. The operations are applied atomically when
+    /// [`commit()`](CollectionTransaction::commit) is called.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: &C) -> Result<(), Error> {
+    /// if let Some(mut doc) = MyCollection::get(&42, db)? {
+    ///     doc.contents.rank += 1;
+    ///     let mut tx = db.collection::<MyCollection>().transaction();
+    ///     tx.push(&MyCollection::default())?.update(&mut doc)?;
+    ///     let results = tx.commit()?;
+    ///     assert_eq!(results.len(), 2);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transaction(&self) -> CollectionTransaction<'a, Cn, Cl>
+    where
+        Cl: schema::SerializedCollection,
+    {
+        CollectionTransaction::new(self.connection)
+    }
 }
 
 /// Retrieves a list of documents from a collection. This structure also offers
@@ -662,6 +882,10 @@ where
     /// The maximum number of results to return.
     pub limit: Option<u32>,
 
+    /// If true, mappings that share a key are ordered by the id of their
+    /// source document.
+    pub order_by_source: bool,
+
     _view: PhantomData<V>,
 }
 
@@ -679,6 +903,7 @@ where
             access_policy: AccessPolicy::UpdateBefore,
             sort: Sort::Ascending,
             limit: None,
+            order_by_source: false,
             _view: PhantomData,
         }
     }
@@ -709,6 +934,7 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
@@ -742,6 +968,7 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
@@ -772,10 +999,37 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
 
+    /// Filters for entries in the view with keys between `start` and `end`.
+    /// Each bound can independently be inclusive, exclusive, or unbounded.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::{Bound, Connection};
+    /// # fn test_fn<C: Connection>(db: C) -> Result<(), Error> {
+    /// // score is an f32 in this example
+    /// for mapping in ScoresByRank::entries(&db)
+    ///     .with_key_bounds(Bound::Excluded(&42), Bound::Included(&50))
+    ///     .query()?
+    /// {
+    ///     assert!(mapping.key > 42 && mapping.key <= 50);
+    ///     println!("Rank {} has a score of {:3}", mapping.key, mapping.value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_key_bounds<K>(self, start: Bound<&'a K>, end: Bound<&'a K>) -> View<'a, Cn, V, K>
+    where
+        K: PartialEq + ?Sized,
+        V::Key: Borrow<K> + PartialEq<K>,
+    {
+        self.with_key_range(RangeRef::borrowed(Range { start, end }))
+    }
+
     /// Filters for entries in the view with keys that begin with `prefix`.
     ///
     /// ```rust
@@ -806,6 +1060,7 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
@@ -895,6 +1150,34 @@ where
         self
     }
 
+    /// Orders mappings that share a key by the id of their source document,
+    /// in the same direction as the keys are sorted. For collections whose
+    /// ids increase as documents are inserted, this returns each key's
+    /// mappings in insertion order.
+    ///
+    /// The ordering is applied to the results after they are retrieved. When
+    /// combined with [`limit()`](Self::limit), only the mappings within the
+    /// limit are ordered.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: C) -> Result<(), Error> {
+    /// for mapping in ScoresByRank::entries(&db)
+    ///     .with_key(&42)
+    ///     .order_by_source()
+    ///     .query()?
+    /// {
+    ///     println!("Document #{} has rank {}", mapping.source.id, mapping.key);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub const fn order_by_source(mut self) -> Self {
+        self.order_by_source = true;
+        self
+    }
+
     /// Executes the query and retrieves the results.
     ///
     /// ```rust
@@ -909,8 +1192,41 @@ where
     /// # }
     /// ```
     pub fn query(self) -> Result<ViewMappings<V>, Error> {
-        self.connection
-            .query::<V, Key>(self.key, self.sort, self.limit, self.access_policy)
+        let mut mappings =
+            self.connection
+                .query::<V, Key>(self.key, self.sort, self.limit, self.access_policy)?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut mappings, self.sort);
+        }
+        Ok(mappings)
+    }
+
+    /// Executes the query and retrieves only the keys and source documents
+    /// of the matching mappings. The mapped values are neither returned nor
+    /// deserialized, making this less expensive than
+    /// [`query()`](Self::query) when only the keys are needed.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: C) -> Result<(), Error> {
+    /// for mapping in ScoresByRank::entries(&db).keys_only()? {
+    ///     println!("Document #{} has rank {}", mapping.source.id, mapping.key);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn keys_only(self) -> Result<ViewKeys<V>, Error> {
+        let mut mappings = self.connection.query_keys::<V, Key>(
+            self.key,
+            self.sort,
+            self.limit,
+            self.access_policy,
+        )?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut mappings, self.sort);
+        }
+        Ok(mappings)
     }
 
     /// Executes the query and retrieves the results with the associated [`Document`s](crate::document::OwnedDocument).
@@ -932,12 +1248,16 @@ where
     /// # }
     /// ```
     pub fn query_with_docs(self) -> Result<MappedDocuments<OwnedDocument, V>, Error> {
-        self.connection.query_with_docs::<V, Key>(
+        let mut results = self.connection.query_with_docs::<V, Key>(
             self.key,
             self.sort,
             self.limit,
             self.access_policy,
-        )
+        )?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut results.mappings, self.sort);
+        }
+        Ok(results)
     }
 
     /// Executes the query and retrieves the results with the associated [`CollectionDocument`s](crate::document::CollectionDocument).
@@ -965,15 +1285,21 @@ where
         V::Collection: SerializedCollection,
         <V::Collection as SerializedCollection>::Contents: std::fmt::Debug,
     {
-        self.connection.query_with_collection_docs::<V, Key>(
+        let mut results = self.connection.query_with_collection_docs::<V, Key>(
             self.key,
             self.sort,
             self.limit,
             self.access_policy,
-        )
+        )?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut results.mappings, self.sort);
+        }
+        Ok(results)
     }
 
-    /// Executes a reduce over the results of the query
+    /// Executes a reduce over the results of the query. If no key filter has
+    /// been specified, the result is a single value reduced over every entry
+    /// in the view, computed from the view's stored reductions.
     ///
     /// ```rust
     /// # bonsaidb_core::__doctest_prelude!();
@@ -1011,6 +1337,25 @@ where
             .reduce_grouped::<V, Key>(self.key, self.access_policy)
     }
 
+    /// Counts the entries matching this query, grouping by key. Only the
+    /// keys are deserialized, making this an inexpensive way to find how many
+    /// entries exist for one or more keys.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: C) -> Result<(), Error> {
+    /// for count in ScoresByRank::entries(&db).with_key(&42).count()? {
+    ///     println!("Rank {} has {} scores", count.key, count.value);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn count(self) -> Result<GroupedCounts<V>, Error> {
+        self.connection
+            .count_grouped::<V, Key>(self.key, self.access_policy)
+    }
+
     /// Deletes all of the associated documents that match this view query.
     ///
     /// ```rust
@@ -1034,10 +1379,55 @@ where
 /// - The value emitted during the map function.
 /// - The source document header that the mappings originated from.
 pub type ViewMappings<V> = Vec<Map<<V as schema::View>::Key, <V as schema::View>::Value>>;
+/// This type is the result of `keys_only()`. It is a list of mappings without
+/// their values, which contains:
+///
+/// - The key emitted during the map function.
+/// - The source document header that the mappings originated from.
+pub type ViewKeys<V> = Vec<Map<<V as schema::View>::Key>>;
+
+/// Sorts each run of consecutive mappings that share a key by the id of their
+/// source document in the direction of `sort`, leaving the order of the keys
+/// unchanged.
+fn sort_mappings_by_source<K: PartialEq, V>(mappings: &mut [Map<K, V>], sort: Sort) {
+    let mut start = 0;
+    while start < mappings.len() {
+        let key = &mappings[start].key;
+        let end = start
+            + mappings[start..]
+                .iter()
+                .take_while(|mapping| &mapping.key == key)
+                .count();
+        let run = &mut mappings[start..end];
+        match sort {
+            Sort::Ascending => run.sort_by(|a, b| a.source.id.cmp(&b.source.id)),
+            Sort::Descending => run.sort_by(|a, b| b.source.id.cmp(&a.source.id)),
+        }
+        start = end;
+    }
+}
 /// This type is the result of `reduce_grouped()`. It is a list of all matching
 /// keys and the reduced value of all mapped entries for that key.
 pub type GroupedReductions<V> =
     Vec<MappedValue<<V as schema::View>::Key, <V as schema::View>::Value>>;
+/// This type is the result of `count()`. It is a list of all matching keys and
+/// the number of mapped entries for that key.
+pub type GroupedCounts<V> = Vec<MappedValue<<V as schema::View>::Key, u64>>;
+
+/// Returns a future that is pending once, allowing other tasks to run before it
+/// completes.
+fn yield_now() -> impl Future<Output = ()> {
+    let mut yielded = false;
+    futures::future::poll_fn(move |cx| {
+        if yielded {
+            std::task::Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+}
 
 /// A connection to a database's [`Schema`](schema::Schema), giving access to
 /// [`Collection`s](crate::schema::Collection) and
@@ -1062,11 +1452,60 @@ pub trait AsyncConnection: AsyncLowLevelConnection + Sized + Send + Sync {
         AsyncView::new(self)
     }
 
-    /// Lists [executed transactions](transaction::Executed) from this [`Schema`](schema::Schema). By default, a maximum of
-    /// 1000 entries will be returned, but that limit can be overridden by
-    /// setting `result_limit`. A hard limit of 100,000 results will be
-    /// returned. To begin listing after another known `transaction_id`, pass
-    /// `transaction_id + 1` into `starting_id`.
+    /// Accesses an append-only [`AsyncEventLog`] whose events are stored in
+    /// the collection `C`.
+    fn event_log<C: schema::SerializedCollection>(&self) -> AsyncEventLog<'_, Self, C> {
+        AsyncEventLog::new(self)
+    }
+
+    /// Returns a stream of executed transactions whose id is greater than or
+    /// equal to `starting_id`, in the order they were executed. Transactions
+    /// are loaded in batches as the stream is polled, and
+    /// [`TransactionStream::position()`] can be stored to resume the stream
+    /// later.
+    fn transaction_stream(&self, starting_id: u64) -> TransactionStream<'_, Self> {
+        TransactionStream::new(self, starting_id)
+    }
+
+    /// Returns the names of the collections defined by this database's
+    /// [`Schema`](schema::Schema), in ascending order.
+    fn collection_names(&self) -> Vec<CollectionName> {
+        let mut names = self.schematic().collections().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Returns the names of the views defined for `collection` by this
+    /// database's [`Schema`](schema::Schema), in ascending order. If the
+    /// schema does not contain `collection`, no names are returned.
+    fn view_names(&self, collection: &CollectionName) -> Vec<ViewName> {
+        let mut names = self
+            .schematic()
+            .views_in_collection(collection)
+            .map(|view| view.view_name())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Returns a summary of this database's [`Schema`](schema::Schema),
+    /// including the primary key of each collection and the key and update
+    /// policy of each view. The summary can be used to query the database
+    /// without access to the Rust types that define the schema.
+    fn schema_summary(&self) -> SchemaSummary {
+        SchemaSummary::from(self.schematic())
+    }
+
+    /// Lists [executed transactions](transaction::Executed) from this
+    /// [`Schema`](schema::Schema). By default, a maximum of
+    /// [`LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT`](crate::limits::LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT)
+    /// entries will be returned, but that limit can be overridden by setting
+    /// `result_limit`. The storage enforces a hard limit on the number of
+    /// results, which defaults to
+    /// [`LIST_TRANSACTIONS_MAX_RESULTS`](crate::limits::LIST_TRANSACTIONS_MAX_RESULTS)
+    /// and can be configured when opening the storage. To begin listing after
+    /// another known `transaction_id`, pass `transaction_id + 1` into
+    /// `starting_id`.
     async fn list_executed_transactions(
         &self,
         starting_id: Option<u64>,
@@ -1076,6 +1515,40 @@ pub trait AsyncConnection: AsyncLowLevelConnection + Sized + Send + Sync {
     /// Fetches the last transaction id that has been committed, if any.
     async fn last_transaction_id(&self) -> Result<Option<u64>, Error>;
 
+    /// Builds and applies a transaction, retrying on conflict.
+    ///
+    /// `build` is invoked to build the [`Transaction`](transaction::Transaction)
+    /// to apply, along with a value to return if it is applied successfully.
+    /// It should read any documents it needs from this connection each time it
+    /// is invoked. If applying the transaction fails with
+    /// [`Error::DocumentConflict`], `build` is invoked again, up to
+    /// `retry_limit` additional times. Unlike
+    /// [`Connection::transaction()`], retries aren't delayed because this
+    /// trait doesn't depend on an async runtime. Instead, the current task
+    /// yields before each retry.
+    ///
+    /// On success, the value returned from `build` is returned. If every
+    /// attempt conflicts, the last [`Error::DocumentConflict`] is returned.
+    /// Errors returned from `build` are returned immediately.
+    async fn transaction<T, F, Fut>(&self, mut retry_limit: usize, mut build: F) -> Result<T, Error>
+    where
+        T: Send,
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<(transaction::Transaction, T), Error>> + Send,
+    {
+        loop {
+            let (tx, value) = build().await?;
+            match tx.apply_async(self).await {
+                Ok(_) => return Ok(value),
+                Err(Error::DocumentConflict(..)) if retry_limit > 0 => {
+                    retry_limit -= 1;
+                    yield_now().await;
+                }
+                Err(other) => return Err(other),
+            }
+        }
+    }
+
     /// Compacts the entire database to reclaim unused disk space.
     ///
     /// This process is done by writing data to a new file and swapping the file
@@ -1103,6 +1576,28 @@ pub trait AsyncConnection: AsyncLowLevelConnection + Sized + Send + Sync {
         self.compact_collection_by_name(C::collection_name()).await
     }
 
+    /// Returns how up-to-date the index of the view `V` is.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::ViewNotFound`]: `V` is not part of this database's schema.
+    async fn view_index_state<V: schema::SerializedView>(&self) -> Result<ViewIndexState, Error> {
+        let view = self.schematic().view::<V>()?;
+        self.view_index_state_by_name(&view.view_name()).await
+    }
+
+    /// Discards the index of the view `V` and regenerates it from every
+    /// document in its collection. This function returns once the index has
+    /// been rebuilt.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::ViewNotFound`]: `V` is not part of this database's schema.
+    async fn rebuild_view<V: schema::SerializedView>(&self) -> Result<(), Error> {
+        let view = self.schematic().view::<V>()?;
+        self.rebuild_view_by_name(&view.view_name()).await
+    }
+
     /// Compacts the key value store to reclaim unused disk space.
     ///
     /// This process is done by writing data to a new file and swapping the file
@@ -1234,6 +1729,13 @@ where
 
     /// Adds a new `Document<Cl>` with the given `id` and contents `item`.
     ///
+    /// Automatic id assignment chooses an id after the largest id ever stored
+    /// in the collection, so it will not collide with ids inserted this way.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DocumentConflict`]: a document with `id` already exists.
+    ///
     /// ```rust
     /// # bonsaidb_core::__doctest_prelude!();
     /// # use bonsaidb_core::connection::AsyncConnection;
@@ -1294,6 +1796,61 @@ where
         self.connection.insert::<Cl, _, B>(Some(id), contents).await
     }
 
+    /// Adds a new `Document<Cl>` with the caller-chosen `id` and contents
+    /// `item`, ignoring [`SerializedCollection::natural_id()`].
+    ///
+    /// This can be used to preserve ids when importing documents from another
+    /// system. The storage backend never automatically assigns `id` to another
+    /// document, even after the inserted document is deleted.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::DocumentConflict`]: a document with `id` already exists.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: &C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let inserted_header = db
+    ///     .collection::<MyCollection>()
+    ///     .push_with_id(&42, &MyCollection::default())
+    ///     .await?;
+    /// println!(
+    ///     "Inserted id {} with revision {}",
+    ///     inserted_header.id, inserted_header.revision
+    /// );
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn push_with_id<PrimaryKey>(
+        &self,
+        id: &PrimaryKey,
+        item: &<Cl as SerializedCollection>::Contents,
+    ) -> Result<CollectionHeader<Cl::PrimaryKey>, crate::Error>
+    where
+        Cl: schema::SerializedCollection,
+        PrimaryKey: KeyEncoding<Cl::PrimaryKey> + ?Sized,
+    {
+        let contents = Cl::serialize(item)?;
+        let tx = transaction::Transaction::insert_with_id(
+            Cl::collection_name(),
+            DocumentId::new(id)?,
+            contents,
+        );
+        let results = self.connection.apply_transaction(tx).await?;
+        if let Some(transaction::OperationResult::DocumentUpdated { header, .. }) =
+            results.into_iter().next()
+        {
+            CollectionHeader::try_from(header)
+        } else {
+            unreachable!(
+                "apply_transaction on a single insert should yield a single DocumentUpdated entry"
+            )
+        }
+    }
+
     /// Updates an existing document. Upon success, `doc.revision` will be
     /// updated with the new revision.
     ///
@@ -1367,6 +1924,28 @@ where
         self.connection.get::<Cl, _>(id).await
     }
 
+    /// Returns true if a `Document<Cl>` with `id` is stored. Unlike
+    /// [`get()`](Self::get), the document's contents are not retrieved.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: &C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// if db.collection::<MyCollection>().exists(&42).await? {
+    ///     println!("Document 42 exists");
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn exists<PrimaryKey>(&self, id: &PrimaryKey) -> Result<bool, Error>
+    where
+        PrimaryKey: KeyEncoding<Cl::PrimaryKey> + ?Sized,
+    {
+        self.connection.exists::<Cl, _>(id).await
+    }
+
     /// Retrieves all documents matching `ids`. Documents that are not found
     /// are not returned, but no error will be generated.
     ///
@@ -1498,6 +2077,51 @@ where
     pub async fn delete<H: HasHeader + Send + Sync>(&self, doc: &H) -> Result<(), Error> {
         self.connection.delete::<Cl, H>(doc).await
     }
+
+    /// Removes every document from this collection in a single transaction.
+    /// Returns the number of documents removed.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: &C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let removed = db.collection::<MyCollection>().delete_all().await?;
+    /// println!("Removed {} documents", removed);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn delete_all(&self) -> Result<u64, Error> {
+        self.connection.delete_all::<Cl>().await
+    }
+
+    /// Returns a builder for a transaction containing multiple operations on
+    /// this collection. The operations are applied atomically when
+    /// [`commit()`](AsyncCollectionTransaction::commit) is called.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: &C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// if let Some(mut doc) = MyCollection::get_async(&42, db).await? {
+    ///     doc.contents.rank += 1;
+    ///     let mut tx = db.collection::<MyCollection>().transaction();
+    ///     tx.push(&MyCollection::default())?.update(&mut doc)?;
+    ///     let results = tx.commit().await?;
+    ///     assert_eq!(results.len(), 2);
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn transaction(&self) -> AsyncCollectionTransaction<'a, Cn, Cl>
+    where
+        Cl: schema::SerializedCollection,
+    {
+        AsyncCollectionTransaction::new(self.connection)
+    }
 }
 
 pub(crate) struct AsyncListBuilder<'a, Cn, Cl, PrimaryKey>
@@ -1841,6 +2465,10 @@ where
     /// The maximum number of results to return.
     pub limit: Option<u32>,
 
+    /// If true, mappings that share a key are ordered by the id of their
+    /// source document.
+    pub order_by_source: bool,
+
     _view: PhantomData<V>,
 }
 
@@ -1858,6 +2486,7 @@ where
             access_policy: AccessPolicy::UpdateBefore,
             sort: Sort::Ascending,
             limit: None,
+            order_by_source: false,
             _view: PhantomData,
         }
     }
@@ -1894,6 +2523,7 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
@@ -1933,6 +2563,7 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
@@ -1971,10 +2602,44 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
 
+    /// Filters for entries in the view with keys between `start` and `end`.
+    /// Each bound can independently be inclusive, exclusive, or unbounded.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::{AsyncConnection, Bound};
+    /// # fn test_fn<C: AsyncConnection>(db: C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// // score is an f32 in this example
+    /// for mapping in ScoresByRank::entries_async(&db)
+    ///     .with_key_bounds(Bound::Excluded(&42), Bound::Included(&50))
+    ///     .query()
+    ///     .await?
+    /// {
+    ///     assert!(mapping.key > 42 && mapping.key <= 50);
+    ///     println!("Rank {} has a score of {:3}", mapping.key, mapping.value);
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub fn with_key_bounds<K>(
+        self,
+        start: Bound<&'a K>,
+        end: Bound<&'a K>,
+    ) -> AsyncView<'a, Cn, V, K>
+    where
+        K: KeyEncoding<V::Key> + PartialEq + ?Sized,
+        V::Key: Borrow<K> + PartialEq<K>,
+    {
+        self.with_key_range(RangeRef::borrowed(Range { start, end }))
+    }
+
     /// Filters for entries in the view with keys that begin with `prefix`.
     ///
     /// ```rust
@@ -2011,6 +2676,7 @@ where
             access_policy: self.access_policy,
             sort: self.sort,
             limit: self.limit,
+            order_by_source: self.order_by_source,
             _view: PhantomData,
         }
     }
@@ -2113,6 +2779,37 @@ where
         self
     }
 
+    /// Orders mappings that share a key by the id of their source document,
+    /// in the same direction as the keys are sorted. For collections whose
+    /// ids increase as documents are inserted, this returns each key's
+    /// mappings in insertion order.
+    ///
+    /// The ordering is applied to the results after they are retrieved. When
+    /// combined with [`limit()`](Self::limit), only the mappings within the
+    /// limit are ordered.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// for mapping in ScoresByRank::entries_async(&db)
+    ///     .with_key(&42)
+    ///     .order_by_source()
+    ///     .query()
+    ///     .await?
+    /// {
+    ///     println!("Document #{} has rank {}", mapping.source.id, mapping.key);
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub const fn order_by_source(mut self) -> Self {
+        self.order_by_source = true;
+        self
+    }
+
     /// Executes the query and retrieves the results.
     ///
     /// ```rust
@@ -2129,9 +2826,42 @@ where
     /// # }
     /// ```
     pub async fn query(self) -> Result<Vec<Map<V::Key, V::Value>>, Error> {
-        self.connection
+        let mut mappings = self
+            .connection
             .query::<V, Key>(self.key, self.sort, self.limit, self.access_policy)
-            .await
+            .await?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut mappings, self.sort);
+        }
+        Ok(mappings)
+    }
+
+    /// Executes the query and retrieves only the keys and source documents
+    /// of the matching mappings. The mapped values are neither returned nor
+    /// deserialized, making this less expensive than
+    /// [`query()`](Self::query) when only the keys are needed.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// for mapping in ScoresByRank::entries_async(&db).keys_only().await? {
+    ///     println!("Document #{} has rank {}", mapping.source.id, mapping.key);
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn keys_only(self) -> Result<ViewKeys<V>, Error> {
+        let mut mappings = self
+            .connection
+            .query_keys::<V, Key>(self.key, self.sort, self.limit, self.access_policy)
+            .await?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut mappings, self.sort);
+        }
+        Ok(mappings)
     }
 
     /// Executes the query and retrieves the results with the associated [`Document`s](crate::document::OwnedDocument).
@@ -2156,9 +2886,14 @@ where
     /// # }
     /// ```
     pub async fn query_with_docs(self) -> Result<MappedDocuments<OwnedDocument, V>, Error> {
-        self.connection
+        let mut results = self
+            .connection
             .query_with_docs::<V, _>(self.key, self.sort, self.limit, self.access_policy)
-            .await
+            .await?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut results.mappings, self.sort);
+        }
+        Ok(results)
     }
 
     /// Executes the query and retrieves the results with the associated [`CollectionDocument`s](crate::document::CollectionDocument).
@@ -2189,12 +2924,19 @@ where
         V::Collection: SerializedCollection,
         <V::Collection as SerializedCollection>::Contents: std::fmt::Debug,
     {
-        self.connection
+        let mut results = self
+            .connection
             .query_with_collection_docs::<V, _>(self.key, self.sort, self.limit, self.access_policy)
-            .await
+            .await?;
+        if self.order_by_source {
+            sort_mappings_by_source(&mut results.mappings, self.sort);
+        }
+        Ok(results)
     }
 
-    /// Executes a reduce over the results of the query
+    /// Executes a reduce over the results of the query. If no key filter has
+    /// been specified, the result is a single value reduced over every entry
+    /// in the view, computed from the view's stored reductions.
     ///
     /// ```rust
     /// # bonsaidb_core::__doctest_prelude!();
@@ -2238,6 +2980,28 @@ where
             .await
     }
 
+    /// Counts the entries matching this query, grouping by key. Only the
+    /// keys are deserialized, making this an inexpensive way to find how many
+    /// entries exist for one or more keys.
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// for count in ScoresByRank::entries_async(&db).with_key(&42).count().await? {
+    ///     println!("Rank {} has {} scores", count.key, count.value);
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    pub async fn count(self) -> Result<GroupedCounts<V>, Error> {
+        self.connection
+            .count_grouped::<V, _>(self.key, self.access_policy)
+            .await
+    }
+
     /// Deletes all of the associated documents that match this view query.
     ///
     /// ```rust
@@ -2257,6 +3021,39 @@ where
     }
 }
 
+/// How up-to-date a view's index is. Returned from
+/// [`Connection::view_index_state()`].
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct ViewIndexState {
+    /// The id of the last transaction whose changes have been indexed, or
+    /// `None` if the view has not been indexed since the database was opened.
+    pub last_indexed_transaction_id: Option<u64>,
+    /// The id of the last transaction committed to the database, if any.
+    pub last_transaction_id: Option<u64>,
+}
+
+impl ViewIndexState {
+    /// Returns true if the index reflects every committed transaction.
+    #[must_use]
+    pub const fn is_up_to_date(&self) -> bool {
+        match (self.last_transaction_id, self.last_indexed_transaction_id) {
+            (None, _) => true,
+            (Some(last_transaction), Some(last_indexed)) => last_indexed >= last_transaction,
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Returns the difference between the last committed transaction id and
+    /// the last indexed transaction id. A view that has not been indexed is
+    /// treated as having indexed transaction id 0.
+    #[must_use]
+    pub fn transactions_behind(&self) -> u64 {
+        self.last_transaction_id.map_or(0, |last_transaction| {
+            last_transaction.saturating_sub(self.last_indexed_transaction_id.unwrap_or(0))
+        })
+    }
+}
+
 /// A sort order.
 #[derive(Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum Sort {
@@ -2960,6 +3757,15 @@ pub enum AccessPolicy {
     /// shouldn't have much overhead, this option removes all overhead related
     /// to view updating from the query.
     NoUpdate,
+
+    /// Wait until the view has indexed the most recent transaction written by
+    /// the current session, then return the results and start an update job
+    /// in the background like `UpdateAfter`. Changes made by other sessions
+    /// may not be reflected yet, but a session will always observe its own
+    /// writes without waiting for a full `UpdateBefore` update. If the
+    /// session hasn't written to the database, this behaves like
+    /// `UpdateAfter`.
+    ReadYourWrites,
 }
 
 /// Functions for interacting with a multi-database BonsaiDb instance.
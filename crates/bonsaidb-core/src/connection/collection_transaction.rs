@@ -0,0 +1,223 @@
+use std::marker::PhantomData;
+
+use super::{AsyncConnection, Connection};
+use crate::document::{CollectionDocument, CollectionHeader, DocumentId, HasHeader};
+use crate::key::KeyEncoding;
+use crate::schema::SerializedCollection;
+use crate::transaction::{Operation, OperationResult, Transaction};
+use crate::Error;
+
+/// Builds a [`Transaction`] of operations on the collection `Cl`, serializing
+/// documents using the collection's format. Returned from
+/// [`Collection::transaction()`](super::Collection::transaction).
+///
+/// No changes are made until [`commit()`](Self::commit) is called, at which
+/// point all operations are applied atomically.
+///
+/// This type is not safe to use within async contexts. For async access, use
+/// [`AsyncCollectionTransaction`].
+#[must_use = "the transaction is not applied until commit() is called"]
+pub struct CollectionTransaction<'a, Cn, Cl> {
+    connection: &'a Cn,
+    transaction: Transaction,
+    updated: Vec<(usize, &'a mut CollectionDocument<Cl>)>,
+    _phantom: PhantomData<Cl>,
+}
+
+impl<'a, Cn, Cl> CollectionTransaction<'a, Cn, Cl>
+where
+    Cn: Connection,
+    Cl: SerializedCollection,
+{
+    pub(crate) fn new(connection: &'a Cn) -> Self {
+        Self {
+            connection,
+            transaction: Transaction::new(),
+            updated: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Adds an operation that pushes a new document with `contents`. See
+    /// [`Operation::push_serialized()`] for how the id is assigned.
+    pub fn push(&mut self, contents: &Cl::Contents) -> Result<&mut Self, Error> {
+        self.transaction
+            .push(Operation::push_serialized::<Cl>(contents)?);
+        Ok(self)
+    }
+
+    /// Adds an operation that inserts a new document with `contents` and `id`.
+    pub fn insert<PrimaryKey>(
+        &mut self,
+        id: &PrimaryKey,
+        contents: &Cl::Contents,
+    ) -> Result<&mut Self, Error>
+    where
+        PrimaryKey: KeyEncoding<Cl::PrimaryKey> + ?Sized,
+    {
+        self.transaction.push(Operation::insert(
+            Cl::collection_name(),
+            Some(DocumentId::new(id)?),
+            Cl::serialize(contents)?,
+        ));
+        Ok(self)
+    }
+
+    /// Adds an operation that updates `document` with its current contents.
+    /// The update will fail with a conflict if the stored document's revision
+    /// doesn't match `document`'s. Once the transaction is committed,
+    /// `document`'s header is updated to the new revision.
+    pub fn update(&mut self, document: &'a mut CollectionDocument<Cl>) -> Result<&mut Self, Error> {
+        document.update_in_transaction(&mut self.transaction)?;
+        self.updated
+            .push((self.transaction.operations.len() - 1, document));
+        Ok(self)
+    }
+
+    /// Adds an operation that deletes `document`.
+    pub fn delete<H: HasHeader>(&mut self, document: &H) -> Result<&mut Self, Error> {
+        self.transaction
+            .push(Operation::delete(Cl::collection_name(), document.header()?));
+        Ok(self)
+    }
+
+    /// Returns the number of operations in this transaction.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.transaction.operations.len()
+    }
+
+    /// Returns true if no operations have been added to this transaction.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.transaction.operations.is_empty()
+    }
+
+    /// Returns the underlying transaction without applying it. The headers of
+    /// updated documents aren't changed.
+    pub fn into_transaction(self) -> Transaction {
+        self.transaction
+    }
+
+    /// Applies every operation in this transaction, returning the results in
+    /// the order the operations were added. All operations will succeed or
+    /// none will be performed and an error will be returned.
+    pub fn commit(self) -> Result<Vec<OperationResult>, Error> {
+        let results = self.transaction.apply(self.connection)?;
+        update_headers(self.updated, &results)?;
+        Ok(results)
+    }
+}
+
+/// Builds a [`Transaction`] of operations on the collection `Cl`, serializing
+/// documents using the collection's format. Returned from
+/// [`AsyncCollection::transaction()`](super::AsyncCollection::transaction).
+///
+/// No changes are made until [`commit()`](Self::commit) is called, at which
+/// point all operations are applied atomically.
+#[must_use = "the transaction is not applied until commit() is called"]
+pub struct AsyncCollectionTransaction<'a, Cn, Cl> {
+    connection: &'a Cn,
+    transaction: Transaction,
+    updated: Vec<(usize, &'a mut CollectionDocument<Cl>)>,
+    _phantom: PhantomData<Cl>,
+}
+
+impl<'a, Cn, Cl> AsyncCollectionTransaction<'a, Cn, Cl>
+where
+    Cn: AsyncConnection,
+    Cl: SerializedCollection,
+{
+    pub(crate) fn new(connection: &'a Cn) -> Self {
+        Self {
+            connection,
+            transaction: Transaction::new(),
+            updated: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Adds an operation that pushes a new document with `contents`. See
+    /// [`Operation::push_serialized()`] for how the id is assigned.
+    pub fn push(&mut self, contents: &Cl::Contents) -> Result<&mut Self, Error> {
+        self.transaction
+            .push(Operation::push_serialized::<Cl>(contents)?);
+        Ok(self)
+    }
+
+    /// Adds an operation that inserts a new document with `contents` and `id`.
+    pub fn insert<PrimaryKey>(
+        &mut self,
+        id: &PrimaryKey,
+        contents: &Cl::Contents,
+    ) -> Result<&mut Self, Error>
+    where
+        PrimaryKey: KeyEncoding<Cl::PrimaryKey> + ?Sized,
+    {
+        self.transaction.push(Operation::insert(
+            Cl::collection_name(),
+            Some(DocumentId::new(id)?),
+            Cl::serialize(contents)?,
+        ));
+        Ok(self)
+    }
+
+    /// Adds an operation that updates `document` with its current contents.
+    /// The update will fail with a conflict if the stored document's revision
+    /// doesn't match `document`'s. Once the transaction is committed,
+    /// `document`'s header is updated to the new revision.
+    pub fn update(&mut self, document: &'a mut CollectionDocument<Cl>) -> Result<&mut Self, Error> {
+        document.update_in_transaction(&mut self.transaction)?;
+        self.updated
+            .push((self.transaction.operations.len() - 1, document));
+        Ok(self)
+    }
+
+    /// Adds an operation that deletes `document`.
+    pub fn delete<H: HasHeader>(&mut self, document: &H) -> Result<&mut Self, Error> {
+        self.transaction
+            .push(Operation::delete(Cl::collection_name(), document.header()?));
+        Ok(self)
+    }
+
+    /// Returns the number of operations in this transaction.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.transaction.operations.len()
+    }
+
+    /// Returns true if no operations have been added to this transaction.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.transaction.operations.is_empty()
+    }
+
+    /// Returns the underlying transaction without applying it. The headers of
+    /// updated documents aren't changed.
+    pub fn into_transaction(self) -> Transaction {
+        self.transaction
+    }
+
+    /// Applies every operation in this transaction, returning the results in
+    /// the order the operations were added. All operations will succeed or
+    /// none will be performed and an error will be returned.
+    pub async fn commit(self) -> Result<Vec<OperationResult>, Error> {
+        let results = self.transaction.apply_async(self.connection).await?;
+        update_headers(self.updated, &results)?;
+        Ok(results)
+    }
+}
+
+/// Stores the new header of each document in `updated`, which contains the
+/// index of the document's update operation.
+fn update_headers<Cl: SerializedCollection>(
+    updated: Vec<(usize, &mut CollectionDocument<Cl>)>,
+    results: &[OperationResult],
+) -> Result<(), Error> {
+    for (index, document) in updated {
+        if let Some(OperationResult::DocumentUpdated { header, .. }) = results.get(index) {
+            document.header = CollectionHeader::try_from(header.clone())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use futures::stream::BoxStream;
+use futures::StreamExt;
+
+use super::{AsyncConnection, Connection};
+use crate::document::{CollectionDocument, CollectionHeader, DocumentId, OwnedDocument};
+use crate::schema::SerializedCollection;
+use crate::transaction::Executed;
+use crate::Error;
+
+/// The number of executed transactions requested at a time while replaying an
+/// event log.
+const REPLAY_BATCH_SIZE: u32 = 1000;
+
+/// An append-only, ordered log of events stored in the collection `Cl`.
+///
+/// Each event is stored as a document, and its position in the log is the id
+/// of the transaction that stored it. Because transaction ids are assigned in
+/// the order transactions are executed, replaying the log returns events in
+/// the order they were appended.
+///
+/// Events are expected to never be modified. Updating an event's document
+/// records a new transaction, which causes the event to be replayed again at
+/// its new position. Deleted events are skipped.
+///
+/// This type is not safe to use within async contexts. For async access, use
+/// [`AsyncEventLog`].
+pub struct EventLog<'a, Cn, Cl> {
+    connection: &'a Cn,
+    _phantom: PhantomData<Cl>,
+}
+
+impl<'a, Cn, Cl> Clone for EventLog<'a, Cn, Cl> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, Cn, Cl> EventLog<'a, Cn, Cl> {
+    /// Creates a new instance using `connection`.
+    pub(crate) const fn new(connection: &'a Cn) -> Self {
+        Self {
+            connection,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, Cn, Cl> EventLog<'a, Cn, Cl>
+where
+    Cn: Connection,
+    Cl: SerializedCollection,
+{
+    /// Appends `event` to the end of this log.
+    pub fn append(&self, event: &Cl::Contents) -> Result<CollectionHeader<Cl::PrimaryKey>, Error> {
+        self.connection.collection::<Cl>().push(event)
+    }
+
+    /// Returns an iterator over all events whose position is greater than or
+    /// equal to `from_position`, in the order they were appended. To resume
+    /// after a previously replayed event, pass `event.position + 1`.
+    ///
+    /// Events are loaded lazily, in batches, as the iterator advances.
+    #[must_use]
+    pub fn replay(&self, from_position: u64) -> EventReplay<'a, Cn, Cl> {
+        EventReplay {
+            connection: self.connection,
+            next_position: Some(from_position),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// An iterator over the events in an [`EventLog`]. Returned from
+/// [`EventLog::replay()`].
+#[must_use]
+pub struct EventReplay<'a, Cn, Cl>
+where
+    Cl: SerializedCollection,
+{
+    connection: &'a Cn,
+    next_position: Option<u64>,
+    pending: VecDeque<LoggedEvent<Cl>>,
+}
+
+impl<'a, Cn, Cl> EventReplay<'a, Cn, Cl>
+where
+    Cn: Connection,
+    Cl: SerializedCollection,
+{
+    fn load_next_batch(&mut self, starting_id: u64) -> Result<(), Error> {
+        let executed = self
+            .connection
+            .list_executed_transactions(Some(starting_id), Some(REPLAY_BATCH_SIZE))?;
+        self.next_position = next_position(&executed);
+        for transaction in executed {
+            let ids = event_ids::<Cl>(&transaction);
+            if ids.is_empty() {
+                continue;
+            }
+
+            let documents = self
+                .connection
+                .get_multiple_from_collection(&ids, &Cl::collection_name())?;
+            self.pending
+                .extend(logged_events::<Cl>(transaction.id, &documents)?);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, Cn, Cl> Iterator for EventReplay<'a, Cn, Cl>
+where
+    Cn: Connection,
+    Cl: SerializedCollection,
+{
+    type Item = Result<LoggedEvent<Cl>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+
+            let starting_id = self.next_position?;
+            if let Err(err) = self.load_next_batch(starting_id) {
+                self.next_position = None;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+/// An append-only, ordered log of events stored in the collection `Cl`.
+///
+/// Each event is stored as a document, and its position in the log is the id
+/// of the transaction that stored it. Because transaction ids are assigned in
+/// the order transactions are executed, replaying the log returns events in
+/// the order they were appended.
+///
+/// Events are expected to never be modified. Updating an event's document
+/// records a new transaction, which causes the event to be replayed again at
+/// its new position. Deleted events are skipped.
+pub struct AsyncEventLog<'a, Cn, Cl> {
+    connection: &'a Cn,
+    _phantom: PhantomData<Cl>,
+}
+
+impl<'a, Cn, Cl> Clone for AsyncEventLog<'a, Cn, Cl> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, Cn, Cl> AsyncEventLog<'a, Cn, Cl> {
+    /// Creates a new instance using `connection`.
+    pub(crate) const fn new(connection: &'a Cn) -> Self {
+        Self {
+            connection,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, Cn, Cl> AsyncEventLog<'a, Cn, Cl>
+where
+    Cn: AsyncConnection,
+    Cl: SerializedCollection + 'a,
+{
+    /// Appends `event` to the end of this log.
+    pub async fn append(
+        &self,
+        event: &Cl::Contents,
+    ) -> Result<CollectionHeader<Cl::PrimaryKey>, Error> {
+        self.connection.collection::<Cl>().push(event).await
+    }
+
+    /// Returns a stream of all events whose position is greater than or equal
+    /// to `from_position`, in the order they were appended. To resume after a
+    /// previously replayed event, pass `event.position + 1`.
+    ///
+    /// Events are loaded lazily, in batches, as the stream is polled.
+    #[must_use]
+    pub fn replay(&self, from_position: u64) -> BoxStream<'a, Result<LoggedEvent<Cl>, Error>> {
+        let connection = self.connection;
+        futures::stream::unfold(Some(from_position), move |next_position| async move {
+            let starting_id = next_position?;
+            match Self::load_batch(connection, starting_id).await {
+                Ok((events, next_position)) => Some((Ok(events), next_position)),
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+        .flat_map(|batch| {
+            futures::stream::iter(match batch {
+                Ok(events) => events.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(err) => vec![Err(err)],
+            })
+        })
+        .boxed()
+    }
+
+    async fn load_batch(
+        connection: &Cn,
+        starting_id: u64,
+    ) -> Result<(Vec<LoggedEvent<Cl>>, Option<u64>), Error> {
+        let executed = connection
+            .list_executed_transactions(Some(starting_id), Some(REPLAY_BATCH_SIZE))
+            .await?;
+        let mut events = Vec::new();
+        for transaction in &executed {
+            let ids = event_ids::<Cl>(transaction);
+            if ids.is_empty() {
+                continue;
+            }
+
+            let documents = connection
+                .get_multiple_from_collection(&ids, &Cl::collection_name())
+                .await?;
+            events.extend(logged_events::<Cl>(transaction.id, &documents)?);
+        }
+        Ok((events, next_position(&executed)))
+    }
+}
+
+/// An event replayed from an [`EventLog`] or [`AsyncEventLog`].
+#[derive(Debug, Clone)]
+pub struct LoggedEvent<Cl>
+where
+    Cl: SerializedCollection,
+{
+    /// The position of this event within the log. This is the id of the
+    /// transaction that stored the event.
+    pub position: u64,
+    /// The document containing the event.
+    pub document: CollectionDocument<Cl>,
+}
+
+fn next_position(executed: &[Executed]) -> Option<u64> {
+    executed
+        .last()
+        .and_then(|transaction| transaction.id.checked_add(1))
+}
+
+fn event_ids<Cl: SerializedCollection>(transaction: &Executed) -> Vec<DocumentId> {
+    let collection = Cl::collection_name();
+    transaction
+        .changes
+        .documents()
+        .map(|changes| {
+            changes
+                .iter()
+                .filter(|(name, changed)| !changed.deleted && **name == collection)
+                .map(|(_, changed)| changed.id.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn logged_events<Cl: SerializedCollection>(
+    position: u64,
+    documents: &[OwnedDocument],
+) -> Result<Vec<LoggedEvent<Cl>>, Error> {
+    documents
+        .iter()
+        .map(|document| {
+            Ok(LoggedEvent {
+                position,
+                document: CollectionDocument::try_from(document)?,
+            })
+        })
+        .collect()
+}
@@ -4,20 +4,24 @@ use std::collections::BTreeMap;
 use arc_bytes::serde::Bytes;
 use async_trait::async_trait;
 
-use super::GroupedReductions;
+use super::{GroupedCounts, GroupedReductions, ViewKeys};
 use crate::connection::{
-    AccessPolicy, HasSession, QueryKey, Range, RangeRef, SerializedQueryKey, Sort, ViewMappings,
+    AccessPolicy, HasSession, QueryKey, Range, RangeRef, SerializedQueryKey, Sort, ViewIndexState,
+    ViewMappings,
 };
 use crate::document::{
     CollectionDocument, CollectionHeader, Document, DocumentId, HasHeader, Header, OwnedDocument,
 };
 use crate::key::{self, ByteSource, Key, KeyEncoding};
-use crate::schema::view::map::{MappedDocuments, MappedSerializedValue};
+use crate::keyvalue::Timestamp;
+use crate::schema::view::map::{
+    MappedDocuments, MappedSerializedCount, MappedSerializedKey, MappedSerializedValue,
+};
 use crate::schema::view::{self};
 use crate::schema::{
     self, CollectionName, Map, MappedValue, Schematic, SerializedCollection, ViewName,
 };
-use crate::transaction::{OperationResult, Transaction};
+use crate::transaction::{OperationResult, Transaction, ViewChanges};
 use crate::Error;
 
 /// The low-level interface to a database's [`schema::Schema`], giving access to
@@ -94,6 +98,29 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         }
     }
 
+    /// Reloads `doc` from the connected [`schema::Schema`] for the
+    /// [`Collection`](schema::Collection) `C`, replacing its header and
+    /// contents with the latest stored revision. Returns true if the stored
+    /// revision differed from the revision `doc` held.
+    ///
+    /// If the document no longer exists, [`Error::DocumentNotFound`] is
+    /// returned and `doc` is left unchanged.
+    fn refresh<C: schema::Collection, D: Document<C> + Send + Sync>(
+        &self,
+        doc: &mut D,
+    ) -> Result<bool, Error> {
+        let current = doc.header().into_header()?;
+        let latest = self
+            .get_from_collection(current.id.clone(), &C::collection_name())?
+            .ok_or_else(|| {
+                Error::DocumentNotFound(C::collection_name(), Box::new(current.id.clone()))
+            })?;
+        let changed = latest.header.revision != current.revision;
+        doc.set_bytes(latest.contents.into_vec())?;
+        doc.set_header(latest.header)?;
+        Ok(changed)
+    }
+
     /// Overwrites an existing document, or inserts a new document. Upon success,
     /// `doc.revision` will be updated with the new revision information.
     ///
@@ -140,6 +167,20 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         self.get_from_collection(DocumentId::new(id)?, &C::collection_name())
     }
 
+    /// Returns true if a document identified by `id` is stored in
+    /// [`Collection`](schema::Collection) `C`. The document's contents are
+    /// not retrieved.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().exists()`](super::Collection::exists).
+    fn exists<C, PrimaryKey>(&self, id: &PrimaryKey) -> Result<bool, Error>
+    where
+        C: schema::Collection,
+        PrimaryKey: KeyEncoding<C::PrimaryKey> + ?Sized,
+    {
+        self.exists_in_collection(DocumentId::new(id)?, &C::collection_name())
+    }
+
     /// Retrieves all documents matching `ids`. Documents that are not found are
     /// not returned, but no error will be generated.
     ///
@@ -258,6 +299,49 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         }
     }
 
+    /// Removes a `Document` from the database, retaining its contents as a
+    /// tombstone until it is purged. Once soft-deleted, the document is no
+    /// longer returned by [`get()`](Self::get) and is removed from all views.
+    /// See [`Command::SoftDelete`](crate::transaction::Command::SoftDelete)
+    /// for more information.
+    fn soft_delete<C: schema::Collection, H: HasHeader + Send + Sync>(
+        &self,
+        doc: &H,
+    ) -> Result<(), Error> {
+        let results = self.apply_transaction(Transaction::soft_delete(
+            C::collection_name(),
+            doc.header()?,
+        ))?;
+        if let OperationResult::DocumentDeleted { .. } = &results[0] {
+            Ok(())
+        } else {
+            unreachable!(
+                "apply_transaction on a single delete should yield a single DocumentDeleted entry"
+            )
+        }
+    }
+
+    /// Retrieves the retained contents of a document from
+    /// [`Collection`](schema::Collection) `C` that was soft-deleted using
+    /// [`soft_delete()`](Self::soft_delete) and whose tombstone hasn't been
+    /// purged yet.
+    fn get_tombstone<C, PrimaryKey>(&self, id: &PrimaryKey) -> Result<Option<OwnedDocument>, Error>
+    where
+        C: schema::Collection,
+        PrimaryKey: KeyEncoding<C::PrimaryKey> + ?Sized,
+    {
+        self.get_tombstone_from_collection(DocumentId::new(id)?, &C::collection_name())
+    }
+
+    /// Removes every document from the collection `C` in a single
+    /// transaction, returning the number of documents removed.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().delete_all()`](super::Collection::delete_all).
+    fn delete_all<C: schema::Collection>(&self) -> Result<u64, Error> {
+        self.delete_all_from_collection(&C::collection_name())
+    }
+
     /// Queries for view entries matching [`View`](schema::View).
     ///
     /// This is a lower-level API. For better ergonomics, consider querying the
@@ -299,6 +383,40 @@ pub trait LowLevelConnection: HasSchema + HasSession {
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Queries for the keys and sources of view entries matching
+    /// [`View`](schema::View). The mapped values are not returned.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider querying the
+    /// view using [`View::entries(self).keys_only()`](super::View::keys_only)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from
+    /// [`SerializedView::entries()`](schema::SerializedView::entries),
+    /// [`SerializedView::entries_async()`](schema::SerializedView::entries_async),
+    /// or [`Connection::view()`](super::Connection::view).
+    fn query_keys<V: schema::SerializedView, Key>(
+        &self,
+        key: Option<QueryKey<'_, V::Key, Key>>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<ViewKeys<V>, Error>
+    where
+        Key: KeyEncoding<V::Key> + PartialEq + ?Sized,
+        V::Key: Borrow<Key> + PartialEq<Key>,
+    {
+        let view = self.schematic().view::<V>()?;
+        self.query_keys_by_name(
+            &view.view_name(),
+            key.map(|key| key.serialized()).transpose()?,
+            order,
+            limit,
+            access_policy,
+        )?
+        .into_iter()
+        .map(|mapping| mapping.deserialized::<V>().map_err(Error::from))
+        .collect::<Result<Vec<_>, Error>>()
+    }
+
     /// Queries for view entries matching [`View`](schema::View) with their
     /// source documents.
     ///
@@ -437,6 +555,43 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Counts the view entries matching [`View`](schema::View), returning the
+    /// number of entries for each unique key. Only the keys are deserialized;
+    /// the mapped values are never read.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider counting the
+    /// view entries using [`View::entries(self).count()`](super::View::count)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from
+    /// [`SerializedView::entries()`](schema::SerializedView::entries),
+    /// [`SerializedView::entries_async()`](schema::SerializedView::entries_async),
+    /// or [`Connection::view()`](super::Connection::view).
+    fn count_grouped<V: schema::SerializedView, Key>(
+        &self,
+        key: Option<QueryKey<'_, V::Key, Key>>,
+        access_policy: AccessPolicy,
+    ) -> Result<GroupedCounts<V>, Error>
+    where
+        Key: KeyEncoding<V::Key> + PartialEq + ?Sized,
+        V::Key: Borrow<Key> + PartialEq<Key>,
+    {
+        let view = self.schematic().view::<V>()?;
+        self.count_grouped_by_name(
+            &view.view_name(),
+            key.map(|key| key.serialized()).transpose()?,
+            access_policy,
+        )?
+        .into_iter()
+        .map(|count| {
+            Ok(MappedValue::new(
+                V::Key::from_ord_bytes(ByteSource::Borrowed(&count.key))
+                    .map_err(view::Error::key_serialization)?,
+                count.count,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()
+    }
+
     /// Deletes all of the documents associated with this view.
     ///
     /// This is a lower-level API. For better ergonomics, consider querying the
@@ -469,6 +624,21 @@ pub trait LowLevelConnection: HasSchema + HasSession {
     /// [`schema::Schema`].
     fn apply_transaction(&self, transaction: Transaction) -> Result<Vec<OperationResult>, Error>;
 
+    /// Applies a [`Transaction`] like
+    /// [`apply_transaction()`](Self::apply_transaction), also returning the
+    /// rows the transaction added to or removed from each view that was
+    /// updated while the transaction was applied.
+    ///
+    /// Only views using
+    /// [`ViewUpdatePolicy::Eager`](view::ViewUpdatePolicy::Eager) or
+    /// [`ViewUpdatePolicy::Unique`](view::ViewUpdatePolicy::Unique) are updated
+    /// during a transaction. If the transaction's idempotency key has already
+    /// been used, the original results are returned without any view changes.
+    fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), Error>;
+
     /// Retrieves the document with `id` stored within the named `collection`.
     ///
     /// This is a lower-level API. For better ergonomics, consider using
@@ -482,6 +652,34 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         collection: &CollectionName,
     ) -> Result<Option<OwnedDocument>, Error>;
 
+    /// Returns true if a document with `id` is stored within the named
+    /// `collection`. The document's contents are not retrieved.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().exists()`](super::Collection::exists).
+    fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, Error>;
+
+    /// Retrieves the retained contents of the document with `id` that was
+    /// soft-deleted from the named `collection`, if its tombstone hasn't been
+    /// purged yet.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`get_tombstone()`](Self::get_tombstone).
+    fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, Error>;
+
+    /// Permanently removes the retained contents of all documents in this
+    /// database that were soft-deleted before `before`, returning the number
+    /// of tombstones purged.
+    fn purge_tombstones(&self, before: Timestamp) -> Result<u64, Error>;
+
     /// Retrieves all documents matching `ids` from the named `collection`.
     /// Documents that are not found are not returned, but no error will be
     /// generated.
@@ -545,6 +743,22 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         collection: &CollectionName,
     ) -> Result<u64, Error>;
 
+    /// Removes every document from the named `collection` in a single
+    /// transaction, returning the number of documents removed. Views of the
+    /// collection are updated as part of the same transaction, so once this
+    /// function returns, querying them will not return any of the removed
+    /// documents.
+    ///
+    /// Other transactions that change the collection wait for this operation
+    /// to complete, so every document in the collection when it runs is
+    /// removed. This requires
+    /// [`DocumentAction::Delete`](crate::permissions::bonsai::DocumentAction::Delete)
+    /// permission on the collection itself, rather than on each document.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().delete_all()`](super::Collection::delete_all).
+    fn delete_all_from_collection(&self, collection: &CollectionName) -> Result<u64, Error>;
+
     /// Compacts the collection to reclaim unused disk space.
     ///
     /// This process is done by writing data to a new file and swapping the file
@@ -558,6 +772,19 @@ pub trait LowLevelConnection: HasSchema + HasSession {
     /// * [`Error::Other`]: an error occurred while compacting the database.
     fn compact_collection_by_name(&self, collection: CollectionName) -> Result<(), Error>;
 
+    /// Returns how up-to-date the index of the named `view` is.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`Connection::view_index_state()`](super::Connection::view_index_state).
+    fn view_index_state_by_name(&self, view: &ViewName) -> Result<ViewIndexState, Error>;
+
+    /// Discards the index of the named `view` and regenerates it from every
+    /// document in its collection.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`Connection::rebuild_view()`](super::Connection::rebuild_view).
+    fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), Error>;
+
     /// Queries for view entries from the named `view`.
     ///
     /// This is a lower-level API. For better ergonomics, consider querying the
@@ -573,6 +800,22 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         access_policy: AccessPolicy,
     ) -> Result<Vec<schema::view::map::Serialized>, Error>;
 
+    /// Queries for the keys and sources of view entries from the named
+    /// `view`. The mapped values are not returned.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider querying the
+    /// view using [`View::entries(self).keys_only()`](super::View::keys_only)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from [`Connection::view()`](super::Connection::view).
+    fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedKey>, Error>;
+
     /// Queries for view entries from the named `view` with their source
     /// documents.
     ///
@@ -618,6 +861,20 @@ pub trait LowLevelConnection: HasSchema + HasSession {
         access_policy: AccessPolicy,
     ) -> Result<Vec<MappedSerializedValue>, Error>;
 
+    /// Counts the view entries from the named `view`, returning the number of
+    /// entries for each unique key.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider counting the
+    /// view entries using [`View::entries(self).count()`](super::View::count)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from [`Connection::view()`](super::Connection::view).
+    fn count_grouped_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, Error>;
+
     /// Deletes all source documents for entries that match within the named
     /// `view`.
     ///
@@ -710,6 +967,30 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         }
     }
 
+    /// Reloads `doc` from the connected [`schema::Schema`] for the
+    /// [`Collection`](schema::Collection) `C`, replacing its header and
+    /// contents with the latest stored revision. Returns true if the stored
+    /// revision differed from the revision `doc` held.
+    ///
+    /// If the document no longer exists, [`Error::DocumentNotFound`] is
+    /// returned and `doc` is left unchanged.
+    async fn refresh<C: schema::Collection, D: Document<C> + Send + Sync>(
+        &self,
+        doc: &mut D,
+    ) -> Result<bool, Error> {
+        let current = doc.header().into_header()?;
+        let latest = self
+            .get_from_collection(current.id.clone(), &C::collection_name())
+            .await?
+            .ok_or_else(|| {
+                Error::DocumentNotFound(C::collection_name(), Box::new(current.id.clone()))
+            })?;
+        let changed = latest.header.revision != current.revision;
+        doc.set_bytes(latest.contents.into_vec())?;
+        doc.set_header(latest.header)?;
+        Ok(changed)
+    }
+
     /// Overwrites an existing document, or inserts a new document. Upon success,
     /// `doc.revision` will be updated with the new revision information.
     ///
@@ -760,6 +1041,21 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
             .await
     }
 
+    /// Returns true if a document identified by `id` is stored in
+    /// [`Collection`](schema::Collection) `C`. The document's contents are
+    /// not retrieved.
+    ///
+    /// This is the lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().exists()`](super::AsyncCollection::exists).
+    async fn exists<C, PrimaryKey>(&self, id: &PrimaryKey) -> Result<bool, Error>
+    where
+        C: schema::Collection,
+        PrimaryKey: KeyEncoding<C::PrimaryKey> + ?Sized,
+    {
+        self.exists_in_collection(DocumentId::new(id)?, &C::collection_name())
+            .await
+    }
+
     /// Retrieves all documents matching `ids`. Documents that are not found
     /// are not returned, but no error will be generated.
     ///
@@ -885,6 +1181,56 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
             )
         }
     }
+
+    /// Removes a `Document` from the database, retaining its contents as a
+    /// tombstone until it is purged. Once soft-deleted, the document is no
+    /// longer returned by [`get()`](Self::get) and is removed from all views.
+    /// See [`Command::SoftDelete`](crate::transaction::Command::SoftDelete)
+    /// for more information.
+    async fn soft_delete<C: schema::Collection, H: HasHeader + Send + Sync>(
+        &self,
+        doc: &H,
+    ) -> Result<(), Error> {
+        let results = self
+            .apply_transaction(Transaction::soft_delete(
+                C::collection_name(),
+                doc.header()?,
+            ))
+            .await?;
+        if let OperationResult::DocumentDeleted { .. } = &results[0] {
+            Ok(())
+        } else {
+            unreachable!(
+                "apply_transaction on a single delete should yield a single DocumentDeleted entry"
+            )
+        }
+    }
+
+    /// Retrieves the retained contents of a document from
+    /// [`Collection`](schema::Collection) `C` that was soft-deleted using
+    /// [`soft_delete()`](Self::soft_delete) and whose tombstone hasn't been
+    /// purged yet.
+    async fn get_tombstone<C, PrimaryKey>(
+        &self,
+        id: &PrimaryKey,
+    ) -> Result<Option<OwnedDocument>, Error>
+    where
+        C: schema::Collection,
+        PrimaryKey: KeyEncoding<C::PrimaryKey> + ?Sized,
+    {
+        self.get_tombstone_from_collection(DocumentId::new(id)?, &C::collection_name())
+            .await
+    }
+
+    /// Removes every document from the collection `C` in a single
+    /// transaction, returning the number of documents removed.
+    ///
+    /// This is the lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().delete_all()`](super::AsyncCollection::delete_all).
+    async fn delete_all<C: schema::Collection>(&self) -> Result<u64, Error> {
+        self.delete_all_from_collection(&C::collection_name()).await
+    }
+
     /// Queries for view entries matching [`View`](schema::View)(super::AsyncView).
     ///
     /// This is the lower-level API. For better ergonomics, consider querying
@@ -926,6 +1272,40 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
             .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Queries for the keys and sources of view entries matching
+    /// [`View`](schema::View). The mapped values are not returned.
+    ///
+    /// This is the lower-level API. For better ergonomics, consider querying
+    /// the view using
+    /// [`View::entries(self).keys_only()`](super::AsyncView::keys_only)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from [`AsyncConnection::view()`](super::AsyncConnection::view).
+    #[must_use]
+    async fn query_keys<V: schema::SerializedView, Key>(
+        &self,
+        key: Option<QueryKey<'_, V::Key, Key>>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<ViewKeys<V>, Error>
+    where
+        Key: KeyEncoding<V::Key> + PartialEq + ?Sized,
+        V::Key: Borrow<Key> + PartialEq<Key>,
+    {
+        let view = self.schematic().view::<V>()?;
+        self.query_keys_by_name(
+            &view.view_name(),
+            key.map(|key| key.serialized()).transpose()?,
+            order,
+            limit,
+            access_policy,
+        )
+        .await?
+        .into_iter()
+        .map(|mapping| mapping.deserialized::<V>().map_err(Error::from))
+        .collect::<Result<Vec<_>, Error>>()
+    }
+
     /// Queries for view entries matching [`View`](schema::View) with their source documents.
     ///
     /// This is the lower-level API. For better ergonomics, consider querying
@@ -1062,6 +1442,43 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         .collect::<Result<Vec<_>, Error>>()
     }
 
+    /// Counts the view entries matching [`View`](schema::View), returning the
+    /// number of entries for each unique key. Only the keys are deserialized;
+    /// the mapped values are never read.
+    ///
+    /// This is the lower-level API. For better ergonomics, consider counting
+    /// the view entries using
+    /// [`View::entries(self).count()`](super::AsyncView::count)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from [`AsyncConnection::view()`](super::AsyncConnection::view).
+    #[must_use]
+    async fn count_grouped<V: schema::SerializedView, Key>(
+        &self,
+        key: Option<QueryKey<'_, V::Key, Key>>,
+        access_policy: AccessPolicy,
+    ) -> Result<GroupedCounts<V>, Error>
+    where
+        Key: KeyEncoding<V::Key> + PartialEq + ?Sized,
+        V::Key: Borrow<Key> + PartialEq<Key>,
+    {
+        let view = self.schematic().view::<V>()?;
+        self.count_grouped_by_name(
+            &view.view_name(),
+            key.map(|key| key.serialized()).transpose()?,
+            access_policy,
+        )
+        .await?
+        .into_iter()
+        .map(|count| {
+            Ok(MappedValue::new(
+                V::Key::from_ord_bytes(ByteSource::Borrowed(&count.key))
+                    .map_err(view::Error::key_serialization)?,
+                count.count,
+            ))
+        })
+        .collect::<Result<Vec<_>, Error>>()
+    }
+
     /// Deletes all of the documents associated with this view.
     ///
     /// This is the lower-level API. For better ergonomics, consider querying
@@ -1096,6 +1513,21 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         transaction: Transaction,
     ) -> Result<Vec<OperationResult>, Error>;
 
+    /// Applies a [`Transaction`] like
+    /// [`apply_transaction()`](Self::apply_transaction), also returning the
+    /// rows the transaction added to or removed from each view that was
+    /// updated while the transaction was applied.
+    ///
+    /// Only views using
+    /// [`ViewUpdatePolicy::Eager`](view::ViewUpdatePolicy::Eager) or
+    /// [`ViewUpdatePolicy::Unique`](view::ViewUpdatePolicy::Unique) are updated
+    /// during a transaction. If the transaction's idempotency key has already
+    /// been used, the original results are returned without any view changes.
+    async fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), Error>;
+
     /// Retrieves the document with `id` stored within the named `collection`.
     ///
     /// This is a lower-level API. For better ergonomics, consider using one of:
@@ -1108,6 +1540,34 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         collection: &CollectionName,
     ) -> Result<Option<OwnedDocument>, Error>;
 
+    /// Returns true if a document with `id` is stored within the named
+    /// `collection`. The document's contents are not retrieved.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().exists()`](super::AsyncCollection::exists).
+    async fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, Error>;
+
+    /// Retrieves the retained contents of the document with `id` that was
+    /// soft-deleted from the named `collection`, if its tombstone hasn't been
+    /// purged yet.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`get_tombstone()`](Self::get_tombstone).
+    async fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, Error>;
+
+    /// Permanently removes the retained contents of all documents in this
+    /// database that were soft-deleted before `before`, returning the number
+    /// of tombstones purged.
+    async fn purge_tombstones(&self, before: Timestamp) -> Result<u64, Error>;
+
     /// Retrieves all documents matching `ids` from the named `collection`.
     /// Documents that are not found are not returned, but no error will be
     /// generated.
@@ -1171,6 +1631,22 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         collection: &CollectionName,
     ) -> Result<u64, Error>;
 
+    /// Removes every document from the named `collection` in a single
+    /// transaction, returning the number of documents removed. Views of the
+    /// collection are updated as part of the same transaction, so once this
+    /// function returns, querying them will not return any of the removed
+    /// documents.
+    ///
+    /// Other transactions that change the collection wait for this operation
+    /// to complete, so every document in the collection when it runs is
+    /// removed. This requires
+    /// [`DocumentAction::Delete`](crate::permissions::bonsai::DocumentAction::Delete)
+    /// permission on the collection itself, rather than on each document.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`self.collection::<Collection>().delete_all()`](super::AsyncCollection::delete_all).
+    async fn delete_all_from_collection(&self, collection: &CollectionName) -> Result<u64, Error>;
+
     /// Compacts the collection to reclaim unused disk space.
     ///
     /// This process is done by writing data to a new file and swapping the file
@@ -1184,6 +1660,19 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
     /// * [`Error::Other`]: an error occurred while compacting the database.
     async fn compact_collection_by_name(&self, collection: CollectionName) -> Result<(), Error>;
 
+    /// Returns how up-to-date the index of the named `view` is.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`AsyncConnection::view_index_state()`](super::AsyncConnection::view_index_state).
+    async fn view_index_state_by_name(&self, view: &ViewName) -> Result<ViewIndexState, Error>;
+
+    /// Discards the index of the named `view` and regenerates it from every
+    /// document in its collection.
+    ///
+    /// This is a lower-level API. For better ergonomics, consider using
+    /// [`AsyncConnection::rebuild_view()`](super::AsyncConnection::rebuild_view).
+    async fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), Error>;
+
     /// Queries for view entries from the named `view`.
     ///
     /// This is the lower-level API. For better ergonomics, consider querying
@@ -1199,6 +1688,23 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         access_policy: AccessPolicy,
     ) -> Result<Vec<schema::view::map::Serialized>, Error>;
 
+    /// Queries for the keys and sources of view entries from the named
+    /// `view`. The mapped values are not returned.
+    ///
+    /// This is the lower-level API. For better ergonomics, consider querying
+    /// the view using
+    /// [`View::entries(self).keys_only()`](super::AsyncView::keys_only)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from [`AsyncConnection::view()`](super::AsyncConnection::view).
+    async fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedKey>, Error>;
+
     /// Queries for view entries from the named `view` with their source
     /// documents.
     ///
@@ -1244,6 +1750,21 @@ pub trait AsyncLowLevelConnection: HasSchema + HasSession + Send + Sync {
         access_policy: AccessPolicy,
     ) -> Result<Vec<MappedSerializedValue>, Error>;
 
+    /// Counts the view entries from the named `view`, returning the number of
+    /// entries for each unique key.
+    ///
+    /// This is the lower-level API. For better ergonomics, consider counting
+    /// the view entries using
+    /// [`View::entries(self).count()`](super::AsyncView::count)
+    /// instead. The parameters for the query can be customized on the builder
+    /// returned from [`AsyncConnection::view()`](super::AsyncConnection::view).
+    async fn count_grouped_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, Error>;
+
     /// Deletes all source documents for entries that match within the named
     /// `view`.
     ///
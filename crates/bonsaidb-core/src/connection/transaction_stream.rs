@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::future::BoxFuture;
+use futures::{FutureExt, Stream};
+
+use super::AsyncConnection;
+use crate::transaction::Executed;
+use crate::Error;
+
+type WaitFn<'a> = Box<dyn FnMut() -> BoxFuture<'a, ()> + Send + 'a>;
+
+/// A stream of executed transactions, in the order they were executed.
+/// Returned from [`AsyncConnection::transaction_stream()`].
+///
+/// Transactions are loaded lazily, in batches, using
+/// [`AsyncConnection::list_executed_transactions()`]. By default, the stream
+/// ends once every executed transaction has been returned. Use
+/// [`follow()`](Self::follow) to keep waiting for new transactions instead.
+#[must_use]
+pub struct TransactionStream<'a, Cn> {
+    connection: &'a Cn,
+    position: u64,
+    pending: VecDeque<Executed>,
+    loading: Option<BoxFuture<'a, Result<Vec<Executed>, Error>>>,
+    waiting: Option<BoxFuture<'a, ()>>,
+    wait: Option<WaitFn<'a>>,
+    finished: bool,
+}
+
+impl<'a, Cn> TransactionStream<'a, Cn>
+where
+    Cn: AsyncConnection,
+{
+    pub(crate) fn new(connection: &'a Cn, starting_id: u64) -> Self {
+        Self {
+            connection,
+            position: starting_id,
+            pending: VecDeque::new(),
+            loading: None,
+            waiting: None,
+            wait: None,
+            finished: false,
+        }
+    }
+
+    /// Keeps this stream open after every executed transaction has been
+    /// returned. Each time the stream has caught up, the future returned from
+    /// `wait` is awaited before checking for new transactions again.
+    ///
+    /// This crate doesn't depend on an async runtime, so `wait` is typically
+    /// a sleep from the runtime in use:
+    ///
+    /// ```rust
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: &C) {
+    /// let stream = db
+    ///     .transaction_stream(0)
+    ///     .follow(|| tokio::time::sleep(std::time::Duration::from_millis(100)));
+    /// # }
+    /// ```
+    pub fn follow<F, Fut>(mut self, mut wait: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'a,
+        Fut: Future<Output = ()> + Send + 'a,
+    {
+        self.wait = Some(Box::new(move || wait().boxed()));
+        self
+    }
+
+    /// Returns the id of the next transaction this stream will return. To
+    /// resume streaming later from the same point, pass this value to
+    /// [`AsyncConnection::transaction_stream()`].
+    #[must_use]
+    pub const fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+impl<'a, Cn> Stream for TransactionStream<'a, Cn>
+where
+    Cn: AsyncConnection,
+{
+    type Item = Result<Executed, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(transaction) = this.pending.pop_front() {
+                this.position = transaction.id.saturating_add(1);
+                return Poll::Ready(Some(Ok(transaction)));
+            }
+
+            if let Some(waiting) = &mut this.waiting {
+                futures::ready!(waiting.poll_unpin(cx));
+                this.waiting = None;
+            }
+
+            if this.finished {
+                return Poll::Ready(None);
+            }
+
+            let connection = this.connection;
+            let starting_id = this.position;
+            let loading = this.loading.get_or_insert_with(|| {
+                connection
+                    .list_executed_transactions(Some(starting_id), None)
+                    .boxed()
+            });
+            let result = futures::ready!(loading.poll_unpin(cx));
+            this.loading = None;
+            match result {
+                Ok(executed) if executed.is_empty() => {
+                    if let Some(wait) = &mut this.wait {
+                        this.waiting = Some(wait());
+                    } else {
+                        this.finished = true;
+                    }
+                }
+                Ok(executed) => this.pending.extend(executed),
+                Err(err) => {
+                    this.finished = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
@@ -83,6 +83,8 @@ where
     }
     /// Returns the contents of this document, serialized.
     fn bytes(&self) -> Result<Vec<u8>, crate::Error>;
+    /// Replaces the contents of this document with `bytes`.
+    fn set_bytes(&mut self, bytes: Vec<u8>) -> Result<(), crate::Error>;
     /// Retrieves `contents` through deserialization into the type `D`.
     fn contents(&self) -> Result<C::Contents, crate::Error>
     where
@@ -133,6 +135,11 @@ where
         Ok(self.contents.to_vec())
     }
 
+    fn set_bytes(&mut self, bytes: Vec<u8>) -> Result<(), crate::Error> {
+        self.contents = CowBytes::from(bytes);
+        Ok(())
+    }
+
     fn id(&self) -> &DocumentId {
         &self.header.id
     }
@@ -175,6 +182,11 @@ where
     fn bytes(&self) -> Result<Vec<u8>, crate::Error> {
         Ok(self.contents.to_vec())
     }
+
+    fn set_bytes(&mut self, bytes: Vec<u8>) -> Result<(), crate::Error> {
+        self.contents = Bytes::from(bytes);
+        Ok(())
+    }
 }
 
 impl AsRef<Header> for OwnedDocument {
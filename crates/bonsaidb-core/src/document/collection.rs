@@ -431,3 +431,26 @@ fn collection_document_serialization() {
         original
     );
 }
+
+#[test]
+fn collection_document_deserialization_error_context() {
+    use crate::test_util::Basic;
+
+    let document = OwnedDocument {
+        header: Header {
+            id: DocumentId::from_u64(1),
+            revision: super::Revision::new(b"hello world"),
+        },
+        contents: Bytes::from(b"not a basic document".to_vec()),
+    };
+    match CollectionDocument::<Basic>::try_from(&document) {
+        Err(Error::Other { origin, error }) => {
+            assert_eq!(origin, "serialization");
+            assert!(
+                error.contains("failed to deserialize document for collection khonsulabs_._basic"),
+                "unexpected error: {error}"
+            );
+        }
+        other => unreachable!("unexpected result: {other:?}"),
+    }
+}
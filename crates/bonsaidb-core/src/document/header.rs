@@ -32,8 +32,13 @@ impl HasHeader for Header {
 
 /// View mapping emit functions. Used when implementing a view's `map()`
 /// function.
+///
+/// Views whose key or value is `()` don't need to construct the empty side:
+/// [`emit_key()`](Self::emit_key) emits a key with an empty value, and
+/// [`emit_value()`](Self::emit_value) emits a value with an empty key.
 pub trait Emit {
-    /// Creates a `Map` result with an empty key and value.
+    /// Creates a `Map` result with an empty key and value. To emit a key with
+    /// an empty value, use [`emit_key()`](Self::emit_key).
     fn emit(&self) -> Result<Mappings<(), ()>, crate::Error> {
         self.emit_key_and_value((), ())
     }
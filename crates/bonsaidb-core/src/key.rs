@@ -1,3 +1,4 @@
+mod net;
 /// [`Key`] implementations for time types.
 pub mod time;
 mod varint;
@@ -758,6 +759,44 @@ impl<'k> IntoPrefixRange<'k, Self> for Vec<u8> {
     }
 }
 
+impl<'k> Key<'k> for &'k [u8] {
+    const CAN_OWN_BYTES: bool = false;
+
+    fn from_ord_bytes<'e>(bytes: ByteSource<'k, 'e>) -> Result<Self, Self::Error> {
+        match bytes {
+            ByteSource::Borrowed(bytes) => Ok(bytes),
+            ByteSource::Ephemeral(_) | ByteSource::Owned(_) => Err(BytesNotBorrowed),
+        }
+    }
+}
+
+impl<'a, 'k> KeyEncoding<&'k [u8]> for &'a [u8] {
+    type Error = BytesNotBorrowed;
+
+    const LENGTH: Option<usize> = None;
+
+    fn describe<Visitor>(visitor: &mut Visitor)
+    where
+        Visitor: KeyVisitor,
+    {
+        visitor.visit_type(KeyKind::Bytes);
+    }
+
+    fn as_ord_bytes(&self) -> Result<Cow<'_, [u8]>, Self::Error> {
+        Ok(Cow::Borrowed(self))
+    }
+}
+
+/// An error that indicates a borrowed [`Key`] was decoded from bytes that
+/// could not be borrowed for the key's lifetime.
+///
+/// Borrowed keys such as `&[u8]` can only be decoded from
+/// [`ByteSource::Borrowed`]. Use an owned type such as `Vec<u8>` or
+/// `Cow<'_, [u8]>` when the source of the bytes is not known.
+#[derive(thiserror::Error, Debug)]
+#[error("key bytes could not be borrowed")]
+pub struct BytesNotBorrowed;
+
 impl<'k, const N: usize> Key<'k> for [u8; N] {
     const CAN_OWN_BYTES: bool = false;
 
@@ -1120,6 +1159,121 @@ impl<'k> KeyEncoding<Self> for Cow<'k, str> {
     }
 }
 
+/// A string [`Key`] that is ordered and matched without regard to case.
+///
+/// The key is encoded using the lowercase form of the string, which means keys
+/// decoded from a view's mappings contain the lowercase form. To retain the
+/// original string, emit it as the view's value:
+///
+/// ```rust
+/// use bonsaidb_core::key::CaseInsensitive;
+///
+/// assert_eq!(CaseInsensitive::from("Ecton"), CaseInsensitive::from("ecton"));
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaseInsensitive(pub String);
+
+impl CaseInsensitive {
+    fn lowercase_chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.0.chars().flat_map(char::to_lowercase)
+    }
+}
+
+impl From<String> for CaseInsensitive {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl<'a> From<&'a str> for CaseInsensitive {
+    fn from(value: &'a str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Deref for CaseInsensitive {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl PartialEq for CaseInsensitive {
+    fn eq(&self, other: &Self) -> bool {
+        self.lowercase_chars().eq(other.lowercase_chars())
+    }
+}
+
+impl Eq for CaseInsensitive {}
+
+impl PartialOrd for CaseInsensitive {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CaseInsensitive {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.lowercase_chars().cmp(other.lowercase_chars())
+    }
+}
+
+impl std::hash::Hash for CaseInsensitive {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for ch in self.lowercase_chars() {
+            std::hash::Hash::hash(&ch, state);
+        }
+    }
+}
+
+impl<'k> Key<'k> for CaseInsensitive {
+    const CAN_OWN_BYTES: bool = true;
+
+    fn from_ord_bytes<'b>(bytes: ByteSource<'k, 'b>) -> Result<Self, Self::Error> {
+        String::from_utf8(bytes.into_owned()).map(Self)
+    }
+}
+
+impl KeyEncoding<Self> for CaseInsensitive {
+    type Error = FromUtf8Error;
+
+    const LENGTH: Option<usize> = None;
+
+    fn describe<Visitor>(visitor: &mut Visitor)
+    where
+        Visitor: KeyVisitor,
+    {
+        visitor.visit_type(KeyKind::String);
+    }
+
+    fn as_ord_bytes(&self) -> Result<Cow<'_, [u8]>, Self::Error> {
+        let lowercase = self.lowercase_chars().collect::<String>();
+        Ok(Cow::Owned(lowercase.into_bytes()))
+    }
+}
+
+#[test]
+fn case_insensitive_key_tests() {
+    let mixed = CaseInsensitive::from("Straße ÄB");
+    let encoded = mixed.as_ord_bytes().unwrap();
+    assert_eq!(&encoded[..], "straße äb".as_bytes());
+    assert_eq!(
+        encoded,
+        CaseInsensitive::from("STRAßE äb").as_ord_bytes().unwrap()
+    );
+
+    let decoded = CaseInsensitive::from_ord_bytes(ByteSource::Borrowed(&encoded)).unwrap();
+    assert_eq!(decoded.0, "straße äb");
+    assert_eq!(decoded, mixed);
+
+    // Ordering matches the encoded bytes rather than the original strings.
+    let upper = CaseInsensitive::from("B");
+    let lower = CaseInsensitive::from("a");
+    assert!(lower < upper);
+    assert!(lower.as_ord_bytes().unwrap() < upper.as_ord_bytes().unwrap());
+}
+
 #[test]
 fn string_prefix_range_tests() {
     use std::ops::RangeBounds;
@@ -2703,6 +2857,8 @@ fn bool_key_encoding_tests() -> anyhow::Result<()> {
     assert!(!bool::from_ord_bytes(ByteSource::Borrowed(
         &false_as_bytes
     ))?);
+    assert_eq!(true_as_bytes.len(), 1);
+    assert!(false_as_bytes < true_as_bytes);
     Ok(())
 }
 
@@ -2717,6 +2873,51 @@ fn vec_key_encoding_tests() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn owned_vec_key_encoding_tests() -> anyhow::Result<()> {
+    const ORIGINAL_VALUE: &[u8] = b"bonsaidb";
+    let vec = ORIGINAL_VALUE.to_vec();
+    let encoded = vec.as_ord_bytes()?;
+    assert_eq!(
+        encoded,
+        Cow::<'_, [u8]>::from(ORIGINAL_VALUE).as_ord_bytes()?
+    );
+    assert_eq!(
+        vec,
+        Vec::<u8>::from_ord_bytes(ByteSource::Borrowed(&encoded))?
+    );
+    assert_eq!(
+        vec,
+        Vec::<u8>::from_ord_bytes(ByteSource::Owned(encoded.to_vec()))?
+    );
+    Ok(())
+}
+
+#[test]
+fn slice_key_encoding_tests() -> anyhow::Result<()> {
+    const ORIGINAL_VALUE: &[u8] = b"bonsaidb";
+    let encoded = KeyEncoding::<&[u8]>::as_ord_bytes(&ORIGINAL_VALUE)?;
+    assert_eq!(
+        encoded,
+        Cow::<'_, [u8]>::from(ORIGINAL_VALUE).as_ord_bytes()?
+    );
+    assert_eq!(
+        ORIGINAL_VALUE,
+        <&[u8]>::from_ord_bytes(ByteSource::Borrowed(&encoded))?
+    );
+    assert!(<&[u8]>::from_ord_bytes(ByteSource::Ephemeral(&encoded)).is_err());
+    assert!(<&[u8]>::from_ord_bytes(ByteSource::Owned(encoded.to_vec())).is_err());
+
+    // Byte slices sort the same way as their encoded form.
+    let lesser: &[u8] = b"a";
+    let greater: &[u8] = b"aa";
+    assert!(
+        KeyEncoding::<&[u8]>::as_ord_bytes(&lesser)?
+            < KeyEncoding::<&[u8]>::as_ord_bytes(&greater)?
+    );
+    Ok(())
+}
+
 #[test]
 fn enum_derive_tests() -> anyhow::Result<()> {
     #[derive(
@@ -2795,3 +2996,39 @@ fn key_serialization() {
     let decoded = <Test as crate::schema::SerializedCollection>::deserialize(&encoded).unwrap();
     assert_eq!(decoded, start);
 }
+
+#[test]
+fn primitive_key_ordering() {
+    use crate::test_util::assert_key_ordering;
+
+    assert_key_ordering(&[false, true]);
+    assert_key_ordering(&[0_u8, 1, 127, 128, u8::MAX]);
+    assert_key_ordering(&[i8::MIN, -1, 0, 1, i8::MAX]);
+    assert_key_ordering(&[0_u16, 1, 255, 256, u16::MAX]);
+    assert_key_ordering(&[i16::MIN, -256, -1, 0, 1, 256, i16::MAX]);
+    assert_key_ordering(&[0_u32, 1, 255, 256, u32::MAX]);
+    assert_key_ordering(&[i32::MIN, -256, -1, 0, 1, 256, i32::MAX]);
+    assert_key_ordering(&[0_u64, 1, 255, 256, u64::MAX]);
+    assert_key_ordering(&[i64::MIN, -256, -1, 0, 1, 256, i64::MAX]);
+    assert_key_ordering(&[0_u128, 1, 255, 256, u128::MAX]);
+    assert_key_ordering(&[i128::MIN, -256, -1, 0, 1, 256, i128::MAX]);
+    assert_key_ordering(&[
+        String::new(),
+        String::from("a"),
+        String::from("ab"),
+        String::from("b"),
+    ]);
+    assert_key_ordering(&[None, Some(0_u64), Some(u64::MAX)]);
+    assert_key_ordering(&[(0_u32, String::from("b")), (1, String::from("a"))]);
+}
+
+#[test]
+#[cfg(feature = "uuid")]
+fn uuid_key_ordering() {
+    crate::test_util::assert_key_ordering(&[
+        uuid::Uuid::nil(),
+        uuid::Uuid::from_u128(1),
+        uuid::Uuid::from_u128(u128::from(u64::MAX) + 1),
+        uuid::Uuid::from_u128(u128::MAX),
+    ]);
+}
@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::key::{
+    ByteSource, CompositeKind, IncorrectByteLength, Key, KeyEncoding, KeyKind, KeyVisitor,
+    NextValueError,
+};
+
+impl<'k> Key<'k> for Ipv4Addr {
+    const CAN_OWN_BYTES: bool = false;
+
+    fn from_ord_bytes<'e>(bytes: ByteSource<'k, 'e>) -> Result<Self, Self::Error> {
+        Ok(Self::from(<[u8; 4]>::try_from(bytes.as_ref())?))
+    }
+
+    fn first_value() -> Result<Self, NextValueError> {
+        Ok(Self::UNSPECIFIED)
+    }
+
+    fn next_value(&self) -> Result<Self, NextValueError> {
+        u32::from(*self)
+            .checked_add(1)
+            .map(Self::from)
+            .ok_or(NextValueError::WouldWrap)
+    }
+}
+
+/// Encodes the address as its four octets, which sorts addresses numerically.
+impl KeyEncoding<Self> for Ipv4Addr {
+    type Error = IncorrectByteLength;
+
+    const LENGTH: Option<usize> = Some(4);
+
+    fn describe<Visitor>(visitor: &mut Visitor)
+    where
+        Visitor: KeyVisitor,
+    {
+        visitor.visit_composite(
+            CompositeKind::Struct(Cow::Borrowed("std::net::Ipv4Addr")),
+            1,
+        );
+        visitor.visit_type(KeyKind::U32);
+    }
+
+    fn as_ord_bytes(&self) -> Result<Cow<'_, [u8]>, Self::Error> {
+        Ok(Cow::Owned(self.octets().to_vec()))
+    }
+}
+
+impl<'k> Key<'k> for Ipv6Addr {
+    const CAN_OWN_BYTES: bool = false;
+
+    fn from_ord_bytes<'e>(bytes: ByteSource<'k, 'e>) -> Result<Self, Self::Error> {
+        Ok(Self::from(<[u8; 16]>::try_from(bytes.as_ref())?))
+    }
+
+    fn first_value() -> Result<Self, NextValueError> {
+        Ok(Self::UNSPECIFIED)
+    }
+
+    fn next_value(&self) -> Result<Self, NextValueError> {
+        u128::from(*self)
+            .checked_add(1)
+            .map(Self::from)
+            .ok_or(NextValueError::WouldWrap)
+    }
+}
+
+/// Encodes the address as its sixteen octets, which sorts addresses
+/// numerically.
+impl KeyEncoding<Self> for Ipv6Addr {
+    type Error = IncorrectByteLength;
+
+    const LENGTH: Option<usize> = Some(16);
+
+    fn describe<Visitor>(visitor: &mut Visitor)
+    where
+        Visitor: KeyVisitor,
+    {
+        visitor.visit_composite(
+            CompositeKind::Struct(Cow::Borrowed("std::net::Ipv6Addr")),
+            1,
+        );
+        visitor.visit_type(KeyKind::U128);
+    }
+
+    fn as_ord_bytes(&self) -> Result<Cow<'_, [u8]>, Self::Error> {
+        Ok(Cow::Owned(self.octets().to_vec()))
+    }
+}
+
+const IP_V4: u8 = 4;
+const IP_V6: u8 = 6;
+
+impl<'k> Key<'k> for IpAddr {
+    const CAN_OWN_BYTES: bool = false;
+
+    fn from_ord_bytes<'e>(bytes: ByteSource<'k, 'e>) -> Result<Self, Self::Error> {
+        match bytes.as_ref().split_first() {
+            Some((&IP_V4, octets)) => Ok(Self::V4(Ipv4Addr::from_ord_bytes(
+                ByteSource::Ephemeral(octets),
+            )?)),
+            Some((&IP_V6, octets)) => Ok(Self::V6(Ipv6Addr::from_ord_bytes(
+                ByteSource::Ephemeral(octets),
+            )?)),
+            _ => Err(IncorrectByteLength),
+        }
+    }
+
+    fn first_value() -> Result<Self, NextValueError> {
+        Ok(Self::V4(Ipv4Addr::UNSPECIFIED))
+    }
+
+    fn next_value(&self) -> Result<Self, NextValueError> {
+        match self {
+            Self::V4(address) => match address.next_value() {
+                Ok(next) => Ok(Self::V4(next)),
+                Err(NextValueError::WouldWrap) => Ok(Self::V6(Ipv6Addr::UNSPECIFIED)),
+                Err(err) => Err(err),
+            },
+            Self::V6(address) => address.next_value().map(Self::V6),
+        }
+    }
+}
+
+/// Encodes the address as a byte identifying the IP version followed by the
+/// address's octets.
+///
+/// Because the version is encoded first, all IPv4 addresses sort before all
+/// IPv6 addresses, and addresses of the same version sort numerically. This
+/// keeps each version grouped together in range queries. IPv4-mapped IPv6
+/// addresses (e.g., `::ffff:127.0.0.1`) are encoded as IPv6 addresses, and
+/// are not equal to the IPv4 address they map.
+impl KeyEncoding<Self> for IpAddr {
+    type Error = IncorrectByteLength;
+
+    const LENGTH: Option<usize> = None;
+
+    fn describe<Visitor>(visitor: &mut Visitor)
+    where
+        Visitor: KeyVisitor,
+    {
+        visitor.visit_composite(CompositeKind::Struct(Cow::Borrowed("std::net::IpAddr")), 2);
+        Ipv4Addr::describe(visitor);
+        Ipv6Addr::describe(visitor);
+    }
+
+    fn as_ord_bytes(&self) -> Result<Cow<'_, [u8]>, Self::Error> {
+        let mut bytes = Vec::with_capacity(17);
+        match self {
+            Self::V4(address) => {
+                bytes.push(IP_V4);
+                bytes.extend_from_slice(&address.octets());
+            }
+            Self::V6(address) => {
+                bytes.push(IP_V6);
+                bytes.extend_from_slice(&address.octets());
+            }
+        }
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+#[test]
+fn ip_addr_key_tests() {
+    let addresses = [
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)),
+        IpAddr::V4(Ipv4Addr::BROADCAST),
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+        IpAddr::V6(Ipv4Addr::new(127, 0, 0, 1).to_ipv6_mapped()),
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+    ];
+
+    let encoded = addresses
+        .iter()
+        .map(|address| address.as_ord_bytes().unwrap().to_vec())
+        .collect::<Vec<_>>();
+    for (address, bytes) in addresses.iter().zip(&encoded) {
+        assert_eq!(
+            &IpAddr::from_ord_bytes(ByteSource::Borrowed(bytes)).unwrap(),
+            address
+        );
+        match address {
+            IpAddr::V4(address) => {
+                let bytes = address.as_ord_bytes().unwrap();
+                assert_eq!(bytes.len(), 4);
+                assert_eq!(
+                    &Ipv4Addr::from_ord_bytes(ByteSource::Borrowed(&bytes)).unwrap(),
+                    address
+                );
+            }
+            IpAddr::V6(address) => {
+                let bytes = address.as_ord_bytes().unwrap();
+                assert_eq!(bytes.len(), 16);
+                assert_eq!(
+                    &Ipv6Addr::from_ord_bytes(ByteSource::Borrowed(&bytes)).unwrap(),
+                    address
+                );
+            }
+        }
+    }
+
+    // The addresses are listed in their expected order.
+    let mut sorted = encoded.clone();
+    sorted.sort();
+    assert_eq!(sorted, encoded);
+
+    assert_eq!(
+        IpAddr::V4(Ipv4Addr::BROADCAST).next_value().unwrap(),
+        IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+    );
+    assert!(matches!(
+        IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)).next_value(),
+        Ok(IpAddr::V4(address)) if address == Ipv4Addr::new(1, 2, 3, 5)
+    ));
+    assert!(IpAddr::from_ord_bytes(ByteSource::Borrowed(&[IP_V4, 1, 2, 3])).is_err());
+    assert!(IpAddr::from_ord_bytes(ByteSource::Borrowed(&[5, 1, 2, 3, 4])).is_err());
+}
@@ -1,9 +1,14 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::time::Duration;
+
 use arc_bytes::serde::Bytes;
 use serde::{Deserialize, Serialize};
 
 mod timestamp;
 
-pub use self::timestamp::Timestamp;
+pub use self::timestamp::{ParseTimestampError, Timestamp};
 use crate::Error;
 
 mod implementation {
@@ -12,7 +17,13 @@ mod implementation {
     use futures::future::BoxFuture;
     use serde::Serialize;
 
-    use crate::keyvalue::{Command, KeyCheck, KeyOperation, KeyStatus, Output, Timestamp};
+    use std::ops::ControlFlow;
+    use std::time::Duration;
+
+    use crate::keyvalue::{
+        Command, Expiration, ExpiringKey, FieldOperation, KeyCheck, KeyOperation, KeyStatus, Lease,
+        Output, Pipeline, SetOperation, Timestamp,
+    };
     use crate::Error;
 
     /// Types for executing get operations.
@@ -21,12 +32,19 @@ mod implementation {
     pub mod increment;
     /// Types for handling key namespaces.
     pub mod namespaced;
+    /// Types for executing pipelines of operations.
+    pub mod pipeline;
     /// Types for executing set operations.
     pub mod set;
 
     use namespaced::Namespaced;
 
     use super::{IncompatibleTypeError, Numeric, Value};
+
+    /// The number of entries requested at a time by
+    /// [`KeyValue::scan_key_value_namespace()`] and
+    /// [`AsyncKeyValue::scan_key_value_namespace()`].
+    const NAMESPACE_SCAN_BATCH_SIZE: usize = 1_000;
     /// Key-Value store methods. The Key-Value store is designed to be a
     /// high-performance, lightweight storage mechanism.
     ///
@@ -52,6 +70,70 @@ mod implementation {
         /// Executes a single [`KeyOperation`].
         fn execute_key_operation(&self, op: KeyOperation) -> Result<Output, Error>;
 
+        /// Returns the keys whose expiration is at or after `start` and before
+        /// `end`, ordered by expiration. Keys in all namespaces are returned,
+        /// unless this store is [`Namespaced`], in which case only keys in its
+        /// namespace are returned.
+        fn keys_expiring_between(
+            &self,
+            start: Timestamp,
+            end: Timestamp,
+        ) -> Result<Vec<ExpiringKey>, Error>;
+
+        /// Returns the expiration of each key in `keys` within `namespace`, in
+        /// the same order as `keys`. Keys that don't exist or don't expire
+        /// have an expiration of `None`.
+        fn execute_key_expirations(
+            &self,
+            namespace: Option<String>,
+            keys: Vec<String>,
+        ) -> Result<Vec<(String, Option<Timestamp>)>, Error>;
+
+        /// Returns up to `limit` keys and values stored in `namespace` whose
+        /// keys sort after `after`, in ascending key order. Keys that have not
+        /// been persisted yet are included. A `limit` of 0 is treated as 1.
+        fn execute_namespace_scan(
+            &self,
+            namespace: Option<String>,
+            after: Option<String>,
+            limit: usize,
+        ) -> Result<Vec<(String, Value)>, Error>;
+
+        /// Moves every key in namespace `from` to namespace `to`, returning the
+        /// number of keys moved. A namespace of `None` refers to the default
+        /// namespace. Values and expirations are preserved, and all keys are
+        /// moved atomically.
+        ///
+        /// If any key in `from` already exists in `to`, no keys are moved and
+        /// [`Error::KeyValueNamespaceConflict`] is returned. The namespaces
+        /// are not relative to the namespace of a [`Namespaced`] store.
+        fn rename_namespace(&self, from: Option<&str>, to: Option<&str>) -> Result<u64, Error>;
+
+        /// Restores the keys of `namespace` as they were at `timestamp` into
+        /// `target`, returning the number of keys restored. A namespace of
+        /// `None` refers to the default namespace.
+        ///
+        /// Afterwards, `target` contains exactly the restored keys: any other
+        /// keys in `target` are removed, and all changes are applied
+        /// atomically. Passing `namespace` as `target` rolls the namespace back
+        /// to `timestamp`. Keys that had expired as of `timestamp` aren't
+        /// restored, and restored keys never expire, even if `target` has a
+        /// default expiration. The namespaces are not relative to the namespace
+        /// of a [`Namespaced`] store.
+        ///
+        /// The past state of `namespace` is reconstructed from a log of
+        /// committed changes. Stores that don't keep this log return an error.
+        fn restore_to(
+            &self,
+            namespace: Option<&str>,
+            timestamp: Timestamp,
+            target: Option<&str>,
+        ) -> Result<u64, Error>;
+
+        /// Executes each step of `pipeline` atomically, returning the output of
+        /// each step. Steps that were skipped have an output of `None`.
+        fn execute_key_pipeline(&self, pipeline: Pipeline) -> Result<Vec<Option<Output>>, Error>;
+
         /// Sets `key` to `value`. This function returns a builder that is also a
         /// Future. Awaiting the builder will execute [`Command::Set`] with the options
         /// given.
@@ -152,6 +234,140 @@ mod implementation {
             get::Builder::new(self, self.key_namespace().map(Into::into), key.into())
         }
 
+        /// Removes `key`, returning the value it contained.
+        ///
+        /// The value is read and the key is removed by a single
+        /// [`Command::Get`] operation, which is executed atomically. Any set
+        /// of `key` is either applied before this operation, in which case
+        /// its value is returned here, or after it, in which case the new
+        /// value remains stored. A value can never be returned by two calls
+        /// to this function, and no concurrent set is lost between the read
+        /// and the removal.
+        fn get_del<S: Into<String>>(&'_ self, key: S) -> Result<Option<Value>, Error> {
+            match self.execute_key_operation(KeyOperation {
+                namespace: self.key_namespace().map(ToOwned::to_owned),
+                key: key.into(),
+                command: Command::Get {
+                    delete: true,
+                    metadata: false,
+                    committed: false,
+                },
+            })? {
+                Output::Value(value) => Ok(value),
+                Output::Status(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from get operation")
+                }
+            }
+        }
+
+        /// Increments the numeric field at `path` within the structured value
+        /// stored at `key`, returning the field's new value. Returns `None` if
+        /// `key` doesn't exist.
+        ///
+        /// The value is modified by a single [`Command::ModifyField`]
+        /// operation, which is executed atomically. The increment saturates
+        /// at the bounds of `amount`'s type.
+        fn increment_field<S: Into<String>, V: Into<Numeric>>(
+            &'_ self,
+            key: S,
+            path: &[&str],
+            amount: V,
+        ) -> Result<Option<Numeric>, Error> {
+            match self.execute_key_operation(KeyOperation {
+                namespace: self.key_namespace().map(ToOwned::to_owned),
+                key: key.into(),
+                command: Command::ModifyField {
+                    path: path.iter().map(ToString::to_string).collect(),
+                    operation: FieldOperation::Increment {
+                        amount: amount.into(),
+                        saturating: true,
+                    },
+                },
+            })? {
+                Output::Value(Some(Value::Numeric(value))) => Ok(Some(value)),
+                Output::Value(None) => Ok(None),
+                Output::Value(Some(Value::Bytes(_) | Value::Set(_)))
+                | Output::Status(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from modify field operation")
+                }
+            }
+        }
+
+        /// Adds `member` to the set stored at `key`, creating the set if `key`
+        /// doesn't exist. Returns true if `member` was added, or false if the
+        /// set already contained it.
+        ///
+        /// Members are serialized using [`pot`] and compared using their
+        /// serialized bytes. Returns an error if `key` contains a value that
+        /// isn't a set.
+        fn add_to_set<S: Into<String>, M: Serialize>(
+            &'_ self,
+            key: S,
+            member: &M,
+        ) -> Result<bool, Error> {
+            let output = self.execute_key_operation(set_operation(
+                self.key_namespace(),
+                key.into(),
+                SetOperation::Add(Bytes::from(pot::to_vec(member)?)),
+            ))?;
+            Ok(set_status(output) != KeyStatus::NotChanged)
+        }
+
+        /// Removes `member` from the set stored at `key`. Returns true if
+        /// `member` was removed, or false if the set didn't contain it. If the
+        /// last member is removed, `key` is deleted.
+        ///
+        /// Returns an error if `key` contains a value that isn't a set.
+        fn remove_from_set<S: Into<String>, M: Serialize>(
+            &'_ self,
+            key: S,
+            member: &M,
+        ) -> Result<bool, Error> {
+            let output = self.execute_key_operation(set_operation(
+                self.key_namespace(),
+                key.into(),
+                SetOperation::Remove(Bytes::from(pot::to_vec(member)?)),
+            ))?;
+            Ok(set_status(output) != KeyStatus::NotChanged)
+        }
+
+        /// Returns true if the set stored at `key` contains `member`.
+        ///
+        /// Returns an error if `key` contains a value that isn't a set.
+        fn set_contains<S: Into<String>, M: Serialize>(
+            &'_ self,
+            key: S,
+            member: &M,
+        ) -> Result<bool, Error> {
+            let output = self.execute_key_operation(set_operation(
+                self.key_namespace(),
+                key.into(),
+                SetOperation::Contains(Bytes::from(pot::to_vec(member)?)),
+            ))?;
+            Ok(set_count(output) > 0)
+        }
+
+        /// Returns the number of members in the set stored at `key`, or `0` if
+        /// `key` doesn't exist.
+        ///
+        /// Returns an error if `key` contains a value that isn't a set.
+        fn set_len<S: Into<String>>(&'_ self, key: S) -> Result<u64, Error> {
+            let output = self.execute_key_operation(set_operation(
+                self.key_namespace(),
+                key.into(),
+                SetOperation::Count,
+            ))?;
+            Ok(set_count(output))
+        }
+
         /// Deletes the value stored at `key`.
         fn delete_key<S: Into<String> + Send>(&'_ self, key: S) -> Result<KeyStatus, Error> {
             match self.execute_key_operation(KeyOperation {
@@ -160,7 +376,199 @@ mod implementation {
                 command: Command::Delete,
             })? {
                 Output::Status(status) => Ok(status),
-                Output::Value(_) => unreachable!("invalid output from delete operation"),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from delete operation")
+                }
+            }
+        }
+
+        /// Sets `key` to expire at `expiration` without changing its value.
+        /// Returns [`KeyStatus::Updated`] if the key exists, or
+        /// [`KeyStatus::NotChanged`] if it doesn't.
+        fn expire_at<S: Into<String> + Send>(
+            &'_ self,
+            key: S,
+            expiration: Timestamp,
+        ) -> Result<KeyStatus, Error> {
+            let output = self.execute_key_operation(touch_operation(
+                self.key_namespace(),
+                key.into(),
+                Some(Expiration::Absolute(expiration)),
+            ))?;
+            Ok(touch_status(output))
+        }
+
+        /// Sets `key` to expire once `duration` has elapsed without changing
+        /// its value. The expiration is computed using the clock of the
+        /// database executing the operation. Returns [`KeyStatus::Updated`] if
+        /// the key exists, or [`KeyStatus::NotChanged`] if it doesn't.
+        fn expire_after<S: Into<String> + Send>(
+            &'_ self,
+            key: S,
+            duration: Duration,
+        ) -> Result<KeyStatus, Error> {
+            let output = self.execute_key_operation(touch_operation(
+                self.key_namespace(),
+                key.into(),
+                Some(Expiration::After(duration)),
+            ))?;
+            Ok(touch_status(output))
+        }
+
+        /// Removes the expiration of `key` without changing its value. Returns
+        /// [`KeyStatus::Updated`] if the key exists, or
+        /// [`KeyStatus::NotChanged`] if it doesn't.
+        fn persist<S: Into<String> + Send>(&'_ self, key: S) -> Result<KeyStatus, Error> {
+            let output = self.execute_key_operation(touch_operation(
+                self.key_namespace(),
+                key.into(),
+                None,
+            ))?;
+            Ok(touch_status(output))
+        }
+
+        /// Returns the expiration of each key in `keys`, in the same order as
+        /// `keys`. Keys that don't exist or don't expire have an expiration of
+        /// `None`. All of the expirations are looked up in a single request.
+        fn key_expirations<S: Into<String>, Keys: IntoIterator<Item = S>>(
+            &'_ self,
+            keys: Keys,
+        ) -> Result<Vec<(String, Option<Timestamp>)>, Error> {
+            self.execute_key_expirations(
+                self.key_namespace().map(ToOwned::to_owned),
+                keys.into_iter().map(Into::into).collect(),
+            )
+        }
+
+        /// Invokes `callback` with each key and value stored in `namespace`,
+        /// in ascending key order. Returning [`ControlFlow::Break`] from
+        /// `callback` stops the scan. The namespace is not relative to the
+        /// namespace of a [`Namespaced`] store.
+        ///
+        /// Entries are requested in batches, so only one batch is held in
+        /// memory at a time. Keys that have not been persisted yet are
+        /// included. Keys modified while the scan is in progress may or may
+        /// not reflect the modification.
+        fn scan_key_value_namespace<F>(
+            &self,
+            namespace: Option<&str>,
+            mut callback: F,
+        ) -> Result<(), Error>
+        where
+            F: FnMut(&str, &Value) -> ControlFlow<()>,
+        {
+            let mut after = None;
+            loop {
+                let mut entries = self.execute_namespace_scan(
+                    namespace.map(ToOwned::to_owned),
+                    after,
+                    NAMESPACE_SCAN_BATCH_SIZE,
+                )?;
+                for (key, value) in &entries {
+                    if callback(key, value).is_break() {
+                        return Ok(());
+                    }
+                }
+                if entries.len() < NAMESPACE_SCAN_BATCH_SIZE {
+                    return Ok(());
+                }
+                after = entries.pop().map(|(key, _)| key);
+            }
+        }
+
+        /// Returns a builder for a [`Pipeline`] of operations that are executed
+        /// atomically. Steps can be made conditional on the outputs of previous
+        /// steps using [`PipelineCondition`](crate::keyvalue::PipelineCondition).
+        ///
+        /// ```rust
+        /// use bonsaidb_core::keyvalue::{KeyValue, Numeric, PipelineCondition};
+        /// # use bonsaidb_core::Error;
+        /// # fn test_fn<KV: KeyValue>(db: &KV) -> Result<(), Error> {
+        /// // Increment a counter, setting a flag if it reaches 10.
+        /// let outputs = db
+        ///     .pipeline()
+        ///     .increment_key_by("counter", 1_u64)
+        ///     .when(PipelineCondition::AtLeast {
+        ///         step: 0,
+        ///         value: Numeric::UnsignedInteger(10),
+        ///     })
+        ///     .set_numeric_key("flag", 1_u64)
+        ///     .execute()?;
+        /// println!("Flag set: {}", outputs[1].is_some());
+        /// # Ok(())
+        /// # }
+        /// ```
+        fn pipeline(&self) -> pipeline::Builder<'_, Self> {
+            pipeline::Builder::new(self, self.key_namespace().map(Into::into))
+        }
+
+        /// Attempts to acquire a [`Lease`] on `key` for `owner`. If `key` is
+        /// vacant, it is set to `owner` and will expire after `ttl` unless the
+        /// lease is renewed using [`KeyValue::renew_lease()`].
+        ///
+        /// Returns `None` if `key` is already present, such as when the lease
+        /// is held by another owner.
+        fn acquire_lease<S: Into<String>, O: Into<String>>(
+            &self,
+            key: S,
+            owner: O,
+            ttl: Duration,
+        ) -> Result<Option<Lease>, Error> {
+            let lease = Lease {
+                namespace: self.key_namespace().map(ToOwned::to_owned),
+                key: key.into(),
+                owner: owner.into(),
+                expiration: Timestamp::now() + ttl,
+            };
+            match self.execute_key_operation(lease.acquire_operation())? {
+                Output::Status(KeyStatus::NotChanged) => Ok(None),
+                Output::Status(_) => Ok(Some(lease)),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from set operation")
+                }
+            }
+        }
+
+        /// Extends `lease` to expire `ttl` from now. Returns false if the lease
+        /// is no longer held by its owner, such as when it has already expired.
+        fn renew_lease(&self, lease: &mut Lease, ttl: Duration) -> Result<bool, Error> {
+            let expiration = Timestamp::now() + ttl;
+            match self.execute_key_operation(lease.renew_operation(expiration))? {
+                Output::Status(KeyStatus::Updated) => {
+                    lease.expiration = expiration;
+                    Ok(true)
+                }
+                Output::Status(_) => Ok(false),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from touch operation")
+                }
+            }
+        }
+
+        /// Releases `lease`, deleting its key if it is still held by its owner.
+        /// Returns false if the lease is no longer held by its owner.
+        fn release_lease(&self, lease: Lease) -> Result<bool, Error> {
+            match self.execute_key_operation(lease.release_operation())? {
+                Output::Status(status) => Ok(status == KeyStatus::Deleted),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from compare and delete operation")
+                }
             }
         }
 
@@ -203,6 +611,77 @@ mod implementation {
         /// Executes a single [`KeyOperation`].
         async fn execute_key_operation(&self, op: KeyOperation) -> Result<Output, Error>;
 
+        /// Returns the keys whose expiration is at or after `start` and before
+        /// `end`, ordered by expiration. Keys in all namespaces are returned,
+        /// unless this store is [`Namespaced`], in which case only keys in its
+        /// namespace are returned.
+        async fn keys_expiring_between(
+            &self,
+            start: Timestamp,
+            end: Timestamp,
+        ) -> Result<Vec<ExpiringKey>, Error>;
+
+        /// Returns the expiration of each key in `keys` within `namespace`, in
+        /// the same order as `keys`. Keys that don't exist or don't expire
+        /// have an expiration of `None`.
+        async fn execute_key_expirations(
+            &self,
+            namespace: Option<String>,
+            keys: Vec<String>,
+        ) -> Result<Vec<(String, Option<Timestamp>)>, Error>;
+
+        /// Returns up to `limit` keys and values stored in `namespace` whose
+        /// keys sort after `after`, in ascending key order. Keys that have not
+        /// been persisted yet are included. A `limit` of 0 is treated as 1.
+        async fn execute_namespace_scan(
+            &self,
+            namespace: Option<String>,
+            after: Option<String>,
+            limit: usize,
+        ) -> Result<Vec<(String, Value)>, Error>;
+
+        /// Moves every key in namespace `from` to namespace `to`, returning the
+        /// number of keys moved. A namespace of `None` refers to the default
+        /// namespace. Values and expirations are preserved, and all keys are
+        /// moved atomically.
+        ///
+        /// If any key in `from` already exists in `to`, no keys are moved and
+        /// [`Error::KeyValueNamespaceConflict`] is returned. The namespaces
+        /// are not relative to the namespace of a [`Namespaced`] store.
+        async fn rename_namespace(
+            &self,
+            from: Option<&str>,
+            to: Option<&str>,
+        ) -> Result<u64, Error>;
+
+        /// Restores the keys of `namespace` as they were at `timestamp` into
+        /// `target`, returning the number of keys restored. A namespace of
+        /// `None` refers to the default namespace.
+        ///
+        /// Afterwards, `target` contains exactly the restored keys: any other
+        /// keys in `target` are removed, and all changes are applied
+        /// atomically. Passing `namespace` as `target` rolls the namespace back
+        /// to `timestamp`. Keys that had expired as of `timestamp` aren't
+        /// restored, and restored keys never expire, even if `target` has a
+        /// default expiration. The namespaces are not relative to the namespace
+        /// of a [`Namespaced`] store.
+        ///
+        /// The past state of `namespace` is reconstructed from a log of
+        /// committed changes. Stores that don't keep this log return an error.
+        async fn restore_to(
+            &self,
+            namespace: Option<&str>,
+            timestamp: Timestamp,
+            target: Option<&str>,
+        ) -> Result<u64, Error>;
+
+        /// Executes each step of `pipeline` atomically, returning the output of
+        /// each step. Steps that were skipped have an output of `None`.
+        async fn execute_key_pipeline(
+            &self,
+            pipeline: Pipeline,
+        ) -> Result<Vec<Option<Output>>, Error>;
+
         /// Sets `key` to `value`. This function returns a builder that is also a
         /// Future. Awaiting the builder will execute [`Command::Set`] with the options
         /// given.
@@ -303,6 +782,154 @@ mod implementation {
             get::AsyncBuilder::new(self, self.key_namespace().map(Into::into), key.into())
         }
 
+        /// Removes `key`, returning the value it contained.
+        ///
+        /// The value is read and the key is removed by a single
+        /// [`Command::Get`] operation, which is executed atomically. Any set
+        /// of `key` is either applied before this operation, in which case
+        /// its value is returned here, or after it, in which case the new
+        /// value remains stored. A value can never be returned by two calls
+        /// to this function, and no concurrent set is lost between the read
+        /// and the removal.
+        async fn get_del<S: Into<String> + Send>(&'_ self, key: S) -> Result<Option<Value>, Error> {
+            match self
+                .execute_key_operation(KeyOperation {
+                    namespace: self.key_namespace().map(ToOwned::to_owned),
+                    key: key.into(),
+                    command: Command::Get {
+                        delete: true,
+                        metadata: false,
+                        committed: false,
+                    },
+                })
+                .await?
+            {
+                Output::Value(value) => Ok(value),
+                Output::Status(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from get operation")
+                }
+            }
+        }
+
+        /// Increments the numeric field at `path` within the structured value
+        /// stored at `key`, returning the field's new value. Returns `None` if
+        /// `key` doesn't exist.
+        ///
+        /// The value is modified by a single [`Command::ModifyField`]
+        /// operation, which is executed atomically. The increment saturates
+        /// at the bounds of `amount`'s type.
+        async fn increment_field<S: Into<String> + Send, V: Into<Numeric> + Send>(
+            &'_ self,
+            key: S,
+            path: &[&str],
+            amount: V,
+        ) -> Result<Option<Numeric>, Error> {
+            match self
+                .execute_key_operation(KeyOperation {
+                    namespace: self.key_namespace().map(ToOwned::to_owned),
+                    key: key.into(),
+                    command: Command::ModifyField {
+                        path: path.iter().map(ToString::to_string).collect(),
+                        operation: FieldOperation::Increment {
+                            amount: amount.into(),
+                            saturating: true,
+                        },
+                    },
+                })
+                .await?
+            {
+                Output::Value(Some(Value::Numeric(value))) => Ok(Some(value)),
+                Output::Value(None) => Ok(None),
+                Output::Value(Some(Value::Bytes(_) | Value::Set(_)))
+                | Output::Status(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from modify field operation")
+                }
+            }
+        }
+
+        /// Adds `member` to the set stored at `key`, creating the set if `key`
+        /// doesn't exist. Returns true if `member` was added, or false if the
+        /// set already contained it.
+        ///
+        /// Members are serialized using [`pot`] and compared using their
+        /// serialized bytes. Returns an error if `key` contains a value that
+        /// isn't a set.
+        async fn add_to_set<S: Into<String> + Send, M: Serialize + Sync>(
+            &'_ self,
+            key: S,
+            member: &M,
+        ) -> Result<bool, Error> {
+            let output = self
+                .execute_key_operation(set_operation(
+                    self.key_namespace(),
+                    key.into(),
+                    SetOperation::Add(Bytes::from(pot::to_vec(member)?)),
+                ))
+                .await?;
+            Ok(set_status(output) != KeyStatus::NotChanged)
+        }
+
+        /// Removes `member` from the set stored at `key`. Returns true if
+        /// `member` was removed, or false if the set didn't contain it. If the
+        /// last member is removed, `key` is deleted.
+        ///
+        /// Returns an error if `key` contains a value that isn't a set.
+        async fn remove_from_set<S: Into<String> + Send, M: Serialize + Sync>(
+            &'_ self,
+            key: S,
+            member: &M,
+        ) -> Result<bool, Error> {
+            let output = self
+                .execute_key_operation(set_operation(
+                    self.key_namespace(),
+                    key.into(),
+                    SetOperation::Remove(Bytes::from(pot::to_vec(member)?)),
+                ))
+                .await?;
+            Ok(set_status(output) != KeyStatus::NotChanged)
+        }
+
+        /// Returns true if the set stored at `key` contains `member`.
+        ///
+        /// Returns an error if `key` contains a value that isn't a set.
+        async fn set_contains<S: Into<String> + Send, M: Serialize + Sync>(
+            &'_ self,
+            key: S,
+            member: &M,
+        ) -> Result<bool, Error> {
+            let output = self
+                .execute_key_operation(set_operation(
+                    self.key_namespace(),
+                    key.into(),
+                    SetOperation::Contains(Bytes::from(pot::to_vec(member)?)),
+                ))
+                .await?;
+            Ok(set_count(output) > 0)
+        }
+
+        /// Returns the number of members in the set stored at `key`, or `0` if
+        /// `key` doesn't exist.
+        ///
+        /// Returns an error if `key` contains a value that isn't a set.
+        async fn set_len<S: Into<String> + Send>(&'_ self, key: S) -> Result<u64, Error> {
+            let output = self
+                .execute_key_operation(set_operation(
+                    self.key_namespace(),
+                    key.into(),
+                    SetOperation::Count,
+                ))
+                .await?;
+            Ok(set_count(output))
+        }
+
         /// Deletes the value stored at `key`.
         async fn delete_key<S: Into<String> + Send>(&'_ self, key: S) -> Result<KeyStatus, Error> {
             match self
@@ -314,7 +941,211 @@ mod implementation {
                 .await?
             {
                 Output::Status(status) => Ok(status),
-                Output::Value(_) => unreachable!("invalid output from delete operation"),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from delete operation")
+                }
+            }
+        }
+
+        /// Sets `key` to expire at `expiration` without changing its value.
+        /// Returns [`KeyStatus::Updated`] if the key exists, or
+        /// [`KeyStatus::NotChanged`] if it doesn't.
+        async fn expire_at<S: Into<String> + Send>(
+            &'_ self,
+            key: S,
+            expiration: Timestamp,
+        ) -> Result<KeyStatus, Error> {
+            let output = self
+                .execute_key_operation(touch_operation(
+                    self.key_namespace(),
+                    key.into(),
+                    Some(Expiration::Absolute(expiration)),
+                ))
+                .await?;
+            Ok(touch_status(output))
+        }
+
+        /// Sets `key` to expire once `duration` has elapsed without changing
+        /// its value. The expiration is computed using the clock of the
+        /// database executing the operation. Returns [`KeyStatus::Updated`] if
+        /// the key exists, or [`KeyStatus::NotChanged`] if it doesn't.
+        async fn expire_after<S: Into<String> + Send>(
+            &'_ self,
+            key: S,
+            duration: Duration,
+        ) -> Result<KeyStatus, Error> {
+            let output = self
+                .execute_key_operation(touch_operation(
+                    self.key_namespace(),
+                    key.into(),
+                    Some(Expiration::After(duration)),
+                ))
+                .await?;
+            Ok(touch_status(output))
+        }
+
+        /// Removes the expiration of `key` without changing its value. Returns
+        /// [`KeyStatus::Updated`] if the key exists, or
+        /// [`KeyStatus::NotChanged`] if it doesn't.
+        async fn persist<S: Into<String> + Send>(&'_ self, key: S) -> Result<KeyStatus, Error> {
+            let output = self
+                .execute_key_operation(touch_operation(self.key_namespace(), key.into(), None))
+                .await?;
+            Ok(touch_status(output))
+        }
+
+        /// Returns the expiration of each key in `keys`, in the same order as
+        /// `keys`. Keys that don't exist or don't expire have an expiration of
+        /// `None`. All of the expirations are looked up in a single request.
+        async fn key_expirations<S: Into<String> + Send, Keys: IntoIterator<Item = S> + Send>(
+            &'_ self,
+            keys: Keys,
+        ) -> Result<Vec<(String, Option<Timestamp>)>, Error> {
+            let keys = keys.into_iter().map(Into::into).collect();
+            self.execute_key_expirations(self.key_namespace().map(ToOwned::to_owned), keys)
+                .await
+        }
+
+        /// Invokes `callback` with each key and value stored in `namespace`,
+        /// in ascending key order. Returning [`ControlFlow::Break`] from
+        /// `callback` stops the scan. The namespace is not relative to the
+        /// namespace of a [`Namespaced`] store.
+        ///
+        /// Entries are requested in batches, so only one batch is held in
+        /// memory at a time. Keys that have not been persisted yet are
+        /// included. Keys modified while the scan is in progress may or may
+        /// not reflect the modification.
+        async fn scan_key_value_namespace<F>(
+            &self,
+            namespace: Option<&str>,
+            mut callback: F,
+        ) -> Result<(), Error>
+        where
+            F: FnMut(&str, &Value) -> ControlFlow<()> + Send,
+        {
+            let mut after = None;
+            loop {
+                let mut entries = self
+                    .execute_namespace_scan(
+                        namespace.map(ToOwned::to_owned),
+                        after,
+                        NAMESPACE_SCAN_BATCH_SIZE,
+                    )
+                    .await?;
+                for (key, value) in &entries {
+                    if callback(key, value).is_break() {
+                        return Ok(());
+                    }
+                }
+                if entries.len() < NAMESPACE_SCAN_BATCH_SIZE {
+                    return Ok(());
+                }
+                after = entries.pop().map(|(key, _)| key);
+            }
+        }
+
+        /// Returns a builder for a [`Pipeline`] of operations that are executed
+        /// atomically. Steps can be made conditional on the outputs of previous
+        /// steps using [`PipelineCondition`](crate::keyvalue::PipelineCondition).
+        ///
+        /// ```rust
+        /// use bonsaidb_core::keyvalue::{AsyncKeyValue, Numeric, PipelineCondition};
+        /// # use bonsaidb_core::Error;
+        /// # async fn test_fn<KV: AsyncKeyValue>(db: &KV) -> Result<(), Error> {
+        /// // Increment a counter, setting a flag if it reaches 10.
+        /// let outputs = db
+        ///     .pipeline()
+        ///     .increment_key_by("counter", 1_u64)
+        ///     .when(PipelineCondition::AtLeast {
+        ///         step: 0,
+        ///         value: Numeric::UnsignedInteger(10),
+        ///     })
+        ///     .set_numeric_key("flag", 1_u64)
+        ///     .await?;
+        /// println!("Flag set: {}", outputs[1].is_some());
+        /// # Ok(())
+        /// # }
+        /// ```
+        fn pipeline(&self) -> pipeline::AsyncBuilder<'_, Self> {
+            pipeline::AsyncBuilder::new(self, self.key_namespace().map(Into::into))
+        }
+
+        /// Attempts to acquire a [`Lease`] on `key` for `owner`. If `key` is
+        /// vacant, it is set to `owner` and will expire after `ttl` unless the
+        /// lease is renewed using [`AsyncKeyValue::renew_lease()`].
+        ///
+        /// Returns `None` if `key` is already present, such as when the lease
+        /// is held by another owner.
+        async fn acquire_lease<S: Into<String> + Send, O: Into<String> + Send>(
+            &self,
+            key: S,
+            owner: O,
+            ttl: Duration,
+        ) -> Result<Option<Lease>, Error> {
+            let lease = Lease {
+                namespace: self.key_namespace().map(ToOwned::to_owned),
+                key: key.into(),
+                owner: owner.into(),
+                expiration: Timestamp::now() + ttl,
+            };
+            match self
+                .execute_key_operation(lease.acquire_operation())
+                .await?
+            {
+                Output::Status(KeyStatus::NotChanged) => Ok(None),
+                Output::Status(_) => Ok(Some(lease)),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from set operation")
+                }
+            }
+        }
+
+        /// Extends `lease` to expire `ttl` from now. Returns false if the lease
+        /// is no longer held by its owner, such as when it has already expired.
+        async fn renew_lease(&self, lease: &mut Lease, ttl: Duration) -> Result<bool, Error> {
+            let expiration = Timestamp::now() + ttl;
+            match self
+                .execute_key_operation(lease.renew_operation(expiration))
+                .await?
+            {
+                Output::Status(KeyStatus::Updated) => {
+                    lease.expiration = expiration;
+                    Ok(true)
+                }
+                Output::Status(_) => Ok(false),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from touch operation")
+                }
+            }
+        }
+
+        /// Releases `lease`, deleting its key if it is still held by its owner.
+        /// Returns false if the lease is no longer held by its owner.
+        async fn release_lease(&self, lease: Lease) -> Result<bool, Error> {
+            match self
+                .execute_key_operation(lease.release_operation())
+                .await?
+            {
+                Output::Status(status) => Ok(status == KeyStatus::Deleted),
+                Output::Value(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("invalid output from compare and delete operation")
+                }
             }
         }
 
@@ -355,6 +1186,67 @@ mod implementation {
             }
         }
     }
+
+    fn set_operation(
+        namespace: Option<&str>,
+        key: String,
+        operation: SetOperation,
+    ) -> KeyOperation {
+        KeyOperation {
+            namespace: namespace.map(ToOwned::to_owned),
+            key,
+            command: Command::ModifySet(operation),
+        }
+    }
+
+    fn set_status(output: Output) -> KeyStatus {
+        match output {
+            Output::Status(status) => status,
+            Output::Value(_)
+            | Output::Bytes(_)
+            | Output::ValueWithMetadata(_)
+            | Output::Bounded(_)
+            | Output::Changed(_) => unreachable!("invalid output from set operation"),
+        }
+    }
+
+    fn touch_operation(
+        namespace: Option<&str>,
+        key: String,
+        expiration: Option<Expiration>,
+    ) -> KeyOperation {
+        KeyOperation {
+            namespace: namespace.map(ToOwned::to_owned),
+            key,
+            command: Command::Touch {
+                expiration,
+                expected: None,
+            },
+        }
+    }
+
+    fn touch_status(output: Output) -> KeyStatus {
+        match output {
+            Output::Status(status) => status,
+            Output::Value(_)
+            | Output::Bytes(_)
+            | Output::ValueWithMetadata(_)
+            | Output::Bounded(_)
+            | Output::Changed(_) => unreachable!("invalid output from touch operation"),
+        }
+    }
+
+    fn set_count(output: Output) -> u64 {
+        match output {
+            Output::Value(Some(Value::Numeric(count))) => count.as_u64_lossy(true),
+            Output::Value(Some(Value::Bytes(_) | Value::Set(_)) | None)
+            | Output::Status(_)
+            | Output::Bytes(_)
+            | Output::ValueWithMetadata(_)
+            | Output::Bounded(_)
+            | Output::Changed(_) => unreachable!("invalid output from set operation"),
+        }
+    }
 }
 
 pub use implementation::*;
@@ -386,8 +1278,29 @@ pub enum Command {
     Set(SetCommand),
     /// Get the value from a key.
     Get {
-        /// Remove the key after retrieving the value.
+        /// Remove the key after retrieving the value. The value is read and
+        /// removed atomically: no other operation on the key can be executed
+        /// between the two.
         delete: bool,
+        /// Return [`Output::ValueWithMetadata`], which includes the stored
+        /// size and expiration of the value, instead of [`Output::Value`].
+        #[serde(default)]
+        metadata: bool,
+        /// Return the value most recently committed to disk instead of the
+        /// current value. By default, a read observes every write that has
+        /// been executed, including writes that the database's persistence
+        /// policy hasn't committed to disk yet. When this is true, those
+        /// pending writes are ignored, and the value returned is the one
+        /// stored on disk. Unless the database records pending writes in a
+        /// write-ahead log, this is the value that would be read after
+        /// reopening the database if it were to stop before committing them.
+        /// With a write-ahead log, pending writes are restored when the
+        /// database is reopened, so the value read after reopening can differ.
+        /// Committed values that have expired are still treated as absent.
+        ///
+        /// A committed read can't be combined with `delete`.
+        #[serde(default)]
+        committed: bool,
     },
     /// Increment a numeric key. Returns an error if the key cannot be
     /// deserialized to the same numeric type as `amount`. If `saturating` is
@@ -399,6 +1312,14 @@ pub enum Command {
         /// If true, the result will be constrained to the numerical bounds of
         /// the type of `amount`.
         saturating: bool,
+        /// If present, the result will not be greater than this value, and
+        /// [`Output::Bounded`] is returned instead of [`Output::Value`].
+        #[serde(default)]
+        maximum: Option<Numeric>,
+        /// Return [`Output::Changed`], which includes the value before the
+        /// increment, instead of [`Output::Value`] or [`Output::Bounded`].
+        #[serde(default)]
+        previous: bool,
     },
     /// Decrement a numeric key. Returns an error if the key cannot be
     /// deserialized to the same numeric type as `amount`. If `saturating` is
@@ -410,9 +1331,333 @@ pub enum Command {
         /// If true, the result will be constrained to the numerical bounds of
         /// the type of `amount`.
         saturating: bool,
+        /// If present, the result will not be less than this value, and
+        /// [`Output::Bounded`] is returned instead of [`Output::Value`].
+        #[serde(default)]
+        minimum: Option<Numeric>,
+        /// Return [`Output::Changed`], which includes the value before the
+        /// decrement, instead of [`Output::Value`] or [`Output::Bounded`].
+        #[serde(default)]
+        previous: bool,
+    },
+    /// Get the bytes stored in a key without wrapping them in a [`Value`],
+    /// returning [`Output::Bytes`]. This allows the stored bytes to be passed
+    /// along without being decoded. Returns an error if the key contains a
+    /// [`Numeric`] value.
+    GetBytes {
+        /// Remove the key after retrieving the bytes.
+        delete: bool,
+        /// Return the bytes most recently committed to disk instead of the
+        /// current value. See [`Command::Get`] for details.
+        #[serde(default)]
+        committed: bool,
     },
     /// Delete a key.
     Delete,
+    /// Delete a key only if its current value is equal to `expected`.
+    /// Returns [`KeyStatus::Deleted`] if the key was deleted.
+    CompareAndDelete {
+        /// The value the key must currently contain to be deleted.
+        expected: Value,
+    },
+    /// Update the expiration of an existing key without changing its value.
+    /// Returns [`KeyStatus::Updated`] if the expiration was updated.
+    Touch {
+        /// The new expiration of the key. If `None`, the key will no longer
+        /// expire.
+        expiration: Option<Expiration>,
+        /// If provided, the expiration is only updated if the key's current
+        /// value is equal to this value.
+        expected: Option<Value>,
+    },
+    /// Modify a numeric field within a structured value that was stored as
+    /// serialized bytes, such as with [`KeyValue::set_key()`]. The value is
+    /// decoded, modified, and stored again atomically. Returns
+    /// [`Output::Value`] containing the field's new [`Numeric`] value, or
+    /// `None` if the key doesn't exist.
+    ///
+    /// Returns an error without modifying the value if the stored value is a
+    /// [`Numeric`], if any component of `path` does not name a field that
+    /// exists, or if the field is not a number.
+    ModifyField {
+        /// The names of the fields to descend through to reach the field to
+        /// modify. For example, `["stats", "visits"]` modifies the `visits`
+        /// field of the structure stored in the `stats` field.
+        path: Vec<String>,
+        /// The modification to make to the field.
+        operation: FieldOperation,
+    },
+    /// Modify or inspect the set of unique members stored in a
+    /// [`Value::Set`]. Members are compared using their bytes. Adding a member
+    /// to a key that doesn't exist creates a new set, and removing the last
+    /// member of a set deletes the key.
+    ///
+    /// Returns an error without modifying the key if it contains a value that
+    /// isn't a [`Value::Set`].
+    ModifySet(SetOperation),
+}
+
+/// An operation on the members of a [`Value::Set`], executed by
+/// [`Command::ModifySet`].
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum SetOperation {
+    /// Add a member to the set. Returns [`Output::Status`] containing
+    /// [`KeyStatus::Inserted`] if the key was created,
+    /// [`KeyStatus::Updated`] if the member was added to an existing set, or
+    /// [`KeyStatus::NotChanged`] if the set already contained the member.
+    Add(Bytes),
+    /// Remove a member from the set. Returns [`Output::Status`] containing
+    /// [`KeyStatus::Updated`] if the member was removed,
+    /// [`KeyStatus::Deleted`] if the member was the last member of the set and
+    /// the key was deleted, or [`KeyStatus::NotChanged`] if the set didn't
+    /// contain the member.
+    Remove(Bytes),
+    /// Check whether the set contains a member. Returns [`Output::Value`]
+    /// containing [`Numeric::UnsignedInteger`] `1` if the member is present,
+    /// or `0` if it isn't or the key doesn't exist.
+    Contains(Bytes),
+    /// Count the members of the set. Returns [`Output::Value`] containing the
+    /// number of members as a [`Numeric::UnsignedInteger`], or `0` if the key
+    /// doesn't exist.
+    Count,
+}
+
+/// A modification made to a numeric field by [`Command::ModifyField`].
+///
+/// The field's new value has the type of `amount`, following the same rules as
+/// [`Command::Increment`] and [`Command::Decrement`]. The field must still be
+/// able to be deserialized as the type stored in it, so the amount should be a
+/// compatible type.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum FieldOperation {
+    /// Increment the field by `amount`.
+    Increment {
+        /// The amount to increment by.
+        amount: Numeric,
+        /// If true, the result will be constrained to the numerical bounds of
+        /// the type of `amount`.
+        saturating: bool,
+    },
+    /// Decrement the field by `amount`.
+    Decrement {
+        /// The amount to decrement by.
+        amount: Numeric,
+        /// If true, the result will be constrained to the numerical bounds of
+        /// the type of `amount`.
+        saturating: bool,
+    },
+}
+
+/// A key that is set to expire, returned from
+/// [`KeyValue::keys_expiring_between()`]/[`AsyncKeyValue::keys_expiring_between()`].
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+pub struct ExpiringKey {
+    /// The namespace of the key.
+    pub namespace: Option<String>,
+    /// The key.
+    pub key: String,
+    /// When the key will expire.
+    pub expiration: Timestamp,
+}
+
+/// A persisted change to a key in the key-value store.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct KeyChange {
+    /// The namespace of the key.
+    pub namespace: Option<String>,
+    /// The key that changed.
+    pub key: String,
+    /// The new value of the key, or `None` if the key was removed.
+    pub value: Option<Value>,
+    /// The expiration of the key, if any.
+    pub expiration: Option<Timestamp>,
+}
+
+/// An event delivered to a key-value watcher.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum KeyWatchEvent {
+    /// A watched key was changed.
+    Changed(KeyChange),
+    /// The watcher fell behind and changes were discarded. Watchers that need
+    /// a consistent view should re-read the keys they are interested in after
+    /// receiving this event.
+    Lagged {
+        /// The number of changed keys that were discarded. This count may
+        /// include changes to keys that the watcher wasn't interested in.
+        dropped_keys: u64,
+    },
+}
+
+/// A sequence of key-value operations that are executed atomically, created
+/// using [`KeyValue::pipeline()`]/[`AsyncKeyValue::pipeline()`].
+///
+/// Steps are executed in order while the key-value store is locked, preventing
+/// other operations from observing or modifying the store between steps. Each
+/// step may have a [`PipelineCondition`] that refers to the outputs of earlier
+/// steps. If the condition isn't met, the step is skipped. If any step returns
+/// an error, the changes made by the previous steps are undone.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct Pipeline {
+    /// The steps to execute, in order.
+    pub steps: Vec<PipelineStep>,
+}
+
+/// A single step in a [`Pipeline`].
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PipelineStep {
+    /// The operation to execute.
+    pub operation: KeyOperation,
+    /// If present, the operation is only executed if this condition is met.
+    pub condition: Option<PipelineCondition>,
+}
+
+/// A condition that controls whether a [`PipelineStep`] is executed. Steps are
+/// referred to by their index in [`Pipeline::steps`], and only steps that come
+/// before the step being evaluated can be referenced.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum PipelineCondition {
+    /// Met if `step` was executed.
+    Executed {
+        /// The index of the step.
+        step: usize,
+    },
+    /// Met if `step` returned `status`.
+    Status {
+        /// The index of the step.
+        step: usize,
+        /// The expected status.
+        status: KeyStatus,
+    },
+    /// Met if `step` returned a value equal to `value`. `None` matches a
+    /// step that found no value.
+    Equals {
+        /// The index of the step.
+        step: usize,
+        /// The expected value.
+        value: Option<Value>,
+    },
+    /// Met if `step` returned a numeric value greater than or equal to
+    /// `value`.
+    AtLeast {
+        /// The index of the step.
+        step: usize,
+        /// The minimum value.
+        value: Numeric,
+    },
+    /// Met if `step` returned a numeric value less than `value`.
+    LessThan {
+        /// The index of the step.
+        step: usize,
+        /// The value the output must be below.
+        value: Numeric,
+    },
+    /// Met if the contained condition is not met.
+    Not(Box<PipelineCondition>),
+}
+
+impl PipelineCondition {
+    /// Evaluates this condition against the outputs of the steps executed so
+    /// far. Steps that were skipped have an output of `None`.
+    ///
+    /// Returns an error if a step that hasn't been executed yet is referenced.
+    pub fn evaluate(&self, outputs: &[Option<Output>]) -> Result<bool, Error> {
+        let output = |step: usize| {
+            outputs.get(step).ok_or_else(|| {
+                Error::other(
+                    "key-value",
+                    format!("pipeline condition references step {step}, which hasn't executed"),
+                )
+            })
+        };
+        Ok(match self {
+            Self::Executed { step } => output(*step)?.is_some(),
+            Self::Status { step, status } => {
+                matches!(output(*step)?, Some(Output::Status(actual)) if actual == status)
+            }
+            Self::Equals { step, value } => {
+                matches!(output(*step)?, Some(Output::Value(actual)) if actual == value)
+            }
+            Self::AtLeast { step, value } => matches!(
+                output(*step)?,
+                Some(
+                    Output::Value(Some(Value::Numeric(actual)))
+                    | Output::Bounded(BoundedValue { value: actual, .. })
+                    | Output::Changed(ChangedValue { value: actual, .. })
+                ) if actual.compare(value).map_or(false, Ordering::is_ge)
+            ),
+            Self::LessThan { step, value } => matches!(
+                output(*step)?,
+                Some(
+                    Output::Value(Some(Value::Numeric(actual)))
+                    | Output::Bounded(BoundedValue { value: actual, .. })
+                    | Output::Changed(ChangedValue { value: actual, .. })
+                ) if actual.compare(value).map_or(false, Ordering::is_lt)
+            ),
+            Self::Not(condition) => !condition.evaluate(outputs)?,
+        })
+    }
+}
+
+/// A lease on a key, acquired using
+/// [`KeyValue::acquire_lease()`]/[`AsyncKeyValue::acquire_lease()`].
+///
+/// A lease is a lock with an owner and an expiration. While the lease is held,
+/// the key contains the owner, and attempts by other owners to acquire the
+/// lease will fail. If the lease is not renewed before it expires, the key is
+/// removed automatically, allowing another owner to acquire it. This prevents
+/// a lock from being held forever by an owner that is no longer running.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[must_use]
+pub struct Lease {
+    /// The namespace of the leased key.
+    pub namespace: Option<String>,
+    /// The leased key.
+    pub key: String,
+    /// The owner that holds this lease.
+    pub owner: String,
+    /// The time this lease expires unless renewed.
+    pub expiration: Timestamp,
+}
+
+impl Lease {
+    fn owner_value(&self) -> Value {
+        Value::Bytes(Bytes::from(self.owner.as_bytes()))
+    }
+
+    fn acquire_operation(&self) -> KeyOperation {
+        KeyOperation {
+            namespace: self.namespace.clone(),
+            key: self.key.clone(),
+            command: Command::Set(SetCommand {
+                value: self.owner_value(),
+                expiration: Some(Expiration::Absolute(self.expiration)),
+                keep_existing_expiration: false,
+                check: Some(KeyCheck::OnlyIfVacant),
+                return_previous_value: false,
+            }),
+        }
+    }
+
+    fn renew_operation(&self, expiration: Timestamp) -> KeyOperation {
+        KeyOperation {
+            namespace: self.namespace.clone(),
+            key: self.key.clone(),
+            command: Command::Touch {
+                expiration: Some(Expiration::Absolute(expiration)),
+                expected: Some(self.owner_value()),
+            },
+        }
+    }
+
+    fn release_operation(&self) -> KeyOperation {
+        KeyOperation {
+            namespace: self.namespace.clone(),
+            key: self.key.clone(),
+            command: Command::CompareAndDelete {
+                expected: self.owner_value(),
+            },
+        }
+    }
 }
 
 /// Set a key/value pair.
@@ -420,8 +1665,11 @@ pub enum Command {
 pub struct SetCommand {
     /// The value.
     pub value: Value,
-    /// If set, the key will be set to expire automatically.
-    pub expiration: Option<Timestamp>,
+    /// If set, the key will be set to expire automatically. If `None`, the
+    /// key's namespace's default expiration is used, if the storage defines
+    /// one. An [`Expiration::Absolute`] of [`Timestamp::MAX`] stores the key
+    /// without an expiration, regardless of any default.
+    pub expiration: Option<Expiration>,
     /// If true and the key already exists, the expiration will not be
     /// updated. If false and an expiration is provided, the expiration will
     /// be set.
@@ -432,6 +1680,36 @@ pub struct SetCommand {
     pub return_previous_value: bool,
 }
 
+/// When a key set by a [`SetCommand`] or updated by [`Command::Touch`]
+/// expires.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Expiration {
+    /// The key expires at the given time.
+    Absolute(Timestamp),
+    /// The key expires once the duration has elapsed after the key is set.
+    /// The expiration is computed using the clock of the database executing
+    /// the command, which avoids depending on the requesting client's clock.
+    After(Duration),
+}
+
+impl Expiration {
+    /// Returns the time this expiration occurs, computing relative
+    /// expirations from `now`.
+    #[must_use]
+    pub fn resolve(self, now: Timestamp) -> Timestamp {
+        match self {
+            Self::Absolute(timestamp) => timestamp,
+            Self::After(duration) => now + duration,
+        }
+    }
+}
+
+impl From<Timestamp> for Expiration {
+    fn from(timestamp: Timestamp) -> Self {
+        Self::Absolute(timestamp)
+    }
+}
+
 /// A value stored in a key.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Value {
@@ -439,6 +1717,9 @@ pub enum Value {
     Bytes(Bytes),
     /// A numeric value.
     Numeric(Numeric),
+    /// A set of unique members, each stored as a byte array. Sets are
+    /// modified using [`Command::ModifySet`].
+    Set(BTreeSet<Bytes>),
 }
 
 impl Value {
@@ -447,6 +1728,7 @@ impl Value {
         match self {
             Self::Numeric(numeric) => numeric.validate().map(Self::Numeric),
             Self::Bytes(vec) => Ok(Self::Bytes(vec)),
+            Self::Set(members) => Ok(Self::Set(members)),
         }
     }
 
@@ -459,6 +1741,10 @@ impl Value {
                 "key-value",
                 "key contains numeric value, not serialized data",
             )),
+            Self::Set(_) => Err(Error::other(
+                "key-value",
+                "key contains a set, not serialized data",
+            )),
         }
     }
 
@@ -466,7 +1752,7 @@ impl Value {
     #[must_use]
     pub fn as_i64_lossy(&self, saturating: bool) -> Option<i64> {
         match self {
-            Self::Bytes(_) => None,
+            Self::Bytes(_) | Self::Set(_) => None,
             Self::Numeric(value) => Some(value.as_i64_lossy(saturating)),
         }
     }
@@ -475,16 +1761,16 @@ impl Value {
     #[must_use]
     pub fn as_u64_lossy(&self, saturating: bool) -> Option<u64> {
         match self {
-            Self::Bytes(_) => None,
+            Self::Bytes(_) | Self::Set(_) => None,
             Self::Numeric(value) => Some(value.as_u64_lossy(saturating)),
         }
     }
 
     /// Returns this value as an `f64`, allowing for precision to be lost if the type was not an `f64` originally. Returns None if the value is bytes.
     #[must_use]
-    pub const fn as_f64_lossy(&self) -> Option<f64> {
+    pub fn as_f64_lossy(&self) -> Option<f64> {
         match self {
-            Self::Bytes(_) => None,
+            Self::Bytes(_) | Self::Set(_) => None,
             Self::Numeric(value) => Some(value.as_f64_lossy()),
         }
     }
@@ -493,7 +1779,7 @@ impl Value {
     #[must_use]
     pub fn as_i64(&self) -> Option<i64> {
         match self {
-            Self::Bytes(_) => None,
+            Self::Bytes(_) | Self::Set(_) => None,
             Self::Numeric(value) => value.as_i64(),
         }
     }
@@ -502,16 +1788,16 @@ impl Value {
     #[must_use]
     pub fn as_u64(&self) -> Option<u64> {
         match self {
-            Self::Bytes(_) => None,
+            Self::Bytes(_) | Self::Set(_) => None,
             Self::Numeric(value) => value.as_u64(),
         }
     }
 
     /// Returns this numeric as an `f64`, allowing for precision to be lost if the type was not an `f64` originally. Returns None if the value is bytes.
     #[must_use]
-    pub const fn as_f64(&self) -> Option<f64> {
+    pub fn as_f64(&self) -> Option<f64> {
         match self {
-            Self::Bytes(_) => None,
+            Self::Bytes(_) | Self::Set(_) => None,
             Self::Numeric(value) => value.as_f64(),
         }
     }
@@ -526,6 +1812,8 @@ pub enum Numeric {
     UnsignedInteger(u64),
     /// A 64-bit floating point number.
     Float(f64),
+    /// A fixed-point decimal number.
+    Decimal(Decimal),
 }
 
 impl Numeric {
@@ -535,14 +1823,17 @@ impl Numeric {
     ///
     /// [`Error::NotANumber`] is returned if this contains a NaN floating point
     /// value.
+    ///
+    /// [`Error::Decimal`] is returned if this contains a [`Decimal`] whose
+    /// scale exceeds [`Decimal::MAX_SCALE`].
     pub fn validate(self) -> Result<Self, Error> {
-        if let Self::Float(float) = self {
-            if float.is_nan() {
-                return Err(Error::NotANumber);
+        match self {
+            Self::Float(float) if float.is_nan() => Err(Error::NotANumber),
+            Self::Decimal(decimal) if decimal.scale > Decimal::MAX_SCALE => {
+                Err(Error::Decimal(DecimalError::ScaleTooLarge(decimal.scale)))
             }
+            _ => Ok(self),
         }
-
-        Ok(self)
     }
 
     /// Returns this numeric as an `i64`. If this conversion cannot be done
@@ -560,6 +1851,7 @@ impl Numeric {
                     Some(*value as i64)
                 }
             }
+            Self::Decimal(value) => value.to_integer(),
         }
     }
 
@@ -579,6 +1871,7 @@ impl Numeric {
                 }
             }
             Self::Float(value) => *value as i64,
+            Self::Decimal(value) => value.truncate(),
         }
     }
 
@@ -597,6 +1890,7 @@ impl Numeric {
                     None
                 }
             }
+            Self::Decimal(value) => value.to_integer().and_then(|value| value.try_into().ok()),
         }
     }
 
@@ -616,6 +1910,14 @@ impl Numeric {
                 }
             }
             Self::Float(value) => *value as u64,
+            Self::Decimal(value) => {
+                let value = value.truncate();
+                if saturating {
+                    value.try_into().unwrap_or(0)
+                } else {
+                    value as u64
+                }
+            }
         }
     }
 
@@ -623,7 +1925,7 @@ impl Numeric {
     /// without losing precision, None will be returned.
     #[must_use]
     #[allow(clippy::cast_precision_loss)]
-    pub const fn as_f64(&self) -> Option<f64> {
+    pub fn as_f64(&self) -> Option<f64> {
         match self {
             Self::UnsignedInteger(value) => {
                 if *value > 2_u64.pow(f64::MANTISSA_DIGITS) {
@@ -642,6 +1944,9 @@ impl Numeric {
                 }
             }
             Self::Float(value) => Some(*value),
+            Self::Decimal(value) => value
+                .to_integer()
+                .and_then(|value| Self::Integer(value).as_f64()),
         }
     }
 
@@ -649,15 +1954,159 @@ impl Numeric {
     /// the type was not an `f64` originally.
     #[must_use]
     #[allow(clippy::cast_precision_loss)]
-    pub const fn as_f64_lossy(&self) -> f64 {
+    pub fn as_f64_lossy(&self) -> f64 {
         match self {
             Self::UnsignedInteger(value) => *value as f64,
             Self::Integer(value) => *value as f64,
             Self::Float(value) => *value,
+            Self::Decimal(value) => value.value as f64 / value.multiplier().unwrap_or(1) as f64,
+        }
+    }
+
+    /// Returns this numeric as a [`Decimal`] with `scale` digits after the
+    /// decimal point.
+    ///
+    /// # Errors
+    ///
+    /// - [`DecimalError::ScaleMismatch`]: This value is a [`Decimal`] with a
+    ///   different scale.
+    /// - [`DecimalError::ScaleTooLarge`]: `scale` exceeds
+    ///   [`Decimal::MAX_SCALE`].
+    /// - [`DecimalError::NotRepresentable`]: This value is a floating point
+    ///   number, or an integer that overflows when scaled.
+    pub fn as_decimal(&self, scale: u32) -> Result<Decimal, DecimalError> {
+        let multiplier = Decimal::new(0, scale)
+            .multiplier()
+            .ok_or(DecimalError::ScaleTooLarge(scale))?;
+        match self {
+            Self::Decimal(decimal) if decimal.scale == scale => Ok(*decimal),
+            Self::Decimal(decimal) => Err(DecimalError::ScaleMismatch {
+                expected: scale,
+                actual: decimal.scale,
+            }),
+            Self::Integer(value) => value
+                .checked_mul(multiplier)
+                .map(|value| Decimal::new(value, scale))
+                .ok_or(DecimalError::NotRepresentable),
+            Self::UnsignedInteger(value) => i64::try_from(*value)
+                .ok()
+                .and_then(|value| value.checked_mul(multiplier))
+                .map(|value| Decimal::new(value, scale))
+                .ok_or(DecimalError::NotRepresentable),
+            Self::Float(_) => Err(DecimalError::NotRepresentable),
+        }
+    }
+
+    /// Compares this value against `other`. Values of the same integer type,
+    /// or decimals with the same scale, are compared exactly. Otherwise, both
+    /// values are compared as `f64`s. Returns `None` if either value is NaN.
+    #[must_use]
+    pub fn compare(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Integer(a), Self::Integer(b)) => Some(a.cmp(b)),
+            (Self::UnsignedInteger(a), Self::UnsignedInteger(b)) => Some(a.cmp(b)),
+            (Self::Decimal(a), Self::Decimal(b)) if a.scale == b.scale => {
+                Some(a.value.cmp(&b.value))
+            }
+            _ => self.as_f64_lossy().partial_cmp(&other.as_f64_lossy()),
+        }
+    }
+}
+
+/// A fixed-point decimal number, stored as an integer `value` and the number
+/// of digits after the decimal point (`scale`).
+///
+/// `Decimal::new(1999, 2)` represents `19.99`. Incrementing and decrementing
+/// decimals with the same scale is exact, making this type suitable for
+/// storing monetary amounts.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Decimal {
+    value: i64,
+    scale: u32,
+}
+
+impl Decimal {
+    /// The largest supported scale.
+    pub const MAX_SCALE: u32 = 18;
+
+    /// Returns a new decimal representing `value / 10^scale`.
+    #[must_use]
+    pub const fn new(value: i64, scale: u32) -> Self {
+        Self { value, scale }
+    }
+
+    /// Returns the unscaled integer value.
+    #[must_use]
+    pub const fn value(&self) -> i64 {
+        self.value
+    }
+
+    /// Returns the number of digits after the decimal point.
+    #[must_use]
+    pub const fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    const fn multiplier(&self) -> Option<i64> {
+        if self.scale > Self::MAX_SCALE {
+            None
+        } else {
+            10_i64.checked_pow(self.scale)
+        }
+    }
+
+    fn truncate(&self) -> i64 {
+        self.multiplier()
+            .map_or(0, |multiplier| self.value / multiplier)
+    }
+
+    fn to_integer(&self) -> Option<i64> {
+        let multiplier = self.multiplier()?;
+        if self.value % multiplier == 0 {
+            Some(self.value / multiplier)
+        } else {
+            None
         }
     }
 }
 
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.multiplier() {
+            Some(1) | None => write!(f, "{}", self.value),
+            Some(multiplier) => {
+                let sign = if self.value < 0 { "-" } else { "" };
+                let whole = (self.value / multiplier).unsigned_abs();
+                let fraction = (self.value % multiplier).unsigned_abs();
+                write!(
+                    f,
+                    "{sign}{whole}.{fraction:0width$}",
+                    width = self.scale as usize
+                )
+            }
+        }
+    }
+}
+
+/// An error performing an operation on a [`Decimal`].
+#[derive(thiserror::Error, Debug, Clone, Serialize, Deserialize)]
+pub enum DecimalError {
+    /// The operands of a decimal operation have different scales.
+    #[error("decimal scale mismatch: expected {expected}, got {actual}")]
+    ScaleMismatch {
+        /// The scale of the stored value.
+        expected: u32,
+        /// The scale of the provided value.
+        actual: u32,
+    },
+    /// The scale exceeds [`Decimal::MAX_SCALE`].
+    #[error("decimal scale {0} exceeds the maximum of 18")]
+    ScaleTooLarge(u32),
+    /// The value cannot be represented exactly as a decimal.
+    #[error("value cannot be represented exactly as a decimal")]
+    NotRepresentable,
+}
+
 /// A conversion between numeric types wasn't supported.
 #[derive(thiserror::Error, Debug)]
 #[error("incompatible numeric type")]
@@ -681,6 +2130,12 @@ impl From<u64> for Numeric {
     }
 }
 
+impl From<Decimal> for Numeric {
+    fn from(value: Decimal) -> Self {
+        Self::Decimal(value)
+    }
+}
+
 #[allow(clippy::fallible_impl_from)]
 impl TryFrom<Numeric> for f64 {
     type Error = IncompatibleTypeError;
@@ -720,6 +2175,19 @@ impl TryFrom<Numeric> for i64 {
     }
 }
 
+#[allow(clippy::fallible_impl_from)]
+impl TryFrom<Numeric> for Decimal {
+    type Error = IncompatibleTypeError;
+
+    fn try_from(value: Numeric) -> Result<Self, IncompatibleTypeError> {
+        if let Numeric::Decimal(value) = value {
+            Ok(value)
+        } else {
+            Err(IncompatibleTypeError)
+        }
+    }
+}
+
 /// The result of a [`KeyOperation`].
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum Output {
@@ -727,7 +2195,56 @@ pub enum Output {
     Status(KeyStatus),
     /// A value was returned.
     Value(Option<Value>),
+    /// The bytes stored in a key were returned from [`Command::GetBytes`].
+    Bytes(Option<Bytes>),
+    /// A value and its metadata were returned from [`Command::Get`] with
+    /// `metadata` set to true.
+    ValueWithMetadata(Option<ValueWithMetadata>),
+    /// The result of a [`Command::Increment`] or [`Command::Decrement`] with a
+    /// limit was returned.
+    Bounded(BoundedValue),
+    /// The result of a [`Command::Increment`] or [`Command::Decrement`] with
+    /// `previous` set to true was returned.
+    Changed(ChangedValue),
+}
+
+/// A value stored in the key-value store along with information about how it
+/// is stored.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ValueWithMetadata {
+    /// The stored value.
+    pub value: Value,
+    /// The number of bytes the value's entry occupies when persisted,
+    /// including its expiration.
+    pub size: u64,
+    /// The time at which the key will expire, if any.
+    pub expiration: Option<Timestamp>,
 }
+
+/// The result of incrementing or decrementing a key with a limit.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BoundedValue {
+    /// The value stored after the operation.
+    pub value: Numeric,
+    /// True if the result would have been past the limit, causing the stored
+    /// value to be the limit instead.
+    pub limit_reached: bool,
+}
+
+/// The result of incrementing or decrementing a key, including the value
+/// stored before the operation.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChangedValue {
+    /// The value stored before the operation, converted to the type of the
+    /// stored value. If the key did not exist, this is zero.
+    pub previous: Numeric,
+    /// The value stored after the operation.
+    pub value: Numeric,
+    /// True if the result would have been past the limit, causing the stored
+    /// value to be the limit instead. Always false when no limit was given.
+    pub limit_reached: bool,
+}
+
 /// The status of an operation on a Key.
 #[derive(Copy, Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
 pub enum KeyStatus {
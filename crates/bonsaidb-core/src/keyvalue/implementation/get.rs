@@ -1,8 +1,9 @@
+use arc_bytes::serde::Bytes;
 use futures::{Future, FutureExt};
 use serde::Deserialize;
 
 use super::{BuilderState, Command, KeyOperation, KeyValue, Output};
-use crate::keyvalue::{AsyncKeyValue, Value};
+use crate::keyvalue::{AsyncKeyValue, Value, ValueWithMetadata};
 use crate::Error;
 
 /// Builder for a [`Command::Get`] key-value operation.
@@ -12,6 +13,7 @@ pub struct Builder<'a, KeyValue> {
     namespace: Option<String>,
     key: String,
     delete: bool,
+    committed: bool,
 }
 impl<'a, K> Builder<'a, K>
 where
@@ -23,6 +25,7 @@ where
             kv,
             namespace,
             delete: false,
+            committed: false,
         }
     }
 
@@ -32,6 +35,13 @@ where
         self
     }
 
+    /// Retrieve the value most recently committed to disk, ignoring writes
+    /// that haven't been persisted yet. See [`Command::Get`] for details.
+    pub const fn committed_only(mut self) -> Self {
+        self.committed = true;
+        self
+    }
+
     /// Deserializes the [`Value`] before returning. If the value is a
     /// [`Numeric`](crate::keyvalue::Numeric), an error will be returned.
     pub fn into<V: for<'de> Deserialize<'de>>(self) -> Result<Option<V>, Error> {
@@ -136,6 +146,55 @@ where
         }
     }
 
+    /// Retrieves the bytes stored in the key without decoding them into a
+    /// [`Value`]. If the value is a [`Numeric`](crate::keyvalue::Numeric), an
+    /// error will be returned.
+    pub fn into_bytes(self) -> Result<Option<Vec<u8>>, Error> {
+        let Self {
+            kv,
+            namespace,
+            key,
+            delete,
+            committed,
+        } = self;
+        let result = kv.execute_key_operation(KeyOperation {
+            namespace,
+            key,
+            command: Command::GetBytes { delete, committed },
+        })?;
+        if let Output::Bytes(bytes) = result {
+            Ok(bytes.map(Bytes::into_vec))
+        } else {
+            unreachable!("Unexpected result from get")
+        }
+    }
+
+    /// Retrieves the value for the key along with its stored size and
+    /// expiration, using the configured options.
+    pub fn query_with_metadata(self) -> Result<Option<ValueWithMetadata>, Error> {
+        let Self {
+            kv,
+            namespace,
+            key,
+            delete,
+            committed,
+        } = self;
+        let result = kv.execute_key_operation(KeyOperation {
+            namespace,
+            key,
+            command: Command::Get {
+                delete,
+                metadata: true,
+                committed,
+            },
+        })?;
+        if let Output::ValueWithMetadata(value) = result {
+            Ok(value)
+        } else {
+            unreachable!("Unexpected result from get")
+        }
+    }
+
     /// Retrieves the value for the key, using the configured options.
     pub fn query(self) -> Result<Option<Value>, Error> {
         let Self {
@@ -143,11 +202,16 @@ where
             namespace,
             key,
             delete,
+            committed,
         } = self;
         let result = kv.execute_key_operation(KeyOperation {
             namespace,
             key,
-            command: Command::Get { delete },
+            command: Command::Get {
+                delete,
+                metadata: false,
+                committed,
+            },
         })?;
         if let Output::Value(value) = result {
             Ok(value)
@@ -169,6 +233,7 @@ struct Options<'a, KeyValue> {
     namespace: Option<String>,
     key: String,
     delete: bool,
+    committed: bool,
 }
 
 impl<'a, K> AsyncBuilder<'a, K>
@@ -182,6 +247,7 @@ where
                 kv,
                 namespace,
                 delete: false,
+                committed: false,
             })),
         }
     }
@@ -200,6 +266,13 @@ where
         self
     }
 
+    /// Retrieve the value most recently committed to disk, ignoring writes
+    /// that haven't been persisted yet. See [`Command::Get`] for details.
+    pub fn committed_only(mut self) -> Self {
+        self.options().committed = true;
+        self
+    }
+
     /// Deserializes the [`Value`] before returning. If the value is a
     /// [`Numeric`](crate::keyvalue::Numeric), an error will be returned.
     pub async fn into<V: for<'de> Deserialize<'de>>(self) -> Result<Option<V>, Error> {
@@ -303,6 +376,73 @@ where
             None => Ok(None),
         }
     }
+
+    /// Retrieves the bytes stored in the key without decoding them into a
+    /// [`Value`]. If the value is a [`Numeric`](crate::keyvalue::Numeric), an
+    /// error will be returned.
+    pub async fn into_bytes(mut self) -> Result<Option<Vec<u8>>, Error> {
+        let Options {
+            kv,
+            namespace,
+            key,
+            delete,
+            committed,
+        } = match &mut self.state {
+            BuilderState::Pending(options) => {
+                options.take().expect("expected builder to have options")
+            }
+            BuilderState::Executing(_) => {
+                unreachable!("Attempted to use after retrieving the result")
+            }
+        };
+        let result = kv
+            .execute_key_operation(KeyOperation {
+                namespace,
+                key,
+                command: Command::GetBytes { delete, committed },
+            })
+            .await?;
+        if let Output::Bytes(bytes) = result {
+            Ok(bytes.map(Bytes::into_vec))
+        } else {
+            unreachable!("Unexpected result from get")
+        }
+    }
+
+    /// Retrieves the value for the key along with its stored size and
+    /// expiration, using the configured options.
+    pub async fn query_with_metadata(mut self) -> Result<Option<ValueWithMetadata>, Error> {
+        let Options {
+            kv,
+            namespace,
+            key,
+            delete,
+            committed,
+        } = match &mut self.state {
+            BuilderState::Pending(options) => {
+                options.take().expect("expected builder to have options")
+            }
+            BuilderState::Executing(_) => {
+                unreachable!("Attempted to use after retrieving the result")
+            }
+        };
+        let result = kv
+            .execute_key_operation(KeyOperation {
+                namespace,
+                key,
+                command: Command::Get {
+                    delete,
+                    metadata: true,
+                    committed,
+                },
+            })
+            .await?;
+        if let Output::ValueWithMetadata(value) = result {
+            Ok(value)
+        } else {
+            unreachable!("Unexpected result from get")
+        }
+    }
 }
 
 impl<'a, K> Future for AsyncBuilder<'a, K>
@@ -323,13 +463,18 @@ where
                     namespace,
                     key,
                     delete,
+                    committed,
                 } = builder.take().expect("expected builder to have options");
                 let future = async move {
                     let result = kv
                         .execute_key_operation(KeyOperation {
                             namespace,
                             key,
-                            command: Command::Get { delete },
+                            command: Command::Get {
+                                delete,
+                                metadata: false,
+                                committed,
+                            },
                         })
                         .await?;
                     if let Output::Value(value) = result {
@@ -3,7 +3,9 @@ use std::marker::PhantomData;
 use futures::{Future, FutureExt};
 
 use super::{BuilderState, Command, KeyOperation, KeyValue, Output};
-use crate::keyvalue::{AsyncKeyValue, IncompatibleTypeError, Numeric, Value};
+use crate::keyvalue::{
+    AsyncKeyValue, BoundedValue, ChangedValue, IncompatibleTypeError, Numeric, Value,
+};
 use crate::Error;
 
 /// Executes a [`Command::Increment`] or [`Command::Decrement`] key-value operation.
@@ -15,6 +17,7 @@ pub struct Builder<'a, KeyValue, V> {
     increment: bool,
     amount: Numeric,
     saturating: bool,
+    limit: Option<Numeric>,
     _value: PhantomData<V>,
 }
 
@@ -37,6 +40,7 @@ where
             increment,
             amount,
             saturating: true,
+            limit: None,
             _value: PhantomData,
         }
     }
@@ -47,8 +51,25 @@ where
         self
     }
 
+    /// Limits the result to `limit`. When incrementing, the stored value will
+    /// not be greater than `limit`. When decrementing, the stored value will
+    /// not be less than `limit`.
+    pub fn limit(mut self, limit: V) -> Self
+    where
+        V: Into<Numeric>,
+    {
+        self.limit = Some(limit.into());
+        self
+    }
+
     /// Executes the operation using the configured options.
     pub fn execute(self) -> Result<V, Error> {
+        self.execute_bounded().map(|(value, _)| value)
+    }
+
+    /// Executes the operation using the configured options, returning the
+    /// stored value and whether the [`limit`](Self::limit) was reached.
+    pub fn execute_bounded(self) -> Result<(V, bool), Error> {
         let Self {
             kv,
             namespace,
@@ -56,22 +77,38 @@ where
             increment,
             amount,
             saturating,
+            limit,
             ..
         } = self;
         let result = kv.execute_key_operation(KeyOperation {
             namespace,
             key,
-            command: if increment {
-                Command::Increment { amount, saturating }
-            } else {
-                Command::Decrement { amount, saturating }
-            },
+            command: command(increment, amount, saturating, limit, false),
         })?;
-        if let Output::Value(Some(Value::Numeric(value))) = result {
-            Ok(V::try_from(value).expect("server should send back identical type"))
-        } else {
-            unreachable!("Unexpected result from key value operation")
-        }
+        Ok(bounded_result(result))
+    }
+
+    /// Executes the operation using the configured options, returning the
+    /// value stored before the operation and the value stored after it. Both
+    /// values are read and written atomically. If the key did not exist, the
+    /// previous value is zero.
+    pub fn execute_with_previous(self) -> Result<(V, V), Error> {
+        let Self {
+            kv,
+            namespace,
+            key,
+            increment,
+            amount,
+            saturating,
+            limit,
+            ..
+        } = self;
+        let result = kv.execute_key_operation(KeyOperation {
+            namespace,
+            key,
+            command: command(increment, amount, saturating, limit, true),
+        })?;
+        Ok(changed_result(result))
     }
 }
 
@@ -88,6 +125,7 @@ struct Options<'a, KeyValue> {
     increment: bool,
     amount: Numeric,
     saturating: bool,
+    limit: Option<Numeric>,
 }
 
 impl<'a, K, V> AsyncBuilder<'a, K, V>
@@ -109,6 +147,7 @@ where
                 increment,
                 amount,
                 saturating: true,
+                limit: None,
             })),
         }
     }
@@ -126,6 +165,83 @@ where
         self.options().saturating = false;
         self
     }
+
+    /// Limits the result to `limit`. When incrementing, the stored value will
+    /// not be greater than `limit`. When decrementing, the stored value will
+    /// not be less than `limit`.
+    pub fn limit(mut self, limit: V) -> Self
+    where
+        V: Into<Numeric>,
+    {
+        self.options().limit = Some(limit.into());
+        self
+    }
+
+    /// Executes the operation using the configured options, returning the
+    /// stored value and whether the [`limit`](Self::limit) was reached.
+    pub async fn execute_bounded(mut self) -> Result<(V, bool), Error>
+    where
+        V: TryFrom<Numeric, Error = IncompatibleTypeError>,
+    {
+        let Options {
+            kv,
+            namespace,
+            key,
+            increment,
+            amount,
+            saturating,
+            limit,
+        } = match &mut self.state {
+            BuilderState::Pending(options) => {
+                options.take().expect("expected builder to have options")
+            }
+            BuilderState::Executing(_) => {
+                unreachable!("Attempted to use after retrieving the result")
+            }
+        };
+        let result = kv
+            .execute_key_operation(KeyOperation {
+                namespace,
+                key,
+                command: command(increment, amount, saturating, limit, false),
+            })
+            .await?;
+        Ok(bounded_result(result))
+    }
+
+    /// Executes the operation using the configured options, returning the
+    /// value stored before the operation and the value stored after it. Both
+    /// values are read and written atomically. If the key did not exist, the
+    /// previous value is zero.
+    pub async fn execute_with_previous(mut self) -> Result<(V, V), Error>
+    where
+        V: TryFrom<Numeric, Error = IncompatibleTypeError>,
+    {
+        let Options {
+            kv,
+            namespace,
+            key,
+            increment,
+            amount,
+            saturating,
+            limit,
+        } = match &mut self.state {
+            BuilderState::Pending(options) => {
+                options.take().expect("expected builder to have options")
+            }
+            BuilderState::Executing(_) => {
+                unreachable!("Attempted to use after retrieving the result")
+            }
+        };
+        let result = kv
+            .execute_key_operation(KeyOperation {
+                namespace,
+                key,
+                command: command(increment, amount, saturating, limit, true),
+            })
+            .await?;
+        Ok(changed_result(result))
+    }
 }
 
 impl<'a, K, V> Future for AsyncBuilder<'a, K, V>
@@ -149,24 +265,18 @@ where
                     increment,
                     amount,
                     saturating,
+                    limit,
                 } = builder.take().expect("expected builder to have options");
                 let future = async move {
                     let result = kv
                         .execute_key_operation(KeyOperation {
                             namespace,
                             key,
-                            command: if increment {
-                                Command::Increment { amount, saturating }
-                            } else {
-                                Command::Decrement { amount, saturating }
-                            },
+                            command: command(increment, amount, saturating, limit, false),
                         })
                         .await?;
-                    if let Output::Value(Some(Value::Numeric(value))) = result {
-                        Ok(V::try_from(value).expect("server should send back identical type"))
-                    } else {
-                        unreachable!("Unexpected result from key value operation")
-                    }
+                    let (value, _) = bounded_result(result);
+                    Ok(value)
                 }
                 .boxed();
 
@@ -176,3 +286,54 @@ where
         }
     }
 }
+
+fn command(
+    increment: bool,
+    amount: Numeric,
+    saturating: bool,
+    limit: Option<Numeric>,
+    previous: bool,
+) -> Command {
+    if increment {
+        Command::Increment {
+            amount,
+            saturating,
+            maximum: limit,
+            previous,
+        }
+    } else {
+        Command::Decrement {
+            amount,
+            saturating,
+            minimum: limit,
+            previous,
+        }
+    }
+}
+
+fn bounded_result<V: TryFrom<Numeric, Error = IncompatibleTypeError>>(result: Output) -> (V, bool) {
+    let (value, limit_reached) = match result {
+        Output::Value(Some(Value::Numeric(value))) => (value, false),
+        Output::Bounded(BoundedValue {
+            value,
+            limit_reached,
+        }) => (value, limit_reached),
+        _ => unreachable!("Unexpected result from key value operation"),
+    };
+    (
+        V::try_from(value).expect("server should send back identical type"),
+        limit_reached,
+    )
+}
+
+fn changed_result<V: TryFrom<Numeric, Error = IncompatibleTypeError>>(result: Output) -> (V, V) {
+    match result {
+        Output::Changed(ChangedValue {
+            previous, value, ..
+        }) => (
+            V::try_from(previous).expect("server should send back identical type"),
+            V::try_from(value).expect("server should send back identical type"),
+        ),
+        _ => unreachable!("Unexpected result from key value operation"),
+    }
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 
-use super::{KeyOperation, KeyValue, Output};
+use super::{ExpiringKey, KeyOperation, KeyValue, Output, Pipeline, Timestamp, Value};
 use crate::keyvalue::AsyncKeyValue;
 use crate::Error;
 
@@ -26,6 +26,50 @@ where
         self.kv.execute_key_operation(op)
     }
 
+    fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, Error> {
+        let mut keys = self.kv.keys_expiring_between(start, end)?;
+        keys.retain(|key| key.namespace.as_deref() == Some(self.namespace.as_str()));
+        Ok(keys)
+    }
+
+    fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, Error> {
+        self.kv.execute_key_expirations(namespace, keys)
+    }
+
+    fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, Error> {
+        self.kv.execute_namespace_scan(namespace, after, limit)
+    }
+
+    fn rename_namespace(&self, from: Option<&str>, to: Option<&str>) -> Result<u64, Error> {
+        self.kv.rename_namespace(from, to)
+    }
+
+    fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, Error> {
+        self.kv.restore_to(namespace, timestamp, target)
+    }
+
+    fn execute_key_pipeline(&self, pipeline: Pipeline) -> Result<Vec<Option<Output>>, Error> {
+        self.kv.execute_key_pipeline(pipeline)
+    }
+
     fn key_namespace(&self) -> Option<&'_ str> {
         Some(&self.namespace)
     }
@@ -50,6 +94,52 @@ where
         self.kv.execute_key_operation(op).await
     }
 
+    async fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, Error> {
+        let mut keys = self.kv.keys_expiring_between(start, end).await?;
+        keys.retain(|key| key.namespace.as_deref() == Some(self.namespace.as_str()));
+        Ok(keys)
+    }
+
+    async fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, Error> {
+        self.kv.execute_key_expirations(namespace, keys).await
+    }
+
+    async fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, Error> {
+        self.kv
+            .execute_namespace_scan(namespace, after, limit)
+            .await
+    }
+
+    async fn rename_namespace(&self, from: Option<&str>, to: Option<&str>) -> Result<u64, Error> {
+        self.kv.rename_namespace(from, to).await
+    }
+
+    async fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, Error> {
+        self.kv.restore_to(namespace, timestamp, target).await
+    }
+
+    async fn execute_key_pipeline(&self, pipeline: Pipeline) -> Result<Vec<Option<Output>>, Error> {
+        self.kv.execute_key_pipeline(pipeline).await
+    }
+
     fn key_namespace(&self) -> Option<&'_ str> {
         Some(&self.namespace)
     }
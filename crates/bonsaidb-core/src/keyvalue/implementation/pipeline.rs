@@ -0,0 +1,269 @@
+use arc_bytes::serde::Bytes;
+use futures::{Future, FutureExt};
+
+use super::{BuilderState, Command, KeyOperation, KeyValue, Output};
+use crate::keyvalue::{
+    AsyncKeyValue, Numeric, Pipeline, PipelineCondition, PipelineStep, SetCommand, Value,
+};
+use crate::Error;
+
+/// Builds and executes a [`Pipeline`] of key-value operations.
+///
+/// Each operation added becomes a step in the pipeline. The first step added
+/// is step `0`, the second is step `1`, and so on. These indexes are used to
+/// refer to the outputs of previous steps in a [`PipelineCondition`].
+#[must_use = "the key-value pipeline is not performed until execute() is called"]
+pub struct Builder<'a, KeyValue> {
+    kv: &'a KeyValue,
+    steps: Steps,
+}
+
+impl<'a, K> Builder<'a, K>
+where
+    K: KeyValue,
+{
+    pub(crate) const fn new(kv: &'a K, namespace: Option<String>) -> Self {
+        Self {
+            kv,
+            steps: Steps::new(namespace),
+        }
+    }
+
+    /// Only executes the next step added if `condition` is met.
+    pub fn when(mut self, condition: PipelineCondition) -> Self {
+        self.steps.condition = Some(condition);
+        self
+    }
+
+    /// Adds a step executing `command` on `key`.
+    pub fn operation<S: Into<String>>(mut self, key: S, command: Command) -> Self {
+        self.steps.push(key.into(), command);
+        self
+    }
+
+    /// Adds a step setting `key` to the numeric `value`.
+    pub fn set_numeric_key<S: Into<String>, V: Into<Numeric>>(self, key: S, value: V) -> Self {
+        self.operation(key, Steps::set(Value::Numeric(value.into())))
+    }
+
+    /// Adds a step setting `key` to `bytes`.
+    pub fn set_binary_key<S: Into<String>, B: Into<Bytes>>(self, key: S, bytes: B) -> Self {
+        self.operation(key, Steps::set(Value::Bytes(bytes.into())))
+    }
+
+    /// Adds a step incrementing `key` by `amount`, saturating at the bounds of
+    /// the numeric type. The step outputs the new value.
+    pub fn increment_key_by<S: Into<String>, V: Into<Numeric>>(self, key: S, amount: V) -> Self {
+        self.operation(
+            key,
+            Command::Increment {
+                amount: amount.into(),
+                saturating: true,
+                maximum: None,
+                previous: false,
+            },
+        )
+    }
+
+    /// Adds a step decrementing `key` by `amount`, saturating at the bounds of
+    /// the numeric type. The step outputs the new value.
+    pub fn decrement_key_by<S: Into<String>, V: Into<Numeric>>(self, key: S, amount: V) -> Self {
+        self.operation(
+            key,
+            Command::Decrement {
+                amount: amount.into(),
+                saturating: true,
+                minimum: None,
+                previous: false,
+            },
+        )
+    }
+
+    /// Adds a step retrieving the value stored at `key`.
+    pub fn get_key<S: Into<String>>(self, key: S) -> Self {
+        self.operation(
+            key,
+            Command::Get {
+                delete: false,
+                metadata: false,
+                committed: false,
+            },
+        )
+    }
+
+    /// Adds a step deleting the value stored at `key`.
+    pub fn delete_key<S: Into<String>>(self, key: S) -> Self {
+        self.operation(key, Command::Delete)
+    }
+
+    /// Executes the pipeline, returning the output of each step. Steps that
+    /// were skipped have an output of `None`.
+    pub fn execute(self) -> Result<Vec<Option<Output>>, Error> {
+        self.kv.execute_key_pipeline(self.steps.pipeline)
+    }
+}
+
+/// Builds and executes a [`Pipeline`] of key-value operations when awaited.
+///
+/// Each operation added becomes a step in the pipeline. The first step added
+/// is step `0`, the second is step `1`, and so on. These indexes are used to
+/// refer to the outputs of previous steps in a [`PipelineCondition`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AsyncBuilder<'a, KeyValue> {
+    state: BuilderState<'a, Options<'a, KeyValue>, Result<Vec<Option<Output>>, Error>>,
+}
+
+struct Options<'a, KeyValue> {
+    kv: &'a KeyValue,
+    steps: Steps,
+}
+
+impl<'a, K> AsyncBuilder<'a, K>
+where
+    K: AsyncKeyValue,
+{
+    pub(crate) const fn new(kv: &'a K, namespace: Option<String>) -> Self {
+        Self {
+            state: BuilderState::Pending(Some(Options {
+                kv,
+                steps: Steps::new(namespace),
+            })),
+        }
+    }
+
+    fn options(&mut self) -> &mut Options<'a, K> {
+        if let BuilderState::Pending(Some(options)) = &mut self.state {
+            options
+        } else {
+            panic!("Attempted to use after retrieving the result")
+        }
+    }
+
+    /// Only executes the next step added if `condition` is met.
+    pub fn when(mut self, condition: PipelineCondition) -> Self {
+        self.options().steps.condition = Some(condition);
+        self
+    }
+
+    /// Adds a step executing `command` on `key`.
+    pub fn operation<S: Into<String>>(mut self, key: S, command: Command) -> Self {
+        self.options().steps.push(key.into(), command);
+        self
+    }
+
+    /// Adds a step setting `key` to the numeric `value`.
+    pub fn set_numeric_key<S: Into<String>, V: Into<Numeric>>(self, key: S, value: V) -> Self {
+        self.operation(key, Steps::set(Value::Numeric(value.into())))
+    }
+
+    /// Adds a step setting `key` to `bytes`.
+    pub fn set_binary_key<S: Into<String>, B: Into<Bytes>>(self, key: S, bytes: B) -> Self {
+        self.operation(key, Steps::set(Value::Bytes(bytes.into())))
+    }
+
+    /// Adds a step incrementing `key` by `amount`, saturating at the bounds of
+    /// the numeric type. The step outputs the new value.
+    pub fn increment_key_by<S: Into<String>, V: Into<Numeric>>(self, key: S, amount: V) -> Self {
+        self.operation(
+            key,
+            Command::Increment {
+                amount: amount.into(),
+                saturating: true,
+                maximum: None,
+                previous: false,
+            },
+        )
+    }
+
+    /// Adds a step decrementing `key` by `amount`, saturating at the bounds of
+    /// the numeric type. The step outputs the new value.
+    pub fn decrement_key_by<S: Into<String>, V: Into<Numeric>>(self, key: S, amount: V) -> Self {
+        self.operation(
+            key,
+            Command::Decrement {
+                amount: amount.into(),
+                saturating: true,
+                minimum: None,
+                previous: false,
+            },
+        )
+    }
+
+    /// Adds a step retrieving the value stored at `key`.
+    pub fn get_key<S: Into<String>>(self, key: S) -> Self {
+        self.operation(
+            key,
+            Command::Get {
+                delete: false,
+                metadata: false,
+                committed: false,
+            },
+        )
+    }
+
+    /// Adds a step deleting the value stored at `key`.
+    pub fn delete_key<S: Into<String>>(self, key: S) -> Self {
+        self.operation(key, Command::Delete)
+    }
+}
+
+impl<'a, K> Future for AsyncBuilder<'a, K>
+where
+    K: AsyncKeyValue,
+{
+    type Output = Result<Vec<Option<Output>>, Error>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        match &mut self.state {
+            BuilderState::Executing(future) => future.as_mut().poll(cx),
+            BuilderState::Pending(builder) => {
+                let Options { kv, steps } =
+                    builder.take().expect("expected builder to have options");
+                let future = async move { kv.execute_key_pipeline(steps.pipeline).await }.boxed();
+
+                self.state = BuilderState::Executing(future);
+                self.poll(cx)
+            }
+        }
+    }
+}
+
+struct Steps {
+    namespace: Option<String>,
+    pipeline: Pipeline,
+    condition: Option<PipelineCondition>,
+}
+
+impl Steps {
+    const fn new(namespace: Option<String>) -> Self {
+        Self {
+            namespace,
+            pipeline: Pipeline { steps: Vec::new() },
+            condition: None,
+        }
+    }
+
+    const fn set(value: Value) -> Command {
+        Command::Set(SetCommand {
+            value,
+            expiration: None,
+            keep_existing_expiration: false,
+            check: None,
+            return_previous_value: false,
+        })
+    }
+
+    fn push(&mut self, key: String, command: Command) {
+        self.pipeline.steps.push(PipelineStep {
+            operation: KeyOperation {
+                namespace: self.namespace.clone(),
+                key,
+                command,
+            },
+            condition: self.condition.take(),
+        });
+    }
+}
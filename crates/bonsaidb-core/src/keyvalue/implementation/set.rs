@@ -1,4 +1,3 @@
-use std::ops::Add;
 use std::time::{Duration, SystemTime};
 
 use futures::{Future, FutureExt};
@@ -8,7 +7,7 @@ use super::{
     BuilderState, Command, KeyCheck, KeyOperation, KeyStatus, KeyValue, Output, PendingValue,
     Timestamp,
 };
-use crate::keyvalue::{AsyncKeyValue, SetCommand, Value};
+use crate::keyvalue::{AsyncKeyValue, Expiration, SetCommand, Value};
 use crate::Error;
 
 /// Builder for a [`Command::Set`] key-value operation.
@@ -18,7 +17,7 @@ pub struct Builder<'a, KeyValue, V> {
     namespace: Option<String>,
     key: String,
     value: PendingValue<'a, V>,
-    expiration: Option<Timestamp>,
+    expiration: Option<Expiration>,
     keep_existing_expiration: bool,
     check: Option<KeyCheck>,
 }
@@ -45,17 +44,23 @@ where
         }
     }
 
-    /// Set this key to expire after `duration` from now.
+    /// Set this key to expire after `duration` from now. The expiration is
+    /// computed using the clock of the database executing the operation.
     pub fn expire_in(mut self, duration: Duration) -> Self {
-        // TODO consider using checked_add here and making it return an error.
-        self.expiration = Some(Timestamp::from(SystemTime::now().add(duration)));
+        self.expiration = Some(Expiration::After(duration));
         self
     }
 
     /// Set this key to expire at the provided `time`.
     pub fn expire_at(mut self, time: SystemTime) -> Self {
         // TODO consider using checked_add here and making it return an error.
-        self.expiration = Some(Timestamp::from(time));
+        self.expiration = Some(Expiration::Absolute(Timestamp::from(time)));
+        self
+    }
+
+    /// Never expire this key, even if its namespace has a default expiration.
+    pub const fn never_expire(mut self) -> Self {
+        self.expiration = Some(Expiration::Absolute(Timestamp::MAX));
         self
     }
 
@@ -105,7 +110,13 @@ where
         match result {
             Output::Value(value) => Ok(value),
             Output::Status(KeyStatus::NotChanged) => Ok(None),
-            Output::Status(_) => unreachable!("Unexpected output from Set"),
+            Output::Status(_)
+            | Output::Bytes(_)
+            | Output::ValueWithMetadata(_)
+            | Output::Bounded(_)
+            | Output::Changed(_) => {
+                unreachable!("Unexpected output from Set")
+            }
         }
     }
 
@@ -161,7 +172,7 @@ struct Options<'a, KeyValue, V> {
     namespace: Option<String>,
     key: String,
     value: PendingValue<'a, V>,
-    expiration: Option<Timestamp>,
+    expiration: Option<Expiration>,
     keep_existing_expiration: bool,
     check: Option<KeyCheck>,
 }
@@ -198,17 +209,23 @@ where
         }
     }
 
-    /// Set this key to expire after `duration` from now.
+    /// Set this key to expire after `duration` from now. The expiration is
+    /// computed using the clock of the database executing the operation.
     pub fn expire_in(mut self, duration: Duration) -> Self {
-        // TODO consider using checked_add here and making it return an error.
-        self.options().expiration = Some(Timestamp::from(SystemTime::now().add(duration)));
+        self.options().expiration = Some(Expiration::After(duration));
         self
     }
 
     /// Set this key to expire at the provided `time`.
     pub fn expire_at(mut self, time: SystemTime) -> Self {
         // TODO consider using checked_add here and making it return an error.
-        self.options().expiration = Some(Timestamp::from(time));
+        self.options().expiration = Some(Expiration::Absolute(Timestamp::from(time)));
+        self
+    }
+
+    /// Never expire this key, even if its namespace has a default expiration.
+    pub fn never_expire(mut self) -> Self {
+        self.options().expiration = Some(Expiration::Absolute(Timestamp::MAX));
         self
     }
 
@@ -261,7 +278,13 @@ where
             match result {
                 Output::Value(value) => Ok(value),
                 Output::Status(KeyStatus::NotChanged) => Ok(None),
-                Output::Status(_) => unreachable!("Unexpected output from Set"),
+                Output::Status(_)
+                | Output::Bytes(_)
+                | Output::ValueWithMetadata(_)
+                | Output::Bounded(_)
+                | Output::Changed(_) => {
+                    unreachable!("Unexpected output from Set")
+                }
             }
         } else {
             panic!("Using future after it's been executed")
@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+use std::fmt::{Display, Write};
+use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
@@ -33,6 +35,237 @@ impl Timestamp {
     pub fn now() -> Self {
         Self::from(SystemTime::now())
     }
+
+    /// Formats this timestamp as an [RFC 3339][rfc3339] date and time in UTC,
+    /// such as `2023-04-01T12:30:00.5Z`. Fractional seconds are only included
+    /// when `nanos` is non-zero, and trailing zeroes are omitted.
+    ///
+    /// RFC 3339 only allows four-digit years. Timestamps after the year 9999
+    /// are formatted with as many year digits as needed, which
+    /// [`Timestamp::from_rfc3339()`] also accepts.
+    ///
+    /// [rfc3339]: https://www.rfc-editor.org/rfc/rfc3339
+    #[must_use]
+    pub fn to_rfc3339(&self) -> String {
+        self.to_string()
+    }
+
+    /// Parses an [RFC 3339][rfc3339] date and time, such as
+    /// `2023-04-01T12:30:00.5Z` or `2023-04-01T14:30:00+02:00`. The UTC offset
+    /// is applied, so the returned timestamp is always relative to
+    /// [`UNIX_EPOCH`].
+    ///
+    /// [rfc3339]: https://www.rfc-editor.org/rfc/rfc3339
+    pub fn from_rfc3339(value: &str) -> Result<Self, ParseTimestampError> {
+        let mut parser = Rfc3339Parser(value.as_bytes());
+        let year = parser.year()?;
+        parser.expect(b'-')?;
+        let month = parser.number(2, 1..=12)?;
+        parser.expect(b'-')?;
+        let day = parser.number(2, 1..=days_in_month(year, month))?;
+        parser.expect_any(b"Tt ")?;
+        let hour = parser.number(2, 0..=23)?;
+        parser.expect(b':')?;
+        let minute = parser.number(2, 0..=59)?;
+        parser.expect(b':')?;
+        let second = parser.number(2, 0..=59)?;
+        let nanos = parser.fraction()?;
+        let offset = parser.offset()?;
+        if !parser.0.is_empty() {
+            return Err(ParseTimestampError::InvalidFormat);
+        }
+
+        let seconds = days_from_civil(year, month, day) * 86_400
+            + i128::from(hour * 3_600 + minute * 60 + second)
+            - offset;
+        Ok(Self {
+            seconds: u64::try_from(seconds).map_err(|_| ParseTimestampError::OutOfRange)?,
+            nanos,
+        })
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = civil_from_days(self.seconds / 86_400);
+        let seconds_of_day = self.seconds % 86_400;
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+            seconds_of_day / 3_600,
+            seconds_of_day / 60 % 60,
+            seconds_of_day % 60
+        )?;
+        if self.nanos > 0 {
+            let nanos = format!("{:09}", self.nanos);
+            f.write_char('.')?;
+            f.write_str(nanos.trim_end_matches('0'))?;
+        }
+        f.write_char('Z')
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = ParseTimestampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_rfc3339(s)
+    }
+}
+
+/// An error parsing a [`Timestamp`] from an RFC 3339 string.
+#[derive(thiserror::Error, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ParseTimestampError {
+    /// The value is not a valid RFC 3339 date and time.
+    #[error("invalid rfc 3339 date and time")]
+    InvalidFormat,
+    /// The value is before [`UNIX_EPOCH`] or too far in the future to be
+    /// represented by a [`Timestamp`].
+    #[error("date and time is out of range")]
+    OutOfRange,
+}
+
+struct Rfc3339Parser<'a>(&'a [u8]);
+
+impl<'a> Rfc3339Parser<'a> {
+    fn digits(&mut self) -> &'a [u8] {
+        let length = self
+            .0
+            .iter()
+            .position(|b| !b.is_ascii_digit())
+            .unwrap_or(self.0.len());
+        let (digits, remaining) = self.0.split_at(length);
+        self.0 = remaining;
+        digits
+    }
+
+    fn year(&mut self) -> Result<i128, ParseTimestampError> {
+        let digits = self.digits();
+        // Years are at least four digits. Longer years are accepted to allow
+        // all formatted timestamps to be parsed, but are limited to avoid
+        // overflowing during the conversion.
+        if !(4..=20).contains(&digits.len()) {
+            return Err(ParseTimestampError::InvalidFormat);
+        }
+        Ok(digits
+            .iter()
+            .fold(0, |year, digit| year * 10 + i128::from(digit - b'0')))
+    }
+
+    fn number(
+        &mut self,
+        length: usize,
+        range: std::ops::RangeInclusive<u32>,
+    ) -> Result<u32, ParseTimestampError> {
+        if self.0.len() < length || !self.0[..length].iter().all(u8::is_ascii_digit) {
+            return Err(ParseTimestampError::InvalidFormat);
+        }
+        let (digits, remaining) = self.0.split_at(length);
+        self.0 = remaining;
+        let value = digits
+            .iter()
+            .fold(0, |value, digit| value * 10 + u32::from(digit - b'0'));
+        if range.contains(&value) {
+            Ok(value)
+        } else {
+            Err(ParseTimestampError::InvalidFormat)
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), ParseTimestampError> {
+        self.expect_any(&[expected])
+    }
+
+    fn expect_any(&mut self, expected: &[u8]) -> Result<(), ParseTimestampError> {
+        match self.0.split_first() {
+            Some((byte, remaining)) if expected.contains(byte) => {
+                self.0 = remaining;
+                Ok(())
+            }
+            _ => Err(ParseTimestampError::InvalidFormat),
+        }
+    }
+
+    fn fraction(&mut self) -> Result<u32, ParseTimestampError> {
+        if self.expect(b'.').is_err() {
+            return Ok(0);
+        }
+
+        let digits = self.digits();
+        if digits.is_empty() {
+            return Err(ParseTimestampError::InvalidFormat);
+        }
+        // Digits beyond nanosecond precision are truncated.
+        let mut nanos = 0;
+        for index in 0..9 {
+            nanos = nanos * 10 + digits.get(index).map_or(0, |digit| u32::from(digit - b'0'));
+        }
+        Ok(nanos)
+    }
+
+    /// Returns the UTC offset in seconds.
+    fn offset(&mut self) -> Result<i128, ParseTimestampError> {
+        if self.expect_any(b"Zz").is_ok() {
+            return Ok(0);
+        }
+
+        let negative = match self.0.first() {
+            Some(b'+') => false,
+            Some(b'-') => true,
+            _ => return Err(ParseTimestampError::InvalidFormat),
+        };
+        self.0 = &self.0[1..];
+        let hours = self.number(2, 0..=23)?;
+        self.expect(b':')?;
+        let minutes = self.number(2, 0..=59)?;
+        let offset = i128::from(hours * 3_600 + minutes * 60);
+        Ok(if negative { -offset } else { offset })
+    }
+}
+
+const fn is_leap_year(year: i128) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+const fn days_in_month(year: i128, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// The conversions between days since the epoch and calendar dates use Howard
+// Hinnant's algorithms for the proleptic Gregorian calendar:
+// <https://howardhinnant.github.io/date_algorithms.html>
+
+fn days_from_civil(year: i128, month: u32, day: u32) -> i128 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = i128::from((month + 9) % 12);
+    let day_of_year = (153 * month_index + 2) / 5 + i128::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
 }
 
 impl From<SystemTime> for Timestamp {
@@ -114,6 +347,58 @@ impl KeyEncoding<Self> for Timestamp {
     }
 }
 
+#[test]
+fn rfc3339_tests() {
+    assert_eq!(Timestamp::MIN.to_rfc3339(), "1970-01-01T00:00:00Z");
+    // 2000-02-29 is a leap day, which verifies the calendar conversion.
+    let timestamp = Timestamp {
+        seconds: 951_827_696,
+        nanos: 120_000_000,
+    };
+    assert_eq!(timestamp.to_rfc3339(), "2000-02-29T12:34:56.12Z");
+    assert_eq!(timestamp.to_string(), timestamp.to_rfc3339());
+    assert_eq!(
+        Timestamp::from_rfc3339("2000-02-29T12:34:56.12Z"),
+        Ok(timestamp)
+    );
+    // Offsets are converted to UTC.
+    assert_eq!(
+        Timestamp::from_rfc3339("2000-02-29T14:04:56.120+01:30"),
+        Ok(timestamp)
+    );
+    assert_eq!(
+        Timestamp::from_rfc3339("2000-02-29t07:34:56.12-05:00"),
+        Ok(timestamp)
+    );
+
+    let now = Timestamp::now();
+    assert_eq!(now.to_rfc3339().parse::<Timestamp>(), Ok(now));
+    assert_eq!(
+        Timestamp::from_rfc3339(&Timestamp::MAX.to_rfc3339()),
+        Ok(Timestamp::MAX)
+    );
+
+    assert_eq!(
+        Timestamp::from_rfc3339("1969-12-31T23:59:59Z"),
+        Err(ParseTimestampError::OutOfRange)
+    );
+    for invalid in [
+        "",
+        "2001-02-29T00:00:00Z",
+        "2000-13-01T00:00:00Z",
+        "2000-01-01T24:00:00Z",
+        "2000-01-01T00:00:00",
+        "2000-01-01T00:00:00.Z",
+        "2000-01-01 00:00:00Z trailing",
+    ] {
+        assert_eq!(
+            Timestamp::from_rfc3339(invalid),
+            Err(ParseTimestampError::InvalidFormat),
+            "{invalid}"
+        );
+    }
+}
+
 #[test]
 fn key_test() {
     let original = Timestamp::now();
@@ -47,6 +47,7 @@ pub mod pubsub;
 
 use std::fmt::Display;
 use std::string::FromUtf8Error;
+use std::time::Duration;
 
 use schema::{view, CollectionName, SchemaName, ViewName};
 use serde::{Deserialize, Serialize};
@@ -57,9 +58,10 @@ pub use {
 
 use crate::api::ApiName;
 use crate::connection::HasSchema;
-use crate::document::{DocumentId, Header, InvalidHexadecimal};
+use crate::document::{DocumentId, Header, InvalidHexadecimal, Revision};
 use crate::key::time::TimeError;
 use crate::key::NextValueError;
+use crate::keyvalue::DecimalError;
 use crate::schema::InsertError;
 
 /// an enumeration of errors that this crate can produce
@@ -81,6 +83,26 @@ pub enum Error {
         stored_schema: SchemaName,
     },
 
+    /// The database named `database_name` was last opened with a newer
+    /// version (`stored_version`) of `schema` than the version being used
+    /// (`schema_version`).
+    #[error(
+        "database '{database_name}' was last opened with version {stored_version} of schema '{schema}', which is newer than version {schema_version}"
+    )]
+    SchemaVersionMismatch {
+        /// The name of the database being accessed.
+        database_name: String,
+
+        /// The schema of the database.
+        schema: SchemaName,
+
+        /// The version of the schema being used.
+        schema_version: u64,
+
+        /// The version of the schema that last opened the database.
+        stored_version: u64,
+    },
+
     /// The [`SchemaName`] returned has already been registered.
     #[error("schema '{0}' was already registered")]
     SchemaAlreadyRegistered(SchemaName),
@@ -107,6 +129,12 @@ pub enum Error {
     #[error("view was not found")]
     ViewNotFound,
 
+    /// The view must use
+    /// [`ViewUpdatePolicy::Unique`](schema::view::ViewUpdatePolicy::Unique)
+    /// for the requested operation.
+    #[error("view '{0}' is not unique")]
+    ViewNotUnique(ViewName),
+
     /// The collection was not found.
     #[error("collection was not found")]
     CollectionNotFound,
@@ -115,6 +143,30 @@ pub enum Error {
     #[error("api '{0}' was not found")]
     ApiNotFound(ApiName),
 
+    /// The response to a request was larger than the server's configured limit.
+    /// Requesting the data in smaller pages, such as by using a limit on a
+    /// query, will avoid this error.
+    #[error("the response was larger than the limit of {limit} bytes")]
+    ResponseTooLarge {
+        /// The maximum response size, in bytes.
+        limit: usize,
+    },
+
+    /// The server is too busy to execute the request. The request was not
+    /// executed and can be retried. If provided, `retry_after` is how long the
+    /// server suggests waiting before retrying.
+    #[error("the server is too busy to execute the request")]
+    Busy {
+        /// The suggested delay before retrying the request.
+        retry_after: Option<Duration>,
+    },
+
+    /// The request's deadline passed before the server finished executing it.
+    /// The server checks the deadline before starting expensive work, such as
+    /// scanning a view, and returns this error instead of continuing.
+    #[error("the request's deadline was exceeded")]
+    DeadlineExceeded,
+
     /// The database name already exists.
     #[error("a database with name '{0}' already exists")]
     DatabaseNameAlreadyTaken(String),
@@ -131,6 +183,36 @@ pub enum Error {
     #[error("the requested document id {1} from collection {0} was not found")]
     DocumentNotFound(CollectionName, Box<DocumentId>),
 
+    /// A document's contents were larger than the storage's configured maximum
+    /// document size.
+    #[error("the document is {size} bytes, which is larger than the limit of {limit} bytes")]
+    DocumentTooLarge {
+        /// The size of the document's contents, in bytes.
+        size: usize,
+        /// The maximum document size, in bytes.
+        limit: usize,
+    },
+
+    /// A key-value operation would have created a new namespace, but the
+    /// key-value store already contains the configured maximum number of
+    /// namespaces.
+    #[error("the key-value store has reached the limit of {limit} namespaces")]
+    TooManyKeyValueNamespaces {
+        /// The maximum number of namespaces.
+        limit: usize,
+    },
+
+    /// A key-value namespace couldn't be renamed because the destination
+    /// namespace already contains `key`.
+    #[error("the key-value namespace `{namespace}` already contains the key `{key}`")]
+    KeyValueNamespaceConflict {
+        /// The namespace being renamed to. An empty namespace is the default
+        /// namespace.
+        namespace: String,
+        /// The conflicting key.
+        key: String,
+    },
+
     /// A value provided as a [`DocumentId`] exceeded [`DocumentId::MAX_LENGTH`].
     #[error(
         "an value was provided for a `DocumentId` that was larger than `DocumentId::MAX_LENGTH`"
@@ -140,8 +222,13 @@ pub enum Error {
     /// When updating a document, if a situation is detected where the contents
     /// have changed on the server since the `Revision` provided, a Conflict
     /// error will be returned.
+    ///
+    /// The [`Header`] is the stored document's current id and revision. The
+    /// [`Revision`] is the revision the operation expected
+    /// the document to have, or `None` if the operation expected the document
+    /// to not exist, such as when inserting with an id that is already in use.
     #[error("a conflict was detected while updating document {1} from collection {0}")]
-    DocumentConflict(CollectionName, Box<Header>),
+    DocumentConflict(CollectionName, Box<Header>, Option<Revision>),
 
     /// When saving a document in a collection with unique views, a document
     /// emits a key that is already emitted by an existing ocument, this error
@@ -198,6 +285,20 @@ pub enum Error {
     #[error("time error: {0}")]
     Time(#[from] TimeError),
 
+    /// An error while operating with a [`Decimal`](crate::keyvalue::Decimal).
+    #[error("decimal error: {0}")]
+    Decimal(#[from] DecimalError),
+
+    /// A transaction was rejected by a validator registered for
+    /// `collection`.
+    #[error("transaction rejected by validator for {collection}: {message}")]
+    Validation {
+        /// The collection whose validator rejected the transaction.
+        collection: CollectionName,
+        /// The reason the transaction was rejected.
+        message: String,
+    },
+
     /// An error from another crate.
     #[error("error from {origin}: {error}")]
     Other {
@@ -217,6 +318,14 @@ impl Error {
         }
     }
 
+    /// Returns an instance of [`Self::Validation`] with the given parameters.
+    pub fn validation(collection: CollectionName, message: impl Display) -> Self {
+        Self::Validation {
+            collection,
+            message: message.to_string(),
+        }
+    }
+
     /// Returns true if this error is a [`Error::UniqueKeyViolation`] from
     /// `View`.
     pub fn is_unique_key_error<View: schema::View, C: HasSchema>(&self, connection: &C) -> bool {
@@ -234,7 +343,7 @@ impl Error {
     #[must_use]
     pub fn conflicting_document<Collection: schema::Collection>(&self) -> Option<Header> {
         match self {
-            Self::DocumentConflict(collection, header)
+            Self::DocumentConflict(collection, header, _)
                 if collection == &Collection::collection_name() =>
             {
                 Some(header.as_ref().clone())
@@ -11,7 +11,13 @@
 //!
 //! When querying previously executed transactions using
 //! [`Connection::list_executed_transactions()`](crate::connection::Connection::list_executed_transactions),
-//! the result set will be limited to [`LIST_TRANSACTIONS_MAX_RESULTS`] entries.
+//! the result set will be limited to [`LIST_TRANSACTIONS_MAX_RESULTS`] entries
+//! by default. Local storage allows raising this limit through its
+//! configuration.
+//!
+//! The results of transactions applied with an
+//! [idempotency key](crate::transaction::Transaction::with_idempotency_key)
+//! are remembered for [`IDEMPOTENCY_KEY_EXPIRATION`].
 //!
 //! # Document Limits
 //!
@@ -48,7 +54,13 @@
 //!
 //! [nebari]: https://github.com/khonsulabs/nebari
 
-/// The maximum number of results allowed to be returned from `list_executed_transactions`.
+use std::time::Duration;
+
+/// The default maximum number of results allowed to be returned from
+/// `list_executed_transactions`.
 pub const LIST_TRANSACTIONS_MAX_RESULTS: u32 = 1000;
 /// If no `result_limit` is specified, this value is the limit used by default.
 pub const LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT: u32 = 100;
+/// The amount of time the results of a transaction applied with an idempotency
+/// key are remembered: 24 hours.
+pub const IDEMPOTENCY_KEY_EXPIRATION: Duration = Duration::from_secs(60 * 60 * 24);
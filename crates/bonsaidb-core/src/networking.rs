@@ -6,13 +6,15 @@ use crate::connection::{
     AccessPolicy, Database, IdentityReference, Range, SerializedQueryKey, Session, SessionId, Sort,
 };
 use crate::document::{DocumentId, Header, OwnedDocument};
-use crate::keyvalue::{KeyOperation, Output};
+use crate::keyvalue::{
+    ExpiringKey, KeyOperation, KeyWatchEvent, Output, Pipeline, Timestamp, Value,
+};
 use crate::schema::view::map::{self, MappedSerializedDocuments};
 use crate::schema::{CollectionName, NamedReference, Qualified, SchemaSummary, ViewName};
-use crate::transaction::{Executed, OperationResult, Transaction};
+use crate::transaction::{Executed, OperationResult, Transaction, ViewChanges};
 
 /// The current protocol version.
-pub const CURRENT_PROTOCOL_VERSION: &str = "bonsai/pre/0";
+pub const CURRENT_PROTOCOL_VERSION: &str = "bonsai/pre/1";
 
 /// A payload with an associated id.
 #[derive(Clone, Deserialize, Serialize, Debug)]
@@ -25,6 +27,10 @@ pub struct Payload {
     pub name: ApiName,
     /// The payload
     pub value: Result<Bytes, crate::Error>,
+    /// The time after which the server should stop working on this request
+    /// and respond with [`Error::DeadlineExceeded`](crate::Error::DeadlineExceeded).
+    /// The deadline is compared against the server's clock.
+    pub deadline: Option<Timestamp>,
 }
 
 /// Creates a database.
@@ -247,6 +253,65 @@ impl Api for Get {
     }
 }
 
+/// Checks whether a document exists without retrieving its contents.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct Exists {
+    /// The name of the database.
+    pub database: String,
+    /// The collection of the document.
+    pub collection: CollectionName,
+    /// The id of the document.
+    pub id: DocumentId,
+}
+
+impl Api for Exists {
+    type Error = crate::Error;
+    type Response = bool;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "Exists")
+    }
+}
+
+/// Retrieves the retained contents of a soft-deleted document.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct GetTombstone {
+    /// The name of the database.
+    pub database: String,
+    /// The collection of the document.
+    pub collection: CollectionName,
+    /// The id of the document.
+    pub id: DocumentId,
+}
+
+impl Api for GetTombstone {
+    type Error = crate::Error;
+    type Response = Option<OwnedDocument>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "GetTombstone")
+    }
+}
+
+/// Permanently removes the retained contents of documents soft-deleted before
+/// a given time.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct PurgeTombstones {
+    /// The name of the database.
+    pub database: String,
+    /// Tombstones created before this time are purged.
+    pub before: Timestamp,
+}
+
+impl Api for PurgeTombstones {
+    type Error = crate::Error;
+    type Response = u64;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "PurgeTombstones")
+    }
+}
+
 /// Retrieve multiple documents.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct GetMultiple {
@@ -324,6 +389,24 @@ impl Api for Count {
     }
 }
 
+/// Deletes every document in a collection.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct DeleteAll {
+    /// The name of the database.
+    pub database: String,
+    /// The collection to delete the documents from.
+    pub collection: CollectionName,
+}
+
+impl Api for DeleteAll {
+    type Error = crate::Error;
+    type Response = u64;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "DeleteAll")
+    }
+}
+
 /// Queries a view.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Query {
@@ -363,6 +446,60 @@ impl Api for QueryWithDocs {
     }
 }
 
+/// Queries a view for the keys and sources of its entries, without their
+/// values.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct QueryKeys(pub Query);
+
+impl Api for QueryKeys {
+    type Error = crate::Error;
+    type Response = Vec<map::MappedSerializedKey>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "QueryKeys")
+    }
+}
+
+/// Queries a view, sending the results to the client in chunks of
+/// [`QueryChunk`]s. The response is the number of chunks sent.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct StreamQuery {
+    /// A unique id chosen by the client, used to identify the chunks of this
+    /// query.
+    pub stream_id: u64,
+    /// The maximum number of mappings to include in each chunk.
+    pub chunk_size: u32,
+    /// The query to execute.
+    pub query: Query,
+}
+
+impl Api for StreamQuery {
+    type Error = crate::Error;
+    type Response = u64;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "StreamQuery")
+    }
+}
+
+/// A chunk of results from a [`StreamQuery`].
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct QueryChunk {
+    /// The id of the [`StreamQuery`] these results belong to.
+    pub stream_id: u64,
+    /// The mappings in this chunk.
+    pub mappings: Vec<map::Serialized>,
+}
+
+impl Api for QueryChunk {
+    type Error = crate::Error;
+    type Response = Self;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "QueryChunk")
+    }
+}
+
 /// Reduces a view.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct Reduce {
@@ -398,6 +535,55 @@ impl Api for ReduceGrouped {
     }
 }
 
+/// Counts the entries of a view, grouping the counts by key.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct CountGrouped(pub Reduce);
+
+impl Api for CountGrouped {
+    type Error = crate::Error;
+    type Response = Vec<map::MappedSerializedCount>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "CountGrouped")
+    }
+}
+
+/// Returns how up-to-date a view's index is.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct GetViewIndexState {
+    /// The name of the database.
+    pub database: String,
+    /// The name of the view.
+    pub view: ViewName,
+}
+
+impl Api for GetViewIndexState {
+    type Error = crate::Error;
+    type Response = crate::connection::ViewIndexState;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "GetViewIndexState")
+    }
+}
+
+/// Discards and regenerates a view's index.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RebuildView {
+    /// The name of the database.
+    pub database: String,
+    /// The name of the view.
+    pub view: ViewName,
+}
+
+impl Api for RebuildView {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "RebuildView")
+    }
+}
+
 /// Deletes the associated documents resulting from the view query.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct DeleteDocs {
@@ -438,6 +624,24 @@ impl Api for ApplyTransaction {
     }
 }
 
+/// Applies a transaction, returning the rows it changed in each view.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ApplyTransactionWithViews {
+    /// The name of the database.
+    pub database: String,
+    /// The transaction to apply.
+    pub transaction: Transaction,
+}
+
+impl Api for ApplyTransactionWithViews {
+    type Error = crate::Error;
+    type Response = (Vec<OperationResult>, ViewChanges);
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ApplyTransactionWithViews")
+    }
+}
+
 /// Lists executed transactions.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct ListExecutedTransactions {
@@ -626,6 +830,189 @@ impl Api for ExecuteKeyOperation {
     }
 }
 
+/// Executes a pipeline of key-value store operations atomically.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ExecuteKeyPipeline {
+    /// The name of the database.
+    pub database: String,
+    /// The pipeline to execute.
+    pub pipeline: Pipeline,
+}
+
+impl Api for ExecuteKeyPipeline {
+    type Error = crate::Error;
+    type Response = Vec<Option<Output>>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ExecuteKeyPipeline")
+    }
+}
+
+/// Lists the key-value entries that expire within a range of time.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct KeysExpiringBetween {
+    /// The name of the database.
+    pub database: String,
+    /// The start of the range, inclusive.
+    pub start: Timestamp,
+    /// The end of the range, exclusive.
+    pub end: Timestamp,
+}
+
+impl Api for KeysExpiringBetween {
+    type Error = crate::Error;
+    type Response = Vec<ExpiringKey>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "KeysExpiringBetween")
+    }
+}
+
+/// Looks up the expirations of multiple key-value entries.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct GetKeyExpirations {
+    /// The name of the database.
+    pub database: String,
+    /// The namespace of the keys.
+    pub namespace: Option<String>,
+    /// The keys to look up.
+    pub keys: Vec<String>,
+}
+
+impl Api for GetKeyExpirations {
+    type Error = crate::Error;
+    type Response = Vec<(String, Option<Timestamp>)>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "GetKeyExpirations")
+    }
+}
+
+/// Returns a batch of the key-value entries stored in a namespace.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ScanKeyNamespace {
+    /// The name of the database.
+    pub database: String,
+    /// The namespace to scan.
+    pub namespace: Option<String>,
+    /// Only keys that sort after this key are returned.
+    pub after: Option<String>,
+    /// The maximum number of entries to return.
+    pub limit: usize,
+}
+
+impl Api for ScanKeyNamespace {
+    type Error = crate::Error;
+    type Response = Vec<(String, Value)>;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "ScanKeyNamespace")
+    }
+}
+
+/// Moves every key-value entry in one namespace to another namespace.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RenameKeyNamespace {
+    /// The name of the database.
+    pub database: String,
+    /// The namespace to move the keys from.
+    pub from: Option<String>,
+    /// The namespace to move the keys to.
+    pub to: Option<String>,
+}
+
+impl Api for RenameKeyNamespace {
+    type Error = crate::Error;
+    type Response = u64;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "RenameKeyNamespace")
+    }
+}
+
+/// Restores the key-value entries of a namespace as they were at a past
+/// instant.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct RestoreKeyNamespace {
+    /// The name of the database.
+    pub database: String,
+    /// The namespace to restore.
+    pub namespace: Option<String>,
+    /// The instant to restore the namespace to.
+    pub timestamp: Timestamp,
+    /// The namespace to write the restored keys to.
+    pub target: Option<String>,
+}
+
+impl Api for RestoreKeyNamespace {
+    type Error = crate::Error;
+    type Response = u64;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "RestoreKeyNamespace")
+    }
+}
+
+/// Watches the key-value store for persisted changes, sending each change to
+/// the client as a [`KeyChanged`].
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct WatchKeys {
+    /// The name of the database.
+    pub database: String,
+    /// A unique id chosen by the client, used to identify the events of this
+    /// watcher.
+    pub watch_id: u64,
+    /// The namespace to watch.
+    pub namespace: Option<String>,
+    /// If present, only keys starting with this prefix are watched.
+    pub prefix: Option<String>,
+}
+
+impl Api for WatchKeys {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "WatchKeys")
+    }
+}
+
+/// An event for a [`WatchKeys`] watcher.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct KeyChanged {
+    /// The id of the [`WatchKeys`] watcher this event belongs to.
+    pub watch_id: u64,
+    /// The event.
+    pub event: KeyWatchEvent,
+}
+
+impl Api for KeyChanged {
+    type Error = crate::Error;
+    type Response = Self;
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "KeyChanged")
+    }
+}
+
+/// Stops a [`WatchKeys`] watcher.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct UnwatchKeys {
+    /// The name of the database.
+    pub database: String,
+    /// The id of the [`WatchKeys`] watcher.
+    pub watch_id: u64,
+}
+
+impl Api for UnwatchKeys {
+    type Error = crate::Error;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::new("bonsaidb", "UnwatchKeys")
+    }
+}
+
 /// Compacts the collection.
 #[derive(Clone, Deserialize, Serialize, Debug)]
 pub struct CompactCollection {
@@ -209,6 +209,10 @@ pub enum DocumentAction {
     /// See [`document_resource_name()`] for the format of document resource
     /// names.
     Delete,
+    /// Allows purging the tombstones of soft-deleted documents. See
+    /// [`collection_resource_name()`] for the format of collection resource
+    /// names.
+    PurgeTombstones,
 }
 
 /// Actions that operate on a view.
@@ -226,6 +230,10 @@ pub enum ViewAction {
     /// [`Connection::delete_docs()`](crate::connection::LowLevelConnection::delete_docs).
     /// See [`view_resource_name`] for the format of view resource names.
     DeleteDocs,
+    /// Allows rebuilding a view's index with
+    /// [`Connection::rebuild_view()`](crate::connection::Connection::rebuild_view).
+    /// See [`view_resource_name`] for the format of view resource names.
+    Rebuild,
 }
 
 /// Actions that operate on transactions.
@@ -241,6 +249,10 @@ pub enum TransactionAction {
     /// This action is checked against the database's resource name. See
     /// [`database_resource_name()`] for the format of database resource names.
     GetLastId,
+    /// Allows pruning executed transactions from the transaction log. This
+    /// action is checked against the database's resource name. See
+    /// [`database_resource_name()`] for the format of database resource names.
+    Prune,
 }
 
 /// Actions that operate on the `PubSub` system.
@@ -275,6 +287,9 @@ pub enum KeyValueAction {
     /// [`KeyValue::execute_key_operation()`](crate::keyvalue::KeyValue::execute_key_operation).
     /// See [`keyvalue_key_resource_name()`] for the format of key resource names.
     ExecuteOperation,
+    /// Allows removing every expired key from a database's key-value store at
+    /// once. The resource name is [`kv_resource_name()`].
+    SweepExpired,
 }
 
 /// Actions that use encryption keys.
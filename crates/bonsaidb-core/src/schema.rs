@@ -8,8 +8,8 @@ pub mod view;
 pub use bonsaidb_macros::{Collection, Schema, View, ViewSchema};
 
 pub use self::collection::{
-    AsyncEntry, AsyncList, Collection, DefaultSerialization, InsertError, List, Nameable,
-    NamedCollection, NamedReference, SerializedCollection,
+    AsyncEntry, AsyncList, Collection, DefaultSerialization, IdStrategy, InsertError, List,
+    Nameable, NamedCollection, NamedReference, SerializedCollection,
 };
 pub use self::names::{
     Authority, CollectionName, InvalidNameError, Name, Qualified, QualifiedName, SchemaName,
@@ -69,10 +69,44 @@ use crate::Error;
 /// #    pub score: f32,
 /// # }
 /// ```
+///
+/// When a schema changes in a way that older code can't read the data written
+/// by newer code, increment its `version`. Databases record the version of the
+/// schema that last opened them, and refuse to be opened with an older version:
+///
+/// ```rust
+/// use bonsaidb_core::schema::Schema;
+///
+/// #[derive(Schema)]
+/// #[schema(name = "MySchema", version = 2, collections = [MyCollection])]
+/// # #[schema(core = bonsaidb_core)]
+/// pub struct MySchema;
+///
+/// # use serde::{Deserialize, Serialize};
+/// # use bonsaidb_core::schema::Collection;
+/// # #[derive(Serialize, Deserialize, Default, Collection)]
+/// # #[collection(name = "MyCollection")]
+/// # #[collection(core = bonsaidb_core)]
+/// # pub struct MyCollection {
+/// #    pub rank: u32,
+/// #    pub score: f32,
+/// # }
+/// ```
 pub trait Schema: Send + Sync + 'static {
     /// Returns the unique [`SchemaName`] for this schema.
     fn schema_name() -> SchemaName;
 
+    /// The version of this schema. Opening a database that was last opened
+    /// with a newer version of this schema fails with
+    /// [`Error::SchemaVersionMismatch`]. Opening a database that was last
+    /// opened with an older version records the new version, after running
+    /// any migration registered for the schema.
+    ///
+    /// The default version is 0.
+    fn schema_version() -> u64 {
+        0
+    }
+
     /// Defines the `Collection`s into `schema`.
     fn define_collections(schema: &mut Schematic) -> Result<(), Error>;
 
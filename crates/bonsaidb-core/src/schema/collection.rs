@@ -1,5 +1,7 @@
 use std::borrow::{Borrow, Cow};
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
 use std::marker::PhantomData;
 use std::task::Poll;
 
@@ -11,13 +13,14 @@ use serde::{Deserialize, Serialize};
 use transmog::{Format, OwnedDeserializer};
 use transmog_pot::Pot;
 
-use crate::connection::{self, AsyncConnection, Connection, RangeRef};
+use crate::connection::{self, AsyncConnection, Connection, HasSchema, RangeRef};
 use crate::document::{
     BorrowedDocument, CollectionDocument, CollectionHeader, Document, DocumentId, Header, KeyId,
     OwnedDocument, OwnedDocuments, Revision,
 };
 use crate::key::{IntoPrefixRange, Key, KeyEncoding};
-use crate::schema::{CollectionName, Schematic};
+use crate::schema::view::ViewUpdatePolicy;
+use crate::schema::{CollectionName, Schematic, ViewName};
 use crate::transaction::{Operation, OperationResult, Transaction};
 use crate::Error;
 
@@ -252,6 +255,34 @@ pub trait Collection: Send + Sync {
     fn encryption_key() -> Option<KeyId> {
         None
     }
+
+    /// Returns the strategy used to assign ids to documents that are pushed
+    /// into this collection without an id. The default is
+    /// [`IdStrategy::Sequential`].
+    #[must_use]
+    fn id_strategy() -> IdStrategy {
+        IdStrategy::Sequential
+    }
+}
+
+/// A strategy for assigning ids to documents pushed into a [`Collection`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum IdStrategy {
+    /// Each new id is the value after the largest id in the collection, as
+    /// returned by [`Key::next_value()`]. If the collection is empty,
+    /// [`Key::first_value()`] is used.
+    #[default]
+    Sequential,
+    /// Each new id is a 64-bit value ordered by the time it was assigned. The
+    /// upper 48 bits contain the number of milliseconds since the Unix epoch
+    /// and the lower 16 bits distinguish ids assigned within the same
+    /// millisecond. Ids never decrease: if the largest id in the collection is
+    /// not less than the id for the current time, the next id is one more
+    /// than the largest id.
+    ///
+    /// This strategy requires the collection's primary key to be encoded the
+    /// same way as `u64`.
+    TimeOrdered,
 }
 
 /// A collection that knows how to serialize and deserialize documents to an associated type.
@@ -294,10 +325,18 @@ pub trait SerializedCollection: Collection {
     fn format() -> Self::Format;
 
     /// Deserialize `data` as `Self::Contents` using this collection's format.
+    ///
+    /// If an error occurs, the returned error includes this collection's name.
     fn deserialize(data: &[u8]) -> Result<Self::Contents, Error> {
-        Self::format()
-            .deserialize_owned(data)
-            .map_err(|err| crate::Error::other("serialization", err))
+        Self::format().deserialize_owned(data).map_err(|err| {
+            crate::Error::other(
+                "serialization",
+                format!(
+                    "failed to deserialize document for collection {}: {err}",
+                    Self::collection_name()
+                ),
+            )
+        })
     }
 
     /// Returns the deserialized contents of `doc`.
@@ -320,10 +359,18 @@ pub trait SerializedCollection: Collection {
     }
 
     /// Serialize `item` using this collection's format.
+    ///
+    /// If an error occurs, the returned error includes this collection's name.
     fn serialize(item: &Self::Contents) -> Result<Vec<u8>, Error> {
-        Self::format()
-            .serialize(item)
-            .map_err(|err| crate::Error::other("serialization", err))
+        Self::format().serialize(item).map_err(|err| {
+            crate::Error::other(
+                "serialization",
+                format!(
+                    "failed to serialize document for collection {}: {err}",
+                    Self::collection_name()
+                ),
+            )
+        })
     }
 
     /// Gets a [`CollectionDocument`] with `id` from `connection`.
@@ -453,6 +500,82 @@ pub trait SerializedCollection: Collection {
             .and_then(|docs| docs.collection_documents())
     }
 
+    /// Retrieves all documents matching `ids`, keyed by their id. Documents
+    /// that are not found are not included in the map, but no error will be
+    /// generated.
+    ///
+    /// The documents are retrieved using a single request, the same as
+    /// [`get_multiple()`](Self::get_multiple).
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: C) -> Result<(), Error> {
+    /// let docs = MyCollection::get_multiple_map(&[42, 43], &db)?;
+    /// if let Some(doc) = docs.get(&42) {
+    ///     println!("Retrieved #42 with deserialized contents: {:?}", doc.contents);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn get_multiple_map<'id, C, DocumentIds, PrimaryKey, I>(
+        ids: DocumentIds,
+        connection: &C,
+    ) -> Result<HashMap<Self::PrimaryKey, CollectionDocument<Self>>, Error>
+    where
+        C: Connection,
+        DocumentIds: IntoIterator<Item = &'id PrimaryKey, IntoIter = I> + Send + Sync,
+        I: Iterator<Item = &'id PrimaryKey> + Send + Sync,
+        PrimaryKey: KeyEncoding<Self::PrimaryKey> + 'id,
+        Self::PrimaryKey: Hash,
+        Self: Sized,
+    {
+        Self::get_multiple(ids, connection).map(|docs| {
+            docs.into_iter()
+                .map(|doc| (doc.header.id.clone(), doc))
+                .collect()
+        })
+    }
+
+    /// Retrieves all documents matching `ids`, keyed by their id. Documents
+    /// that are not found are not included in the map, but no error will be
+    /// generated.
+    ///
+    /// The documents are retrieved using a single request, the same as
+    /// [`get_multiple_async()`](Self::get_multiple_async).
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let docs = MyCollection::get_multiple_map_async(&[42, 43], &db).await?;
+    /// if let Some(doc) = docs.get(&42) {
+    ///     println!("Retrieved #42 with deserialized contents: {:?}", doc.contents);
+    /// }
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    async fn get_multiple_map_async<'id, C, DocumentIds, PrimaryKey, I>(
+        ids: DocumentIds,
+        connection: &C,
+    ) -> Result<HashMap<Self::PrimaryKey, CollectionDocument<Self>>, Error>
+    where
+        C: AsyncConnection,
+        DocumentIds: IntoIterator<Item = &'id PrimaryKey, IntoIter = I> + Send + Sync,
+        I: Iterator<Item = &'id PrimaryKey> + Send + Sync,
+        PrimaryKey: KeyEncoding<Self::PrimaryKey> + 'id,
+        Self::PrimaryKey: Hash,
+        Self: Sized,
+    {
+        Self::get_multiple_async(ids, connection).await.map(|docs| {
+            docs.into_iter()
+                .map(|doc| (doc.header.id.clone(), doc))
+                .collect()
+        })
+    }
+
     /// Retrieves all documents matching the range of `ids`.
     ///
     /// ```rust
@@ -733,6 +856,100 @@ pub trait SerializedCollection: Collection {
         Ok(CollectionDocument { header, contents })
     }
 
+    /// Pushes `contents` into the collection unless a document already emits
+    /// the same key in the unique view `V`. Returns the header of the newly
+    /// created document, or the header of the existing document that uses the
+    /// same key.
+    ///
+    /// The check is performed by the unique view's enforcement while the
+    /// insert is applied, so concurrent calls with the same key never create
+    /// more than one document.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::ViewNotUnique`]: `V` does not use
+    ///   [`ViewUpdatePolicy::Unique`](crate::schema::view::ViewUpdatePolicy::Unique).
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::Connection;
+    /// # fn test_fn<C: Connection>(db: C) -> Result<(), Error> {
+    /// let header = MyCollection::push_if_absent::<MyCollectionByName, _>(&MyCollection::default(), &db)?;
+    /// println!("Document {} uses this key", header.id);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn push_if_absent<V, Cn>(
+        contents: &Self::Contents,
+        connection: &Cn,
+    ) -> Result<CollectionHeader<Self::PrimaryKey>, Error>
+    where
+        V: crate::schema::View<Collection = Self> + 'static,
+        Cn: Connection,
+        Self: Sized + 'static,
+    {
+        let view_name = unique_view_name::<V, _>(connection)?;
+        match connection.collection::<Self>().push(contents) {
+            Ok(header) => Ok(header),
+            Err(Error::UniqueKeyViolation {
+                view,
+                existing_document,
+                ..
+            }) if view == view_name => CollectionHeader::try_from(*existing_document),
+            Err(other) => Err(other),
+        }
+    }
+
+    /// Pushes `contents` into the collection unless a document already emits
+    /// the same key in the unique view `V`. Returns the header of the newly
+    /// created document, or the header of the existing document that uses the
+    /// same key.
+    ///
+    /// The check is performed by the unique view's enforcement while the
+    /// insert is applied, so concurrent calls with the same key never create
+    /// more than one document.
+    ///
+    /// ## Errors
+    ///
+    /// * [`Error::ViewNotUnique`]: `V` does not use
+    ///   [`ViewUpdatePolicy::Unique`](crate::schema::view::ViewUpdatePolicy::Unique).
+    ///
+    /// ```rust
+    /// # bonsaidb_core::__doctest_prelude!();
+    /// # use bonsaidb_core::connection::AsyncConnection;
+    /// # fn test_fn<C: AsyncConnection>(db: C) -> Result<(), Error> {
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let header = MyCollection::push_if_absent_async::<MyCollectionByName, _>(
+    ///     &MyCollection::default(),
+    ///     &db,
+    /// )
+    /// .await?;
+    /// println!("Document {} uses this key", header.id);
+    /// # Ok(())
+    /// # })
+    /// # }
+    /// ```
+    async fn push_if_absent_async<V, Cn>(
+        contents: &Self::Contents,
+        connection: &Cn,
+    ) -> Result<CollectionHeader<Self::PrimaryKey>, Error>
+    where
+        V: crate::schema::View<Collection = Self> + 'static,
+        Cn: AsyncConnection,
+        Self: Sized + 'static,
+    {
+        let view_name = unique_view_name::<V, _>(connection)?;
+        match connection.collection::<Self>().push(contents).await {
+            Ok(header) => Ok(header),
+            Err(Error::UniqueKeyViolation {
+                view,
+                existing_document,
+                ..
+            }) if view == view_name => CollectionHeader::try_from(*existing_document),
+            Err(other) => Err(other),
+        }
+    }
+
     /// Pushes all `contents` in a single transaction. If successful, all
     /// collection documents will be returned. If an error occurs during this
     /// operation, no documents will be pushed.
@@ -1257,6 +1474,18 @@ pub trait SerializedCollection: Collection {
     }
 }
 
+/// Returns the name of the view `V`, ensuring that it is a unique view.
+fn unique_view_name<V: crate::schema::View + 'static, C: HasSchema>(
+    connection: &C,
+) -> Result<ViewName, Error> {
+    let view = connection.schematic().view::<V>()?;
+    if matches!(view.update_policy(), ViewUpdatePolicy::Unique) {
+        Ok(view.view_name())
+    } else {
+        Err(Error::ViewNotUnique(view.view_name()))
+    }
+}
+
 /// A convenience trait for easily storing Serde-compatible types in documents.
 pub trait DefaultSerialization: Collection {
     /// Returns the natural identifier of `contents`. This is called when
@@ -1736,7 +1965,7 @@ where
                     update.call(&mut existing.contents);
                     match existing.update(connection) {
                         Ok(()) => return Ok(Some(existing)),
-                        Err(Error::DocumentConflict(collection, header)) => {
+                        Err(Error::DocumentConflict(collection, header, expected)) => {
                             // Another client has updated the document underneath us.
                             if retry_limit > 0 {
                                 retry_limit -= 1;
@@ -1746,7 +1975,7 @@ where
                                     None => break Ok(None),
                                 }
                             } else {
-                                break Err(Error::DocumentConflict(collection, header));
+                                break Err(Error::DocumentConflict(collection, header, expected));
                             }
                         }
                         Err(other) => break Err(other),
@@ -1858,7 +2087,7 @@ where
                     update.call(&mut existing.contents);
                     match existing.update_async(connection).await {
                         Ok(()) => return Ok(Some(existing)),
-                        Err(Error::DocumentConflict(collection, header)) => {
+                        Err(Error::DocumentConflict(collection, header, expected)) => {
                             // Another client has updated the document underneath us.
                             if retry_limit > 0 {
                                 retry_limit -= 1;
@@ -1868,7 +2097,7 @@ where
                                     None => break Ok(None),
                                 }
                             } else {
-                                break Err(Error::DocumentConflict(collection, header));
+                                break Err(Error::DocumentConflict(collection, header, expected));
                             }
                         }
                         Err(other) => break Err(other),
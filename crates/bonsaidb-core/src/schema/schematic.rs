@@ -2,13 +2,15 @@ use std::any::TypeId;
 use std::collections::{hash_map, HashMap};
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use derive_where::derive_where;
 
-use crate::document::{BorrowedDocument, DocumentId, KeyId};
-use crate::key::{ByteSource, Key, KeyDescription};
-use crate::schema::collection::Collection;
-use crate::schema::view::map::{self, MappedValue};
+use crate::document::{BorrowedDocument, DocumentId, Header, KeyId};
+use crate::key::{ByteSource, Key, KeyDescription, NextValueError};
+use crate::keyvalue::Timestamp;
+use crate::schema::collection::{Collection, IdStrategy};
+use crate::schema::view::map::{self, MapContext, MappedValue};
 use crate::schema::view::{
     self, MapReduce, Serialized, SerializedView, ViewSchema, ViewUpdatePolicy,
 };
@@ -19,6 +21,8 @@ use crate::Error;
 pub struct Schematic {
     /// The name of the schema this was built from.
     pub name: SchemaName,
+    /// The [version](Schema::schema_version) of the schema this was built from.
+    pub version: u64,
     contained_collections: HashMap<CollectionName, KeyDescription>,
     collections_by_type_id: HashMap<TypeId, CollectionName>,
     collection_encryption_keys: HashMap<CollectionName, KeyId>,
@@ -34,6 +38,7 @@ impl Schematic {
     pub fn from_schema<S: Schema + ?Sized>() -> Result<Self, Error> {
         let mut schematic = Self {
             name: S::schema_name(),
+            version: S::schema_version(),
             contained_collections: HashMap::new(),
             collections_by_type_id: HashMap::new(),
             collection_encryption_keys: HashMap::new(),
@@ -57,8 +62,18 @@ impl Schematic {
                 if let Some(key) = C::encryption_key() {
                     self.collection_encryption_keys.insert(name.clone(), key);
                 }
-                self.collection_id_generators
-                    .insert(name, Box::<KeyIdGenerator<C>>::default());
+                let generator: Box<dyn IdGenerator> = match C::id_strategy() {
+                    IdStrategy::Sequential => Box::<KeyIdGenerator<C>>::default(),
+                    IdStrategy::TimeOrdered => {
+                        if KeyDescription::for_key::<C::PrimaryKey>()
+                            != KeyDescription::for_key::<u64>()
+                        {
+                            return Err(Error::DocumentPush(name, NextValueError::Unsupported));
+                        }
+                        Box::<TimeOrderedIdGenerator<C>>::default()
+                    }
+                };
+                self.collection_id_generators.insert(name, generator);
                 entry.insert(KeyDescription::for_key::<C::PrimaryKey>());
                 C::define_views(self)
             }
@@ -90,7 +105,9 @@ impl Schematic {
         }
 
         let collection = instance.collection();
-        let eager = instance.update_policy().is_eager();
+        // Deletion history views are updated within the transactions that
+        // delete documents.
+        let eager = instance.update_policy().is_eager() || instance.deletion_history();
         self.views.insert(TypeId::of::<V>(), Box::new(instance));
         self.views_by_name.insert(name, TypeId::of::<V>());
 
@@ -178,7 +195,8 @@ impl Schematic {
     }
 
     /// Iterates over all views that are eagerly updated that belong to
-    /// `collection`.
+    /// `collection`. This includes [deletion history
+    /// views](ViewSchema::deletion_history).
     pub fn eager_views_in_collection(
         &self,
         collection: &CollectionName,
@@ -216,6 +234,7 @@ impl Debug for Schematic {
 
         f.debug_struct("Schematic")
             .field("name", &self.name)
+            .field("version", &self.version)
             .field("contained_collections", &self.contained_collections)
             .field("collections_by_type_id", &self.collections_by_type_id)
             .field(
@@ -262,8 +281,38 @@ where
         self.view.view_name()
     }
 
-    fn map(&self, document: &BorrowedDocument<'_>) -> Result<Vec<map::Serialized>, view::Error> {
-        let mappings = self.schema.map(document)?;
+    fn related_collections(&self) -> Vec<CollectionName> {
+        self.schema.related_collections()
+    }
+
+    fn incremental_reduce(&self) -> bool {
+        self.schema.incremental_reduce()
+    }
+
+    fn deletion_history(&self) -> bool {
+        self.schema.deletion_history()
+    }
+
+    fn map(
+        &self,
+        document: &BorrowedDocument<'_>,
+        context: &MapContext<'_>,
+    ) -> Result<Vec<map::Serialized>, view::Error> {
+        let mappings = self.schema.map_with_context(document, context)?;
+
+        mappings
+            .iter()
+            .map(map::Map::serialized::<V>)
+            .collect::<Result<_, _>>()
+            .map_err(view::Error::key_serialization)
+    }
+
+    fn map_deleted(
+        &self,
+        header: &Header,
+        deleted_at: Timestamp,
+    ) -> Result<Vec<map::Serialized>, view::Error> {
+        let mappings = self.schema.map_deleted(header, deleted_at)?;
 
         mappings
             .iter()
@@ -318,6 +367,41 @@ where
     }
 }
 
+#[derive_where(Default, Debug)]
+pub struct TimeOrderedIdGenerator<C: Collection>(PhantomData<C>);
+
+impl<C> TimeOrderedIdGenerator<C>
+where
+    C: Collection,
+{
+    const SEQUENCE_BITS: u32 = 16;
+
+    fn id_for_now() -> u64 {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX))
+            .unwrap_or_default();
+        millis.min(u64::MAX >> Self::SEQUENCE_BITS) << Self::SEQUENCE_BITS
+    }
+}
+
+impl<C> IdGenerator for TimeOrderedIdGenerator<C>
+where
+    C: Collection,
+{
+    fn next_id(&self, id: Option<DocumentId>) -> Result<DocumentId, Error> {
+        let last = id.map(|id| id.deserialize::<u64>()).transpose()?;
+        let candidate = Self::id_for_now();
+        let next = match last {
+            Some(last) if last >= candidate => last.checked_add(1).ok_or_else(|| {
+                Error::DocumentPush(C::collection_name(), NextValueError::WouldWrap)
+            })?,
+            _ => candidate,
+        };
+        DocumentId::new(&next)
+    }
+}
+
 #[test]
 fn schema_tests() -> anyhow::Result<()> {
     use crate::test_util::{Basic, BasicCount};
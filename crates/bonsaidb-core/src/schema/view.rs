@@ -6,12 +6,15 @@ use transmog::{Format, OwnedDeserializer};
 use transmog_pot::Pot;
 
 use crate::connection::{self, AsyncConnection, Connection};
-use crate::document::{BorrowedDocument, CollectionDocument};
+use crate::document::{BorrowedDocument, CollectionDocument, Header};
 use crate::key::{ByteSource, Key, KeyDescription};
-use crate::schema::view::map::{MappedValue, Mappings, ViewMappedValue};
+use crate::keyvalue::Timestamp;
+use crate::schema::view::map::{MapContext, MappedValue, Mappings, ViewMappedValue};
 use crate::schema::{Collection, CollectionName, Name, SerializedCollection, ViewName};
 use crate::AnyError;
 
+/// Helpers for grouping numeric keys into buckets.
+pub mod bucket;
 /// Types for defining a `Map` within a `View`.
 pub mod map;
 
@@ -196,6 +199,51 @@ pub trait ViewSchema: Send + Sync + 'static {
     fn version(&self) -> u64 {
         0
     }
+
+    /// Returns the collections whose documents can be read through the
+    /// [`MapContext`] passed to [`MapReduce::map_with_context()`]. The
+    /// provided implementation returns an empty list.
+    ///
+    /// Documents read while mapping are tracked, and when one of them changes,
+    /// the documents that read it are re-indexed. Related documents are only
+    /// supported by views using the [`ViewUpdatePolicy::Lazy`] policy.
+    fn related_collections(&self) -> Vec<CollectionName> {
+        Vec::new()
+    }
+
+    /// Returns true if this view's reduced values should be stored as the
+    /// view is updated. The provided implementation returns false.
+    ///
+    /// When enabled, the reduced value of each key is stored alongside
+    /// partially re-reduced values covering ranges of keys. Both are updated
+    /// using [`MapReduce::reduce()`] with `rereduce` set to true whenever the
+    /// view's entries change. Reduce queries then read these partials instead
+    /// of scanning every entry in the view, which is significantly faster for
+    /// views with many entries, at the cost of extra work when the view is
+    /// updated.
+    ///
+    /// Changing the value this function returns causes the view's index to
+    /// be rebuilt.
+    fn incremental_reduce(&self) -> bool {
+        false
+    }
+
+    /// Returns true if this view records the documents deleted from its
+    /// collection rather than indexing the collection's current documents.
+    /// The provided implementation returns false.
+    ///
+    /// Deletion history views are updated within each transaction that deletes
+    /// documents by calling [`MapReduce::map_deleted()`] with the deleted
+    /// document's header and the time it was deleted. Unlike other views, the
+    /// emitted entries are kept after the document is deleted, and aren't
+    /// replaced when another document is saved with the same id.
+    /// [`MapReduce::map()`] is never called for these views.
+    ///
+    /// Deletions can't be mapped again, so the history is cleared if the
+    /// view's index is rebuilt, including when its version changes.
+    fn deletion_history(&self) -> bool {
+        false
+    }
 }
 
 /// The policy under which a [`View`] is updated when documents are saved.
@@ -248,6 +296,35 @@ pub trait MapReduce: ViewSchema {
     /// works](https://dev.bonsaidb.io/main/guide/about/concepts/view.html#map).
     fn map<'doc>(&self, document: &'doc BorrowedDocument<'_>) -> ViewMapResult<'doc, Self>;
 
+    /// The map function for this view, with access to documents in
+    /// [related collections](ViewSchema::related_collections) through
+    /// `context`. The provided implementation calls [`Self::map()`].
+    ///
+    /// Views that override this function are never mapped through
+    /// [`Self::map()`], and can implement it by returning
+    /// [`Mappings::none()`].
+    #[allow(unused_variables)]
+    fn map_with_context<'doc>(
+        &self,
+        document: &'doc BorrowedDocument<'_>,
+        context: &MapContext<'_>,
+    ) -> ViewMapResult<'doc, Self> {
+        self.map(document)
+    }
+
+    /// The map function for [deletion history
+    /// views](ViewSchema::deletion_history), which is called with the `header`
+    /// of each document deleted from the view's collection and the time it was
+    /// `deleted_at`. The provided implementation returns [`Mappings::none()`].
+    #[allow(unused_variables)]
+    fn map_deleted<'doc>(
+        &self,
+        header: &'doc Header,
+        deleted_at: Timestamp,
+    ) -> ViewMapResult<'doc, Self> {
+        Ok(Mappings::none())
+    }
+
     /// Returns a value that is produced by reducing a list of `mappings` into a
     /// single value. If `rereduce` is true, the values contained in the
     /// mappings have already been reduced at least one time. If an error of
@@ -338,6 +415,38 @@ where
     where
         CollectionDocument<<Self::View as View>::Collection>: 'doc;
 
+    /// The map function for this view, with access to documents in
+    /// [related collections](ViewSchema::related_collections) through
+    /// `context`. The provided implementation calls [`Self::map()`].
+    ///
+    /// Views that override this function are never mapped through
+    /// [`Self::map()`], and can implement it by returning
+    /// [`Mappings::none()`].
+    #[allow(unused_variables)]
+    fn map_with_context<'doc>(
+        &self,
+        document: CollectionDocument<<Self::View as View>::Collection>,
+        context: &MapContext<'_>,
+    ) -> ViewMapResult<'doc, Self>
+    where
+        CollectionDocument<<Self::View as View>::Collection>: 'doc,
+    {
+        self.map(document)
+    }
+
+    /// The map function for [deletion history
+    /// views](ViewSchema::deletion_history), which is called with the `header`
+    /// of each document deleted from the view's collection and the time it was
+    /// `deleted_at`. The provided implementation returns [`Mappings::none()`].
+    #[allow(unused_variables)]
+    fn map_deleted<'doc>(
+        &self,
+        header: &'doc Header,
+        deleted_at: Timestamp,
+    ) -> ViewMapResult<'doc, Self> {
+        Ok(Mappings::none())
+    }
+
     /// The reduce function for this view. If `Err(Error::ReduceUnimplemented)`
     /// is returned, queries that ask for a reduce operation will return an
     /// error. See [`CouchDB`'s Reduce/Rereduce
@@ -363,6 +472,22 @@ where
         T::map(self, CollectionDocument::try_from(document)?)
     }
 
+    fn map_with_context<'doc>(
+        &self,
+        document: &'doc BorrowedDocument<'_>,
+        context: &MapContext<'_>,
+    ) -> ViewMapResult<'doc, Self> {
+        T::map_with_context(self, CollectionDocument::try_from(document)?, context)
+    }
+
+    fn map_deleted<'doc>(
+        &self,
+        header: &'doc Header,
+        deleted_at: Timestamp,
+    ) -> ViewMapResult<'doc, Self> {
+        T::map_deleted(self, header, deleted_at)
+    }
+
     fn reduce(
         &self,
         mappings: &[ViewMappedValue<'_, Self>],
@@ -385,8 +510,24 @@ pub trait Serialized: Send + Sync {
     fn version(&self) -> u64;
     /// Wraps [`View::view_name`]
     fn view_name(&self) -> ViewName;
-    /// Wraps [`MapReduce::map`]
-    fn map(&self, document: &BorrowedDocument<'_>) -> Result<Vec<map::Serialized>, Error>;
+    /// Wraps [`ViewSchema::related_collections`]
+    fn related_collections(&self) -> Vec<CollectionName>;
+    /// Wraps [`ViewSchema::incremental_reduce`]
+    fn incremental_reduce(&self) -> bool;
+    /// Wraps [`ViewSchema::deletion_history`]
+    fn deletion_history(&self) -> bool;
+    /// Wraps [`MapReduce::map_with_context`]
+    fn map(
+        &self,
+        document: &BorrowedDocument<'_>,
+        context: &MapContext<'_>,
+    ) -> Result<Vec<map::Serialized>, Error>;
+    /// Wraps [`MapReduce::map_deleted`]
+    fn map_deleted(
+        &self,
+        header: &Header,
+        deleted_at: Timestamp,
+    ) -> Result<Vec<map::Serialized>, Error>;
     /// Wraps [`MapReduce::reduce`]
     fn reduce(&self, mappings: &[(&[u8], &[u8])], rereduce: bool) -> Result<Vec<u8>, Error>;
 }
@@ -0,0 +1,136 @@
+use std::ops::RangeInclusive;
+
+/// A scheme for grouping numeric values into buckets, such as when building a
+/// histogram.
+///
+/// [`bucketize()`](Self::bucketize) returns the lowest value in the bucket a
+/// value belongs to. By emitting this value as a view's key, each bucket
+/// becomes a single key in the view's index. Combined with a
+/// [`reduce()`](crate::schema::MapReduce::reduce) implementation that counts
+/// the mappings, [`reduce_grouped()`](crate::connection::View::reduce_grouped)
+/// returns the number of documents in each bucket:
+///
+/// ```rust
+/// # use bonsaidb_core::connection::Connection;
+/// use bonsaidb_core::document::{CollectionDocument, Emit};
+/// use bonsaidb_core::schema::view::bucket::Buckets;
+/// use bonsaidb_core::schema::{
+///     Collection, CollectionMapReduce, ReduceResult, SerializedView, View, ViewMapResult,
+///     ViewMappedValue, ViewSchema,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// const LATENCY_BUCKETS: Buckets = Buckets::exponential(2);
+///
+/// #[derive(Collection, Debug, Serialize, Deserialize)]
+/// #[collection(name = "requests", views = [RequestsByLatency])]
+/// # #[collection(core = bonsaidb_core)]
+/// struct Request {
+///     latency_ms: u64,
+/// }
+///
+/// #[derive(View, ViewSchema, Debug, Clone)]
+/// #[view(collection = Request, key = u64, value = u64)]
+/// # #[view(core = bonsaidb_core)]
+/// # #[view_schema(core = bonsaidb_core)]
+/// struct RequestsByLatency;
+///
+/// impl CollectionMapReduce for RequestsByLatency {
+///     fn map<'doc>(&self, document: CollectionDocument<Request>) -> ViewMapResult<'doc, Self> {
+///         let bucket = LATENCY_BUCKETS.bucketize(document.contents.latency_ms);
+///         document.header.emit_key_and_value(bucket, 1)
+///     }
+///
+///     fn reduce(
+///         &self,
+///         mappings: &[ViewMappedValue<'_, Self>],
+///         _rereduce: bool,
+///     ) -> ReduceResult<Self> {
+///         Ok(mappings.iter().map(|mapping| mapping.value).sum())
+///     }
+/// }
+///
+/// # fn test_fn<C: Connection>(db: &C) -> Result<(), bonsaidb_core::Error> {
+/// for bucket in RequestsByLatency::entries(db).reduce_grouped()? {
+///     println!(
+///         "{:?} ms: {} requests",
+///         LATENCY_BUCKETS.bucket_range(bucket.key),
+///         bucket.value
+///     );
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Buckets(Scheme);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Scheme {
+    FixedWidth(u64),
+    Exponential(u64),
+}
+
+impl Buckets {
+    /// Returns buckets that each contain `width` values: `0..width`,
+    /// `width..width * 2`, and so on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is 0.
+    #[must_use]
+    pub const fn fixed_width(width: u64) -> Self {
+        assert!(width > 0, "bucket width must be greater than 0");
+        Self(Scheme::FixedWidth(width))
+    }
+
+    /// Returns buckets whose bounds are powers of `base`: `0..1`, `1..base`,
+    /// `base..base^2`, and so on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` is less than 2.
+    #[must_use]
+    pub const fn exponential(base: u64) -> Self {
+        assert!(base >= 2, "bucket base must be at least 2");
+        Self(Scheme::Exponential(base))
+    }
+
+    /// Returns the lowest value in the bucket containing `value`.
+    #[must_use]
+    pub const fn bucketize(&self, value: u64) -> u64 {
+        match self.0 {
+            Scheme::FixedWidth(width) => value - value % width,
+            Scheme::Exponential(base) => {
+                if value == 0 {
+                    return 0;
+                }
+                let mut bound = 1_u64;
+                while let Some(next) = bound.checked_mul(base) {
+                    if next > value {
+                        break;
+                    }
+                    bound = next;
+                }
+                bound
+            }
+        }
+    }
+
+    /// Returns the range of values in the bucket containing `value`. The
+    /// returned range can be passed to
+    /// [`with_key_range()`](crate::connection::View::with_key_range) to query
+    /// a single bucket.
+    #[must_use]
+    pub const fn bucket_range(&self, value: u64) -> RangeInclusive<u64> {
+        let start = self.bucketize(value);
+        let end = match self.0 {
+            Scheme::FixedWidth(width) => start.saturating_add(width - 1),
+            Scheme::Exponential(_) if start == 0 => 0,
+            Scheme::Exponential(base) => match start.checked_mul(base) {
+                Some(next) => next - 1,
+                None => u64::MAX,
+            },
+        };
+        start..=end
+    }
+}
@@ -1,11 +1,14 @@
+use std::cell::RefCell;
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 
 use arc_bytes::serde::Bytes;
 use serde::{Deserialize, Serialize};
 
-use crate::document::{DocumentId, Header, OwnedDocument};
+use crate::document::{CollectionDocument, DocumentId, Header, OwnedDocument};
+use crate::key::KeyEncoding;
 use crate::schema::view::{self, ByteSource, Key, SerializedView, View, ViewSchema};
+use crate::schema::{CollectionName, SerializedCollection};
 
 /// A document's entry in a View's mappings.
 #[derive(Eq, PartialEq, Debug)]
@@ -368,3 +371,120 @@ pub struct MappedSerializedValue {
     /// The serialized value.
     pub value: Bytes,
 }
+
+/// The number of entries for a serialized key in a view.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MappedSerializedCount {
+    /// The serialized key.
+    pub key: Bytes,
+    /// The number of entries mapped with this key.
+    pub count: u64,
+}
+
+/// A serialized view entry's key and source, without the mapped value.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct MappedSerializedKey {
+    /// The header of the document that emitted this entry.
+    pub source: Header,
+    /// The serialized key.
+    pub key: Bytes,
+}
+
+impl MappedSerializedKey {
+    /// Deserializes this key.
+    pub fn deserialized<View: SerializedView>(&self) -> Result<Map<View::Key>, view::Error> {
+        Ok(Map::new(
+            self.source.clone(),
+            <View::Key as Key>::from_ord_bytes(ByteSource::Borrowed(&self.key))
+                .map_err(view::Error::key_serialization)?,
+            (),
+        ))
+    }
+}
+
+/// A function that loads a document from a collection while mapping a view.
+pub type RelatedDocumentLoader<'a> =
+    dyn Fn(&CollectionName, &DocumentId) -> Result<Option<OwnedDocument>, crate::Error> + 'a;
+
+/// Provides access to documents in related collections while a view's map
+/// function is executing.
+///
+/// Only collections returned from
+/// [`ViewSchema::related_collections()`](crate::schema::ViewSchema::related_collections)
+/// can be accessed. Each document requested is recorded as a dependency of the
+/// document being mapped. When a dependency is changed, the document being
+/// mapped is re-indexed.
+pub struct MapContext<'a> {
+    related_collections: &'a [CollectionName],
+    loader: &'a RelatedDocumentLoader<'a>,
+    dependencies: RefCell<Vec<(CollectionName, DocumentId)>>,
+}
+
+impl<'a> MapContext<'a> {
+    /// Returns a new context that loads documents from `related_collections`
+    /// using `loader`.
+    #[must_use]
+    pub fn new(
+        related_collections: &'a [CollectionName],
+        loader: &'a RelatedDocumentLoader<'a>,
+    ) -> Self {
+        Self {
+            related_collections,
+            loader,
+            dependencies: RefCell::default(),
+        }
+    }
+
+    /// Retrieves the document with `id` from the collection `C`, and records it
+    /// as a dependency of the document being mapped.
+    pub fn get<C, PrimaryKey>(
+        &self,
+        id: &PrimaryKey,
+    ) -> Result<Option<CollectionDocument<C>>, crate::Error>
+    where
+        C: SerializedCollection,
+        PrimaryKey: KeyEncoding<C::PrimaryKey> + ?Sized,
+    {
+        self.get_from_collection(
+            DocumentId::new::<C::PrimaryKey, PrimaryKey>(id)?,
+            &C::collection_name(),
+        )?
+        .as_ref()
+        .map(CollectionDocument::try_from)
+        .transpose()
+    }
+
+    /// Retrieves the document with `id` from `collection`, and records it as a
+    /// dependency of the document being mapped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `collection` is not one of the view's related
+    /// collections.
+    pub fn get_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, crate::Error> {
+        if !self.related_collections.contains(collection) {
+            return Err(crate::Error::other(
+                "view",
+                format!("{collection} is not a related collection of this view"),
+            ));
+        }
+
+        let document = (self.loader)(collection, &id)?;
+        // Missing documents are tracked too, so that creating the document
+        // later will re-index the mapped document.
+        self.dependencies
+            .borrow_mut()
+            .push((collection.clone(), id));
+        Ok(document)
+    }
+
+    /// Returns the documents that were requested through this context.
+    #[must_use]
+    pub fn into_dependencies(self) -> Vec<(CollectionName, DocumentId)> {
+        self.dependencies.into_inner()
+    }
+}
@@ -20,13 +20,14 @@ use crate::connection::{
 use crate::document::{
     BorrowedDocument, CollectionDocument, CollectionHeader, DocumentId, Emit, Header, KeyId,
 };
-use crate::keyvalue::{AsyncKeyValue, KeyValue};
+use crate::key::{ByteSource, Key, KeyEncoding};
+use crate::keyvalue::{AsyncKeyValue, KeyValue, Timestamp};
 use crate::limits::{LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT, LIST_TRANSACTIONS_MAX_RESULTS};
-use crate::schema::view::map::{Mappings, ViewMappedValue};
+use crate::schema::view::map::{MapContext, Mappings, ViewMappedValue};
 use crate::schema::view::{MapReduce, ReduceResult, SerializedView, ViewUpdatePolicy};
 use crate::schema::{
     Collection, CollectionName, MappedValue, NamedCollection, Qualified, Schema, SchemaName,
-    Schematic, SerializedCollection, View, ViewMapResult, ViewSchema,
+    SchemaSummary, Schematic, SerializedCollection, View, ViewMapResult, ViewName, ViewSchema,
 };
 use crate::transaction::{Operation, OperationResult, Transaction};
 use crate::Error;
@@ -39,7 +40,7 @@ use crate::{
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Default, Clone, Collection)]
 // This collection purposely uses names with characters that need
 // escaping, since it's used in backup/restore.
-#[collection(name = "_basic", authority = "khonsulabs_", views = [BasicCount, BasicByParentId, BasicByParentIdEager, BasicByTag, BasicByCategory, BasicByCategoryCow], core = crate)]
+#[collection(name = "_basic", authority = "khonsulabs_", views = [BasicCount, BasicByParentId, BasicByParentIdEager, BasicByTag, BasicByCategory, BasicByCategoryCow, BasicByParentValue], core = crate)]
 #[must_use]
 pub struct Basic {
     pub value: String,
@@ -81,7 +82,7 @@ pub struct BasicCount;
 
 impl MapReduce for BasicCount {
     fn map<'doc>(&self, document: &'doc BorrowedDocument<'_>) -> ViewMapResult<'doc, Self> {
-        document.header.emit_key_and_value((), 1)
+        document.header.emit_value(1)
     }
 
     fn reduce(
@@ -227,6 +228,30 @@ impl MapReduce for BasicByTag {
     }
 }
 
+#[derive(Debug, Clone, View, ViewSchema)]
+#[view(collection = Basic, key = String, value = (), name = "by-parent-value", core = crate)]
+#[view_schema(core = crate, related_collections = [Basic])]
+pub struct BasicByParentValue;
+
+impl MapReduce for BasicByParentValue {
+    fn map<'doc>(&self, _document: &'doc BorrowedDocument<'_>) -> ViewMapResult<'doc, Self> {
+        Ok(Mappings::none())
+    }
+
+    fn map_with_context<'doc>(
+        &self,
+        document: &'doc BorrowedDocument<'_>,
+        context: &MapContext<'_>,
+    ) -> ViewMapResult<'doc, Self> {
+        let contents = Basic::document_contents(document)?;
+        let Some(parent_id) = contents.parent_id else { return Ok(Mappings::none()) };
+        match context.get::<Basic, _>(&parent_id)? {
+            Some(parent) => document.header.emit_key(parent.contents.value),
+            None => Ok(Mappings::none()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, View, ViewSchema)]
 #[view(collection = Basic, key = (), value = (), name = "by-parent-id", core = crate)]
 #[view_schema(core = crate)]
@@ -275,7 +300,7 @@ pub struct EncryptedBasicCount;
 
 impl MapReduce for EncryptedBasicCount {
     fn map<'doc>(&self, document: &'doc BorrowedDocument<'_>) -> ViewMapResult<'doc, Self> {
-        document.header.emit_key_and_value((), 1)
+        document.header.emit_value(1)
     }
 
     fn reduce(
@@ -412,6 +437,49 @@ impl Deref for TestDirectory {
     }
 }
 
+/// Asserts that a [`Key`] implementation encodes `samples` consistently.
+///
+/// `samples` must be sorted in ascending order. For each sample, this function
+/// asserts that decoding its encoded bytes returns the original value, and
+/// that comparing the encoded bytes of adjacent samples produces the same
+/// ordering as comparing the samples themselves.
+///
+/// This can be used to validate custom [`Key`] implementations before using
+/// them in views or as primary keys.
+pub fn assert_key_ordering<K>(samples: &[K])
+where
+    K: for<'k> Key<'k> + Ord + Debug,
+{
+    let encoded = samples
+        .iter()
+        .map(|sample| {
+            let bytes = sample
+                .as_ord_bytes()
+                .unwrap_or_else(|err| panic!("{sample:?} failed to encode: {err}"));
+            let decoded = K::from_ord_bytes(ByteSource::Borrowed(&bytes))
+                .unwrap_or_else(|err| panic!("{sample:?} failed to decode: {err}"));
+            assert_eq!(&decoded, sample, "{sample:?} did not round-trip");
+            bytes.into_owned()
+        })
+        .collect::<Vec<_>>();
+
+    for (samples, encoded) in samples.windows(2).zip(encoded.windows(2)) {
+        assert!(
+            samples[0] <= samples[1],
+            "samples must be sorted: {:?} > {:?}",
+            samples[0],
+            samples[1]
+        );
+        assert_eq!(
+            samples[0].cmp(&samples[1]),
+            encoded[0].cmp(&encoded[1]),
+            "encoded ordering of {:?} and {:?} does not match",
+            samples[0],
+            samples[1]
+        );
+    }
+}
+
 #[derive(Debug)]
 pub struct BasicCollectionWithNoViews;
 
@@ -495,6 +563,30 @@ pub enum HarnessTest {
     KvExpiration,
     KvDeleteExpire,
     KvTransactions,
+    KvDecimal,
+    EventLog,
+    ViewRelatedDocuments,
+    KvLease,
+    ViewCount,
+    TransactionIdempotency,
+    TransactionRetry,
+    ViewRebuild,
+    KvExpiringBetween,
+    CollectionDeleteAll,
+    TransactionMetadata,
+    KvPipeline,
+    KvGetBytes,
+    ViewKeysOnly,
+    DocumentRefresh,
+    KvBoundedIncrement,
+    DocumentExists,
+    ViewKeyBounds,
+    SchemaIntrospection,
+    KvIncrementWithPrevious,
+    KvKeyExpirations,
+    DocumentPushWithId,
+    KvNamespaceScan,
+    DocumentTombstones,
 }
 
 impl HarnessTest {
@@ -764,6 +856,150 @@ macro_rules! define_async_connection_test_suite {
                 $crate::test_util::compaction_tests(&db).await?;
                 harness.shutdown().await
             }
+
+            #[tokio::test]
+            async fn event_log() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::EventLog).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::event_log_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn view_related_documents() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::ViewRelatedDocuments).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::view_related_document_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn view_count() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewCount).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::view_count_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn transaction_idempotency() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::TransactionIdempotency).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::transaction_idempotency_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn transaction_retry() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::TransactionRetry).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::transaction_retry_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn view_rebuild() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewRebuild).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::view_rebuild_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn collection_delete_all() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::CollectionDeleteAll).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::collection_delete_all_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn transaction_metadata() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::TransactionMetadata).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::transaction_metadata_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn view_keys_only() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewKeysOnly).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::view_keys_only_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn document_refresh() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::DocumentRefresh).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::document_refresh_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn document_exists() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::DocumentExists).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::document_exists_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn document_tombstones() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::DocumentTombstones).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::document_tombstones_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn document_push_with_id() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::DocumentPushWithId).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::push_with_id_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn view_key_bounds() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewKeyBounds).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::view_key_bounds_tests(&db).await?;
+                harness.shutdown().await
+            }
+
+            #[tokio::test]
+            async fn schema_introspection() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::SchemaIntrospection).await?;
+                let db = harness.connect().await?;
+
+                $crate::test_util::schema_introspection_tests(&db)?;
+                harness.shutdown().await
+            }
         }
     };
 }
@@ -1008,6 +1244,142 @@ macro_rules! define_blocking_connection_test_suite {
                 $crate::test_util::blocking_compaction_tests(&db)?;
                 harness.shutdown()
             }
+
+            #[test]
+            fn event_log() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::EventLog)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_event_log_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn view_related_documents() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewRelatedDocuments)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_view_related_document_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn view_count() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewCount)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_view_count_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn transaction_idempotency() -> anyhow::Result<()> {
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::TransactionIdempotency)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_transaction_idempotency_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn transaction_retry() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::TransactionRetry)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_transaction_retry_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn view_rebuild() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewRebuild)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_view_rebuild_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn collection_delete_all() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::CollectionDeleteAll)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_collection_delete_all_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn transaction_metadata() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::TransactionMetadata)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_transaction_metadata_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn view_keys_only() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewKeysOnly)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_view_keys_only_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn document_refresh() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::DocumentRefresh)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_document_refresh_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn document_exists() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::DocumentExists)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_document_exists_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn document_tombstones() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::DocumentTombstones)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_document_tombstones_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn document_push_with_id() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::DocumentPushWithId)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_push_with_id_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn view_key_bounds() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::ViewKeyBounds)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_view_key_bounds_tests(&db)?;
+                harness.shutdown()
+            }
+
+            #[test]
+            fn schema_introspection() -> anyhow::Result<()> {
+                let harness = $harness::new($crate::test_util::HarnessTest::SchemaIntrospection)?;
+                let db = harness.connect()?;
+
+                $crate::test_util::blocking_schema_introspection_tests(&db)?;
+                harness.shutdown()
+            }
         }
     };
 }
@@ -1310,11 +1682,21 @@ pub async fn conflict_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
 
     // To generate a conflict, let's try to do the same update again by
     // reverting the header
+    let updated_revision = doc.header.revision;
     doc.header = Header::try_from(header).unwrap();
-    let conflicting_header = db
+    let conflict = db
         .update::<Basic, _>(&mut doc)
         .await
-        .expect_err("conflict should have generated an error")
+        .expect_err("conflict should have generated an error");
+    match &conflict {
+        Error::DocumentConflict(_, stored, expected) => {
+            assert_eq!(stored.id, doc.header.id);
+            assert_eq!(stored.revision, updated_revision);
+            assert_eq!(expected, &Some(doc.header.revision));
+        }
+        other => unreachable!("unexpected error: {other:?}"),
+    }
+    let conflicting_header = conflict
         .conflicting_document::<Basic>()
         .expect("conflict not detected");
     assert_eq!(conflicting_header.id, doc.header.id);
@@ -1351,10 +1733,20 @@ pub fn blocking_conflict_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
 
     // To generate a conflict, let's try to do the same update again by
     // reverting the header
+    let updated_revision = doc.header.revision;
     doc.header = Header::try_from(header).unwrap();
-    let conflicting_header = db
+    let conflict = db
         .update::<Basic, _>(&mut doc)
-        .expect_err("conflict should have generated an error")
+        .expect_err("conflict should have generated an error");
+    match &conflict {
+        Error::DocumentConflict(_, stored, expected) => {
+            assert_eq!(stored.id, doc.header.id);
+            assert_eq!(stored.revision, updated_revision);
+            assert_eq!(expected, &Some(doc.header.revision));
+        }
+        other => unreachable!("unexpected error: {other:?}"),
+    }
+    let conflicting_header = conflict
         .conflicting_document::<Basic>()
         .expect("conflict not detected");
     assert_eq!(conflicting_header.id, doc.header.id);
@@ -1457,6 +1849,15 @@ pub async fn get_multiple_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()
         .expect("Couldn't find doc2");
     assert_eq!(doc2.contents.value, doc2_value.value);
 
+    // Ids that aren't found are absent from the map.
+    let missing_id = u64::MAX;
+    let docs =
+        Basic::get_multiple_map_async([&doc1.header.id, &missing_id, &doc2.header.id], db).await?;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[&doc1.header.id].contents.value, doc1_value.value);
+    assert_eq!(docs[&doc2.header.id].contents.value, doc2_value.value);
+    assert!(!docs.contains_key(&missing_id));
+
     Ok(())
 }
 
@@ -1487,6 +1888,14 @@ pub fn blocking_get_multiple_tests<C: Connection>(db: &C) -> anyhow::Result<()>
         .expect("Couldn't find doc2");
     assert_eq!(doc2.contents.value, doc2_value.value);
 
+    // Ids that aren't found are absent from the map.
+    let missing_id = u64::MAX;
+    let docs = Basic::get_multiple_map([&doc1.header.id, &missing_id, &doc2.header.id], db)?;
+    assert_eq!(docs.len(), 2);
+    assert_eq!(docs[&doc1.header.id].contents.value, doc1_value.value);
+    assert_eq!(docs[&doc2.header.id].contents.value, doc2_value.value);
+    assert!(!docs.contains_key(&missing_id));
+
     Ok(())
 }
 
@@ -1682,43 +2091,918 @@ pub fn blocking_list_transactions_tests<C: Connection + Clone + 'static>(
     Ok(())
 }
 
-pub async fn transaction_tests<C: AsyncConnection + 'static>(db: &C) -> anyhow::Result<()> {
-    let mut tx = Transaction::new();
-    Basic::new("test").push_in_transaction(&mut tx)?;
-    let results = tx.apply_async(db).await?;
-    let OperationResult::DocumentUpdated { header, .. } = &results[0] else { unreachable!("unexpected tx result") };
-    let id: u64 = header.id.deserialize()?;
+pub async fn event_log_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    use futures::TryStreamExt;
 
-    // Update the doc
-    let mut tx = Transaction::new();
-    let mut doc = Basic::get_async(&id, db).await?.expect("doc not found");
-    doc.contents.category = Some(String::from("cat"));
-    doc.update_in_transaction(&mut tx)?;
-    tx.apply_async(db).await?;
-    let doc = Basic::get_async(&id, db).await?.expect("doc not found");
-    assert_eq!(doc.contents.category.as_deref(), Some("cat"));
+    let events = db.event_log::<Basic>();
+    for value in ["a", "b", "c"] {
+        events.append(&Basic::new(value)).await?;
+        // Documents in other collections aren't part of the log.
+        db.collection::<Unique>().push(&Unique::new(value)).await?;
+    }
+    let replayed = events.replay(0).try_collect::<Vec<_>>().await?;
+    assert_eq!(replayed.len(), 3);
+
+    // Resume after the second event, as a consumer with a checkpoint would.
+    let checkpoint = replayed[1].position;
+    events.append(&Basic::new("d")).await?;
+    let resumed = events
+        .replay(checkpoint + 1)
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert_eq!(
+        resumed
+            .iter()
+            .map(|event| event.document.contents.value.as_str())
+            .collect::<Vec<_>>(),
+        ["c", "d"]
+    );
+    assert!(resumed
+        .windows(2)
+        .all(|pair| pair[0].position < pair[1].position));
 
-    // Overwrite the document.
-    let mut tx = Transaction::new();
-    Basic::new("test").overwrite_in_transaction(&id, &mut tx)?;
-    tx.apply_async(db).await?;
-    let doc = Basic::get_async(&id, db).await?.expect("doc not found");
-    assert_eq!(doc.contents.category, None);
+    Ok(())
+}
 
-    // Delete the document
-    let mut tx = Transaction::new();
-    doc.delete_in_transaction(&mut tx)?;
-    tx.apply_async(db).await?;
+pub fn blocking_event_log_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let events = db.event_log::<Basic>();
+    for value in ["a", "b", "c"] {
+        events.append(&Basic::new(value))?;
+        // Documents in other collections aren't part of the log.
+        db.collection::<Unique>().push(&Unique::new(value))?;
+    }
+    let replayed = events.replay(0).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(replayed.len(), 3);
+
+    // Resume after the second event, as a consumer with a checkpoint would.
+    let checkpoint = replayed[1].position;
+    events.append(&Basic::new("d"))?;
+    let resumed = events
+        .replay(checkpoint + 1)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        resumed
+            .iter()
+            .map(|event| event.document.contents.value.as_str())
+            .collect::<Vec<_>>(),
+        ["c", "d"]
+    );
+    assert!(resumed
+        .windows(2)
+        .all(|pair| pair[0].position < pair[1].position));
 
     Ok(())
 }
 
-pub fn blocking_transaction_tests<C: Connection + 'static>(db: &C) -> anyhow::Result<()> {
-    let mut tx = Transaction::new();
-    Basic::new("test").push_in_transaction(&mut tx)?;
-    let results = tx.apply(db)?;
-    let OperationResult::DocumentUpdated { header, .. } = &results[0] else { unreachable!("unexpected tx result") };
-    let id: u64 = header.id.deserialize()?;
+pub async fn view_related_document_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let mut parent = Basic::new("parent").push_into_async(db).await?;
+    let child = Basic::new("child")
+        .with_parent_id(parent.header.id)
+        .push_into_async(db)
+        .await?;
+
+    let mappings = db
+        .view::<BasicByParentValue>()
+        .with_key(&String::from("parent"))
+        .query()
+        .await?;
+    assert_eq!(mappings.len(), 1);
+    assert_eq!(mappings[0].source.revision, child.header.revision);
+
+    // Changing the parent re-indexes the child, even though the child itself
+    // was not modified.
+    parent.contents.value = String::from("renamed");
+    parent.update_async(db).await?;
+    assert!(db
+        .view::<BasicByParentValue>()
+        .with_key(&String::from("parent"))
+        .query()
+        .await?
+        .is_empty());
+    let mappings = db
+        .view::<BasicByParentValue>()
+        .with_key(&String::from("renamed"))
+        .query()
+        .await?;
+    assert_eq!(mappings.len(), 1);
+    assert_eq!(mappings[0].source.revision, child.header.revision);
+
+    Ok(())
+}
+
+pub fn blocking_view_related_document_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let mut parent = Basic::new("parent").push_into(db)?;
+    let child = Basic::new("child")
+        .with_parent_id(parent.header.id)
+        .push_into(db)?;
+
+    let mappings = db
+        .view::<BasicByParentValue>()
+        .with_key(&String::from("parent"))
+        .query()?;
+    assert_eq!(mappings.len(), 1);
+    assert_eq!(mappings[0].source.revision, child.header.revision);
+
+    // Changing the parent re-indexes the child, even though the child itself
+    // was not modified.
+    parent.contents.value = String::from("renamed");
+    parent.update(db)?;
+    assert!(db
+        .view::<BasicByParentValue>()
+        .with_key(&String::from("parent"))
+        .query()?
+        .is_empty());
+    let mappings = db
+        .view::<BasicByParentValue>()
+        .with_key(&String::from("renamed"))
+        .query()?;
+    assert_eq!(mappings.len(), 1);
+    assert_eq!(mappings[0].source.revision, child.header.revision);
+
+    Ok(())
+}
+
+const VIEW_COUNT_CATEGORIES: [&str; 6] = [
+    "count-a", "count-a", "count-a", "count-b", "count-b", "count-c",
+];
+
+pub async fn view_count_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    for category in VIEW_COUNT_CATEGORIES {
+        Basic::new("test")
+            .with_category(category)
+            .push_into_async(db)
+            .await?;
+    }
+
+    let counts = db
+        .view::<BasicByCategory>()
+        .with_key(&String::from("count-a"))
+        .count()
+        .await?;
+    assert_eq!(counts, vec![MappedValue::new(String::from("count-a"), 3)]);
+
+    let keys = [
+        String::from("count-a"),
+        String::from("count-c"),
+        String::from("count-missing"),
+    ];
+    let counts = db
+        .view::<BasicByCategory>()
+        .with_keys(&keys)
+        .count()
+        .await?;
+    assert_eq!(
+        counts,
+        vec![
+            MappedValue::new(String::from("count-a"), 3),
+            MappedValue::new(String::from("count-c"), 1),
+        ]
+    );
+
+    Ok(())
+}
+
+pub fn blocking_view_count_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    for category in VIEW_COUNT_CATEGORIES {
+        Basic::new("test").with_category(category).push_into(db)?;
+    }
+
+    let counts = db
+        .view::<BasicByCategory>()
+        .with_key(&String::from("count-a"))
+        .count()?;
+    assert_eq!(counts, vec![MappedValue::new(String::from("count-a"), 3)]);
+
+    let keys = [
+        String::from("count-a"),
+        String::from("count-c"),
+        String::from("count-missing"),
+    ];
+    let counts = db.view::<BasicByCategory>().with_keys(&keys).count()?;
+    assert_eq!(
+        counts,
+        vec![
+            MappedValue::new(String::from("count-a"), 3),
+            MappedValue::new(String::from("count-c"), 1),
+        ]
+    );
+
+    Ok(())
+}
+
+pub async fn view_rebuild_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    for category in ["a", "b", "a"] {
+        Basic::new("rebuild")
+            .with_category(category)
+            .push_into_async(db)
+            .await?;
+    }
+    db.view::<BasicByCategory>().query().await?;
+    let state = db.view_index_state::<BasicByCategory>().await?;
+    assert!(state.is_up_to_date());
+    assert_eq!(state.transactions_behind(), 0);
+
+    // Inserting another document leaves the lazy view behind.
+    Basic::new("rebuild")
+        .with_category("c")
+        .push_into_async(db)
+        .await?;
+    let state = db.view_index_state::<BasicByCategory>().await?;
+    assert!(!state.is_up_to_date());
+    assert!(state.transactions_behind() > 0);
+
+    db.rebuild_view::<BasicByCategory>().await?;
+    let state = db.view_index_state::<BasicByCategory>().await?;
+    assert!(state.is_up_to_date());
+    let mappings = db
+        .view::<BasicByCategory>()
+        .with_access_policy(AccessPolicy::NoUpdate)
+        .query()
+        .await?;
+    assert_eq!(mappings.len(), 4);
+
+    Ok(())
+}
+
+pub fn blocking_view_rebuild_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    for category in ["a", "b", "a"] {
+        Basic::new("rebuild")
+            .with_category(category)
+            .push_into(db)?;
+    }
+    db.view::<BasicByCategory>().query()?;
+    let state = db.view_index_state::<BasicByCategory>()?;
+    assert!(state.is_up_to_date());
+    assert_eq!(state.transactions_behind(), 0);
+
+    // Inserting another document leaves the lazy view behind.
+    Basic::new("rebuild").with_category("c").push_into(db)?;
+    let state = db.view_index_state::<BasicByCategory>()?;
+    assert!(!state.is_up_to_date());
+    assert!(state.transactions_behind() > 0);
+
+    db.rebuild_view::<BasicByCategory>()?;
+    let state = db.view_index_state::<BasicByCategory>()?;
+    assert!(state.is_up_to_date());
+    let mappings = db
+        .view::<BasicByCategory>()
+        .with_access_policy(AccessPolicy::NoUpdate)
+        .query()?;
+    assert_eq!(mappings.len(), 4);
+
+    Ok(())
+}
+
+pub async fn collection_delete_all_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let collection = db.collection::<Basic>();
+    assert_eq!(collection.delete_all().await?, 0);
+
+    for category in ["a", "b", "a"] {
+        Basic::new("delete_all")
+            .with_category(category)
+            .push_into_async(db)
+            .await?;
+    }
+    assert_eq!(db.view::<BasicByCategory>().query().await?.len(), 3);
+
+    assert_eq!(collection.delete_all().await?, 3);
+    assert_eq!(collection.all().count().await?, 0);
+    assert!(db.view::<BasicByCategory>().query().await?.is_empty());
+
+    // The collection is still usable after being emptied.
+    Basic::new("delete_all")
+        .with_category("c")
+        .push_into_async(db)
+        .await?;
+    assert_eq!(db.view::<BasicByCategory>().query().await?.len(), 1);
+
+    Ok(())
+}
+
+pub fn blocking_collection_delete_all_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let collection = db.collection::<Basic>();
+    assert_eq!(collection.delete_all()?, 0);
+
+    for category in ["a", "b", "a"] {
+        Basic::new("delete_all")
+            .with_category(category)
+            .push_into(db)?;
+    }
+    assert_eq!(db.view::<BasicByCategory>().query()?.len(), 3);
+
+    assert_eq!(collection.delete_all()?, 3);
+    assert_eq!(collection.all().count()?, 0);
+    assert!(db.view::<BasicByCategory>().query()?.is_empty());
+
+    // The removed documents are recorded as deleted by a single transaction.
+    let transactions = db.list_executed_transactions(None, None)?;
+    let changes = transactions
+        .last()
+        .unwrap()
+        .changes
+        .documents()
+        .expect("document changes");
+    assert_eq!(changes.documents.len(), 3);
+    assert!(changes.documents.iter().all(|doc| doc.deleted));
+
+    // The collection is still usable after being emptied.
+    Basic::new("delete_all").with_category("c").push_into(db)?;
+    assert_eq!(db.view::<BasicByCategory>().query()?.len(), 1);
+
+    Ok(())
+}
+
+pub async fn transaction_idempotency_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let make_transaction = |key: &str| -> anyhow::Result<Transaction> {
+        let mut tx = Transaction::new().with_idempotency_key(key);
+        Basic::new("idempotent").push_in_transaction(&mut tx)?;
+        Ok(tx)
+    };
+
+    let first = make_transaction("a")?.apply_async(db).await?;
+    let retried = make_transaction("a")?.apply_async(db).await?;
+    match (&first[0], &retried[0]) {
+        (
+            OperationResult::DocumentUpdated { header: first, .. },
+            OperationResult::DocumentUpdated { header: second, .. },
+        ) => assert_eq!(first, second),
+        other => unreachable!("unexpected tx results: {other:?}"),
+    }
+    assert_eq!(Basic::all_async(db).count().await?, 1);
+
+    // A different key executes the transaction again.
+    make_transaction("b")?.apply_async(db).await?;
+    assert_eq!(Basic::all_async(db).count().await?, 2);
+
+    Ok(())
+}
+
+pub fn blocking_transaction_idempotency_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let make_transaction = |key: &str| -> anyhow::Result<Transaction> {
+        let mut tx = Transaction::new().with_idempotency_key(key);
+        Basic::new("idempotent").push_in_transaction(&mut tx)?;
+        Ok(tx)
+    };
+
+    let first = make_transaction("a")?.apply(db)?;
+    let retried = make_transaction("a")?.apply(db)?;
+    match (&first[0], &retried[0]) {
+        (
+            OperationResult::DocumentUpdated { header: first, .. },
+            OperationResult::DocumentUpdated { header: second, .. },
+        ) => assert_eq!(first, second),
+        other => unreachable!("unexpected tx results: {other:?}"),
+    }
+    assert_eq!(Basic::all(db).count()?, 1);
+
+    // A different key executes the transaction again.
+    make_transaction("b")?.apply(db)?;
+    assert_eq!(Basic::all(db).count()?, 2);
+
+    Ok(())
+}
+
+pub async fn transaction_metadata_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let mut tx = Transaction::new().with_metadata(b"actor=ecton;reason=import".to_vec());
+    Basic::new("annotated").push_in_transaction(&mut tx)?;
+    tx.apply_async(db).await?;
+    Basic::new("unannotated").push_into_async(db).await?;
+
+    let transactions = db.list_executed_transactions(None, None).await?;
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(
+        transactions[0].metadata.as_deref().map(Vec::as_slice),
+        Some(&b"actor=ecton;reason=import"[..])
+    );
+    assert!(transactions[1].metadata.is_none());
+
+    Ok(())
+}
+
+pub fn blocking_transaction_metadata_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let mut tx = Transaction::new().with_metadata(b"actor=ecton;reason=import".to_vec());
+    Basic::new("annotated").push_in_transaction(&mut tx)?;
+    tx.apply(db)?;
+    Basic::new("unannotated").push_into(db)?;
+
+    let transactions = db.list_executed_transactions(None, None)?;
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(
+        transactions[0].metadata.as_deref().map(Vec::as_slice),
+        Some(&b"actor=ecton;reason=import"[..])
+    );
+    assert!(transactions[1].metadata.is_none());
+
+    Ok(())
+}
+
+pub async fn view_keys_only_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let mut a_ids = Vec::new();
+    for category in ["a", "b", "a"] {
+        let doc = Basic::new("keys-only")
+            .with_category(category)
+            .push_into_async(db)
+            .await?;
+        if category == "a" {
+            a_ids.push(doc.header.id);
+        }
+    }
+
+    let keys = db.view::<BasicByCategory>().keys_only().await?;
+    let mappings = db.view::<BasicByCategory>().query().await?;
+    assert_eq!(keys.len(), 3);
+    for (key, mapping) in keys.iter().zip(&mappings) {
+        assert_eq!(key.key, mapping.key);
+        assert_eq!(key.source, mapping.source);
+    }
+
+    let keys = db
+        .view::<BasicByCategory>()
+        .with_key("a")
+        .keys_only()
+        .await?;
+    let mut ids = keys
+        .iter()
+        .map(|mapping| {
+            assert_eq!(mapping.key, "a");
+            mapping.source.id.deserialize::<u64>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    ids.sort();
+    assert_eq!(ids, a_ids);
+
+    let keys = db
+        .view::<BasicByCategory>()
+        .descending()
+        .limit(1)
+        .keys_only()
+        .await?;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key, "b");
+
+    Ok(())
+}
+
+pub fn blocking_view_keys_only_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let mut a_ids = Vec::new();
+    for category in ["a", "b", "a"] {
+        let doc = Basic::new("keys-only")
+            .with_category(category)
+            .push_into(db)?;
+        if category == "a" {
+            a_ids.push(doc.header.id);
+        }
+    }
+
+    let keys = db.view::<BasicByCategory>().keys_only()?;
+    let mappings = db.view::<BasicByCategory>().query()?;
+    assert_eq!(keys.len(), 3);
+    for (key, mapping) in keys.iter().zip(&mappings) {
+        assert_eq!(key.key, mapping.key);
+        assert_eq!(key.source, mapping.source);
+    }
+
+    let keys = db.view::<BasicByCategory>().with_key("a").keys_only()?;
+    let mut ids = keys
+        .iter()
+        .map(|mapping| {
+            assert_eq!(mapping.key, "a");
+            mapping.source.id.deserialize::<u64>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    ids.sort();
+    assert_eq!(ids, a_ids);
+
+    let keys = db
+        .view::<BasicByCategory>()
+        .descending()
+        .limit(1)
+        .keys_only()?;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key, "b");
+
+    Ok(())
+}
+
+pub async fn document_refresh_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let id = Basic::new("initial").push_into_async(db).await?.header.id;
+    let mut stale = db.get::<Basic, _>(&id).await?.expect("doc not found");
+
+    // Refreshing an up-to-date document reports no change.
+    assert!(!db.refresh::<Basic, _>(&mut stale).await?);
+
+    let mut doc = Basic::get_async(&id, db).await?.expect("doc not found");
+    doc.contents.value = String::from("updated");
+    doc.update_async(db).await?;
+
+    assert!(db.refresh::<Basic, _>(&mut stale).await?);
+    assert_eq!(stale.header.revision, doc.header.revision);
+    let contents = <Basic as SerializedCollection>::deserialize(&stale.contents)?;
+    assert_eq!(contents.value, "updated");
+
+    doc.delete_async(db).await?;
+    assert!(matches!(
+        db.refresh::<Basic, _>(&mut stale).await,
+        Err(Error::DocumentNotFound(..))
+    ));
+
+    Ok(())
+}
+
+pub fn blocking_document_refresh_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let id = Basic::new("initial").push_into(db)?.header.id;
+    let mut stale = db.get::<Basic, _>(&id)?.expect("doc not found");
+
+    // Refreshing an up-to-date document reports no change.
+    assert!(!db.refresh::<Basic, _>(&mut stale)?);
+
+    let mut doc = Basic::get(&id, db)?.expect("doc not found");
+    doc.contents.value = String::from("updated");
+    doc.update(db)?;
+
+    assert!(db.refresh::<Basic, _>(&mut stale)?);
+    assert_eq!(stale.header.revision, doc.header.revision);
+    let contents = <Basic as SerializedCollection>::deserialize(&stale.contents)?;
+    assert_eq!(contents.value, "updated");
+
+    doc.delete(db)?;
+    assert!(matches!(
+        db.refresh::<Basic, _>(&mut stale),
+        Err(Error::DocumentNotFound(..))
+    ));
+
+    Ok(())
+}
+
+pub async fn document_exists_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let doc = Basic::new("exists").push_into_async(db).await?;
+    let collection = db.collection::<Basic>();
+    assert!(collection.exists(&doc.header.id).await?);
+    assert!(!collection.exists(&(doc.header.id + 1)).await?);
+    // The low-level API only ever produces a bool, never the document.
+    assert!(
+        db.exists_in_collection(DocumentId::new(&doc.header.id)?, &Basic::collection_name())
+            .await?
+    );
+
+    doc.delete_async(db).await?;
+    assert!(!collection.exists(&doc.header.id).await?);
+
+    Ok(())
+}
+
+pub fn blocking_document_exists_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let doc = Basic::new("exists").push_into(db)?;
+    let collection = db.collection::<Basic>();
+    assert!(collection.exists(&doc.header.id)?);
+    assert!(!collection.exists(&(doc.header.id + 1))?);
+    // The low-level API only ever produces a bool, never the document.
+    assert!(db.exists_in_collection(DocumentId::new(&doc.header.id)?, &Basic::collection_name())?);
+
+    doc.delete(db)?;
+    assert!(!collection.exists(&doc.header.id)?);
+
+    Ok(())
+}
+
+pub async fn document_tombstones_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let doc = Basic::new("retained").push_into_async(db).await?;
+    db.soft_delete::<Basic, _>(&doc).await?;
+    assert!(Basic::get_async(&doc.header.id, db).await?.is_none());
+
+    let tombstone = db
+        .get_tombstone::<Basic, _>(&doc.header.id)
+        .await?
+        .expect("tombstone not retained");
+    assert_eq!(Basic::document_contents(&tombstone)?.value, "retained");
+
+    assert_eq!(db.purge_tombstones(Timestamp::MIN).await?, 0);
+    assert_eq!(db.purge_tombstones(Timestamp::MAX).await?, 1);
+    assert!(db
+        .get_tombstone::<Basic, _>(&doc.header.id)
+        .await?
+        .is_none());
+
+    Ok(())
+}
+
+pub fn blocking_document_tombstones_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let doc = Basic::new("retained").push_into(db)?;
+    db.soft_delete::<Basic, _>(&doc)?;
+    assert!(Basic::get(&doc.header.id, db)?.is_none());
+
+    let tombstone = db
+        .get_tombstone::<Basic, _>(&doc.header.id)?
+        .expect("tombstone not retained");
+    assert_eq!(Basic::document_contents(&tombstone)?.value, "retained");
+
+    assert_eq!(db.purge_tombstones(Timestamp::MIN)?, 0);
+    assert_eq!(db.purge_tombstones(Timestamp::MAX)?, 1);
+    assert!(db.get_tombstone::<Basic, _>(&doc.header.id)?.is_none());
+
+    Ok(())
+}
+
+pub async fn push_with_id_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let collection = db.collection::<Basic>();
+    let header = collection
+        .push_with_id(&42, &Basic::new("imported"))
+        .await?;
+    assert_eq!(header.id, 42);
+    let doc = Basic::get_async(&42, db)
+        .await?
+        .expect("document not found");
+    assert_eq!(doc.contents.value, "imported");
+
+    // Inserting at an id that is already taken is a conflict.
+    let conflict = collection
+        .push_with_id(&42, &Basic::new("duplicate"))
+        .await
+        .unwrap_err();
+    assert!(matches!(conflict, Error::DocumentConflict(..)));
+
+    // Deleting the document with the largest id doesn't allow its id to be
+    // assigned to another document.
+    doc.delete_async(db).await?;
+    let pushed = collection.push(&Basic::new("pushed")).await?;
+    assert_eq!(pushed.id, 43);
+
+    Ok(())
+}
+
+pub fn blocking_push_with_id_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let collection = db.collection::<Basic>();
+    let header = collection.push_with_id(&42, &Basic::new("imported"))?;
+    assert_eq!(header.id, 42);
+    let doc = Basic::get(&42, db)?.expect("document not found");
+    assert_eq!(doc.contents.value, "imported");
+
+    // Inserting at an id that is already taken is a conflict.
+    let conflict = collection
+        .push_with_id(&42, &Basic::new("duplicate"))
+        .unwrap_err();
+    assert!(matches!(conflict, Error::DocumentConflict(..)));
+
+    // Deleting the document with the largest id doesn't allow its id to be
+    // assigned to another document.
+    doc.delete(db)?;
+    let pushed = collection.push(&Basic::new("pushed"))?;
+    assert_eq!(pushed.id, 43);
+
+    Ok(())
+}
+
+/// The key bounds checked by the view key bounds tests, paired with the keys
+/// each is expected to match from the keys `a` through `e`.
+#[allow(clippy::type_complexity)]
+const VIEW_KEY_BOUNDS: &[(
+    crate::connection::Bound<&str>,
+    crate::connection::Bound<&str>,
+    &[&str],
+)] = {
+    use crate::connection::Bound::{Excluded, Included, Unbounded};
+    &[
+        (Included("b"), Included("d"), &["b", "c", "d"]),
+        (Included("b"), Excluded("d"), &["b", "c"]),
+        (Excluded("b"), Included("d"), &["c", "d"]),
+        (Excluded("b"), Excluded("d"), &["c"]),
+        (Excluded("b"), Unbounded, &["c", "d", "e"]),
+        (Unbounded, Included("b"), &["a", "b"]),
+        (Unbounded, Excluded("b"), &["a"]),
+        (Excluded("c"), Excluded("d"), &[]),
+        (Unbounded, Unbounded, &["a", "b", "c", "d", "e"]),
+    ]
+};
+
+pub async fn view_key_bounds_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    for category in ["a", "b", "c", "d", "e"] {
+        Basic::new("bounds")
+            .with_category(category)
+            .push_into_async(db)
+            .await?;
+    }
+
+    for (start, end, expected) in VIEW_KEY_BOUNDS {
+        let keys = db
+            .view::<BasicByCategory>()
+            .with_key_bounds(*start, *end)
+            .keys_only()
+            .await?
+            .into_iter()
+            .map(|mapping| mapping.key)
+            .collect::<Vec<_>>();
+        assert_eq!(&keys, expected, "{start:?} to {end:?}");
+
+        let mut keys = db
+            .view::<BasicByCategory>()
+            .with_key_bounds(*start, *end)
+            .descending()
+            .keys_only()
+            .await?
+            .into_iter()
+            .map(|mapping| mapping.key)
+            .collect::<Vec<_>>();
+        keys.reverse();
+        assert_eq!(&keys, expected, "{start:?} to {end:?} descending");
+    }
+
+    Ok(())
+}
+
+pub fn blocking_view_key_bounds_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    for category in ["a", "b", "c", "d", "e"] {
+        Basic::new("bounds").with_category(category).push_into(db)?;
+    }
+
+    for (start, end, expected) in VIEW_KEY_BOUNDS {
+        let keys = db
+            .view::<BasicByCategory>()
+            .with_key_bounds(*start, *end)
+            .keys_only()?
+            .into_iter()
+            .map(|mapping| mapping.key)
+            .collect::<Vec<_>>();
+        assert_eq!(&keys, expected, "{start:?} to {end:?}");
+
+        let mut keys = db
+            .view::<BasicByCategory>()
+            .with_key_bounds(*start, *end)
+            .descending()
+            .keys_only()?
+            .into_iter()
+            .map(|mapping| mapping.key)
+            .collect::<Vec<_>>();
+        keys.reverse();
+        assert_eq!(&keys, expected, "{start:?} to {end:?} descending");
+    }
+
+    Ok(())
+}
+
+pub fn schema_introspection_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    check_schema_introspection(
+        &db.collection_names(),
+        |collection| db.view_names(collection),
+        &db.schema_summary(),
+    );
+    Ok(())
+}
+
+pub fn blocking_schema_introspection_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    check_schema_introspection(
+        &db.collection_names(),
+        |collection| db.view_names(collection),
+        &db.schema_summary(),
+    );
+    Ok(())
+}
+
+fn check_schema_introspection(
+    collections: &[CollectionName],
+    view_names: impl Fn(&CollectionName) -> Vec<ViewName>,
+    summary: &SchemaSummary,
+) {
+    assert!(collections.contains(&Basic::collection_name()));
+    assert!(collections.contains(&Unique::collection_name()));
+    assert!(collections.windows(2).all(|pair| pair[0] < pair[1]));
+
+    assert_eq!(
+        view_names(&Unique::collection_name()),
+        vec![UniqueValue.view_name()]
+    );
+    assert!(view_names(&Basic::collection_name()).contains(&BasicByCategory.view_name()));
+    assert!(view_names(&UnassociatedCollection::collection_name()).is_empty());
+
+    assert_eq!(summary.name, BasicSchema::schema_name());
+    let unique = summary
+        .collection(&Unique::collection_name())
+        .expect("unique collection not found");
+    let unique_value = unique
+        .view(&UniqueValue.view_name())
+        .expect("unique view not found");
+    assert_eq!(unique_value.policy, ViewUpdatePolicy::Unique);
+}
+
+pub async fn transaction_retry_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()> {
+    let id = Basic::new("initial").push_into_async(db).await?.header.id;
+    // Updates the document, simulating another client changing it after the
+    // transaction has read it on the first attempt.
+    let update_with_conflict = |attempt: usize| async move {
+        let mut doc = Basic::get_async(&id, db).await?.expect("doc not found");
+        if attempt == 1 {
+            let mut concurrent = doc.clone();
+            concurrent.contents.value = format!("concurrent {}", concurrent.contents.value);
+            concurrent.update_async(db).await?;
+        }
+        doc.contents.category = Some(String::from("retried"));
+        let mut tx = Transaction::new();
+        doc.update_in_transaction(&mut tx)?;
+        Ok::<_, Error>(tx)
+    };
+
+    let mut attempts = 0;
+    let result = db
+        .transaction(1, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move { Ok::<_, Error>((update_with_conflict(attempt).await?, attempt)) }
+        })
+        .await?;
+    assert_eq!(result, 2);
+    let doc = Basic::get_async(&id, db).await?.expect("doc not found");
+    assert_eq!(doc.contents.value, "concurrent initial");
+    assert_eq!(doc.contents.category.as_deref(), Some("retried"));
+
+    // Without any retries, the conflict is returned.
+    let mut attempts = 0;
+    let result = db
+        .transaction(0, || {
+            attempts += 1;
+            let attempt = attempts;
+            async move { Ok::<_, Error>((update_with_conflict(attempt).await?, ())) }
+        })
+        .await;
+    assert!(matches!(result, Err(Error::DocumentConflict(..))));
+    assert_eq!(attempts, 1);
+
+    Ok(())
+}
+
+pub fn blocking_transaction_retry_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
+    let id = Basic::new("initial").push_into(db)?.header.id;
+    // Updates the document, simulating another client changing it after the
+    // transaction has read it on the first attempt.
+    let update_with_conflict = |tx: &mut Transaction, attempt: usize| {
+        let mut doc = Basic::get(&id, db)?.expect("doc not found");
+        if attempt == 1 {
+            let mut concurrent = doc.clone();
+            concurrent.contents.value = format!("concurrent {}", concurrent.contents.value);
+            concurrent.update(db)?;
+        }
+        doc.contents.category = Some(String::from("retried"));
+        doc.update_in_transaction(tx)
+    };
+
+    let mut attempts = 0;
+    let result = db.transaction(1, |tx| {
+        attempts += 1;
+        update_with_conflict(tx, attempts)?;
+        Ok(attempts)
+    })?;
+    assert_eq!(result, 2);
+    let doc = Basic::get(&id, db)?.expect("doc not found");
+    assert_eq!(doc.contents.value, "concurrent initial");
+    assert_eq!(doc.contents.category.as_deref(), Some("retried"));
+
+    // Without any retries, the conflict is returned.
+    let mut attempts = 0;
+    let result = db.transaction(0, |tx| {
+        attempts += 1;
+        update_with_conflict(tx, attempts)
+    });
+    assert!(matches!(result, Err(Error::DocumentConflict(..))));
+    assert_eq!(attempts, 1);
+
+    Ok(())
+}
+
+pub async fn transaction_tests<C: AsyncConnection + 'static>(db: &C) -> anyhow::Result<()> {
+    let mut tx = Transaction::new();
+    Basic::new("test").push_in_transaction(&mut tx)?;
+    let results = tx.apply_async(db).await?;
+    let OperationResult::DocumentUpdated { header, .. } = &results[0] else { unreachable!("unexpected tx result") };
+    let id: u64 = header.id.deserialize()?;
+
+    // Update the doc
+    let mut tx = Transaction::new();
+    let mut doc = Basic::get_async(&id, db).await?.expect("doc not found");
+    doc.contents.category = Some(String::from("cat"));
+    doc.update_in_transaction(&mut tx)?;
+    tx.apply_async(db).await?;
+    let doc = Basic::get_async(&id, db).await?.expect("doc not found");
+    assert_eq!(doc.contents.category.as_deref(), Some("cat"));
+
+    // Overwrite the document.
+    let mut tx = Transaction::new();
+    Basic::new("test").overwrite_in_transaction(&id, &mut tx)?;
+    tx.apply_async(db).await?;
+    let doc = Basic::get_async(&id, db).await?.expect("doc not found");
+    assert_eq!(doc.contents.category, None);
+
+    // Delete the document
+    let mut tx = Transaction::new();
+    doc.delete_in_transaction(&mut tx)?;
+    tx.apply_async(db).await?;
+
+    Ok(())
+}
+
+pub fn blocking_transaction_tests<C: Connection + 'static>(db: &C) -> anyhow::Result<()> {
+    let mut tx = Transaction::new();
+    Basic::new("test").push_in_transaction(&mut tx)?;
+    let results = tx.apply(db)?;
+    let OperationResult::DocumentUpdated { header, .. } = &results[0] else { unreachable!("unexpected tx result") };
+    let id: u64 = header.id.deserialize()?;
 
     // Update the doc
     let mut tx = Transaction::new();
@@ -1767,7 +3051,7 @@ pub async fn transaction_check_tests<C: AsyncConnection + 'static>(db: &C) -> an
     )?);
     tx.push(Operation::check_document_exists::<Basic>(&42)?);
     let result = tx.apply_async(db).await.unwrap_err();
-    assert!(matches!(result, Error::DocumentConflict(_, _)));
+    assert!(matches!(result, Error::DocumentConflict(..)));
 
     let mut tx = Transaction::new();
     tx.push(Operation::check_document_exists::<Basic>(&42)?);
@@ -1803,7 +3087,7 @@ pub fn blocking_transaction_check_tests<C: Connection + 'static>(db: &C) -> anyh
     )?);
     tx.push(Operation::check_document_exists::<Basic>(&42)?);
     let result = tx.apply(db).unwrap_err();
-    assert!(matches!(result, Error::DocumentConflict(_, _)));
+    assert!(matches!(result, Error::DocumentConflict(..)));
 
     let mut tx = Transaction::new();
     tx.push(Operation::check_document_exists::<Basic>(&42)?);
@@ -2122,6 +3406,8 @@ pub async fn view_update_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()>
         0
     );
     assert_eq!(db.view::<BasicByParentId>().reduce().await?, 3);
+    // Without a key, the reduction covers every document in the collection.
+    assert_eq!(db.view::<BasicCount>().reduce().await?, 3);
 
     // Update the record, but don't change its mapping. Ensure the source's
     // header is updated.
@@ -2230,6 +3516,8 @@ pub fn blocking_view_update_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
         0
     );
     assert_eq!(db.view::<BasicByParentId>().reduce()?, 3);
+    // Without a key, the reduction covers every document in the collection.
+    assert_eq!(db.view::<BasicCount>().reduce()?, 3);
 
     // Update the record, but don't change its mapping. Ensure the source's
     // header is updated.
@@ -2551,6 +3839,15 @@ pub async fn unique_view_tests<C: AsyncConnection>(db: &C) -> anyhow::Result<()>
         unreachable!("unique key violation not triggered");
     }
 
+    // Pushing the same key twice only creates a single document.
+    let inserted = Unique::push_if_absent_async::<UniqueValue, _>(&Unique::new("3"), db).await?;
+    let existing = Unique::push_if_absent_async::<UniqueValue, _>(&Unique::new("3"), db).await?;
+    assert_eq!(inserted, existing);
+    assert_eq!(
+        db.view::<UniqueValue>().with_key("3").query().await?.len(),
+        1
+    );
+
     Ok(())
 }
 
@@ -2591,6 +3888,12 @@ pub fn blocking_unique_view_tests<C: Connection>(db: &C) -> anyhow::Result<()> {
         unreachable!("unique key violation not triggered");
     }
 
+    // Pushing the same key twice only creates a single document.
+    let inserted = Unique::push_if_absent::<UniqueValue, _>(&Unique::new("3"), db)?;
+    let existing = Unique::push_if_absent::<UniqueValue, _>(&Unique::new("3"), db)?;
+    assert_eq!(inserted, existing);
+    assert_eq!(db.view::<UniqueValue>().with_key("3").query()?.len(), 1);
+
     Ok(())
 }
 
@@ -3229,6 +4532,42 @@ macro_rules! define_async_kv_test_suite {
                 Ok(())
             }
 
+            #[tokio::test]
+            async fn kv_decimal_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{AsyncKeyValue, Decimal, DecimalError};
+                let harness = $harness::new($crate::test_util::HarnessTest::KvDecimal).await?;
+                let db = harness.connect().await?;
+                let kv = db.with_key_namespace("decimal");
+
+                // 0.10 can't be represented exactly as a float, but ten
+                // increments should add up to exactly 1.00.
+                for _ in 0..10 {
+                    kv.increment_key_by("balance", Decimal::new(10, 2)).await?;
+                }
+                assert_eq!(kv.get_key("balance").into_i64().await?, Some(1));
+                assert_eq!(
+                    kv.decrement_key_by("balance", Decimal::new(1, 2)).await?,
+                    Decimal::new(99, 2)
+                );
+
+                // Mixing scales is rejected and leaves the value untouched.
+                assert!(matches!(
+                    kv.increment_key_by("balance", Decimal::new(1, 3)).await,
+                    Err(bonsaidb_core::Error::Decimal(DecimalError::ScaleMismatch {
+                        expected: 2,
+                        actual: 3
+                    }))
+                ));
+                assert_eq!(
+                    kv.increment_key_by("balance", Decimal::new(0, 2)).await?,
+                    Decimal::new(99, 2)
+                );
+
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
             #[tokio::test]
             async fn kv_expiration_tests() -> anyhow::Result<()> {
                 use std::time::Duration;
@@ -3275,79 +4614,437 @@ macro_rules! define_async_kv_test_suite {
                         continue;
                     }
 
-                    assert_eq!(r1?, KeyStatus::Updated, "a wasn't an update");
-                    assert_eq!(r2?, KeyStatus::Updated, "b wasn't an update");
+                    assert_eq!(r1?, KeyStatus::Updated, "a wasn't an update");
+                    assert_eq!(r2?, KeyStatus::Updated, "b wasn't an update");
+
+                    let a = kv.get_key("a").into().await?;
+                    assert_eq!(a, Some(1u32), "a shouldn't have expired yet");
+
+                    // Before checking the value, make sure we haven't elapsed too
+                    // much time. If so, just restart the test.
+                    if !timing.wait_until_async(Duration::from_secs_f32(3.)).await {
+                        println!(
+                            "Restarting test {}. Took too long {:?}",
+                            line!(),
+                            timing.elapsed()
+                        );
+                        continue;
+                    }
+
+                    assert_eq!(kv.get_key("b").await?, None, "b never expired");
+
+                    timing.wait_until_async(Duration::from_secs_f32(5.)).await;
+                    assert_eq!(kv.get_key("a").await?, None, "a never expired");
+                    break;
+                }
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn delete_expire_tests() -> anyhow::Result<()> {
+                use std::time::Duration;
+
+                use $crate::keyvalue::{AsyncKeyValue, KeyStatus};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvDeleteExpire).await?;
+                let db = harness.connect().await?;
+
+                loop {
+                    let kv = db.with_key_namespace("delete_expire");
+
+                    kv.delete_key("a").await?;
+
+                    let timing = $crate::test_util::TimingTest::new(Duration::from_millis(100));
+
+                    // Create a key with an expiration. Delete the key. Set a new
+                    // value at that key with no expiration. Ensure it doesn't
+                    // expire.
+                    kv.set_key("a", &0_u32)
+                        .expire_in(Duration::from_secs(2))
+                        .await?;
+                    kv.delete_key("a").await?;
+                    kv.set_key("a", &1_u32).await?;
+                    if timing.elapsed() > Duration::from_secs(1) {
+                        println!(
+                            "Restarting test {}. Took too long {:?}",
+                            line!(),
+                            timing.elapsed(),
+                        );
+                        continue;
+                    }
+                    if !timing.wait_until_async(Duration::from_secs_f32(2.5)).await {
+                        println!(
+                            "Restarting test {}. Took too long {:?}",
+                            line!(),
+                            timing.elapsed()
+                        );
+                        continue;
+                    }
+
+                    assert_eq!(kv.get_key("a").into().await?, Some(1u32));
+
+                    break;
+                }
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn kv_lease_tests() -> anyhow::Result<()> {
+                use std::time::Duration;
+
+                use $crate::keyvalue::{AsyncKeyValue, Lease, Timestamp};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvLease).await?;
+                let db = harness.connect().await?;
+                let kv = db.with_key_namespace("lease");
+
+                let mut lease = kv
+                    .acquire_lease("lock", "a", Duration::from_secs(60))
+                    .await?
+                    .expect("lock should be vacant");
+                assert_eq!(lease.owner, "a");
+                assert!(kv
+                    .acquire_lease("lock", "b", Duration::from_secs(60))
+                    .await?
+                    .is_none());
+
+                let original_expiration = lease.expiration;
+                assert!(kv.renew_lease(&mut lease, Duration::from_secs(120)).await?);
+                assert!(lease.expiration > original_expiration);
+
+                // Only the owner can release the lease.
+                let other_owner = Lease {
+                    owner: String::from("b"),
+                    ..lease.clone()
+                };
+                assert!(!kv.release_lease(other_owner).await?);
+                assert!(kv.release_lease(lease).await?);
+                assert_eq!(kv.get_key("lock").await?, None);
+
+                // A lease that isn't renewed expires, allowing another owner to
+                // acquire it. The expired key is checked when it is accessed,
+                // so this doesn't depend on when expired keys are removed.
+                let mut stale = kv
+                    .acquire_lease("stale", "a", Duration::from_secs(60))
+                    .await?
+                    .expect("stale should be vacant");
+                kv.expire_at("stale", Timestamp::MIN).await?;
+                assert!(!kv.renew_lease(&mut stale, Duration::from_secs(60)).await?);
+                let fresh = kv
+                    .acquire_lease("stale", "b", Duration::from_secs(60))
+                    .await?
+                    .expect("stale lease should have expired");
+                assert!(!kv.release_lease(stale).await?);
+                assert!(kv.release_lease(fresh).await?);
+
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn kv_expiring_between_tests() -> anyhow::Result<()> {
+                use std::time::Duration;
+
+                use $crate::keyvalue::{AsyncKeyValue, Timestamp};
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::KvExpiringBetween).await?;
+                let db = harness.connect().await?;
+                let start = Timestamp::now();
+                db.set_key("soon", &0_u32)
+                    .expire_in(Duration::from_secs(60))
+                    .await?;
+                db.set_key("later", &0_u32)
+                    .expire_in(Duration::from_secs(7200))
+                    .await?;
+                db.set_key("never", &0_u32).await?;
+                db.with_key_namespace("other")
+                    .set_key("sooner", &0_u32)
+                    .expire_in(Duration::from_secs(30))
+                    .await?;
+
+                let end = Timestamp::now() + Duration::from_secs(3600);
+                let expiring = db.keys_expiring_between(start, end).await?;
+                let expiring = expiring
+                    .iter()
+                    .map(|key| (key.namespace.as_deref(), key.key.as_str()))
+                    .collect::<Vec<_>>();
+                assert_eq!(expiring, [(Some("other"), "sooner"), (None, "soon")]);
+
+                // Namespaced stores only return keys in their namespace.
+                let expiring = db
+                    .with_key_namespace("other")
+                    .keys_expiring_between(start, end)
+                    .await?;
+                assert_eq!(expiring.len(), 1);
+                assert_eq!(expiring[0].key, "sooner");
+
+                // Removing an expiration removes the key from the results.
+                db.set_key("soon", &0_u32).await?;
+                let expiring = db.keys_expiring_between(start, end).await?;
+                assert_eq!(expiring.len(), 1);
+                assert_eq!(expiring[0].key, "sooner");
+
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn kv_key_expirations_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{AsyncKeyValue, Timestamp};
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::KvKeyExpirations).await?;
+                let db = harness.connect().await?;
+                let soon = Timestamp::now() + std::time::Duration::from_secs(60);
+                let later = Timestamp::now() + std::time::Duration::from_secs(7200);
+                for key in ["soon", "later", "never"] {
+                    db.set_key(key, &0_u32).await?;
+                }
+                db.expire_at("soon", soon).await?;
+                db.expire_at("later", later).await?;
+                let other = db.with_key_namespace("other");
+                other.set_key("soon", &0_u32).await?;
+                other.expire_at("soon", later).await?;
+
+                let expirations = db
+                    .key_expirations(["later", "never", "missing", "soon"])
+                    .await?;
+                assert_eq!(
+                    expirations,
+                    [
+                        (String::from("later"), Some(later)),
+                        (String::from("never"), None),
+                        (String::from("missing"), None),
+                        (String::from("soon"), Some(soon)),
+                    ]
+                );
+
+                // Keys are looked up in the store's namespace.
+                let expirations = other.key_expirations(["soon", "never"]).await?;
+                assert_eq!(
+                    expirations,
+                    [(String::from("soon"), Some(later)), (String::from("never"), None)]
+                );
+
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn kv_namespace_scan_tests() -> anyhow::Result<()> {
+                use std::ops::ControlFlow;
+
+                use $crate::keyvalue::AsyncKeyValue;
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::KvNamespaceScan).await?;
+                let db = harness.connect().await?;
+                let scanned = db.with_key_namespace("scanned");
+                for key in ["c", "a", "b"] {
+                    scanned.set_key(key, &0_u32).await?;
+                }
+                db.set_key("a", &0_u32).await?;
+
+                let mut keys = Vec::new();
+                db.scan_key_value_namespace(Some("scanned"), |key, _| {
+                    keys.push(key.to_string());
+                    ControlFlow::Continue(())
+                })
+                .await?;
+                assert_eq!(keys, ["a", "b", "c"]);
+
+                let batch = db
+                    .execute_namespace_scan(
+                        Some(String::from("scanned")),
+                        Some(String::from("a")),
+                        1,
+                    )
+                    .await?;
+                assert_eq!(batch.len(), 1);
+                assert_eq!(batch[0].0, "b");
+
+                harness.shutdown().await?;
 
-                    let a = kv.get_key("a").into().await?;
-                    assert_eq!(a, Some(1u32), "a shouldn't have expired yet");
+                Ok(())
+            }
 
-                    // Before checking the value, make sure we haven't elapsed too
-                    // much time. If so, just restart the test.
-                    if !timing.wait_until_async(Duration::from_secs_f32(3.)).await {
-                        println!(
-                            "Restarting test {}. Took too long {:?}",
-                            line!(),
-                            timing.elapsed()
-                        );
-                        continue;
-                    }
+            #[tokio::test]
+            async fn kv_pipeline_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{
+                    AsyncKeyValue, KeyStatus, Numeric, Output, PipelineCondition,
+                };
 
-                    assert_eq!(kv.get_key("b").await?, None, "b never expired");
+                let harness = $harness::new($crate::test_util::HarnessTest::KvPipeline).await?;
+                let db = harness.connect().await?;
 
-                    timing.wait_until_async(Duration::from_secs_f32(5.)).await;
-                    assert_eq!(kv.get_key("a").await?, None, "a never expired");
-                    break;
+                // Increment a counter, setting a flag once it reaches 3.
+                for expected in 1_u64..=3 {
+                    let outputs = db
+                        .pipeline()
+                        .increment_key_by("counter", 1_u64)
+                        .when(PipelineCondition::AtLeast {
+                            step: 0,
+                            value: Numeric::UnsignedInteger(3),
+                        })
+                        .set_numeric_key("flag", 1_u64)
+                        .await?;
+                    assert_eq!(outputs.len(), 2);
+                    assert!(matches!(
+                        &outputs[0],
+                        Some(Output::Value(Some(value))) if value.as_u64() == Some(expected)
+                    ));
+                    if expected < 3 {
+                        assert!(outputs[1].is_none());
+                        assert_eq!(db.get_key("flag").await?, None);
+                    } else {
+                        assert!(matches!(
+                            outputs[1],
+                            Some(Output::Status(KeyStatus::Inserted))
+                        ));
+                        assert_eq!(db.get_key("flag").into_u64().await?, Some(1));
+                    }
                 }
+
+                // When a step fails, the changes made by previous steps are undone.
+                db.set_key("text", &"hello").await?;
+                assert!(db
+                    .pipeline()
+                    .increment_key_by("counter", 1_u64)
+                    .delete_key("flag")
+                    .increment_key_by("text", 1_u64)
+                    .await
+                    .is_err());
+                assert_eq!(db.get_key("counter").into_u64().await?, Some(3));
+                assert_eq!(db.get_key("flag").into_u64().await?, Some(1));
+
                 harness.shutdown().await?;
 
                 Ok(())
             }
 
             #[tokio::test]
-            async fn delete_expire_tests() -> anyhow::Result<()> {
-                use std::time::Duration;
+            async fn kv_get_bytes_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{AsyncKeyValue, Value};
 
-                use $crate::keyvalue::{AsyncKeyValue, KeyStatus};
+                let harness = $harness::new($crate::test_util::HarnessTest::KvGetBytes).await?;
+                let db = harness.connect().await?;
 
-                let harness = $harness::new($crate::test_util::HarnessTest::KvDeleteExpire).await?;
+                db.set_key("akey", &String::from("hello")).await?;
+                let value = db.get_key("akey").await?.expect("key not found");
+                let bytes = db.get_key("akey").into_bytes().await?.expect("key not found");
+                assert!(matches!(&value, Value::Bytes(stored) if stored[..] == bytes[..]));
+                assert_eq!(value.deserialize::<String>()?, "hello");
+
+                assert_eq!(db.get_key("missing").into_bytes().await?, None);
+                db.set_numeric_key("numeric", 1_u64).await?;
+                assert!(db.get_key("numeric").into_bytes().await.is_err());
+
+                let bytes = db.get_key("akey").and_delete().into_bytes().await?;
+                assert!(bytes.is_some());
+                assert_eq!(db.get_key("akey").await?, None);
+
+                harness.shutdown().await?;
+
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn kv_bounded_increment_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::AsyncKeyValue;
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::KvBoundedIncrement).await?;
                 let db = harness.connect().await?;
 
-                loop {
-                    let kv = db.with_key_namespace("delete_expire");
+                // Incrementing up to the limit doesn't report reaching it.
+                assert_eq!(
+                    db.increment_key_by("counter", 2_u64)
+                        .limit(3)
+                        .execute_bounded()
+                        .await?,
+                    (2, false)
+                );
+                // Incrementing past the limit stores the limit.
+                assert_eq!(
+                    db.increment_key_by("counter", 2_u64)
+                        .limit(3)
+                        .execute_bounded()
+                        .await?,
+                    (3, true)
+                );
+                assert_eq!(db.increment_key_by("counter", 1_u64).limit(3).await?, 3);
+                assert_eq!(db.get_key("counter").into_u64().await?, Some(3));
 
-                    kv.delete_key("a").await?;
+                // Decrementing is limited by a floor.
+                assert_eq!(
+                    db.decrement_key_by("counter", 2_i64)
+                        .limit(1)
+                        .execute_bounded()
+                        .await?,
+                    (1, false)
+                );
+                assert_eq!(
+                    db.decrement_key_by("counter", 2_i64)
+                        .limit(0)
+                        .execute_bounded()
+                        .await?,
+                    (0, true)
+                );
+                assert_eq!(db.get_key("counter").into_i64().await?, Some(0));
 
-                    let timing = $crate::test_util::TimingTest::new(Duration::from_millis(100));
+                harness.shutdown().await?;
 
-                    // Create a key with an expiration. Delete the key. Set a new
-                    // value at that key with no expiration. Ensure it doesn't
-                    // expire.
-                    kv.set_key("a", &0_u32)
-                        .expire_in(Duration::from_secs(2))
+                Ok(())
+            }
+
+            #[tokio::test]
+            async fn kv_increment_with_previous_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::AsyncKeyValue;
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::KvIncrementWithPrevious)
                         .await?;
-                    kv.delete_key("a").await?;
-                    kv.set_key("a", &1_u32).await?;
-                    if timing.elapsed() > Duration::from_secs(1) {
-                        println!(
-                            "Restarting test {}. Took too long {:?}",
-                            line!(),
-                            timing.elapsed(),
-                        );
-                        continue;
-                    }
-                    if !timing.wait_until_async(Duration::from_secs_f32(2.5)).await {
-                        println!(
-                            "Restarting test {}. Took too long {:?}",
-                            line!(),
-                            timing.elapsed()
-                        );
-                        continue;
-                    }
+                let db = harness.connect().await?;
 
-                    assert_eq!(kv.get_key("a").into().await?, Some(1u32));
+                db.set_numeric_key("counter", 5_u64).await?;
+                assert_eq!(
+                    db.increment_key_by("counter", 2_u64)
+                        .execute_with_previous()
+                        .await?,
+                    (5, 7)
+                );
+                assert_eq!(
+                    db.decrement_key_by("counter", 3_u64)
+                        .execute_with_previous()
+                        .await?,
+                    (7, 4)
+                );
+                // Limits apply to the new value.
+                assert_eq!(
+                    db.increment_key_by("counter", 10_u64)
+                        .limit(6)
+                        .execute_with_previous()
+                        .await?,
+                    (4, 6)
+                );
+                assert_eq!(db.get_key("counter").into_u64().await?, Some(6));
+
+                // A missing key starts at zero.
+                assert_eq!(
+                    db.increment_key_by("missing", 1_u64)
+                        .execute_with_previous()
+                        .await?,
+                    (0, 1)
+                );
 
-                    break;
-                }
                 harness.shutdown().await?;
 
                 Ok(())
@@ -3689,6 +5386,45 @@ macro_rules! define_blocking_kv_test_suite {
                 Ok(())
             }
 
+            #[test]
+            fn kv_decimal_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{Decimal, DecimalError, KeyValue};
+                let harness = $harness::new($crate::test_util::HarnessTest::KvDecimal)?;
+                let db = harness.connect()?;
+                let kv = db.with_key_namespace("decimal");
+
+                // 0.10 can't be represented exactly as a float, but ten
+                // increments should add up to exactly 1.00.
+                for _ in 0..10 {
+                    kv.increment_key_by("balance", Decimal::new(10, 2))
+                        .execute()?;
+                }
+                assert_eq!(kv.get_key("balance").into_i64()?, Some(1));
+                assert_eq!(
+                    kv.decrement_key_by("balance", Decimal::new(1, 2))
+                        .execute()?,
+                    Decimal::new(99, 2)
+                );
+
+                // Mixing scales is rejected and leaves the value untouched.
+                assert!(matches!(
+                    kv.increment_key_by("balance", Decimal::new(1, 3)).execute(),
+                    Err(bonsaidb_core::Error::Decimal(DecimalError::ScaleMismatch {
+                        expected: 2,
+                        actual: 3
+                    }))
+                ));
+                assert_eq!(
+                    kv.increment_key_by("balance", Decimal::new(0, 2))
+                        .execute()?,
+                    Decimal::new(99, 2)
+                );
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
             #[test]
             fn kv_expiration_tests() -> anyhow::Result<()> {
                 use std::time::Duration;
@@ -3817,6 +5553,342 @@ macro_rules! define_blocking_kv_test_suite {
                 Ok(())
             }
 
+            #[test]
+            fn kv_lease_tests() -> anyhow::Result<()> {
+                use std::time::Duration;
+
+                use $crate::keyvalue::{KeyValue, Lease, Timestamp};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvLease)?;
+                let db = harness.connect()?;
+                let kv = db.with_key_namespace("lease");
+
+                let mut lease = kv
+                    .acquire_lease("lock", "a", Duration::from_secs(60))?
+                    .expect("lock should be vacant");
+                assert_eq!(lease.owner, "a");
+                assert!(kv
+                    .acquire_lease("lock", "b", Duration::from_secs(60))?
+                    .is_none());
+
+                let original_expiration = lease.expiration;
+                assert!(kv.renew_lease(&mut lease, Duration::from_secs(120))?);
+                assert!(lease.expiration > original_expiration);
+
+                // Only the owner can release the lease.
+                let other_owner = Lease {
+                    owner: String::from("b"),
+                    ..lease.clone()
+                };
+                assert!(!kv.release_lease(other_owner)?);
+                assert!(kv.release_lease(lease)?);
+                assert_eq!(kv.get_key("lock").query()?, None);
+
+                // A lease that isn't renewed expires, allowing another owner to
+                // acquire it. The expired key is checked when it is accessed,
+                // so this doesn't depend on when expired keys are removed.
+                let mut stale = kv
+                    .acquire_lease("stale", "a", Duration::from_secs(60))?
+                    .expect("stale should be vacant");
+                kv.expire_at("stale", Timestamp::MIN)?;
+                assert!(!kv.renew_lease(&mut stale, Duration::from_secs(60))?);
+                let fresh = kv
+                    .acquire_lease("stale", "b", Duration::from_secs(60))?
+                    .expect("stale lease should have expired");
+                assert!(!kv.release_lease(stale)?);
+                assert!(kv.release_lease(fresh)?);
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_expiring_between_tests() -> anyhow::Result<()> {
+                use std::time::Duration;
+
+                use $crate::keyvalue::{KeyValue, Timestamp};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvExpiringBetween)?;
+                let db = harness.connect()?;
+                let start = Timestamp::now();
+                db.set_key("soon", &0_u32)
+                    .expire_in(Duration::from_secs(60))
+                    .execute()?;
+                db.set_key("later", &0_u32)
+                    .expire_in(Duration::from_secs(7200))
+                    .execute()?;
+                db.set_key("never", &0_u32).execute()?;
+                db.with_key_namespace("other")
+                    .set_key("sooner", &0_u32)
+                    .expire_in(Duration::from_secs(30))
+                    .execute()?;
+
+                let end = Timestamp::now() + Duration::from_secs(3600);
+                let expiring = db.keys_expiring_between(start, end)?;
+                let expiring = expiring
+                    .iter()
+                    .map(|key| (key.namespace.as_deref(), key.key.as_str()))
+                    .collect::<Vec<_>>();
+                assert_eq!(expiring, [(Some("other"), "sooner"), (None, "soon")]);
+
+                // Namespaced stores only return keys in their namespace.
+                let expiring = db
+                    .with_key_namespace("other")
+                    .keys_expiring_between(start, end)?;
+                assert_eq!(expiring.len(), 1);
+                assert_eq!(expiring[0].key, "sooner");
+
+                // Removing an expiration removes the key from the results.
+                db.set_key("soon", &0_u32).execute()?;
+                let expiring = db.keys_expiring_between(start, end)?;
+                assert_eq!(expiring.len(), 1);
+                assert_eq!(expiring[0].key, "sooner");
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_key_expirations_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{KeyValue, Timestamp};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvKeyExpirations)?;
+                let db = harness.connect()?;
+                let soon = Timestamp::now() + std::time::Duration::from_secs(60);
+                let later = Timestamp::now() + std::time::Duration::from_secs(7200);
+                for key in ["soon", "later", "never"] {
+                    db.set_key(key, &0_u32).execute()?;
+                }
+                db.expire_at("soon", soon)?;
+                db.expire_at("later", later)?;
+                let other = db.with_key_namespace("other");
+                other.set_key("soon", &0_u32).execute()?;
+                other.expire_at("soon", later)?;
+
+                let expirations = db.key_expirations(["later", "never", "missing", "soon"])?;
+                assert_eq!(
+                    expirations,
+                    [
+                        (String::from("later"), Some(later)),
+                        (String::from("never"), None),
+                        (String::from("missing"), None),
+                        (String::from("soon"), Some(soon)),
+                    ]
+                );
+
+                // Keys are looked up in the store's namespace.
+                let expirations = other.key_expirations(["soon", "never"])?;
+                assert_eq!(
+                    expirations,
+                    [(String::from("soon"), Some(later)), (String::from("never"), None)]
+                );
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_namespace_scan_tests() -> anyhow::Result<()> {
+                use std::ops::ControlFlow;
+
+                use $crate::keyvalue::KeyValue;
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvNamespaceScan)?;
+                let db = harness.connect()?;
+                let scanned = db.with_key_namespace("scanned");
+                for key in ["c", "a", "b"] {
+                    scanned.set_key(key, &0_u32).execute()?;
+                }
+                db.set_key("a", &0_u32).execute()?;
+
+                let mut keys = Vec::new();
+                db.scan_key_value_namespace(Some("scanned"), |key, _| {
+                    keys.push(key.to_string());
+                    ControlFlow::Continue(())
+                })?;
+                assert_eq!(keys, ["a", "b", "c"]);
+
+                let batch = db.execute_namespace_scan(
+                    Some(String::from("scanned")),
+                    Some(String::from("a")),
+                    1,
+                )?;
+                assert_eq!(batch.len(), 1);
+                assert_eq!(batch[0].0, "b");
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_pipeline_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{KeyStatus, KeyValue, Numeric, Output, PipelineCondition};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvPipeline)?;
+                let db = harness.connect()?;
+
+                // Increment a counter, setting a flag once it reaches 3.
+                for expected in 1_u64..=3 {
+                    let outputs = db
+                        .pipeline()
+                        .increment_key_by("counter", 1_u64)
+                        .when(PipelineCondition::AtLeast {
+                            step: 0,
+                            value: Numeric::UnsignedInteger(3),
+                        })
+                        .set_numeric_key("flag", 1_u64)
+                        .execute()?;
+                    assert_eq!(outputs.len(), 2);
+                    assert!(matches!(
+                        &outputs[0],
+                        Some(Output::Value(Some(value))) if value.as_u64() == Some(expected)
+                    ));
+                    if expected < 3 {
+                        assert!(outputs[1].is_none());
+                        assert_eq!(db.get_key("flag").query()?, None);
+                    } else {
+                        assert!(matches!(
+                            outputs[1],
+                            Some(Output::Status(KeyStatus::Inserted))
+                        ));
+                        assert_eq!(db.get_key("flag").into_u64()?, Some(1));
+                    }
+                }
+
+                // When a step fails, the changes made by previous steps are undone.
+                db.set_key("text", &"hello").execute()?;
+                assert!(db
+                    .pipeline()
+                    .increment_key_by("counter", 1_u64)
+                    .delete_key("flag")
+                    .increment_key_by("text", 1_u64)
+                    .execute()
+                    .is_err());
+                assert_eq!(db.get_key("counter").into_u64()?, Some(3));
+                assert_eq!(db.get_key("flag").into_u64()?, Some(1));
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_get_bytes_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::{KeyValue, Value};
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvGetBytes)?;
+                let db = harness.connect()?;
+
+                db.set_key("akey", &String::from("hello")).execute()?;
+                let value = db.get_key("akey").query()?.expect("key not found");
+                let bytes = db.get_key("akey").into_bytes()?.expect("key not found");
+                assert!(matches!(&value, Value::Bytes(stored) if stored[..] == bytes[..]));
+                assert_eq!(value.deserialize::<String>()?, "hello");
+
+                assert_eq!(db.get_key("missing").into_bytes()?, None);
+                db.set_numeric_key("numeric", 1_u64).execute()?;
+                assert!(db.get_key("numeric").into_bytes().is_err());
+
+                let bytes = db.get_key("akey").and_delete().into_bytes()?;
+                assert!(bytes.is_some());
+                assert_eq!(db.get_key("akey").query()?, None);
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_bounded_increment_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::KeyValue;
+
+                let harness = $harness::new($crate::test_util::HarnessTest::KvBoundedIncrement)?;
+                let db = harness.connect()?;
+
+                // Incrementing up to the limit doesn't report reaching it.
+                assert_eq!(
+                    db.increment_key_by("counter", 2_u64)
+                        .limit(3)
+                        .execute_bounded()?,
+                    (2, false)
+                );
+                // Incrementing past the limit stores the limit.
+                assert_eq!(
+                    db.increment_key_by("counter", 2_u64)
+                        .limit(3)
+                        .execute_bounded()?,
+                    (3, true)
+                );
+                assert_eq!(
+                    db.increment_key_by("counter", 1_u64).limit(3).execute()?,
+                    3
+                );
+                assert_eq!(db.get_key("counter").into_u64()?, Some(3));
+
+                // Decrementing is limited by a floor.
+                assert_eq!(
+                    db.decrement_key_by("counter", 2_i64)
+                        .limit(1)
+                        .execute_bounded()?,
+                    (1, false)
+                );
+                assert_eq!(
+                    db.decrement_key_by("counter", 2_i64)
+                        .limit(0)
+                        .execute_bounded()?,
+                    (0, true)
+                );
+                assert_eq!(db.get_key("counter").into_i64()?, Some(0));
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
+            #[test]
+            fn kv_increment_with_previous_tests() -> anyhow::Result<()> {
+                use $crate::keyvalue::KeyValue;
+
+                let harness =
+                    $harness::new($crate::test_util::HarnessTest::KvIncrementWithPrevious)?;
+                let db = harness.connect()?;
+
+                db.set_numeric_key("counter", 5_u64).execute()?;
+                assert_eq!(
+                    db.increment_key_by("counter", 2_u64)
+                        .execute_with_previous()?,
+                    (5, 7)
+                );
+                assert_eq!(
+                    db.decrement_key_by("counter", 3_u64)
+                        .execute_with_previous()?,
+                    (7, 4)
+                );
+                // Limits apply to the new value.
+                assert_eq!(
+                    db.increment_key_by("counter", 10_u64)
+                        .limit(6)
+                        .execute_with_previous()?,
+                    (4, 6)
+                );
+                assert_eq!(db.get_key("counter").into_u64()?, Some(6));
+
+                // A missing key starts at zero.
+                assert_eq!(
+                    db.increment_key_by("missing", 1_u64)
+                        .execute_with_previous()?,
+                    (0, 1)
+                );
+
+                harness.shutdown()?;
+
+                Ok(())
+            }
+
             #[test]
             // This test can fail when the machine its running on is under high load or
             // constrained resources. We need a command that persists (and waits until
@@ -4,7 +4,8 @@ use serde::{Deserialize, Serialize};
 use crate::connection::{AsyncLowLevelConnection, LowLevelConnection};
 use crate::document::{CollectionHeader, DocumentId, HasHeader, Header, Revision};
 use crate::key::KeyEncoding;
-use crate::schema::{Collection, CollectionName, SerializedCollection};
+use crate::schema::view::map;
+use crate::schema::{Collection, CollectionName, SerializedCollection, ViewName};
 use crate::Error;
 
 /// A list of operations to execute as a single unit. If any operation fails,
@@ -66,6 +67,15 @@ use crate::Error;
 pub struct Transaction {
     /// The operations in this transaction.
     pub operations: Vec<Operation>,
+    /// An optional key that uniquely identifies this transaction, allowing it
+    /// to be safely retried. See
+    /// [`with_idempotency_key()`](Self::with_idempotency_key).
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+    /// Optional metadata describing this transaction, such as who made the
+    /// change or why. See [`with_metadata()`](Self::with_metadata).
+    #[serde(default)]
+    pub metadata: Option<Bytes>,
 }
 
 impl Transaction {
@@ -85,6 +95,34 @@ impl Transaction {
         self
     }
 
+    /// Sets the idempotency key of this transaction and returns self.
+    ///
+    /// When a transaction with an idempotency key is applied, the database
+    /// remembers the results for
+    /// [`IDEMPOTENCY_KEY_EXPIRATION`](crate::limits::IDEMPOTENCY_KEY_EXPIRATION).
+    /// Applying another transaction with the same key during that time returns
+    /// the original results without executing the transaction again. This
+    /// allows a transaction to be retried safely when it is unknown whether
+    /// the original attempt succeeded, such as after a network error.
+    ///
+    /// If a transaction fails, its key is forgotten and the transaction can
+    /// be retried.
+    pub fn with_idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Attaches `metadata` to this transaction and returns self.
+    ///
+    /// The metadata is stored alongside the executed transaction record and is
+    /// returned in [`Executed::metadata`] when listing executed transactions.
+    /// The contents are opaque to the database, allowing applications to
+    /// record details such as an actor id or the reason for a change.
+    pub fn with_metadata(mut self, metadata: impl Into<Bytes>) -> Self {
+        self.metadata = Some(metadata.into());
+        self
+    }
+
     /// Applies the transaction to the `database`, returning the results of the
     /// operations. All operations will succeed or none will be performed and an
     /// error will be returned.
@@ -110,6 +148,8 @@ impl From<Operation> for Transaction {
     fn from(operation: Operation) -> Self {
         Self {
             operations: vec![operation],
+            idempotency_key: None,
+            metadata: None,
         }
     }
 }
@@ -126,6 +166,16 @@ impl Transaction {
         Self::from(Operation::insert(collection, id, contents))
     }
 
+    /// Inserts a new document with `contents` into `collection` at the
+    /// caller-chosen `id`. See [`Command::InsertWithId`] for more information.
+    pub fn insert_with_id(
+        collection: CollectionName,
+        id: DocumentId,
+        contents: impl Into<Bytes>,
+    ) -> Self {
+        Self::from(Operation::insert_with_id(collection, id, contents))
+    }
+
     /// Updates a document in `collection`.
     pub fn update(collection: CollectionName, header: Header, contents: impl Into<Bytes>) -> Self {
         Self::from(Operation::update(collection, header, contents))
@@ -146,6 +196,18 @@ impl Transaction {
     pub fn delete(collection: CollectionName, header: Header) -> Self {
         Self::from(Operation::delete(collection, header))
     }
+
+    /// Soft-deletes a document from a `collection`. See
+    /// [`Command::SoftDelete`] for more information.
+    pub fn soft_delete(collection: CollectionName, header: Header) -> Self {
+        Self::from(Operation::soft_delete(collection, header))
+    }
+
+    /// Applies a merge patch to a document in `collection`. See
+    /// [`Command::Patch`] for more information.
+    pub fn patch(collection: CollectionName, header: Header, patch: impl Into<Bytes>) -> Self {
+        Self::from(Operation::patch(collection, header, patch))
+    }
 }
 
 /// A single operation performed on a `Collection`.
@@ -177,6 +239,22 @@ impl Operation {
         }
     }
 
+    /// Inserts a new document with `contents` into `collection` at the
+    /// caller-chosen `id`. See [`Command::InsertWithId`] for more information.
+    pub fn insert_with_id(
+        collection: CollectionName,
+        id: DocumentId,
+        contents: impl Into<Bytes>,
+    ) -> Self {
+        Self {
+            collection,
+            command: Command::InsertWithId {
+                id,
+                contents: contents.into(),
+            },
+        }
+    }
+
     /// Inserts a new document with the serialized representation of `contents`
     /// into `collection`. If `id` is `None` a unique id will be generated. If
     /// an id is provided and a document already exists with that id, a conflict
@@ -275,6 +353,42 @@ impl Operation {
         }
     }
 
+    /// Soft-deletes a document from a `collection`. See
+    /// [`Command::SoftDelete`] for more information.
+    pub const fn soft_delete(collection: CollectionName, header: Header) -> Self {
+        Self {
+            collection,
+            command: Command::SoftDelete { header },
+        }
+    }
+
+    /// Applies a merge patch to a document in `collection`. `patch` must be a
+    /// Pot-encoded value. See [`Command::Patch`] for more information.
+    pub fn patch(collection: CollectionName, header: Header, patch: impl Into<Bytes>) -> Self {
+        Self {
+            collection,
+            command: Command::Patch {
+                header,
+                patch: patch.into(),
+            },
+        }
+    }
+
+    /// Applies `patch` as a merge patch to the document identified by
+    /// `doc_or_header` in [`Collection`] `C`. Only the fields serialized by
+    /// `patch` are changed, and fields whose value is serialized as `None` are
+    /// removed. See [`Command::Patch`] for more information.
+    pub fn patch_serialized<C: Collection, H: HasHeader, P: Serialize>(
+        doc_or_header: &H,
+        patch: &P,
+    ) -> Result<Self, Error> {
+        Ok(Self::patch(
+            C::collection_name(),
+            doc_or_header.header()?,
+            pot::to_vec(patch)?,
+        ))
+    }
+
     /// Check that the document `id` still exists in `collection`. If a document
     /// with that id is not present, the transaction will not be applied and
     /// [`Error::DocumentNotFound`] will be returned.
@@ -337,6 +451,19 @@ pub enum Command {
         contents: Bytes,
     },
 
+    /// Inserts a new document containing `contents` with the caller-chosen
+    /// `id`, such as an id imported from another system. If a document already
+    /// exists with `id`, a conflict error will be returned.
+    ///
+    /// Storage implementations must never automatically assign `id` to another
+    /// document, even after this document is deleted.
+    InsertWithId {
+        /// The id of the document.
+        id: DocumentId,
+        /// The initial contents of the document.
+        contents: Bytes,
+    },
+
     /// Update an existing `Document` identified by `header`. `header.revision` must match
     /// the currently stored revision on the `Document`. If it does not, the
     /// command fill fail with a `DocumentConflict` error.
@@ -368,6 +495,20 @@ pub enum Command {
         header: Header,
     },
 
+    /// Delete an existing `Document` identified by `id`, retaining its
+    /// contents as a tombstone until the tombstone is purged. `revision` must
+    /// match the currently stored revision on the `Document`. If it does not,
+    /// the command fill fail with a `DocumentConflict` error.
+    ///
+    /// Once soft-deleted, the document is no longer returned when retrieving
+    /// or listing documents, and it is removed from all views, exactly as if
+    /// it had been deleted. How tombstones are retained and purged is up to
+    /// the storage implementation.
+    SoftDelete {
+        /// The current header of the `Document`.
+        header: Header,
+    },
+
     /// Checks whether a document exists, and optionally whether its revision is
     /// still current. If the document is not found, a `DocumentNotFound` error
     /// will be returned.  If the document revision is provided and does not
@@ -378,6 +519,76 @@ pub enum Command {
         /// The revision of the document to check.
         revision: Option<Revision>,
     },
+
+    /// Applies a merge patch to the contents of an existing `Document`
+    /// identified by `header`, without sending the document's full contents.
+    /// `header.revision` must match the currently stored revision on the
+    /// `Document`. If it does not, the command will fail with a
+    /// `DocumentConflict` error.
+    ///
+    /// The stored contents and `patch` must both be encoded using Pot, which is
+    /// the default format of [`SerializedCollection`]. The patch is applied
+    /// using [`apply_merge_patch()`].
+    Patch {
+        /// The header of the `Document`. The revision must match the current
+        /// document.
+        header: Header,
+
+        /// The Pot-encoded merge patch to apply to the document's contents.
+        patch: Bytes,
+    },
+}
+
+/// Applies the Pot-encoded merge `patch` to the Pot-encoded `contents`,
+/// returning the patched contents.
+///
+/// The patch is applied similarly to a JSON Merge Patch ([RFC 7386]):
+///
+/// - If the patch is a map, each of its entries is applied to the matching
+///   entry of the contents. An entry whose value is `None` removes the entry
+///   from the contents. Any other value is merged into the existing entry, or
+///   added if the contents don't have a matching entry. If the contents aren't
+///   a map, they are replaced with an empty map before the entries are
+///   applied.
+/// - Otherwise, the patch replaces the contents.
+///
+/// [RFC 7386]: https://www.rfc-editor.org/rfc/rfc7386
+pub fn apply_merge_patch(contents: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut contents = pot::from_slice::<pot::Value<'_>>(contents)?;
+    let patch = pot::from_slice::<pot::Value<'_>>(patch)?;
+    merge_patch(&mut contents, patch);
+    Ok(pot::to_vec(&contents)?)
+}
+
+fn merge_patch<'a>(target: &mut pot::Value<'a>, patch: pot::Value<'a>) {
+    let patch = match patch {
+        pot::Value::Mappings(patch) => patch,
+        patch => {
+            *target = patch;
+            return;
+        }
+    };
+    if !matches!(target, pot::Value::Mappings(_)) {
+        *target = pot::Value::Mappings(Vec::new());
+    }
+    let pot::Value::Mappings(target) = target else {
+        unreachable!("target is a map")
+    };
+    for (key, value) in patch {
+        let existing = target.iter().position(|(existing, _)| existing == &key);
+        match (existing, value) {
+            (Some(index), pot::Value::None) => {
+                target.remove(index);
+            }
+            (None, pot::Value::None) => {}
+            (Some(index), value) => merge_patch(&mut target[index].1, value),
+            (None, value) => {
+                let mut field = pot::Value::None;
+                merge_patch(&mut field, value);
+                target.push((key, field));
+            }
+        }
+    }
 }
 
 /// Information about the result of each `Operation` in a transaction.
@@ -405,6 +616,88 @@ pub enum OperationResult {
     },
 }
 
+/// The rows of a view's index that were changed by an `Operation`.
+///
+/// Only views that are updated as part of the transaction report their
+/// changes. Views using
+/// [`ViewUpdatePolicy::Lazy`](crate::schema::view::ViewUpdatePolicy::Lazy) are
+/// not mapped until they are next queried.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ViewRowChanges {
+    /// The name of the view whose index was changed.
+    pub view: ViewName,
+
+    /// The rows added to the view's index.
+    pub added: Vec<map::Serialized>,
+
+    /// The rows removed from the view's index. When an existing row is
+    /// replaced by the same document, the previous row is reported here and
+    /// the new row is reported in `added`.
+    pub removed: Vec<map::Serialized>,
+}
+
+impl ViewRowChanges {
+    /// Returns an empty set of changes for `view`.
+    #[must_use]
+    pub const fn new(view: ViewName) -> Self {
+        Self {
+            view,
+            added: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+
+    /// Returns true if no rows were added or removed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The rows of each view's index that were changed by a transaction, returned
+/// by
+/// [`LowLevelConnection::apply_transaction_with_views()`]/[`AsyncLowLevelConnection::apply_transaction_with_views()`].
+///
+/// Like [`ViewRowChanges`], only views that are updated as part of the
+/// transaction report their changes. The rows of each view are listed in the
+/// order the transaction's operations were applied, so a row added by one
+/// operation and removed by a later operation is listed in both `added` and
+/// `removed`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ViewChanges {
+    /// The changes of each view whose index was changed, in the order each
+    /// view was first changed.
+    pub views: Vec<ViewRowChanges>,
+}
+
+impl ViewChanges {
+    /// Adds the rows changed in `changes.view` to this list.
+    pub fn push(&mut self, changes: ViewRowChanges) {
+        if let Some(existing) = self
+            .views
+            .iter_mut()
+            .find(|existing| existing.view == changes.view)
+        {
+            existing.added.extend(changes.added);
+            existing.removed.extend(changes.removed);
+        } else {
+            self.views.push(changes);
+        }
+    }
+
+    /// Returns the rows changed in `view`, if any were changed.
+    #[must_use]
+    pub fn get(&self, view: &ViewName) -> Option<&ViewRowChanges> {
+        self.views.iter().find(|changes| &changes.view == view)
+    }
+
+    /// Returns true if no view's rows were changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.views.iter().all(ViewRowChanges::is_empty)
+    }
+}
+
 /// Details about an executed transaction.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Executed {
@@ -413,6 +706,11 @@ pub struct Executed {
 
     /// A list of containing ids of `Documents` changed.
     pub changes: Changes,
+
+    /// The metadata attached to the transaction, if any. See
+    /// [`Transaction::with_metadata()`].
+    #[serde(default)]
+    pub metadata: Option<Bytes>,
 }
 
 /// A list of changes.
@@ -606,6 +904,69 @@ fn document_changes_iter() {
     assert_eq!(b_changes, 1);
 }
 
+#[test]
+fn merge_patch_application() {
+    #[derive(Serialize)]
+    struct Contents {
+        a: u32,
+        b: Inner,
+        c: &'static str,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Inner {
+        x: u32,
+        y: Option<u32>,
+    }
+
+    #[derive(Serialize)]
+    struct Patch {
+        b: Inner,
+        c: Option<&'static str>,
+        d: Vec<u32>,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Patched {
+        a: u32,
+        b: Inner,
+        c: Option<String>,
+        d: Vec<u32>,
+    }
+
+    let contents = pot::to_vec(&Contents {
+        a: 1,
+        b: Inner { x: 2, y: Some(3) },
+        c: "removed",
+    })
+    .unwrap();
+    let patch = pot::to_vec(&Patch {
+        b: Inner { x: 4, y: None },
+        c: None,
+        d: vec![5],
+    })
+    .unwrap();
+    let patched = apply_merge_patch(&contents, &patch).unwrap();
+    assert_eq!(
+        pot::from_slice::<Patched>(&patched).unwrap(),
+        Patched {
+            a: 1,
+            b: Inner { x: 4, y: None },
+            c: None,
+            d: vec![5],
+        }
+    );
+    // Fields set to `None` are removed rather than stored.
+    let pot::Value::Mappings(fields) = pot::from_slice::<pot::Value<'_>>(&patched).unwrap() else {
+        unreachable!("patched contents should be a map")
+    };
+    assert_eq!(fields.len(), 3);
+
+    // A patch that isn't a map replaces the contents.
+    let patched = apply_merge_patch(&contents, &pot::to_vec(&7_u32).unwrap()).unwrap();
+    assert_eq!(pot::from_slice::<u32>(&patched).unwrap(), 7);
+}
+
 /// A record of a changed document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChangedDocument {
@@ -1,25 +1,32 @@
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use bonsaidb_core::connection::{
     self, AccessPolicy, AsyncConnection, AsyncLowLevelConnection, AsyncStorageConnection,
     Connection, HasSchema, HasSession, IdentityReference, LowLevelConnection, Range,
-    SerializedQueryKey, Session, Sort, StorageConnection,
+    SerializedQueryKey, Session, Sort, StorageConnection, ViewIndexState,
 };
 use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
-use bonsaidb_core::keyvalue::{AsyncKeyValue, KeyOperation, KeyValue, Output};
+use bonsaidb_core::keyvalue::{
+    AsyncKeyValue, ExpiringKey, KeyOperation, KeyValue, Output, Pipeline, Timestamp, Value,
+};
 use bonsaidb_core::permissions::Permissions;
 use bonsaidb_core::pubsub::{self, AsyncPubSub, AsyncSubscriber, PubSub, Receiver};
-use bonsaidb_core::schema::view::map::MappedSerializedValue;
+use bonsaidb_core::schema::view::map::{MappedSerializedCount, MappedSerializedValue};
 use bonsaidb_core::schema::{
     self, CollectionName, Nameable, Schema, SchemaName, SchemaSummary, Schematic, ViewName,
 };
-use bonsaidb_core::transaction::{self, OperationResult, Transaction};
+use bonsaidb_core::transaction::{self, OperationResult, Transaction, ViewChanges};
+use futures::{Stream, TryStreamExt};
 
 use crate::config::StorageConfiguration;
+use crate::database::keyvalue::KeyValueEntry;
 use crate::database::DatabaseNonBlocking;
 use crate::storage::{AnyBackupLocation, StorageNonBlocking};
-use crate::{Database, Error, Storage, Subscriber};
+use crate::tasks::BlockingPool;
+use crate::{Database, Error, IntegrityProblem, Storage, Subscriber};
 
 /// A file-based, multi-database, multi-user database engine. This type is
 /// designed for use with [Tokio](https://tokio.rs). For blocking
@@ -138,16 +145,61 @@ impl AsyncStorage {
     /// Restores all data from a previously stored backup `location`.
     pub async fn restore<L: AnyBackupLocation + 'static>(&self, location: L) -> Result<(), Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.restore(&location))
+        self.spawn_blocking(move || task_self.storage.restore(&location))
             .await?
     }
 
     /// Stores a copy of all data in this instance to `location`.
     pub async fn backup<L: AnyBackupLocation + 'static>(&self, location: L) -> Result<(), Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.backup(&location))
+        self.spawn_blocking(move || task_self.storage.backup(&location))
+            .await?
+    }
+
+    /// Exports the database named `name` to a single file at `path`. See
+    /// [`Storage::export_database()`] for more information.
+    pub async fn export_database<P: AsRef<Path> + Send + 'static>(
+        &self,
+        name: &str,
+        path: P,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        let name = name.to_string();
+        self.spawn_blocking(move || task_self.storage.export_database(&name, path))
+            .await?
+    }
+
+    /// Imports a database previously exported using
+    /// [`export_database()`](Self::export_database) from the file at `path`,
+    /// creating it as a new database named `name`. See
+    /// [`Storage::import_database()`] for more information.
+    pub async fn import_database<P: AsRef<Path> + Send + 'static>(
+        &self,
+        name: &str,
+        path: P,
+    ) -> Result<(), Error> {
+        let task_self = self.clone();
+        let name = name.to_string();
+        self.spawn_blocking(move || task_self.storage.import_database(&name, path))
+            .await?
+    }
+
+    /// Removes all expired keys from the key-value stores of every database
+    /// in this storage, returning the number of keys removed from each
+    /// database. See [`Storage::vacuum_expired_across_databases()`] for more
+    /// information.
+    pub async fn vacuum_expired_across_databases(&self) -> Result<BTreeMap<String, usize>, Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || task_self.storage.vacuum_expired_across_databases())
+            .await?
+    }
+
+    /// Verifies the integrity of every database in this storage, returning the
+    /// problems found in each database. See [`Storage::verify()`] for more
+    /// information.
+    pub async fn verify(&self) -> Result<BTreeMap<String, Vec<IntegrityProblem>>, Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || task_self.storage.verify())
             .await?
     }
 
@@ -168,14 +220,13 @@ impl AsyncStorage {
     pub async fn database_without_schema(&self, name: &str) -> Result<AsyncDatabase, Error> {
         let name = name.to_owned();
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .storage
-                    .database_without_schema(&name)
-                    .map(Database::into_async)
-            })
-            .await?
+        self.spawn_blocking(move || {
+            task_self
+                .storage
+                .database_without_schema(&name)
+                .map(Database::into_async)
+        })
+        .await?
     }
 
     /// Converts this instance into its blocking version, which is able to be
@@ -195,6 +246,14 @@ impl AsyncStorage {
     pub fn as_blocking(&self) -> &Storage {
         &self.storage
     }
+
+    async fn spawn_blocking<F, R>(&self, job: F) -> Result<R, Error>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        spawn_blocking(self.storage.instance.blocking_pool(), &self.runtime, job).await
+    }
 }
 
 impl<'a> From<&'a AsyncStorage> for Storage {
@@ -321,6 +380,46 @@ impl AsyncDatabase {
     pub fn as_blocking(&self) -> &Database {
         &self.database
     }
+
+    /// Returns a stream of batches of up to `batch_size` entries from every
+    /// namespace of this database's key-value store.
+    ///
+    /// Each batch is read by a separate blocking task when the stream is
+    /// polled, allowing other tasks to run between batches. See
+    /// [`Database::key_value_entries_in_batches()`] for more information.
+    pub fn key_value_entry_batches(
+        &self,
+        batch_size: usize,
+    ) -> impl Stream<Item = Result<Vec<KeyValueEntry>, bonsaidb_core::Error>> + Send + 'static {
+        let database = self.clone();
+        futures::stream::try_unfold(Some(None), move |after: Option<Option<String>>| {
+            let database = database.clone();
+            async move {
+                let Some(after) = after else { return Ok(None) };
+                let task_database = database.database.clone();
+                let (batch, next) = database
+                    .spawn_blocking(move || {
+                        task_database.key_value_entries_batch(after.as_deref(), batch_size)
+                    })
+                    .await??;
+                Ok::<_, bonsaidb_core::Error>(Some((batch, next.map(Some))))
+            }
+        })
+        .try_filter(|batch| futures::future::ready(!batch.is_empty()))
+    }
+
+    async fn spawn_blocking<F, R>(&self, job: F) -> Result<R, Error>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        spawn_blocking(
+            self.database.storage.instance.blocking_pool(),
+            &self.runtime,
+            job,
+        )
+        .await
+    }
 }
 
 impl From<AsyncDatabase> for Database {
@@ -355,8 +454,7 @@ impl AsyncStorageConnection for AsyncStorage {
     async fn admin(&self) -> Self::Database {
         let task_self = self.clone();
 
-        self.runtime
-            .spawn_blocking(move || task_self.storage.admin())
+        self.spawn_blocking(move || task_self.storage.admin())
             .await
             .unwrap()
             .into_async()
@@ -370,17 +468,15 @@ impl AsyncStorageConnection for AsyncStorage {
     ) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
         let name = name.to_owned();
-        self.runtime
-            .spawn_blocking(move || {
-                StorageConnection::create_database_with_schema(
-                    &task_self.storage,
-                    &name,
-                    schema,
-                    only_if_needed,
-                )
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            StorageConnection::create_database_with_schema(
+                &task_self.storage,
+                &name,
+                schema,
+                only_if_needed,
+            )
+        })
+        .await?
     }
 
     async fn database<DB: Schema>(
@@ -389,49 +485,39 @@ impl AsyncStorageConnection for AsyncStorage {
     ) -> Result<Self::Database, bonsaidb_core::Error> {
         let task_self = self.clone();
         let name = name.to_owned();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .storage
-                    .database::<DB>(&name)
-                    .map(Database::into_async)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .storage
+                .database::<DB>(&name)
+                .map(Database::into_async)
+        })
+        .await?
     }
 
     async fn delete_database(&self, name: &str) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
         let name = name.to_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.delete_database(&name))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.delete_database(&name))
+            .await?
     }
 
     async fn list_databases(&self) -> Result<Vec<connection::Database>, bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.list_databases())
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.list_databases())
+            .await?
     }
 
     async fn list_available_schemas(&self) -> Result<Vec<SchemaSummary>, bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.list_available_schemas())
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.list_available_schemas())
+            .await?
     }
 
     async fn create_user(&self, username: &str) -> Result<u64, bonsaidb_core::Error> {
         let task_self = self.clone();
         let username = username.to_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.create_user(&username))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.create_user(&username))
+            .await?
     }
 
     async fn delete_user<'user, U: Nameable<'user, u64> + Send + Sync>(
@@ -440,10 +526,8 @@ impl AsyncStorageConnection for AsyncStorage {
     ) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
         let user = user.name()?.into_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.delete_user(user))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.delete_user(user))
+            .await?
     }
 
     #[cfg(feature = "password-hashing")]
@@ -454,10 +538,8 @@ impl AsyncStorageConnection for AsyncStorage {
     ) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
         let user = user.name()?.into_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.set_user_password(user, password))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.set_user_password(user, password))
+            .await?
     }
 
     #[cfg(any(feature = "token-authentication", feature = "password-hashing"))]
@@ -466,15 +548,13 @@ impl AsyncStorageConnection for AsyncStorage {
         authentication: bonsaidb_core::connection::Authentication,
     ) -> Result<Self, bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .storage
-                    .authenticate(authentication)
-                    .map(Storage::into_async)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .storage
+                .authenticate(authentication)
+                .map(Storage::into_async)
+        })
+        .await?
     }
 
     async fn assume_identity(
@@ -483,15 +563,13 @@ impl AsyncStorageConnection for AsyncStorage {
     ) -> Result<Self::Authenticated, bonsaidb_core::Error> {
         let task_self = self.clone();
         let identity = identity.into_owned();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .storage
-                    .assume_identity(identity)
-                    .map(Storage::into_async)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .storage
+                .assume_identity(identity)
+                .map(Storage::into_async)
+        })
+        .await?
     }
 
     async fn add_permission_group_to_user<
@@ -507,10 +585,8 @@ impl AsyncStorageConnection for AsyncStorage {
         let task_self = self.clone();
         let user = user.name()?.into_owned();
         let group = permission_group.name()?.into_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.add_permission_group_to_user(user, group))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.add_permission_group_to_user(user, group))
+            .await?
     }
 
     async fn remove_permission_group_from_user<
@@ -526,14 +602,12 @@ impl AsyncStorageConnection for AsyncStorage {
         let task_self = self.clone();
         let user = user.name()?.into_owned();
         let group = permission_group.name()?.into_owned();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .storage
-                    .remove_permission_group_from_user(user, group)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .storage
+                .remove_permission_group_from_user(user, group)
+        })
+        .await?
     }
 
     async fn add_role_to_user<
@@ -549,10 +623,8 @@ impl AsyncStorageConnection for AsyncStorage {
         let task_self = self.clone();
         let user = user.name()?.into_owned();
         let role = role.name()?.into_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.add_role_to_user(user, role))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.add_role_to_user(user, role))
+            .await?
     }
 
     async fn remove_role_from_user<
@@ -568,10 +640,8 @@ impl AsyncStorageConnection for AsyncStorage {
         let task_self = self.clone();
         let user = user.name()?.into_owned();
         let role = role.name()?.into_owned();
-        self.runtime
-            .spawn_blocking(move || task_self.storage.remove_role_from_user(user, role))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.storage.remove_role_from_user(user, role))
+            .await?
     }
 }
 
@@ -598,14 +668,12 @@ impl AsyncConnection for AsyncDatabase {
         result_limit: Option<u32>,
     ) -> Result<Vec<transaction::Executed>, bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .list_executed_transactions(starting_id, result_limit)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .list_executed_transactions(starting_id, result_limit)
+        })
+        .await?
     }
 
     async fn last_transaction_id(&self) -> Result<Option<u64>, bonsaidb_core::Error> {
@@ -618,26 +686,20 @@ impl AsyncConnection for AsyncDatabase {
 
     async fn compact(&self) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || Connection::compact(&task_self.database))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || Connection::compact(&task_self.database))
+            .await?
     }
 
     async fn compact_collection<C: schema::Collection>(&self) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || Connection::compact_collection::<C>(&task_self.database))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || Connection::compact_collection::<C>(&task_self.database))
+            .await?
     }
 
     async fn compact_key_value_store(&self) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || Connection::compact_key_value_store(&task_self.database))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || Connection::compact_key_value_store(&task_self.database))
+            .await?
     }
 }
 
@@ -648,10 +710,88 @@ impl AsyncKeyValue for AsyncDatabase {
         op: KeyOperation,
     ) -> Result<Output, bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || KeyValue::execute_key_operation(&task_self.database, op))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || KeyValue::execute_key_operation(&task_self.database, op))
+            .await?
+    }
+
+    async fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || {
+            KeyValue::keys_expiring_between(&task_self.database, start, end)
+        })
+        .await?
+    }
+
+    async fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || {
+            KeyValue::execute_key_expirations(&task_self.database, namespace, keys)
+        })
+        .await?
+    }
+
+    async fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || {
+            KeyValue::execute_namespace_scan(&task_self.database, namespace, after, limit)
+        })
+        .await?
+    }
+
+    async fn rename_namespace(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let from = from.map(String::from);
+        let to = to.map(String::from);
+        self.spawn_blocking(move || {
+            KeyValue::rename_namespace(&task_self.database, from.as_deref(), to.as_deref())
+        })
+        .await?
+    }
+
+    async fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let namespace = namespace.map(String::from);
+        let target = target.map(String::from);
+        self.spawn_blocking(move || {
+            KeyValue::restore_to(
+                &task_self.database,
+                namespace.as_deref(),
+                timestamp,
+                target.as_deref(),
+            )
+        })
+        .await?
+    }
+
+    async fn execute_key_pipeline(
+        &self,
+        pipeline: Pipeline,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || KeyValue::execute_key_pipeline(&task_self.database, pipeline))
+            .await?
     }
 }
 
@@ -708,10 +848,17 @@ impl AsyncLowLevelConnection for AsyncDatabase {
         transaction: Transaction,
     ) -> Result<Vec<OperationResult>, bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.database.apply_transaction(transaction))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.database.apply_transaction(transaction))
+            .await?
+    }
+
+    async fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || task_self.database.apply_transaction_with_views(transaction))
+            .await?
     }
 
     async fn get_from_collection(
@@ -721,10 +868,40 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
         let task_self = self.clone();
         let collection = collection.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.database.get_from_collection(id, &collection))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.database.get_from_collection(id, &collection))
+            .await?
+    }
+
+    async fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let collection = collection.clone();
+        self.spawn_blocking(move || task_self.database.exists_in_collection(id, &collection))
+            .await?
+    }
+
+    async fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let collection = collection.clone();
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .get_tombstone_from_collection(id, &collection)
+        })
+        .await?
+    }
+
+    async fn purge_tombstones(&self, before: Timestamp) -> Result<u64, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        self.spawn_blocking(move || task_self.database.purge_tombstones(before))
+            .await?
     }
 
     async fn list_from_collection(
@@ -736,14 +913,12 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<Vec<OwnedDocument>, bonsaidb_core::Error> {
         let task_self = self.clone();
         let collection = collection.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .list_from_collection(ids, order, limit, &collection)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .list_from_collection(ids, order, limit, &collection)
+        })
+        .await?
     }
 
     async fn list_headers_from_collection(
@@ -755,14 +930,12 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<Vec<Header>, bonsaidb_core::Error> {
         let task_self = self.clone();
         let collection = collection.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .list_headers_from_collection(ids, order, limit, &collection)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .list_headers_from_collection(ids, order, limit, &collection)
+        })
+        .await?
     }
 
     async fn count_from_collection(
@@ -772,10 +945,18 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<u64, bonsaidb_core::Error> {
         let task_self = self.clone();
         let collection = collection.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.database.count_from_collection(ids, &collection))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.database.count_from_collection(ids, &collection))
+            .await?
+    }
+
+    async fn delete_all_from_collection(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let collection = collection.clone();
+        self.spawn_blocking(move || task_self.database.delete_all_from_collection(&collection))
+            .await?
     }
 
     async fn get_multiple_from_collection(
@@ -787,14 +968,12 @@ impl AsyncLowLevelConnection for AsyncDatabase {
         // TODO avoid the allocation here, switch to IntoIterator.
         let ids = ids.to_vec();
         let collection = collection.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .get_multiple_from_collection(&ids, &collection)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .get_multiple_from_collection(&ids, &collection)
+        })
+        .await?
     }
 
     async fn compact_collection_by_name(
@@ -802,10 +981,8 @@ impl AsyncLowLevelConnection for AsyncDatabase {
         collection: CollectionName,
     ) -> Result<(), bonsaidb_core::Error> {
         let task_self = self.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.database.compact_collection_by_name(collection))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.database.compact_collection_by_name(collection))
+            .await?
     }
 
     async fn query_by_name(
@@ -818,14 +995,30 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<Vec<schema::view::map::Serialized>, bonsaidb_core::Error> {
         let task_self = self.clone();
         let view = view.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .query_by_name(&view, key, order, limit, access_policy)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .query_by_name(&view, key, order, limit, access_policy)
+        })
+        .await?
+    }
+
+    async fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<schema::view::map::MappedSerializedKey>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let view = view.clone();
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .query_keys_by_name(&view, key, order, limit, access_policy)
+        })
+        .await?
     }
 
     async fn query_by_name_with_docs(
@@ -838,14 +1031,12 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<schema::view::map::MappedSerializedDocuments, bonsaidb_core::Error> {
         let task_self = self.clone();
         let view = view.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .query_by_name_with_docs(&view, key, order, limit, access_policy)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .query_by_name_with_docs(&view, key, order, limit, access_policy)
+        })
+        .await?
     }
 
     async fn reduce_by_name(
@@ -856,10 +1047,8 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<Vec<u8>, bonsaidb_core::Error> {
         let task_self = self.clone();
         let view = view.clone();
-        self.runtime
-            .spawn_blocking(move || task_self.database.reduce_by_name(&view, key, access_policy))
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || task_self.database.reduce_by_name(&view, key, access_policy))
+            .await?
     }
 
     async fn reduce_grouped_by_name(
@@ -870,14 +1059,45 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<Vec<MappedSerializedValue>, bonsaidb_core::Error> {
         let task_self = self.clone();
         let view = view.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .reduce_grouped_by_name(&view, key, access_policy)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .reduce_grouped_by_name(&view, key, access_policy)
+        })
+        .await?
+    }
+
+    async fn count_grouped_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let view = view.clone();
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .count_grouped_by_name(&view, key, access_policy)
+        })
+        .await?
+    }
+
+    async fn view_index_state_by_name(
+        &self,
+        view: &ViewName,
+    ) -> Result<ViewIndexState, bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let view = view.clone();
+        self.spawn_blocking(move || task_self.database.view_index_state_by_name(&view))
+            .await?
+    }
+
+    async fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), bonsaidb_core::Error> {
+        let task_self = self.clone();
+        let view = view.clone();
+        self.spawn_blocking(move || task_self.database.rebuild_view_by_name(&view))
+            .await?
     }
 
     async fn delete_docs_by_name(
@@ -888,13 +1108,29 @@ impl AsyncLowLevelConnection for AsyncDatabase {
     ) -> Result<u64, bonsaidb_core::Error> {
         let task_self = self.clone();
         let view = view.clone();
-        self.runtime
-            .spawn_blocking(move || {
-                task_self
-                    .database
-                    .delete_docs_by_name(&view, key, access_policy)
-            })
-            .await
-            .map_err(Error::from)?
+        self.spawn_blocking(move || {
+            task_self
+                .database
+                .delete_docs_by_name(&view, key, access_policy)
+        })
+        .await?
+    }
+}
+
+/// Runs `job` on the storage's dedicated blocking thread pool, if one was
+/// configured, or on the runtime's blocking thread pool otherwise.
+async fn spawn_blocking<F, R>(
+    pool: Option<&BlockingPool>,
+    runtime: &tokio::runtime::Handle,
+    job: F,
+) -> Result<R, Error>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    if let Some(pool) = pool {
+        pool.run(job).await
+    } else {
+        Ok(runtime.spawn_blocking(job).await?)
     }
 }
@@ -1,9 +1,10 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use clap::Subcommand;
 
 use crate::config::StorageConfiguration;
-use crate::{Error, Storage};
+use crate::{Error, IntegrityProblem, Storage};
 
 /// Commands for administering the bonsaidb server.
 pub mod admin;
@@ -24,6 +25,11 @@ pub enum StorageCommand {
     Admin(admin::Command),
     /// Executes a schema query.
     Schema(schema::Command),
+    /// Removes expired key-value entries from all databases.
+    VacuumExpired,
+    /// Scans all databases for corrupt or inconsistent data without modifying
+    /// anything.
+    Verify,
 }
 
 /// A backup location.
@@ -50,6 +56,14 @@ impl StorageCommand {
             StorageCommand::Restore(location) => location.restore(storage),
             StorageCommand::Admin(admin) => admin.execute(storage),
             StorageCommand::Schema(schema) => schema.execute(storage),
+            StorageCommand::VacuumExpired => {
+                print_vacuum_results(&storage.vacuum_expired_across_databases()?);
+                Ok(())
+            }
+            StorageCommand::Verify => {
+                print_verify_results(&storage.verify()?);
+                Ok(())
+            }
         }
     }
 
@@ -61,6 +75,31 @@ impl StorageCommand {
             StorageCommand::Restore(location) => location.restore_async(storage).await,
             StorageCommand::Admin(admin) => admin.execute_async(storage).await,
             StorageCommand::Schema(schema) => schema.execute_async(storage).await,
+            StorageCommand::VacuumExpired => {
+                print_vacuum_results(&storage.vacuum_expired_across_databases().await?);
+                Ok(())
+            }
+            StorageCommand::Verify => {
+                print_verify_results(&storage.verify().await?);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn print_vacuum_results(removed: &BTreeMap<String, usize>) {
+    for (database, count) in removed {
+        println!("{database}: {count} expired keys removed");
+    }
+}
+
+fn print_verify_results(problems: &BTreeMap<String, Vec<IntegrityProblem>>) {
+    if problems.is_empty() {
+        println!("No problems found");
+    }
+    for (database, problems) in problems {
+        for problem in problems {
+            println!("{database}: {problem}");
         }
     }
 }
@@ -5,6 +5,7 @@ use std::time::Duration;
 
 #[cfg(feature = "encryption")]
 use bonsaidb_core::document::KeyId;
+use bonsaidb_core::limits::LIST_TRANSACTIONS_MAX_RESULTS;
 use bonsaidb_core::permissions::Permissions;
 use bonsaidb_core::schema::{Schema, SchemaName};
 use sysinfo::{CpuRefreshKind, RefreshKind, System, SystemExt};
@@ -12,7 +13,7 @@ use sysinfo::{CpuRefreshKind, RefreshKind, System, SystemExt};
 use crate::storage::{DatabaseOpener, StorageSchemaOpener};
 #[cfg(feature = "encryption")]
 use crate::vault::AnyVaultKeyStorage;
-use crate::Error;
+use crate::{Database, Error};
 
 #[cfg(feature = "password-hashing")]
 mod argon;
@@ -20,12 +21,34 @@ mod argon;
 pub use argon::*;
 
 /// Configuration options for [`Storage`](crate::storage::Storage).
+///
+/// # Durability
+///
+/// Document transactions and key-value persistence are both written by
+/// [Nebari](https://github.com/khonsulabs/nebari), which synchronizes each
+/// commit to disk before reporting success. Nebari does not offer a relaxed
+/// mode that leaves flushing to the operating system, so there is no option to
+/// trade durability for write throughput here. Workloads with many small
+/// key-value writes can reduce the number of synchronized commits by using a
+/// lazy [`KeyValuePersistence`], at the cost of losing unpersisted keys on a
+/// crash.
 #[derive(Clone)]
 #[non_exhaustive]
 pub struct StorageConfiguration {
     /// The path to the database. Defaults to `db.bonsaidb` if not specified.
     pub path: Option<PathBuf>,
 
+    /// Additional directories to spread databases across, such as directories
+    /// on separate disks. Each database is stored in one of `path` or these
+    /// directories, chosen by hashing the database's name. The placement is
+    /// deterministic, so a database is always found in the same directory
+    /// while the list of directories is unchanged. A database that already
+    /// exists in any of the directories continues to be opened from there,
+    /// which allows directories to be added to an existing storage.
+    ///
+    /// The storage's id, lock, and vault keys are always stored in `path`.
+    pub shard_paths: Vec<PathBuf>,
+
     /// Prevents storing data on the disk. This is intended for testing purposes
     /// primarily. Keep in mind that the underlying storage format is
     /// append-only.
@@ -37,6 +60,71 @@ pub struct StorageConfiguration {
     /// one specified here.
     pub unique_id: Option<u64>,
 
+    /// The maximum amount of time to wait for the storage's lock to be
+    /// released by another instance that has the same path open. While
+    /// waiting, acquiring the lock is retried with an increasing delay between
+    /// attempts. If the lock can't be acquired in time,
+    /// [`Error::StorageLocked`](crate::Error::StorageLocked) is returned.
+    ///
+    /// If `None`, opening the storage waits until the lock is released, no
+    /// matter how long it takes. This is the default.
+    pub lock_wait: Option<Duration>,
+
+    /// Limits how long executed transactions are kept in each database's
+    /// transaction log. Transactions outside of these limits are no longer
+    /// returned from
+    /// [`list_executed_transactions()`](bonsaidb_core::connection::Connection::list_executed_transactions),
+    /// and are removed as documents are written and when the database is
+    /// compacted.
+    ///
+    /// By default, executed transactions are retained until they are pruned
+    /// using
+    /// [`Database::prune_transaction_log()`](crate::Database::prune_transaction_log).
+    pub transaction_log_retention: TransactionLogRetention,
+
+    /// The maximum number of entries returned from a single call to
+    /// [`list_executed_transactions()`](bonsaidb_core::connection::Connection::list_executed_transactions),
+    /// regardless of the `result_limit` requested. Raising this allows large
+    /// exports to be listed with fewer calls, at the cost of loading every
+    /// returned transaction into memory at once.
+    ///
+    /// The default value is
+    /// [`LIST_TRANSACTIONS_MAX_RESULTS`](bonsaidb_core::limits::LIST_TRANSACTIONS_MAX_RESULTS).
+    pub list_transactions_max_results: u32,
+
+    /// The maximum size, in bytes, of a document's contents. Inserting or
+    /// updating a document whose contents are larger than this limit fails
+    /// with [`bonsaidb_core::Error::DocumentTooLarge`], and no part of the
+    /// transaction is written. A value of 0 disables the limit.
+    ///
+    /// The default value is 0.
+    pub max_document_size: usize,
+
+    /// The number of threads in a dedicated pool that executes the blocking
+    /// storage work of requests made through
+    /// [`AsyncStorage`](crate::AsyncStorage) and
+    /// [`AsyncDatabase`](crate::AsyncDatabase). Bounding this work to its own
+    /// pool prevents it from saturating, or being stalled by, the async
+    /// runtime's blocking thread pool. Requests beyond the number of threads
+    /// wait for a thread to become available.
+    ///
+    /// If `None`, requests use the runtime's blocking thread pool. This is the
+    /// default.
+    pub blocking_threads: Option<usize>,
+
+    /// If set, transactions, view queries, and key-value persistence that take
+    /// at least this long are logged with their operation type and duration.
+    /// Events are emitted through [`tracing`](https://docs.rs/tracing) when
+    /// the `instrument` feature is enabled.
+    ///
+    /// The default value is `None`, which disables logging slow operations.
+    pub slow_operation_threshold: Option<Duration>,
+
+    /// The level slow operations are logged at.
+    ///
+    /// The default value is [`log::Level::Warn`].
+    pub slow_operation_level: log::Level,
+
     /// The vault key storage to use. If not specified,
     /// [`LocalVaultKeyStorage`](crate::vault::LocalVaultKeyStorage) will be
     /// used with the server's data folder as the path. This is **incredibly
@@ -51,8 +139,12 @@ pub struct StorageConfiguration {
 
     /// The default encryption key for the database. If specified, all documents
     /// will be stored encrypted at-rest using the key specified. Having this
-    /// key specified will also encrypt views. Without this, views will be
-    /// stored unencrypted.
+    /// key specified will also encrypt views and the key-value store. Without
+    /// this, views and the key-value store will be stored unencrypted.
+    ///
+    /// Each encrypted payload is tagged and uses a randomly generated nonce.
+    /// Data that was stored before this key was specified can still be read,
+    /// and is encrypted when it is next written.
     #[cfg(feature = "encryption")]
     pub default_encryption_key: Option<KeyId>,
 
@@ -65,6 +157,14 @@ pub struct StorageConfiguration {
     /// Controls how the key-value store persists keys, on a per-database basis.
     pub key_value_persistence: KeyValuePersistence,
 
+    /// Controls how the key-value store handles stored entries that can't be
+    /// deserialized.
+    pub corrupt_key_value_policy: CorruptKeyValuePolicy,
+
+    /// Controls which thread performs the background work of each database's
+    /// key-value store.
+    pub key_value_worker: KeyValueWorker,
+
     /// Sets the default compression algorithm.
     #[cfg(feature = "compression")]
     pub default_compression: Option<Compression>,
@@ -88,8 +188,16 @@ impl Default for StorageConfiguration {
         system.refresh_specifics(system_specs);
         Self {
             path: None,
+            shard_paths: Vec::new(),
             memory_only: false,
             unique_id: None,
+            lock_wait: None,
+            transaction_log_retention: TransactionLogRetention::default(),
+            list_transactions_max_results: LIST_TRANSACTIONS_MAX_RESULTS,
+            max_document_size: 0,
+            blocking_threads: None,
+            slow_operation_threshold: None,
+            slow_operation_level: log::Level::Warn,
             #[cfg(feature = "encryption")]
             vault_key_storage: None,
             #[cfg(feature = "encryption")]
@@ -99,6 +207,8 @@ impl Default for StorageConfiguration {
             workers: Tasks::default_for(&system),
             views: Views::default(),
             key_value_persistence: KeyValuePersistence::default(),
+            corrupt_key_value_policy: CorruptKeyValuePolicy::default(),
+            key_value_worker: KeyValueWorker::default(),
             authenticated_permissions: Permissions::default(),
             #[cfg(feature = "password-hashing")]
             argon: ArgonConfiguration::default_for(&system),
@@ -113,11 +223,24 @@ impl std::fmt::Debug for StorageConfiguration {
         schemas.sort();
         let mut f = f.debug_struct("StorageConfiguration");
         f.field("path", &self.path)
+            .field("shard_paths", &self.shard_paths)
             .field("memory_only", &self.memory_only)
             .field("unique_id", &self.unique_id)
+            .field("lock_wait", &self.lock_wait)
+            .field("transaction_log_retention", &self.transaction_log_retention)
+            .field(
+                "list_transactions_max_results",
+                &self.list_transactions_max_results,
+            )
+            .field("max_document_size", &self.max_document_size)
+            .field("blocking_threads", &self.blocking_threads)
+            .field("slow_operation_threshold", &self.slow_operation_threshold)
+            .field("slow_operation_level", &self.slow_operation_level)
             .field("workers", &self.workers)
             .field("views", &self.views)
             .field("key_value_persistence", &self.key_value_persistence)
+            .field("corrupt_key_value_policy", &self.corrupt_key_value_policy)
+            .field("key_value_worker", &self.key_value_worker)
             .field("authenticated_permissions", &self.authenticated_permissions)
             .field("initial_schemas", &schemas);
 
@@ -143,6 +266,23 @@ impl StorageConfiguration {
             .insert(S::schema_name(), Arc::new(StorageSchemaOpener::<S>::new()?));
         Ok(())
     }
+
+    /// Registers the schema provided, along with a migration that is run when
+    /// the storage is opened for each database that was last opened with an
+    /// older [version](Schema::schema_version) of the schema. The migration is
+    /// invoked with the database and the version that last opened it. Once
+    /// the migration succeeds, the database's stored version is updated.
+    pub fn register_schema_with_migration<S, F>(&mut self, migration: F) -> Result<(), Error>
+    where
+        S: Schema,
+        F: Fn(&Database, u64) -> Result<(), bonsaidb_core::Error> + Send + Sync + 'static,
+    {
+        self.initial_schemas.insert(
+            S::schema_name(),
+            Arc::new(StorageSchemaOpener::<S>::new()?.with_migration(migration)),
+        );
+        Ok(())
+    }
 }
 
 /// Configuration options for background tasks.
@@ -174,7 +314,7 @@ impl SystemDefault for Tasks {
 }
 
 /// Configuration options for views.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Views {
     /// If true, the database will scan all views during the call to
     /// `open_local`. This will cause database opening to take longer, but once
@@ -182,6 +322,62 @@ pub struct Views {
     /// be checked. However, for faster startup time, you may wish to delay the
     /// integrity scan. Default value is `false`.
     pub check_integrity_on_open: bool,
+
+    /// The maximum number of view query results to cache across all
+    /// databases. Results are cached by view and query parameters, and a
+    /// cached result is only returned if the view's index hasn't changed since
+    /// it was cached. When the cache is full, the least recently used result
+    /// is evicted. Only
+    /// [`query()`](bonsaidb_core::connection::View::query)-style requests are
+    /// cached.
+    ///
+    /// Each cached result holds a copy of every mapping returned, so this
+    /// should be kept small for views that return large results. Default value
+    /// is `0`, which disables the cache.
+    pub result_cache_size: usize,
+
+    /// The number of workers used to map a view's invalidated documents, such
+    /// as when a view's index is rebuilt after its version changes. The
+    /// invalidated document ids are split into this many contiguous ranges,
+    /// and each range is read and mapped by its own worker. Each worker's
+    /// mappings are written to the view's index as soon as they are mapped.
+    /// Documents are only partitioned when every worker would map more than
+    /// one batch of documents.
+    ///
+    /// Default value is `1`, which reads the documents on a single worker
+    /// while still mapping them using
+    /// [`Tasks::parallelization`](Tasks#structfield.parallelization) threads.
+    pub rebuild_workers: usize,
+}
+
+impl Default for Views {
+    fn default() -> Self {
+        Self {
+            check_integrity_on_open: false,
+            result_cache_size: 0,
+            rebuild_workers: 1,
+        }
+    }
+}
+
+/// Limits on how long executed transactions are kept in a database's
+/// transaction log. A transaction is removed once it falls outside of either
+/// limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TransactionLogRetention {
+    /// The number of most recent transaction ids to retain. Transaction ids
+    /// are also assigned to internal writes, such as persisting key-value
+    /// changes, so fewer executed transactions than this may be retained.
+    /// Default value is `None`, which retains transactions regardless of how
+    /// many have been executed since.
+    pub transactions: Option<u64>,
+
+    /// The maximum amount of time to retain a transaction after it was
+    /// executed. Transactions executed by versions of BonsaiDb that didn't
+    /// record when transactions were executed are treated as being executed
+    /// when the database was first opened by this version. Default value is
+    /// `None`, which retains transactions regardless of their age.
+    pub max_age: Option<Duration>,
 }
 
 /// Rules for persisting key-value changes. Default persistence is to
@@ -232,9 +428,135 @@ pub struct Views {
 /// // regardless of duration.
 /// assert!(persistence.should_commit(100, Duration::ZERO));
 /// ```
+///
+/// ## Applying backpressure
+///
+/// Commits happen in the background, which means writers can produce changes
+/// faster than they can be persisted. To bound the amount of memory used by
+/// pending changes, a high-water mark can be set using
+/// [`with_max_dirty_keys()`](Self::with_max_dirty_keys). Once this many
+/// changes are waiting to be persisted, writes will block until the
+/// in-progress commit completes.
+///
+/// ## Per-namespace persistence
+///
+/// Namespaces can be given their own rules using
+/// [`with_namespace()`](Self::with_namespace). Changes to keys in a namespace
+/// with its own rules are only counted against that namespace's rules, while
+/// all other changes are counted against the default rules. A commit is
+/// started as soon as any set of rules is met, and it persists all
+/// outstanding changes.
+///
+/// ```rust
+/// # use bonsaidb_local::config::{KeyValuePersistence, PersistenceThreshold};
+/// # use std::time::Duration;
+/// #
+/// let persistence = KeyValuePersistence::immediate().with_namespace(
+///     "sessions",
+///     KeyValuePersistence::lazy([
+///         PersistenceThreshold::after_changes(1).and_duration(Duration::from_secs(60)),
+///     ]),
+/// );
+///
+/// assert!(!persistence.namespace_should_commit("sessions", 1, Duration::ZERO));
+/// assert!(persistence.namespace_should_commit("payments", 1, Duration::ZERO));
+/// ```
+///
+/// ## Default expiration
+///
+/// A namespace can be given a default expiration using
+/// [`with_namespace_default_expiration()`](Self::with_namespace_default_expiration).
+/// Keys set in the namespace without an expiration expire once the default
+/// duration has elapsed. An explicit expiration overrides the default, and
+/// [`never_expire()`](bonsaidb_core::keyvalue::set::Builder::never_expire)
+/// stores a key without any expiration.
+///
+/// ```rust
+/// # use bonsaidb_local::config::KeyValuePersistence;
+/// # use std::time::Duration;
+/// #
+/// let persistence = KeyValuePersistence::immediate()
+///     .with_namespace_default_expiration("cache", Duration::from_secs(300));
+///
+/// assert_eq!(
+///     persistence.namespace_default_expiration("cache"),
+///     Some(Duration::from_secs(300))
+/// );
+/// assert_eq!(persistence.namespace_default_expiration("sessions"), None);
+/// ```
+///
+/// ## Write-ahead log
+///
+/// With lazy rules, a change that hasn't been committed yet is lost if the
+/// process exits unexpectedly. Enabling the write-ahead log using
+/// [`with_write_ahead_log()`](Self::with_write_ahead_log) appends each change
+/// to a log file before the operation returns. Appending to the log is much
+/// cheaper than committing the key-value tree, and the tree is still
+/// committed in the background according to the rules. When the database is
+/// opened, any changes left in the log are committed before the key-value
+/// store is used.
+///
+/// ```rust
+/// # use bonsaidb_local::config::{KeyValuePersistence, PersistenceThreshold};
+/// # use std::time::Duration;
+/// #
+/// let persistence = KeyValuePersistence::lazy([
+///     PersistenceThreshold::after_changes(1).and_duration(Duration::from_secs(5)),
+/// ])
+/// .with_write_ahead_log();
+///
+/// assert!(persistence.uses_write_ahead_log());
+/// ```
+///
+/// ## Blob storage
+///
+/// Large values can be stored out-of-line in a content-addressed blob store by
+/// using [`with_blob_storage()`](Self::with_blob_storage). Keys whose values
+/// contain identical bytes share a single blob, which is removed once the last
+/// key referencing it is overwritten or removed.
+///
+/// ```rust
+/// # use bonsaidb_local::config::{KeyValueBlobStorage, KeyValuePersistence};
+/// let persistence =
+///     KeyValuePersistence::immediate().with_blob_storage(KeyValueBlobStorage::new(4096));
+///
+/// assert_eq!(persistence.blob_storage().unwrap().minimum_size, 4096);
+/// ```
+///
+/// ## Operation log
+///
+/// Committing a change overwrites the previous value of the key. Enabling the
+/// operation log using [`with_operation_log()`](Self::with_operation_log)
+/// appends each committed change to the `kv-operation-log` tree along with the
+/// time it was committed, allowing the state of a namespace at a past instant
+/// to be restored using
+/// [`KeyValue::restore_to()`](bonsaidb_core::keyvalue::KeyValue::restore_to).
+///
+/// The log keeps a complete copy of every value that is committed, including
+/// values stored in the blob store, and it is never truncated. The disk space
+/// used by the log grows with the total size of all changes ever committed
+/// rather than with the size of the current keys. With lazy persistence,
+/// multiple changes to a key between commits are only logged once.
+///
+/// ```rust
+/// # use bonsaidb_local::config::KeyValuePersistence;
+/// let persistence = KeyValuePersistence::immediate().with_operation_log();
+///
+/// assert!(persistence.uses_operation_log());
+/// ```
 #[derive(Debug, Clone)]
 #[must_use]
-pub struct KeyValuePersistence(KeyValuePersistenceInner);
+pub struct KeyValuePersistence {
+    rules: KeyValuePersistenceInner,
+    max_dirty_keys: Option<usize>,
+    max_namespaces: Option<usize>,
+    namespaces: HashMap<String, KeyValuePersistenceInner>,
+    default_expirations: HashMap<String, Duration>,
+    write_ahead_log: bool,
+    blob_storage: Option<KeyValueBlobStorage>,
+    operation_log: bool,
+    entry_encoding: KeyValueEntryEncoding,
+}
 
 #[derive(Debug, Clone)]
 enum KeyValuePersistenceInner {
@@ -252,7 +574,17 @@ impl Default for KeyValuePersistence {
 impl KeyValuePersistence {
     /// Returns a ruleset that commits all changes immediately.
     pub const fn immediate() -> Self {
-        Self(KeyValuePersistenceInner::Immediate)
+        Self {
+            rules: KeyValuePersistenceInner::Immediate,
+            max_dirty_keys: None,
+            max_namespaces: None,
+            namespaces: HashMap::new(),
+            default_expirations: HashMap::new(),
+            write_ahead_log: false,
+            blob_storage: None,
+            operation_log: false,
+            entry_encoding: KeyValueEntryEncoding::Pot,
+        }
     }
 
     /// Returns a ruleset that lazily commits data based on a list of thresholds.
@@ -262,7 +594,177 @@ impl KeyValuePersistence {
     {
         let mut rules = rules.into_iter().collect::<Vec<_>>();
         rules.sort_by(|a, b| a.number_of_changes.cmp(&b.number_of_changes));
-        Self(KeyValuePersistenceInner::Lazy(rules))
+        Self {
+            rules: KeyValuePersistenceInner::Lazy(rules),
+            max_dirty_keys: None,
+            max_namespaces: None,
+            namespaces: HashMap::new(),
+            default_expirations: HashMap::new(),
+            write_ahead_log: false,
+            blob_storage: None,
+            operation_log: false,
+            entry_encoding: KeyValueEntryEncoding::Pot,
+        }
+    }
+
+    /// Sets the maximum number of changed keys that can be waiting to be
+    /// persisted and returns self. When this limit is reached, key-value
+    /// writes will wait for the in-progress commit to complete before being
+    /// applied. A commit is started immediately if one is not already in
+    /// progress.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_dirty_keys` is 0.
+    pub fn with_max_dirty_keys(mut self, max_dirty_keys: usize) -> Self {
+        assert!(max_dirty_keys > 0, "max_dirty_keys must be at least 1");
+        self.max_dirty_keys = Some(max_dirty_keys);
+        self
+    }
+
+    /// Returns the maximum number of changed keys that can be waiting to be
+    /// persisted, if a limit has been set.
+    #[must_use]
+    pub const fn max_dirty_keys(&self) -> Option<usize> {
+        self.max_dirty_keys
+    }
+
+    /// Sets the maximum number of distinct namespaces that can contain keys
+    /// and returns self. Operations that would store a key in a new namespace
+    /// once this limit is reached return
+    /// [`Error::TooManyKeyValueNamespaces`](bonsaidb_core::Error::TooManyKeyValueNamespaces).
+    /// Keys stored without a namespace are not counted.
+    ///
+    /// Namespaces are counted when a key is first stored in them. A namespace
+    /// whose keys have all been removed continues to count against the limit
+    /// until the database is reopened.
+    ///
+    /// By default, there is no limit.
+    pub fn with_max_namespaces(mut self, max_namespaces: usize) -> Self {
+        self.max_namespaces = Some(max_namespaces);
+        self
+    }
+
+    /// Returns the maximum number of distinct namespaces that can contain
+    /// keys, if a limit has been set.
+    #[must_use]
+    pub const fn max_namespaces(&self) -> Option<usize> {
+        self.max_namespaces
+    }
+
+    /// Uses the rules from `persistence` for keys in `namespace` and returns
+    /// self. Changes to keys in `namespace` will no longer be counted against
+    /// the default rules. The
+    /// [`max_dirty_keys()`](Self::max_dirty_keys) of `persistence` is
+    /// ignored.
+    pub fn with_namespace(
+        mut self,
+        namespace: impl Into<String>,
+        persistence: KeyValuePersistence,
+    ) -> Self {
+        self.namespaces.insert(namespace.into(), persistence.rules);
+        self
+    }
+
+    /// Sets the expiration applied to keys in `namespace` that are set without
+    /// an expiration and returns self. Keys expire once `expiration` has
+    /// elapsed after being set.
+    ///
+    /// By default, keys without an expiration never expire.
+    pub fn with_namespace_default_expiration(
+        mut self,
+        namespace: impl Into<String>,
+        expiration: Duration,
+    ) -> Self {
+        self.default_expirations
+            .insert(namespace.into(), expiration);
+        self
+    }
+
+    /// Returns the default expiration of keys in `namespace`, if one has been
+    /// set using
+    /// [`with_namespace_default_expiration()`](Self::with_namespace_default_expiration).
+    #[must_use]
+    pub fn namespace_default_expiration(&self, namespace: &str) -> Option<Duration> {
+        self.default_expirations.get(namespace).copied()
+    }
+
+    /// Enables the write-ahead log and returns self. Each change is durably
+    /// appended to the log before the operation that made it completes,
+    /// allowing lazily committed changes to survive the process exiting
+    /// unexpectedly.
+    ///
+    /// The log's segments are written directly to the database's directory,
+    /// so [`Storage::open()`](crate::Storage::open) returns an error if the
+    /// write-ahead log is enabled for memory-only storage or storage with a
+    /// default encryption key.
+    ///
+    /// By default, the write-ahead log is disabled.
+    pub fn with_write_ahead_log(mut self) -> Self {
+        self.write_ahead_log = true;
+        self
+    }
+
+    /// Returns true if the write-ahead log has been enabled using
+    /// [`with_write_ahead_log()`](Self::with_write_ahead_log).
+    #[must_use]
+    pub const fn uses_write_ahead_log(&self) -> bool {
+        self.write_ahead_log
+    }
+
+    /// Stores values of at least [`KeyValueBlobStorage::minimum_size`] bytes
+    /// in a content-addressed blob store and returns self.
+    ///
+    /// By default, all values are stored alongside their keys.
+    pub fn with_blob_storage(mut self, blob_storage: KeyValueBlobStorage) -> Self {
+        self.blob_storage = Some(blob_storage);
+        self
+    }
+
+    /// Returns the blob storage configuration set using
+    /// [`with_blob_storage()`](Self::with_blob_storage), if any.
+    #[must_use]
+    pub const fn blob_storage(&self) -> Option<&KeyValueBlobStorage> {
+        self.blob_storage.as_ref()
+    }
+
+    /// Enables the operation log and returns self. Each committed change is
+    /// appended to the log along with the time it was committed.
+    ///
+    /// By default, the operation log is disabled.
+    pub fn with_operation_log(mut self) -> Self {
+        self.operation_log = true;
+        self
+    }
+
+    /// Returns true if the operation log has been enabled using
+    /// [`with_operation_log()`](Self::with_operation_log).
+    #[must_use]
+    pub const fn uses_operation_log(&self) -> bool {
+        self.operation_log
+    }
+
+    /// Sets the encoding used to store entries and returns self.
+    ///
+    /// By default, entries are stored using [`KeyValueEntryEncoding::Pot`].
+    pub fn with_entry_encoding(mut self, encoding: KeyValueEntryEncoding) -> Self {
+        self.entry_encoding = encoding;
+        self
+    }
+
+    /// Returns the encoding used to store entries.
+    #[must_use]
+    pub const fn entry_encoding(&self) -> KeyValueEntryEncoding {
+        self.entry_encoding
+    }
+
+    /// Returns true if `namespace` has been given its own rules using
+    /// [`with_namespace()`](Self::with_namespace).
+    #[must_use]
+    pub fn has_namespace_rules(&self, namespace: &str) -> bool {
+        self.namespaces.contains_key(namespace)
     }
 
     /// Returns true if these rules determine that the outstanding changes should be persisted.
@@ -276,15 +778,55 @@ impl KeyValuePersistence {
             == Some(Duration::ZERO)
     }
 
+    /// Returns true if the rules for `namespace` determine that its outstanding
+    /// changes should be persisted. If `namespace` has not been given its own
+    /// rules, the default rules are used.
+    #[must_use]
+    pub fn namespace_should_commit(
+        &self,
+        namespace: &str,
+        number_of_changes: usize,
+        elapsed_since_last_commit: Duration,
+    ) -> bool {
+        self.namespace_duration_until_next_commit(
+            namespace,
+            number_of_changes,
+            elapsed_since_last_commit,
+        ) == Some(Duration::ZERO)
+    }
+
     pub(crate) fn duration_until_next_commit(
         &self,
         number_of_changes: usize,
         elapsed_since_last_commit: Duration,
+    ) -> Option<Duration> {
+        self.rules
+            .duration_until_next_commit(number_of_changes, elapsed_since_last_commit)
+    }
+
+    pub(crate) fn namespace_duration_until_next_commit(
+        &self,
+        namespace: &str,
+        number_of_changes: usize,
+        elapsed_since_last_commit: Duration,
+    ) -> Option<Duration> {
+        self.namespaces
+            .get(namespace)
+            .unwrap_or(&self.rules)
+            .duration_until_next_commit(number_of_changes, elapsed_since_last_commit)
+    }
+}
+
+impl KeyValuePersistenceInner {
+    fn duration_until_next_commit(
+        &self,
+        number_of_changes: usize,
+        elapsed_since_last_commit: Duration,
     ) -> Option<Duration> {
         if number_of_changes == 0 {
             None
         } else {
-            match &self.0 {
+            match self {
                 KeyValuePersistenceInner::Immediate => Some(Duration::ZERO),
                 KeyValuePersistenceInner::Lazy(rules) => {
                     let mut shortest_duration = Duration::MAX;
@@ -338,6 +880,119 @@ impl PersistenceThreshold {
     }
 }
 
+/// Controls how the key-value store handles stored entries that can't be
+/// deserialized, such as an entry that was corrupted on disk.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum CorruptKeyValuePolicy {
+    /// Return an error from the operation that read the corrupt entry.
+    Error,
+    /// Log an error and treat the key as if it were not present. The corrupt
+    /// entry is left on disk.
+    #[default]
+    SkipAndLog,
+    /// Log an error, move the corrupt entry's bytes into the `kv-quarantine`
+    /// tree for inspection, and treat the key as if it were not present.
+    Quarantine,
+}
+
+/// Controls which thread performs the background work of each database's
+/// key-value store, such as removing expired keys and committing changes
+/// persisted using [`KeyValuePersistence::lazy()`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum KeyValueWorker {
+    /// Each open database spawns its own worker thread.
+    #[default]
+    PerDatabase,
+    /// A single worker thread services every database opened by the storage.
+    /// The worker sleeps until the earliest target of any database, which
+    /// avoids keeping a mostly idle thread for each database when many
+    /// databases are open.
+    Shared,
+}
+
+/// The format used to store the key-value store's entries.
+///
+/// Each stored entry records which encoding it was written with, so the
+/// encoding of an existing database can be changed at any time. Existing
+/// entries continue to be read using their original encoding until they are
+/// next written.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum KeyValueEntryEncoding {
+    /// Encode entries using [Pot](https://github.com/khonsulabs/pot), a
+    /// self-describing format. Entries remain readable when fields are added
+    /// to them.
+    #[default]
+    Pot,
+    /// Encode entries using bincode. Bincode entries are smaller, but the
+    /// format isn't self-describing, so entries can't be read if their
+    /// structure changes. Databases created before this option existed stored
+    /// all entries using bincode.
+    Bincode,
+}
+
+/// Configures the key-value store's content-addressed blob store.
+///
+/// Byte values of at least [`minimum_size`](Self::minimum_size) bytes are
+/// stored once per unique value, keyed by the hash produced by
+/// [`hasher`](Self::hasher). Each blob tracks how many keys reference it, and
+/// a blob is removed when the last key referencing it is overwritten or
+/// removed.
+///
+/// If a value's hash matches a blob containing different bytes, the value is
+/// stored alongside its key instead, so a weak hash can reduce how often
+/// values are deduplicated but can never return the wrong value. Changing the
+/// hasher of an existing database is supported: existing keys continue to
+/// reference the blobs they were stored with.
+#[derive(Debug, Clone)]
+pub struct KeyValueBlobStorage {
+    /// The minimum length of a value, in bytes, to store it in the blob store.
+    pub minimum_size: usize,
+    /// The function used to produce the key of each blob.
+    pub hasher: Arc<dyn BlobHasher>,
+}
+
+impl KeyValueBlobStorage {
+    /// Returns a configuration that stores values of at least `minimum_size`
+    /// bytes in the blob store, hashed with [`Fnv1aBlobHasher`].
+    #[must_use]
+    pub fn new(minimum_size: usize) -> Self {
+        Self {
+            minimum_size,
+            hasher: Arc::new(Fnv1aBlobHasher),
+        }
+    }
+
+    /// Sets the function used to produce the key of each blob and returns
+    /// self.
+    #[must_use]
+    pub fn with_hasher<Hasher: BlobHasher>(mut self, hasher: Hasher) -> Self {
+        self.hasher = Arc::new(hasher);
+        self
+    }
+}
+
+/// A hash function used to address blobs in the key-value store's blob store.
+pub trait BlobHasher: std::fmt::Debug + Send + Sync + 'static {
+    /// Returns the hash of `value`. The same bytes must always produce the
+    /// same hash, including across process restarts.
+    fn hash(&self, value: &[u8]) -> Vec<u8>;
+}
+
+/// The default [`BlobHasher`], producing a 64-bit FNV-1a hash.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Fnv1aBlobHasher;
+
+impl BlobHasher for Fnv1aBlobHasher {
+    fn hash(&self, value: &[u8]) -> Vec<u8> {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+        let hash = value.iter().fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+        });
+        hash.to_be_bytes().to_vec()
+    }
+}
+
 /// Storage configuration builder methods.
 pub trait Builder: Sized {
     /// Creates a default configuration with `path` set.
@@ -350,6 +1005,13 @@ pub trait Builder: Sized {
     }
     /// Registers the schema and returns self.
     fn with_schema<S: Schema>(self) -> Result<Self, Error>;
+    /// Registers the schema with a migration and returns self. See
+    /// [`StorageConfiguration::register_schema_with_migration()`] for more
+    /// information.
+    fn with_schema_migration<S, F>(self, migration: F) -> Result<Self, Error>
+    where
+        S: Schema,
+        F: Fn(&Database, u64) -> Result<(), bonsaidb_core::Error> + Send + Sync + 'static;
 
     /// Sets [`StorageConfiguration::memory_only`](StorageConfiguration#structfield.memory_only) to true and returns self.
     #[must_use]
@@ -357,6 +1019,9 @@ pub trait Builder: Sized {
     /// Sets [`StorageConfiguration::path`](StorageConfiguration#structfield.path) to `path` and returns self.
     #[must_use]
     fn path<P: AsRef<Path>>(self, path: P) -> Self;
+    /// Adds `path` to [`StorageConfiguration::shard_paths`](StorageConfiguration#structfield.shard_paths) and returns self.
+    #[must_use]
+    fn shard_path<P: AsRef<Path>>(self, path: P) -> Self;
     /// Sets [`StorageConfiguration::unique_id`](StorageConfiguration#structfield.unique_id) to `unique_id` and returns self.
     #[must_use]
     fn unique_id(self, unique_id: u64) -> Self;
@@ -380,6 +1045,12 @@ pub trait Builder: Sized {
     /// Sets [`Views::check_integrity_on_open`] to `check` and returns self.
     #[must_use]
     fn check_view_integrity_on_open(self, check: bool) -> Self;
+    /// Sets [`Views::result_cache_size`] to `entries` and returns self.
+    #[must_use]
+    fn view_result_cache_size(self, entries: usize) -> Self;
+    /// Sets [`Views::rebuild_workers`] to `workers` and returns self.
+    #[must_use]
+    fn view_rebuild_workers(self, workers: usize) -> Self;
     /// Sets [`StorageConfiguration::default_compression`](StorageConfiguration#structfield.default_compression) to `path` and returns self.
     #[cfg(feature = "compression")]
     #[must_use]
@@ -387,6 +1058,36 @@ pub trait Builder: Sized {
     /// Sets [`StorageConfiguration::key_value_persistence`](StorageConfiguration#structfield.key_value_persistence) to `persistence` and returns self.
     #[must_use]
     fn key_value_persistence(self, persistence: KeyValuePersistence) -> Self;
+    /// Sets [`StorageConfiguration::corrupt_key_value_policy`](StorageConfiguration#structfield.corrupt_key_value_policy) to `policy` and returns self.
+    #[must_use]
+    fn corrupt_key_value_policy(self, policy: CorruptKeyValuePolicy) -> Self;
+    /// Sets [`StorageConfiguration::key_value_worker`](StorageConfiguration#structfield.key_value_worker) to `worker` and returns self.
+    #[must_use]
+    fn key_value_worker(self, worker: KeyValueWorker) -> Self;
+    /// Sets [`StorageConfiguration::lock_wait`](StorageConfiguration#structfield.lock_wait) to `wait` and returns self.
+    #[must_use]
+    fn lock_wait(self, wait: Duration) -> Self;
+    /// Sets [`TransactionLogRetention::transactions`] to `transactions` and returns self.
+    #[must_use]
+    fn transaction_log_retention(self, transactions: u64) -> Self;
+    /// Sets [`TransactionLogRetention::max_age`] to `age` and returns self.
+    #[must_use]
+    fn transaction_log_max_age(self, age: Duration) -> Self;
+    /// Sets [`StorageConfiguration::list_transactions_max_results`](StorageConfiguration#structfield.list_transactions_max_results) to `max_results` and returns self.
+    #[must_use]
+    fn list_transactions_max_results(self, max_results: u32) -> Self;
+    /// Sets [`StorageConfiguration::max_document_size`](StorageConfiguration#structfield.max_document_size) to `bytes` and returns self.
+    #[must_use]
+    fn max_document_size(self, bytes: usize) -> Self;
+    /// Sets [`StorageConfiguration::blocking_threads`](StorageConfiguration#structfield.blocking_threads) to `threads` and returns self.
+    #[must_use]
+    fn blocking_threads(self, threads: usize) -> Self;
+    /// Sets [`StorageConfiguration::slow_operation_threshold`](StorageConfiguration#structfield.slow_operation_threshold) to `threshold` and returns self.
+    #[must_use]
+    fn slow_operation_threshold(self, threshold: Duration) -> Self;
+    /// Sets [`StorageConfiguration::slow_operation_level`](StorageConfiguration#structfield.slow_operation_level) to `level` and returns self.
+    #[must_use]
+    fn slow_operation_level(self, level: log::Level) -> Self;
     /// Sets [`Self::authenticated_permissions`](Self#structfield.authenticated_permissions) to `authenticated_permissions` and returns self.
     #[must_use]
     fn authenticated_permissions<P: Into<Permissions>>(self, authenticated_permissions: P) -> Self;
@@ -402,6 +1103,15 @@ impl Builder for StorageConfiguration {
         Ok(self)
     }
 
+    fn with_schema_migration<S, F>(mut self, migration: F) -> Result<Self, Error>
+    where
+        S: Schema,
+        F: Fn(&Database, u64) -> Result<(), bonsaidb_core::Error> + Send + Sync + 'static,
+    {
+        self.register_schema_with_migration::<S, F>(migration)?;
+        Ok(self)
+    }
+
     fn memory_only(mut self) -> Self {
         self.memory_only = true;
         self
@@ -412,6 +1122,11 @@ impl Builder for StorageConfiguration {
         self
     }
 
+    fn shard_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.shard_paths.push(path.as_ref().to_owned());
+        self
+    }
+
     fn unique_id(mut self, unique_id: u64) -> Self {
         self.unique_id = Some(unique_id);
         self
@@ -453,11 +1168,71 @@ impl Builder for StorageConfiguration {
         self
     }
 
+    fn view_result_cache_size(mut self, entries: usize) -> Self {
+        self.views.result_cache_size = entries;
+        self
+    }
+
+    fn view_rebuild_workers(mut self, workers: usize) -> Self {
+        self.views.rebuild_workers = workers;
+        self
+    }
+
     fn key_value_persistence(mut self, persistence: KeyValuePersistence) -> Self {
         self.key_value_persistence = persistence;
         self
     }
 
+    fn corrupt_key_value_policy(mut self, policy: CorruptKeyValuePolicy) -> Self {
+        self.corrupt_key_value_policy = policy;
+        self
+    }
+
+    fn key_value_worker(mut self, worker: KeyValueWorker) -> Self {
+        self.key_value_worker = worker;
+        self
+    }
+
+    fn lock_wait(mut self, wait: Duration) -> Self {
+        self.lock_wait = Some(wait);
+        self
+    }
+
+    fn transaction_log_retention(mut self, transactions: u64) -> Self {
+        self.transaction_log_retention.transactions = Some(transactions);
+        self
+    }
+
+    fn transaction_log_max_age(mut self, age: Duration) -> Self {
+        self.transaction_log_retention.max_age = Some(age);
+        self
+    }
+
+    fn list_transactions_max_results(mut self, max_results: u32) -> Self {
+        self.list_transactions_max_results = max_results;
+        self
+    }
+
+    fn max_document_size(mut self, bytes: usize) -> Self {
+        self.max_document_size = bytes;
+        self
+    }
+
+    fn blocking_threads(mut self, threads: usize) -> Self {
+        self.blocking_threads = Some(threads);
+        self
+    }
+
+    fn slow_operation_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_operation_threshold = Some(threshold);
+        self
+    }
+
+    fn slow_operation_level(mut self, level: log::Level) -> Self {
+        self.slow_operation_level = level;
+        self
+    }
+
     fn authenticated_permissions<P: Into<Permissions>>(
         mut self,
         authenticated_permissions: P,
@@ -1,4 +1,5 @@
 use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::Infallible;
 use std::ops::{self, Deref};
@@ -6,30 +7,30 @@ use std::sync::Arc;
 use std::u8;
 
 use bonsaidb_core::arc_bytes::serde::CowBytes;
-use bonsaidb_core::arc_bytes::ArcBytes;
+use bonsaidb_core::arc_bytes::{ArcBytes, OwnedBytes};
 use bonsaidb_core::connection::{
     self, AccessPolicy, Connection, HasSchema, HasSession, LowLevelConnection, Range,
-    SerializedQueryKey, Session, Sort, StorageConnection,
+    SerializedQueryKey, Session, SessionId, Sort, StorageConnection, ViewIndexState,
 };
 #[cfg(any(feature = "encryption", feature = "compression"))]
 use bonsaidb_core::document::KeyId;
 use bonsaidb_core::document::{BorrowedDocument, DocumentId, Header, OwnedDocument, Revision};
-use bonsaidb_core::keyvalue::{KeyOperation, Output, Timestamp};
-use bonsaidb_core::limits::{
-    LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT, LIST_TRANSACTIONS_MAX_RESULTS,
-};
+use bonsaidb_core::keyvalue::{Command as KeyCommand, KeyOperation, Output, Pipeline, Timestamp};
+use bonsaidb_core::limits::{IDEMPOTENCY_KEY_EXPIRATION, LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT};
 use bonsaidb_core::permissions::bonsai::{
     collection_resource_name, database_resource_name, document_resource_name, kv_resource_name,
     view_resource_name, BonsaiAction, DatabaseAction, DocumentAction, TransactionAction,
     ViewAction,
 };
 use bonsaidb_core::permissions::Permissions;
-use bonsaidb_core::schema::view::map::MappedSerializedValue;
+use bonsaidb_core::schema::view::map::{
+    MappedSerializedCount, MappedSerializedKey, MappedSerializedValue,
+};
 use bonsaidb_core::schema::view::{self};
 use bonsaidb_core::schema::{self, CollectionName, Schema, Schematic, ViewName};
 use bonsaidb_core::transaction::{
-    self, ChangedDocument, Changes, Command, DocumentChanges, Operation, OperationResult,
-    Transaction,
+    self, apply_merge_patch, ChangedDocument, Changes, Command, DocumentChanges, Operation,
+    OperationResult, Transaction, ViewChanges, ViewRowChanges,
 };
 use itertools::Itertools;
 use nebari::io::any::AnyFile;
@@ -42,16 +43,20 @@ use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use watchable::Watchable;
 
-use crate::config::{Builder, KeyValuePersistence, StorageConfiguration};
+use crate::config::{CorruptKeyValuePolicy, KeyValuePersistence, StorageConfiguration};
 use crate::database::keyvalue::BackgroundWorkerProcessTarget;
+use crate::database::transaction_log::EXECUTED_TRANSACTIONS_TREE;
 use crate::error::Error;
 use crate::open_trees::OpenTrees;
-use crate::storage::StorageLock;
-#[cfg(feature = "encryption")]
+use crate::slow_operations::{OperationTimer, SlowOperationLog};
+#[cfg(any(feature = "encryption", feature = "compression"))]
 use crate::storage::TreeVault;
+use crate::storage::{SchemaMigration, StorageLock};
+use crate::views::result_cache::{IndexPosition, ViewResultCache};
 use crate::views::{
-    mapper, view_document_map_tree_name, view_entries_tree_name, view_invalidated_docs_tree_name,
-    ViewEntry,
+    mapper, reductions, view_dependencies_tree_name, view_dependency_key,
+    view_document_map_tree_name, view_entries_tree_name, view_invalidated_docs_tree_name,
+    view_reductions_tree_name, ViewEntry, ViewEntryCount,
 };
 use crate::Storage;
 
@@ -59,6 +64,10 @@ pub mod keyvalue;
 
 pub(crate) mod compat;
 pub mod pubsub;
+pub(crate) mod transaction_log;
+mod verify;
+
+pub use self::verify::IntegrityProblem;
 
 /// A database stored in BonsaiDb. This type blocks the current thread when
 /// used. See [`AsyncDatabase`](crate::AsyncDatabase) for this type's async counterpart.
@@ -181,8 +190,16 @@ impl Database {
     /// have its own thread pool, cache, task worker pool, and more. By using a
     /// single [`Storage`] instance, BonsaiDb will use less resources and likely
     /// perform better.
-    pub fn open<DB: Schema>(configuration: StorageConfiguration) -> Result<Self, Error> {
-        let storage = Storage::open(configuration.with_schema::<DB>()?)?;
+    pub fn open<DB: Schema>(mut configuration: StorageConfiguration) -> Result<Self, Error> {
+        // Keep the schema's existing registration, which may include a
+        // migration.
+        if !configuration
+            .initial_schemas
+            .contains_key(&DB::schema_name())
+        {
+            configuration.register_schema::<DB>()?;
+        }
+        let storage = Storage::open(configuration)?;
 
         Ok(storage.create_database::<DB>("default", true)?)
     }
@@ -193,6 +210,33 @@ impl Database {
         &self.data.schema
     }
 
+    /// Compares the version of this database's schema against the version that
+    /// last opened it. An older version is refused. A newer version runs
+    /// `migration`, if provided, and then records the new version. This is
+    /// checked for each database when its storage is opened.
+    pub(crate) fn upgrade_schema_version(
+        &self,
+        migration: Option<&SchemaMigration>,
+    ) -> Result<(), Error> {
+        let schema_version = self.data.schema.version;
+        let stored_version = stored_schema_version(self.roots())?;
+        match stored_version.cmp(&schema_version) {
+            Ordering::Equal => Ok(()),
+            Ordering::Greater => Err(Error::Core(bonsaidb_core::Error::SchemaVersionMismatch {
+                database_name: self.name().to_string(),
+                schema: self.data.schema.name.clone(),
+                schema_version,
+                stored_version,
+            })),
+            Ordering::Less => {
+                if let Some(migration) = migration {
+                    migration(self, stored_version)?;
+                }
+                store_schema_version(self.roots(), schema_version)
+            }
+        }
+    }
+
     pub(crate) fn roots(&self) -> &'_ nebari::Roots<AnyFile> {
         &self.data.context.roots
     }
@@ -206,11 +250,51 @@ impl Database {
         access_policy: AccessPolicy,
         mut callback: F,
     ) -> Result<(), bonsaidb_core::Error> {
+        self.read_view_tree(
+            view,
+            view_entries_tree_name(&view.view_name()),
+            access_policy,
+            |view_entries| {
+                for entry in Self::create_view_iterator(view_entries, key, order, limit)? {
+                    callback(entry)?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Invokes `read` with the tree named `tree_name` that stores data for
+    /// `view`, updating the view before and/or after as requested by
+    /// `access_policy`.
+    fn read_view_tree<
+        T,
+        F: FnOnce(&Tree<Unversioned, AnyFile>) -> Result<T, bonsaidb_core::Error>,
+    >(
+        &self,
+        view: &dyn view::Serialized,
+        tree_name: String,
+        access_policy: AccessPolicy,
+        read: F,
+    ) -> Result<T, bonsaidb_core::Error> {
+        let session_write = if matches!(access_policy, AccessPolicy::ReadYourWrites) {
+            self.last_session_write()
+        } else {
+            None
+        };
         if matches!(access_policy, AccessPolicy::UpdateBefore) {
             self.storage
                 .instance
                 .tasks()
                 .update_view_if_needed(view, self, true)?;
+        } else if let Some(transaction_id) = session_write {
+            let tasks = self.storage.instance.tasks();
+            if let Some(integrity_check) = tasks.spawn_integrity_check(view, self) {
+                integrity_check
+                    .receive()
+                    .map_err(Error::from)?
+                    .map_err(Error::from)?;
+            }
+            tasks.update_view_through_transaction(view, self, transaction_id, true)?;
         } else if let Some(integrity_check) = self
             .storage
             .instance
@@ -223,21 +307,17 @@ impl Database {
                 .map_err(Error::from)?;
         }
 
-        let view_entries = self
+        let tree = self
             .roots()
-            .tree(self.collection_tree(
-                &view.collection(),
-                view_entries_tree_name(&view.view_name()),
-            )?)
+            .tree(self.collection_tree::<Unversioned, _>(&view.collection(), tree_name)?)
             .map_err(Error::from)?;
 
-        {
-            for entry in Self::create_view_iterator(&view_entries, key, order, limit)? {
-                callback(entry)?;
-            }
-        }
+        let result = read(&tree)?;
 
-        if matches!(access_policy, AccessPolicy::UpdateAfter) {
+        if matches!(
+            access_policy,
+            AccessPolicy::UpdateAfter | AccessPolicy::ReadYourWrites
+        ) {
             let db = self.clone();
             let view_name = view.view_name();
             let view = db
@@ -251,11 +331,241 @@ impl Database {
                 .update_view_if_needed(view, &db, false)?;
         }
 
+        Ok(result)
+    }
+
+    /// Queries `view`, returning results from `cache` if the same query was
+    /// cached while the view's index was at its current position.
+    fn query_with_result_cache(
+        &self,
+        cache: &ViewResultCache,
+        view: &dyn view::Serialized,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<schema::view::map::Serialized>, bonsaidb_core::Error> {
+        let view_name = view.view_name();
+        let parameters = pot::to_vec(&(&key, order, limit)).map_err(Error::from)?;
+        self.read_view_tree(
+            view,
+            view_entries_tree_name(&view_name),
+            access_policy,
+            |view_entries| {
+                // The position is read after any update requested by the
+                // access policy has completed.
+                let position = match self.storage.instance.tasks().last_indexed_transaction_id(
+                    self.data.name.clone(),
+                    view.collection(),
+                    view_name.clone(),
+                ) {
+                    Some(indexed) => Some(IndexPosition {
+                        indexed,
+                        database: self.last_transaction_id()?,
+                    }),
+                    None => None,
+                };
+                if let Some(position) = position {
+                    if let Some(results) =
+                        cache.get(&self.data.name, &view_name, &parameters, position)
+                    {
+                        return Ok(results);
+                    }
+                }
+
+                let mut results = Vec::new();
+                for entry in Self::create_view_iterator(view_entries, key, order, limit)? {
+                    for mapping in entry.mappings {
+                        results.push(bonsaidb_core::schema::view::map::Serialized {
+                            source: mapping.source,
+                            key: entry.key.clone(),
+                            value: mapping.value,
+                        });
+                    }
+                }
+
+                if let Some(position) = position {
+                    cache.insert(
+                        &self.data.name,
+                        &view_name,
+                        parameters,
+                        position,
+                        results.clone(),
+                    );
+                }
+
+                Ok(results)
+            },
+        )
+    }
+
+    /// Queries the view named `view`, invoking `callback` with the results in
+    /// chunks of at most `chunk_size` mappings. The chunks are provided in the
+    /// same order that [`query_by_name()`](LowLevelConnection::query_by_name)
+    /// returns mappings in.
+    ///
+    /// If `callback` returns an error, the query is stopped and the error is
+    /// returned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn query_by_name_in_chunks<F>(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+        chunk_size: usize,
+        mut callback: F,
+    ) -> Result<(), bonsaidb_core::Error>
+    where
+        F: FnMut(Vec<schema::view::map::Serialized>) -> Result<(), bonsaidb_core::Error>
+            + Send
+            + Sync,
+    {
+        let view = self.schematic().view_by_name(view)?;
+        self.check_permission(
+            view_resource_name(self.name(), &view.view_name()),
+            &BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
+        )?;
+        let chunk_size = chunk_size.max(1);
+        let mut chunk = Vec::with_capacity(chunk_size);
+        let mut push_entry = |entry: ViewEntry| {
+            for mapping in entry.mappings {
+                chunk.push(bonsaidb_core::schema::view::map::Serialized {
+                    source: mapping.source,
+                    key: entry.key.clone(),
+                    value: mapping.value,
+                });
+                if chunk.len() == chunk_size {
+                    callback(std::mem::replace(
+                        &mut chunk,
+                        Vec::with_capacity(chunk_size),
+                    ))?;
+                }
+            }
+            Ok(())
+        };
+
+        let range = match key {
+            Some(SerializedQueryKey::Range(range)) => range.map(|bytes| bytes.to_vec()),
+            None => Range {
+                start: connection::Bound::Unbounded,
+                end: connection::Bound::Unbounded,
+            },
+            // Only the requested keys are read, so these results are bounded
+            // by the size of the query.
+            Some(key) => {
+                self.for_each_in_view(view, Some(key), order, limit, access_policy, push_entry)?;
+                if !chunk.is_empty() {
+                    callback(chunk)?;
+                }
+                return Ok(());
+            }
+        };
+
+        self.read_view_tree(
+            view,
+            view_entries_tree_name(&view.view_name()),
+            access_policy,
+            |view_entries| {
+                Self::scan_view_in_chunks(
+                    view_entries,
+                    range,
+                    order,
+                    limit,
+                    chunk_size,
+                    &mut push_entry,
+                )
+            },
+        )?;
+
+        if !chunk.is_empty() {
+            callback(chunk)?;
+        }
+
+        Ok(())
+    }
+
+    /// Scans the entries of `view_entries` within `range`, reading at most
+    /// `chunk_size` entries at a time before invoking `callback` with each of
+    /// them. Only one batch of entries is held in memory at a time. Each batch
+    /// is read separately, so changes made to the view while it is being
+    /// scanned may be partially reflected.
+    fn scan_view_in_chunks(
+        view_entries: &Tree<Unversioned, AnyFile>,
+        mut range: Range<Vec<u8>>,
+        order: Sort,
+        limit: Option<u32>,
+        chunk_size: usize,
+        callback: &mut dyn FnMut(ViewEntry) -> Result<(), bonsaidb_core::Error>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let forwards = matches!(order, Sort::Ascending);
+        let mut remaining = limit.map_or(usize::MAX, |limit| {
+            usize::try_from(limit).unwrap_or(usize::MAX)
+        });
+        while remaining > 0 {
+            let to_read = chunk_size.min(remaining);
+            let mut keys_read = 0;
+            let mut entries = Vec::with_capacity(to_read);
+            let mut last_key = None;
+            view_entries
+                .scan::<Infallible, _, _, _, _>(
+                    &range.map_ref(|bytes| &bytes[..]),
+                    forwards,
+                    |_, _, _| ScanEvaluation::ReadData,
+                    |_, _| {
+                        if keys_read >= to_read {
+                            return ScanEvaluation::Stop;
+                        }
+                        keys_read += 1;
+                        ScanEvaluation::ReadData
+                    },
+                    |key, _, value| {
+                        last_key = Some(key.to_vec());
+                        entries.push(value);
+                        Ok(())
+                    },
+                )
+                .map_err(Error::from)?;
+
+            let entries_read = entries.len();
+            for value in entries {
+                callback(bincode::deserialize(&value).map_err(Error::from)?)?;
+            }
+
+            match last_key {
+                Some(last_key) if entries_read == to_read => {
+                    remaining -= entries_read;
+                    if forwards {
+                        range.start = connection::Bound::Excluded(last_key);
+                    } else {
+                        range.end = connection::Bound::Excluded(last_key);
+                    }
+                }
+                // Fewer entries than requested were read, so the scan reached
+                // the end of the range.
+                _ => break,
+            }
+        }
+
         Ok(())
     }
 
     fn open_trees_for_transaction(&self, transaction: &Transaction) -> Result<OpenTrees, Error> {
         let mut open_trees = OpenTrees::default();
+        open_trees.open_tree::<Unversioned>(
+            EXECUTED_TRANSACTIONS_TREE,
+            #[cfg(any(feature = "encryption", feature = "compression"))]
+            self.storage().tree_vault().cloned(),
+        );
+        if transaction.idempotency_key.is_some() {
+            open_trees.open_tree::<Unversioned>(
+                IDEMPOTENCY_TREE,
+                #[cfg(any(feature = "encryption", feature = "compression"))]
+                self.storage().tree_vault().cloned(),
+            );
+        }
+
         for op in &transaction.operations {
             if self
                 .data
@@ -267,30 +577,26 @@ impl Database {
             }
 
             #[cfg(any(feature = "encryption", feature = "compression"))]
-            let vault = if let Some(encryption_key) =
-                self.collection_encryption_key(&op.collection).cloned()
-            {
-                #[cfg(feature = "encryption")]
-                if let Some(mut vault) = self.storage().tree_vault().cloned() {
-                    vault.key = Some(encryption_key);
-                    Some(vault)
-                } else {
-                    TreeVault::new_if_needed(
-                        Some(encryption_key),
-                        self.storage().vault(),
-                        #[cfg(feature = "compression")]
-                        None,
-                    )
-                }
+            let vault = self.transaction_tree_vault(&op.collection)?;
 
-                #[cfg(not(feature = "encryption"))]
-                {
-                    drop(encryption_key);
-                    return Err(Error::EncryptionDisabled);
-                }
-            } else {
-                self.storage().tree_vault().cloned()
-            };
+            if matches!(op.command, Command::SoftDelete { .. }) {
+                open_trees.open_tree::<Unversioned>(
+                    &document_tombstones_tree_name(&op.collection),
+                    #[cfg(any(feature = "encryption", feature = "compression"))]
+                    vault.clone(),
+                );
+            }
+
+            if matches!(
+                op.command,
+                Command::Insert { .. } | Command::InsertWithId { .. } | Command::Overwrite { .. }
+            ) {
+                open_trees.open_tree::<Unversioned>(
+                    &document_ids_tree_name(&op.collection),
+                    #[cfg(any(feature = "encryption", feature = "compression"))]
+                    vault.clone(),
+                );
+            }
 
             open_trees.open_trees_for_document_change(
                 &op.collection,
@@ -298,14 +604,58 @@ impl Database {
                 #[cfg(any(feature = "encryption", feature = "compression"))]
                 vault,
             );
+
+            // Lazy views that read documents from this collection need to be
+            // invalidated when those documents change.
+            for view in self.data.schema.views().filter(|view| {
+                !view.update_policy().is_eager()
+                    && !view.deletion_history()
+                    && view.related_collections().contains(&op.collection)
+            }) {
+                open_trees.open_trees_for_related_view(
+                    &view.view_name(),
+                    #[cfg(any(feature = "encryption", feature = "compression"))]
+                    self.transaction_tree_vault(&view.collection())?,
+                );
+            }
         }
 
         Ok(open_trees)
     }
 
+    #[cfg(any(feature = "encryption", feature = "compression"))]
+    fn transaction_tree_vault(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<Option<TreeVault>, Error> {
+        if let Some(encryption_key) = self.collection_encryption_key(collection).cloned() {
+            #[cfg(feature = "encryption")]
+            if let Some(mut vault) = self.storage().tree_vault().cloned() {
+                vault.key = Some(encryption_key);
+                Ok(Some(vault))
+            } else {
+                Ok(TreeVault::new_if_needed(
+                    Some(encryption_key),
+                    self.storage().vault(),
+                    #[cfg(feature = "compression")]
+                    None,
+                ))
+            }
+
+            #[cfg(not(feature = "encryption"))]
+            {
+                drop(encryption_key);
+                Err(Error::EncryptionDisabled)
+            }
+        } else {
+            Ok(self.storage().tree_vault().cloned())
+        }
+    }
+
     fn apply_transaction_to_roots(
         &self,
         transaction: &Transaction,
+        mut view_rows: Option<&mut Vec<Vec<ViewRowChanges>>>,
     ) -> Result<Vec<OperationResult>, Error> {
         let open_trees = self.open_trees_for_transaction(transaction)?;
 
@@ -315,16 +665,38 @@ impl Database {
             .roots
             .transaction::<_, dyn AnyTreeRoot<AnyFile>>(&open_trees.trees)?;
 
+        // The idempotency tree is locked by the transaction, so transactions
+        // using the same idempotency key are applied one at a time.
+        let now = Timestamp::now();
+        if let Some(idempotency_key) = &transaction.idempotency_key {
+            if let Some(results) = Self::applied_idempotent_results(
+                &mut roots_transaction,
+                &open_trees,
+                idempotency_key,
+                now,
+            )? {
+                // Dropping the transaction rolls it back.
+                return Ok(results);
+            }
+        }
+
         let mut results = Vec::new();
         let mut changed_documents = Vec::new();
         let mut collection_indexes = HashMap::new();
         let mut collections = Vec::new();
         for op in &transaction.operations {
+            let mut operation_view_rows = view_rows.is_some().then(Vec::new);
             let result = self.execute_operation(
                 op,
                 &mut roots_transaction,
                 &open_trees.trees_index_by_name,
+                operation_view_rows.as_mut(),
             )?;
+            if let (Some(view_rows), Some(operation_view_rows)) =
+                (view_rows.as_deref_mut(), operation_view_rows)
+            {
+                view_rows.push(operation_view_rows);
+            }
 
             if let Some((collection, id, deleted)) = match &result {
                 OperationResult::DocumentUpdated { header, collection } => {
@@ -362,21 +734,271 @@ impl Database {
             &collections,
             &changed_documents,
         )?;
+        self.invalidate_dependent_documents(
+            &mut roots_transaction,
+            &open_trees,
+            &collections,
+            &changed_documents,
+        )?;
+
+        if let Some(idempotency_key) = &transaction.idempotency_key {
+            Self::record_idempotent_results(
+                &mut roots_transaction,
+                &open_trees,
+                idempotency_key,
+                &results,
+                now,
+            )?;
+        }
 
-        roots_transaction
-            .entry_mut()
-            .set_data(compat::serialize_executed_transaction_changes(
+        let transaction_id = roots_transaction.entry().id;
+        let mut executed_transactions = roots_transaction
+            .tree::<Unversioned>(open_trees.trees_index_by_name[EXECUTED_TRANSACTIONS_TREE])
+            .unwrap();
+        transaction_log::record(
+            &mut executed_transactions,
+            transaction_id,
+            now,
+            compat::serialize_executed_transaction(
                 &Changes::Documents(DocumentChanges {
                     collections,
                     documents: changed_documents,
                 }),
-            )?)?;
+                transaction.metadata.as_ref(),
+            )?,
+        )?;
+        // Only one batch of transactions outside of the retention is removed,
+        // limiting the work done by any single transaction. Any remaining
+        // transactions are removed by subsequent transactions.
+        transaction_log::prune_batch(
+            &mut executed_transactions,
+            &transaction_log::Cutoff::for_retention(
+                self.storage.instance.transaction_log_retention(),
+                Some(transaction_id),
+            ),
+        )?;
+        drop(executed_transactions);
 
         roots_transaction.commit()?;
+        self.record_session_write(transaction_id);
 
         Ok(results)
     }
 
+    /// Removes every document in `collection` by clearing the collection's
+    /// document and view trees in a single transaction, returning the number
+    /// of documents removed.
+    ///
+    /// Every tree the collection's documents are stored or indexed in is
+    /// locked by the transaction, preventing other transactions from changing
+    /// the collection until it completes.
+    fn clear_collection(&self, collection: &CollectionName) -> Result<u64, Error> {
+        if self
+            .data
+            .schema
+            .collection_primary_key_description(collection)
+            .is_none()
+        {
+            return Err(Error::Core(bonsaidb_core::Error::CollectionNotFound));
+        }
+
+        #[cfg(any(feature = "encryption", feature = "compression"))]
+        let vault = self.transaction_tree_vault(collection)?;
+
+        let mut open_trees = OpenTrees::default();
+        open_trees.open_tree::<Unversioned>(
+            EXECUTED_TRANSACTIONS_TREE,
+            #[cfg(any(feature = "encryption", feature = "compression"))]
+            self.storage().tree_vault().cloned(),
+        );
+        open_trees.open_trees_for_document_change(
+            collection,
+            &self.data.schema,
+            #[cfg(any(feature = "encryption", feature = "compression"))]
+            vault.clone(),
+        );
+        // Unlike deleting individual documents, the indexes of every view in
+        // the collection are cleared, including the indexes of lazy views.
+        let mut cleared_view_trees = Vec::new();
+        for view in self
+            .data
+            .schema
+            .views_in_collection(collection)
+            .filter(|view| !view.deletion_history())
+        {
+            let view_name = view.view_name();
+            cleared_view_trees.push(view_entries_tree_name(&view_name));
+            cleared_view_trees.push(view_document_map_tree_name(&view_name));
+            cleared_view_trees.push(view_invalidated_docs_tree_name(&view_name));
+            if view.incremental_reduce() {
+                cleared_view_trees.push(view_reductions_tree_name(&view_name));
+            }
+            if !view.related_collections().is_empty() {
+                cleared_view_trees.push(view_dependencies_tree_name(&view_name));
+            }
+        }
+        for tree_name in &cleared_view_trees {
+            open_trees.open_tree::<Unversioned>(
+                tree_name,
+                #[cfg(any(feature = "encryption", feature = "compression"))]
+                vault.clone(),
+            );
+        }
+        for view in self.data.schema.views().filter(|view| {
+            !view.update_policy().is_eager()
+                && !view.deletion_history()
+                && view.related_collections().contains(collection)
+        }) {
+            open_trees.open_trees_for_related_view(
+                &view.view_name(),
+                #[cfg(any(feature = "encryption", feature = "compression"))]
+                self.transaction_tree_vault(&view.collection())?,
+            );
+        }
+
+        let mut roots_transaction = self
+            .data
+            .context
+            .roots
+            .transaction::<_, dyn AnyTreeRoot<AnyFile>>(&open_trees.trees)?;
+
+        // Deletion history views need each deleted document's header, which
+        // requires reading the documents rather than only their ids.
+        let read_documents = self
+            .data
+            .schema
+            .eager_views_in_collection(collection)
+            .any(|view| view.deletion_history());
+        let mut ids = Vec::new();
+        let mut documents = Vec::new();
+        let mut documents_tree = roots_transaction
+            .tree::<Versioned>(open_trees.trees_index_by_name[&document_tree_name(collection)])
+            .unwrap();
+        documents_tree.scan::<Infallible, _, _, _, _>(
+            &(..),
+            true,
+            |_, _, _| ScanEvaluation::ReadData,
+            |key, _| {
+                ids.push(key.clone());
+                if read_documents {
+                    ScanEvaluation::ReadData
+                } else {
+                    ScanEvaluation::Skip
+                }
+            },
+            |_, _, document| {
+                documents.push(document);
+                Ok(())
+            },
+        )?;
+        if ids.is_empty() {
+            // Dropping the transaction rolls it back.
+            return Ok(0);
+        }
+        documents_tree.modify(ids.clone(), nebari::tree::Operation::Remove)?;
+        drop(documents_tree);
+
+        let deleted_at = Timestamp::now();
+        for document in documents {
+            let document = deserialize_document(&document)?;
+            self.update_deletion_history_views(
+                &document.header,
+                deleted_at,
+                collection,
+                &mut roots_transaction,
+                &open_trees.trees_index_by_name,
+                None,
+            )?;
+        }
+
+        let collections = vec![collection.clone()];
+        let changed_documents = ids
+            .iter()
+            .map(|id| {
+                Ok(ChangedDocument {
+                    collection: 0,
+                    id: DocumentId::try_from(id.as_slice())?,
+                    deleted: true,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        // Views in other collections that read the removed documents are
+        // invalidated before the collection's own view trees are cleared,
+        // which removes any invalidations recorded for the collection's views.
+        self.invalidate_dependent_documents(
+            &mut roots_transaction,
+            &open_trees,
+            &collections,
+            &changed_documents,
+        )?;
+        for tree_name in &cleared_view_trees {
+            let mut tree = roots_transaction
+                .tree::<Unversioned>(open_trees.trees_index_by_name[tree_name])
+                .unwrap();
+            let mut keys = Vec::new();
+            tree.scan::<Infallible, _, _, _, _>(
+                &(..),
+                true,
+                |_, _, _| ScanEvaluation::ReadData,
+                |key, _| {
+                    keys.push(key.clone());
+                    ScanEvaluation::Skip
+                },
+                |_, _, _| unreachable!(),
+            )?;
+            if !keys.is_empty() {
+                tree.modify(keys, nebari::tree::Operation::Remove)?;
+            }
+        }
+
+        let transaction_id = roots_transaction.entry().id;
+        let now = Timestamp::now();
+        let mut executed_transactions = roots_transaction
+            .tree::<Unversioned>(open_trees.trees_index_by_name[EXECUTED_TRANSACTIONS_TREE])
+            .unwrap();
+        transaction_log::record(
+            &mut executed_transactions,
+            transaction_id,
+            now,
+            compat::serialize_executed_transaction_changes(&Changes::Documents(DocumentChanges {
+                collections,
+                documents: changed_documents,
+            }))?,
+        )?;
+        transaction_log::prune_batch(
+            &mut executed_transactions,
+            &transaction_log::Cutoff::for_retention(
+                self.storage.instance.transaction_log_retention(),
+                Some(transaction_id),
+            ),
+        )?;
+        drop(executed_transactions);
+
+        roots_transaction.commit()?;
+        self.record_session_write(transaction_id);
+
+        Ok(ids.len() as u64)
+    }
+
+    fn record_session_write(&self, transaction_id: u64) {
+        let session_id = self.session().and_then(|session| session.id);
+        let mut last_session_writes = self.data.context.last_session_writes.lock();
+        let last_write = last_session_writes.entry(session_id).or_default();
+        *last_write = (*last_write).max(transaction_id);
+    }
+
+    /// Returns the id of the most recent transaction committed through this
+    /// database by the current session, if any.
+    fn last_session_write(&self) -> Option<u64> {
+        let session_id = self.session().and_then(|session| session.id);
+        self.data
+            .context
+            .last_session_writes
+            .lock()
+            .get(&session_id)
+            .copied()
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
     fn invalidate_changed_documents(
         &self,
@@ -393,7 +1015,7 @@ impl Database {
                 .data
                 .schema
                 .views_in_collection(collection)
-                .filter(|view| !view.update_policy().is_eager())
+                .filter(|view| !view.update_policy().is_eager() && !view.deletion_history())
                 .peekable();
             if views.peek().is_some() {
                 let changed_documents = changed_documents.collect::<Vec<_>>();
@@ -412,16 +1034,104 @@ impl Database {
         Ok(())
     }
 
+    /// Invalidates documents in lazy views that read one of the changed
+    /// documents through a [`MapContext`](bonsaidb_core::schema::view::map::MapContext).
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn invalidate_dependent_documents(
+        &self,
+        roots_transaction: &mut ExecutingTransaction<AnyFile>,
+        open_trees: &OpenTrees,
+        collections: &[CollectionName],
+        changed_documents: &[ChangedDocument],
+    ) -> Result<(), Error> {
+        for (collection, changed_documents) in &changed_documents
+            .iter()
+            .group_by(|doc| &collections[usize::from(doc.collection)])
+        {
+            let mut views = self
+                .data
+                .schema
+                .views()
+                .filter(|view| {
+                    !view.update_policy().is_eager()
+                        && !view.deletion_history()
+                        && view.related_collections().contains(collection)
+                })
+                .peekable();
+            if views.peek().is_some() {
+                let changed_documents = changed_documents.collect::<Vec<_>>();
+                for view in views {
+                    let view_name = view.view_name();
+                    let mut dependent_ids = HashSet::new();
+                    {
+                        let mut dependencies = roots_transaction
+                            .tree::<Unversioned>(
+                                open_trees.trees_index_by_name
+                                    [&view_dependencies_tree_name(&view_name)],
+                            )
+                            .unwrap();
+                        for changed_document in &changed_documents {
+                            // The dependency will be recorded again when the
+                            // dependent documents are mapped.
+                            if let Some(dependents) = dependencies
+                                .remove(&view_dependency_key(collection, &changed_document.id))?
+                            {
+                                dependent_ids.extend(bincode::deserialize::<HashSet<OwnedBytes>>(
+                                    &dependents,
+                                )?);
+                            }
+                        }
+                    }
+
+                    if !dependent_ids.is_empty() {
+                        let mut invalidated_docs = roots_transaction
+                            .tree::<Unversioned>(
+                                open_trees.trees_index_by_name
+                                    [&view_invalidated_docs_tree_name(&view_name)],
+                            )
+                            .unwrap();
+                        for id in dependent_ids {
+                            invalidated_docs.set(id.0, b"")?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn execute_operation(
         &self,
         operation: &Operation,
         transaction: &mut ExecutingTransaction<AnyFile>,
         tree_index_map: &HashMap<String, usize>,
+        view_rows: Option<&mut Vec<ViewRowChanges>>,
     ) -> Result<OperationResult, Error> {
+        if let Command::Insert { contents, .. }
+        | Command::InsertWithId { contents, .. }
+        | Command::Update { contents, .. }
+        | Command::Overwrite { contents, .. } = &operation.command
+        {
+            self.check_document_size(contents)?;
+        }
+
         match &operation.command {
-            Command::Insert { id, contents } => {
-                self.execute_insert(operation, transaction, tree_index_map, id.clone(), contents)
-            }
+            Command::Insert { id, contents } => self.execute_insert(
+                operation,
+                transaction,
+                tree_index_map,
+                id.clone(),
+                contents,
+                view_rows,
+            ),
+            Command::InsertWithId { id, contents } => self.execute_insert(
+                operation,
+                transaction,
+                tree_index_map,
+                Some(id.clone()),
+                contents,
+                view_rows,
+            ),
             Command::Update { header, contents } => self.execute_update(
                 operation,
                 transaction,
@@ -429,13 +1139,37 @@ impl Database {
                 &header.id,
                 Some(&header.revision),
                 contents,
+                view_rows,
             ),
             Command::Overwrite { id, contents } => {
-                self.execute_update(operation, transaction, tree_index_map, id, None, contents)
-            }
-            Command::Delete { header } => {
-                self.execute_delete(operation, transaction, tree_index_map, header)
+                let result = self.execute_update(
+                    operation,
+                    transaction,
+                    tree_index_map,
+                    id,
+                    None,
+                    contents,
+                    view_rows,
+                )?;
+                Self::record_document_id(transaction, tree_index_map, &operation.collection, id)?;
+                Ok(result)
             }
+            Command::Delete { header } => self.execute_delete(
+                operation,
+                transaction,
+                tree_index_map,
+                header,
+                false,
+                view_rows,
+            ),
+            Command::SoftDelete { header } => self.execute_delete(
+                operation,
+                transaction,
+                tree_index_map,
+                header,
+                true,
+                view_rows,
+            ),
             Command::Check { id, revision } => Self::execute_check(
                 operation,
                 transaction,
@@ -443,14 +1177,40 @@ impl Database {
                 id.clone(),
                 *revision,
             ),
+            Command::Patch { header, patch } => self.execute_patch(
+                operation,
+                transaction,
+                tree_index_map,
+                header,
+                patch,
+                view_rows,
+            ),
+        }
+    }
+
+    fn check_document_size(&self, contents: &[u8]) -> Result<(), Error> {
+        let limit = self.storage.instance.max_document_size();
+        if limit > 0 && contents.len() > limit {
+            Err(Error::Core(bonsaidb_core::Error::DocumentTooLarge {
+                size: contents.len(),
+                limit,
+            }))
+        } else {
+            Ok(())
         }
     }
 
+    /// Starts timing `operation`, which is logged if it is slower than the
+    /// configured threshold.
+    fn time_operation(&self, operation: &'static str) -> OperationTimer {
+        self.storage.instance.slow_operation_log().start(operation)
+    }
+
     #[cfg_attr(
         feature = "tracing",
         tracing::instrument(
             level = "trace",
-            skip(self, operation, transaction, tree_index_map, contents),
+            skip(self, operation, transaction, tree_index_map, contents, view_rows),
             fields(
                 database = self.name(),
                 collection.name = operation.collection.name.as_ref(),
@@ -458,6 +1218,7 @@ impl Database {
             )
         )
     )]
+    #[allow(clippy::too_many_arguments)]
     fn execute_update(
         &self,
         operation: &Operation,
@@ -466,6 +1227,7 @@ impl Database {
         id: &DocumentId,
         check_revision: Option<&Revision>,
         contents: &[u8],
+        view_rows: Option<&mut Vec<ViewRowChanges>>,
     ) -> Result<OperationResult, crate::Error> {
         let mut documents = transaction
             .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
@@ -524,6 +1286,7 @@ impl Database {
                         result = Some(Err(Error::Core(bonsaidb_core::Error::DocumentConflict(
                             operation.collection.clone(),
                             Box::new(doc.header),
+                            check_revision.copied(),
                         ))));
                     }
                 } else if check_revision.is_none() {
@@ -553,7 +1316,13 @@ impl Database {
         drop(documents);
 
         if updated {
-            self.update_eager_views(&document_id, operation, transaction, tree_index_map)?;
+            self.update_eager_views(
+                &document_id,
+                operation,
+                transaction,
+                tree_index_map,
+                view_rows,
+            )?;
         }
 
         result.expect("nebari should invoke the callback even when the key isn't found")
@@ -563,7 +1332,7 @@ impl Database {
         feature = "tracing",
         tracing::instrument(
             level = "trace",
-            skip(self, operation, transaction, tree_index_map, contents),
+            skip(self, operation, transaction, tree_index_map, contents, view_rows),
             fields(
                 database = self.name(),
                 collection.name = operation.collection.name.as_ref(),
@@ -578,14 +1347,14 @@ impl Database {
         tree_index_map: &HashMap<String, usize>,
         id: Option<DocumentId>,
         contents: &[u8],
+        view_rows: Option<&mut Vec<ViewRowChanges>>,
     ) -> Result<OperationResult, Error> {
-        let mut documents = transaction
-            .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
-            .unwrap();
         let id = if let Some(id) = id {
             id
-        } else if let Some(last_key) = documents.last_key()? {
-            let id = DocumentId::try_from(last_key.as_slice())?;
+        } else if let Some(largest_id) =
+            Self::largest_document_id(transaction, tree_index_map, &operation.collection)?
+        {
+            let id = DocumentId::try_from(largest_id.as_slice())?;
             self.data
                 .schema
                 .next_id_for_collection(&operation.collection, Some(id))?
@@ -595,6 +1364,10 @@ impl Database {
                 .next_id_for_collection(&operation.collection, None)?
         };
 
+        let mut documents = transaction
+            .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
+            .unwrap();
+
         let doc = BorrowedDocument::new(id, contents);
         let serialized: Vec<u8> = serialize_document(&doc)?;
         let document_id = ArcBytes::from(doc.header.id.as_ref().to_vec());
@@ -603,10 +1376,23 @@ impl Database {
             Err(Error::Core(bonsaidb_core::Error::DocumentConflict(
                 operation.collection.clone(),
                 Box::new(doc.header),
+                None,
             )))
         } else {
             drop(documents);
-            self.update_eager_views(&document_id, operation, transaction, tree_index_map)?;
+            Self::record_document_id(
+                transaction,
+                tree_index_map,
+                &operation.collection,
+                &doc.header.id,
+            )?;
+            self.update_eager_views(
+                &document_id,
+                operation,
+                transaction,
+                tree_index_map,
+                view_rows,
+            )?;
 
             Ok(OperationResult::DocumentUpdated {
                 collection: operation.collection.clone(),
@@ -615,9 +1401,55 @@ impl Database {
         }
     }
 
+    /// Returns the largest document id ever stored in `collection`, including
+    /// the ids of documents that have since been deleted.
+    fn largest_document_id(
+        transaction: &mut ExecutingTransaction<AnyFile>,
+        tree_index_map: &HashMap<String, usize>,
+        collection: &CollectionName,
+    ) -> Result<Option<ArcBytes<'static>>, Error> {
+        let recorded = transaction
+            .tree::<Unversioned>(tree_index_map[&document_ids_tree_name(collection)])
+            .unwrap()
+            .get(LARGEST_DOCUMENT_ID_KEY)?;
+        let stored = transaction
+            .tree::<Versioned>(tree_index_map[&document_tree_name(collection)])
+            .unwrap()
+            .last_key()?;
+        // Collections written before the largest id was recorded only have
+        // their stored documents to go by.
+        Ok(match (recorded, stored) {
+            (Some(recorded), Some(stored)) if recorded.as_slice() > stored.as_slice() => {
+                Some(recorded)
+            }
+            (recorded, None) => recorded,
+            (_, stored) => stored,
+        })
+    }
+
+    /// Records `id` as the largest document id ever stored in `collection` if
+    /// it is larger than the currently recorded id.
+    fn record_document_id(
+        transaction: &mut ExecutingTransaction<AnyFile>,
+        tree_index_map: &HashMap<String, usize>,
+        collection: &CollectionName,
+        id: &DocumentId,
+    ) -> Result<(), Error> {
+        let mut ids = transaction
+            .tree::<Unversioned>(tree_index_map[&document_ids_tree_name(collection)])
+            .unwrap();
+        let is_largest = ids
+            .get(LARGEST_DOCUMENT_ID_KEY)?
+            .map_or(true, |largest| id.as_ref() > largest.as_slice());
+        if is_largest {
+            ids.set(LARGEST_DOCUMENT_ID_KEY, id.to_vec())?;
+        }
+        Ok(())
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
-        skip(self, operation, transaction, tree_index_map),
+        skip(self, operation, transaction, tree_index_map, view_rows),
         fields(
             database = self.name(),
             collection.name = operation.collection.name.as_ref(),
@@ -630,6 +1462,8 @@ impl Database {
         transaction: &mut ExecutingTransaction<AnyFile>,
         tree_index_map: &HashMap<String, usize>,
         header: &Header,
+        retain_tombstone: bool,
+        mut view_rows: Option<&mut Vec<ViewRowChanges>>,
     ) -> Result<OperationResult, Error> {
         let mut documents = transaction
             .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
@@ -638,13 +1472,36 @@ impl Database {
             drop(documents);
             let doc = deserialize_document(&vec)?;
             if &doc.header == header {
+                let deleted_at = Timestamp::now();
                 self.update_eager_views(
                     &ArcBytes::from(doc.header.id.to_vec()),
                     operation,
                     transaction,
                     tree_index_map,
+                    view_rows.as_deref_mut(),
+                )?;
+                self.update_deletion_history_views(
+                    header,
+                    deleted_at,
+                    &operation.collection,
+                    transaction,
+                    tree_index_map,
+                    view_rows,
                 )?;
 
+                if retain_tombstone {
+                    let tombstone = pot::to_vec(&Tombstone {
+                        deleted_at,
+                        document: doc,
+                    })?;
+                    let mut tombstones = transaction
+                        .tree::<Unversioned>(
+                            tree_index_map[&document_tombstones_tree_name(&operation.collection)],
+                        )
+                        .unwrap();
+                    tombstones.set(header.id.to_vec(), tombstone)?;
+                }
+
                 Ok(OperationResult::DocumentDeleted {
                     collection: operation.collection.clone(),
                     id: header.id.clone(),
@@ -652,7 +1509,8 @@ impl Database {
             } else {
                 Err(Error::Core(bonsaidb_core::Error::DocumentConflict(
                     operation.collection.clone(),
-                    Box::new(header.clone()),
+                    Box::new(doc.header),
+                    Some(header.revision),
                 )))
             }
         } else {
@@ -665,7 +1523,7 @@ impl Database {
 
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
-        skip(self, operation, transaction, tree_index_map),
+        skip(self, operation, transaction, tree_index_map, view_rows),
         fields(
             database = self.name(),
             collection.name = operation.collection.name.as_ref(),
@@ -678,11 +1536,13 @@ impl Database {
         operation: &Operation,
         transaction: &mut ExecutingTransaction<AnyFile>,
         tree_index_map: &HashMap<String, usize>,
+        mut view_rows: Option<&mut Vec<ViewRowChanges>>,
     ) -> Result<(), Error> {
         let mut eager_views = self
             .data
             .schema
             .eager_views_in_collection(&operation.collection)
+            .filter(|view| !view.deletion_history())
             .peekable();
         if eager_views.peek().is_some() {
             let documents = transaction
@@ -696,6 +1556,12 @@ impl Database {
                 let view_entries = transaction
                     .unlocked_tree(tree_index_map[&view_entries_tree_name(&name)])
                     .unwrap();
+                let reductions = tree_index_map
+                    .get(&view_reductions_tree_name(&name))
+                    .and_then(|index| transaction.unlocked_tree(*index));
+                let mut changes = view_rows
+                    .is_some()
+                    .then(|| ViewRowChanges::new(name.clone()));
                 mapper::DocumentRequest {
                     database: self,
                     document_ids: vec![document_id.clone()],
@@ -707,15 +1573,130 @@ impl Database {
                     document_map,
                     documents,
                     view_entries,
+                    dependencies: None,
+                    reductions,
                     view,
+                    changes: changes.as_mut(),
                 }
                 .map()?;
+                if let (Some(view_rows), Some(changes)) = (view_rows.as_deref_mut(), changes) {
+                    if !changes.is_empty() {
+                        view_rows.push(changes);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the entries that [deletion history
+    /// views](bonsaidb_core::schema::ViewSchema::deletion_history) emit for a
+    /// document being deleted.
+    fn update_deletion_history_views(
+        &self,
+        header: &Header,
+        deleted_at: Timestamp,
+        collection: &CollectionName,
+        transaction: &mut ExecutingTransaction<AnyFile>,
+        tree_index_map: &HashMap<String, usize>,
+        mut view_rows: Option<&mut Vec<ViewRowChanges>>,
+    ) -> Result<(), Error> {
+        for view in self
+            .data
+            .schema
+            .eager_views_in_collection(collection)
+            .filter(|view| view.deletion_history())
+        {
+            let name = view.view_name();
+            let view_entries = transaction
+                .unlocked_tree(tree_index_map[&view_entries_tree_name(&name)])
+                .unwrap();
+            let reductions = tree_index_map
+                .get(&view_reductions_tree_name(&name))
+                .and_then(|index| transaction.unlocked_tree(*index));
+            let mut changes = view_rows
+                .is_some()
+                .then(|| ViewRowChanges::new(name.clone()));
+            mapper::map_deleted_document(
+                view,
+                &mapper::Map {
+                    database: self.data.name.clone(),
+                    collection: collection.clone(),
+                    view_name: name,
+                },
+                header,
+                deleted_at,
+                view_entries,
+                reductions,
+                changes.as_mut(),
+            )?;
+            if let (Some(view_rows), Some(changes)) = (view_rows.as_deref_mut(), changes) {
+                if !changes.is_empty() {
+                    view_rows.push(changes);
+                }
             }
         }
 
         Ok(())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip(self, operation, transaction, tree_index_map, patch, view_rows),
+            fields(
+                database = self.name(),
+                collection.name = operation.collection.name.as_ref(),
+                collection.authority = operation.collection.authority.as_ref()
+            )
+        )
+    )]
+    fn execute_patch(
+        &self,
+        operation: &Operation,
+        transaction: &mut ExecutingTransaction<AnyFile>,
+        tree_index_map: &HashMap<String, usize>,
+        header: &Header,
+        patch: &[u8],
+        view_rows: Option<&mut Vec<ViewRowChanges>>,
+    ) -> Result<OperationResult, Error> {
+        let mut documents = transaction
+            .tree::<Versioned>(tree_index_map[&document_tree_name(&operation.collection)])
+            .unwrap();
+        let stored = documents.get(header.id.as_ref())?;
+        drop(documents);
+        let doc = match stored {
+            Some(stored) => deserialize_document(&stored)?,
+            None => {
+                return Err(Error::Core(bonsaidb_core::Error::DocumentNotFound(
+                    operation.collection.clone(),
+                    Box::new(header.id.clone()),
+                )))
+            }
+        };
+        if doc.header.revision != header.revision {
+            return Err(Error::Core(bonsaidb_core::Error::DocumentConflict(
+                operation.collection.clone(),
+                Box::new(doc.header),
+                Some(header.revision),
+            )));
+        }
+
+        let contents = apply_merge_patch(&doc.contents, patch)?;
+        self.check_document_size(&contents)?;
+        self.execute_update(
+            operation,
+            transaction,
+            tree_index_map,
+            &header.id,
+            Some(&header.revision),
+            &contents,
+            view_rows,
+        )
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
         skip(operation, transaction, tree_index_map),
@@ -742,7 +1723,8 @@ impl Database {
                 if doc.header.revision != revision {
                     return Err(Error::Core(bonsaidb_core::Error::DocumentConflict(
                         operation.collection.clone(),
-                        Box::new(Header { id, revision }),
+                        Box::new(doc.header),
+                        Some(revision),
                     )));
                 }
             }
@@ -762,6 +1744,19 @@ impl Database {
         order: Sort,
         limit: Option<u32>,
     ) -> Result<Vec<ViewEntry>, Error> {
+        Self::read_view_entries(view_entries, key, order, limit)?
+            .into_iter()
+            .map(|value| bincode::deserialize(&value).map_err(Error::from))
+            .collect()
+    }
+
+    /// Returns the serialized view entries matching `key`.
+    fn read_view_entries(
+        view_entries: &Tree<Unversioned, AnyFile>,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+    ) -> Result<Vec<ArcBytes<'static>>, Error> {
         let mut values = Vec::new();
         let forwards = match order {
             Sort::Ascending => true,
@@ -825,10 +1820,7 @@ impl Database {
             )?;
         }
 
-        values
-            .into_iter()
-            .map(|value| bincode::deserialize(&value).map_err(Error::from))
-            .collect::<Result<Vec<_>, Error>>()
+        Ok(values)
     }
 
     #[cfg(any(feature = "encryption", feature = "compression"))]
@@ -906,6 +1898,86 @@ impl Database {
             .update_key_expiration(tree_key, expiration);
     }
 
+    /// Removes all executed transactions with an id less than `before_id` from
+    /// this database's transaction log, returning the number of transactions
+    /// removed. Removed transactions are no longer returned from
+    /// [`list_executed_transactions()`](Connection::list_executed_transactions),
+    /// and listing transactions starting at a removed id begins with the
+    /// oldest remaining transaction. The space used by the removed
+    /// transactions is reclaimed when the database is
+    /// [compacted](Connection::compact).
+    pub fn prune_transaction_log(&self, before_id: u64) -> Result<u64, bonsaidb_core::Error> {
+        self.prune_transaction_log_with_cutoff(&transaction_log::Cutoff {
+            before_id,
+            executed_before: None,
+        })
+    }
+
+    /// Removes all transactions executed before `executed_before` from this
+    /// database's transaction log, returning the number of transactions
+    /// removed. See
+    /// [`prune_transaction_log()`](Self::prune_transaction_log) for more
+    /// information.
+    pub fn prune_transaction_log_executed_before(
+        &self,
+        executed_before: Timestamp,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.prune_transaction_log_with_cutoff(&transaction_log::Cutoff {
+            before_id: 0,
+            executed_before: Some(executed_before),
+        })
+    }
+
+    fn prune_transaction_log_with_cutoff(
+        &self,
+        cutoff: &transaction_log::Cutoff,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.check_permission(
+            database_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::Transaction(TransactionAction::Prune)),
+        )?;
+        Ok(transaction_log::prune(self.roots(), cutoff)?)
+    }
+
+    /// Removes the executed transactions outside of the configured
+    /// [`transaction_log_retention`](StorageConfiguration::transaction_log_retention).
+    pub(crate) fn enforce_transaction_log_retention(&self) -> Result<u64, Error> {
+        transaction_log::prune(self.roots(), &self.transaction_log_cutoff())
+    }
+
+    fn transaction_log_cutoff(&self) -> transaction_log::Cutoff {
+        transaction_log::Cutoff::for_retention(
+            self.storage.instance.transaction_log_retention(),
+            self.roots().transactions().current_transaction_id(),
+        )
+    }
+
+    /// Applies `transaction` like
+    /// [`apply_transaction()`](LowLevelConnection::apply_transaction), also
+    /// returning the rows each operation added to or removed from this
+    /// database's views.
+    ///
+    /// The rows are gathered while the views are updated within the
+    /// transaction, which only happens for views using
+    /// [`ViewUpdatePolicy::Eager`](view::ViewUpdatePolicy::Eager) or
+    /// [`ViewUpdatePolicy::Unique`](view::ViewUpdatePolicy::Unique). Views
+    /// without changes are omitted. If the transaction's idempotency key has
+    /// already been used, the original results are returned without any view
+    /// rows.
+    ///
+    /// [`apply_transaction_with_views()`](LowLevelConnection::apply_transaction_with_views)
+    /// returns the same rows combined for the entire transaction.
+    pub fn apply_transaction_with_view_rows(
+        &self,
+        transaction: Transaction,
+    ) -> Result<Vec<(OperationResult, Vec<ViewRowChanges>)>, bonsaidb_core::Error> {
+        let mut view_rows = Vec::new();
+        let results =
+            self.apply_transaction_collecting_view_rows(transaction, Some(&mut view_rows))?;
+        view_rows.resize_with(results.len(), Vec::new);
+        Ok(results.into_iter().zip(view_rows).collect())
+    }
+
     /// Converts this instance into its blocking version, which is able to be
     /// used without async. The returned instance uses the current Tokio runtime
     /// handle to spawn blocking tasks.
@@ -953,6 +2025,14 @@ impl Database {
         self.clone().into_async_with_runtime(runtime)
     }
 }
+/// The retained contents of a soft-deleted document.
+#[derive(Serialize, Deserialize)]
+struct Tombstone<'a> {
+    deleted_at: Timestamp,
+    #[serde(borrow)]
+    document: BorrowedDocument<'a>,
+}
+
 #[derive(Serialize, Deserialize)]
 struct LegacyHeader {
     id: u64,
@@ -1019,43 +2099,29 @@ impl Connection for Database {
         let result_limit = usize::try_from(
             result_limit
                 .unwrap_or(LIST_TRANSACTIONS_DEFAULT_RESULT_COUNT)
-                .min(LIST_TRANSACTIONS_MAX_RESULTS),
+                .min(self.storage.instance.list_transactions_max_results()),
         )
         .unwrap();
         if result_limit > 0 {
-            let range = if let Some(starting_id) = starting_id {
-                Range::from(starting_id..)
-            } else {
-                Range::from(..)
-            };
-
-            let mut entries = Vec::new();
-            self.roots()
-                .transactions()
-                .scan(range, |entry| {
-                    if entry.data().is_some() {
-                        entries.push(entry);
-                    }
-                    entries.len() < result_limit
-                })
-                .map_err(Error::from)?;
-
-            entries
-                .into_iter()
-                .map(|entry| {
-                    if let Some(data) = entry.data() {
-                        let changes = compat::deserialize_executed_transaction_changes(data)?;
-                        Ok(Some(transaction::Executed {
-                            id: entry.id,
-                            changes,
-                        }))
-                    } else {
-                        Ok(None)
-                    }
+            // Transactions outside of the retention may not have been removed
+            // yet, so they are skipped.
+            transaction_log::list(
+                self.roots(),
+                starting_id.unwrap_or_default(),
+                result_limit,
+                &self.transaction_log_cutoff(),
+            )?
+            .into_iter()
+            .map(|(id, changes)| {
+                let record = compat::deserialize_executed_transaction(&changes)?;
+                Ok(transaction::Executed {
+                    id,
+                    changes: record.changes,
+                    metadata: record.metadata,
                 })
-                .filter_map(Result::transpose)
-                .collect::<Result<Vec<_>, Error>>()
-                .map_err(bonsaidb_core::Error::from)
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(bonsaidb_core::Error::from)
         } else {
             // A request was made to return an empty result? This should probably be
             // an error, but technically this is a correct response.
@@ -1117,25 +2183,133 @@ impl Connection for Database {
     }
 }
 
-impl LowLevelConnection for Database {
-    #[cfg_attr(feature = "tracing", tracing::instrument(
-        level = "trace",
-        skip(self,  transaction),
-        fields(
-            database = self.name(),
-        )
-    ))]
-    fn apply_transaction(
+/// The tree storing the version of the schema that last opened the database.
+const SCHEMA_VERSION_TREE: &str = "schema-version";
+/// Stored in [`SCHEMA_VERSION_TREE`] with the version of the database's schema.
+const SCHEMA_VERSION_KEY: &[u8] = b"version";
+
+/// Returns the version of the schema that last opened the database stored in
+/// `roots`. Databases that were created before versions were recorded return
+/// 0.
+pub(crate) fn stored_schema_version(roots: &Roots<AnyFile>) -> Result<u64, Error> {
+    let tree = roots.tree(Unversioned::tree(SCHEMA_VERSION_TREE))?;
+    match tree.get(SCHEMA_VERSION_KEY)? {
+        Some(bytes) => Ok(pot::from_slice(&bytes)?),
+        None => Ok(0),
+    }
+}
+
+/// Records `version` as the version of the schema that last opened the
+/// database stored in `roots`.
+pub(crate) fn store_schema_version(roots: &Roots<AnyFile>, version: u64) -> Result<(), Error> {
+    let tree = roots.tree(Unversioned::tree(SCHEMA_VERSION_TREE))?;
+    tree.set(SCHEMA_VERSION_KEY.to_vec(), pot::to_vec(&version)?)?;
+    Ok(())
+}
+
+/// The tree storing the results of transactions applied with an idempotency
+/// key. Each record is stored under [`IDEMPOTENCY_RECORD_PREFIX`] followed by
+/// the idempotency key, and is indexed by when it expires under
+/// [`IDEMPOTENCY_EXPIRATION_PREFIX`] followed by the expiration and the
+/// idempotency key.
+const IDEMPOTENCY_TREE: &str = "idempotency";
+const IDEMPOTENCY_RECORD_PREFIX: u8 = b'r';
+const IDEMPOTENCY_EXPIRATION_PREFIX: u8 = b'e';
+
+#[derive(Serialize, Deserialize)]
+struct IdempotencyRecord {
+    expires_at: Timestamp,
+    results: Vec<OperationResult>,
+}
+
+fn idempotency_record_key(idempotency_key: &[u8]) -> Vec<u8> {
+    let mut record_key = Vec::with_capacity(1 + idempotency_key.len());
+    record_key.push(IDEMPOTENCY_RECORD_PREFIX);
+    record_key.extend_from_slice(idempotency_key);
+    record_key
+}
+
+fn idempotency_expiration_key(expires_at: Timestamp, idempotency_key: &[u8]) -> Vec<u8> {
+    let mut index_key = Vec::with_capacity(13 + idempotency_key.len());
+    index_key.push(IDEMPOTENCY_EXPIRATION_PREFIX);
+    index_key.extend_from_slice(&expires_at.seconds.to_be_bytes());
+    index_key.extend_from_slice(&expires_at.nanos.to_be_bytes());
+    index_key.extend_from_slice(idempotency_key);
+    index_key
+}
+
+impl Database {
+    /// Returns the results of the transaction previously applied with
+    /// `idempotency_key`, if it has not expired as of `now`.
+    fn applied_idempotent_results(
+        roots_transaction: &mut ExecutingTransaction<AnyFile>,
+        open_trees: &OpenTrees,
+        idempotency_key: &str,
+        now: Timestamp,
+    ) -> Result<Option<Vec<OperationResult>>, Error> {
+        let mut records = roots_transaction
+            .tree::<Unversioned>(open_trees.trees_index_by_name[IDEMPOTENCY_TREE])
+            .unwrap();
+        if let Some(record) = records.get(&idempotency_record_key(idempotency_key.as_bytes()))? {
+            let record = pot::from_slice::<IdempotencyRecord>(&record)?;
+            if record.expires_at > now {
+                return Ok(Some(record.results));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Records the `results` of the transaction being applied with
+    /// `idempotency_key`, removing any records that have expired as of `now`.
+    /// The record is committed with the transaction, so a transaction that
+    /// fails leaves the key available to be retried.
+    fn record_idempotent_results(
+        roots_transaction: &mut ExecutingTransaction<AnyFile>,
+        open_trees: &OpenTrees,
+        idempotency_key: &str,
+        results: &[OperationResult],
+        now: Timestamp,
+    ) -> Result<(), Error> {
+        let mut records = roots_transaction
+            .tree::<Unversioned>(open_trees.trees_index_by_name[IDEMPOTENCY_TREE])
+            .unwrap();
+        let expired = records.get_range(
+            &([IDEMPOTENCY_EXPIRATION_PREFIX].as_slice()
+                ..idempotency_expiration_key(now, b"").as_slice()),
+        )?;
+        for (index_key, _) in expired {
+            records.remove(&index_key)?;
+            records.remove(&idempotency_record_key(&index_key[13..]))?;
+        }
+
+        let expires_at = now + IDEMPOTENCY_KEY_EXPIRATION;
+        records.set(
+            idempotency_record_key(idempotency_key.as_bytes()),
+            pot::to_vec(&IdempotencyRecord {
+                expires_at,
+                results: results.to_vec(),
+            })?,
+        )?;
+        records.set(
+            idempotency_expiration_key(expires_at, idempotency_key.as_bytes()),
+            b"",
+        )?;
+        Ok(())
+    }
+
+    fn apply_transaction_collecting_view_rows(
         &self,
         transaction: Transaction,
+        view_rows: Option<&mut Vec<Vec<ViewRowChanges>>>,
     ) -> Result<Vec<OperationResult>, bonsaidb_core::Error> {
+        let _timer = self.time_operation("apply_transaction");
         for op in &transaction.operations {
             let (resource, action) = match &op.command {
-                Command::Insert { .. } => (
+                Command::Insert { .. } | Command::InsertWithId { .. } => (
                     collection_resource_name(self.name(), &op.collection),
                     BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Insert)),
                 ),
-                Command::Update { header, .. } => (
+                Command::Update { header, .. } | Command::Patch { header, .. } => (
                     document_resource_name(self.name(), &op.collection, &header.id),
                     BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Update)),
                 ),
@@ -1143,7 +2317,7 @@ impl LowLevelConnection for Database {
                     document_resource_name(self.name(), &op.collection, id),
                     BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Overwrite)),
                 ),
-                Command::Delete { header } => (
+                Command::Delete { header } | Command::SoftDelete { header } => (
                     document_resource_name(self.name(), &op.collection, &header.id),
                     BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Delete)),
                 ),
@@ -1155,13 +2329,26 @@ impl LowLevelConnection for Database {
             self.check_permission(resource, &action)?;
         }
 
+        self.wait_for_eager_view_integrity(
+            transaction
+                .operations
+                .iter()
+                .map(|op| &op.collection)
+                .collect::<HashSet<_>>(),
+        )?;
+
+        self.apply_transaction_to_roots(&transaction, view_rows)
+            .map_err(bonsaidb_core::Error::from)
+    }
+
+    /// Waits for the integrity checks of the eager views in `collections`,
+    /// including any mapping the checks require, to complete.
+    fn wait_for_eager_view_integrity<'a>(
+        &self,
+        collections: impl IntoIterator<Item = &'a CollectionName>,
+    ) -> Result<(), bonsaidb_core::Error> {
         let mut eager_view_tasks = Vec::new();
-        for collection_name in transaction
-            .operations
-            .iter()
-            .map(|op| &op.collection)
-            .collect::<HashSet<_>>()
-        {
+        for collection_name in collections {
             for view in self.data.schema.eager_views_in_collection(collection_name) {
                 if let Some(task) = self
                     .storage
@@ -1188,8 +2375,48 @@ impl LowLevelConnection for Database {
             }
         }
 
-        self.apply_transaction_to_roots(&transaction)
-            .map_err(bonsaidb_core::Error::from)
+        Ok(())
+    }
+}
+
+impl LowLevelConnection for Database {
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self,  transaction),
+        fields(
+            database = self.name(),
+        )
+    ))]
+    fn apply_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> Result<Vec<OperationResult>, bonsaidb_core::Error> {
+        self.apply_transaction_collecting_view_rows(transaction, None)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self,  transaction),
+        fields(
+            database = self.name(),
+        )
+    ))]
+    fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), bonsaidb_core::Error> {
+        let mut changes = ViewChanges::default();
+        let results = self
+            .apply_transaction_with_view_rows(transaction)?
+            .into_iter()
+            .map(|(result, view_rows)| {
+                for rows in view_rows {
+                    changes.push(rows);
+                }
+                result
+            })
+            .collect();
+        Ok((results, changes))
     }
 
     #[cfg_attr(feature = "tracing", tracing::instrument(
@@ -1223,6 +2450,101 @@ impl LowLevelConnection for Database {
         }
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self, collection),
+        fields(
+            database = self.name(),
+            collection.name = collection.name.as_ref(),
+            collection.authority = collection.authority.as_ref(),
+        )
+    ))]
+    fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, bonsaidb_core::Error> {
+        self.check_permission(
+            document_resource_name(self.name(), collection, &id),
+            &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Get)),
+        )?;
+        let tree = self
+            .data
+            .context
+            .roots
+            .tree(self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
+            .map_err(Error::from)?;
+        // Only the key index is inspected, the document's contents are never
+        // read from disk.
+        let mut exists = false;
+        tree.scan::<Infallible, _, _, _, _>(
+            &(id.as_ref()..=id.as_ref()),
+            true,
+            |_, _, _| ScanEvaluation::ReadData,
+            |_, _| {
+                exists = true;
+                ScanEvaluation::Stop
+            },
+            |_, _, _| Ok(()),
+        )
+        .map_err(Error::from)?;
+        Ok(exists)
+    }
+
+    fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        self.check_permission(
+            document_resource_name(self.name(), collection, &id),
+            &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Get)),
+        )?;
+        let tree = self
+            .roots()
+            .tree(self.collection_tree::<Unversioned, _>(
+                collection,
+                document_tombstones_tree_name(collection),
+            )?)
+            .map_err(Error::from)?;
+        if let Some(bytes) = tree.get(id.as_ref()).map_err(Error::from)? {
+            let tombstone = pot::from_slice::<Tombstone<'_>>(&bytes).map_err(Error::from)?;
+            Ok(Some(tombstone.document.into_owned()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn purge_tombstones(&self, before: Timestamp) -> Result<u64, bonsaidb_core::Error> {
+        let mut purged = 0;
+        for collection in self.schematic().collections() {
+            self.check_permission(
+                collection_resource_name(self.name(), collection),
+                &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::PurgeTombstones)),
+            )?;
+            let tree = self
+                .roots()
+                .tree(self.collection_tree::<Unversioned, _>(
+                    collection,
+                    document_tombstones_tree_name(collection),
+                )?)
+                .map_err(Error::from)?;
+            let mut expired = Vec::new();
+            for (id, bytes) in tree.get_range(&(..)).map_err(Error::from)? {
+                let tombstone = pot::from_slice::<Tombstone<'_>>(&bytes).map_err(Error::from)?;
+                if tombstone.deleted_at < before {
+                    expired.push(id);
+                }
+            }
+            if !expired.is_empty() {
+                purged += expired.len() as u64;
+                tree.modify(expired, nebari::tree::Operation::Remove)
+                    .map_err(Error::from)?;
+            }
+        }
+        Ok(purged)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
         skip(self, collection),
@@ -1379,6 +2701,29 @@ impl LowLevelConnection for Database {
         Ok(stats.alive_keys)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self, collection),
+        fields(
+            database = self.name(),
+            collection.name = collection.name.as_ref(),
+            collection.authority = collection.authority.as_ref(),
+        )
+    ))]
+    fn delete_all_from_collection(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.check_permission(
+            collection_resource_name(self.name(), collection),
+            &BonsaiAction::Database(DatabaseAction::Document(DocumentAction::Delete)),
+        )?;
+        self.wait_for_eager_view_integrity([collection])?;
+
+        self.clear_collection(collection)
+            .map_err(bonsaidb_core::Error::from)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
         skip(self, collection),
@@ -1463,11 +2808,16 @@ impl LowLevelConnection for Database {
         limit: Option<u32>,
         access_policy: AccessPolicy,
     ) -> Result<Vec<schema::view::map::Serialized>, bonsaidb_core::Error> {
+        let _timer = self.time_operation("query_view");
         let view = self.schematic().view_by_name(view)?;
         self.check_permission(
             view_resource_name(self.name(), &view.view_name()),
             &BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
         )?;
+        if let Some(cache) = self.storage.instance.view_result_cache() {
+            return self.query_with_result_cache(cache, view, key, order, limit, access_policy);
+        }
+
         let mut results = Vec::new();
         self.for_each_in_view(view, key, order, limit, access_policy, |entry| {
             for mapping in entry.mappings {
@@ -1483,6 +2833,46 @@ impl LowLevelConnection for Database {
         Ok(results)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self, view),
+        fields(
+            database = self.name(),
+            view.collection.name = view.collection.name.as_ref(),
+            view.collection.authority = view.collection.authority.as_ref(),
+            view.name = view.name.as_ref(),
+        )
+    ))]
+    fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedKey>, bonsaidb_core::Error> {
+        let _timer = self.time_operation("query_view_keys");
+        let view = self.schematic().view_by_name(view)?;
+        self.check_permission(
+            view_resource_name(self.name(), &view.view_name()),
+            &BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
+        )?;
+        let mut results = Vec::new();
+        self.for_each_in_view(view, key, order, limit, access_policy, |entry| {
+            // The mapped values are dropped without being copied into the
+            // results or deserialized.
+            for mapping in entry.mappings {
+                results.push(MappedSerializedKey {
+                    source: mapping.source,
+                    key: entry.key.clone(),
+                });
+            }
+            Ok(())
+        })?;
+
+        Ok(results)
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
         skip(self, view),
@@ -1540,12 +2930,26 @@ impl LowLevelConnection for Database {
         key: Option<SerializedQueryKey>,
         access_policy: AccessPolicy,
     ) -> Result<Vec<u8>, bonsaidb_core::Error> {
+        let view = self.data.schema.view_by_name(view_name)?;
+        if key.is_none() && view.incremental_reduce() {
+            let _timer = self.time_operation("reduce_view");
+            self.check_permission(
+                view_resource_name(self.name(), &view.view_name()),
+                &BonsaiAction::Database(DatabaseAction::View(ViewAction::Reduce)),
+            )?;
+            return self.read_view_tree(
+                view,
+                view_reductions_tree_name(view_name),
+                access_policy,
+                |reductions| Ok(reductions::reduce(view, reductions)?),
+            );
+        }
+
         let mut mappings = self.reduce_grouped_by_name(view_name, key, access_policy)?;
 
         let result = if mappings.len() == 1 {
             mappings.pop().unwrap().value.into_vec()
         } else {
-            let view = self.data.schema.view_by_name(view_name)?;
             view.reduce(
                 &mappings
                     .iter()
@@ -1575,11 +2979,21 @@ impl LowLevelConnection for Database {
         key: Option<SerializedQueryKey>,
         access_policy: AccessPolicy,
     ) -> Result<Vec<MappedSerializedValue>, bonsaidb_core::Error> {
+        let _timer = self.time_operation("reduce_view");
         let view = self.data.schema.view_by_name(view_name)?;
         self.check_permission(
             view_resource_name(self.name(), &view.view_name()),
             &BonsaiAction::Database(DatabaseAction::View(ViewAction::Reduce)),
         )?;
+        if view.incremental_reduce() {
+            return self.read_view_tree(
+                view,
+                view_reductions_tree_name(view_name),
+                access_policy,
+                |reductions| Ok(reductions::reduce_grouped(reductions, key)?),
+            );
+        }
+
         let mut mappings = Vec::new();
         self.for_each_in_view(view, key, Sort::Ascending, None, access_policy, |entry| {
             mappings.push(MappedSerializedValue {
@@ -1592,6 +3006,99 @@ impl LowLevelConnection for Database {
         Ok(mappings)
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self, view_name),
+        fields(
+            database = self.name(),
+            view.collection.name = view_name.collection.name.as_ref(),
+            view.collection.authority = view_name.collection.authority.as_ref(),
+            view.name = view_name.name.as_ref(),
+        )
+    ))]
+    fn count_grouped_by_name(
+        &self,
+        view_name: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, bonsaidb_core::Error> {
+        let view = self.data.schema.view_by_name(view_name)?;
+        self.check_permission(
+            view_resource_name(self.name(), &view.view_name()),
+            &BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
+        )?;
+        self.read_view_tree(
+            view,
+            view_entries_tree_name(&view.view_name()),
+            access_policy,
+            |view_entries| {
+                Self::read_view_entries(view_entries, key, Sort::Ascending, None)?
+                    .into_iter()
+                    .map(|value| -> Result<_, bonsaidb_core::Error> {
+                        let entry =
+                            bincode::deserialize::<ViewEntryCount>(&value).map_err(Error::from)?;
+                        Ok(MappedSerializedCount {
+                            key: entry.key,
+                            count: entry.mappings,
+                        })
+                    })
+                    .collect()
+            },
+        )
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self, view),
+        fields(
+            database = self.name(),
+            view.collection.name = view.collection.name.as_ref(),
+            view.collection.authority = view.collection.authority.as_ref(),
+            view.name = view.name.as_ref(),
+        )
+    ))]
+    fn view_index_state_by_name(
+        &self,
+        view: &ViewName,
+    ) -> Result<ViewIndexState, bonsaidb_core::Error> {
+        let view = self.data.schema.view_by_name(view)?;
+        self.check_permission(
+            view_resource_name(self.name(), &view.view_name()),
+            &BonsaiAction::Database(DatabaseAction::View(ViewAction::Query)),
+        )?;
+        let last_transaction_id = self.roots().transactions().current_transaction_id();
+        let last_indexed_transaction_id =
+            self.storage.instance.tasks().last_indexed_transaction_id(
+                self.data.name.clone(),
+                view.collection(),
+                view.view_name(),
+            );
+        Ok(ViewIndexState {
+            last_indexed_transaction_id,
+            last_transaction_id,
+        })
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(
+        level = "trace",
+        skip(self, view),
+        fields(
+            database = self.name(),
+            view.collection.name = view.collection.name.as_ref(),
+            view.collection.authority = view.collection.authority.as_ref(),
+            view.name = view.name.as_ref(),
+        )
+    ))]
+    fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), bonsaidb_core::Error> {
+        let view = self.data.schema.view_by_name(view)?;
+        self.check_permission(
+            view_resource_name(self.name(), &view.view_name()),
+            &BonsaiAction::Database(DatabaseAction::View(ViewAction::Rebuild)),
+        )?;
+        self.storage.instance.tasks().rebuild_view(view, self)?;
+        Ok(())
+    }
+
     #[cfg_attr(feature = "tracing", tracing::instrument(
         level = "trace",
         skip(self, view),
@@ -1666,6 +3173,9 @@ impl Deref for Context {
 pub(crate) struct ContextData {
     pub(crate) roots: Roots<AnyFile>,
     key_value_state: Arc<Mutex<keyvalue::KeyValueState>>,
+    /// The id of the most recent transaction committed by each session, used
+    /// to resolve [`AccessPolicy::ReadYourWrites`].
+    last_session_writes: Mutex<HashMap<Option<SessionId>, u64>>,
 }
 
 impl Borrow<Roots<AnyFile>> for Context {
@@ -1678,22 +3188,18 @@ impl Context {
     pub(crate) fn new(
         roots: Roots<AnyFile>,
         key_value_persistence: KeyValuePersistence,
+        corrupt_key_value_policy: CorruptKeyValuePolicy,
         storage_lock: Option<StorageLock>,
     ) -> Self {
         let background_worker_target = Watchable::new(BackgroundWorkerProcessTarget::Never);
         let mut background_worker_target_watcher = background_worker_target.watch();
-        let key_value_state = Arc::new(Mutex::new(keyvalue::KeyValueState::new(
+        let context = Self::with_worker_target(
+            roots,
             key_value_persistence,
-            roots.clone(),
+            corrupt_key_value_policy,
             background_worker_target,
-        )));
-        let background_worker_state = Arc::downgrade(&key_value_state);
-        let context = Self {
-            data: Arc::new(ContextData {
-                roots,
-                key_value_state,
-            }),
-        };
+        );
+        let background_worker_state = Arc::downgrade(&context.data.key_value_state);
         std::thread::Builder::new()
             .name(String::from("keyvalue-worker"))
             .spawn(move || {
@@ -1707,14 +3213,93 @@ impl Context {
         context
     }
 
+    /// Creates a context whose key-value store is serviced by
+    /// `shared_worker` instead of its own worker thread.
+    pub(crate) fn with_shared_worker(
+        roots: Roots<AnyFile>,
+        key_value_persistence: KeyValuePersistence,
+        corrupt_key_value_policy: CorruptKeyValuePolicy,
+        storage_lock: Option<StorageLock>,
+        shared_worker: &keyvalue::SharedWorker,
+    ) -> Self {
+        let context = Self::with_worker_target(
+            roots,
+            key_value_persistence,
+            corrupt_key_value_policy,
+            Watchable::new(BackgroundWorkerProcessTarget::Never),
+        );
+        let registration =
+            shared_worker.register(Arc::downgrade(&context.data.key_value_state), storage_lock);
+        context
+            .data
+            .key_value_state
+            .lock()
+            .set_shared_worker(registration);
+        context
+    }
+
+    fn with_worker_target(
+        roots: Roots<AnyFile>,
+        key_value_persistence: KeyValuePersistence,
+        corrupt_key_value_policy: CorruptKeyValuePolicy,
+        background_worker_target: Watchable<BackgroundWorkerProcessTarget>,
+    ) -> Self {
+        let key_value_state = Arc::new(Mutex::new(keyvalue::KeyValueState::new(
+            key_value_persistence,
+            corrupt_key_value_policy,
+            roots.clone(),
+            background_worker_target,
+        )));
+        Self {
+            data: Arc::new(ContextData {
+                roots,
+                key_value_state,
+                last_session_writes: Mutex::default(),
+            }),
+        }
+    }
+
+    /// Logs key-value persistence that takes longer than allowed by
+    /// `slow_operation_log`.
+    pub(crate) fn log_slow_operations(&self, slow_operation_log: SlowOperationLog) {
+        self.data
+            .key_value_state
+            .lock()
+            .set_slow_operation_log(slow_operation_log);
+    }
+
+    /// Forgets the transaction recorded for `session_id` by
+    /// [`Database::record_session_write()`] once the session has ended.
+    pub(crate) fn forget_session_writes(&self, session_id: SessionId) {
+        self.data
+            .last_session_writes
+            .lock()
+            .remove(&Some(session_id));
+    }
+
     pub(crate) fn perform_kv_operation(
         &self,
         op: KeyOperation,
     ) -> Result<Output, bonsaidb_core::Error> {
-        let mut state = self.data.key_value_state.lock();
+        let mut state = if matches!(
+            op.command,
+            KeyCommand::Get { delete: false, .. } | KeyCommand::GetBytes { delete: false, .. }
+        ) {
+            self.data.key_value_state.lock()
+        } else {
+            keyvalue::KeyValueState::lock_for_write(&self.data.key_value_state)?
+        };
         state.perform_kv_operation(op, &self.data.key_value_state)
     }
 
+    pub(crate) fn perform_kv_pipeline(
+        &self,
+        pipeline: Pipeline,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        let mut state = keyvalue::KeyValueState::lock_for_write(&self.data.key_value_state)?;
+        state.perform_kv_pipeline(pipeline, &self.data.key_value_state)
+    }
+
     pub(crate) fn update_key_expiration<'key>(
         &self,
         tree_key: impl Into<Cow<'key, str>>,
@@ -1724,6 +3309,43 @@ impl Context {
         state.update_key_expiration(tree_key, expiration);
     }
 
+    pub(crate) fn clear_all_key_value_entries(&self) -> Result<(), Error> {
+        keyvalue::KeyValueState::clear_all(&self.data.key_value_state)
+    }
+
+    pub(crate) fn sweep_expired_key_value_entries(&self) -> Result<usize, Error> {
+        keyvalue::KeyValueState::sweep_expired(&self.data.key_value_state, Timestamp::now())
+    }
+
+    pub(crate) fn bulk_load_key_value_entries<I>(&self, entries: I) -> Result<usize, Error>
+    where
+        I: IntoIterator<Item = (String, keyvalue::Entry)>,
+    {
+        keyvalue::KeyValueState::bulk_load(&self.data.key_value_state, entries)
+    }
+
+    pub(crate) fn rename_key_value_namespace(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        keyvalue::KeyValueState::rename_namespace(&self.data.key_value_state, from, to)
+    }
+
+    pub(crate) fn restore_key_value_namespace(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        keyvalue::KeyValueState::restore_namespace(
+            &self.data.key_value_state,
+            namespace,
+            timestamp,
+            target,
+        )
+    }
+
     #[cfg(test)]
     pub(crate) fn kv_persistence_watcher(&self) -> watchable::Watcher<Timestamp> {
         let state = self.data.key_value_state.lock();
@@ -1746,6 +3368,21 @@ pub fn document_tree_name(collection: &CollectionName) -> String {
     format!("collection.{collection:#}")
 }
 
+/// The tree storing the retained contents of soft-deleted documents.
+pub fn document_tombstones_tree_name(collection: &CollectionName) -> String {
+    format!("collection.{collection:#}.tombstones")
+}
+
+/// The tree recording the largest document id ever stored in a collection, so
+/// that automatically assigned ids are never reissued.
+pub fn document_ids_tree_name(collection: &CollectionName) -> String {
+    format!("collection.{collection:#}.ids")
+}
+
+/// The key in the [document ids tree](document_ids_tree_name) storing the
+/// largest document id.
+const LARGEST_DOCUMENT_ID_KEY: &[u8] = b"largest";
+
 pub struct DocumentIdRange(Range<DocumentId>);
 
 impl<'a> BorrowByteRange<'a> for DocumentIdRange {
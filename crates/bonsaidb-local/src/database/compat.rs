@@ -36,6 +36,7 @@ impl<T> std::fmt::Display for UnknownVersion<T> {
 enum ChangesVersions {
     Legacy = 0,
     V1 = 1,
+    V2 = 2,
 }
 
 impl Versioned for ChangesVersions {
@@ -51,12 +52,15 @@ impl TryFrom<u64> for ChangesVersions {
         match value {
             0 => Ok(ChangesVersions::Legacy),
             1 => Ok(ChangesVersions::V1),
+            2 => Ok(ChangesVersions::V2),
             _ => Err(UnknownVersion::default()),
         }
     }
 }
 
-pub fn deserialize_executed_transaction_changes(data: &[u8]) -> Result<Changes, crate::Error> {
+pub fn deserialize_executed_transaction(
+    data: &[u8],
+) -> Result<ExecutedTransactionRecord, crate::Error> {
     let (version, data) = transmog_versions::unwrap_version(data);
     match ChangesVersions::try_from(version)? {
         ChangesVersions::Legacy => {
@@ -65,12 +69,35 @@ pub fn deserialize_executed_transaction_changes(data: &[u8]) -> Result<Changes,
                 Err(pot::Error::NotAPot) => ChangesV0::Documents(bincode::deserialize(data)?),
                 other => other?,
             };
-            Changes::try_from(legacy).map_err(crate::Error::from)
+            Ok(ExecutedTransactionRecord {
+                changes: Changes::try_from(legacy)?,
+                metadata: None,
+            })
         }
-        ChangesVersions::V1 => pot::from_slice(data).map_err(crate::Error::from),
+        ChangesVersions::V1 => Ok(ExecutedTransactionRecord {
+            changes: pot::from_slice(data)?,
+            metadata: None,
+        }),
+        ChangesVersions::V2 => pot::from_slice(data).map_err(crate::Error::from),
     }
 }
 
+/// Decodes the data BonsaiDb records for each executed transaction, returning
+/// the transaction's changes and metadata. Previous versions of BonsaiDb
+/// stored this data in each entry of a database's transaction log.
+///
+/// The data begins with a [`transmog_versions`] header identifying the
+/// version of the format, followed by the changes encoded using
+/// [Pot](https://github.com/khonsulabs/pot). Both document and key-value
+/// changes are always written using Pot. Data written by older versions of
+/// BonsaiDb, including bincode-encoded document changes, is also supported.
+/// This is the same decoder used by
+/// [`list_executed_transactions()`](bonsaidb_core::connection::LowLevelConnection::list_executed_transactions).
+pub fn decode_executed_transaction(data: &[u8]) -> Result<(Changes, Option<Bytes>), crate::Error> {
+    let record = deserialize_executed_transaction(data)?;
+    Ok((record.changes, record.metadata))
+}
+
 pub fn serialize_executed_transaction_changes(changes: &Changes) -> Result<Vec<u8>, crate::Error> {
     let mut serialized = Vec::new();
     transmog_versions::write_header(&ChangesVersions::V1, &mut serialized)?;
@@ -78,6 +105,37 @@ pub fn serialize_executed_transaction_changes(changes: &Changes) -> Result<Vec<u
     Ok(serialized)
 }
 
+/// Serializes `changes` along with the transaction's `metadata`. Transactions
+/// without metadata continue to use the V1 format.
+pub fn serialize_executed_transaction(
+    changes: &Changes,
+    metadata: Option<&Bytes>,
+) -> Result<Vec<u8>, crate::Error> {
+    if metadata.is_none() {
+        return serialize_executed_transaction_changes(changes);
+    }
+    let mut serialized = Vec::new();
+    transmog_versions::write_header(&ChangesVersions::V2, &mut serialized)?;
+    pot::to_writer(
+        &ExecutedTransactionRecordRef { changes, metadata },
+        &mut serialized,
+    )?;
+    Ok(serialized)
+}
+
+/// The contents of an executed transaction log entry.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ExecutedTransactionRecord {
+    pub changes: Changes,
+    pub metadata: Option<Bytes>,
+}
+
+#[derive(Serialize)]
+struct ExecutedTransactionRecordRef<'a> {
+    changes: &'a Changes,
+    metadata: Option<&'a Bytes>,
+}
+
 /// A list of changes.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ChangesV0 {
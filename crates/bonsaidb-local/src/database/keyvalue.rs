@@ -1,30 +1,47 @@
 use std::borrow::Cow;
-use std::collections::{btree_map, BTreeMap, VecDeque};
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::ops::{Bound, ControlFlow};
+use std::path::PathBuf;
 use std::sync::{Arc, Weak};
 use std::time::Duration;
 
+use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::connection::{Connection, HasSession};
 use bonsaidb_core::keyvalue::{
-    Command, KeyCheck, KeyOperation, KeyStatus, KeyValue, Numeric, Output, SetCommand, Timestamp,
-    Value,
+    BoundedValue, ChangedValue, Command, Decimal, Expiration, ExpiringKey, FieldOperation,
+    KeyCheck, KeyOperation, KeyStatus, KeyValue, Numeric, Output, Pipeline, PipelineStep,
+    SetCommand, SetOperation, Timestamp, Value, ValueWithMetadata,
 };
 use bonsaidb_core::permissions::bonsai::{
-    keyvalue_key_resource_name, BonsaiAction, DatabaseAction, KeyValueAction,
+    keyvalue_key_resource_name, kv_resource_name, BonsaiAction, DatabaseAction, KeyValueAction,
 };
 use bonsaidb_core::transaction::{ChangedKey, Changes};
 use nebari::io::any::AnyFile;
 use nebari::tree::{CompareSwap, Operation, Root, ScanEvaluation, Unversioned};
 use nebari::{AbortError, ArcBytes, Roots};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, MutexGuard};
 use serde::{Deserialize, Serialize};
 use watchable::{Watchable, Watcher};
 
-use crate::config::KeyValuePersistence;
-use crate::database::compat;
+use crate::config::{CorruptKeyValuePolicy, KeyValueEntryEncoding, KeyValuePersistence};
+use crate::database::transaction_log::{self, EXECUTED_TRANSACTIONS_TREE};
+use crate::database::{compat, IntegrityProblem};
+use crate::slow_operations::SlowOperationLog;
 use crate::storage::StorageLock;
 use crate::tasks::{Job, Keyed, Task};
 use crate::{Database, DatabaseNonBlocking, Error};
 
+mod shared_worker;
+mod wal;
+
+use self::shared_worker::Registration;
+pub use self::shared_worker::SharedWorker;
+use self::wal::WriteAheadLog;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Entry {
     pub value: Value,
@@ -34,6 +51,12 @@ pub struct Entry {
 }
 
 impl Entry {
+    /// Returns true if this entry's expiration is at or before `now`.
+    pub(crate) fn is_expired(&self, now: Timestamp) -> bool {
+        self.expiration
+            .map_or(false, |expiration| expiration <= now)
+    }
+
     pub(crate) fn restore(
         self,
         namespace: Option<String>,
@@ -45,7 +68,7 @@ impl Entry {
             key,
             command: Command::Set(SetCommand {
                 value: self.value,
-                expiration: self.expiration,
+                expiration: self.expiration.map(Expiration::Absolute),
                 keep_existing_expiration: false,
                 check: None,
                 return_previous_value: false,
@@ -63,6 +86,184 @@ impl KeyValue for Database {
         )?;
         self.data.context.perform_kv_operation(op)
     }
+
+    fn execute_key_pipeline(
+        &self,
+        pipeline: Pipeline,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        for step in &pipeline.steps {
+            self.check_permission(
+                keyvalue_key_resource_name(
+                    self.name(),
+                    step.operation.namespace.as_deref(),
+                    &step.operation.key,
+                ),
+                &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+            )?;
+        }
+        self.data.context.perform_kv_pipeline(pipeline)
+    }
+
+    fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        // The index is built in the background after the database is opened,
+        // and is incomplete until the expiration loader finishes.
+        if let Some(loader) = self
+            .storage
+            .instance
+            .tasks()
+            .spawn_key_value_expiration_loader(self)
+        {
+            loader
+                .receive()
+                .map_err(Error::from)?
+                .map_err(Error::from)?;
+        }
+
+        // Keys that haven't been persisted aren't reflected in the index yet,
+        // and their pending expiration takes precedence over the indexed one.
+        let mut unpersisted = HashMap::new();
+        {
+            let state = self.data.context.key_value_state.lock();
+            let pending = state
+                .keys_being_persisted
+                .iter()
+                .flat_map(|keys| keys.iter());
+            for (full_key, entry) in pending.chain(&state.dirty_keys) {
+                unpersisted.insert(
+                    full_key.clone(),
+                    entry.as_ref().and_then(|entry| entry.expiration),
+                );
+            }
+        }
+
+        let range_start = expiration_index_key(start, "");
+        let range_end = expiration_index_key(end, "");
+        let mut expiring = self
+            .roots()
+            .tree(Unversioned::tree(EXPIRATION_TREE))
+            .map_err(Error::from)?
+            .get_range(&(range_start.as_slice()..range_end.as_slice()))
+            .map_err(Error::from)?
+            .into_iter()
+            .filter_map(|(index_key, _)| {
+                let (expiration, full_key) = parse_expiration_index_key(&index_key)?;
+                (!unpersisted.contains_key(full_key)).then(|| (expiration, full_key.to_string()))
+            })
+            .collect::<Vec<_>>();
+        expiring.extend(
+            unpersisted
+                .into_iter()
+                .filter_map(|(full_key, expiration)| {
+                    expiration
+                        .filter(|expiration| (start..end).contains(expiration))
+                        .map(|expiration| (expiration, full_key))
+                }),
+        );
+        expiring.sort();
+
+        Ok(expiring
+            .into_iter()
+            .filter_map(|(expiration, full_key)| {
+                let (namespace, key) = split_key(&full_key)?;
+                Some(ExpiringKey {
+                    namespace,
+                    key,
+                    expiration,
+                })
+            })
+            .collect())
+    }
+
+    fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, bonsaidb_core::Error> {
+        for key in &keys {
+            self.check_permission(
+                keyvalue_key_resource_name(self.name(), namespace.as_deref(), key),
+                &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+            )?;
+        }
+        let full_keys = keys
+            .iter()
+            .map(|key| full_key(namespace.as_deref(), key))
+            .collect::<Vec<_>>();
+        let expirations = self
+            .data
+            .context
+            .key_value_state
+            .lock()
+            .key_expirations(&full_keys, Timestamp::now())?;
+        Ok(keys.into_iter().zip(expirations).collect())
+    }
+
+    fn rename_namespace(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        self.data.context.rename_key_value_namespace(from, to)
+    }
+
+    fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, bonsaidb_core::Error> {
+        let limit = limit.max(1);
+        let mut entries = Vec::new();
+        self.scan_namespace_after(namespace.as_deref(), after.as_deref(), |key, value| {
+            entries.push((key.to_string(), value.clone()));
+            if entries.len() < limit {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })?;
+        Ok(entries)
+    }
+
+    fn scan_key_value_namespace<F>(
+        &self,
+        namespace: Option<&str>,
+        callback: F,
+    ) -> Result<(), bonsaidb_core::Error>
+    where
+        F: FnMut(&str, &Value) -> ControlFlow<()>,
+    {
+        // Unlike the provided implementation, this streams the entries
+        // directly from the tree rather than reading them in batches.
+        self.scan_namespace_after(namespace, None, callback)
+    }
+
+    fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        self.data
+            .context
+            .restore_key_value_namespace(namespace, timestamp, target)
+    }
 }
 
 impl Database {
@@ -71,9 +272,62 @@ impl Database {
     ) -> Result<BTreeMap<(Option<String>, String), Entry>, Error> {
         // Lock the state so that new new modifications can be made while we gather this snapshot.
         let state = self.data.context.key_value_state.lock();
+        self.key_value_entries(&state)
+    }
+
+    /// Appends a problem to `problems` for each entry in [`KEY_TREE`] that
+    /// can't be loaded. Unlike [`deserialize_entry()`], corrupt entries are
+    /// never quarantined.
+    pub(crate) fn verify_key_value_entries(
+        &self,
+        problems: &mut Vec<IntegrityProblem>,
+    ) -> Result<(), Error> {
+        let roots = self.roots();
+        roots
+            .tree(Unversioned::tree(KEY_TREE))?
+            .scan::<Error, _, _, _, _>(
+                &(..),
+                true,
+                |_, _, _| ScanEvaluation::ReadData,
+                |_, _| ScanEvaluation::ReadData,
+                |key, _, bytes: ArcBytes<'static>| {
+                    let error = match StoredEntry::deserialize(&bytes) {
+                        Ok(stored) => stored
+                            .into_entry(roots)
+                            .map_err(AbortError::Other)?
+                            .err()
+                            .map(|err| err.to_string()),
+                        Err(err) => Some(err.to_string()),
+                    };
+                    let error = error.or_else(|| {
+                        std::str::from_utf8(&key)
+                            .ok()
+                            .and_then(split_key_parts)
+                            .is_none()
+                            .then(|| String::from("invalid key"))
+                    });
+                    if let Some(error) = error {
+                        problems.push(IntegrityProblem::CorruptKeyValueEntry {
+                            key: String::from_utf8_lossy(&key).into_owned(),
+                            error,
+                        });
+                    }
+                    Ok(())
+                },
+            )?;
+
+        Ok(())
+    }
+
+    fn key_value_entries(
+        &self,
+        state: &KeyValueState,
+    ) -> Result<BTreeMap<(Option<String>, String), Entry>, Error> {
         let database = self.clone();
         // Initialize our entries with any dirty keys and any keys that are about to be persisted.
         let mut all_entries = BTreeMap::new();
+        let policy = state.corrupt_key_value_policy;
+        let mut corrupt = Vec::new();
         database
             .roots()
             .tree(Unversioned::tree(KEY_TREE))?
@@ -83,12 +337,13 @@ impl Database {
                 |_, _, _| ScanEvaluation::ReadData,
                 |_, _| ScanEvaluation::ReadData,
                 |key, _, entry: ArcBytes<'static>| {
-                    let entry = bincode::deserialize::<Entry>(&entry)
-                        .map_err(|err| AbortError::Other(Error::from(err)))?;
+                    let entry =
+                        deserialize_entry(database.roots(), &key, &entry, policy, &mut corrupt)
+                            .map_err(AbortError::Other)?;
                     let full_key = std::str::from_utf8(&key)
                         .map_err(|err| AbortError::Other(Error::from(err)))?;
 
-                    if let Some(split_key) = split_key(full_key) {
+                    if let (Some(entry), Some(split_key)) = (entry, split_key(full_key)) {
                         // Do not overwrite the existing key
                         all_entries.entry(split_key).or_insert(entry);
                     }
@@ -96,6 +351,7 @@ impl Database {
                     Ok(())
                 },
             )?;
+        quarantine_entries(database.roots(), corrupt)?;
 
         // Apply the pending writes first
         if let Some(pending_keys) = &state.keys_being_persisted {
@@ -120,789 +376,4033 @@ impl Database {
 
         Ok(all_entries)
     }
-}
 
-pub(crate) const KEY_TREE: &str = "kv";
-
-fn full_key(namespace: Option<&str>, key: &str) -> String {
-    let full_length = namespace.map_or_else(|| 0, str::len) + key.len() + 1;
-    let mut full_key = String::with_capacity(full_length);
-    if let Some(ns) = namespace {
-        full_key.push_str(ns);
-    }
-    full_key.push('\0');
-    full_key.push_str(key);
-    full_key
-}
+    /// Invokes `callback` with each key and value stored in `namespace` whose
+    /// key sorts after `after`, in ascending key order, without loading every
+    /// entry into memory at once. Returning [`ControlFlow::Break`] from
+    /// `callback` stops the scan.
+    ///
+    /// Keys that have not been persisted yet are included. Keys modified while
+    /// the scan is in progress may or may not reflect the modification.
+    fn scan_namespace_after<F>(
+        &self,
+        namespace: Option<&str>,
+        after: Option<&str>,
+        mut callback: F,
+    ) -> Result<(), bonsaidb_core::Error>
+    where
+        F: FnMut(&str, &Value) -> ControlFlow<()>,
+    {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        let prefix = full_key(namespace, "");
+        // Gather the keys that haven't been persisted. Dirty keys take
+        // precedence over keys that are currently being persisted.
+        let mut unpersisted = BTreeMap::new();
+        let policy = {
+            let state = self.data.context.key_value_state.lock();
+            let pending = state
+                .keys_being_persisted
+                .iter()
+                .flat_map(|keys| keys.iter());
+            for (full_key, entry) in pending.chain(&state.dirty_keys) {
+                if let Some(key) = full_key.strip_prefix(&prefix) {
+                    if after.map_or(true, |after| key > after) {
+                        unpersisted.insert(key.to_string(), entry.clone());
+                    }
+                }
+            }
+            state.corrupt_key_value_policy
+        };
+        let mut unpersisted = unpersisted.into_iter().peekable();
+        let mut corrupt = Vec::new();
 
-fn split_key(full_key: &str) -> Option<(Option<String>, String)> {
-    if let Some((namespace, key)) = full_key.split_once('\0') {
-        let namespace = if namespace.is_empty() {
-            None
-        } else {
-            Some(namespace.to_string())
+        // The key prefix ends with either a length byte or the last byte of a
+        // UTF-8 namespace, neither of which can be 0xFF, so incrementing it
+        // produces the exclusive end of the namespace's range.
+        let mut end = prefix.as_bytes().to_vec();
+        *end.last_mut().expect("prefix always contains a length") += 1;
+        let after = after.map(|after| full_key(namespace, after));
+        let start = match &after {
+            Some(after) => Bound::Excluded(after.as_bytes()),
+            None => Bound::Included(prefix.as_bytes()),
         };
-        Some((namespace, key.to_string()))
-    } else {
-        None
-    }
-}
+        let stopped = Cell::new(false);
+        let mut visit = |key: &str, value: &Value| {
+            if callback(key, value).is_break() {
+                stopped.set(true);
+            }
+        };
+        self.roots()
+            .tree(Unversioned::tree(KEY_TREE))
+            .map_err(Error::from)?
+            .scan::<Error, _, _, _, _>(
+                &(start, Bound::Excluded(end.as_slice())),
+                true,
+                |_, _, _| {
+                    if stopped.get() {
+                        ScanEvaluation::Stop
+                    } else {
+                        ScanEvaluation::ReadData
+                    }
+                },
+                |_, _| {
+                    if stopped.get() {
+                        ScanEvaluation::Stop
+                    } else {
+                        ScanEvaluation::ReadData
+                    }
+                },
+                |full_key, _, entry: ArcBytes<'static>| {
+                    let key = std::str::from_utf8(&full_key[prefix.len()..])
+                        .map_err(|err| AbortError::Other(Error::from(err)))?;
+                    // Visit unpersisted keys that sort before this key.
+                    while !stopped.get() {
+                        match unpersisted.next_if(|(pending, _)| pending.as_str() < key) {
+                            Some((pending, Some(entry))) => visit(&pending, &entry.value),
+                            Some((_, None)) => {}
+                            None => break,
+                        }
+                    }
+                    if stopped.get() {
+                        return Ok(());
+                    }
 
-fn increment(existing: &Numeric, amount: &Numeric, saturating: bool) -> Numeric {
-    match amount {
-        Numeric::Integer(amount) => {
-            let existing_value = existing.as_i64_lossy(saturating);
-            let new_value = if saturating {
-                existing_value.saturating_add(*amount)
-            } else {
-                existing_value.wrapping_add(*amount)
-            };
-            Numeric::Integer(new_value)
-        }
-        Numeric::UnsignedInteger(amount) => {
-            let existing_value = existing.as_u64_lossy(saturating);
-            let new_value = if saturating {
-                existing_value.saturating_add(*amount)
-            } else {
-                existing_value.wrapping_add(*amount)
-            };
-            Numeric::UnsignedInteger(new_value)
-        }
-        Numeric::Float(amount) => {
-            let existing_value = existing.as_f64_lossy();
-            let new_value = existing_value + *amount;
-            Numeric::Float(new_value)
+                    match unpersisted.next_if(|(pending, _)| pending == key) {
+                        Some((_, Some(entry))) => visit(key, &entry.value),
+                        Some((_, None)) => {}
+                        None => {
+                            if let Some(entry) = deserialize_entry(
+                                self.roots(),
+                                &full_key,
+                                &entry,
+                                policy,
+                                &mut corrupt,
+                            )
+                            .map_err(AbortError::Other)?
+                            {
+                                visit(key, &entry.value);
+                            }
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(Error::from)?;
+
+        for (key, entry) in unpersisted {
+            if stopped.get() {
+                break;
+            }
+            if let Some(entry) = entry {
+                visit(&key, &entry.value);
+            }
         }
+
+        quarantine_entries(self.roots(), corrupt)?;
+        Ok(())
     }
-}
 
-fn decrement(existing: &Numeric, amount: &Numeric, saturating: bool) -> Numeric {
-    match amount {
-        Numeric::Integer(amount) => {
-            let existing_value = existing.as_i64_lossy(saturating);
-            let new_value = if saturating {
-                existing_value.saturating_sub(*amount)
-            } else {
-                existing_value.wrapping_sub(*amount)
-            };
-            Numeric::Integer(new_value)
-        }
-        Numeric::UnsignedInteger(amount) => {
-            let existing_value = existing.as_u64_lossy(saturating);
-            let new_value = if saturating {
-                existing_value.saturating_sub(*amount)
-            } else {
-                existing_value.wrapping_sub(*amount)
-            };
-            Numeric::UnsignedInteger(new_value)
-        }
-        Numeric::Float(amount) => {
-            let existing_value = existing.as_f64_lossy();
-            let new_value = existing_value - *amount;
-            Numeric::Float(new_value)
+    /// Invokes `callback` with batches of up to `batch_size` entries from
+    /// every namespace of this database's key-value store. Returning
+    /// [`ControlFlow::Break`] from `callback` stops the scan.
+    ///
+    /// Unlike reading the entire store at once, only one batch is held in
+    /// memory at a time, and other key-value operations are only blocked while
+    /// each batch is being read. Each batch includes keys that have not been
+    /// persisted yet, as well as keys that have expired but have not been
+    /// removed yet. Keys modified between batches may or may not reflect the
+    /// modification.
+    pub fn key_value_entries_in_batches<F>(
+        &self,
+        batch_size: usize,
+        mut callback: F,
+    ) -> Result<(), bonsaidb_core::Error>
+    where
+        F: FnMut(Vec<KeyValueEntry>) -> ControlFlow<()>,
+    {
+        let mut after = None;
+        loop {
+            let (batch, next) = self.key_value_entries_batch(after.as_deref(), batch_size)?;
+            if !batch.is_empty() && callback(batch).is_break() {
+                break;
+            }
+            match next {
+                Some(next) => after = Some(next),
+                None => break,
+            }
         }
+        Ok(())
     }
-}
 
-#[derive(Debug)]
-pub struct KeyValueState {
-    roots: Roots<AnyFile>,
-    persistence: KeyValuePersistence,
-    last_commit: Timestamp,
-    background_worker_target: Watchable<BackgroundWorkerProcessTarget>,
-    expiring_keys: BTreeMap<String, Timestamp>,
-    expiration_order: VecDeque<String>,
-    dirty_keys: BTreeMap<String, Option<Entry>>,
-    keys_being_persisted: Option<Arc<BTreeMap<String, Option<Entry>>>>,
-    last_persistence: Watchable<Timestamp>,
-    shutdown: Option<flume::Sender<()>>,
-}
+    /// Reads the next batch of up to `batch_size` key-value entries whose
+    /// full keys sort after `after`, returning the entries and the full key to
+    /// continue reading after, if any entries may remain.
+    pub(crate) fn key_value_entries_batch(
+        &self,
+        after: Option<&str>,
+        batch_size: usize,
+    ) -> Result<(Vec<KeyValueEntry>, Option<String>), bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        let batch_size = batch_size.max(1);
 
-impl KeyValueState {
-    pub fn new(
-        persistence: KeyValuePersistence,
-        roots: Roots<AnyFile>,
-        background_worker_target: Watchable<BackgroundWorkerProcessTarget>,
-    ) -> Self {
-        Self {
-            roots,
-            persistence,
-            last_commit: Timestamp::now(),
-            expiring_keys: BTreeMap::new(),
-            background_worker_target,
-            expiration_order: VecDeque::new(),
-            dirty_keys: BTreeMap::new(),
-            keys_being_persisted: None,
-            last_persistence: Watchable::new(Timestamp::MIN),
-            shutdown: None,
-        }
-    }
+        // Lock the state so that each batch is a consistent snapshot.
+        let state = self.data.context.key_value_state.lock();
+        let policy = state.corrupt_key_value_policy;
+        let mut corrupt = Vec::new();
+        let mut entries = BTreeMap::new();
+        let mut last_scanned = None;
+        let scanned = Cell::new(0_usize);
+        let stopped = Cell::new(false);
+        let evaluate = || {
+            if scanned.get() < batch_size {
+                ScanEvaluation::ReadData
+            } else {
+                stopped.set(true);
+                ScanEvaluation::Stop
+            }
+        };
+        let start = after.map_or(Bound::Unbounded, |after| Bound::Excluded(after.as_bytes()));
+        self.roots()
+            .tree(Unversioned::tree(KEY_TREE))
+            .map_err(Error::from)?
+            .scan::<Error, _, _, _, _>(
+                &(start, Bound::Unbounded),
+                true,
+                |_, _, _| evaluate(),
+                |_, _| evaluate(),
+                |key, _, entry: ArcBytes<'static>| {
+                    scanned.set(scanned.get() + 1);
+                    let full_key = std::str::from_utf8(&key)
+                        .map_err(|err| AbortError::Other(Error::from(err)))?
+                        .to_string();
+                    if let Some(entry) =
+                        deserialize_entry(self.roots(), &key, &entry, policy, &mut corrupt)
+                            .map_err(AbortError::Other)?
+                    {
+                        entries.insert(full_key.clone(), entry);
+                    }
+                    last_scanned = Some(full_key);
+                    Ok(())
+                },
+            )
+            .map_err(Error::from)?;
+        quarantine_entries(self.roots(), corrupt)?;
 
-    pub fn shutdown(&mut self, state: &Arc<Mutex<KeyValueState>>) -> Option<flume::Receiver<()>> {
-        if self.keys_being_persisted.is_none() && self.commit_dirty_keys(state) {
-            let (shutdown_sender, shutdown_receiver) = flume::bounded(1);
-            self.shutdown = Some(shutdown_sender);
-            Some(shutdown_receiver)
+        // Apply the unpersisted changes within the range that was scanned.
+        // Dirty keys take precedence over keys that are being persisted.
+        let scanned_through = if stopped.get() {
+            last_scanned.as_deref()
         } else {
             None
+        };
+        let pending = state
+            .keys_being_persisted
+            .iter()
+            .flat_map(|keys| keys.iter());
+        for (full_key, entry) in pending.chain(&state.dirty_keys) {
+            if after.map_or(false, |after| full_key.as_str() <= after)
+                || scanned_through.map_or(false, |last| full_key.as_str() > last)
+            {
+                continue;
+            }
+            if let Some(entry) = entry {
+                entries.insert(full_key.clone(), entry.clone());
+            } else {
+                entries.remove(full_key);
+            }
         }
+        drop(state);
+
+        let truncated = entries.len() > batch_size;
+        let entries = entries.into_iter().take(batch_size).collect::<Vec<_>>();
+        let next = if truncated {
+            entries.last().map(|(full_key, _)| full_key.clone())
+        } else if stopped.get() {
+            last_scanned
+        } else {
+            None
+        };
+        let batch = entries
+            .into_iter()
+            .filter_map(|(full_key, entry)| {
+                let (namespace, key) = split_key(&full_key)?;
+                Some(KeyValueEntry {
+                    namespace,
+                    key,
+                    value: entry.value,
+                    expiration: entry.expiration,
+                })
+            })
+            .collect();
+        Ok((batch, next))
     }
 
-    pub fn perform_kv_operation(
-        &mut self,
-        op: KeyOperation,
+    /// Removes all expired keys from this database's key-value store,
+    /// returning the number of keys removed.
+    ///
+    /// Expired keys are normally removed in the background. This function can
+    /// be used to ensure that keys that expired while the database was not
+    /// open are removed immediately. It is safe to call while the database is
+    /// in use.
+    ///
+    /// Expired keys are found and removed in batches, and other key-value
+    /// operations are only blocked while each batch is being removed.
+    pub fn sweep_expired(&self) -> Result<usize, bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::SweepExpired)),
+        )?;
+        Ok(self.data.context.sweep_expired_key_value_entries()?)
+    }
+
+    /// Permanently deletes every key in this database's key-value store,
+    /// including keys in all namespaces.
+    ///
+    /// This operation is not recorded in the transaction log and is not able
+    /// to be undone. It is intended for tasks such as resetting state between
+    /// tests, and should not be used while other clients are accessing the
+    /// key-value store.
+    pub fn clear_all_key_value_entries(&self) -> Result<(), bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        self.data.context.clear_all_key_value_entries()?;
+        Ok(())
+    }
+
+    /// Writes `entries` to `namespace` of this database's key-value store,
+    /// returning the number of entries written. Each entry is a key, its
+    /// value, and its optional expiration.
+    ///
+    /// This function is intended for loading a large number of keys, such as
+    /// when seeding a new database. Entries are written directly to disk in
+    /// large batches, and the in-memory expiration tracking is rebuilt once
+    /// all entries are written. Unlike executing a key-value operation for
+    /// each entry:
+    ///
+    /// - No existing values are returned and no checks are performed. Any
+    ///   existing value for a key is overwritten.
+    /// - Changes are not sent to replicas registered with
+    ///   [`register_key_value_replica()`](Self::register_key_value_replica),
+    ///   which means remote clients watching keys are not notified.
+    /// - All other key-value operations on this database wait for the load to
+    ///   complete.
+    ///
+    /// Keys with changes that haven't been persisted yet continue to report
+    /// their unpersisted values until they are persisted, at which point they
+    /// overwrite the loaded values. Loading should be done before other
+    /// writers are using the key-value store.
+    pub fn bulk_load_key_value_entries<I>(
+        &self,
+        namespace: Option<&str>,
+        entries: I,
+    ) -> Result<usize, bonsaidb_core::Error>
+    where
+        I: IntoIterator<Item = (String, Value, Option<Timestamp>)>,
+    {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        let last_updated = Timestamp::now();
+        let entries = entries.into_iter().map(|(key, value, expiration)| {
+            (
+                full_key(namespace, &key),
+                Entry {
+                    value,
+                    expiration,
+                    last_updated,
+                },
+            )
+        });
+        Ok(self.data.context.bulk_load_key_value_entries(entries)?)
+    }
+
+    /// Registers `sink` as a replica of this database's key-value store.
+    ///
+    /// After each batch of key-value changes is persisted, the changes are
+    /// sent to `sink` on a dedicated thread. Replication never blocks the
+    /// primary store: if `sink` falls more than [`REPLICATION_BUFFER_SIZE`]
+    /// batches behind, new batches are discarded and
+    /// [`KvReplicationSink::replication_failed()`] is invoked.
+    ///
+    /// Only changes persisted after `sink` is registered are replicated.
+    pub fn register_key_value_replica<Sink: KvReplicationSink>(
+        &self,
+        sink: Sink,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.check_permission(
+            kv_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+        let mut state = self.data.context.key_value_state.lock();
+        state.register_replica(Arc::new(sink));
+        Ok(())
+    }
+}
+
+/// The maximum number of persisted batches of key-value changes that can be
+/// waiting to be delivered to a [`KvReplicationSink`].
+pub const REPLICATION_BUFFER_SIZE: usize = 64;
+
+/// A destination for key-value changes replicated from a [`Database`]. See
+/// [`Database::register_key_value_replica()`].
+pub trait KvReplicationSink: Debug + Send + Sync + 'static {
+    /// Applies a batch of persisted `changes` to this replica. Batches are
+    /// delivered in the order they were persisted.
+    fn replicate(&self, changes: &[ReplicatedKey]) -> Result<(), bonsaidb_core::Error>;
+
+    /// Invoked when a batch of changes could not be replicated. By default,
+    /// the error is logged.
+    fn replication_failed(&self, error: KvReplicationError) {
+        log::error!("key-value replication failed: {error}");
+    }
+
+    /// Returns true if this sink no longer accepts changes. Closed sinks are
+    /// unregistered the next time changes are replicated. By default, sinks
+    /// are never closed.
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+/// An entry in a database's key-value store, returned by
+/// [`Database::key_value_entries_in_batches()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyValueEntry {
+    /// The namespace of the key.
+    pub namespace: Option<String>,
+    /// The key.
+    pub key: String,
+    /// The value stored in the key.
+    pub value: Value,
+    /// The expiration of the key, if any.
+    pub expiration: Option<Timestamp>,
+}
+
+/// A persisted key-value change being replicated to a [`KvReplicationSink`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicatedKey {
+    /// The namespace of the key.
+    pub namespace: Option<String>,
+    /// The key that changed.
+    pub key: String,
+    /// The new value of the key, or `None` if the key was deleted.
+    pub value: Option<Value>,
+    /// The expiration of the key, if any.
+    pub expiration: Option<Timestamp>,
+}
+
+/// An error replicating key-value changes to a [`KvReplicationSink`].
+#[derive(thiserror::Error, Debug)]
+pub enum KvReplicationError {
+    /// The sink returned an error while replicating a batch of changes.
+    #[error("the replica returned an error: {0}")]
+    Sink(bonsaidb_core::Error),
+    /// The sink was too far behind, and a batch of changes was discarded.
+    #[error("the replica fell behind, {dropped_keys} changed keys were discarded")]
+    BufferFull {
+        /// The number of changed keys that were discarded.
+        dropped_keys: usize,
+    },
+}
+
+#[derive(Debug)]
+struct Replica {
+    sink: Arc<dyn KvReplicationSink>,
+    sender: flume::Sender<Arc<Vec<ReplicatedKey>>>,
+}
+
+impl Replica {
+    fn spawn(sink: Arc<dyn KvReplicationSink>) -> Self {
+        let (sender, receiver) = flume::bounded::<Arc<Vec<ReplicatedKey>>>(REPLICATION_BUFFER_SIZE);
+        let thread_sink = sink.clone();
+        std::thread::Builder::new()
+            .name(String::from("keyvalue-replica"))
+            .spawn(move || {
+                // The loop exits once the key-value state is dropped.
+                while let Ok(changes) = receiver.recv() {
+                    if let Err(err) = thread_sink.replicate(&changes) {
+                        thread_sink.replication_failed(KvReplicationError::Sink(err));
+                    }
+                }
+            })
+            .unwrap();
+        Self { sink, sender }
+    }
+
+    fn send(&self, changes: &Arc<Vec<ReplicatedKey>>) {
+        if self.sender.try_send(changes.clone()).is_err() {
+            self.sink
+                .replication_failed(KvReplicationError::BufferFull {
+                    dropped_keys: changes.len(),
+                });
+        }
+    }
+}
+
+pub(crate) const KEY_TREE: &str = "kv";
+/// An index of the keys in [`KEY_TREE`] that have an expiration, ordered by
+/// expiration.
+pub(crate) const EXPIRATION_TREE: &str = "kv-expirations";
+/// Stored in [`EXPIRATION_TREE`] once the index has been built from the
+/// existing entries.
+const EXPIRATION_INDEX_BUILT: &[u8] = b"built";
+/// Holds the bytes of corrupt entries removed from [`KEY_TREE`] by
+/// [`CorruptKeyValuePolicy::Quarantine`].
+pub(crate) const QUARANTINE_TREE: &str = "kv-quarantine";
+/// Records which encoding the keys in [`KEY_TREE`] are stored with.
+const KEY_ENCODING_TREE: &str = "kv-encoding";
+/// Holds the values stored out-of-line when
+/// [`KeyValueBlobStorage`](crate::config::KeyValueBlobStorage) is
+/// configured, keyed by the hash of their bytes.
+pub(crate) const BLOB_TREE: &str = "kv-blobs";
+/// Holds each change committed to [`KEY_TREE`] when
+/// [`KeyValuePersistence::with_operation_log()`] is enabled, keyed by
+/// [`operation_log_key()`].
+pub(crate) const OPERATION_LOG_TREE: &str = "kv-operation-log";
+/// The prefix of an entry in [`KEY_TREE`] whose value is stored in
+/// [`BLOB_TREE`]. An [`Entry`] serialized by bincode has no prefix, and begins
+/// with the little-endian index of its [`Value`] variant, which can never be
+/// these bytes.
+const BLOB_ENTRY_MARKER: [u8; 4] = *b"\xffblb";
+/// The prefix of an [`Entry`] in [`KEY_TREE`] serialized by Pot.
+const POT_ENTRY_MARKER: [u8; 4] = *b"\xffpot";
+/// Stored in [`KEY_ENCODING_TREE`] once existing keys have been migrated to the
+/// encoding produced by [`full_key()`].
+const LENGTH_PREFIXED_KEYS: &[u8] = b"length-prefixed";
+/// The number of entries written to [`KEY_TREE`] in each transaction by
+/// [`KeyValueState::bulk_load()`].
+const BULK_LOAD_BATCH_SIZE: usize = 10_000;
+/// The number of keys in [`EXPIRATION_TREE`] read at once by
+/// [`KeyValueState::sweep_expired()`].
+const SWEEP_EXPIRED_BATCH_SIZE: usize = 1_000;
+
+/// An entry in [`KEY_TREE`] whose value is stored in [`BLOB_TREE`].
+#[derive(Serialize, Deserialize)]
+struct BlobReference {
+    hash: Bytes,
+    expiration: Option<Timestamp>,
+    last_updated: Timestamp,
+}
+
+/// A value stored in [`BLOB_TREE`], along with the number of entries that
+/// reference it.
+#[derive(Serialize, Deserialize)]
+struct Blob {
+    references: u64,
+    value: Bytes,
+}
+
+/// A view of a stored [`Entry`] that borrows the contents of a
+/// [`Value::Bytes`] from the serialized entry rather than decoding a [`Value`].
+#[derive(Deserialize)]
+struct RawEntry<'a> {
+    #[serde(borrow)]
+    value: RawValue<'a>,
+    expiration: Option<Timestamp>,
+    #[serde(default)]
+    #[allow(dead_code)]
+    last_updated: Timestamp,
+}
+
+/// Mirrors [`Value`], borrowing the contents of [`Value::Bytes`].
+#[derive(Deserialize)]
+#[serde(rename = "Value")]
+enum RawValue<'a> {
+    Bytes(&'a [u8]),
+    #[allow(dead_code)]
+    Numeric(Numeric),
+    #[allow(dead_code)]
+    Set(#[serde(borrow)] BTreeSet<&'a [u8]>),
+}
+
+/// A view of a [`BlobReference`] that borrows its hash.
+#[derive(Deserialize)]
+struct RawBlobReference<'a> {
+    hash: &'a [u8],
+    expiration: Option<Timestamp>,
+}
+
+/// A view of a [`Blob`] that borrows its value.
+#[derive(Deserialize)]
+struct RawBlob<'a> {
+    #[allow(dead_code)]
+    references: u64,
+    value: &'a [u8],
+}
+
+/// Reads the bytes of the entry stored as `bytes` without decoding its
+/// [`Value`]. Returns the expiration of the entry along with its bytes, or
+/// `None` if the entry doesn't contain bytes.
+fn read_raw_bytes(
+    roots: &Roots<AnyFile>,
+    bytes: &[u8],
+) -> Result<(Option<Timestamp>, Option<Bytes>), Error> {
+    if let Some(reference) = bytes.strip_prefix(&BLOB_ENTRY_MARKER) {
+        let reference = bincode::deserialize::<RawBlobReference<'_>>(reference)?;
+        let blob = roots
+            .tree(Unversioned::tree(BLOB_TREE))?
+            .get(reference.hash)?
+            .ok_or_else(|| Error::other("key-value", "referenced blob is missing"))?;
+        let blob = bincode::deserialize::<RawBlob<'_>>(&blob)?;
+        return Ok((reference.expiration, Some(Bytes::from(blob.value.to_vec()))));
+    }
+    let entry = if let Some(entry) = bytes.strip_prefix(&POT_ENTRY_MARKER) {
+        pot::from_slice::<RawEntry<'_>>(entry)?
+    } else {
+        bincode::deserialize::<RawEntry<'_>>(bytes)?
+    };
+    let value = match entry.value {
+        RawValue::Bytes(value) => Some(Bytes::from(value.to_vec())),
+        RawValue::Numeric(_) | RawValue::Set(_) => None,
+    };
+    Ok((entry.expiration, value))
+}
+
+/// An entry as it is stored in [`KEY_TREE`].
+enum StoredEntry {
+    Inline(Entry),
+    Blob(BlobReference),
+}
+
+impl StoredEntry {
+    fn deserialize(bytes: &[u8]) -> Result<Self, Error> {
+        if let Some(reference) = bytes.strip_prefix(&BLOB_ENTRY_MARKER) {
+            Ok(Self::Blob(bincode::deserialize(reference)?))
+        } else if let Some(entry) = bytes.strip_prefix(&POT_ENTRY_MARKER) {
+            Ok(Self::Inline(pot::from_slice(entry)?))
+        } else {
+            Ok(Self::Inline(bincode::deserialize(bytes)?))
+        }
+    }
+
+    fn expiration(&self) -> Option<Timestamp> {
+        match self {
+            Self::Inline(entry) => entry.expiration,
+            Self::Blob(reference) => reference.expiration,
+        }
+    }
+
+    fn blob_hash(&self) -> Option<&[u8]> {
+        match self {
+            Self::Inline(_) => None,
+            Self::Blob(reference) => Some(&reference.hash[..]),
+        }
+    }
+
+    /// Returns the entry, loading its value from [`BLOB_TREE`] if needed.
+    fn into_entry(self, roots: &Roots<AnyFile>) -> Result<Result<Entry, Error>, Error> {
+        match self {
+            Self::Inline(entry) => Ok(Ok(entry)),
+            Self::Blob(reference) => {
+                let blob = roots
+                    .tree(Unversioned::tree(BLOB_TREE))?
+                    .get(&reference.hash[..])?;
+                let blob = match blob {
+                    Some(blob) => bincode::deserialize::<Blob>(&blob).map_err(Error::from),
+                    None => Err(Error::other("key-value", "referenced blob is missing")),
+                };
+                Ok(blob.map(|blob| Entry {
+                    value: Value::Bytes(blob.value),
+                    expiration: reference.expiration,
+                    last_updated: reference.last_updated,
+                }))
+            }
+        }
+    }
+}
+
+/// Serializes `entry` for [`KEY_TREE`] using `encoding`.
+fn serialize_entry(entry: &Entry, encoding: KeyValueEntryEncoding) -> Vec<u8> {
+    match encoding {
+        KeyValueEntryEncoding::Pot => {
+            let mut bytes = POT_ENTRY_MARKER.to_vec();
+            bytes.extend(pot::to_vec(entry).unwrap());
+            bytes
+        }
+        KeyValueEntryEncoding::Bincode => bincode::serialize(entry).unwrap(),
+    }
+}
+
+/// Serializes `entry` for [`KEY_TREE`] as a reference to the blob stored at
+/// `hash`.
+fn serialize_blob_reference(hash: &[u8], entry: &Entry) -> Vec<u8> {
+    let mut bytes = BLOB_ENTRY_MARKER.to_vec();
+    bincode::serialize_into(
+        &mut bytes,
+        &BlobReference {
+            hash: Bytes::from(hash.to_vec()),
+            expiration: entry.expiration,
+            last_updated: entry.last_updated,
+        },
+    )
+    .unwrap();
+    bytes
+}
+
+/// Deserializes the entry stored at `full_key`, handling a corrupt entry as
+/// dictated by `policy`. When quarantining, corrupt entries are added to
+/// `corrupt` to be passed to [`quarantine_entries()`].
+fn deserialize_entry(
+    roots: &Roots<AnyFile>,
+    full_key: &[u8],
+    bytes: &[u8],
+    policy: CorruptKeyValuePolicy,
+    corrupt: &mut Vec<(Vec<u8>, Vec<u8>)>,
+) -> Result<Option<Entry>, Error> {
+    let entry = match StoredEntry::deserialize(bytes) {
+        Ok(stored) => stored.into_entry(roots)?,
+        Err(err) => Err(err),
+    };
+    match entry {
+        Ok(entry) => Ok(Some(entry)),
+        Err(err) if policy == CorruptKeyValuePolicy::Error => Err(err),
+        Err(err) => {
+            log::error!(
+                "corrupt key-value entry {:?}: {err}",
+                String::from_utf8_lossy(full_key)
+            );
+            if policy == CorruptKeyValuePolicy::Quarantine {
+                corrupt.push((full_key.to_vec(), bytes.to_vec()));
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// Moves `corrupt` entries from [`KEY_TREE`] into [`QUARANTINE_TREE`]. Entries
+/// that have been overwritten since they were read are left in place.
+fn quarantine_entries(
+    roots: &Roots<AnyFile>,
+    corrupt: Vec<(Vec<u8>, Vec<u8>)>,
+) -> Result<(), Error> {
+    if corrupt.is_empty() {
+        return Ok(());
+    }
+
+    let corrupt = corrupt.into_iter().collect::<BTreeMap<_, _>>();
+    let mut transaction = roots.transaction(&[
+        Unversioned::tree(KEY_TREE),
+        Unversioned::tree(QUARANTINE_TREE),
+        Unversioned::tree(EXECUTED_TRANSACTIONS_TREE),
+    ])?;
+    let mut quarantined = BTreeMap::new();
+    transaction.tree::<Unversioned>(0).unwrap().modify(
+        corrupt
+            .keys()
+            .map(|key| ArcBytes::from(key.clone()))
+            .collect(),
+        Operation::CompareSwap(CompareSwap::new(&mut |key, existing_value| {
+            let corrupt_bytes = corrupt.get(&key[..]).map(Vec::as_slice);
+            match existing_value {
+                Some(existing) if Some(&existing[..]) == corrupt_bytes => {
+                    quarantined.insert(key.to_vec(), existing);
+                    nebari::tree::KeyOperation::Remove
+                }
+                _ => nebari::tree::KeyOperation::Skip,
+            }
+        })),
+    )?;
+    if quarantined.is_empty() {
+        return Ok(());
+    }
+
+    transaction.tree::<Unversioned>(1).unwrap().modify(
+        quarantined
+            .keys()
+            .map(|key| ArcBytes::from(key.clone()))
+            .collect(),
+        Operation::CompareSwap(CompareSwap::new(&mut |key, _| {
+            nebari::tree::KeyOperation::Set(quarantined[&key[..]].clone())
+        })),
+    )?;
+    let changed_keys = quarantined
+        .keys()
+        .filter_map(|full_key| {
+            let (namespace, key) = split_key(std::str::from_utf8(full_key).ok()?)?;
+            Some(ChangedKey {
+                namespace,
+                key,
+                deleted: true,
+            })
+        })
+        .collect();
+    let transaction_id = transaction.entry().id;
+    transaction_log::record(
+        &mut transaction.tree::<Unversioned>(2).unwrap(),
+        transaction_id,
+        Timestamp::now(),
+        compat::serialize_executed_transaction_changes(&Changes::Keys(changed_keys))?,
+    )?;
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Returns the key in [`OPERATION_LOG_TREE`] of the change to `full_key`
+/// committed at `committed_at`. The timestamp is placed between the encoded
+/// namespace and the key, which groups the changes by namespace and orders
+/// each namespace's changes by the time they were committed.
+fn operation_log_key(committed_at: Timestamp, full_key: &str) -> Vec<u8> {
+    let key = split_key_parts(full_key).map_or("", |(_, key)| key);
+    let namespace = &full_key[..full_key.len() - key.len()];
+    let mut log_key = Vec::with_capacity(12 + full_key.len());
+    log_key.extend_from_slice(namespace.as_bytes());
+    log_key.extend_from_slice(&committed_at.seconds.to_be_bytes());
+    log_key.extend_from_slice(&committed_at.nanos.to_be_bytes());
+    log_key.extend_from_slice(key.as_bytes());
+    log_key
+}
+
+/// Replays [`OPERATION_LOG_TREE`] to reconstruct the keys of `namespace` as
+/// they were at `timestamp`.
+fn replay_operation_log(
+    roots: &Roots<AnyFile>,
+    namespace: Option<&str>,
+    timestamp: Timestamp,
+) -> Result<BTreeMap<String, Entry>, Error> {
+    // The encoded namespace is never a prefix of another namespace's
+    // encoding, so only the changes to `namespace` begin with it. Keys are
+    // UTF-8 and never contain 0xFF, so `end` sorts after every change
+    // committed at `timestamp`.
+    let prefix = full_key(namespace, "");
+    let mut end = operation_log_key(timestamp, &prefix);
+    end.push(0xFF);
+    let mut entries = BTreeMap::new();
+    roots
+        .tree(Unversioned::tree(OPERATION_LOG_TREE))?
+        .scan::<Error, _, _, _, _>(
+            &(prefix.as_bytes()..end.as_slice()),
+            true,
+            |_, _, _| ScanEvaluation::ReadData,
+            |_, _| ScanEvaluation::ReadData,
+            |log_key, _, change: ArcBytes<'static>| {
+                let key = std::str::from_utf8(&log_key[prefix.len() + 12..]).ok();
+                if let Some(key) = key {
+                    match bincode::deserialize::<Option<Entry>>(&change)
+                        .map_err(|err| AbortError::Other(Error::from(err)))?
+                    {
+                        Some(entry) => entries.insert(key.to_string(), entry),
+                        None => entries.remove(key),
+                    };
+                }
+                Ok(())
+            },
+        )?;
+    entries.retain(|_, entry| !entry.is_expired(timestamp));
+    Ok(entries)
+}
+
+fn expiration_index_key(expiration: Timestamp, full_key: &str) -> Vec<u8> {
+    let mut index_key = Vec::with_capacity(12 + full_key.len());
+    index_key.extend_from_slice(&expiration.seconds.to_be_bytes());
+    index_key.extend_from_slice(&expiration.nanos.to_be_bytes());
+    index_key.extend_from_slice(full_key.as_bytes());
+    index_key
+}
+
+fn parse_expiration_index_key(index_key: &[u8]) -> Option<(Timestamp, &str)> {
+    if index_key.len() < 12 {
+        return None;
+    }
+    let seconds = u64::from_be_bytes(index_key[..8].try_into().ok()?);
+    let nanos = u32::from_be_bytes(index_key[8..12].try_into().ok()?);
+    let full_key = std::str::from_utf8(&index_key[12..]).ok()?;
+    Some((Timestamp { seconds, nanos }, full_key))
+}
+
+/// Encodes `namespace` and `key` into the key stored in [`KEY_TREE`].
+///
+/// The namespace is prefixed with its length, which keeps the boundary between
+/// the namespace and key unambiguous regardless of the bytes either contains.
+/// The length is encoded as a variable-length integer storing 6 bits per byte,
+/// with `0x40` set on every byte except the last. Because every byte of the
+/// length is below `0x80`, the encoded key is still valid UTF-8.
+#[allow(clippy::cast_possible_truncation)]
+fn full_key(namespace: Option<&str>, key: &str) -> String {
+    let namespace = namespace.unwrap_or_default();
+    let mut full_key = String::with_capacity(namespace.len() + key.len() + 2);
+    let mut remaining_length = namespace.len();
+    loop {
+        let low_bits = (remaining_length & 0x3F) as u8;
+        remaining_length >>= 6;
+        if remaining_length == 0 {
+            full_key.push(char::from(low_bits));
+            break;
+        }
+        full_key.push(char::from(low_bits | 0x40));
+    }
+    full_key.push_str(namespace);
+    full_key.push_str(key);
+    full_key
+}
+
+fn split_key(full_key: &str) -> Option<(Option<String>, String)> {
+    let (namespace, key) = split_key_parts(full_key)?;
+    let namespace = if namespace.is_empty() {
+        None
+    } else {
+        Some(namespace.to_string())
+    };
+    Some((namespace, key.to_string()))
+}
+
+/// Returns true if executing `command` can store a key that doesn't exist.
+fn creates_keys(command: &Command) -> bool {
+    match command {
+        Command::Set(_)
+        | Command::Increment { .. }
+        | Command::Decrement { .. }
+        | Command::ModifySet(SetOperation::Add(_)) => true,
+        Command::Get { .. }
+        | Command::GetBytes { .. }
+        | Command::Delete
+        | Command::CompareAndDelete { .. }
+        | Command::Touch { .. }
+        | Command::ModifyField { .. }
+        | Command::ModifySet(
+            SetOperation::Remove(_) | SetOperation::Contains(_) | SetOperation::Count,
+        ) => false,
+    }
+}
+
+/// Adds the namespace of `full_key` to `namespaces`. Keys without a namespace
+/// are ignored.
+fn record_namespace(namespaces: &mut HashSet<String>, full_key: &str) {
+    if let Some((namespace, _)) = split_key_parts(full_key) {
+        if !namespace.is_empty() && !namespaces.contains(namespace) {
+            namespaces.insert(namespace.to_string());
+        }
+    }
+}
+
+/// Returns the namespace and key of `full_key` without copying them. A key
+/// without a namespace has an empty namespace.
+fn split_key_parts(full_key: &str) -> Option<(&str, &str)> {
+    let mut namespace_length = 0_usize;
+    let mut offset = 0;
+    for (index, byte) in full_key.bytes().enumerate() {
+        if byte >= 0x80 {
+            return None;
+        }
+        namespace_length |= usize::from(byte & 0x3F).checked_shl(u32::try_from(index * 6).ok()?)?;
+        if byte & 0x40 == 0 {
+            offset = index + 1;
+            break;
+        }
+    }
+    if offset == 0 {
+        return None;
+    }
+
+    let key_start = offset.checked_add(namespace_length)?;
+    let namespace = full_key.get(offset..key_start)?;
+    let key = full_key.get(key_start..)?;
+    Some((namespace, key))
+}
+
+/// Converts a key written by previous versions, which separated the namespace
+/// and key with a null byte, into the encoding produced by [`full_key()`].
+fn migrate_legacy_key(legacy_key: &str) -> Option<String> {
+    let (namespace, key) = legacy_key.split_once('\0')?;
+    Some(full_key(Some(namespace), key))
+}
+
+/// Rewrites the keys in [`KEY_TREE`], [`EXPIRATION_TREE`], and
+/// [`QUARANTINE_TREE`] that were written by previous versions to the encoding
+/// produced by [`full_key()`]. This must be called before the key-value store
+/// is accessed.
+pub(crate) fn migrate_key_encoding(roots: &Roots<AnyFile>) -> Result<(), Error> {
+    if roots
+        .tree(Unversioned::tree(KEY_ENCODING_TREE))?
+        .get(LENGTH_PREFIXED_KEYS)?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    let migrate_full_key = |legacy_key: &[u8]| {
+        migrate_legacy_key(std::str::from_utf8(legacy_key).ok()?).map(String::into_bytes)
+    };
+    let key_changes = legacy_key_changes(roots, KEY_TREE, migrate_full_key)?;
+    let index_changes = legacy_key_changes(roots, EXPIRATION_TREE, |legacy_key| {
+        let (expiration, legacy_key) = parse_expiration_index_key(legacy_key)?;
+        Some(expiration_index_key(
+            expiration,
+            &migrate_legacy_key(legacy_key)?,
+        ))
+    })?;
+    let quarantine_changes = legacy_key_changes(roots, QUARANTINE_TREE, migrate_full_key)?;
+
+    let mut transaction = roots.transaction(&[
+        Unversioned::tree(KEY_TREE),
+        Unversioned::tree(EXPIRATION_TREE),
+        Unversioned::tree(QUARANTINE_TREE),
+        Unversioned::tree(KEY_ENCODING_TREE),
+    ])?;
+    for (tree_index, changes) in [key_changes, index_changes, quarantine_changes]
+        .into_iter()
+        .enumerate()
+    {
+        if changes.is_empty() {
+            continue;
+        }
+        let keys = changes
+            .keys()
+            .map(|key| ArcBytes::from(key.clone()))
+            .collect();
+        let mut tree = transaction.tree::<Unversioned>(tree_index).unwrap();
+        tree.modify(
+            keys,
+            Operation::CompareSwap(CompareSwap::new(&mut |key, _| match &changes[&key[..]] {
+                Some(value) => nebari::tree::KeyOperation::Set(value.clone()),
+                None => nebari::tree::KeyOperation::Remove,
+            })),
+        )?;
+    }
+    transaction
+        .tree::<Unversioned>(3)
+        .unwrap()
+        .set(LENGTH_PREFIXED_KEYS.to_vec(), b"")?;
+    // The migration doesn't change any values, so no data is recorded in the
+    // transaction log.
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Returns the changes needed to migrate every key in `tree` using `migrate`.
+/// Legacy keys map to `None` to remove them, and migrated keys map to their
+/// values.
+fn legacy_key_changes(
+    roots: &Roots<AnyFile>,
+    tree: &'static str,
+    migrate: impl Fn(&[u8]) -> Option<Vec<u8>>,
+) -> Result<BTreeMap<Vec<u8>, Option<ArcBytes<'static>>>, Error> {
+    let mut changes = BTreeMap::new();
+    let mut migrated = Vec::new();
+    for (legacy_key, value) in roots.tree(Unversioned::tree(tree))?.get_range(&(..))? {
+        if let Some(key) = migrate(&legacy_key[..]) {
+            if key[..] != legacy_key[..] {
+                changes.insert(legacy_key.to_vec(), None);
+                migrated.push((key, value));
+            }
+        }
+    }
+    // A migrated key can be identical to another entry's legacy key, in which
+    // case the migrated entry must not be removed.
+    for (key, value) in migrated {
+        changes.insert(key, Some(value));
+    }
+    Ok(changes)
+}
+
+fn increment(
+    existing: &Numeric,
+    amount: &Numeric,
+    saturating: bool,
+) -> Result<Numeric, bonsaidb_core::Error> {
+    Ok(match (existing, amount) {
+        (Numeric::Decimal(decimal), _) | (_, Numeric::Decimal(decimal)) => {
+            // Once either side is a decimal, both sides must be exactly
+            // representable at the same scale.
+            let existing_value = existing.as_decimal(decimal.scale())?;
+            let amount = amount.as_decimal(decimal.scale())?;
+            let new_value = if saturating {
+                existing_value.value().saturating_add(amount.value())
+            } else {
+                existing_value.value().wrapping_add(amount.value())
+            };
+            Numeric::Decimal(Decimal::new(new_value, decimal.scale()))
+        }
+        (_, Numeric::Integer(amount)) => {
+            let existing_value = existing.as_i64_lossy(saturating);
+            let new_value = if saturating {
+                existing_value.saturating_add(*amount)
+            } else {
+                existing_value.wrapping_add(*amount)
+            };
+            Numeric::Integer(new_value)
+        }
+        (_, Numeric::UnsignedInteger(amount)) => {
+            let existing_value = existing.as_u64_lossy(saturating);
+            let new_value = if saturating {
+                existing_value.saturating_add(*amount)
+            } else {
+                existing_value.wrapping_add(*amount)
+            };
+            Numeric::UnsignedInteger(new_value)
+        }
+        (_, Numeric::Float(amount)) => {
+            let existing_value = existing.as_f64_lossy();
+            let new_value = existing_value + *amount;
+            Numeric::Float(new_value)
+        }
+    })
+}
+
+/// Returns the field reached by descending through the fields named in `path`.
+fn find_field<'v, 'a>(
+    value: &'v mut pot::Value<'a>,
+    path: &[String],
+) -> Result<&'v mut pot::Value<'a>, bonsaidb_core::Error> {
+    let mut field = value;
+    for name in path {
+        let pot::Value::Mappings(mappings) = field else {
+            return Err(bonsaidb_core::Error::other(
+                "bonsaidb-local",
+                format!("field `{name}` can't be found in a value that isn't a structure"),
+            ));
+        };
+        field = mappings
+            .iter_mut()
+            .find_map(|(key, value)| {
+                matches!(key, pot::Value::String(key) if **key == **name).then_some(value)
+            })
+            .ok_or_else(|| {
+                bonsaidb_core::Error::other("bonsaidb-local", format!("field `{name}` not found"))
+            })?;
+    }
+    Ok(field)
+}
+
+/// Converts a numeric field to a [`Numeric`]. Integers are converted to
+/// [`Numeric::Integer`] if they fit in an `i64`.
+fn field_as_numeric(field: &pot::Value<'_>) -> Result<Numeric, bonsaidb_core::Error> {
+    match field {
+        pot::Value::Integer(_) => {
+            let bytes = pot::to_vec(field)?;
+            match pot::from_slice::<i64>(&bytes) {
+                Ok(value) => Ok(Numeric::Integer(value)),
+                Err(_) => Ok(Numeric::UnsignedInteger(pot::from_slice::<u64>(&bytes)?)),
+            }
+        }
+        pot::Value::Float(_) => {
+            let bytes = pot::to_vec(field)?;
+            Ok(Numeric::Float(pot::from_slice::<f64>(&bytes)?))
+        }
+        _ => Err(bonsaidb_core::Error::other(
+            "bonsaidb-local",
+            "the field is not a number",
+        )),
+    }
+}
+
+fn decrement(
+    existing: &Numeric,
+    amount: &Numeric,
+    saturating: bool,
+) -> Result<Numeric, bonsaidb_core::Error> {
+    Ok(match (existing, amount) {
+        (Numeric::Decimal(decimal), _) | (_, Numeric::Decimal(decimal)) => {
+            // Once either side is a decimal, both sides must be exactly
+            // representable at the same scale.
+            let existing_value = existing.as_decimal(decimal.scale())?;
+            let amount = amount.as_decimal(decimal.scale())?;
+            let new_value = if saturating {
+                existing_value.value().saturating_sub(amount.value())
+            } else {
+                existing_value.value().wrapping_sub(amount.value())
+            };
+            Numeric::Decimal(Decimal::new(new_value, decimal.scale()))
+        }
+        (_, Numeric::Integer(amount)) => {
+            let existing_value = existing.as_i64_lossy(saturating);
+            let new_value = if saturating {
+                existing_value.saturating_sub(*amount)
+            } else {
+                existing_value.wrapping_sub(*amount)
+            };
+            Numeric::Integer(new_value)
+        }
+        (_, Numeric::UnsignedInteger(amount)) => {
+            let existing_value = existing.as_u64_lossy(saturating);
+            let new_value = if saturating {
+                existing_value.saturating_sub(*amount)
+            } else {
+                existing_value.wrapping_sub(*amount)
+            };
+            Numeric::UnsignedInteger(new_value)
+        }
+        (_, Numeric::Float(amount)) => {
+            let existing_value = existing.as_f64_lossy();
+            let new_value = existing_value - *amount;
+            Numeric::Float(new_value)
+        }
+    })
+}
+
+/// Converts `limit` to the same numeric type as `value`, so that limiting a
+/// value doesn't change the type stored in the key.
+fn limit_as_type_of(limit: &Numeric, value: &Numeric) -> Result<Numeric, bonsaidb_core::Error> {
+    Ok(match value {
+        Numeric::Integer(_) => Numeric::Integer(limit.as_i64_lossy(true)),
+        Numeric::UnsignedInteger(_) => Numeric::UnsignedInteger(limit.as_u64_lossy(true)),
+        Numeric::Float(_) => Numeric::Float(limit.as_f64_lossy()),
+        Numeric::Decimal(decimal) => Numeric::Decimal(limit.as_decimal(decimal.scale())?),
+    })
+}
+
+#[derive(Debug)]
+pub struct KeyValueState {
+    roots: Roots<AnyFile>,
+    persistence: KeyValuePersistence,
+    corrupt_key_value_policy: CorruptKeyValuePolicy,
+    last_commit: Timestamp,
+    background_worker_target: Watchable<BackgroundWorkerProcessTarget>,
+    /// The registration with the storage's shared worker, if the key-value
+    /// store isn't serviced by its own worker.
+    shared_worker: Option<Registration>,
+    expiring_keys: BTreeMap<String, Timestamp>,
+    expiration_order: VecDeque<String>,
+    dirty_keys: BTreeMap<String, Option<Entry>>,
+    /// The number of keys in `dirty_keys` for each namespace that has its own
+    /// persistence rules.
+    dirty_namespaces: HashMap<String, usize>,
+    /// The namespaces that contain keys, loaded the first time the configured
+    /// namespace limit is checked. Namespaces are not removed when their last
+    /// key is removed.
+    namespaces_in_use: Option<HashSet<String>>,
+    keys_being_persisted: Option<Arc<BTreeMap<String, Option<Entry>>>>,
+    last_persistence: Watchable<Timestamp>,
+    shutdown: Option<flume::Sender<()>>,
+    replicas: Vec<Replica>,
+    write_ahead_log: Option<WriteAheadLog>,
+    /// The sealed write-ahead log segment containing the changes in
+    /// `keys_being_persisted`.
+    wal_segment_being_persisted: Option<PathBuf>,
+    slow_operation_log: SlowOperationLog,
+}
+
+impl KeyValueState {
+    pub fn new(
+        persistence: KeyValuePersistence,
+        corrupt_key_value_policy: CorruptKeyValuePolicy,
+        roots: Roots<AnyFile>,
+        background_worker_target: Watchable<BackgroundWorkerProcessTarget>,
+    ) -> Self {
+        let write_ahead_log = persistence
+            .uses_write_ahead_log()
+            .then(|| WriteAheadLog::new(roots.path()));
+        Self {
+            roots,
+            persistence,
+            corrupt_key_value_policy,
+            last_commit: Timestamp::now(),
+            expiring_keys: BTreeMap::new(),
+            background_worker_target,
+            shared_worker: None,
+            expiration_order: VecDeque::new(),
+            dirty_keys: BTreeMap::new(),
+            dirty_namespaces: HashMap::new(),
+            namespaces_in_use: None,
+            keys_being_persisted: None,
+            last_persistence: Watchable::new(Timestamp::MIN),
+            shutdown: None,
+            replicas: Vec::new(),
+            write_ahead_log,
+            wal_segment_being_persisted: None,
+            slow_operation_log: SlowOperationLog::default(),
+        }
+    }
+
+    pub fn set_slow_operation_log(&mut self, slow_operation_log: SlowOperationLog) {
+        self.slow_operation_log = slow_operation_log;
+    }
+
+    /// Services this key-value store using `registration` instead of a
+    /// dedicated worker.
+    pub fn set_shared_worker(&mut self, registration: Registration) {
+        registration.schedule(*self.background_worker_target.read());
+        self.shared_worker = Some(registration);
+    }
+
+    fn register_replica(&mut self, sink: Arc<dyn KvReplicationSink>) {
+        self.replicas.push(Replica::spawn(sink));
+    }
+
+    pub fn shutdown(&mut self, state: &Arc<Mutex<KeyValueState>>) -> Option<flume::Receiver<()>> {
+        if self.keys_being_persisted.is_none() && self.commit_dirty_keys(state) {
+            let (shutdown_sender, shutdown_receiver) = flume::bounded(1);
+            self.shutdown = Some(shutdown_sender);
+            Some(shutdown_receiver)
+        } else {
+            None
+        }
+    }
+
+    pub fn perform_kv_operation(
+        &mut self,
+        op: KeyOperation,
+        state: &Arc<Mutex<KeyValueState>>,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let now = Timestamp::now();
+        // If there are any keys that have expired, clear them before executing any operations.
+        self.remove_expired_keys(now);
+        // Flushing the write-ahead log can fail after the change has been
+        // made in memory, in which case the change needs to be reverted.
+        let mut previous_entries = Vec::new();
+        if self.write_ahead_log.is_some() {
+            self.remember_previous_entry(&op, &mut previous_entries)?;
+        }
+        let output = self.execute_operation(op, now)?;
+        if let Err(err) = self.flush_write_ahead_log() {
+            self.restore_previous_entries(previous_entries);
+            return Err(err);
+        }
+        self.after_operations(now, state);
+        Ok(output)
+    }
+
+    pub fn perform_kv_pipeline(
+        &mut self,
+        pipeline: Pipeline,
         state: &Arc<Mutex<KeyValueState>>,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        let now = Timestamp::now();
+        self.remove_expired_keys(now);
+        let mut outputs = Vec::with_capacity(pipeline.steps.len());
+        let mut previous_entries = Vec::new();
+        for step in pipeline.steps {
+            match self.execute_pipeline_step(step, &outputs, &mut previous_entries, now) {
+                Ok(output) => outputs.push(output),
+                Err(err) => {
+                    self.restore_previous_entries(previous_entries);
+                    return Err(err);
+                }
+            }
+        }
+        if let Err(err) = self.flush_write_ahead_log() {
+            self.restore_previous_entries(previous_entries);
+            return Err(err);
+        }
+        self.after_operations(now, state);
+        Ok(outputs)
+    }
+
+    /// Waits for the changes made by the current operation to be durably
+    /// recorded in the write-ahead log, if one is enabled. The changes must
+    /// not be acknowledged if this fails, as they wouldn't be recovered after
+    /// a crash.
+    fn flush_write_ahead_log(&mut self) -> Result<(), bonsaidb_core::Error> {
+        if let Some(wal) = &mut self.write_ahead_log {
+            wal.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Pushes the entry currently stored for the key `operation` modifies onto
+    /// `previous_entries`, so that the change can be reverted.
+    fn remember_previous_entry(
+        &self,
+        operation: &KeyOperation,
+        previous_entries: &mut Vec<(String, Option<Entry>)>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        if !matches!(
+            operation.command,
+            Command::Get { delete: false, .. } | Command::GetBytes { delete: false, .. }
+        ) {
+            let full_key = full_key(operation.namespace.as_deref(), &operation.key);
+            let previous_entry = self.get(&full_key)?;
+            previous_entries.push((full_key, previous_entry));
+        }
+        Ok(())
+    }
+
+    /// Restores the keys in `previous_entries`, most recent first, so that no
+    /// changes made after they were remembered remain. Changes recorded in the
+    /// write-ahead log that haven't been flushed are discarded.
+    fn restore_previous_entries(&mut self, previous_entries: Vec<(String, Option<Entry>)>) {
+        for (full_key, entry) in previous_entries.into_iter().rev() {
+            let expiration = entry.as_ref().and_then(|entry| entry.expiration);
+            self.update_key_expiration(&full_key, expiration);
+            self.mark_dirty(full_key, entry);
+        }
+        if let Some(wal) = &mut self.write_ahead_log {
+            wal.discard();
+        }
+    }
+
+    fn execute_pipeline_step(
+        &mut self,
+        step: PipelineStep,
+        outputs: &[Option<Output>],
+        previous_entries: &mut Vec<(String, Option<Entry>)>,
+        now: Timestamp,
+    ) -> Result<Option<Output>, bonsaidb_core::Error> {
+        if let Some(condition) = &step.condition {
+            if !condition.evaluate(outputs)? {
+                return Ok(None);
+            }
+        }
+        self.remember_previous_entry(&step.operation, previous_entries)?;
+        self.execute_operation(step.operation, now).map(Some)
+    }
+
+    fn after_operations(&mut self, now: Timestamp, state: &Arc<Mutex<KeyValueState>>) {
+        if self.needs_commit(now) {
+            self.commit_dirty_keys(state);
+        }
+        self.update_background_worker_target();
+    }
+
+    fn execute_operation(
+        &mut self,
+        op: KeyOperation,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        self.check_namespace_limit(op.namespace.as_deref(), &op.command)?;
+        match op.command {
+            Command::Set(command) => {
+                self.execute_set_operation(op.namespace.as_deref(), &op.key, command, now)
+            }
+            Command::Get {
+                delete,
+                metadata,
+                committed,
+            } => self.execute_get_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                delete,
+                metadata,
+                committed,
+                now,
+            ),
+            Command::GetBytes { delete, committed } => self.execute_get_bytes_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                delete,
+                committed,
+                now,
+            ),
+            Command::Delete => self.execute_delete_operation(op.namespace.as_deref(), &op.key, now),
+            Command::CompareAndDelete { expected } => self.execute_compare_and_delete_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                &expected,
+                now,
+            ),
+            Command::Touch {
+                expiration,
+                expected,
+            } => self.execute_touch_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                expiration,
+                expected.as_ref(),
+                now,
+            ),
+            Command::Increment {
+                amount,
+                saturating,
+                maximum,
+                previous,
+            } => self.execute_increment_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                &amount,
+                saturating,
+                maximum.as_ref(),
+                previous,
+                now,
+            ),
+            Command::Decrement {
+                amount,
+                saturating,
+                minimum,
+                previous,
+            } => self.execute_decrement_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                &amount,
+                saturating,
+                minimum.as_ref(),
+                previous,
+                now,
+            ),
+            Command::ModifyField { path, operation } => self.execute_modify_field_operation(
+                op.namespace.as_deref(),
+                &op.key,
+                &path,
+                &operation,
+                now,
+            ),
+            Command::ModifySet(operation) => {
+                self.execute_modify_set_operation(op.namespace.as_deref(), &op.key, operation, now)
+            }
+        }
+    }
+
+    /// Returns an error if `command` can store a key in `namespace`,
+    /// `namespace` doesn't contain any keys, and the configured maximum
+    /// number of namespaces has been reached.
+    fn check_namespace_limit(
+        &mut self,
+        namespace: Option<&str>,
+        command: &Command,
+    ) -> Result<(), bonsaidb_core::Error> {
+        match namespace {
+            Some(namespace) if creates_keys(command) => self.check_new_namespace(namespace),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if `namespace` doesn't contain any keys and the
+    /// configured maximum number of namespaces has been reached.
+    fn check_new_namespace(&mut self, namespace: &str) -> Result<(), bonsaidb_core::Error> {
+        let limit = match self.persistence.max_namespaces() {
+            Some(limit) if !namespace.is_empty() => limit,
+            _ => return Ok(()),
+        };
+        if self.namespaces_in_use.is_none() {
+            self.namespaces_in_use = Some(self.load_namespaces_in_use()?);
+        }
+        match &self.namespaces_in_use {
+            Some(namespaces) if namespaces.len() >= limit && !namespaces.contains(namespace) => {
+                Err(bonsaidb_core::Error::TooManyKeyValueNamespaces { limit })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the namespaces of the keys stored on disk and the keys waiting
+    /// to be persisted.
+    fn load_namespaces_in_use(&self) -> Result<HashSet<String>, Error> {
+        let mut namespaces = HashSet::new();
+        self.roots
+            .tree(Unversioned::tree(KEY_TREE))?
+            .scan::<Infallible, _, _, _, _>(
+                &(..),
+                true,
+                |_, _, _| ScanEvaluation::ReadData,
+                |key, _| {
+                    if let Ok(full_key) = std::str::from_utf8(key) {
+                        record_namespace(&mut namespaces, full_key);
+                    }
+                    ScanEvaluation::Skip
+                },
+                |_, _, _| unreachable!(),
+            )?;
+        let pending = self
+            .keys_being_persisted
+            .iter()
+            .flat_map(|keys| keys.iter())
+            .chain(&self.dirty_keys);
+        for (full_key, entry) in pending {
+            if entry.is_some() {
+                record_namespace(&mut namespaces, full_key);
+            }
+        }
+
+        Ok(namespaces)
+    }
+
+    /// Returns the full keys stored on disk or waiting to be persisted that
+    /// begin with `prefix`. Keys that are pending removal are included.
+    fn keys_with_prefix(&self, prefix: &str) -> Result<BTreeSet<String>, Error> {
+        // See `scan_key_value_namespace()` for why incrementing the last byte
+        // produces the end of the range.
+        let start = prefix.as_bytes().to_vec();
+        let mut end = start.clone();
+        *end.last_mut().expect("prefix always contains a length") += 1;
+        let mut keys = BTreeSet::new();
+        self.roots
+            .tree(Unversioned::tree(KEY_TREE))?
+            .scan::<Infallible, _, _, _, _>(
+                &(start.as_slice()..end.as_slice()),
+                true,
+                |_, _, _| ScanEvaluation::ReadData,
+                |key, _| {
+                    if let Ok(full_key) = std::str::from_utf8(key) {
+                        keys.insert(full_key.to_string());
+                    }
+                    ScanEvaluation::Skip
+                },
+                |_, _, _| unreachable!(),
+            )?;
+        let pending = self
+            .keys_being_persisted
+            .iter()
+            .flat_map(|keys| keys.keys())
+            .chain(self.dirty_keys.keys());
+        keys.extend(pending.filter(|key| key.starts_with(prefix)).cloned());
+
+        Ok(keys)
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, set, now),)
+    )]
+    fn execute_set_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        set: SetCommand,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let expiration = match set.expiration {
+            Some(Expiration::Absolute(Timestamp::MAX)) => None,
+            Some(expiration) => Some(expiration.resolve(now)),
+            None => namespace
+                .and_then(|namespace| self.persistence.namespace_default_expiration(namespace))
+                .map(|expiration| now + expiration),
+        };
+        let mut entry = Entry {
+            value: set.value.validate()?,
+            expiration,
+            last_updated: now,
+        };
+        let full_key = full_key(namespace, key);
+        let possible_existing_value =
+            if set.check.is_some() || set.return_previous_value || set.keep_existing_expiration {
+                Some(self.get_unexpired(&full_key, now)?)
+            } else {
+                None
+            };
+        let existing_value_ref = possible_existing_value.as_ref().and_then(Option::as_ref);
+
+        let updating = match set.check {
+            Some(KeyCheck::OnlyIfPresent) => existing_value_ref.is_some(),
+            Some(KeyCheck::OnlyIfVacant) => existing_value_ref.is_none(),
+            None => true,
+        };
+        if updating {
+            if set.keep_existing_expiration {
+                if let Some(existing_value) = existing_value_ref {
+                    entry.expiration = existing_value.expiration;
+                }
+            }
+            self.update_key_expiration(&full_key, entry.expiration);
+
+            let previous_value = if let Some(existing_value) = possible_existing_value {
+                // we already fetched, no need to ask for the existing value back
+                self.set(full_key, entry);
+                existing_value
+            } else {
+                self.replace(full_key, entry)?
+                    .filter(|entry| !entry.is_expired(now))
+            };
+            if set.return_previous_value {
+                Ok(Output::Value(previous_value.map(|entry| entry.value)))
+            } else if previous_value.is_none() {
+                Ok(Output::Status(KeyStatus::Inserted))
+            } else {
+                Ok(Output::Status(KeyStatus::Updated))
+            }
+        } else {
+            Ok(Output::Status(KeyStatus::NotChanged))
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, tree_key, expiration))
+    )]
+    pub fn update_key_expiration<'key>(
+        &mut self,
+        tree_key: impl Into<Cow<'key, str>>,
+        expiration: Option<Timestamp>,
+    ) {
+        let tree_key = tree_key.into();
+        let mut changed_first_expiration = false;
+        if let Some(expiration) = expiration {
+            let key = if self.expiring_keys.contains_key(tree_key.as_ref()) {
+                // Update the existing entry.
+                let existing_entry_index = self
+                    .expiration_order
+                    .iter()
+                    .enumerate()
+                    .find_map(
+                        |(index, key)| {
+                            if &tree_key == key {
+                                Some(index)
+                            } else {
+                                None
+                            }
+                        },
+                    )
+                    .unwrap();
+                changed_first_expiration = existing_entry_index == 0;
+                self.expiration_order.remove(existing_entry_index).unwrap()
+            } else {
+                tree_key.into_owned()
+            };
+
+            // Insert the key into the expiration_order queue
+            let mut insert_at = None;
+            for (index, expiring_key) in self.expiration_order.iter().enumerate() {
+                if self.expiring_keys.get(expiring_key).unwrap() > &expiration {
+                    insert_at = Some(index);
+                    break;
+                }
+            }
+            if let Some(insert_at) = insert_at {
+                changed_first_expiration |= insert_at == 0;
+
+                self.expiration_order.insert(insert_at, key.clone());
+            } else {
+                changed_first_expiration |= self.expiration_order.is_empty();
+                self.expiration_order.push_back(key.clone());
+            }
+            self.expiring_keys.insert(key, expiration);
+        } else if self.expiring_keys.remove(tree_key.as_ref()).is_some() {
+            let index = self
+                .expiration_order
+                .iter()
+                .enumerate()
+                .find_map(|(index, key)| {
+                    if tree_key.as_ref() == key {
+                        Some(index)
+                    } else {
+                        None
+                    }
+                })
+                .unwrap();
+
+            changed_first_expiration |= index == 0;
+            self.expiration_order.remove(index);
+        }
+
+        if changed_first_expiration {
+            self.update_background_worker_target();
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn execute_get_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        delete: bool,
+        metadata: bool,
+        committed: bool,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        if committed && delete {
+            return Err(bonsaidb_core::Error::other(
+                "key-value",
+                "a committed read can't delete the key",
+            ));
+        }
+        let full_key = full_key(namespace, key);
+        let bytes = self.get_bytes(&full_key, committed, now)?;
+        if delete && bytes.is_some() {
+            self.remove(full_key)?;
+        }
+        Ok(Output::Bytes(bytes))
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn execute_get_bytes_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        delete: bool,
+        committed: bool,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let full_key = full_key(namespace, key);
+        let entry = if committed {
+            self.get_committed(&full_key, delete, now)?
+        } else {
+            self.get_unexpired(&full_key, now)?
+        };
+        match entry {
+            Some(Entry {
+                value: Value::Bytes(bytes),
+                ..
+            }) => {
+                if delete {
+                    self.remove(full_key)?;
+                }
+                Ok(Output::Bytes(Some(bytes)))
+            }
+            Some(_) => Err(bonsaidb_core::Error::other(
+                "key-value",
+                "key does not contain bytes",
+            )),
+            None => Ok(Output::Bytes(None)),
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn execute_delete_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let full_key = full_key(namespace, key);
+        let value = self.remove(full_key)?;
+        if value.map_or(false, |entry| !entry.is_expired(now)) {
+            Ok(Output::Status(KeyStatus::Deleted))
+        } else {
+            Ok(Output::Status(KeyStatus::NotChanged))
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn execute_compare_and_delete_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        expected: &Value,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let full_key = full_key(namespace, key);
+        let matches = self
+            .get_unexpired(&full_key, now)?
+            .map_or(false, |entry| &entry.value == expected);
+        if matches {
+            self.remove(full_key)?;
+            Ok(Output::Status(KeyStatus::Deleted))
+        } else {
+            Ok(Output::Status(KeyStatus::NotChanged))
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn execute_touch_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        expiration: Option<Expiration>,
+        expected: Option<&Value>,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let expiration = expiration.map(|expiration| expiration.resolve(now));
+        let full_key = full_key(namespace, key);
+        match self.get_unexpired(&full_key, now)? {
+            Some(mut entry) if expected.map_or(true, |expected| &entry.value == expected) => {
+                entry.expiration = expiration;
+                self.update_key_expiration(&full_key, expiration);
+                self.set(full_key, entry);
+                Ok(Output::Status(KeyStatus::Updated))
+            }
+            _ => Ok(Output::Status(KeyStatus::NotChanged)),
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, amount, saturating, previous, now))
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn execute_increment_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        amount: &Numeric,
+        saturating: bool,
+        maximum: Option<&Numeric>,
+        previous: bool,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let limit = maximum.map(|maximum| (maximum, Ordering::Greater));
+        self.execute_numeric_operation(
+            namespace, key, amount, saturating, limit, previous, now, increment,
+        )
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, amount, saturating, previous, now))
+    )]
+    #[allow(clippy::too_many_arguments)]
+    fn execute_decrement_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        amount: &Numeric,
+        saturating: bool,
+        minimum: Option<&Numeric>,
+        previous: bool,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let limit = minimum.map(|minimum| (minimum, Ordering::Less));
+        self.execute_numeric_operation(
+            namespace, key, amount, saturating, limit, previous, now, decrement,
+        )
+    }
+
+    /// Applies `op` to the numeric value stored at `key`. If `limit` is
+    /// provided, a result that compares to the limit with the given ordering
+    /// is replaced by the limit, and [`Output::Bounded`] is returned. If
+    /// `previous` is true, [`Output::Changed`] is returned instead, including
+    /// the value stored before the operation.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_numeric_operation<
+        F: Fn(&Numeric, &Numeric, bool) -> Result<Numeric, bonsaidb_core::Error>,
+    >(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        amount: &Numeric,
+        saturating: bool,
+        limit: Option<(&Numeric, Ordering)>,
+        previous: bool,
+        now: Timestamp,
+        op: F,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let full_key = full_key(namespace, key);
+        let current = self.get_unexpired(&full_key, now)?;
+        let mut entry = current.unwrap_or(Entry {
+            value: Value::Numeric(Numeric::UnsignedInteger(0)),
+            expiration: None,
+            last_updated: now,
+        });
+
+        match entry.value {
+            Value::Numeric(existing) => {
+                let mut value = op(&existing, amount, saturating)?.validate()?;
+                let mut limit_reached = false;
+                if let Some((limit, past_limit)) = limit {
+                    let limit = limit_as_type_of(limit, &value)?;
+                    if value.compare(&limit) == Some(past_limit) {
+                        value = limit;
+                        limit_reached = true;
+                    }
+                }
+                entry.value = Value::Numeric(value.clone());
+
+                self.set(full_key, entry);
+                if previous {
+                    Ok(Output::Changed(ChangedValue {
+                        previous: limit_as_type_of(&existing, &value)?,
+                        value,
+                        limit_reached,
+                    }))
+                } else if limit.is_some() {
+                    Ok(Output::Bounded(BoundedValue {
+                        value,
+                        limit_reached,
+                    }))
+                } else {
+                    Ok(Output::Value(Some(Value::Numeric(value))))
+                }
+            }
+            Value::Bytes(_) | Value::Set(_) => Err(bonsaidb_core::Error::other(
+                "bonsaidb-local",
+                "type of stored `Value` is not `Numeric`",
+            )),
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, operation, now))
+    )]
+    fn execute_modify_field_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        path: &[String],
+        operation: &FieldOperation,
+        now: Timestamp,
     ) -> Result<Output, bonsaidb_core::Error> {
+        let full_key = full_key(namespace, key);
+        let mut entry = match self.get_unexpired(&full_key, now)? {
+            Some(entry) => entry,
+            None => return Ok(Output::Value(None)),
+        };
+        let Value::Bytes(bytes) = &entry.value else {
+            return Err(bonsaidb_core::Error::other(
+                "bonsaidb-local",
+                "type of stored `Value` is not `Bytes`",
+            ));
+        };
+
+        let mut value = pot::from_slice::<pot::Value<'_>>(bytes)?;
+        let field = find_field(&mut value, path)?;
+        let new_value = match operation {
+            FieldOperation::Increment { amount, saturating } => {
+                increment(&field_as_numeric(field)?, amount, *saturating)?
+            }
+            FieldOperation::Decrement { amount, saturating } => {
+                decrement(&field_as_numeric(field)?, amount, *saturating)?
+            }
+        }
+        .validate()?;
+        *field = match new_value {
+            Numeric::Integer(value) => pot::Value::from(value),
+            Numeric::UnsignedInteger(value) => pot::Value::from(value),
+            Numeric::Float(value) => pot::Value::from(value),
+            Numeric::Decimal(_) => {
+                return Err(bonsaidb_core::Error::other(
+                    "bonsaidb-local",
+                    "a `Decimal` can't be stored in a field",
+                ))
+            }
+        };
+
+        let modified = pot::to_vec(&value)?;
+        // `value` borrows from the entry's current value.
+        drop(value);
+        entry.value = Value::Bytes(Bytes::from(modified));
+        self.set(full_key, entry);
+        Ok(Output::Value(Some(Value::Numeric(new_value))))
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(self, operation, now))
+    )]
+    fn execute_modify_set_operation(
+        &mut self,
+        namespace: Option<&str>,
+        key: &str,
+        operation: SetOperation,
+        now: Timestamp,
+    ) -> Result<Output, bonsaidb_core::Error> {
+        let full_key = full_key(namespace, key);
+        let entry = self.get_unexpired(&full_key, now)?;
+        if let Some(Entry {
+            value: Value::Bytes(_) | Value::Numeric(_),
+            ..
+        }) = &entry
+        {
+            return Err(bonsaidb_core::Error::other(
+                "bonsaidb-local",
+                "type of stored `Value` is not `Set`",
+            ));
+        }
+
+        match operation {
+            SetOperation::Add(member) => {
+                let Some(mut entry) = entry else {
+                    self.set(
+                        full_key,
+                        Entry {
+                            value: Value::Set(BTreeSet::from([member])),
+                            expiration: None,
+                            last_updated: now,
+                        },
+                    );
+                    return Ok(Output::Status(KeyStatus::Inserted));
+                };
+                let Value::Set(members) = &mut entry.value else {
+                    unreachable!("checked above")
+                };
+                if members.insert(member) {
+                    entry.last_updated = now;
+                    self.set(full_key, entry);
+                    Ok(Output::Status(KeyStatus::Updated))
+                } else {
+                    Ok(Output::Status(KeyStatus::NotChanged))
+                }
+            }
+            SetOperation::Remove(member) => {
+                let Some(mut entry) = entry else {
+                    return Ok(Output::Status(KeyStatus::NotChanged));
+                };
+                let Value::Set(members) = &mut entry.value else {
+                    unreachable!("checked above")
+                };
+                if !members.remove(&member) {
+                    Ok(Output::Status(KeyStatus::NotChanged))
+                } else if members.is_empty() {
+                    self.remove(full_key)?;
+                    Ok(Output::Status(KeyStatus::Deleted))
+                } else {
+                    entry.last_updated = now;
+                    self.set(full_key, entry);
+                    Ok(Output::Status(KeyStatus::Updated))
+                }
+            }
+            SetOperation::Contains(member) => {
+                let contains = matches!(
+                    entry,
+                    Some(Entry { value: Value::Set(members), .. }) if members.contains(&member)
+                );
+                Ok(Output::Value(Some(Value::Numeric(
+                    Numeric::UnsignedInteger(u64::from(contains)),
+                ))))
+            }
+            SetOperation::Count => {
+                let count = match entry {
+                    Some(Entry {
+                        value: Value::Set(members),
+                        ..
+                    }) => members.len() as u64,
+                    _ => 0,
+                };
+                Ok(Output::Value(Some(Value::Numeric(
+                    Numeric::UnsignedInteger(count),
+                ))))
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn remove(&mut self, key: String) -> Result<Option<Entry>, Error> {
+        self.update_key_expiration(&key, None);
+
+        if let Some(dirty_entry) = self.dirty_keys.get_mut(&key) {
+            let previous_value = dirty_entry.take();
+            if let Some(wal) = &mut self.write_ahead_log {
+                wal.record(&key, None);
+            }
+            Ok(previous_value)
+        } else if let Some(persisting_entry) = self
+            .keys_being_persisted
+            .as_ref()
+            .and_then(|keys| keys.get(&key))
+        {
+            self.mark_dirty(key, None);
+            Ok(persisting_entry.clone())
+        } else {
+            // There might be a value on-disk we need to remove.
+            let previous_value =
+                Self::retrieve_key_from_disk(&self.roots, self.corrupt_key_value_policy, &key)?;
+            self.mark_dirty(key, None);
+            Ok(previous_value)
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
+    fn get(&self, key: &str) -> Result<Option<Entry>, Error> {
+        if let Some(entry) = self.dirty_keys.get(key) {
+            Ok(entry.clone())
+        } else if let Some(persisting_entry) = self
+            .keys_being_persisted
+            .as_ref()
+            .and_then(|keys| keys.get(key))
+        {
+            Ok(persisting_entry.clone())
+        } else {
+            Self::retrieve_key_from_disk(&self.roots, self.corrupt_key_value_policy, key)
+        }
+    }
+
+    /// Returns the entry stored at `key`, treating an entry that has expired
+    /// as of `now` as absent. The background worker may not have removed an
+    /// expired entry yet, such as when its expiration hasn't been loaded
+    /// since the database was opened, so the entry is removed here instead.
+    fn get_unexpired(&mut self, key: &str, now: Timestamp) -> Result<Option<Entry>, Error> {
+        match self.get(key)? {
+            Some(entry) if entry.is_expired(now) => {
+                self.update_key_expiration(key, None);
+                self.mark_dirty(key.to_string(), None);
+                Ok(None)
+            }
+            entry => Ok(entry),
+        }
+    }
+
+    /// Returns the bytes stored at `key`, treating an entry that has expired
+    /// as of `now` as absent like [`Self::get_unexpired()`]. Entries read from
+    /// disk have their bytes copied out of the stored entry without decoding
+    /// a [`Value`]. When `committed`, unpersisted changes are ignored and
+    /// expired entries are left in place.
+    fn get_bytes(
+        &mut self,
+        key: &str,
+        committed: bool,
+        now: Timestamp,
+    ) -> Result<Option<Bytes>, bonsaidb_core::Error> {
+        let pending = if committed {
+            None
+        } else {
+            self.dirty_keys.get(key).or_else(|| {
+                self.keys_being_persisted
+                    .as_ref()
+                    .and_then(|keys| keys.get(key))
+            })
+        };
+        let (expiration, bytes) = match pending {
+            Some(None) => return Ok(None),
+            Some(Some(entry)) => match &entry.value {
+                Value::Bytes(bytes) => (entry.expiration, Some(bytes.clone())),
+                Value::Numeric(_) | Value::Set(_) => (entry.expiration, None),
+            },
+            None => {
+                let stored = self
+                    .roots
+                    .tree(Unversioned::tree(KEY_TREE))
+                    .map_err(Error::from)?
+                    .get(key.as_bytes())
+                    .map_err(Error::from)?;
+                let raw = match &stored {
+                    Some(stored) => read_raw_bytes(&self.roots, stored).ok(),
+                    None => return Ok(None),
+                };
+                if let Some(raw) = raw {
+                    raw
+                } else {
+                    // The entry couldn't be read, so it is handled as dictated
+                    // by the corrupt entry policy.
+                    match Self::retrieve_key_from_disk(
+                        &self.roots,
+                        self.corrupt_key_value_policy,
+                        key,
+                    )? {
+                        Some(Entry {
+                            value: Value::Bytes(bytes),
+                            expiration,
+                            ..
+                        }) => (expiration, Some(bytes)),
+                        Some(entry) => (entry.expiration, None),
+                        None => return Ok(None),
+                    }
+                }
+            }
+        };
+
+        if expiration.map_or(false, |expiration| expiration <= now) {
+            if !committed {
+                self.update_key_expiration(key, None);
+                self.mark_dirty(key.to_string(), None);
+            }
+            Ok(None)
+        } else if bytes.is_some() {
+            Ok(bytes)
+        } else {
+            Err(bonsaidb_core::Error::other(
+                "key-value",
+                "key does not contain bytes",
+            ))
+        }
+    }
+
+    /// Returns the expiration of each key in `full_keys`, in order. Keys that
+    /// don't exist, don't expire, or have expired as of `now` have an
+    /// expiration of `None`. Keys with unpersisted changes or a tracked
+    /// expiration are answered from memory, and the remaining keys are read
+    /// from disk at once.
+    fn key_expirations(
+        &self,
+        full_keys: &[String],
+        now: Timestamp,
+    ) -> Result<Vec<Option<Timestamp>>, Error> {
+        let mut expirations = Vec::with_capacity(full_keys.len());
+        // The indexes of the keys that need to be read from disk.
+        let mut unresolved = BTreeMap::<&str, Vec<usize>>::new();
+        for (index, full_key) in full_keys.iter().enumerate() {
+            let pending = self.dirty_keys.get(full_key).or_else(|| {
+                self.keys_being_persisted
+                    .as_ref()
+                    .and_then(|keys| keys.get(full_key))
+            });
+            if let Some(entry) = pending {
+                expirations.push(entry.as_ref().and_then(|entry| entry.expiration));
+            } else if let Some(expiration) = self.expiring_keys.get(full_key) {
+                expirations.push(Some(*expiration));
+            } else {
+                expirations.push(None);
+                unresolved.entry(full_key).or_default().push(index);
+            }
+        }
+
+        if !unresolved.is_empty() {
+            let stored = self
+                .roots
+                .tree(Unversioned::tree(KEY_TREE))?
+                .get_multiple(unresolved.keys().map(|full_key| full_key.as_bytes()))?;
+            let mut corrupt = Vec::new();
+            for (full_key, bytes) in stored {
+                let entry = deserialize_entry(
+                    &self.roots,
+                    &full_key,
+                    &bytes,
+                    self.corrupt_key_value_policy,
+                    &mut corrupt,
+                )?;
+                let indexes = std::str::from_utf8(&full_key)
+                    .ok()
+                    .and_then(|full_key| unresolved.get(full_key));
+                if let (Some(entry), Some(indexes)) = (entry, indexes) {
+                    for &index in indexes {
+                        expirations[index] = entry.expiration;
+                    }
+                }
+            }
+            quarantine_entries(&self.roots, corrupt)?;
+        }
+
+        for expiration in &mut expirations {
+            if expiration.map_or(false, |expiration| expiration <= now) {
+                *expiration = None;
+            }
+        }
+        Ok(expirations)
+    }
+
+    /// Returns the entry most recently committed to disk at `key`, ignoring
+    /// changes that haven't finished persisting. Committed reads are
+    /// read-only, so `delete` is rejected.
+    fn get_committed(
+        &self,
+        key: &str,
+        delete: bool,
+        now: Timestamp,
+    ) -> Result<Option<Entry>, bonsaidb_core::Error> {
+        if delete {
+            return Err(bonsaidb_core::Error::other(
+                "key-value",
+                "a committed read can't delete the key",
+            ));
+        }
+        let entry = Self::retrieve_key_from_disk(&self.roots, self.corrupt_key_value_policy, key)?;
+        Ok(entry.filter(|entry| !entry.is_expired(now)))
+    }
+
+    fn set(&mut self, key: String, value: Entry) {
+        self.mark_dirty(key, Some(value));
+    }
+
+    fn replace(&mut self, key: String, value: Entry) -> Result<Option<Entry>, Error> {
+        if let Some(dirty_entry) = self.dirty_keys.get_mut(&key) {
+            // This key is already dirty, we can just replace the value and
+            // return the old value.
+            let previous_value = std::mem::replace(dirty_entry, Some(value));
+            if let Some(wal) = &mut self.write_ahead_log {
+                wal.record(&key, dirty_entry.as_ref());
+            }
+            Ok(previous_value)
+        } else {
+            // This key is clean, and the caller is expecting the previous
+            // value.
+            let stored_value = if let Some(persisting_entry) = self
+                .keys_being_persisted
+                .as_ref()
+                .and_then(|keys| keys.get(&key))
+            {
+                persisting_entry.clone()
+            } else {
+                Self::retrieve_key_from_disk(&self.roots, self.corrupt_key_value_policy, &key)?
+            };
+            self.mark_dirty(key, Some(value));
+            Ok(stored_value)
+        }
+    }
+
+    /// Stores `entry` as the pending change for `key`, counting the key
+    /// against its namespace's persistence rules if it wasn't already dirty.
+    fn mark_dirty(&mut self, key: String, entry: Option<Entry>) {
+        if let Some(wal) = &mut self.write_ahead_log {
+            wal.record(&key, entry.as_ref());
+        }
+        if let (Some(namespaces), Some(_)) = (&mut self.namespaces_in_use, &entry) {
+            record_namespace(namespaces, &key);
+        }
+        if let Some(dirty_entry) = self.dirty_keys.get_mut(&key) {
+            *dirty_entry = entry;
+        } else {
+            if let Some((namespace, _)) = split_key_parts(&key) {
+                if self.persistence.has_namespace_rules(namespace) {
+                    *self
+                        .dirty_namespaces
+                        .entry(namespace.to_string())
+                        .or_default() += 1;
+                }
+            }
+            self.dirty_keys.insert(key, entry);
+        }
+    }
+
+    /// Returns how long until the dirty keys should be committed according to
+    /// the persistence rules of each namespace with changes.
+    fn duration_until_next_commit(&self, now: Timestamp) -> Option<Duration> {
+        let since_last_commit = (now - self.last_commit).unwrap_or_default();
+        let namespaced_keys = self.dirty_namespaces.values().sum::<usize>();
+        let mut next_commit = self
+            .persistence
+            .duration_until_next_commit(self.dirty_keys.len() - namespaced_keys, since_last_commit);
+        for (namespace, dirty_keys) in &self.dirty_namespaces {
+            if let Some(duration) = self.persistence.namespace_duration_until_next_commit(
+                namespace,
+                *dirty_keys,
+                since_last_commit,
+            ) {
+                next_commit = Some(next_commit.map_or(duration, |next| next.min(duration)));
+            }
+        }
+        next_commit
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(roots)))]
+    fn retrieve_key_from_disk(
+        roots: &Roots<AnyFile>,
+        policy: CorruptKeyValuePolicy,
+        key: &str,
+    ) -> Result<Option<Entry>, Error> {
+        let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+        let Some(current) = tree.get(key.as_bytes())? else { return Ok(None) };
+        let mut corrupt = Vec::new();
+        let entry = deserialize_entry(roots, key.as_bytes(), &current, policy, &mut corrupt)?;
+        quarantine_entries(roots, corrupt)?;
+        Ok(entry)
+    }
+
+    fn update_background_worker_target(&mut self) {
+        let key_expiration_target = self.expiration_order.get(0).map(|key| {
+            let expiration_timeout = self.expiring_keys.get(key).unwrap();
+            *expiration_timeout
+        });
+        let now = Timestamp::now();
+        let persisting = self.keys_being_persisted.is_some();
+        let commit_target = (!persisting)
+            .then(|| self.duration_until_next_commit(now))
+            .flatten()
+            .map(|duration| now + duration);
+        match (commit_target, key_expiration_target) {
+            (Some(target), _) | (_, Some(target)) if target <= now => {
+                self.background_worker_target
+                    .replace(BackgroundWorkerProcessTarget::Now);
+            }
+            (Some(commit_target), Some(key_target)) => {
+                let closest_target = key_target.min(commit_target);
+                let new_target = BackgroundWorkerProcessTarget::Timestamp(closest_target);
+                let _: Result<_, _> = self.background_worker_target.update(new_target);
+            }
+            (Some(target), None) | (None, Some(target)) => {
+                let _: Result<_, _> = self
+                    .background_worker_target
+                    .update(BackgroundWorkerProcessTarget::Timestamp(target));
+            }
+            (None, None) => {
+                let _: Result<_, _> = self
+                    .background_worker_target
+                    .update(BackgroundWorkerProcessTarget::Never);
+            }
+        }
+        if let Some(shared_worker) = &self.shared_worker {
+            shared_worker.schedule(*self.background_worker_target.read());
+        }
+    }
+
+    /// Removes the keys that have expired and commits the dirty keys if the
+    /// persistence rules require it.
+    fn perform_background_operations(&mut self, key_value_state: &Arc<Mutex<KeyValueState>>) {
         let now = Timestamp::now();
-        // If there are any keys that have expired, clear them before executing any operations.
         self.remove_expired_keys(now);
-        let result = match op.command {
-            Command::Set(command) => {
-                self.execute_set_operation(op.namespace.as_deref(), &op.key, command, now)
+        if self.needs_commit(now) {
+            self.commit_dirty_keys(key_value_state);
+        }
+        self.update_background_worker_target();
+    }
+
+    /// Rebuilds `expiration_order` from `expiring_keys`.
+    fn rebuild_expiration_order(&mut self) {
+        let mut expiration_order = self
+            .expiring_keys
+            .iter()
+            .map(|(key, expiration)| (*expiration, key.clone()))
+            .collect::<Vec<_>>();
+        expiration_order.sort_unstable();
+        self.expiration_order = expiration_order.into_iter().map(|(_, key)| key).collect();
+    }
+
+    fn remove_expired_keys(&mut self, now: Timestamp) {
+        while !self.expiration_order.is_empty()
+            && self.expiring_keys.get(&self.expiration_order[0]).unwrap() <= &now
+        {
+            let key = self.expiration_order.pop_front().unwrap();
+            self.expiring_keys.remove(&key);
+            self.mark_dirty(key, None);
+        }
+    }
+
+    /// Removes every key that has expired as of `now`, returning the number of
+    /// keys removed.
+    ///
+    /// Persisted keys are found using [`EXPIRATION_TREE`], which is read in
+    /// batches of up to [`SWEEP_EXPIRED_BATCH_SIZE`] keys. The state is locked
+    /// while each batch is read and removed, which prevents the keys from
+    /// being updated in between, and is unlocked between batches.
+    pub fn sweep_expired(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+        now: Timestamp,
+    ) -> Result<usize, Error> {
+        // Index keys sort by expiration, and a key expiring at `now` sorts
+        // after the index key for `now` with an empty key.
+        let end = expiration_index_key(now + Duration::from_nanos(1), "");
+        let mut after = None::<ArcBytes<'static>>;
+        let mut removed = 0;
+        loop {
+            let mut state = Self::lock_for_write(key_value_state)?;
+            let mut expired = BTreeSet::new();
+            if after.is_none() {
+                // Keys that haven't been persisted aren't in the index yet.
+                // Dirty keys take precedence over keys being persisted.
+                let mut pending = BTreeMap::new();
+                for (full_key, entry) in state
+                    .keys_being_persisted
+                    .iter()
+                    .flat_map(|keys| keys.iter())
+                    .chain(&state.dirty_keys)
+                {
+                    pending.insert(full_key, entry);
+                }
+                expired.extend(
+                    pending
+                        .into_iter()
+                        .filter(|(_, entry)| {
+                            entry.as_ref().map_or(false, |entry| entry.is_expired(now))
+                        })
+                        .map(|(full_key, _)| full_key.clone()),
+                );
             }
-            Command::Get { delete } => {
-                self.execute_get_operation(op.namespace.as_deref(), &op.key, delete)
+
+            let mut index_keys = Vec::new();
+            let start = after.as_deref().map_or(Bound::Unbounded, Bound::Excluded);
+            state
+                .roots
+                .tree(Unversioned::tree(EXPIRATION_TREE))?
+                .scan::<Infallible, _, _, _, _>(
+                    &(start, Bound::Excluded(end.as_slice())),
+                    true,
+                    |_, _, _| ScanEvaluation::ReadData,
+                    |index_key, _| {
+                        if index_keys.len() < SWEEP_EXPIRED_BATCH_SIZE {
+                            index_keys.push(index_key.clone());
+                            ScanEvaluation::Skip
+                        } else {
+                            ScanEvaluation::Stop
+                        }
+                    },
+                    |_, _, _| unreachable!(),
+                )?;
+            for index_key in &index_keys {
+                if let Some((expiration, full_key)) = parse_expiration_index_key(index_key) {
+                    // A key with unpersisted changes may no longer expire, or
+                    // may have already been removed.
+                    let pending = state.dirty_keys.get(full_key).or_else(|| {
+                        state
+                            .keys_being_persisted
+                            .as_ref()
+                            .and_then(|keys| keys.get(full_key))
+                    });
+                    let is_expired = match pending {
+                        Some(entry) => entry.as_ref().map_or(false, |entry| entry.is_expired(now)),
+                        None => expiration <= now,
+                    };
+                    if is_expired {
+                        expired.insert(full_key.to_string());
+                    }
+                }
             }
-            Command::Delete => self.execute_delete_operation(op.namespace.as_deref(), &op.key),
-            Command::Increment { amount, saturating } => self.execute_increment_operation(
-                op.namespace.as_deref(),
-                &op.key,
-                &amount,
-                saturating,
-                now,
-            ),
-            Command::Decrement { amount, saturating } => self.execute_decrement_operation(
-                op.namespace.as_deref(),
-                &op.key,
-                &amount,
-                saturating,
-                now,
-            ),
-        };
-        if result.is_ok() {
-            if self.needs_commit(now) {
-                self.commit_dirty_keys(state);
+
+            removed += expired.len();
+            for full_key in expired {
+                state.update_key_expiration(full_key.as_str(), None);
+                state.mark_dirty(full_key, None);
             }
-            self.update_background_worker_target();
+            if state.needs_commit(now) {
+                state.commit_dirty_keys(key_value_state);
+            }
+            state.update_background_worker_target();
+            drop(state);
+
+            if index_keys.len() < SWEEP_EXPIRED_BATCH_SIZE {
+                return Ok(removed);
+            }
+            after = index_keys.pop();
+        }
+    }
+
+    /// Removes every entry from the key-value trees and forgets all pending
+    /// changes and expirations. The trees are cleared in a single transaction,
+    /// so either every key is removed or none are. If keys are currently being
+    /// persisted, this function waits for them to be written before clearing
+    /// the trees.
+    pub fn clear_all(key_value_state: &Arc<Mutex<KeyValueState>>) -> Result<(), Error> {
+        let mut state = Self::lock_when_not_persisting(key_value_state)?;
+
+        let mut trees = vec![
+            Unversioned::tree(KEY_TREE),
+            Unversioned::tree(EXPIRATION_TREE),
+            Unversioned::tree(BLOB_TREE),
+        ];
+        if state.persistence.uses_operation_log() {
+            trees.push(Unversioned::tree(OPERATION_LOG_TREE));
+        }
+        let mut transaction = state.roots.transaction(&trees)?;
+        for index in 0..trees.len() {
+            // The expiration index remains built, as it is consistent with the
+            // empty key tree.
+            let retained = (index == 1).then_some(EXPIRATION_INDEX_BUILT);
+            let mut tree = transaction.tree::<Unversioned>(index).unwrap();
+            let mut keys = Vec::new();
+            tree.scan::<Infallible, _, _, _, _>(
+                &(..),
+                true,
+                |_, _, _| ScanEvaluation::ReadData,
+                |key, _| {
+                    if retained != Some(&key[..]) {
+                        keys.push(key.clone());
+                    }
+                    ScanEvaluation::Skip
+                },
+                |_, _, _| unreachable!(),
+            )?;
+            if !keys.is_empty() {
+                tree.modify(keys, Operation::Remove)?;
+            }
+        }
+        transaction.commit()?;
+
+        if let Some(namespaces) = &mut state.namespaces_in_use {
+            namespaces.clear();
+        }
+        if let Some(wal) = &mut state.write_ahead_log {
+            wal.clear()?;
+        }
+        state.dirty_keys.clear();
+        state.dirty_namespaces.clear();
+        state.expiring_keys.clear();
+        state.expiration_order.clear();
+        state.update_background_worker_target();
+        Ok(())
+    }
+
+    /// Writes `entries` directly to the key-value tree in batches of
+    /// [`BULK_LOAD_BATCH_SIZE`], then rebuilds the expiration tracking once.
+    /// Replicas aren't sent the loaded changes.
+    pub fn bulk_load<I>(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+        entries: I,
+    ) -> Result<usize, Error>
+    where
+        I: IntoIterator<Item = (String, Entry)>,
+    {
+        let mut state = Self::lock_when_not_persisting(key_value_state)?;
+        let mut loaded = 0;
+        let mut batch = BTreeMap::new();
+        // The final expiration of each loaded key that either expires or
+        // previously expired.
+        let mut expirations = HashMap::new();
+        for (full_key, entry) in entries {
+            if entry.expiration.is_some()
+                || state.expiring_keys.contains_key(&full_key)
+                || expirations.contains_key(&full_key)
+            {
+                expirations.insert(full_key.clone(), entry.expiration);
+            }
+            batch.insert(full_key, Some(entry));
+            loaded += 1;
+            if batch.len() >= BULK_LOAD_BATCH_SIZE {
+                commit_keys(
+                    &state.roots,
+                    &std::mem::take(&mut batch),
+                    &state.persistence,
+                )?;
+            }
+        }
+        if !batch.is_empty() {
+            commit_keys(&state.roots, &batch, &state.persistence)?;
+        }
+
+        if !expirations.is_empty() {
+            for (full_key, expiration) in expirations {
+                if let Some(expiration) = expiration {
+                    state.expiring_keys.insert(full_key, expiration);
+                } else {
+                    state.expiring_keys.remove(&full_key);
+                }
+            }
+            state.rebuild_expiration_order();
+            state.update_background_worker_target();
+        }
+        Ok(loaded)
+    }
+
+    /// Moves every key in namespace `from` to namespace `to`, returning the
+    /// number of keys moved. The changes are committed immediately, which
+    /// writes every moved key in a single transaction. If any key in `from`
+    /// already exists in `to`, no keys are moved.
+    pub fn rename_namespace(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        let from_prefix = full_key(from, "");
+        let to_prefix = full_key(to, "");
+        if from_prefix == to_prefix {
+            return Ok(0);
+        }
+
+        let mut state = Self::lock_when_not_persisting(key_value_state)?;
+        let now = Timestamp::now();
+        state.remove_expired_keys(now);
+        let mut moved = Vec::new();
+        for old_key in state.keys_with_prefix(&from_prefix)? {
+            if let Some(entry) = state.get_unexpired(&old_key, now)? {
+                let key = &old_key[from_prefix.len()..];
+                let new_key = format!("{to_prefix}{key}");
+                if state.get_unexpired(&new_key, now)?.is_some() {
+                    return Err(bonsaidb_core::Error::KeyValueNamespaceConflict {
+                        namespace: to.unwrap_or_default().to_string(),
+                        key: key.to_string(),
+                    });
+                }
+                moved.push((old_key, new_key, entry));
+            }
+        }
+        if moved.is_empty() {
+            return Ok(0);
+        }
+        if let Some(to) = to {
+            state.check_new_namespace(to)?;
+        }
+
+        let mut expirations_changed = false;
+        let moved_keys = moved.len() as u64;
+        for (old_key, new_key, entry) in moved {
+            expirations_changed |= state.expiring_keys.remove(&old_key).is_some();
+            if let Some(expiration) = entry.expiration {
+                state.expiring_keys.insert(new_key.clone(), expiration);
+                expirations_changed = true;
+            }
+            state.mark_dirty(old_key, None);
+            state.mark_dirty(new_key, Some(entry));
+        }
+        if expirations_changed {
+            state.rebuild_expiration_order();
+        }
+        state.flush_write_ahead_log()?;
+        state.commit_dirty_keys(key_value_state);
+        state.update_background_worker_target();
+        Ok(moved_keys)
+    }
+
+    /// Restores the keys of `namespace` as they were at `timestamp` into
+    /// `target` by replaying [`OPERATION_LOG_TREE`], returning the number of
+    /// keys restored. Any other keys in `target` are removed. The changes are
+    /// committed immediately, which writes every key in a single transaction.
+    ///
+    /// Restored keys are written without an expiration, bypassing any default
+    /// expiration configured for `target`.
+    pub fn restore_namespace(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        let mut state = Self::lock_when_not_persisting(key_value_state)?;
+        if !state.persistence.uses_operation_log() {
+            return Err(bonsaidb_core::Error::other(
+                "bonsaidb-local",
+                "the key-value operation log is not enabled",
+            ));
+        }
+        let restored = replay_operation_log(&state.roots, namespace, timestamp)?;
+
+        let now = Timestamp::now();
+        state.remove_expired_keys(now);
+        let target_prefix = full_key(target, "");
+        let mut changes = Vec::new();
+        for full_key in state.keys_with_prefix(&target_prefix)? {
+            let key = &full_key[target_prefix.len()..];
+            if !restored.contains_key(key) && state.get_unexpired(&full_key, now)?.is_some() {
+                changes.push((full_key, None));
+            }
+        }
+        match target {
+            Some(target) if !restored.is_empty() => state.check_new_namespace(target)?,
+            _ => {}
+        }
+
+        let restored_keys = restored.len() as u64;
+        changes.extend(restored.into_iter().map(|(key, entry)| {
+            let entry = Entry {
+                value: entry.value,
+                expiration: None,
+                last_updated: now,
+            };
+            (format!("{target_prefix}{key}"), Some(entry))
+        }));
+        if changes.is_empty() {
+            return Ok(0);
+        }
+
+        let mut expirations_changed = false;
+        for (full_key, entry) in changes {
+            expirations_changed |= state.expiring_keys.remove(&full_key).is_some();
+            state.mark_dirty(full_key, entry);
+        }
+        if expirations_changed {
+            state.rebuild_expiration_order();
+        }
+        state.flush_write_ahead_log()?;
+        state.commit_dirty_keys(key_value_state);
+        state.update_background_worker_target();
+        Ok(restored_keys)
+    }
+
+    /// Builds the expiration index from the persisted entries, unless it has
+    /// already been built. Databases created before the index existed don't
+    /// have one.
+    pub fn build_expiration_index(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+    ) -> Result<(), Error> {
+        let state = Self::lock_when_not_persisting(key_value_state)?;
+        let index = state.roots.tree(Unversioned::tree(EXPIRATION_TREE))?;
+        if index.get(EXPIRATION_INDEX_BUILT)?.is_some() {
+            return Ok(());
+        }
+
+        let mut index_keys = vec![ArcBytes::from(EXPIRATION_INDEX_BUILT.to_vec())];
+        for (full_key, entry) in state
+            .roots
+            .tree(Unversioned::tree(KEY_TREE))?
+            .get_range(&(..))?
+        {
+            // Corrupt entries are handled according to the configured policy
+            // when the expiration loader reads all entries.
+            let expiration = StoredEntry::deserialize(&entry)
+                .ok()
+                .and_then(|entry| entry.expiration());
+            if let (Ok(full_key), Some(expiration)) = (std::str::from_utf8(&full_key), expiration) {
+                index_keys.push(ArcBytes::from(expiration_index_key(expiration, full_key)));
+            }
+        }
+        index_keys.sort();
+        index.modify(index_keys, Operation::Set(ArcBytes::from(Vec::new())))?;
+        Ok(())
+    }
+
+    /// Locks the key-value state once no keys are being persisted. If keys are
+    /// currently being persisted, this function waits for them to be written.
+    fn lock_when_not_persisting(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+    ) -> Result<MutexGuard<'_, KeyValueState>, Error> {
+        loop {
+            let state = key_value_state.lock();
+            if state.keys_being_persisted.is_none() {
+                return Ok(state);
+            }
+
+            let mut persistence_watcher = state.last_persistence.watch();
+            drop(state);
+            persistence_watcher
+                .watch()
+                .map_err(|_| Error::InternalCommunication)?;
+        }
+    }
+
+    /// Locks the key-value state for an operation that modifies keys. If the
+    /// persistence's maximum number of dirty keys has been reached, this
+    /// function starts a commit or waits for the in-progress commit to
+    /// complete before returning the lock.
+    pub fn lock_for_write(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+    ) -> Result<MutexGuard<'_, KeyValueState>, Error> {
+        loop {
+            let mut state = key_value_state.lock();
+            let max_dirty_keys = state.persistence.max_dirty_keys().unwrap_or(usize::MAX);
+            if state.dirty_keys.len() < max_dirty_keys {
+                return Ok(state);
+            } else if state.keys_being_persisted.is_none() {
+                state.commit_dirty_keys(key_value_state);
+                state.update_background_worker_target();
+                return Ok(state);
+            }
+
+            let mut persistence_watcher = state.last_persistence.watch();
+            drop(state);
+            persistence_watcher
+                .watch()
+                .map_err(|_| Error::InternalCommunication)?;
+        }
+    }
+
+    fn needs_commit(&mut self, now: Timestamp) -> bool {
+        if self.keys_being_persisted.is_some() {
+            false
+        } else {
+            self.duration_until_next_commit(now) == Some(Duration::ZERO)
+        }
+    }
+
+    fn stage_dirty_keys(&mut self) -> Option<Arc<BTreeMap<String, Option<Entry>>>> {
+        if !self.dirty_keys.is_empty() && self.keys_being_persisted.is_none() {
+            let keys = Arc::new(std::mem::take(&mut self.dirty_keys));
+            self.dirty_namespaces.clear();
+            self.keys_being_persisted = Some(keys.clone());
+            self.wal_segment_being_persisted =
+                self.write_ahead_log.as_mut().and_then(WriteAheadLog::seal);
+            Some(keys)
+        } else {
+            None
+        }
+    }
+
+    pub fn commit_dirty_keys(&mut self, state: &Arc<Mutex<KeyValueState>>) -> bool {
+        if let Some(keys) = self.stage_dirty_keys() {
+            let roots = self.roots.clone();
+            let persistence = self.persistence.clone();
+            let slow_operation_log = self.slow_operation_log;
+            let state = state.clone();
+            // Events emitted while persisting are sent to the subscriber of
+            // the thread that committed the keys.
+            #[cfg(feature = "instrument")]
+            let dispatch = tracing::dispatcher::get_default(Clone::clone);
+            std::thread::Builder::new()
+                .name(String::from("keyvalue-persist"))
+                .spawn(move || {
+                    #[cfg(feature = "instrument")]
+                    let _dispatch = tracing::dispatcher::set_default(&dispatch);
+                    let _timer = slow_operation_log.start("persist_keys");
+                    Self::persist_keys(&state, &roots, &keys, &persistence)
+                })
+                .unwrap();
+            self.last_commit = Timestamp::now();
+            true
+        } else {
+            false
         }
-        result
     }
 
-    #[cfg_attr(
-        feature = "tracing",
-        tracing::instrument(level = "trace", skip(self, set, now),)
-    )]
-    fn execute_set_operation(
-        &mut self,
-        namespace: Option<&str>,
-        key: &str,
-        set: SetCommand,
-        now: Timestamp,
-    ) -> Result<Output, bonsaidb_core::Error> {
-        let mut entry = Entry {
-            value: set.value.validate()?,
-            expiration: set.expiration,
-            last_updated: now,
-        };
-        let full_key = full_key(namespace, key);
-        let possible_existing_value =
-            if set.check.is_some() || set.return_previous_value || set.keep_existing_expiration {
-                Some(self.get(&full_key).map_err(Error::from)?)
+    #[cfg(test)]
+    pub fn persistence_watcher(&self) -> Watcher<Timestamp> {
+        self.last_persistence.watch()
+    }
+
+    #[cfg_attr(feature = "instrument", tracing::instrument(level = "trace", skip_all))]
+    fn persist_keys(
+        key_value_state: &Arc<Mutex<KeyValueState>>,
+        roots: &Roots<AnyFile>,
+        keys: &BTreeMap<String, Option<Entry>>,
+        persistence: &KeyValuePersistence,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let replicated_keys = commit_keys(roots, keys, persistence)?;
+
+        // If we are shutting down, check if we still have dirty keys.
+        let (removed_segment, final_keys) = {
+            let mut state = key_value_state.lock();
+            if !replicated_keys.is_empty() && !state.replicas.is_empty() {
+                state.replicas.retain(|replica| !replica.sink.is_closed());
+                let replicated_keys = Arc::new(replicated_keys);
+                for replica in &state.replicas {
+                    replica.send(&replicated_keys);
+                }
+            }
+            let removed_segment = match state.wal_segment_being_persisted.take() {
+                Some(segment) => wal::remove_segment(&segment),
+                None => Ok(()),
+            };
+            state.last_persistence.replace(Timestamp::now());
+            state.keys_being_persisted = None;
+            state.update_background_worker_target();
+            // This block is a little ugly to avoid having to acquire the lock
+            // twice. If we're shutting down and have no dirty keys, we notify
+            // the waiting shutdown task. If we have any dirty keys, we wait do
+            // to that step because we're going to recurse and reach this spot
+            // again.
+            let final_keys = if state.shutdown.is_some() {
+                let staged_keys = state.stage_dirty_keys();
+                if staged_keys.is_none() {
+                    let shutdown = state.shutdown.take().unwrap();
+                    let _: Result<_, _> = shutdown.send(());
+                }
+                staged_keys
             } else {
                 None
             };
-        let existing_value_ref = possible_existing_value.as_ref().and_then(Option::as_ref);
-
-        let updating = match set.check {
-            Some(KeyCheck::OnlyIfPresent) => existing_value_ref.is_some(),
-            Some(KeyCheck::OnlyIfVacant) => existing_value_ref.is_none(),
-            None => true,
+            (removed_segment, final_keys)
         };
-        if updating {
-            if set.keep_existing_expiration {
-                if let Some(existing_value) = existing_value_ref {
-                    entry.expiration = existing_value.expiration;
-                }
+        if let Some(final_keys) = final_keys {
+            Self::persist_keys(key_value_state, roots, &final_keys, persistence)?;
+        }
+        Ok(removed_segment?)
+    }
+}
+
+/// Commits `keys` to the key-value tree, returning the changes that should be
+/// sent to replicas. If `persistence` has blob storage configured, large
+/// values are stored in [`BLOB_TREE`].
+fn commit_keys(
+    roots: &Roots<AnyFile>,
+    keys: &BTreeMap<String, Option<Entry>>,
+    persistence: &KeyValuePersistence,
+) -> Result<Vec<ReplicatedKey>, bonsaidb_core::Error> {
+    let mut trees = vec![
+        Unversioned::tree(KEY_TREE),
+        Unversioned::tree(EXPIRATION_TREE),
+        Unversioned::tree(BLOB_TREE),
+    ];
+    if persistence.uses_operation_log() {
+        trees.push(Unversioned::tree(OPERATION_LOG_TREE));
+    }
+    trees.push(Unversioned::tree(EXECUTED_TRANSACTIONS_TREE));
+    let executed_transactions_index = trees.len() - 1;
+    let mut transaction = roots.transaction(&trees).map_err(Error::from)?;
+
+    // The hash of each value that will be stored in the blob store, and the
+    // value of each of those blobs. A value whose hash matches a blob with
+    // different bytes is stored inline instead.
+    let mut blob_hashes = HashMap::new();
+    let mut blob_values = HashMap::<Vec<u8>, &Bytes>::new();
+    if let Some(blob_storage) = persistence.blob_storage() {
+        let blobs = transaction.tree::<Unversioned>(2).unwrap();
+        for (full_key, entry) in keys {
+            let Some(Entry { value: Value::Bytes(value), .. }) = entry else { continue };
+            if value.len() < blob_storage.minimum_size {
+                continue;
             }
-            self.update_key_expiration(&full_key, entry.expiration);
 
-            let previous_value = if let Some(existing_value) = possible_existing_value {
-                // we already fetched, no need to ask for the existing value back
-                self.set(full_key, entry);
-                existing_value
+            let hash = blob_storage.hasher.hash(&value[..]);
+            let matches = if let Some(existing) = blob_values.get(&hash) {
+                *existing == value
+            } else if let Some(stored) = blobs.get(&hash).map_err(Error::from)? {
+                bincode::deserialize::<Blob>(&stored).map_or(false, |blob| blob.value == *value)
             } else {
-                self.replace(full_key, entry).map_err(Error::from)?
+                true
             };
-            if set.return_previous_value {
-                Ok(Output::Value(previous_value.map(|entry| entry.value)))
-            } else if previous_value.is_none() {
-                Ok(Output::Status(KeyStatus::Inserted))
-            } else {
-                Ok(Output::Status(KeyStatus::Updated))
+            if matches {
+                blob_values.entry(hash.clone()).or_insert(value);
+                blob_hashes.insert(full_key.as_str(), hash);
             }
-        } else {
-            Ok(Output::Status(KeyStatus::NotChanged))
         }
     }
+    // The change in the number of references to each blob.
+    let mut blob_references = BTreeMap::<Vec<u8>, i64>::new();
+    // The serialized changes to append to the operation log.
+    let committed_at = Timestamp::now();
+    let mut logged_changes = BTreeMap::new();
 
-    #[cfg_attr(
-        feature = "tracing",
-        tracing::instrument(level = "trace", skip(self, tree_key, expiration))
-    )]
-    pub fn update_key_expiration<'key>(
-        &mut self,
-        tree_key: impl Into<Cow<'key, str>>,
-        expiration: Option<Timestamp>,
-    ) {
-        let tree_key = tree_key.into();
-        let mut changed_first_expiration = false;
-        if let Some(expiration) = expiration {
-            let key = if self.expiring_keys.contains_key(tree_key.as_ref()) {
-                // Update the existing entry.
-                let existing_entry_index = self
-                    .expiration_order
-                    .iter()
-                    .enumerate()
-                    .find_map(
-                        |(index, key)| {
-                            if &tree_key == key {
-                                Some(index)
-                            } else {
-                                None
-                            }
-                        },
-                    )
-                    .unwrap();
-                changed_first_expiration = existing_entry_index == 0;
-                self.expiration_order.remove(existing_entry_index).unwrap()
-            } else {
-                tree_key.into_owned()
-            };
+    let all_keys = keys
+        .keys()
+        .map(|key| ArcBytes::from(key.as_bytes().to_vec()))
+        .collect();
+    let mut changed_keys = Vec::new();
+    let mut replicated_keys = Vec::new();
+    // Index keys to insert (true) or remove (false) from the expiration
+    // index.
+    let mut index_changes = BTreeMap::new();
+    transaction
+        .tree::<Unversioned>(0)
+        .unwrap()
+        .modify(
+            all_keys,
+            Operation::CompareSwap(CompareSwap::new(&mut |key, existing_value| {
+                let full_key = std::str::from_utf8(key).unwrap();
+                let (namespace, key) = split_key(full_key).unwrap();
 
-            // Insert the key into the expiration_order queue
-            let mut insert_at = None;
-            for (index, expiring_key) in self.expiration_order.iter().enumerate() {
-                if self.expiring_keys.get(expiring_key).unwrap() > &expiration {
-                    insert_at = Some(index);
-                    break;
+                let existing = existing_value
+                    .as_ref()
+                    .and_then(|existing| StoredEntry::deserialize(existing).ok());
+                if let Some(hash) = existing.as_ref().and_then(StoredEntry::blob_hash) {
+                    *blob_references.entry(hash.to_vec()).or_default() -= 1;
+                }
+                let existing_expiration = existing.as_ref().and_then(StoredEntry::expiration);
+                let new_expiration = keys
+                    .get(full_key)
+                    .unwrap()
+                    .as_ref()
+                    .and_then(|new_value| new_value.expiration);
+                if existing_expiration != new_expiration {
+                    if let Some(expiration) = existing_expiration {
+                        index_changes.insert(expiration_index_key(expiration, full_key), false);
+                    }
+                    if let Some(expiration) = new_expiration {
+                        index_changes.insert(expiration_index_key(expiration, full_key), true);
+                    }
                 }
-            }
-            if let Some(insert_at) = insert_at {
-                changed_first_expiration |= insert_at == 0;
 
-                self.expiration_order.insert(insert_at, key.clone());
-            } else {
-                changed_first_expiration |= self.expiration_order.is_empty();
-                self.expiration_order.push_back(key.clone());
-            }
-            self.expiring_keys.insert(key, expiration);
-        } else if self.expiring_keys.remove(tree_key.as_ref()).is_some() {
-            let index = self
-                .expiration_order
-                .iter()
-                .enumerate()
-                .find_map(|(index, key)| {
-                    if tree_key.as_ref() == key {
-                        Some(index)
+                if persistence.uses_operation_log()
+                    && (existing_value.is_some() || keys[full_key].is_some())
+                {
+                    logged_changes.insert(
+                        operation_log_key(committed_at, full_key),
+                        bincode::serialize(&keys[full_key]).unwrap(),
+                    );
+                }
+
+                if let Some(new_value) = keys.get(full_key).unwrap() {
+                    replicated_keys.push(ReplicatedKey {
+                        namespace: namespace.clone(),
+                        key: key.clone(),
+                        value: Some(new_value.value.clone()),
+                        expiration: new_value.expiration,
+                    });
+                    changed_keys.push(ChangedKey {
+                        namespace,
+                        key,
+                        deleted: false,
+                    });
+                    let bytes = if let Some(hash) = blob_hashes.get(full_key) {
+                        *blob_references.entry(hash.clone()).or_default() += 1;
+                        serialize_blob_reference(hash, new_value)
                     } else {
-                        None
+                        serialize_entry(new_value, persistence.entry_encoding())
+                    };
+                    nebari::tree::KeyOperation::Set(ArcBytes::from(bytes))
+                } else if existing_value.is_some() {
+                    replicated_keys.push(ReplicatedKey {
+                        namespace: namespace.clone(),
+                        key: key.clone(),
+                        value: None,
+                        expiration: None,
+                    });
+                    changed_keys.push(ChangedKey {
+                        namespace,
+                        key,
+                        deleted: existing_value.is_some(),
+                    });
+                    nebari::tree::KeyOperation::Remove
+                } else {
+                    nebari::tree::KeyOperation::Skip
+                }
+            })),
+        )
+        .map_err(Error::from)?;
+
+    if !index_changes.is_empty() {
+        let index_keys = index_changes
+            .keys()
+            .map(|key| ArcBytes::from(key.clone()))
+            .collect();
+        transaction
+            .tree::<Unversioned>(1)
+            .unwrap()
+            .modify(
+                index_keys,
+                Operation::CompareSwap(CompareSwap::new(&mut |key, _| {
+                    if index_changes[&key[..]] {
+                        nebari::tree::KeyOperation::Set(ArcBytes::from(Vec::new()))
+                    } else {
+                        nebari::tree::KeyOperation::Remove
                     }
-                })
-                .unwrap();
+                })),
+            )
+            .map_err(Error::from)?;
+    }
 
-            changed_first_expiration |= index == 0;
-            self.expiration_order.remove(index);
+    blob_references.retain(|_, change| *change != 0);
+    if !blob_references.is_empty() {
+        let blob_keys = blob_references
+            .keys()
+            .map(|hash| ArcBytes::from(hash.clone()))
+            .collect();
+        transaction
+            .tree::<Unversioned>(2)
+            .unwrap()
+            .modify(
+                blob_keys,
+                Operation::CompareSwap(CompareSwap::new(&mut |hash, existing_value| {
+                    let existing = existing_value
+                        .and_then(|existing| bincode::deserialize::<Blob>(&existing).ok());
+                    let new_blob = || {
+                        blob_values.get(&hash[..]).map(|value| Blob {
+                            references: 0,
+                            value: (*value).clone(),
+                        })
+                    };
+                    let Some(mut blob) = existing.or_else(new_blob) else {
+                        return nebari::tree::KeyOperation::Skip;
+                    };
+                    let references = i64::try_from(blob.references).unwrap_or(i64::MAX)
+                        + blob_references[&hash[..]];
+                    match u64::try_from(references) {
+                        Ok(references) if references > 0 => {
+                            blob.references = references;
+                            nebari::tree::KeyOperation::Set(ArcBytes::from(
+                                bincode::serialize(&blob).unwrap(),
+                            ))
+                        }
+                        _ => nebari::tree::KeyOperation::Remove,
+                    }
+                })),
+            )
+            .map_err(Error::from)?;
+    }
+
+    if !logged_changes.is_empty() {
+        let log_keys = logged_changes
+            .keys()
+            .map(|key| ArcBytes::from(key.clone()))
+            .collect();
+        transaction
+            .tree::<Unversioned>(3)
+            .unwrap()
+            .modify(
+                log_keys,
+                Operation::CompareSwap(CompareSwap::new(&mut |key, _| {
+                    nebari::tree::KeyOperation::Set(ArcBytes::from(
+                        logged_changes[&key[..]].clone(),
+                    ))
+                })),
+            )
+            .map_err(Error::from)?;
+    }
+
+    if !changed_keys.is_empty() {
+        let transaction_id = transaction.entry().id;
+        transaction_log::record(
+            &mut transaction
+                .tree::<Unversioned>(executed_transactions_index)
+                .unwrap(),
+            transaction_id,
+            Timestamp::now(),
+            compat::serialize_executed_transaction_changes(&Changes::Keys(changed_keys))?,
+        )?;
+        transaction.commit().map_err(Error::from)?;
+    }
+
+    Ok(replicated_keys)
+}
+
+/// Commits any changes left in the key-value write-ahead log by a previous
+/// process to the key-value tree. This must be called before the database's
+/// key-value store is used.
+pub(crate) fn replay_write_ahead_log(
+    roots: &Roots<AnyFile>,
+    persistence: &KeyValuePersistence,
+) -> Result<(), Error> {
+    let (changes, segments) = wal::read_segments(roots.path())?;
+    if !changes.is_empty() {
+        commit_keys(roots, &changes, persistence)?;
+    }
+    for segment in segments {
+        std::fs::remove_file(segment)?;
+    }
+    Ok(())
+}
+
+pub fn background_worker(
+    key_value_state: &Weak<Mutex<KeyValueState>>,
+    timestamp_receiver: &mut Watcher<BackgroundWorkerProcessTarget>,
+    storage_lock: Option<StorageLock>,
+) {
+    loop {
+        let mut perform_operations = false;
+        let current_target = *timestamp_receiver.read();
+        match current_target {
+            // With no target, sleep until we receive a target.
+            BackgroundWorkerProcessTarget::Never => {
+                if timestamp_receiver.watch().is_err() {
+                    break;
+                }
+            }
+            BackgroundWorkerProcessTarget::Timestamp(target) => {
+                // With a target, we need to wait to receive a target only as
+                // long as there is time remaining.
+                let remaining = target - Timestamp::now();
+                if let Some(remaining) = remaining {
+                    // recv_timeout panics if Instant::checked_add(remaining)
+                    // fails. So, we will cap the sleep time at 1 day.
+                    let remaining = remaining.min(Duration::from_secs(60 * 60 * 24));
+                    match timestamp_receiver.watch_timeout(remaining) {
+                        Ok(_) | Err(watchable::TimeoutError::Timeout) => {
+                            perform_operations = true;
+                        }
+                        Err(watchable::TimeoutError::Disconnected) => break,
+                    }
+                } else {
+                    perform_operations = true;
+                }
+            }
+            BackgroundWorkerProcessTarget::Now => {
+                perform_operations = true;
+            }
+        };
+
+        let Some(key_value_state) = key_value_state.upgrade() else { break };
+
+        if perform_operations {
+            key_value_state
+                .lock()
+                .perform_background_operations(&key_value_state);
         }
+    }
+
+    // The key-value store's delayed persistence can cause the key-value storage
+    // to be written past when the last reference to the storage is still held.
+    // The storage lock being held ensures that another reader/writer doesn't
+    // begin accessing this same storage again.
+    drop(storage_lock);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BackgroundWorkerProcessTarget {
+    Now,
+    Timestamp(Timestamp),
+    Never,
+}
+
+#[derive(Debug)]
+pub struct ExpirationLoader {
+    pub database: Database,
+    pub launched_at: Timestamp,
+}
 
-        if changed_first_expiration {
-            self.update_background_worker_target();
-        }
+impl Keyed<Task> for ExpirationLoader {
+    fn key(&self) -> Task {
+        Task::ExpirationLoader(self.database.data.name.clone())
     }
+}
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
-    fn execute_get_operation(
-        &mut self,
-        namespace: Option<&str>,
-        key: &str,
-        delete: bool,
-    ) -> Result<Output, bonsaidb_core::Error> {
-        let full_key = full_key(namespace, key);
-        let entry = if delete {
-            self.remove(full_key).map_err(Error::from)?
-        } else {
-            self.get(&full_key).map_err(Error::from)?
-        };
+impl Job for ExpirationLoader {
+    type Error = Error;
+    type Output = ();
 
-        Ok(Output::Value(entry.map(|e| e.value)))
-    }
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+    fn execute(&mut self) -> Result<Self::Output, Self::Error> {
+        let database = self.database.clone();
+        let launched_at = self.launched_at;
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
-    fn execute_delete_operation(
-        &mut self,
-        namespace: Option<&str>,
-        key: &str,
-    ) -> Result<Output, bonsaidb_core::Error> {
-        let full_key = full_key(namespace, key);
-        let value = self.remove(full_key).map_err(Error::from)?;
-        if value.is_some() {
-            Ok(Output::Status(KeyStatus::Deleted))
-        } else {
-            Ok(Output::Status(KeyStatus::NotChanged))
+        KeyValueState::build_expiration_index(&database.data.context.key_value_state)?;
+
+        for ((namespace, key), entry) in database.all_key_value_entries()? {
+            if entry.last_updated < launched_at && entry.expiration.is_some() {
+                self.database
+                    .update_key_expiration(full_key(namespace.as_deref(), &key), entry.expiration);
+            }
         }
-    }
 
-    #[cfg_attr(
-        feature = "tracing",
-        tracing::instrument(level = "trace", skip(self, amount, saturating, now))
-    )]
-    fn execute_increment_operation(
-        &mut self,
-        namespace: Option<&str>,
-        key: &str,
-        amount: &Numeric,
-        saturating: bool,
-        now: Timestamp,
-    ) -> Result<Output, bonsaidb_core::Error> {
-        self.execute_numeric_operation(namespace, key, amount, saturating, now, increment)
-    }
+        self.database
+            .storage()
+            .instance
+            .tasks()
+            .mark_key_value_expiration_loaded(self.database.data.name.clone());
 
-    #[cfg_attr(
-        feature = "tracing",
-        tracing::instrument(level = "trace", skip(self, amount, saturating, now))
-    )]
-    fn execute_decrement_operation(
-        &mut self,
-        namespace: Option<&str>,
-        key: &str,
-        amount: &Numeric,
-        saturating: bool,
-        now: Timestamp,
-    ) -> Result<Output, bonsaidb_core::Error> {
-        self.execute_numeric_operation(namespace, key, amount, saturating, now, decrement)
+        Ok(())
     }
+}
 
-    fn execute_numeric_operation<F: Fn(&Numeric, &Numeric, bool) -> Numeric>(
-        &mut self,
-        namespace: Option<&str>,
-        key: &str,
-        amount: &Numeric,
-        saturating: bool,
-        now: Timestamp,
-        op: F,
-    ) -> Result<Output, bonsaidb_core::Error> {
-        let full_key = full_key(namespace, key);
-        let current = self.get(&full_key).map_err(Error::from)?;
-        let mut entry = current.unwrap_or(Entry {
-            value: Value::Numeric(Numeric::UnsignedInteger(0)),
-            expiration: None,
-            last_updated: now,
-        });
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
 
-        match entry.value {
-            Value::Numeric(existing) => {
-                let value = Value::Numeric(op(&existing, amount, saturating).validate()?);
-                entry.value = value.clone();
+    use bonsaidb_core::arc_bytes::serde::Bytes;
+    use bonsaidb_core::test_util::{TestDirectory, TimingTest};
+    use nebari::io::any::{AnyFile, AnyFileManager};
 
-                self.set(full_key, entry);
-                Ok(Output::Value(Some(value)))
-            }
-            Value::Bytes(_) => Err(bonsaidb_core::Error::other(
-                "bonsaidb-local",
-                "type of stored `Value` is not `Numeric`",
-            )),
-        }
+    use super::*;
+    use crate::config::{BlobHasher, KeyValueBlobStorage, PersistenceThreshold};
+    use crate::database::Context;
+
+    fn run_test_with_persistence<
+        F: Fn(Context, nebari::Roots<AnyFile>) -> anyhow::Result<()> + Send,
+    >(
+        name: &str,
+        persistence: KeyValuePersistence,
+        test_contents: &F,
+    ) -> anyhow::Result<()> {
+        let dir = TestDirectory::new(name);
+        let sled = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+
+        let context = Context::new(
+            sled.clone(),
+            persistence,
+            CorruptKeyValuePolicy::default(),
+            None,
+        );
+
+        test_contents(context, sled)?;
+
+        Ok(())
     }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
-    fn remove(&mut self, key: String) -> Result<Option<Entry>, nebari::Error> {
-        self.update_key_expiration(&key, None);
+    fn run_test<F: Fn(Context, nebari::Roots<AnyFile>) -> anyhow::Result<()> + Send>(
+        name: &str,
+        test_contents: F,
+    ) -> anyhow::Result<()> {
+        run_test_with_persistence(name, KeyValuePersistence::default(), &test_contents)
+    }
 
-        if let Some(dirty_entry) = self.dirty_keys.get_mut(&key) {
-            Ok(dirty_entry.take())
-        } else if let Some(persisting_entry) = self
-            .keys_being_persisted
-            .as_ref()
-            .and_then(|keys| keys.get(&key))
-        {
-            self.dirty_keys.insert(key, None);
-            Ok(persisting_entry.clone())
-        } else {
-            // There might be a value on-disk we need to remove.
-            let previous_value = Self::retrieve_key_from_disk(&self.roots, &key)?;
-            self.dirty_keys.insert(key, None);
-            Ok(previous_value)
+    #[test]
+    fn namespace_boundaries() {
+        // These pairs were encoded identically when the namespace and key were
+        // separated by a null byte.
+        let colliding = [
+            ((Some("a\0b"), "c"), (Some("a"), "b\0c")),
+            ((Some("a"), "\0b"), (Some("a\0"), "b")),
+        ];
+        for ((first_namespace, first_key), (second_namespace, second_key)) in colliding {
+            assert_ne!(
+                full_key(first_namespace, first_key),
+                full_key(second_namespace, second_key)
+            );
         }
-    }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
-    fn get(&self, key: &str) -> Result<Option<Entry>, nebari::Error> {
-        if let Some(entry) = self.dirty_keys.get(key) {
-            Ok(entry.clone())
-        } else if let Some(persisting_entry) = self
-            .keys_being_persisted
-            .as_ref()
-            .and_then(|keys| keys.get(key))
-        {
-            Ok(persisting_entry.clone())
-        } else {
-            Self::retrieve_key_from_disk(&self.roots, key)
+        let long_namespace = "namespace".repeat(100);
+        for (namespace, key) in [
+            (None, "a\0b"),
+            (Some("a\0b"), "c"),
+            (Some("a"), "b\0c"),
+            (Some("\u{1f333}"), "\u{1f333}"),
+            (Some(long_namespace.as_str()), "key"),
+        ] {
+            assert_eq!(
+                split_key(&full_key(namespace, key)),
+                Some((namespace.map(String::from), String::from(key)))
+            );
         }
+
+        // An empty namespace is the same as no namespace.
+        assert_eq!(full_key(Some(""), "key"), full_key(None, "key"));
     }
 
-    fn set(&mut self, key: String, value: Entry) {
-        self.dirty_keys.insert(key, Some(value));
+    #[test]
+    fn legacy_key_migration() -> anyhow::Result<()> {
+        let dir = TestDirectory::new("kv-legacy-key-migration");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let expiration = Timestamp::now() + Duration::from_secs(60);
+        let entry = bincode::serialize(&Entry {
+            value: Value::Numeric(Numeric::UnsignedInteger(1)),
+            expiration: Some(expiration),
+            last_updated: Timestamp::now(),
+        })?;
+        let keys = roots.tree(Unversioned::tree(KEY_TREE))?;
+        keys.set(b"atree\0akey", entry.clone())?;
+        keys.set(b"\0bkey", entry.clone())?;
+        let index = roots.tree(Unversioned::tree(EXPIRATION_TREE))?;
+        index.set(expiration_index_key(expiration, "atree\0akey"), b"")?;
+
+        migrate_key_encoding(&roots)?;
+
+        let akey = full_key(Some("atree"), "akey");
+        assert_eq!(keys.get(akey.as_bytes())?.as_deref(), Some(&entry[..]));
+        assert!(keys.get(b"atree\0akey")?.is_none());
+        // Keys without a namespace are encoded the same way in both formats.
+        assert!(keys.get(full_key(None, "bkey").as_bytes())?.is_some());
+        let index_keys = index.get_range(&(..))?;
+        assert_eq!(index_keys.len(), 1);
+        assert_eq!(
+            &index_keys[0].0[..],
+            &expiration_index_key(expiration, &akey)[..]
+        );
+
+        // Once migrated, existing keys are not migrated again.
+        keys.set(b"atree\0ckey", entry)?;
+        migrate_key_encoding(&roots)?;
+        assert!(keys.get(b"atree\0ckey")?.is_some());
+
+        let context = Context::new(
+            roots.clone(),
+            KeyValuePersistence::default(),
+            CorruptKeyValuePolicy::default(),
+            None,
+        );
+        let output = context.perform_kv_operation(KeyOperation {
+            namespace: Some(String::from("atree")),
+            key: String::from("akey"),
+            command: Command::Get {
+                delete: false,
+                metadata: false,
+                committed: false,
+            },
+        })?;
+        assert!(matches!(
+            output,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(1))))
+        ));
+
+        Ok(())
     }
 
-    fn replace(&mut self, key: String, value: Entry) -> Result<Option<Entry>, nebari::Error> {
-        let mut value = Some(value);
-        let map_entry = self.dirty_keys.entry(key);
-        if matches!(map_entry, btree_map::Entry::Vacant(_)) {
-            // This key is clean, and the caller is expecting the previous
-            // value.
-            let stored_value = if let Some(persisting_entry) = self
-                .keys_being_persisted
-                .as_ref()
-                .and_then(|keys| keys.get(map_entry.key()))
-            {
-                persisting_entry.clone()
-            } else {
-                Self::retrieve_key_from_disk(&self.roots, map_entry.key())?
-            };
-            map_entry.or_insert(value);
-            Ok(stored_value)
-        } else {
-            // This key is already dirty, we can just replace the value and
-            // return the old value.
-            map_entry.and_modify(|map_entry| {
-                std::mem::swap(&mut value, map_entry);
-            });
-            Ok(value)
+    #[test]
+    fn blob_deduplication() -> anyhow::Result<()> {
+        /// Hashes values by their length, causing values of the same length
+        /// to collide.
+        #[derive(Debug)]
+        struct LengthHasher;
+
+        impl BlobHasher for LengthHasher {
+            fn hash(&self, value: &[u8]) -> Vec<u8> {
+                value.len().to_be_bytes().to_vec()
+            }
         }
-    }
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(roots)))]
-    fn retrieve_key_from_disk(
-        roots: &Roots<AnyFile>,
-        key: &str,
-    ) -> Result<Option<Entry>, nebari::Error> {
-        roots
-            .tree(Unversioned::tree(KEY_TREE))?
-            .get(key.as_bytes())
-            .map(|current| current.and_then(|current| bincode::deserialize::<Entry>(&current).ok()))
+        fn entry(value: &[u8]) -> Option<Entry> {
+            Some(Entry {
+                value: Value::Bytes(Bytes::from(value.to_vec())),
+                expiration: None,
+                last_updated: Timestamp::now(),
+            })
+        }
+
+        fn get(roots: &nebari::Roots<AnyFile>, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+            let entry = KeyValueState::retrieve_key_from_disk(
+                roots,
+                CorruptKeyValuePolicy::Error,
+                &full_key(None, key),
+            )?;
+            Ok(entry.map(|entry| match entry.value {
+                Value::Bytes(bytes) => bytes.to_vec(),
+                Value::Numeric(_) | Value::Set(_) => unreachable!("only bytes are stored"),
+            }))
+        }
+
+        let dir = TestDirectory::new("kv-blob-deduplication");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let persistence = KeyValuePersistence::default()
+            .with_blob_storage(KeyValueBlobStorage::new(64).with_hasher(LengthHasher));
+        let blobs = roots.tree(Unversioned::tree(BLOB_TREE))?;
+        let large = vec![1_u8; 128];
+
+        commit_keys(
+            &roots,
+            &BTreeMap::from([
+                (full_key(None, "a"), entry(&large)),
+                (full_key(None, "b"), entry(&large)),
+                (full_key(None, "small"), entry(b"small")),
+            ]),
+            &persistence,
+        )?;
+        assert_eq!(blobs.get_range(&(..))?.len(), 1);
+        assert_eq!(get(&roots, "a")?, Some(large.clone()));
+        assert_eq!(get(&roots, "b")?, Some(large.clone()));
+        assert_eq!(get(&roots, "small")?, Some(b"small".to_vec()));
+
+        // A different value with a colliding hash is stored inline.
+        let colliding = vec![2_u8; 128];
+        commit_keys(
+            &roots,
+            &BTreeMap::from([(full_key(None, "c"), entry(&colliding))]),
+            &persistence,
+        )?;
+        assert_eq!(blobs.get_range(&(..))?.len(), 1);
+        assert_eq!(get(&roots, "c")?, Some(colliding.clone()));
+
+        // The blob is kept until the last key referencing it is removed.
+        commit_keys(
+            &roots,
+            &BTreeMap::from([(full_key(None, "a"), None)]),
+            &persistence,
+        )?;
+        assert_eq!(blobs.get_range(&(..))?.len(), 1);
+        assert_eq!(get(&roots, "b")?, Some(large));
+        // References are released even if blob storage is no longer
+        // configured.
+        commit_keys(
+            &roots,
+            &BTreeMap::from([(full_key(None, "b"), None)]),
+            &KeyValuePersistence::default(),
+        )?;
+        assert!(blobs.get_range(&(..))?.is_empty());
+        assert_eq!(get(&roots, "c")?, Some(colliding));
+
+        Ok(())
     }
 
-    fn update_background_worker_target(&mut self) {
-        let key_expiration_target = self.expiration_order.get(0).map(|key| {
-            let expiration_timeout = self.expiring_keys.get(key).unwrap();
-            *expiration_timeout
-        });
-        let now = Timestamp::now();
-        let persisting = self.keys_being_persisted.is_some();
-        let commit_target = (!persisting)
-            .then(|| {
-                self.persistence.duration_until_next_commit(
-                    self.dirty_keys.len(),
-                    (now - self.last_commit).unwrap_or_default(),
-                )
-            })
-            .flatten()
-            .map(|duration| now + duration);
-        match (commit_target, key_expiration_target) {
-            (Some(target), _) | (_, Some(target)) if target <= now => {
-                self.background_worker_target
-                    .replace(BackgroundWorkerProcessTarget::Now);
-            }
-            (Some(commit_target), Some(key_target)) => {
-                let closest_target = key_target.min(commit_target);
-                let new_target = BackgroundWorkerProcessTarget::Timestamp(closest_target);
-                let _: Result<_, _> = self.background_worker_target.update(new_target);
-            }
-            (Some(target), None) | (None, Some(target)) => {
-                let _: Result<_, _> = self
-                    .background_worker_target
-                    .update(BackgroundWorkerProcessTarget::Timestamp(target));
-            }
-            (None, None) => {
-                let _: Result<_, _> = self
-                    .background_worker_target
-                    .update(BackgroundWorkerProcessTarget::Never);
-            }
+    #[test]
+    fn restore_from_operation_log() -> anyhow::Result<()> {
+        use bonsaidb_core::connection::StorageConnection;
+
+        use crate::config::{Builder, StorageConfiguration};
+        use crate::Storage;
+
+        let dir = TestDirectory::new("kv-restore-from-operation-log");
+        let storage = Storage::open(
+            StorageConfiguration::new(&dir).key_value_persistence(
+                KeyValuePersistence::immediate()
+                    .with_operation_log()
+                    .with_namespace_default_expiration("restored", Duration::from_secs(3600)),
+            ),
+        )?;
+        let db = storage.create_database::<()>("restore", false)?;
+        let settings = db.with_key_namespace("settings");
+        let restored = db.with_key_namespace("restored");
+        let mut persistence_watcher = db.data.context.kv_persistence_watcher();
+        let tree = db.roots().tree(Unversioned::tree(KEY_TREE))?;
+        let limit_key = full_key(Some("settings"), "limit");
+        let other_key = full_key(Some("settings"), "other");
+
+        let before_changes = Timestamp::now();
+        settings.set_numeric_key("limit", 1_u64).execute()?;
+        while tree.get(limit_key.as_bytes())?.is_none() {
+            persistence_watcher.next_value()?;
+        }
+        let first = Timestamp::now();
+        settings.set_numeric_key("limit", 2_u64).execute()?;
+        settings.set_numeric_key("other", 3_u64).execute()?;
+        // Keys are committed in the order they were changed.
+        while tree.get(other_key.as_bytes())?.is_none() {
+            persistence_watcher.next_value()?;
+        }
+        let second = Timestamp::now();
+        settings.delete_key("limit")?;
+        while tree.get(limit_key.as_bytes())?.is_some() {
+            persistence_watcher.next_value()?;
         }
+
+        assert_eq!(db.restore_to(Some("settings"), first, Some("restored"))?, 1);
+        assert_eq!(restored.get_key("limit").into_u64()?, Some(1));
+        assert!(restored.get_key("other").query()?.is_none());
+        // Restored keys don't receive the namespace's default expiration.
+        assert_eq!(
+            db.execute_key_expirations(
+                Some(String::from("restored")),
+                vec![String::from("limit")]
+            )?,
+            vec![(String::from("limit"), None)]
+        );
+
+        assert_eq!(
+            db.restore_to(Some("settings"), second, Some("restored"))?,
+            2
+        );
+        assert_eq!(restored.get_key("limit").into_u64()?, Some(2));
+        assert_eq!(restored.get_key("other").into_u64()?, Some(3));
+
+        // Keys that didn't exist at the restored instant are removed.
+        assert_eq!(
+            db.restore_to(Some("settings"), before_changes, Some("restored"))?,
+            0
+        );
+        assert!(restored.get_key("limit").query()?.is_none());
+        assert!(restored.get_key("other").query()?.is_none());
+
+        // The current state is unaffected by restoring.
+        assert!(settings.get_key("limit").query()?.is_none());
+        assert_eq!(settings.get_key("other").into_u64()?, Some(3));
+
+        // Restoring a namespace into itself rolls it back.
+        assert_eq!(db.restore_to(Some("settings"), first, Some("settings"))?, 1);
+        assert_eq!(settings.get_key("limit").into_u64()?, Some(1));
+        assert!(settings.get_key("other").query()?.is_none());
+
+        Ok(())
     }
 
-    fn remove_expired_keys(&mut self, now: Timestamp) {
-        while !self.expiration_order.is_empty()
-            && self.expiring_keys.get(&self.expiration_order[0]).unwrap() <= &now
-        {
-            let key = self.expiration_order.pop_front().unwrap();
-            self.expiring_keys.remove(&key);
-            self.dirty_keys.insert(key, None);
+    #[test]
+    fn corrupt_entry_policies() -> anyhow::Result<()> {
+        fn get(context: &Context) -> Result<Output, bonsaidb_core::Error> {
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from("corrupt"),
+                command: Command::Get {
+                    delete: false,
+                    metadata: false,
+                    committed: false,
+                },
+            })
         }
+
+        let dir = TestDirectory::new("kv-corrupt-entry-policies");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+        tree.set(b"\0corrupt", b"garbage")?;
+
+        let context = Context::new(
+            roots.clone(),
+            KeyValuePersistence::default(),
+            CorruptKeyValuePolicy::Error,
+            None,
+        );
+        assert!(get(&context).is_err());
+        drop(context);
+
+        let context = Context::new(
+            roots.clone(),
+            KeyValuePersistence::default(),
+            CorruptKeyValuePolicy::SkipAndLog,
+            None,
+        );
+        assert!(matches!(get(&context)?, Output::Value(None)));
+        assert!(tree.get(b"\0corrupt")?.is_some());
+        drop(context);
+
+        let context = Context::new(
+            roots.clone(),
+            KeyValuePersistence::default(),
+            CorruptKeyValuePolicy::Quarantine,
+            None,
+        );
+        assert!(matches!(get(&context)?, Output::Value(None)));
+        assert!(tree.get(b"\0corrupt")?.is_none());
+        let quarantine = roots.tree(Unversioned::tree(QUARANTINE_TREE))?;
+        assert_eq!(
+            quarantine.get(b"\0corrupt")?.as_deref(),
+            Some(&b"garbage"[..])
+        );
+
+        Ok(())
     }
 
-    fn needs_commit(&mut self, now: Timestamp) -> bool {
-        if self.keys_being_persisted.is_some() {
-            false
-        } else {
-            let since_last_commit = (now - self.last_commit).unwrap_or_default();
-            self.persistence
-                .should_commit(self.dirty_keys.len(), since_last_commit)
+    #[test]
+    fn expired_entries_are_not_read() -> anyhow::Result<()> {
+        fn get(context: &Context, key: &str) -> Result<Output, bonsaidb_core::Error> {
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from(key),
+                command: Command::Get {
+                    delete: false,
+                    metadata: false,
+                    committed: false,
+                },
+            })
         }
-    }
 
-    fn stage_dirty_keys(&mut self) -> Option<Arc<BTreeMap<String, Option<Entry>>>> {
-        if !self.dirty_keys.is_empty() && self.keys_being_persisted.is_none() {
-            let keys = Arc::new(std::mem::take(&mut self.dirty_keys));
-            self.keys_being_persisted = Some(keys.clone());
-            Some(keys)
-        } else {
-            None
+        let dir = TestDirectory::new("kv-expired-entries-are-not-read");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        // Store entries directly, as if they were persisted before the
+        // database was opened. Their expirations are not known to the
+        // context, so they haven't been scheduled for removal.
+        let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+        for (key, expiration) in [
+            (
+                "expired",
+                Timestamp::from(std::time::SystemTime::now() - Duration::from_secs(1)),
+            ),
+            ("valid", Timestamp::now() + Duration::from_secs(60)),
+        ] {
+            tree.set(
+                full_key(None, key).into_bytes(),
+                bincode::serialize(&Entry {
+                    value: Value::Numeric(Numeric::UnsignedInteger(1)),
+                    expiration: Some(expiration),
+                    last_updated: Timestamp::now(),
+                })?,
+            )?;
         }
-    }
 
-    pub fn commit_dirty_keys(&mut self, state: &Arc<Mutex<KeyValueState>>) -> bool {
-        if let Some(keys) = self.stage_dirty_keys() {
-            let roots = self.roots.clone();
-            let state = state.clone();
-            std::thread::Builder::new()
-                .name(String::from("keyvalue-persist"))
-                .spawn(move || Self::persist_keys(&state, &roots, &keys))
-                .unwrap();
-            self.last_commit = Timestamp::now();
-            true
-        } else {
-            false
+        let context = Context::new(
+            roots.clone(),
+            KeyValuePersistence::immediate(),
+            CorruptKeyValuePolicy::default(),
+            None,
+        );
+        let mut persistence_watcher = context.kv_persistence_watcher();
+        assert!(matches!(get(&context, "expired")?, Output::Value(None)));
+        assert!(matches!(
+            get(&context, "valid")?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(1))))
+        ));
+
+        // The expired entry was removed when it was read.
+        while tree.get(full_key(None, "expired").as_bytes())?.is_some() {
+            persistence_watcher.next_value()?;
         }
-    }
+        assert!(tree.get(full_key(None, "valid").as_bytes())?.is_some());
 
-    #[cfg(test)]
-    pub fn persistence_watcher(&self) -> Watcher<Timestamp> {
-        self.last_persistence.watch()
+        Ok(())
     }
 
-    #[cfg_attr(feature = "instrument", tracing::instrument(level = "trace", skip_all))]
-    fn persist_keys(
-        key_value_state: &Arc<Mutex<KeyValueState>>,
-        roots: &Roots<AnyFile>,
-        keys: &BTreeMap<String, Option<Entry>>,
-    ) -> Result<(), bonsaidb_core::Error> {
-        let mut transaction = roots
-            .transaction(&[Unversioned::tree(KEY_TREE)])
-            .map_err(Error::from)?;
-        let all_keys = keys
-            .keys()
-            .map(|key| ArcBytes::from(key.as_bytes().to_vec()))
-            .collect();
-        let mut changed_keys = Vec::new();
-        transaction
-            .tree::<Unversioned>(0)
-            .unwrap()
-            .modify(
-                all_keys,
-                Operation::CompareSwap(CompareSwap::new(&mut |key, existing_value| {
-                    let full_key = std::str::from_utf8(key).unwrap();
-                    let (namespace, key) = split_key(full_key).unwrap();
-
-                    if let Some(new_value) = keys.get(full_key).unwrap() {
-                        changed_keys.push(ChangedKey {
-                            namespace,
-                            key,
-                            deleted: false,
-                        });
-                        let bytes = bincode::serialize(new_value).unwrap();
-                        nebari::tree::KeyOperation::Set(ArcBytes::from(bytes))
-                    } else if existing_value.is_some() {
-                        changed_keys.push(ChangedKey {
-                            namespace,
-                            key,
-                            deleted: existing_value.is_some(),
-                        });
-                        nebari::tree::KeyOperation::Remove
-                    } else {
-                        nebari::tree::KeyOperation::Skip
-                    }
-                })),
-            )
-            .map_err(Error::from)?;
-
-        if !changed_keys.is_empty() {
-            transaction
-                .entry_mut()
-                .set_data(compat::serialize_executed_transaction_changes(
-                    &Changes::Keys(changed_keys),
-                )?)
-                .map_err(Error::from)?;
-            transaction.commit().map_err(Error::from)?;
+    #[test]
+    fn conditional_operations_ignore_expired_entries() -> anyhow::Result<()> {
+        fn set(
+            context: &Context,
+            key: &str,
+            check: KeyCheck,
+        ) -> Result<Output, bonsaidb_core::Error> {
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from(key),
+                command: Command::Set(SetCommand {
+                    value: Value::Numeric(Numeric::UnsignedInteger(2)),
+                    expiration: None,
+                    keep_existing_expiration: false,
+                    check: Some(check),
+                    return_previous_value: false,
+                }),
+            })
         }
 
-        // If we are shutting down, check if we still have dirty keys.
-        let final_keys = {
-            let mut state = key_value_state.lock();
-            state.last_persistence.replace(Timestamp::now());
-            state.keys_being_persisted = None;
-            state.update_background_worker_target();
-            // This block is a little ugly to avoid having to acquire the lock
-            // twice. If we're shutting down and have no dirty keys, we notify
-            // the waiting shutdown task. If we have any dirty keys, we wait do
-            // to that step because we're going to recurse and reach this spot
-            // again.
-            if state.shutdown.is_some() {
-                let staged_keys = state.stage_dirty_keys();
-                if staged_keys.is_none() {
-                    let shutdown = state.shutdown.take().unwrap();
-                    let _: Result<_, _> = shutdown.send(());
-                }
-                staged_keys
-            } else {
-                None
-            }
-        };
-        if let Some(final_keys) = final_keys {
-            Self::persist_keys(key_value_state, roots, &final_keys)?;
+        let dir = TestDirectory::new("kv-conditional-operations-ignore-expired-entries");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        // Store expired entries directly so that they haven't been scheduled
+        // for removal by the context.
+        let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+        for key in ["vacant", "present", "deleted", "counter"] {
+            tree.set(
+                full_key(None, key).into_bytes(),
+                bincode::serialize(&Entry {
+                    value: Value::Numeric(Numeric::UnsignedInteger(1)),
+                    expiration: Some(Timestamp::from(
+                        std::time::SystemTime::now() - Duration::from_secs(1),
+                    )),
+                    last_updated: Timestamp::now(),
+                })?,
+            )?;
         }
+
+        let context = Context::new(
+            roots,
+            KeyValuePersistence::immediate(),
+            CorruptKeyValuePolicy::default(),
+            None,
+        );
+        assert!(matches!(
+            set(&context, "vacant", KeyCheck::OnlyIfVacant)?,
+            Output::Status(KeyStatus::Inserted)
+        ));
+        assert!(matches!(
+            set(&context, "present", KeyCheck::OnlyIfPresent)?,
+            Output::Status(KeyStatus::NotChanged)
+        ));
+        assert!(matches!(
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from("deleted"),
+                command: Command::Delete,
+            })?,
+            Output::Status(KeyStatus::NotChanged)
+        ));
+        // Incrementing an expired value starts from zero.
+        assert!(matches!(
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from("counter"),
+                command: Command::Increment {
+                    amount: Numeric::UnsignedInteger(1),
+                    saturating: false,
+                    maximum: None,
+                    previous: false,
+                },
+            })?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(1))))
+        ));
+
         Ok(())
     }
-}
 
-pub fn background_worker(
-    key_value_state: &Weak<Mutex<KeyValueState>>,
-    timestamp_receiver: &mut Watcher<BackgroundWorkerProcessTarget>,
-    storage_lock: Option<StorageLock>,
-) {
-    loop {
-        let mut perform_operations = false;
-        let current_target = *timestamp_receiver.read();
-        match current_target {
-            // With no target, sleep until we receive a target.
-            BackgroundWorkerProcessTarget::Never => {
-                if timestamp_receiver.watch().is_err() {
-                    break;
-                }
-            }
-            BackgroundWorkerProcessTarget::Timestamp(target) => {
-                // With a target, we need to wait to receive a target only as
-                // long as there is time remaining.
-                let remaining = target - Timestamp::now();
-                if let Some(remaining) = remaining {
-                    // recv_timeout panics if Instant::checked_add(remaining)
-                    // fails. So, we will cap the sleep time at 1 day.
-                    let remaining = remaining.min(Duration::from_secs(60 * 60 * 24));
-                    match timestamp_receiver.watch_timeout(remaining) {
-                        Ok(_) | Err(watchable::TimeoutError::Timeout) => {
-                            perform_operations = true;
-                        }
-                        Err(watchable::TimeoutError::Disconnected) => break,
-                    }
-                } else {
-                    perform_operations = true;
-                }
-            }
-            BackgroundWorkerProcessTarget::Now => {
-                perform_operations = true;
-            }
+    #[test]
+    fn mixed_entry_encodings() -> anyhow::Result<()> {
+        let dir = TestDirectory::new("kv-mixed-entry-encodings");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let entry = |value: u64| Entry {
+            value: Value::Numeric(Numeric::UnsignedInteger(value)),
+            expiration: None,
+            last_updated: Timestamp::now(),
         };
+        // Entries written before the encoding was configurable were always
+        // serialized by bincode.
+        let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+        tree.set(
+            full_key(None, "bincode").into_bytes(),
+            bincode::serialize(&entry(1))?,
+        )?;
+        tree.set(
+            full_key(None, "pot").into_bytes(),
+            serialize_entry(&entry(2), KeyValueEntryEncoding::Pot),
+        )?;
 
-        let Some(key_value_state) = key_value_state.upgrade() else { break };
+        let context = Context::new(
+            roots,
+            KeyValuePersistence::immediate(),
+            CorruptKeyValuePolicy::Error,
+            None,
+        );
+        let mut persistence_watcher = context.kv_persistence_watcher();
+        let get = |key: &str| {
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from(key),
+                command: Command::Get {
+                    delete: false,
+                    metadata: false,
+                    committed: false,
+                },
+            })
+        };
+        assert!(matches!(
+            get("bincode")?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(1))))
+        ));
+        assert!(matches!(
+            get("pot")?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(2))))
+        ));
 
-        if perform_operations {
-            let mut state = key_value_state.lock();
-            let now = Timestamp::now();
-            state.remove_expired_keys(now);
-            if state.needs_commit(now) {
-                state.commit_dirty_keys(&key_value_state);
-            }
-            state.update_background_worker_target();
+        // Writing the bincode entry stores it using the configured encoding.
+        context.perform_kv_operation(KeyOperation {
+            namespace: None,
+            key: String::from("bincode"),
+            command: Command::Set(SetCommand {
+                value: Value::Numeric(Numeric::UnsignedInteger(3)),
+                expiration: None,
+                keep_existing_expiration: false,
+                check: None,
+                return_previous_value: false,
+            }),
+        })?;
+        while !tree
+            .get(full_key(None, "bincode").as_bytes())?
+            .map_or(false, |bytes| bytes.starts_with(&POT_ENTRY_MARKER))
+        {
+            persistence_watcher.next_value()?;
         }
-    }
-
-    // The key-value store's delayed persistence can cause the key-value storage
-    // to be written past when the last reference to the storage is still held.
-    // The storage lock being held ensures that another reader/writer doesn't
-    // begin accessing this same storage again.
-    drop(storage_lock);
-}
+        assert!(matches!(
+            get("bincode")?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(3))))
+        ));
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum BackgroundWorkerProcessTarget {
-    Now,
-    Timestamp(Timestamp),
-    Never,
-}
+        Ok(())
+    }
 
-#[derive(Debug)]
-pub struct ExpirationLoader {
-    pub database: Database,
-    pub launched_at: Timestamp,
-}
+    #[test]
+    fn get_with_metadata() -> anyhow::Result<()> {
+        let dir = TestDirectory::new("kv-get-with-metadata");
+        let roots = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let entry = Entry {
+            value: Value::Bytes(Bytes::from(b"somevalue".to_vec())),
+            expiration: Some(Timestamp::now() + Duration::from_secs(60)),
+            last_updated: Timestamp::now(),
+        };
+        let serialized = serialize_entry(&entry, KeyValueEntryEncoding::default());
+        let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+        tree.set(full_key(None, "akey").into_bytes(), serialized.clone())?;
 
-impl Keyed<Task> for ExpirationLoader {
-    fn key(&self) -> Task {
-        Task::ExpirationLoader(self.database.data.name.clone())
+        let context = Context::new(
+            roots,
+            KeyValuePersistence::immediate(),
+            CorruptKeyValuePolicy::default(),
+            None,
+        );
+        let get = |key: &str| {
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from(key),
+                command: Command::Get {
+                    delete: false,
+                    metadata: true,
+                    committed: false,
+                },
+            })
+        };
+        match get("akey")? {
+            Output::ValueWithMetadata(Some(metadata)) => {
+                assert_eq!(metadata.value, entry.value);
+                assert_eq!(metadata.expiration, entry.expiration);
+                assert_eq!(metadata.size, u64::try_from(serialized.len())?);
+            }
+            other => unreachable!("unexpected output: {other:?}"),
+        }
+        assert!(matches!(get("missing")?, Output::ValueWithMetadata(None)));
+
+        Ok(())
     }
-}
 
-impl Job for ExpirationLoader {
-    type Error = Error;
-    type Output = ();
+    #[test]
+    fn committed_reads() -> anyhow::Result<()> {
+        use bonsaidb_core::connection::StorageConnection;
 
-    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
-    fn execute(&mut self) -> Result<Self::Output, Self::Error> {
-        let database = self.database.clone();
-        let launched_at = self.launched_at;
+        use crate::config::{Builder, StorageConfiguration};
+        use crate::Storage;
 
-        for ((namespace, key), entry) in database.all_key_value_entries()? {
-            if entry.last_updated < launched_at && entry.expiration.is_some() {
-                self.database
-                    .update_key_expiration(full_key(namespace.as_deref(), &key), entry.expiration);
-            }
+        let dir = TestDirectory::new("kv-committed-reads");
+        let storage = Storage::open(StorageConfiguration::new(&dir).key_value_persistence(
+            KeyValuePersistence::lazy([PersistenceThreshold::after_changes(3)]),
+        ))?;
+        let db = storage.create_database::<()>("committed", false)?;
+        let mut persistence_watcher = db.data.context.kv_persistence_watcher();
+        let tree = db.roots().tree(Unversioned::tree(KEY_TREE))?;
+
+        // Three changes reach the threshold, committing all of the keys.
+        db.set_numeric_key("a", 1_u64).execute()?;
+        db.set_numeric_key("b", 1_u64).execute()?;
+        db.set_numeric_key("c", 1_u64).execute()?;
+        while tree.get(full_key(None, "c").as_bytes())?.is_none() {
+            persistence_watcher.next_value()?;
         }
 
-        self.database
-            .storage()
-            .instance
-            .tasks()
-            .mark_key_value_expiration_loaded(self.database.data.name.clone());
+        // Fewer changes than the threshold aren't committed.
+        db.set_numeric_key("a", 2_u64).execute()?;
+        assert_eq!(db.get_key("a").into_u64()?, Some(2));
+        assert_eq!(db.get_key("a").committed_only().into_u64()?, Some(1));
+        db.delete_key("b")?;
+        assert!(db.get_key("b").query()?.is_none());
+        assert_eq!(db.get_key("b").committed_only().into_u64()?, Some(1));
+
+        // Committed reads can't delete the key.
+        assert!(db
+            .get_key("a")
+            .committed_only()
+            .and_delete()
+            .query()
+            .is_err());
+        assert_eq!(db.get_key("a").into_u64()?, Some(2));
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::time::{Duration, Instant};
+    #[test]
+    fn batched_entries() -> anyhow::Result<()> {
+        use bonsaidb_core::connection::StorageConnection;
 
-    use bonsaidb_core::arc_bytes::serde::Bytes;
-    use bonsaidb_core::test_util::{TestDirectory, TimingTest};
-    use nebari::io::any::{AnyFile, AnyFileManager};
+        use crate::config::{Builder, StorageConfiguration};
+        use crate::Storage;
 
-    use super::*;
-    use crate::config::PersistenceThreshold;
-    use crate::database::Context;
+        fn sorted(mut entries: Vec<KeyValueEntry>) -> Vec<KeyValueEntry> {
+            entries.sort_by(|a, b| (&a.namespace, &a.key).cmp(&(&b.namespace, &b.key)));
+            entries
+        }
 
-    fn run_test_with_persistence<
-        F: Fn(Context, nebari::Roots<AnyFile>) -> anyhow::Result<()> + Send,
-    >(
-        name: &str,
-        persistence: KeyValuePersistence,
-        test_contents: &F,
-    ) -> anyhow::Result<()> {
-        let dir = TestDirectory::new(name);
-        let sled = nebari::Config::new(&dir)
-            .file_manager(AnyFileManager::std())
-            .open()?;
+        let dir = TestDirectory::new("kv-batched-entries");
+        let storage = Storage::open(StorageConfiguration::new(&dir).key_value_persistence(
+            KeyValuePersistence::lazy([PersistenceThreshold::after_changes(10)]),
+        ))?;
+        let db = storage.create_database::<()>("batched", false)?;
+        let mut persistence_watcher = db.data.context.kv_persistence_watcher();
+        let tree = db.roots().tree(Unversioned::tree(KEY_TREE))?;
+
+        for index in 0..10_u64 {
+            db.set_numeric_key(format!("key{index}"), index).execute()?;
+        }
+        while tree.get(full_key(None, "key9").as_bytes())?.is_none() {
+            persistence_watcher.next_value()?;
+        }
+        // Leave updates, deletions, and insertions unpersisted.
+        db.set_numeric_key("key3", 30_u64).execute()?;
+        db.delete_key("key4")?;
+        db.delete_key("key5")?;
+        db.with_key_namespace("other")
+            .set_numeric_key("key", 1_u64)
+            .execute()?;
+        db.set_numeric_key("new", 1_u64).execute()?;
 
-        let context = Context::new(sled.clone(), persistence, None);
+        let expected = sorted(
+            db.all_key_value_entries()?
+                .into_iter()
+                .map(|((namespace, key), entry)| KeyValueEntry {
+                    namespace,
+                    key,
+                    value: entry.value,
+                    expiration: entry.expiration,
+                })
+                .collect(),
+        );
+        assert_eq!(expected.len(), 10);
 
-        test_contents(context, sled)?;
+        for batch_size in [1, 2, 3, 100] {
+            let mut batches = Vec::new();
+            db.key_value_entries_in_batches(batch_size, |batch| {
+                batches.push(batch);
+                ControlFlow::Continue(())
+            })?;
+            assert!(batches
+                .iter()
+                .all(|batch| !batch.is_empty() && batch.len() <= batch_size));
+            assert_eq!(sorted(batches.into_iter().flatten().collect()), expected);
+        }
 
-        Ok(())
-    }
+        // Breaking stops the scan.
+        let mut batches = 0;
+        db.key_value_entries_in_batches(2, |_| {
+            batches += 1;
+            ControlFlow::Break(())
+        })?;
+        assert_eq!(batches, 1);
 
-    fn run_test<F: Fn(Context, nebari::Roots<AnyFile>) -> anyhow::Result<()> + Send>(
-        name: &str,
-        test_contents: F,
-    ) -> anyhow::Result<()> {
-        run_test_with_persistence(name, KeyValuePersistence::default(), &test_contents)
+        Ok(())
     }
 
     #[test]
@@ -912,18 +4412,17 @@ mod tests {
             let mut persistence_watcher = context.kv_persistence_watcher();
             roots.delete_tree(KEY_TREE)?;
             let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
-            tree.set(b"atree\0akey", b"somevalue")?;
+            let akey = full_key(Some("atree"), "akey");
+            tree.set(akey.as_bytes().to_vec(), b"somevalue")?;
 
             // Expire the existing key
-            context.update_key_expiration(
-                full_key(Some("atree"), "akey"),
-                Some(Timestamp::now() + Duration::from_millis(100)),
-            );
+            context
+                .update_key_expiration(&akey, Some(Timestamp::now() + Duration::from_millis(100)));
             // Wait for persistence.
             persistence_watcher.next_value()?;
 
             // Verify it is gone.
-            assert!(tree.get(b"akey")?.is_none());
+            assert!(tree.get(akey.as_bytes())?.is_none());
 
             Ok(())
         })
@@ -936,25 +4435,22 @@ mod tests {
             let mut persistence_watcher = context.kv_persistence_watcher();
             roots.delete_tree(KEY_TREE)?;
             let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
-            tree.set(b"atree\0akey", b"somevalue")?;
+            let akey = full_key(Some("atree"), "akey");
+            tree.set(akey.as_bytes().to_vec(), b"somevalue")?;
             let start = Timestamp::now();
 
             // Set the expiration once.
-            context.update_key_expiration(
-                full_key(Some("atree"), "akey"),
-                Some(start + Duration::from_millis(100)),
-            );
+            context.update_key_expiration(&akey, Some(start + Duration::from_millis(100)));
             // Set the expiration to a longer value.
             let correct_expiration = start + Duration::from_secs(1);
-            context
-                .update_key_expiration(full_key(Some("atree"), "akey"), Some(correct_expiration));
+            context.update_key_expiration(&akey, Some(correct_expiration));
 
             // Wait for persistence, and ensure that the next persistence is
             // after our expiration timestamp.
             assert!(persistence_watcher.next_value()? > correct_expiration);
 
             // Verify the key is gone now.
-            assert_eq!(tree.get(b"atree\0akey")?, None);
+            assert_eq!(tree.get(akey.as_bytes())?, None);
 
             Ok(())
         })
@@ -967,27 +4463,24 @@ mod tests {
             let mut persistence_watcher = context.kv_persistence_watcher();
             roots.delete_tree(KEY_TREE)?;
             let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
-            tree.set(b"atree\0akey", b"somevalue")?;
-            tree.set(b"atree\0bkey", b"somevalue")?;
+            let akey = full_key(Some("atree"), "akey");
+            let bkey = full_key(Some("atree"), "bkey");
+            tree.set(akey.as_bytes().to_vec(), b"somevalue")?;
+            tree.set(bkey.as_bytes().to_vec(), b"somevalue")?;
 
             // Expire both keys, one for a shorter time than the other.
-            context.update_key_expiration(
-                full_key(Some("atree"), "akey"),
-                Some(Timestamp::now() + Duration::from_millis(100)),
-            );
-            context.update_key_expiration(
-                full_key(Some("atree"), "bkey"),
-                Some(Timestamp::now() + Duration::from_secs(1)),
-            );
+            context
+                .update_key_expiration(&akey, Some(Timestamp::now() + Duration::from_millis(100)));
+            context.update_key_expiration(&bkey, Some(Timestamp::now() + Duration::from_secs(1)));
 
             // Wait for the first persistence.
             persistence_watcher.next_value()?;
-            assert!(tree.get(b"atree\0akey")?.is_none());
-            assert!(tree.get(b"atree\0bkey")?.is_some());
+            assert!(tree.get(akey.as_bytes())?.is_none());
+            assert!(tree.get(bkey.as_bytes())?.is_some());
 
             // Wait for the second persistence.
             persistence_watcher.next_value()?;
-            assert!(tree.get(b"atree\0bkey")?.is_none());
+            assert!(tree.get(bkey.as_bytes())?.is_none());
 
             Ok(())
         })
@@ -999,19 +4492,20 @@ mod tests {
             loop {
                 sled.delete_tree(KEY_TREE)?;
                 let tree = sled.tree(Unversioned::tree(KEY_TREE))?;
-                tree.set(b"atree\0akey", b"somevalue")?;
+                let akey = full_key(Some("atree"), "akey");
+                tree.set(akey.as_bytes().to_vec(), b"somevalue")?;
                 let timing = TimingTest::new(Duration::from_millis(100));
                 sender.update_key_expiration(
-                    full_key(Some("atree"), "akey"),
+                    &akey,
                     Some(Timestamp::now() + Duration::from_millis(100)),
                 );
-                sender.update_key_expiration(full_key(Some("atree"), "akey"), None);
+                sender.update_key_expiration(&akey, None);
                 if timing.elapsed() > Duration::from_millis(100) {
                     // Restart, took too long.
                     continue;
                 }
                 timing.wait_until(Duration::from_millis(150));
-                assert!(tree.get(b"atree\0akey")?.is_some());
+                assert!(tree.get(akey.as_bytes())?.is_some());
                 break;
             }
 
@@ -1022,28 +4516,22 @@ mod tests {
     #[test]
     fn out_of_order_expiration() -> anyhow::Result<()> {
         run_test("kv-out-of-order-expiration", |context, roots| loop {
-            context.update_key_expiration(full_key(Some("atree"), "akey"), None);
-            context.update_key_expiration(full_key(Some("atree"), "bkey"), None);
-            context.update_key_expiration(full_key(Some("atree"), "ckey"), None);
+            let akey = full_key(Some("atree"), "akey");
+            let bkey = full_key(Some("atree"), "bkey");
+            let ckey = full_key(Some("atree"), "ckey");
+            context.update_key_expiration(&akey, None);
+            context.update_key_expiration(&bkey, None);
+            context.update_key_expiration(&ckey, None);
             let mut persistence_watcher = context.kv_persistence_watcher();
             drop(roots.delete_tree(KEY_TREE));
             let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
-            tree.set(b"atree\0akey", b"somevalue")?;
-            tree.set(b"atree\0bkey", b"somevalue")?;
-            tree.set(b"atree\0ckey", b"somevalue")?;
+            tree.set(akey.as_bytes().to_vec(), b"somevalue")?;
+            tree.set(bkey.as_bytes().to_vec(), b"somevalue")?;
+            tree.set(ckey.as_bytes().to_vec(), b"somevalue")?;
             let timing = TimingTest::new(Duration::from_millis(100));
-            context.update_key_expiration(
-                full_key(Some("atree"), "akey"),
-                Some(Timestamp::now() + Duration::from_secs(3)),
-            );
-            context.update_key_expiration(
-                full_key(Some("atree"), "ckey"),
-                Some(Timestamp::now() + Duration::from_secs(1)),
-            );
-            context.update_key_expiration(
-                full_key(Some("atree"), "bkey"),
-                Some(Timestamp::now() + Duration::from_secs(2)),
-            );
+            context.update_key_expiration(&akey, Some(Timestamp::now() + Duration::from_secs(3)));
+            context.update_key_expiration(&ckey, Some(Timestamp::now() + Duration::from_secs(1)));
+            context.update_key_expiration(&bkey, Some(Timestamp::now() + Duration::from_secs(2)));
             persistence_watcher.mark_read();
             if timing.elapsed() > Duration::from_millis(500) {
                 println!("Restarting");
@@ -1059,9 +4547,9 @@ mod tests {
                 println!("Restarting");
                 continue;
             }
-            assert!(tree.get(b"atree\0akey")?.is_some());
-            assert!(tree.get(b"atree\0bkey")?.is_some());
-            assert!(tree.get(b"atree\0ckey")?.is_none());
+            assert!(tree.get(akey.as_bytes())?.is_some());
+            assert!(tree.get(bkey.as_bytes())?.is_some());
+            assert!(tree.get(ckey.as_bytes())?.is_none());
 
             // Wait for the next key to expire.
             persistence_watcher
@@ -1072,8 +4560,8 @@ mod tests {
                 println!("Restarting");
                 continue;
             }
-            assert!(tree.get(b"atree\0akey")?.is_some());
-            assert!(tree.get(b"atree\0bkey")?.is_none());
+            assert!(tree.get(akey.as_bytes())?.is_some());
+            assert!(tree.get(bkey.as_bytes())?.is_none());
 
             // Wait for the final key to expire.
             persistence_watcher
@@ -1083,7 +4571,7 @@ mod tests {
                 println!("Restarting");
                 continue;
             }
-            assert!(tree.get(b"atree\0akey")?.is_none());
+            assert!(tree.get(akey.as_bytes())?.is_none());
 
             return Ok(());
         })
@@ -1164,6 +4652,337 @@ mod tests {
         )
     }
 
+    #[test]
+    fn dirty_keys_backpressure() -> anyhow::Result<()> {
+        const MAX_DIRTY_KEYS: usize = 10;
+        run_test_with_persistence(
+            "kv-dirty-keys-backpressure",
+            // Without the high-water mark, these rules would never commit.
+            KeyValuePersistence::lazy([PersistenceThreshold::after_changes(usize::MAX)])
+                .with_max_dirty_keys(MAX_DIRTY_KEYS),
+            &|context, roots| {
+                for i in 0..1_000 {
+                    context
+                        .perform_kv_operation(KeyOperation {
+                            namespace: None,
+                            key: format!("key{i}"),
+                            command: Command::Set(SetCommand {
+                                value: Value::Bytes(Bytes::default()),
+                                expiration: None,
+                                keep_existing_expiration: false,
+                                check: None,
+                                return_previous_value: false,
+                            }),
+                        })
+                        .unwrap();
+
+                    let state = context.key_value_state.lock();
+                    assert!(state.dirty_keys.len() <= MAX_DIRTY_KEYS);
+                    assert!(state
+                        .keys_being_persisted
+                        .as_ref()
+                        .map_or(true, |keys| keys.len() <= MAX_DIRTY_KEYS));
+                }
+
+                // Wait for any in-progress commit to finish, and verify that
+                // everything but the remaining dirty keys has been persisted.
+                let dirty_keys = loop {
+                    let state = context.key_value_state.lock();
+                    if state.keys_being_persisted.is_none() {
+                        break state.dirty_keys.len();
+                    }
+                    let mut persistence_watcher = state.persistence_watcher();
+                    drop(state);
+                    persistence_watcher.watch()?;
+                };
+                let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+                assert_eq!(tree.get_range(&(..))?.len(), 1_000 - dirty_keys);
+
+                Ok(())
+            },
+        )
+    }
+
+    #[test]
+    fn namespace_persistence() -> anyhow::Result<()> {
+        fn set(context: &Context, namespace: &str, key: &str) {
+            context
+                .perform_kv_operation(KeyOperation {
+                    namespace: Some(String::from(namespace)),
+                    key: String::from(key),
+                    command: Command::Set(SetCommand {
+                        value: Value::Bytes(Bytes::default()),
+                        expiration: None,
+                        keep_existing_expiration: false,
+                        check: None,
+                        return_previous_value: false,
+                    }),
+                })
+                .unwrap();
+        }
+
+        run_test_with_persistence(
+            "kv-namespace-persistence",
+            KeyValuePersistence::immediate()
+                .with_namespace(
+                    "sessions",
+                    KeyValuePersistence::lazy([PersistenceThreshold::after_changes(1)
+                        .and_duration(Duration::from_secs(3600))]),
+                )
+                .with_namespace("payments", KeyValuePersistence::immediate()),
+            &|context, roots| {
+                let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+
+                // The lazy namespace shouldn't start a commit.
+                set(&context, "sessions", "token");
+                {
+                    let state = context.key_value_state.lock();
+                    assert_eq!(state.dirty_keys.len(), 1);
+                    assert!(state.keys_being_persisted.is_none());
+                    assert_eq!(state.dirty_namespaces.get("sessions"), Some(&1));
+                }
+                assert!(tree
+                    .get(full_key(Some("sessions"), "token").as_bytes())?
+                    .is_none());
+
+                // The eager namespace commits immediately, which persists all
+                // outstanding changes.
+                set(&context, "payments", "counter");
+                loop {
+                    let state = context.key_value_state.lock();
+                    if state.keys_being_persisted.is_none() {
+                        assert!(state.dirty_keys.is_empty());
+                        assert!(state.dirty_namespaces.is_empty());
+                        break;
+                    }
+                    let mut persistence_watcher = state.persistence_watcher();
+                    drop(state);
+                    persistence_watcher.watch()?;
+                }
+                assert!(tree
+                    .get(full_key(Some("payments"), "counter").as_bytes())?
+                    .is_some());
+                assert!(tree
+                    .get(full_key(Some("sessions"), "token").as_bytes())?
+                    .is_some());
+
+                Ok(())
+            },
+        )
+    }
+
+    #[test]
+    fn clear_all() -> anyhow::Result<()> {
+        run_test("kv-clear-all", |context, roots| {
+            for (key, expiration) in [
+                ("persistent", None),
+                ("expiring", Some(Expiration::After(Duration::from_secs(60)))),
+            ] {
+                context
+                    .perform_kv_operation(KeyOperation {
+                        namespace: Some(String::from("atree")),
+                        key: String::from(key),
+                        command: Command::Set(SetCommand {
+                            value: Value::Bytes(Bytes::default()),
+                            expiration,
+                            keep_existing_expiration: false,
+                            check: None,
+                            return_previous_value: false,
+                        }),
+                    })
+                    .unwrap();
+            }
+
+            // Wait for the keys to be persisted, so that clearing them must
+            // remove them from the trees.
+            loop {
+                let state = context.key_value_state.lock();
+                if state.dirty_keys.is_empty() && state.keys_being_persisted.is_none() {
+                    break;
+                }
+                let mut persistence_watcher = state.persistence_watcher();
+                drop(state);
+                persistence_watcher.watch()?;
+            }
+            let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+            let index = roots.tree(Unversioned::tree(EXPIRATION_TREE))?;
+            for key in ["persistent", "expiring"] {
+                assert!(tree.get(full_key(Some("atree"), key).as_bytes())?.is_some());
+            }
+            assert_eq!(index.get_range(&(..))?.len(), 1);
+
+            context.clear_all_key_value_entries()?;
+
+            for key in ["persistent", "expiring"] {
+                assert!(tree.get(full_key(Some("atree"), key).as_bytes())?.is_none());
+            }
+            assert!(index.get_range(&(..))?.is_empty());
+            {
+                let state = context.key_value_state.lock();
+                assert!(state.dirty_keys.is_empty());
+                assert!(state.expiring_keys.is_empty());
+                assert!(state.expiration_order.is_empty());
+                assert_eq!(
+                    *state.background_worker_target.read(),
+                    BackgroundWorkerProcessTarget::Never
+                );
+            }
+            assert!(matches!(
+                context.perform_kv_operation(KeyOperation {
+                    namespace: Some(String::from("atree")),
+                    key: String::from("persistent"),
+                    command: Command::Get {
+                        delete: false,
+                        metadata: false,
+                        committed: false,
+                    },
+                })?,
+                Output::Value(None)
+            ));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn sweep_expired() -> anyhow::Result<()> {
+        run_test("kv-sweep-expired", |context, roots| {
+            // Keys persisted with an expiration aren't tracked until the
+            // expiration loader runs, as if they expired while the database
+            // was closed. Enough keys are written to require multiple batches.
+            let now = Timestamp::now();
+            let expired = Timestamp::from(std::time::SystemTime::now() - Duration::from_secs(1));
+            let entry = |expiration| {
+                Some(Entry {
+                    value: Value::Bytes(Bytes::default()),
+                    expiration,
+                    last_updated: now,
+                })
+            };
+            let expired_keys = (0..=SWEEP_EXPIRED_BATCH_SIZE)
+                .map(|index| full_key(None, &format!("expired-{index}")))
+                .collect::<Vec<_>>();
+            let mut keys = expired_keys
+                .iter()
+                .map(|key| (key.clone(), entry(Some(expired))))
+                .collect::<BTreeMap<_, _>>();
+            keys.insert(
+                full_key(None, "future"),
+                entry(Some(now + Duration::from_secs(3600))),
+            );
+            keys.insert(full_key(None, "updated"), entry(Some(expired)));
+            commit_keys(&roots, &keys, &KeyValuePersistence::default())?;
+
+            // A key whose expiration was removed by a newer change is kept,
+            // even if the change hasn't been persisted yet.
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from("updated"),
+                command: Command::Set(SetCommand {
+                    value: Value::Bytes(Bytes::default()),
+                    expiration: None,
+                    keep_existing_expiration: false,
+                    check: None,
+                    return_previous_value: false,
+                }),
+            })?;
+
+            assert_eq!(
+                context.sweep_expired_key_value_entries()?,
+                SWEEP_EXPIRED_BATCH_SIZE + 1
+            );
+            {
+                // Each key is either waiting to be persisted or has been
+                // persisted. The lock prevents persistence from completing
+                // while the keys are checked.
+                let state = context.key_value_state.lock();
+                let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+                let exists = |key: &String| -> anyhow::Result<bool> {
+                    let pending = state.dirty_keys.get(key).or_else(|| {
+                        state
+                            .keys_being_persisted
+                            .as_ref()
+                            .and_then(|keys| keys.get(key))
+                    });
+                    match pending {
+                        Some(entry) => Ok(entry.is_some()),
+                        None => Ok(tree.get(key.as_bytes())?.is_some()),
+                    }
+                };
+                for key in &expired_keys {
+                    assert!(!exists(key)?);
+                }
+                assert!(exists(&full_key(None, "future"))?);
+                assert!(exists(&full_key(None, "updated"))?);
+            }
+
+            // The removed keys aren't counted again.
+            assert_eq!(context.sweep_expired_key_value_entries()?, 0);
+
+            Ok(())
+        })
+    }
+
+    #[derive(Debug)]
+    struct MemoryReplica(flume::Sender<Vec<ReplicatedKey>>);
+
+    impl KvReplicationSink for MemoryReplica {
+        fn replicate(&self, changes: &[ReplicatedKey]) -> Result<(), bonsaidb_core::Error> {
+            self.0.send(changes.to_vec()).unwrap();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn replication() -> anyhow::Result<()> {
+        run_test("kv-replication", |context, _roots| {
+            let (sender, receiver) = flume::unbounded();
+            context
+                .key_value_state
+                .lock()
+                .register_replica(Arc::new(MemoryReplica(sender)));
+
+            let expiration = Timestamp::now() + Duration::from_secs(60);
+            context.perform_kv_operation(KeyOperation {
+                namespace: Some(String::from("replicated")),
+                key: String::from("a"),
+                command: Command::Set(SetCommand {
+                    value: Value::Numeric(Numeric::UnsignedInteger(1)),
+                    expiration: Some(Expiration::Absolute(expiration)),
+                    keep_existing_expiration: false,
+                    check: None,
+                    return_previous_value: false,
+                }),
+            })?;
+            assert_eq!(
+                receiver.recv_timeout(Duration::from_secs(5))?,
+                vec![ReplicatedKey {
+                    namespace: Some(String::from("replicated")),
+                    key: String::from("a"),
+                    value: Some(Value::Numeric(Numeric::UnsignedInteger(1))),
+                    expiration: Some(expiration),
+                }]
+            );
+
+            context.perform_kv_operation(KeyOperation {
+                namespace: Some(String::from("replicated")),
+                key: String::from("a"),
+                command: Command::Delete,
+            })?;
+            assert_eq!(
+                receiver.recv_timeout(Duration::from_secs(5))?,
+                vec![ReplicatedKey {
+                    namespace: Some(String::from("replicated")),
+                    key: String::from("a"),
+                    value: None,
+                    expiration: None,
+                }]
+            );
+
+            Ok(())
+        })
+    }
+
     #[test]
     fn saves_on_drop() -> anyhow::Result<()> {
         let dir = TestDirectory::new("saves-on-drop.bonsaidb");
@@ -1175,6 +4994,7 @@ mod tests {
         let context = Context::new(
             sled,
             KeyValuePersistence::lazy([PersistenceThreshold::after_changes(2)]),
+            CorruptKeyValuePolicy::default(),
             None,
         );
         context
@@ -1199,4 +5019,185 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn bulk_load() -> anyhow::Result<()> {
+        const ENTRIES: u64 = BULK_LOAD_BATCH_SIZE as u64 * 2 + 500;
+        run_test("kv-bulk-load", |context, roots| {
+            let mut persistence_watcher = context.kv_persistence_watcher();
+            let tree = roots.tree(Unversioned::tree(KEY_TREE))?;
+            let expiration = Timestamp::now() + Duration::from_millis(500);
+            // Every 100th key expires.
+            let entries = (0..ENTRIES).map(|index| {
+                (
+                    full_key(Some("bulk"), &index.to_string()),
+                    Entry {
+                        value: Value::Numeric(Numeric::UnsignedInteger(index)),
+                        expiration: (index % 100 == 0).then_some(expiration),
+                        last_updated: Timestamp::now(),
+                    },
+                )
+            });
+            assert_eq!(
+                context.bulk_load_key_value_entries(entries)?,
+                usize::try_from(ENTRIES).unwrap()
+            );
+
+            // Expiring keys may have already expired by the time they're read.
+            for index in (0..ENTRIES).filter(|index| index % 100 != 0) {
+                let value = context.perform_kv_operation(KeyOperation {
+                    namespace: Some(String::from("bulk")),
+                    key: index.to_string(),
+                    command: Command::Get {
+                        delete: false,
+                        metadata: false,
+                        committed: false,
+                    },
+                })?;
+                assert!(matches!(
+                    value,
+                    Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(value))))
+                        if value == index
+                ));
+            }
+
+            // The expiring keys are removed without any further operations,
+            // which is only possible if the expirations were registered.
+            persistence_watcher.next_value()?;
+            for index in 0..ENTRIES {
+                let key = full_key(Some("bulk"), &index.to_string());
+                assert_eq!(
+                    tree.get(key.as_bytes())?.is_none(),
+                    index % 100 == 0,
+                    "unexpected state for {index}"
+                );
+            }
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn write_ahead_log_replay() -> anyhow::Result<()> {
+        fn set(context: &Context, key: &str, value: u64) {
+            context
+                .perform_kv_operation(KeyOperation {
+                    namespace: None,
+                    key: String::from(key),
+                    command: Command::Set(SetCommand {
+                        value: Value::Numeric(Numeric::UnsignedInteger(value)),
+                        expiration: None,
+                        keep_existing_expiration: false,
+                        check: None,
+                        return_previous_value: false,
+                    }),
+                })
+                .unwrap();
+        }
+
+        let dir = TestDirectory::new("kv-write-ahead-log.bonsaidb");
+        let sled = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let tree = sled.tree(Unversioned::tree(KEY_TREE))?;
+        // The thresholds are never met, so the only durable copy of the
+        // changes is the write-ahead log.
+        let persistence =
+            KeyValuePersistence::lazy([PersistenceThreshold::after_changes(usize::MAX)])
+                .with_write_ahead_log();
+
+        let context = Context::new(
+            sled.clone(),
+            persistence.clone(),
+            CorruptKeyValuePolicy::default(),
+            None,
+        );
+        set(&context, "key1", 1);
+        set(&context, "key2", 2);
+        // Overwrite and delete keys that are already dirty.
+        set(&context, "key1", 3);
+        context.perform_kv_operation(KeyOperation {
+            namespace: None,
+            key: String::from("key2"),
+            command: Command::Delete,
+        })?;
+        set(&context, "key3", 4);
+        assert!(tree.get(b"\0key1").unwrap().is_none());
+
+        // Simulate the process exiting without committing the dirty keys.
+        std::mem::forget(context);
+        assert!(tree.get(b"\0key1").unwrap().is_none());
+
+        replay_write_ahead_log(&sled, &persistence)?;
+        assert!(dir.join("kv-wal").read_dir()?.next().is_none());
+
+        let context = Context::new(sled, persistence, CorruptKeyValuePolicy::default(), None);
+        let get = |key: &str| {
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from(key),
+                command: Command::Get {
+                    delete: false,
+                    metadata: false,
+                    committed: false,
+                },
+            })
+        };
+        assert!(matches!(
+            get("key1")?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(3))))
+        ));
+        assert!(matches!(get("key2")?, Output::Value(None)));
+        assert!(matches!(
+            get("key3")?,
+            Output::Value(Some(Value::Numeric(Numeric::UnsignedInteger(4))))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_ahead_log_failure_reverts_changes() -> anyhow::Result<()> {
+        let dir = TestDirectory::new("kv-write-ahead-log-failure.bonsaidb");
+        let sled = nebari::Config::new(&dir)
+            .file_manager(AnyFileManager::std())
+            .open()?;
+        let persistence =
+            KeyValuePersistence::lazy([PersistenceThreshold::after_changes(usize::MAX)])
+                .with_write_ahead_log();
+        // A file in place of the write-ahead log's directory prevents any
+        // changes from being written to it.
+        std::fs::write(dir.join("kv-wal"), b"")?;
+
+        let context = Context::new(sled, persistence, CorruptKeyValuePolicy::default(), None);
+        assert!(context
+            .perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from("key"),
+                command: Command::Set(SetCommand {
+                    value: Value::Numeric(Numeric::UnsignedInteger(1)),
+                    expiration: None,
+                    keep_existing_expiration: false,
+                    check: None,
+                    return_previous_value: false,
+                }),
+            })
+            .is_err());
+
+        // The change that couldn't be recorded was never applied.
+        assert!(matches!(
+            context.perform_kv_operation(KeyOperation {
+                namespace: None,
+                key: String::from("key"),
+                command: Command::Get {
+                    delete: false,
+                    metadata: false,
+                    committed: false,
+                },
+            })?,
+            Output::Value(None)
+        ));
+
+        Ok(())
+    }
 }
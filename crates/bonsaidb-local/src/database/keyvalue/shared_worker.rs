@@ -0,0 +1,227 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use bonsaidb_core::keyvalue::Timestamp;
+use parking_lot::{Condvar, Mutex, MutexGuard};
+
+use super::{BackgroundWorkerProcessTarget, KeyValueState};
+use crate::storage::StorageLock;
+
+/// A background worker that removes expired keys and commits dirty keys for
+/// the key-value stores of many databases using a single thread.
+///
+/// Each registered database has at most one pending target. The targets of
+/// every database are kept in a set ordered by when they are due, allowing
+/// the worker to sleep until the earliest target of any database. The worker
+/// thread exits once every handle to the worker has been dropped.
+#[derive(Debug)]
+pub struct SharedWorker {
+    data: Arc<SharedWorkerData>,
+}
+
+#[derive(Debug)]
+struct SharedWorkerData {
+    schedule: Mutex<Schedule>,
+    changed: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct Schedule {
+    /// The number of [`SharedWorker`] handles, including the handle held by
+    /// each [`Registration`].
+    handles: usize,
+    next_id: u64,
+    databases: HashMap<u64, RegisteredDatabase>,
+    /// The pending target of each database, ordered by when it is due.
+    targets: BTreeSet<(Timestamp, u64)>,
+}
+
+#[derive(Debug)]
+struct RegisteredDatabase {
+    state: Weak<Mutex<KeyValueState>>,
+    target: Option<Timestamp>,
+    /// Held until the database is no longer registered. Delayed persistence
+    /// can write to the database after the last reference to its storage is
+    /// dropped, and the lock prevents another process from opening the same
+    /// storage in the meantime.
+    _storage_lock: Option<StorageLock>,
+}
+
+impl SharedWorker {
+    /// Spawns the worker's thread.
+    pub fn spawn() -> Self {
+        let data = Arc::new(SharedWorkerData {
+            schedule: Mutex::new(Schedule {
+                handles: 1,
+                ..Schedule::default()
+            }),
+            changed: Condvar::new(),
+        });
+        let worker_data = data.clone();
+        std::thread::Builder::new()
+            .name(String::from("keyvalue-shared-worker"))
+            .spawn(move || worker_data.run())
+            .unwrap();
+        Self { data }
+    }
+
+    /// Registers the key-value store `state` to be serviced by this worker.
+    /// The store is serviced until the returned registration is dropped.
+    pub fn register(
+        &self,
+        state: Weak<Mutex<KeyValueState>>,
+        storage_lock: Option<StorageLock>,
+    ) -> Registration {
+        let mut schedule = self.data.schedule.lock();
+        let id = schedule.next_id;
+        schedule.next_id += 1;
+        schedule.databases.insert(
+            id,
+            RegisteredDatabase {
+                state,
+                target: None,
+                _storage_lock: storage_lock,
+            },
+        );
+        drop(schedule);
+
+        Registration {
+            worker: self.clone(),
+            id,
+        }
+    }
+}
+
+impl Clone for SharedWorker {
+    fn clone(&self) -> Self {
+        self.data.schedule.lock().handles += 1;
+        Self {
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl Drop for SharedWorker {
+    fn drop(&mut self) {
+        let mut schedule = self.data.schedule.lock();
+        schedule.handles -= 1;
+        if schedule.handles == 0 {
+            self.data.changed.notify_one();
+        }
+    }
+}
+
+/// A key-value store's registration with a [`SharedWorker`].
+#[derive(Debug)]
+pub struct Registration {
+    worker: SharedWorker,
+    id: u64,
+}
+
+impl Registration {
+    /// Replaces the store's pending target with `target`.
+    pub fn schedule(&self, target: BackgroundWorkerProcessTarget) {
+        let due = match target {
+            BackgroundWorkerProcessTarget::Now => Some(Timestamp::now()),
+            BackgroundWorkerProcessTarget::Timestamp(target) => Some(target),
+            BackgroundWorkerProcessTarget::Never => None,
+        };
+        let mut schedule = self.worker.data.schedule.lock();
+        if schedule.reschedule(self.id, due) {
+            self.worker.data.changed.notify_one();
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let removed = {
+            let mut schedule = self.worker.data.schedule.lock();
+            schedule.reschedule(self.id, None);
+            schedule.databases.remove(&self.id)
+        };
+        // The storage lock is released after the schedule is unlocked.
+        drop(removed);
+    }
+}
+
+impl Schedule {
+    /// Replaces the pending target of database `id` with `due`, returning
+    /// true if the earliest target of all databases changed.
+    fn reschedule(&mut self, id: u64, due: Option<Timestamp>) -> bool {
+        if let Some(database) = self.databases.get_mut(&id) {
+            if database.target == due {
+                return false;
+            }
+
+            let earliest = self.targets.iter().next().copied();
+            if let Some(previous) = database.target.take() {
+                self.targets.remove(&(previous, id));
+            }
+            if let Some(due) = due {
+                self.targets.insert((due, id));
+                database.target = Some(due);
+            }
+            self.targets.iter().next().copied() != earliest
+        } else {
+            false
+        }
+    }
+
+    /// Removes every target that is due as of `now`, returning the states of
+    /// the databases that need to be serviced.
+    fn take_due(&mut self, now: Timestamp) -> Vec<Weak<Mutex<KeyValueState>>> {
+        let mut due = Vec::new();
+        while let Some(&(target, id)) = self.targets.iter().next() {
+            if target > now {
+                break;
+            }
+
+            self.targets.remove(&(target, id));
+            if let Some(database) = self.databases.get_mut(&id) {
+                database.target = None;
+                due.push(database.state.clone());
+            }
+        }
+        due
+    }
+}
+
+impl SharedWorkerData {
+    fn run(&self) {
+        let mut schedule = self.schedule.lock();
+        while schedule.handles > 0 {
+            let now = Timestamp::now();
+            let earliest = schedule.targets.iter().next().map(|&(target, _)| target);
+            match earliest {
+                None => {
+                    self.changed.wait(&mut schedule);
+                }
+                Some(target) if target > now => {
+                    // Sleep no longer than a day, matching the per-database
+                    // worker.
+                    let remaining = (target - now)
+                        .unwrap_or_default()
+                        .min(Duration::from_secs(60 * 60 * 24));
+                    self.changed.wait_for(&mut schedule, remaining);
+                }
+                Some(_) => {
+                    let due = schedule.take_due(now);
+                    // Servicing a store reschedules it, and the last reference
+                    // to a store may be dropped here, both of which lock the
+                    // schedule.
+                    MutexGuard::unlocked(&mut schedule, || {
+                        for state in due {
+                            if let Some(key_value_state) = state.upgrade() {
+                                key_value_state
+                                    .lock()
+                                    .perform_background_operations(&key_value_state);
+                            }
+                        }
+                    });
+                }
+            }
+        }
+    }
+}
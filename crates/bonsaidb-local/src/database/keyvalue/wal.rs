@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use super::Entry;
+use crate::Error;
+
+/// The directory within a database's directory that contains the write-ahead
+/// log's segments.
+const WAL_DIRECTORY: &str = "kv-wal";
+
+/// An append-only log of key-value changes that haven't been committed to the
+/// key-value tree.
+///
+/// Each change is stored as a little-endian `u32` length followed by the
+/// bincode-encoded `(full_key, Option<Entry>)`. Changes are appended to the
+/// active segment until the dirty keys are staged for a commit, at which
+/// point the segment is sealed. Once the staged keys are committed, the
+/// sealed segment is no longer needed and is removed.
+#[derive(Debug)]
+pub struct WriteAheadLog {
+    directory: PathBuf,
+    active: Option<(PathBuf, File)>,
+    pending: Vec<(String, Option<Entry>)>,
+}
+
+impl WriteAheadLog {
+    pub fn new(database_path: &Path) -> Self {
+        Self {
+            directory: database_path.join(WAL_DIRECTORY),
+            active: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Records that `full_key` has been changed to `entry`. The change isn't
+    /// durable until [`flush()`](Self::flush) returns.
+    pub fn record(&mut self, full_key: &str, entry: Option<&Entry>) {
+        self.pending.push((full_key.to_string(), entry.cloned()));
+    }
+
+    /// Discards the changes recorded since the last flush.
+    pub fn discard(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Appends all recorded changes to the active segment and waits for the
+    /// operating system to report that they have been written to disk.
+    ///
+    /// If an error occurs, the recorded changes are discarded and the active
+    /// segment is truncated to the changes written by previous flushes. The
+    /// caller is responsible for reverting the changes in memory.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let result = self.write_pending();
+        self.pending.clear();
+        result
+    }
+
+    fn write_pending(&mut self) -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        for change in &self.pending {
+            let record = bincode::serialize(change)?;
+            let length = u32::try_from(record.len())
+                .map_err(|_| Error::other("write-ahead log", "key-value entry too large"))?;
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(&record);
+        }
+
+        let file = match &mut self.active {
+            Some((_, file)) => file,
+            active => {
+                let segment = open_segment(&self.directory)?;
+                &mut active.insert(segment).1
+            }
+        };
+        let flushed_length = file.metadata()?.len();
+        let result = file
+            .write_all(&bytes)
+            .and_then(|()| file.sync_data())
+            .map_err(Error::from);
+        if result.is_err() {
+            // Remove any part of the changes that was written, so that they
+            // aren't replayed after they have been reverted in memory.
+            if let Err(err) = file.set_len(flushed_length).and_then(|()| file.sync_data()) {
+                log::error!("error truncating key-value write-ahead log segment: {err}");
+            }
+        }
+        result
+    }
+
+    /// Stops appending to the active segment and returns its path, if any
+    /// changes have been written to it. Changes that haven't been flushed are
+    /// discarded, as they are about to be committed along with the rest of
+    /// the staged keys.
+    pub fn seal(&mut self) -> Option<PathBuf> {
+        self.pending.clear();
+        self.active.take().map(|(path, _)| path)
+    }
+
+    /// Removes every segment, including the active segment.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.pending.clear();
+        self.active = None;
+        for (_, path) in segments(&self.directory)? {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+fn open_segment(directory: &Path) -> Result<(PathBuf, File), Error> {
+    fs::create_dir_all(directory)?;
+    let id = segments(directory)?
+        .last()
+        .map_or(0, |(last_id, _)| last_id + 1);
+    let path = directory.join(format!("{id:020}"));
+    let file = OpenOptions::new()
+        .create_new(true)
+        .append(true)
+        .open(&path)?;
+    // The new file's directory entry must also be durable for the segment to
+    // be found after a crash.
+    #[cfg(unix)]
+    File::open(directory)?.sync_all()?;
+    Ok((path, file))
+}
+
+/// Returns the ids and paths of all segments in `directory`, oldest first.
+fn segments(directory: &Path) -> Result<Vec<(u64, PathBuf)>, Error> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(Error::from(err)),
+    };
+    let mut segments = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u64>().ok())
+        {
+            segments.push((id, entry.path()));
+        }
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+/// Reads the changes from every segment left in `database_path`, returning the
+/// latest change for each key and the paths of the segments that were read.
+///
+/// A crash while a change is being appended can leave a partially written
+/// change at the end of a segment. Reading a segment stops at the first
+/// change that can't be decoded, as it was never acknowledged.
+pub fn read_segments(
+    database_path: &Path,
+) -> Result<(BTreeMap<String, Option<Entry>>, Vec<PathBuf>), Error> {
+    let mut changes = BTreeMap::new();
+    let mut paths = Vec::new();
+    for (_, path) in segments(&database_path.join(WAL_DIRECTORY))? {
+        let contents = fs::read(&path)?;
+        let mut remaining = &contents[..];
+        while remaining.len() >= 4 {
+            let (length, rest) = remaining.split_at(4);
+            let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+            if rest.len() < length {
+                break;
+            }
+            let (record, rest) = rest.split_at(length);
+            match bincode::deserialize::<(String, Option<Entry>)>(record) {
+                Ok((full_key, entry)) => {
+                    changes.insert(full_key, entry);
+                }
+                Err(_) => break,
+            }
+            remaining = rest;
+        }
+        paths.push(path);
+    }
+    Ok((changes, paths))
+}
+
+/// Removes a segment whose changes have been committed. A segment that is left
+/// behind would be replayed over newer changes the next time the database is
+/// opened, so failing to remove it is an error.
+pub fn remove_segment(path: &Path) -> Result<(), Error> {
+    fs::remove_file(path).map_err(Error::from)
+}
@@ -0,0 +1,282 @@
+//! Storage for the changes made by each executed transaction.
+//!
+//! Nebari's transaction log is append-only, so rather than storing each
+//! executed transaction's changes in its log entry, they are stored in
+//! [`EXECUTED_TRANSACTIONS_TREE`] keyed by the transaction's id. This allows
+//! pruned transactions to be removed, and the space they used to be reclaimed
+//! when the tree is compacted.
+
+use std::cell::{Cell, RefCell};
+use std::convert::Infallible;
+use std::time::SystemTime;
+
+use bonsaidb_core::arc_bytes::serde::Bytes;
+use bonsaidb_core::keyvalue::Timestamp;
+use nebari::io::any::AnyFile;
+use nebari::tree::{Operation, Root, ScanEvaluation, Unversioned};
+use nebari::{AbortError, LockedTransactionTree, Roots};
+use serde::{Deserialize, Serialize};
+
+use crate::config::TransactionLogRetention;
+use crate::Error;
+
+/// The tree storing the changes of each executed transaction, keyed by the
+/// transaction's id encoded in big-endian.
+pub(crate) const EXECUTED_TRANSACTIONS_TREE: &str = "executed-transactions";
+/// Stored in [`EXECUTED_TRANSACTIONS_TREE`] once the changes that previous
+/// versions stored in the transaction log have been copied into the tree. The
+/// key is shorter than every transaction id, so it sorts before them.
+const LEGACY_ENTRIES_COPIED_KEY: &[u8] = &[0];
+/// The maximum number of transactions read at once while listing or pruning.
+const BATCH_SIZE: usize = 1_000;
+
+#[derive(Serialize, Deserialize)]
+struct ExecutedTransactionEntry {
+    executed_at: Timestamp,
+    changes: Bytes,
+}
+
+fn transaction_key(id: u64) -> [u8; 8] {
+    id.to_be_bytes()
+}
+
+/// The transactions to remove from a database's transaction log. Because
+/// transaction ids are assigned in the order transactions are executed, the
+/// transactions matching a cutoff are always the oldest transactions.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Cutoff {
+    /// Transactions with an id less than this are removed.
+    pub before_id: u64,
+    /// Transactions executed before this are removed.
+    pub executed_before: Option<Timestamp>,
+}
+
+impl Cutoff {
+    /// Returns the cutoff for `retention` when the most recent transaction id
+    /// is `current_id`.
+    pub fn for_retention(retention: &TransactionLogRetention, current_id: Option<u64>) -> Self {
+        let before_id = match (retention.transactions, current_id) {
+            (Some(transactions), Some(current_id)) => {
+                current_id.saturating_add(1).saturating_sub(transactions)
+            }
+            _ => 0,
+        };
+        let executed_before = retention.max_age.map(|max_age| {
+            SystemTime::now()
+                .checked_sub(max_age)
+                .map_or(Timestamp::MIN, Timestamp::from)
+        });
+        Self {
+            before_id,
+            executed_before,
+        }
+    }
+
+    /// Returns true if this cutoff can match any transaction.
+    fn removes_any(&self) -> bool {
+        self.before_id > 0 || self.executed_before.is_some()
+    }
+
+    fn removes(&self, id: u64, executed_at: Timestamp) -> bool {
+        id < self.before_id || self.removes_executed_at(executed_at)
+    }
+
+    fn removes_executed_at(&self, executed_at: Timestamp) -> bool {
+        self.executed_before
+            .map_or(false, |executed_before| executed_at < executed_before)
+    }
+}
+
+/// Stores the `changes` made by the transaction `id`, which was executed at
+/// `executed_at`.
+pub(crate) fn record(
+    tree: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+    id: u64,
+    executed_at: Timestamp,
+    changes: Vec<u8>,
+) -> Result<(), Error> {
+    tree.set(
+        transaction_key(id).to_vec(),
+        pot::to_vec(&ExecutedTransactionEntry {
+            executed_at,
+            changes: Bytes::from(changes),
+        })?,
+    )?;
+    Ok(())
+}
+
+/// Returns up to `limit` transactions with an id of at least `starting_id`
+/// that don't match `cutoff`. Each transaction is returned with its id and
+/// the changes passed to [`record()`].
+pub(crate) fn list(
+    roots: &Roots<AnyFile>,
+    starting_id: u64,
+    limit: usize,
+    cutoff: &Cutoff,
+) -> Result<Vec<(u64, Bytes)>, Error> {
+    let tree = roots.tree(Unversioned::tree(EXECUTED_TRANSACTIONS_TREE))?;
+    let mut transactions = Vec::new();
+    let mut next_id = starting_id.max(cutoff.before_id);
+    while transactions.len() < limit {
+        let start = transaction_key(next_id);
+        let mut read = 0;
+        let mut entries = Vec::new();
+        tree.scan::<Infallible, _, _, _, _>(
+            &(start.as_slice()..),
+            true,
+            |_, _, _| ScanEvaluation::ReadData,
+            |_, _| {
+                if read >= BATCH_SIZE {
+                    return ScanEvaluation::Stop;
+                }
+                read += 1;
+                ScanEvaluation::ReadData
+            },
+            |key, _, value| {
+                entries.push((key, value));
+                Ok(())
+            },
+        )?;
+        if entries.is_empty() {
+            break;
+        }
+
+        for (key, value) in entries {
+            let id = parse_transaction_key(&key)?;
+            next_id = id.saturating_add(1);
+            let entry = pot::from_slice::<ExecutedTransactionEntry>(&value)?;
+            if !cutoff.removes(id, entry.executed_at) {
+                transactions.push((id, entry.changes));
+                if transactions.len() == limit {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(transactions)
+}
+
+/// Removes up to one batch of the oldest transactions matching `cutoff`,
+/// returning the number of transactions removed and whether every matching
+/// transaction has been removed.
+pub(crate) fn prune_batch(
+    tree: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+    cutoff: &Cutoff,
+) -> Result<(u64, bool), Error> {
+    if !cutoff.removes_any() {
+        return Ok((0, true));
+    }
+
+    let start = transaction_key(0);
+    let mut read = 0;
+    // Set once a transaction that doesn't match the cutoff is found. Every
+    // later transaction is newer, so the scan stops there.
+    let kept = Cell::new(false);
+    let expired = RefCell::new(Vec::new());
+    tree.scan::<Error, _, _, _, _>(
+        &(start.as_slice()..),
+        true,
+        |_, _, _| ScanEvaluation::ReadData,
+        |key, _| {
+            if read >= BATCH_SIZE || kept.get() {
+                return ScanEvaluation::Stop;
+            }
+            read += 1;
+            match parse_transaction_key(key) {
+                // Transactions before the cutoff's id are removed without
+                // reading their entries.
+                Ok(id) if id < cutoff.before_id => {
+                    expired.borrow_mut().push(key.clone());
+                    ScanEvaluation::Skip
+                }
+                // Only the entry's age can cause a later transaction to be
+                // removed.
+                Ok(_) if cutoff.executed_before.is_none() => {
+                    kept.set(true);
+                    ScanEvaluation::Stop
+                }
+                _ => ScanEvaluation::ReadData,
+            }
+        },
+        |key, _, value| {
+            parse_transaction_key(&key).map_err(AbortError::Other)?;
+            let entry = pot::from_slice::<ExecutedTransactionEntry>(&value)
+                .map_err(|err| AbortError::Other(Error::from(err)))?;
+            if cutoff.removes_executed_at(entry.executed_at) {
+                expired.borrow_mut().push(key);
+            } else {
+                kept.set(true);
+            }
+            Ok(())
+        },
+    )?;
+
+    let expired = expired.into_inner();
+    let complete = kept.get() || read < BATCH_SIZE;
+    let removed = expired.len() as u64;
+    if !expired.is_empty() {
+        tree.modify(expired, Operation::Remove)?;
+    }
+    Ok((removed, complete))
+}
+
+/// Removes every transaction matching `cutoff`, returning the number of
+/// transactions removed. Each batch of transactions is removed in its own
+/// transaction.
+pub(crate) fn prune(roots: &Roots<AnyFile>, cutoff: &Cutoff) -> Result<u64, Error> {
+    let mut removed = 0;
+    loop {
+        let mut transaction =
+            roots.transaction(&[Unversioned::tree(EXECUTED_TRANSACTIONS_TREE)])?;
+        let (batch_removed, complete) =
+            prune_batch(&mut transaction.tree::<Unversioned>(0).unwrap(), cutoff)?;
+        if batch_removed > 0 {
+            transaction.commit()?;
+            removed += batch_removed;
+        }
+        if complete {
+            return Ok(removed);
+        }
+    }
+}
+
+/// Copies the changes that previous versions stored in the transaction log
+/// into [`EXECUTED_TRANSACTIONS_TREE`]. This must be called before executed
+/// transactions are listed.
+pub(crate) fn copy_legacy_entries(roots: &Roots<AnyFile>) -> Result<(), Error> {
+    if roots
+        .tree(Unversioned::tree(EXECUTED_TRANSACTIONS_TREE))?
+        .get(LEGACY_ENTRIES_COPIED_KEY)?
+        .is_some()
+    {
+        return Ok(());
+    }
+
+    let mut legacy_entries = Vec::new();
+    roots.transactions().scan(0.., |entry| {
+        if let Some(data) = entry.data() {
+            legacy_entries.push((entry.id, data.to_vec()));
+        }
+        true
+    })?;
+
+    let mut transaction = roots.transaction(&[Unversioned::tree(EXECUTED_TRANSACTIONS_TREE)])?;
+    let mut tree = transaction.tree::<Unversioned>(0).unwrap();
+    // Previous versions didn't record when transactions were executed.
+    let executed_at = Timestamp::now();
+    for (id, changes) in legacy_entries {
+        record(&mut tree, id, executed_at, changes)?;
+    }
+    tree.set(LEGACY_ENTRIES_COPIED_KEY.to_vec(), b"")?;
+    drop(tree);
+    // The copied changes were already recorded, so no data is recorded in the
+    // transaction log.
+    transaction.commit()?;
+    Ok(())
+}
+
+fn parse_transaction_key(key: &[u8]) -> Result<u64, Error> {
+    <[u8; 8]>::try_from(key)
+        .map(u64::from_be_bytes)
+        .map_err(|_| Error::other("transaction log", "invalid transaction id"))
+}
@@ -0,0 +1,224 @@
+use std::fmt::{self, Display};
+
+use bonsaidb_core::connection::{Connection, HasSession};
+use bonsaidb_core::document::Header;
+use bonsaidb_core::permissions::bonsai::{database_resource_name, BonsaiAction, DatabaseAction};
+use bonsaidb_core::schema::{view, CollectionName, ViewName};
+use nebari::tree::{ScanEvaluation, Unversioned, Versioned};
+use nebari::{AbortError, ArcBytes};
+
+use crate::database::{deserialize_document, document_tree_name};
+use crate::views::{view_entries_tree_name, view_invalidated_docs_tree_name, ViewEntry};
+use crate::{Database, Error};
+
+/// A problem found while verifying the integrity of a database. See
+/// [`Database::verify_integrity()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityProblem {
+    /// A stored document could not be deserialized.
+    CorruptDocument {
+        /// The collection the document is stored in.
+        collection: CollectionName,
+        /// The key the document is stored under.
+        key: Vec<u8>,
+        /// The deserialization error.
+        error: String,
+    },
+    /// A stored document's header contains a different id than the key it is
+    /// stored under.
+    MismatchedDocumentId {
+        /// The collection the document is stored in.
+        collection: CollectionName,
+        /// The key the document is stored under.
+        key: Vec<u8>,
+        /// The header stored in the document.
+        header: Header,
+    },
+    /// A view entry could not be deserialized.
+    CorruptViewEntry {
+        /// The view's name.
+        view: ViewName,
+        /// The key of the view entry.
+        key: Vec<u8>,
+        /// The deserialization error.
+        error: String,
+    },
+    /// A view entry contains a mapping from a document that does not exist
+    /// and is not waiting to be re-mapped.
+    MissingViewSource {
+        /// The view's name.
+        view: ViewName,
+        /// The key of the view entry.
+        key: Vec<u8>,
+        /// The header of the missing source document.
+        source: Header,
+    },
+    /// A key-value entry could not be deserialized, or it could not be
+    /// loaded.
+    CorruptKeyValueEntry {
+        /// The full key of the entry, including its namespace.
+        key: String,
+        /// The error encountered while loading the entry.
+        error: String,
+    },
+}
+
+impl Display for IntegrityProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CorruptDocument {
+                collection,
+                key,
+                error,
+            } => write!(f, "document {key:?} in {collection} is corrupt: {error}"),
+            Self::MismatchedDocumentId {
+                collection,
+                key,
+                header,
+            } => write!(
+                f,
+                "document {key:?} in {collection} has a header for document {}",
+                header.id
+            ),
+            Self::CorruptViewEntry { view, key, error } => {
+                write!(f, "view entry {key:?} in {view} is corrupt: {error}")
+            }
+            Self::MissingViewSource { view, key, source } => write!(
+                f,
+                "view entry {key:?} in {view} maps missing document {}",
+                source.id
+            ),
+            Self::CorruptKeyValueEntry { key, error } => {
+                write!(f, "key-value entry {key:?} is corrupt: {error}")
+            }
+        }
+    }
+}
+
+impl Database {
+    /// Scans every document, view, and key-value tree in this database,
+    /// returning each problem found. No data is modified, including corrupt
+    /// key-value entries that would otherwise be handled by the configured
+    /// [`CorruptKeyValuePolicy`](crate::config::CorruptKeyValuePolicy).
+    ///
+    /// Views are checked against the data that has been indexed so far. A
+    /// view entry that maps a deleted document is only reported if the
+    /// document is not waiting to be re-mapped. Entries in deletion history
+    /// views are expected to map deleted documents, and are only checked for
+    /// corruption.
+    pub fn verify_integrity(&self) -> Result<Vec<IntegrityProblem>, bonsaidb_core::Error> {
+        self.check_permission(
+            database_resource_name(self.name()),
+            &BonsaiAction::Database(DatabaseAction::Compact),
+        )?;
+        let mut problems = Vec::new();
+        for collection in self.schematic().collections() {
+            self.verify_documents(collection, &mut problems)?;
+        }
+        for view in self.schematic().views() {
+            self.verify_view(view, &mut problems)?;
+        }
+        self.verify_key_value_entries(&mut problems)?;
+
+        Ok(problems)
+    }
+
+    fn verify_documents(
+        &self,
+        collection: &CollectionName,
+        problems: &mut Vec<IntegrityProblem>,
+    ) -> Result<(), Error> {
+        let documents = self.roots().tree(
+            self.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?,
+        )?;
+        documents.scan::<Error, _, _, _, _>(
+            &(..),
+            true,
+            |_, _, _| ScanEvaluation::ReadData,
+            |_, _| ScanEvaluation::ReadData,
+            |key, _, bytes: ArcBytes<'static>| {
+                match deserialize_document(&bytes) {
+                    Ok(document) if document.header.id.as_ref() != &key[..] => {
+                        problems.push(IntegrityProblem::MismatchedDocumentId {
+                            collection: collection.clone(),
+                            key: key.to_vec(),
+                            header: document.header,
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(err) => problems.push(IntegrityProblem::CorruptDocument {
+                        collection: collection.clone(),
+                        key: key.to_vec(),
+                        error: err.to_string(),
+                    }),
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn verify_view(
+        &self,
+        view: &dyn view::Serialized,
+        problems: &mut Vec<IntegrityProblem>,
+    ) -> Result<(), Error> {
+        let collection = view.collection();
+        let view_name = view.view_name();
+        let documents = self.roots().tree(
+            self.collection_tree::<Versioned, _>(&collection, document_tree_name(&collection))?,
+        )?;
+        let invalidated_documents = self.roots().tree(self.collection_tree::<Unversioned, _>(
+            &collection,
+            view_invalidated_docs_tree_name(&view_name),
+        )?)?;
+        let view_entries = self.roots().tree(self.collection_tree::<Unversioned, _>(
+            &collection,
+            view_entries_tree_name(&view_name),
+        )?)?;
+        view_entries.scan::<Error, _, _, _, _>(
+            &(..),
+            true,
+            |_, _, _| ScanEvaluation::ReadData,
+            |_, _| ScanEvaluation::ReadData,
+            |key, _, bytes: ArcBytes<'static>| {
+                let entry = match bincode::deserialize::<ViewEntry>(&bytes) {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        problems.push(IntegrityProblem::CorruptViewEntry {
+                            view: view_name.clone(),
+                            key: key.to_vec(),
+                            error: err.to_string(),
+                        });
+                        return Ok(());
+                    }
+                };
+                if view.deletion_history() {
+                    return Ok(());
+                }
+                for mapping in entry.mappings {
+                    let id = mapping.source.id.as_ref();
+                    let exists = documents
+                        .get(id)
+                        .map_err(|err| AbortError::Other(Error::from(err)))?
+                        .is_some();
+                    let invalidated = invalidated_documents
+                        .get(id)
+                        .map_err(|err| AbortError::Other(Error::from(err)))?
+                        .is_some();
+                    if !exists && !invalidated {
+                        problems.push(IntegrityProblem::MissingViewSource {
+                            view: view_name.clone(),
+                            key: key.to_vec(),
+                            source: mapping.source,
+                        });
+                    }
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(())
+    }
+}
@@ -59,6 +59,11 @@ pub enum Error {
     #[error("a concurrency error ocurred: {0}")]
     TaskJoin(#[from] tokio::task::JoinError),
 
+    /// The storage's lock is held by another instance, and it wasn't released
+    /// within [`StorageConfiguration::lock_wait`](crate::config::StorageConfiguration::lock_wait).
+    #[error("the storage is locked by another instance")]
+    StorageLocked,
+
     /// An io error occurred.
     #[error("an IO error occurred: {0}")]
     Io(#[from] std::io::Error),
@@ -23,6 +23,7 @@ pub mod config;
 mod database;
 mod error;
 mod open_trees;
+mod slow_operations;
 mod storage;
 mod tasks;
 #[cfg(feature = "encryption")]
@@ -34,8 +35,12 @@ pub use argon2;
 #[cfg(not(feature = "included-from-omnibus"))]
 pub use bonsaidb_core as core;
 
+pub use self::database::compat::decode_executed_transaction;
+pub use self::database::keyvalue::{
+    KeyValueEntry, KvReplicationError, KvReplicationSink, ReplicatedKey, REPLICATION_BUFFER_SIZE,
+};
 pub use self::database::pubsub::Subscriber;
-pub use self::database::{Database, DatabaseNonBlocking};
+pub use self::database::{Database, DatabaseNonBlocking, IntegrityProblem};
 pub use self::error::Error;
 pub use self::storage::{BackupLocation, Storage, StorageId, StorageNonBlocking};
 
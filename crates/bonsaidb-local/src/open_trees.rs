@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use bonsaidb_core::schema::{CollectionName, Schematic};
+use bonsaidb_core::schema::{CollectionName, Schematic, ViewName};
 use nebari::io::any::AnyFile;
 use nebari::tree::{AnyTreeRoot, Root, Unversioned, Versioned};
 
@@ -8,7 +8,8 @@ use crate::database::document_tree_name;
 #[cfg(any(feature = "encryption", feature = "compression"))]
 use crate::storage::TreeVault;
 use crate::views::{
-    view_document_map_tree_name, view_entries_tree_name, view_invalidated_docs_tree_name,
+    view_dependencies_tree_name, view_document_map_tree_name, view_entries_tree_name,
+    view_invalidated_docs_tree_name, view_reductions_tree_name,
 };
 
 #[derive(Default)]
@@ -54,7 +55,7 @@ impl OpenTrees {
 
         for view in schema.views_in_collection(collection) {
             let view_name = view.view_name();
-            if view.update_policy().is_eager() {
+            if view.update_policy().is_eager() || view.deletion_history() {
                 self.open_tree::<Unversioned>(
                     &view_document_map_tree_name(&view_name),
                     #[cfg(any(feature = "encryption", feature = "compression"))]
@@ -65,6 +66,13 @@ impl OpenTrees {
                     #[cfg(any(feature = "encryption", feature = "compression"))]
                     vault.clone(),
                 );
+                if view.incremental_reduce() {
+                    self.open_tree::<Unversioned>(
+                        &view_reductions_tree_name(&view_name),
+                        #[cfg(any(feature = "encryption", feature = "compression"))]
+                        vault.clone(),
+                    );
+                }
             } else {
                 self.open_tree::<Unversioned>(
                     &view_invalidated_docs_tree_name(&view_name),
@@ -74,4 +82,21 @@ impl OpenTrees {
             }
         }
     }
+
+    pub fn open_trees_for_related_view(
+        &mut self,
+        view_name: &ViewName,
+        #[cfg(any(feature = "encryption", feature = "compression"))] vault: Option<TreeVault>,
+    ) {
+        self.open_tree::<Unversioned>(
+            &view_dependencies_tree_name(view_name),
+            #[cfg(any(feature = "encryption", feature = "compression"))]
+            vault.clone(),
+        );
+        self.open_tree::<Unversioned>(
+            &view_invalidated_docs_tree_name(view_name),
+            #[cfg(any(feature = "encryption", feature = "compression"))]
+            vault,
+        );
+    }
 }
@@ -0,0 +1,81 @@
+use std::time::{Duration, Instant};
+
+/// Logs operations that take longer than
+/// [`StorageConfiguration::slow_operation_threshold`](crate::config::StorageConfiguration#structfield.slow_operation_threshold).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SlowOperationLog {
+    threshold: Option<Duration>,
+    #[cfg_attr(not(feature = "instrument"), allow(dead_code))]
+    level: log::Level,
+}
+
+impl Default for SlowOperationLog {
+    fn default() -> Self {
+        Self {
+            threshold: None,
+            level: log::Level::Warn,
+        }
+    }
+}
+
+impl SlowOperationLog {
+    pub fn new(threshold: Option<Duration>, level: log::Level) -> Self {
+        Self { threshold, level }
+    }
+
+    /// Starts timing `operation`. The operation is logged when the returned
+    /// timer is dropped if it took longer than the threshold.
+    pub fn start(&self, operation: &'static str) -> OperationTimer {
+        OperationTimer {
+            log: *self,
+            operation,
+            started: self.threshold.map(|_| Instant::now()),
+        }
+    }
+
+    #[cfg(feature = "instrument")]
+    fn record(&self, operation: &'static str, elapsed: Duration) {
+        use tracing::Level;
+
+        match self.level {
+            log::Level::Error => {
+                tracing::event!(Level::ERROR, operation, ?elapsed, "slow operation");
+            }
+            log::Level::Warn => {
+                tracing::event!(Level::WARN, operation, ?elapsed, "slow operation");
+            }
+            log::Level::Info => {
+                tracing::event!(Level::INFO, operation, ?elapsed, "slow operation");
+            }
+            log::Level::Debug => {
+                tracing::event!(Level::DEBUG, operation, ?elapsed, "slow operation");
+            }
+            log::Level::Trace => {
+                tracing::event!(Level::TRACE, operation, ?elapsed, "slow operation");
+            }
+        }
+    }
+
+    #[cfg(not(feature = "instrument"))]
+    #[allow(clippy::unused_self)]
+    fn record(&self, _operation: &'static str, _elapsed: Duration) {}
+}
+
+/// Times an operation started with [`SlowOperationLog::start()`].
+#[must_use]
+pub(crate) struct OperationTimer {
+    log: SlowOperationLog,
+    operation: &'static str,
+    started: Option<Instant>,
+}
+
+impl Drop for OperationTimer {
+    fn drop(&mut self) {
+        if let (Some(started), Some(threshold)) = (self.started, self.log.threshold) {
+            let elapsed = started.elapsed();
+            if elapsed >= threshold {
+                self.log.record(self.operation, elapsed);
+            }
+        }
+    }
+}
@@ -1,11 +1,12 @@
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Debug, Display};
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use bonsaidb_core::admin::database::{self, ByName, Database as DatabaseRecord};
 use bonsaidb_core::admin::user::User;
@@ -19,6 +20,7 @@ use bonsaidb_core::connection::{
 use bonsaidb_core::document::CollectionDocument;
 #[cfg(any(feature = "encryption", feature = "compression"))]
 use bonsaidb_core::document::KeyId;
+use bonsaidb_core::keyvalue::Timestamp;
 use bonsaidb_core::permissions::bonsai::{
     bonsaidb_resource_name, database_resource_name, role_resource_name, user_resource_name,
     BonsaiAction, ServerAction,
@@ -37,13 +39,22 @@ use rand::{thread_rng, Rng};
 
 #[cfg(feature = "compression")]
 use crate::config::Compression;
-use crate::config::{KeyValuePersistence, StorageConfiguration};
-use crate::database::Context;
+use crate::config::{
+    CorruptKeyValuePolicy, KeyValuePersistence, KeyValueWorker, StorageConfiguration,
+    TransactionLogRetention,
+};
+use crate::database::{
+    keyvalue, store_schema_version, stored_schema_version, transaction_log, Context,
+};
+use crate::slow_operations::SlowOperationLog;
 use crate::tasks::manager::Manager;
+#[cfg(feature = "async")]
+use crate::tasks::BlockingPool;
 use crate::tasks::TaskManager;
 #[cfg(feature = "encryption")]
 use crate::vault::{self, LocalVaultKeyStorage, Vault};
-use crate::{Database, Error};
+use crate::views::result_cache::ViewResultCache;
+use crate::{Database, Error, IntegrityProblem};
 
 #[cfg(feature = "password-hashing")]
 mod argon;
@@ -194,8 +205,7 @@ impl Drop for AuthenticatedSession {
         if let Some(id) = session.id.take() {
             if let Some(storage) = self.storage.upgrade() {
                 // Deregister the session id once dropped.
-                let mut sessions = storage.sessions.write();
-                sessions.sessions.remove(&id);
+                storage.sessions.write().sessions.remove(&id);
 
                 // Remove all subscribers.
                 let mut sessions = storage.subscribers.write();
@@ -207,6 +217,12 @@ impl Drop for AuthenticatedSession {
                 {
                     sessions.subscribers.remove(&id);
                 }
+                drop(sessions);
+
+                // Forget the writes tracked for the session's reads.
+                for context in storage.open_roots.lock().values() {
+                    context.forget_session_writes(id);
+                }
             }
         }
     }
@@ -236,7 +252,9 @@ impl From<StorageInstance> for Storage {
 struct Data {
     lock: StorageLock,
     path: PathBuf,
+    shard_paths: Vec<PathBuf>,
     parallelization: usize,
+    view_rebuild_workers: usize,
     threadpool: ThreadPool<AnyFile>,
     file_manager: AnyFileManager,
     pub(crate) tasks: TaskManager,
@@ -256,8 +274,19 @@ struct Data {
     #[cfg(any(feature = "compression", feature = "encryption"))]
     tree_vault: Option<TreeVault>,
     pub(crate) key_value_persistence: KeyValuePersistence,
+    corrupt_key_value_policy: CorruptKeyValuePolicy,
+    /// The worker servicing every database's key-value store, if databases
+    /// don't spawn their own worker.
+    key_value_worker: Option<keyvalue::SharedWorker>,
+    transaction_log_retention: TransactionLogRetention,
+    list_transactions_max_results: u32,
+    max_document_size: usize,
+    slow_operation_log: SlowOperationLog,
+    #[cfg(feature = "async")]
+    blocking_pool: Option<BlockingPool>,
     chunk_cache: ChunkCache,
     pub(crate) check_view_integrity_on_database_open: bool,
+    view_result_cache: Option<ViewResultCache>,
     relay: Relay,
 }
 
@@ -268,6 +297,7 @@ impl Storage {
             .path
             .clone()
             .unwrap_or_else(|| PathBuf::from("db.bonsaidb"));
+        Self::check_write_ahead_log(&configuration)?;
         let file_manager = if configuration.memory_only {
             AnyFileManager::memory()
         } else {
@@ -281,6 +311,9 @@ impl Storage {
         let tasks = TaskManager::new(manager);
 
         fs::create_dir_all(&owned_path)?;
+        for shard_path in &configuration.shard_paths {
+            fs::create_dir_all(shard_path)?;
+        }
 
         let storage_lock = Self::lookup_or_create_id(&configuration, &owned_path)?;
 
@@ -302,8 +335,25 @@ impl Storage {
         };
 
         let parallelization = configuration.workers.parallelization;
+        let view_rebuild_workers = configuration.views.rebuild_workers.max(1);
         let check_view_integrity_on_database_open = configuration.views.check_integrity_on_open;
+        let view_result_cache = (configuration.views.result_cache_size > 0)
+            .then(|| ViewResultCache::new(configuration.views.result_cache_size));
         let key_value_persistence = configuration.key_value_persistence;
+        let corrupt_key_value_policy = configuration.corrupt_key_value_policy;
+        let key_value_worker = match configuration.key_value_worker {
+            KeyValueWorker::PerDatabase => None,
+            KeyValueWorker::Shared => Some(keyvalue::SharedWorker::spawn()),
+        };
+        let transaction_log_retention = configuration.transaction_log_retention;
+        let list_transactions_max_results = configuration.list_transactions_max_results;
+        let max_document_size = configuration.max_document_size;
+        let slow_operation_log = SlowOperationLog::new(
+            configuration.slow_operation_threshold,
+            configuration.slow_operation_level,
+        );
+        #[cfg(feature = "async")]
+        let blocking_pool = configuration.blocking_threads.map(BlockingPool::new);
         #[cfg(feature = "password-hashing")]
         let argon = argon::Hasher::new(configuration.argon);
         #[cfg(feature = "encryption")]
@@ -327,6 +377,7 @@ impl Storage {
                     lock: storage_lock,
                     tasks,
                     parallelization,
+                    view_rebuild_workers,
                     subscribers: Arc::default(),
                     authenticated_permissions,
                     sessions: RwLock::default(),
@@ -339,6 +390,7 @@ impl Storage {
                     #[cfg(any(feature = "compression", feature = "encryption"))]
                     tree_vault,
                     path: owned_path,
+                    shard_paths: configuration.shard_paths,
                     file_manager,
                     chunk_cache: ChunkCache::new(2000, 160_384),
                     threadpool: ThreadPool::new(parallelization),
@@ -346,7 +398,16 @@ impl Storage {
                     available_databases: RwLock::default(),
                     open_roots: Mutex::default(),
                     key_value_persistence,
+                    corrupt_key_value_policy,
+                    key_value_worker,
+                    transaction_log_retention,
+                    list_transactions_max_results,
+                    max_document_size,
+                    slow_operation_log,
+                    #[cfg(feature = "async")]
+                    blocking_pool,
                     check_view_integrity_on_database_open,
+                    view_result_cache,
                     relay: Relay::default(),
                 }),
             },
@@ -358,6 +419,8 @@ impl Storage {
 
         storage.create_admin_database_if_needed()?;
 
+        storage.upgrade_schema_versions(None)?;
+
         Ok(storage)
     }
 
@@ -369,6 +432,30 @@ impl Storage {
             .database_without_schema(&name, Some(self), None)
     }
 
+    /// Returns an error if the key-value write-ahead log is enabled alongside
+    /// an option that its segments can't honor. Segments are written directly
+    /// to the filesystem, so they would be unencrypted and outlive a
+    /// memory-only storage.
+    fn check_write_ahead_log(configuration: &StorageConfiguration) -> Result<(), Error> {
+        if !configuration.key_value_persistence.uses_write_ahead_log() {
+            return Ok(());
+        }
+        if configuration.memory_only {
+            return Err(Error::other(
+                "key-value",
+                "the write-ahead log can't be used with memory-only storage",
+            ));
+        }
+        #[cfg(feature = "encryption")]
+        if configuration.default_encryption_key.is_some() {
+            return Err(Error::other(
+                "key-value",
+                "the write-ahead log can't be used with a default encryption key",
+            ));
+        }
+        Ok(())
+    }
+
     fn lookup_or_create_id(
         configuration: &StorageConfiguration,
         path: &Path,
@@ -395,7 +482,7 @@ impl Storage {
                 file.write_all(id.as_bytes())?;
                 file
             };
-            file.lock_exclusive()?;
+            lock_storage_file(&file, configuration.lock_wait)?;
             (id, file)
         } else {
             // Load/Store a randomly generated id into a file. While the value
@@ -406,7 +493,7 @@ impl Storage {
                 // This value is important enought to not allow launching the
                 // server if the file can't be read or contains unexpected data.
                 let mut file = File::open(id_path)?;
-                file.lock_exclusive()?;
+                lock_storage_file(&file, configuration.lock_wait)?;
                 let mut bytes = Vec::new();
                 file.read_to_end(&mut bytes)?;
                 let existing_id =
@@ -416,7 +503,7 @@ impl Storage {
             } else {
                 let id = { thread_rng().gen::<u64>() };
                 let mut file = File::create(id_path)?;
-                file.lock_exclusive()?;
+                lock_storage_file(&file, configuration.lock_wait)?;
 
                 file.write_all(id.to_string().as_bytes())?;
 
@@ -439,6 +526,35 @@ impl Storage {
         Ok(())
     }
 
+    /// Checks the stored schema version of each database whose schema is
+    /// registered, limited to databases using `schema` if provided. Databases
+    /// last opened with a newer version of their schema are refused, and
+    /// databases last opened with an older version are migrated.
+    fn upgrade_schema_versions(&self, schema: Option<&SchemaName>) -> Result<(), Error> {
+        let databases = self
+            .instance
+            .data
+            .available_databases
+            .read()
+            .iter()
+            .filter(|(_, database_schema)| schema.map_or(true, |schema| *database_schema == schema))
+            .map(|(name, schema)| (name.clone(), schema.clone()))
+            .collect::<Vec<_>>();
+        for (name, schema) in databases {
+            let opener = match self.instance.data.schemas.read().get(&schema) {
+                Some(opener) => opener.clone(),
+                None => continue,
+            };
+            let stored_version = stored_schema_version(&self.instance.open_roots(&name)?.roots)?;
+            if stored_version != opener.schematic().version {
+                self.instance
+                    .database_without_schema(&name, Some(self), None)?
+                    .upgrade_schema_version(opener.migration())?;
+            }
+        }
+        Ok(())
+    }
+
     fn create_admin_database_if_needed(&self) -> Result<(), Error> {
         self.register_schema::<Admin>()?;
         match self.database::<Admin>(ADMIN_DATABASE_NAME) {
@@ -469,6 +585,11 @@ impl Storage {
         self.instance.data.parallelization
     }
 
+    #[must_use]
+    pub(crate) fn view_rebuild_workers(&self) -> usize {
+        self.instance.data.view_rebuild_workers
+    }
+
     #[must_use]
     #[cfg(feature = "encryption")]
     pub(crate) fn vault(&self) -> &Arc<Vault> {
@@ -494,7 +615,9 @@ impl Storage {
         None
     }
 
-    /// Registers a schema for use within the server.
+    /// Registers a schema for use within the server. The schema versions of
+    /// existing databases using the schema are checked as they are when the
+    /// storage is opened.
     pub fn register_schema<DB: Schema>(&self) -> Result<(), Error> {
         let mut schemas = self.instance.data.schemas.write();
         if schemas
@@ -504,7 +627,8 @@ impl Storage {
             )
             .is_none()
         {
-            Ok(())
+            drop(schemas);
+            self.upgrade_schema_versions(Some(&DB::schema_name()))
         } else {
             Err(Error::Core(bonsaidb_core::Error::SchemaAlreadyRegistered(
                 DB::schema_name(),
@@ -512,6 +636,99 @@ impl Storage {
         }
     }
 
+    /// Removes all expired keys from the key-value stores of every database
+    /// in this storage, returning the number of keys removed from each
+    /// database.
+    ///
+    /// Expired keys are normally removed in the background. After a period
+    /// of downtime, this function can be used to remove keys that expired
+    /// while the storage was not open without waiting for each database to be
+    /// used. It is safe to call while the storage is in use.
+    pub fn vacuum_expired_across_databases(&self) -> Result<BTreeMap<String, usize>, Error> {
+        let databases = {
+            self.instance
+                .data
+                .available_databases
+                .read()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let mut removed = BTreeMap::new();
+        for name in databases {
+            let database = self
+                .instance
+                .database_without_schema(&name, Some(self), None)?;
+            removed.insert(name, database.sweep_expired()?);
+        }
+
+        Ok(removed)
+    }
+
+    /// Removes all transactions executed before `executed_before` from the
+    /// transaction log of every database in this storage, returning the
+    /// number of transactions removed from each database. See
+    /// [`Database::prune_transaction_log_executed_before()`] for more
+    /// information.
+    pub fn prune_transaction_logs_executed_before(
+        &self,
+        executed_before: Timestamp,
+    ) -> Result<BTreeMap<String, u64>, Error> {
+        let databases = {
+            self.instance
+                .data
+                .available_databases
+                .read()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let mut removed = BTreeMap::new();
+        for name in databases {
+            let database = self
+                .instance
+                .database_without_schema(&name, Some(self), None)?;
+            removed.insert(
+                name,
+                database.prune_transaction_log_executed_before(executed_before)?,
+            );
+        }
+
+        Ok(removed)
+    }
+
+    /// Verifies the integrity of every database in this storage, returning the
+    /// problems found in each database. Databases without any problems are
+    /// not included in the result. See [`Database::verify_integrity()`] for
+    /// more information. No data is modified, and it is safe to call while
+    /// the storage is in use.
+    pub fn verify(&self) -> Result<BTreeMap<String, Vec<IntegrityProblem>>, Error> {
+        let databases = {
+            self.instance
+                .data
+                .available_databases
+                .read()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        let mut problems = BTreeMap::new();
+        for name in databases {
+            let database = self
+                .instance
+                .database_without_schema(&name, Some(self), None)?;
+            let database_problems = database.verify_integrity()?;
+            if !database_problems.is_empty() {
+                problems.insert(name, database_problems);
+            }
+        }
+
+        Ok(problems)
+    }
+
     fn validate_name(name: &str) -> Result<(), Error> {
         if name.chars().enumerate().all(|(index, c)| {
             c.is_ascii_alphanumeric()
@@ -594,7 +811,9 @@ impl Debug for Data {
         let mut f = f.debug_struct("Data");
         f.field("lock", &self.lock)
             .field("path", &self.path)
+            .field("shard_paths", &self.shard_paths)
             .field("parallelization", &self.parallelization)
+            .field("view_rebuild_workers", &self.view_rebuild_workers)
             .field("threadpool", &self.threadpool)
             .field("file_manager", &self.file_manager)
             .field("tasks", &self.tasks)
@@ -604,11 +823,21 @@ impl Debug for Data {
             .field("sessions", &self.sessions)
             .field("subscribers", &self.subscribers)
             .field("key_value_persistence", &self.key_value_persistence)
+            .field("corrupt_key_value_policy", &self.corrupt_key_value_policy)
+            .field("key_value_worker", &self.key_value_worker)
+            .field("transaction_log_retention", &self.transaction_log_retention)
+            .field(
+                "list_transactions_max_results",
+                &self.list_transactions_max_results,
+            )
+            .field("max_document_size", &self.max_document_size)
+            .field("slow_operation_log", &self.slow_operation_log)
             .field("chunk_cache", &self.chunk_cache)
             .field(
                 "check_view_integrity_on_database_open",
                 &self.check_view_integrity_on_database_open,
             )
+            .field("view_result_cache", &self.view_result_cache)
             .field("relay", &self.relay);
 
         if let Some(schemas) = self.schemas.try_read() {
@@ -619,6 +848,8 @@ impl Debug for Data {
             f.field("schemas", &"RwLock locked");
         }
 
+        #[cfg(feature = "async")]
+        f.field("blocking_pool", &self.blocking_pool);
         #[cfg(feature = "password-hashing")]
         f.field("argon", &self.argon);
         #[cfg(feature = "encryption")]
@@ -634,6 +865,30 @@ impl Debug for Data {
 }
 
 impl StorageInstance {
+    /// Returns the directory that the database named `name` is stored in.
+    ///
+    /// If the database already exists in one of the storage's directories,
+    /// that location is returned. Otherwise, a directory is chosen by hashing
+    /// the name.
+    pub(crate) fn database_path(&self, name: &str) -> PathBuf {
+        if self.data.shard_paths.is_empty() {
+            return self.data.path.join(name);
+        }
+
+        let directories = std::iter::once(&self.data.path)
+            .chain(&self.data.shard_paths)
+            .collect::<Vec<_>>();
+        if let Some(existing) = directories
+            .iter()
+            .map(|directory| directory.join(name))
+            .find(|path| path.exists())
+        {
+            return existing;
+        }
+
+        directories[shard_index(name, directories.len())].join(name)
+    }
+
     #[cfg_attr(
         not(any(feature = "encryption", feature = "compression")),
         allow(unused_mut)
@@ -645,7 +900,7 @@ impl StorageInstance {
         } else {
             let task_name = name.to_string();
 
-            let mut config = nebari::Config::new(self.data.path.join(task_name))
+            let mut config = nebari::Config::new(self.database_path(&task_name))
                 .file_manager(self.data.file_manager.clone())
                 .cache(self.data.chunk_cache.clone())
                 .shared_thread_pool(&self.data.threadpool);
@@ -656,11 +911,26 @@ impl StorageInstance {
             }
 
             let roots = config.open().map_err(Error::from)?;
-            let context = Context::new(
-                roots,
-                self.data.key_value_persistence.clone(),
-                Some(self.data.lock.clone()),
-            );
+            keyvalue::migrate_key_encoding(&roots)?;
+            transaction_log::copy_legacy_entries(&roots)?;
+            keyvalue::replay_write_ahead_log(&roots, &self.data.key_value_persistence)?;
+            let context = if let Some(key_value_worker) = &self.data.key_value_worker {
+                Context::with_shared_worker(
+                    roots,
+                    self.data.key_value_persistence.clone(),
+                    self.data.corrupt_key_value_policy,
+                    Some(self.data.lock.clone()),
+                    key_value_worker,
+                )
+            } else {
+                Context::new(
+                    roots,
+                    self.data.key_value_persistence.clone(),
+                    self.data.corrupt_key_value_policy,
+                    Some(self.data.lock.clone()),
+                )
+            };
+            context.log_slow_operations(self.data.slow_operation_log);
 
             open_roots.insert(name.to_owned(), context.clone());
 
@@ -676,6 +946,31 @@ impl StorageInstance {
         self.data.check_view_integrity_on_database_open
     }
 
+    pub(crate) fn transaction_log_retention(&self) -> &TransactionLogRetention {
+        &self.data.transaction_log_retention
+    }
+
+    pub(crate) fn list_transactions_max_results(&self) -> u32 {
+        self.data.list_transactions_max_results
+    }
+
+    pub(crate) fn max_document_size(&self) -> usize {
+        self.data.max_document_size
+    }
+
+    pub(crate) fn slow_operation_log(&self) -> &SlowOperationLog {
+        &self.data.slow_operation_log
+    }
+
+    pub(crate) fn view_result_cache(&self) -> Option<&ViewResultCache> {
+        self.data.view_result_cache.as_ref()
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn blocking_pool(&self) -> Option<&BlockingPool> {
+        self.data.blocking_pool.as_ref()
+    }
+
     pub(crate) fn relay(&self) -> &'_ Relay {
         &self.data.relay
     }
@@ -911,11 +1206,17 @@ impl StorageInstance {
 
 pub trait DatabaseOpener: Send + Sync {
     fn schematic(&self) -> &'_ Schematic;
+    fn migration(&self) -> Option<&SchemaMigration>;
     fn open(&self, name: String, storage: &Storage) -> Result<Database, Error>;
 }
 
+/// Migrates a database from the schema version it was last opened with.
+pub(crate) type SchemaMigration =
+    dyn Fn(&Database, u64) -> Result<(), bonsaidb_core::Error> + Send + Sync;
+
 pub struct StorageSchemaOpener<DB: Schema> {
     schematic: Schematic,
+    migration: Option<Box<SchemaMigration>>,
     _phantom: PhantomData<DB>,
 }
 
@@ -927,9 +1228,18 @@ where
         let schematic = DB::schematic()?;
         Ok(Self {
             schematic,
+            migration: None,
             _phantom: PhantomData::default(),
         })
     }
+
+    pub fn with_migration<F>(mut self, migration: F) -> Self
+    where
+        F: Fn(&Database, u64) -> Result<(), bonsaidb_core::Error> + Send + Sync + 'static,
+    {
+        self.migration = Some(Box::new(migration));
+        self
+    }
 }
 
 impl<DB> DatabaseOpener for StorageSchemaOpener<DB>
@@ -940,6 +1250,10 @@ where
         &self.schematic
     }
 
+    fn migration(&self) -> Option<&SchemaMigration> {
+        self.migration.as_deref()
+    }
+
     fn open(&self, name: String, storage: &Storage) -> Result<Database, Error> {
         let roots = storage.instance.open_roots(&name)?;
         let db = Database::new::<DB, _>(name, roots, storage)?;
@@ -982,12 +1296,13 @@ impl StorageConnection for StorageInstance {
     ) -> Result<(), bonsaidb_core::Error> {
         Storage::validate_name(name)?;
 
-        {
+        let version = {
             let schemas = self.data.schemas.read();
-            if !schemas.contains_key(&schema) {
+            let Some(opener) = schemas.get(&schema) else {
                 return Err(bonsaidb_core::Error::SchemaNotRegistered(schema));
-            }
-        }
+            };
+            opener.schematic().version
+        };
 
         let mut available_databases = self.data.available_databases.write();
         let admin = self.admin();
@@ -998,6 +1313,11 @@ impl StorageConnection for StorageInstance {
                     name: name.to_string(),
                     schema: schema.clone(),
                 })?;
+            // New databases don't need to be migrated, so they start at the
+            // current version of their schema.
+            if version > 0 {
+                store_schema_version(&self.open_roots(name)?.roots, version)?;
+            }
             available_databases.insert(name.to_string(), schema);
         } else if !only_if_needed {
             return Err(bonsaidb_core::Error::DatabaseNameAlreadyTaken(
@@ -1022,7 +1342,7 @@ impl StorageConnection for StorageInstance {
         let mut open_roots = self.data.open_roots.lock();
         open_roots.remove(name);
 
-        let database_folder = self.data.path.join(name);
+        let database_folder = self.database_path(name);
         if database_folder.exists() {
             let file_manager = self.data.file_manager.clone();
             file_manager
@@ -1781,6 +2101,48 @@ impl StorageLock {
     }
 }
 
+/// Returns the index of the directory that a new database named `name` is
+/// placed in. FNV-1a is used rather than the standard library's hasher because
+/// the placement must remain stable across processes and releases.
+#[allow(clippy::cast_possible_truncation)]
+fn shard_index(name: &str, directories: usize) -> usize {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for byte in name.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % directories as u64) as usize
+}
+
+/// The longest delay between attempts to acquire the storage's lock.
+const MAX_LOCK_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Acquires an exclusive lock on `file`. If `wait` is `None`, this blocks until
+/// the lock is acquired. Otherwise, acquiring the lock is retried with an
+/// increasing delay until `wait` has elapsed.
+fn lock_storage_file(file: &File, wait: Option<Duration>) -> Result<(), Error> {
+    let wait = match wait {
+        Some(wait) => wait,
+        None => return file.lock_exclusive().map_err(Error::from),
+    };
+    let deadline = Instant::now() + wait;
+    let mut delay = Duration::from_millis(10);
+    loop {
+        match file.try_lock_exclusive() {
+            Ok(()) => return Ok(()),
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => {}
+            Err(err) => return Err(Error::from(err)),
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::StorageLocked);
+        }
+        std::thread::sleep(delay.min(deadline - now));
+        delay = (delay * 2).min(MAX_LOCK_RETRY_DELAY);
+    }
+}
+
 #[derive(Debug)]
 struct LockData(File);
 
@@ -1,12 +1,15 @@
-use std::fs::DirEntry;
-use std::io::ErrorKind;
+use std::fs::{DirEntry, File};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
+use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::connection::{LowLevelConnection, Range, Sort, StorageConnection};
 use bonsaidb_core::document::DocumentId;
-use bonsaidb_core::schema::{Collection, Qualified, SchemaName};
+use bonsaidb_core::schema::{Collection, CollectionName, Qualified, SchemaName};
 use bonsaidb_core::transaction::{Operation, Transaction};
 use bonsaidb_core::{admin, AnyError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
 use crate::database::keyvalue::Entry;
 use crate::database::DatabaseNonBlocking;
@@ -97,6 +100,121 @@ impl Storage {
         Ok(())
     }
 
+    /// Exports the database named `name` to a single file at `path`.
+    ///
+    /// Unlike [`backup()`](Self::backup), only one database is exported. The
+    /// file contains the database's documents and key-value entries, and can
+    /// be imported into any storage with the database's schema registered
+    /// using [`import_database()`](Self::import_database). Views are not
+    /// exported, as they are rebuilt from the imported documents.
+    ///
+    /// The file is written one collection at a time, so only a single
+    /// collection's documents are held in memory at once.
+    pub fn export_database<P: AsRef<Path>>(&self, name: &str, path: P) -> Result<(), Error> {
+        let database = self
+            .instance
+            .database_without_schema(name, Some(self), None)?;
+        let mut file = BufWriter::new(File::create(path)?);
+        write_export_record(
+            &mut file,
+            &ExportHeader {
+                schema: database.schematic().name.clone(),
+            },
+        )?;
+        for collection in database.schematic().collections() {
+            for document in
+                database.list_from_collection(Range::from(..), Sort::Ascending, None, collection)?
+            {
+                write_export_record(
+                    &mut file,
+                    &ExportedObject::Document {
+                        collection: collection.clone(),
+                        id: document.header.id,
+                        contents: document.contents,
+                    },
+                )?;
+            }
+        }
+        for ((namespace, key), entry) in database.all_key_value_entries()? {
+            write_export_record(
+                &mut file,
+                &ExportedObject::KeyValue {
+                    namespace,
+                    key,
+                    entry,
+                },
+            )?;
+        }
+        file.into_inner()
+            .map_err(|err| Error::from(err.into_error()))?
+            .sync_all()?;
+        Ok(())
+    }
+
+    /// Imports a database previously exported using
+    /// [`export_database()`](Self::export_database) from the file at `path`,
+    /// creating it as a new database named `name`. The name does not need to
+    /// match the name of the exported database, allowing a database to be
+    /// cloned within the same storage.
+    ///
+    /// The exported database's schema must be registered with this storage,
+    /// and no database named `name` may exist. If the import fails, the
+    /// partially imported database is deleted.
+    pub fn import_database<P: AsRef<Path>>(&self, name: &str, path: P) -> Result<(), Error> {
+        let mut file = BufReader::new(File::open(path)?);
+        let header = read_export_record::<ExportHeader>(&mut file)?.ok_or_else(|| {
+            Error::other("import", "the file does not contain an exported database")
+        })?;
+        self.create_database_with_schema(name, header.schema, false)?;
+
+        let result = self
+            .instance
+            .database_without_schema(name, Some(self), None)
+            .and_then(|database| Self::import_objects(&database, &mut file));
+        if let Err(err) = result {
+            // Don't leave a partially imported database behind. The import's
+            // error is more useful than any error deleting the database.
+            drop(self.delete_database(name));
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Imports the objects following the header of an exported database.
+    /// Consecutive documents are inserted in transactions of up to
+    /// [`IMPORT_TRANSACTION_SIZE`] documents.
+    fn import_objects(database: &Database, file: &mut impl BufRead) -> Result<(), Error> {
+        // The Databases list is recreated as databases are created, so it
+        // isn't imported.
+        let database_collection = admin::Database::collection_name();
+        let mut transaction = Transaction::new();
+        while let Some(object) = read_export_record::<ExportedObject>(file)? {
+            match object {
+                ExportedObject::Document {
+                    collection,
+                    id,
+                    contents,
+                } => {
+                    if collection != database_collection {
+                        transaction.push(Operation::insert(collection, Some(id), contents));
+                        if transaction.operations.len() == IMPORT_TRANSACTION_SIZE {
+                            database.apply_transaction(std::mem::take(&mut transaction))?;
+                        }
+                    }
+                }
+                ExportedObject::KeyValue {
+                    namespace,
+                    key,
+                    entry,
+                } => entry.restore(namespace, key, database)?,
+            }
+        }
+        if !transaction.operations.is_empty() {
+            database.apply_transaction(transaction)?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn backup_database(
         database: &Database,
         location: &dyn AnyBackupLocation,
@@ -184,6 +302,55 @@ impl Storage {
     }
 }
 
+/// The maximum number of documents inserted by each transaction while
+/// importing a database.
+const IMPORT_TRANSACTION_SIZE: usize = 1_000;
+
+/// The first record of a file written by [`Storage::export_database()`].
+#[derive(Serialize, Deserialize)]
+struct ExportHeader {
+    schema: SchemaName,
+}
+
+/// A record following the [`ExportHeader`] of a file written by
+/// [`Storage::export_database()`].
+#[derive(Serialize, Deserialize)]
+enum ExportedObject {
+    Document {
+        collection: CollectionName,
+        id: DocumentId,
+        contents: Bytes,
+    },
+    KeyValue {
+        namespace: Option<String>,
+        key: String,
+        entry: Entry,
+    },
+}
+
+/// Writes `record` to an export, prefixed with its encoded length.
+fn write_export_record<T: Serialize>(file: &mut impl Write, record: &T) -> Result<(), Error> {
+    let record = pot::to_vec(record)?;
+    file.write_all(&(record.len() as u64).to_be_bytes())?;
+    file.write_all(&record)?;
+    Ok(())
+}
+
+/// Reads the next record written by [`write_export_record()`], returning
+/// `None` if the end of the export has been reached.
+fn read_export_record<T: DeserializeOwned>(file: &mut impl BufRead) -> Result<Option<T>, Error> {
+    if file.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut length = [0; 8];
+    file.read_exact(&mut length)?;
+    let length = usize::try_from(u64::from_be_bytes(length))
+        .map_err(|_| Error::other("import", "exported record is too large"))?;
+    let mut record = vec![0; length];
+    file.read_exact(&mut record)?;
+    Ok(Some(pot::from_slice(&record)?))
+}
+
 pub trait AnyBackupLocation: Send + Sync {
     fn store(
         &self,
@@ -441,9 +608,10 @@ fn container_folder(
 #[cfg(test)]
 mod tests {
     use bonsaidb_core::connection::{Connection as _, StorageConnection as _};
+    use bonsaidb_core::document::DocumentId;
     use bonsaidb_core::keyvalue::KeyValue;
     use bonsaidb_core::schema::SerializedCollection;
-    use bonsaidb_core::test_util::{Basic, TestDirectory};
+    use bonsaidb_core::test_util::{Basic, BasicByCategory, TestDirectory};
 
     use crate::config::{Builder, KeyValuePersistence, PersistenceThreshold, StorageConfiguration};
     use crate::Storage;
@@ -495,4 +663,154 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn sharded_backup_restore() -> anyhow::Result<()> {
+        const DATABASES: [&str; 4] = ["alpha", "beta", "gamma", "delta"];
+        let backup_destination = TestDirectory::new("sharded-backup-restore.bonsaidb.backup");
+
+        let (primary_ids, shard_ids) = {
+            let primary = TestDirectory::new("sharded-backup-restore.bonsaidb");
+            let shard = TestDirectory::new("sharded-backup-restore-shard.bonsaidb");
+            let storage = Storage::open(
+                StorageConfiguration::new(&primary)
+                    .shard_path(&shard)
+                    .with_schema::<Basic>()?,
+            )?;
+
+            let mut primary_ids = Vec::new();
+            let mut shard_ids = Vec::new();
+            for name in DATABASES {
+                let db = storage.create_database::<Basic>(name, false)?;
+                let doc = db.collection::<Basic>().push(&Basic::new(name))?;
+                db.set_key("name", &name).execute()?;
+
+                // Each database must be stored in exactly one of the directories.
+                let in_primary = primary.0.join(name).exists();
+                let in_shard = shard.0.join(name).exists();
+                assert_ne!(in_primary, in_shard);
+                if in_primary {
+                    primary_ids.push((name, doc.id));
+                } else {
+                    shard_ids.push((name, doc.id));
+                }
+            }
+            assert!(!primary_ids.is_empty());
+            assert!(!shard_ids.is_empty());
+
+            storage.backup(&backup_destination.0).unwrap();
+
+            (primary_ids, shard_ids)
+        };
+
+        let primary = TestDirectory::new("sharded-backup-restore.bonsaidb");
+        let shard = TestDirectory::new("sharded-backup-restore-shard.bonsaidb");
+        let restored_storage = Storage::open(
+            StorageConfiguration::new(&primary)
+                .shard_path(&shard)
+                .with_schema::<Basic>()?,
+        )?;
+        restored_storage.restore(&backup_destination.0).unwrap();
+
+        for ((name, id), directory) in primary_ids
+            .into_iter()
+            .map(|entry| (entry, &primary))
+            .chain(shard_ids.into_iter().map(|entry| (entry, &shard)))
+        {
+            // Placement is deterministic, so the restored database is stored in
+            // the same directory as the original.
+            assert!(directory.0.join(name).exists());
+            let db = restored_storage.database::<Basic>(name)?;
+            let doc = Basic::get(&id, &db)?.expect("Backed up document not found");
+            assert_eq!(doc.contents.value, name);
+            assert_eq!(db.get_key("name").into::<String>()?.as_deref(), Some(name));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_import_database() -> anyhow::Result<()> {
+        let export_directory = TestDirectory::new("export-import-database.export");
+        let export_path = export_directory.0.join("basic.bonsaidb-export");
+        std::fs::create_dir_all(&export_directory.0)?;
+
+        let (first_doc, second_doc) = {
+            let database_directory = TestDirectory::new("export-import-database-source.bonsaidb");
+            let storage = Storage::open(
+                StorageConfiguration::new(&database_directory).with_schema::<Basic>()?,
+            )?;
+            let db = storage.create_database::<Basic>("basic", false)?;
+            let first_doc = db
+                .collection::<Basic>()
+                .push(&Basic::new("first").with_category("a"))?;
+            let second_doc = db
+                .collection::<Basic>()
+                .push(&Basic::new("second").with_category("b"))?;
+            db.set_numeric_key("counter", 42_u64).execute()?;
+            db.with_key_namespace("namespace")
+                .set_key("name", &"basic")
+                .execute()?;
+
+            // Another database in the same storage isn't exported.
+            let other = storage.create_database::<Basic>("other", false)?;
+            other.collection::<Basic>().push(&Basic::new("other"))?;
+
+            storage.export_database("basic", &export_path)?;
+
+            // The export can be imported into the same storage under a new name.
+            storage.import_database("clone", &export_path)?;
+            let clone = storage.database::<Basic>("clone")?;
+            assert_eq!(
+                Basic::all(&clone).query()?.len(),
+                Basic::all(&db).query()?.len()
+            );
+            // Importing over an existing database fails.
+            assert!(storage.import_database("other", &export_path).is_err());
+
+            (first_doc, second_doc)
+        };
+
+        // The export can be imported into another storage under a new name.
+        let database_directory = TestDirectory::new("export-import-database-destination.bonsaidb");
+        let storage =
+            Storage::open(StorageConfiguration::new(&database_directory).with_schema::<Basic>()?)?;
+        storage.import_database("copy", &export_path)?;
+        assert!(storage.database::<Basic>("basic").is_err());
+        assert!(storage.database::<Basic>("other").is_err());
+
+        let db = storage.database::<Basic>("copy")?;
+        let docs = Basic::all(&db).query()?;
+        assert_eq!(docs.len(), 2);
+        let first = Basic::get(&first_doc.id, &db)?.expect("exported document not found");
+        assert_eq!(first.contents.value, "first");
+        let second = Basic::get(&second_doc.id, &db)?.expect("exported document not found");
+        assert_eq!(second.contents.value, "second");
+
+        // Views are rebuilt from the imported documents.
+        let mappings = db.view::<BasicByCategory>().with_key("a").query()?;
+        assert_eq!(mappings.len(), 1);
+        assert_eq!(mappings[0].source.id, DocumentId::from_u64(first_doc.id));
+
+        assert_eq!(db.get_key("counter").into_u64()?, Some(42));
+        assert_eq!(
+            db.with_key_namespace("namespace")
+                .get_key("name")
+                .into::<String>()?
+                .as_deref(),
+            Some("basic")
+        );
+
+        // A failed import doesn't leave a partially imported database behind.
+        let export = std::fs::read(&export_path)?;
+        let truncated_path = export_directory.0.join("truncated.bonsaidb-export");
+        std::fs::write(&truncated_path, &export[..export.len() - 1])?;
+        assert!(storage
+            .import_database("truncated", &truncated_path)
+            .is_err());
+        assert!(storage.database::<Basic>("truncated").is_err());
+        storage.import_database("truncated", &export_path)?;
+
+        Ok(())
+    }
 }
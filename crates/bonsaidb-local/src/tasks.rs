@@ -5,6 +5,7 @@ use std::sync::Arc;
 use bonsaidb_core::connection::Connection;
 use bonsaidb_core::keyvalue::Timestamp;
 use bonsaidb_core::schema::{view, CollectionName, ViewName};
+use nebari::tree::Unversioned;
 use parking_lot::RwLock;
 
 use crate::database::keyvalue::ExpirationLoader;
@@ -14,6 +15,7 @@ use crate::tasks::handle::Handle;
 use crate::tasks::manager::Manager;
 use crate::views::integrity_scanner::{IntegrityScan, IntegrityScanner, OptionalViewMapHandle};
 use crate::views::mapper::{Map, Mapper};
+use crate::views::view_versions_tree_name;
 use crate::Error;
 
 /// Types related to defining [`Job`]s.
@@ -24,9 +26,13 @@ mod traits;
 
 pub use self::traits::{Job, Keyed};
 
+#[cfg(feature = "async")]
+mod blocking;
 mod compactor;
 mod task;
 
+#[cfg(feature = "async")]
+pub use blocking::BlockingPool;
 pub use task::Task;
 
 #[derive(Debug, Clone)]
@@ -58,49 +64,69 @@ impl TaskManager {
         database: &Database,
         block_until_updated: bool,
     ) -> Result<(), crate::Error> {
-        let view_name = view.view_name();
         if let Some(job) = self.spawn_integrity_check(view, database) {
             job.receive()??;
         }
 
         // If there is no transaction id, there is no data, so the view is "up-to-date"
         if let Some(current_transaction_id) = database.last_transaction_id()? {
-            let needs_reindex = {
-                // When views finish updating, they store the last transaction_id
-                // they mapped. If that value is current, we don't need to go
-                // through the jobs system at all.
-                let statuses = self.statuses.read();
-                if let Some(last_transaction_indexed) = statuses.view_update_last_status.get(&(
-                    database.data.name.clone(),
-                    view.collection(),
-                    view.view_name(),
-                )) {
-                    last_transaction_indexed < &current_transaction_id
-                } else {
-                    true
+            self.update_view_through_transaction(
+                view,
+                database,
+                current_transaction_id,
+                block_until_updated,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Ensures `view` has been updated to include `transaction_id`, optionally
+    /// blocking until the mapper has indexed it. Unlike
+    /// [`update_view_if_needed()`](Self::update_view_if_needed), this does not
+    /// wait for transactions committed after `transaction_id`.
+    pub fn update_view_through_transaction(
+        &self,
+        view: &dyn view::Serialized,
+        database: &Database,
+        transaction_id: u64,
+        block_until_updated: bool,
+    ) -> Result<(), crate::Error> {
+        let view_name = view.view_name();
+        let needs_reindex = {
+            // When views finish updating, they store the last transaction_id
+            // they mapped. If that value is current, we don't need to go
+            // through the jobs system at all.
+            let statuses = self.statuses.read();
+            if let Some(last_transaction_indexed) = statuses.view_update_last_status.get(&(
+                database.data.name.clone(),
+                view.collection(),
+                view_name.clone(),
+            )) {
+                last_transaction_indexed < &transaction_id
+            } else {
+                true
+            }
+        };
+
+        if needs_reindex {
+            loop {
+                let job = self.jobs.lookup_or_enqueue(Mapper {
+                    database: database.clone(),
+                    map: Map {
+                        database: database.data.name.clone(),
+                        collection: view.collection(),
+                        view_name: view_name.clone(),
+                    },
+                });
+
+                if !block_until_updated {
+                    break;
                 }
-            };
-
-            if needs_reindex {
-                let wait_for_transaction = current_transaction_id;
-                loop {
-                    let job = self.jobs.lookup_or_enqueue(Mapper {
-                        database: database.clone(),
-                        map: Map {
-                            database: database.data.name.clone(),
-                            collection: view.collection(),
-                            view_name: view_name.clone(),
-                        },
-                    });
-
-                    if !block_until_updated {
-                        break;
-                    }
-
-                    let id = job.receive()??;
-                    if wait_for_transaction <= id {
-                        break;
-                    }
+
+                let id = job.receive()??;
+                if transaction_id <= id {
+                    break;
                 }
             }
         }
@@ -108,6 +134,48 @@ impl TaskManager {
         Ok(())
     }
 
+    pub fn last_indexed_transaction_id(
+        &self,
+        database: Arc<Cow<'static, str>>,
+        collection: CollectionName,
+        view_name: ViewName,
+    ) -> Option<u64> {
+        let statuses = self.statuses.read();
+        statuses
+            .view_update_last_status
+            .get(&(database, collection, view_name))
+            .copied()
+    }
+
+    pub fn rebuild_view(
+        &self,
+        view: &dyn view::Serialized,
+        database: &Database,
+    ) -> Result<(), crate::Error> {
+        let collection = view.collection();
+        let view_name = view.view_name();
+        // Forgetting the stored version causes the integrity scanner to
+        // discard the existing index and queue every document for mapping.
+        let view_versions = database
+            .roots()
+            .tree(database.collection_tree::<Unversioned, _>(
+                &collection,
+                view_versions_tree_name(&collection),
+            )?)?;
+        view_versions.remove(view_name.to_string().as_bytes())?;
+        {
+            let mut statuses = self.statuses.write();
+            let key = (database.data.name.clone(), collection, view_name.clone());
+            statuses.completed_integrity_checks.remove(&key);
+            statuses.view_update_last_status.remove(&key);
+        }
+        if let Some(cache) = database.storage.instance.view_result_cache() {
+            cache.invalidate_view(&database.data.name, &view_name);
+        }
+
+        self.update_view_if_needed(view, database, true)
+    }
+
     pub fn key_value_expiration_loaded(&self, database: &Arc<Cow<'static, str>>) -> bool {
         let statuses = self.statuses.read();
         statuses.key_value_expiration_loads.contains(database)
@@ -143,6 +211,8 @@ impl TaskManager {
                 scan: IntegrityScan {
                     database: database.data.name.clone(),
                     view_version: view.version(),
+                    incremental_reduce: view.incremental_reduce(),
+                    deletion_history: view.deletion_history(),
                     collection: view.collection(),
                     view_name,
                 },
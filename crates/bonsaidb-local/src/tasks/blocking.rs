@@ -0,0 +1,49 @@
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::Error;
+
+type BlockingJob = Box<dyn FnOnce() + Send>;
+
+/// A bounded set of threads that executes blocking storage work, configured by
+/// [`StorageConfiguration::blocking_threads`](crate::config::StorageConfiguration::blocking_threads).
+#[derive(Debug, Clone)]
+pub struct BlockingPool {
+    sender: flume::Sender<BlockingJob>,
+}
+
+impl BlockingPool {
+    pub fn new(threads: usize) -> Self {
+        let (sender, receiver) = flume::unbounded::<BlockingJob>();
+        for index in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            std::thread::Builder::new()
+                .name(format!("bonsaidb-blocking-{index}"))
+                .spawn(move || {
+                    // The loop exits once the storage and all of its handles
+                    // have been dropped.
+                    while let Ok(job) = receiver.recv() {
+                        if catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            log::error!("a blocking storage job panicked");
+                        }
+                    }
+                })
+                .unwrap();
+        }
+        Self { sender }
+    }
+
+    /// Runs `job` on the next available thread and returns its result.
+    pub async fn run<F, R>(&self, job: F) -> Result<R, Error>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (result_sender, result_receiver) = flume::bounded(1);
+        self.sender
+            .send(Box::new(move || {
+                drop(result_sender.send(job()));
+            }))
+            .map_err(|_| Error::InternalCommunication)?;
+        Ok(result_receiver.recv_async().await?)
+    }
+}
@@ -4,12 +4,13 @@ use bonsaidb_core::connection::Connection;
 use bonsaidb_core::schema::CollectionName;
 use nebari::tree::{Root, Unversioned, Versioned};
 
-use crate::database::keyvalue::KEY_TREE;
+use crate::database::keyvalue::{BLOB_TREE, EXPIRATION_TREE, KEY_TREE};
+use crate::database::transaction_log::EXECUTED_TRANSACTIONS_TREE;
 use crate::database::{document_tree_name, DatabaseNonBlocking};
 use crate::tasks::{Job, Keyed, Task};
 use crate::views::{
     view_document_map_tree_name, view_entries_tree_name, view_invalidated_docs_tree_name,
-    view_versions_tree_name,
+    view_reductions_tree_name, view_versions_tree_name,
 };
 use crate::{Database, Error};
 
@@ -68,9 +69,18 @@ impl Target {
                 gather_collection_trees(database, &collection, &mut trees);
                 compact_trees(database, trees)
             }
-            Target::KeyValue => compact_tree::<Unversioned, _>(database, KEY_TREE),
+            Target::KeyValue => {
+                compact_tree::<Unversioned, _>(database, KEY_TREE)?;
+                compact_tree::<Unversioned, _>(database, EXPIRATION_TREE)?;
+                compact_tree::<Unversioned, _>(database, BLOB_TREE)
+            }
             Target::Database => {
-                let mut trees = Vec::new();
+                // Transactions outside of the configured retention are removed
+                // first so that the space they used is reclaimed.
+                database.enforce_transaction_log_retention()?;
+                let mut trees = vec![Target::UnversionedTree(
+                    EXECUTED_TRANSACTIONS_TREE.to_string(),
+                )];
                 for collection in database.schematic().collections() {
                     gather_collection_trees(database, collection, &mut trees);
                 }
@@ -112,6 +122,9 @@ fn gather_collection_trees(
         trees.push(Target::UnversionedTree(view_invalidated_docs_tree_name(
             &name,
         )));
+        if view.incremental_reduce() {
+            trees.push(Target::UnversionedTree(view_reductions_tree_name(&name)));
+        }
     }
 }
 
@@ -266,6 +266,68 @@ fn encryption() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(feature = "encryption")]
+fn key_value_encryption() -> anyhow::Result<()> {
+    use std::path::Path;
+
+    use bonsaidb_core::document::KeyId;
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    fn files_contain(path: &Path, needle: &[u8]) -> anyhow::Result<bool> {
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let found = if entry.file_type()?.is_dir() {
+                files_contain(&entry.path(), needle)?
+            } else {
+                std::fs::read(entry.path())?
+                    .windows(needle.len())
+                    .any(|window| window == needle)
+            };
+            if found {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    const LEGACY_VALUE: &[u8] = b"legacy key-value contents";
+    const SECRET_VALUE: &[u8] = b"encrypted key-value contents";
+    let path = TestDirectory::new("key-value-encryption");
+    let encrypted_config =
+        || StorageConfiguration::new(&path).default_encryption_key(KeyId::Master);
+
+    // Store a value before encryption is enabled.
+    {
+        let db = Database::open::<()>(StorageConfiguration::new(&path))?;
+        db.set_binary_key("legacy", LEGACY_VALUE).execute()?;
+    }
+    assert!(files_contain(&path, LEGACY_VALUE)?);
+
+    {
+        let db = Database::open::<()>(encrypted_config())?;
+        // Values stored without encryption can still be read.
+        assert_eq!(
+            db.get_key("legacy").into_bytes()?.as_deref(),
+            Some(LEGACY_VALUE)
+        );
+        db.set_binary_key("secret", SECRET_VALUE).execute()?;
+    }
+    assert!(!files_contain(&path, SECRET_VALUE)?);
+
+    let db = Database::open::<()>(encrypted_config())?;
+    assert_eq!(
+        db.get_key("secret").into_bytes()?.as_deref(),
+        Some(SECRET_VALUE)
+    );
+    assert_eq!(
+        db.get_key("legacy").into_bytes()?.as_deref(),
+        Some(LEGACY_VALUE)
+    );
+
+    Ok(())
+}
+
 #[test]
 fn expiration_after_close() -> anyhow::Result<()> {
     use bonsaidb_core::keyvalue::KeyValue;
@@ -318,3 +380,2471 @@ fn expiration_after_close() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn vacuum_expired_across_databases() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::keyvalue::KeyValue;
+    use bonsaidb_core::test_util::TimingTest;
+    loop {
+        let path = TestDirectory::new("vacuum-expired-across-databases");
+        let timing = TimingTest::new(Duration::from_millis(100));
+        // Set a key that expires while the storage is closed in two databases.
+        {
+            let storage = Storage::open(StorageConfiguration::new(&path))?;
+            for name in ["a", "b"] {
+                let db = storage.create_database::<()>(name, false)?;
+                db.set_key("expiring", &0_u32)
+                    .expire_in(Duration::from_secs(1))
+                    .execute()?;
+                db.set_key("kept", &0_u32).execute()?;
+            }
+        }
+        if timing.elapsed() > Duration::from_millis(500) {
+            println!("Retrying vacuum_expired_across_databases because it was too slow");
+            continue;
+        }
+
+        timing.wait_until(Duration::from_secs(2));
+
+        let storage = Storage::open(StorageConfiguration::new(&path))?;
+        let removed = storage.vacuum_expired_across_databases()?;
+        // See expiration_after_close for why a missing key causes a retry.
+        let a = storage.database::<()>("a")?;
+        let b = storage.database::<()>("b")?;
+        if a.get_key("kept").query()?.is_none() || b.get_key("kept").query()?.is_none() {
+            println!("Retrying vacuum_expired_across_databases because keys weren't persisted");
+            continue;
+        }
+
+        // Opening a database also schedules its expired keys to be removed in
+        // the background, which can race with the vacuum, so the number of
+        // keys a sweep removes is verified by the key-value store's
+        // `sweep_expired` test. Regardless of which removes the key, each
+        // database must have been swept.
+        assert!(removed.contains_key("a"));
+        assert!(removed.contains_key("b"));
+        assert!(a.get_key("expiring").query()?.is_none());
+        assert!(b.get_key("expiring").query()?.is_none());
+
+        break;
+    }
+    Ok(())
+}
+
+#[test]
+fn namespace_default_expiration() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    use crate::config::KeyValuePersistence;
+
+    let path = TestDirectory::new("namespace-default-expiration");
+    let db = Database::open::<()>(
+        StorageConfiguration::new(&path).key_value_persistence(
+            KeyValuePersistence::immediate()
+                .with_namespace_default_expiration("cache", Duration::from_secs(1)),
+        ),
+    )?;
+    let cache = db.with_key_namespace("cache");
+    cache.set_key("default", &0_u32).execute()?;
+    cache.set_key("never", &0_u32).never_expire().execute()?;
+    cache
+        .set_key("explicit", &0_u32)
+        .expire_in(Duration::from_secs(60))
+        .execute()?;
+    db.set_key("default", &0_u32).execute()?;
+    assert!(cache.get_key("default").query()?.is_some());
+
+    std::thread::sleep(Duration::from_millis(1500));
+
+    assert!(cache.get_key("default").query()?.is_none());
+    assert!(cache.get_key("never").query()?.is_some());
+    assert!(cache.get_key("explicit").query()?.is_some());
+    // Keys outside of the namespace don't use its default.
+    assert!(db.get_key("default").query()?.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn key_value_namespace_limit() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    use crate::config::KeyValuePersistence;
+
+    let path = TestDirectory::new("key-value-namespace-limit");
+    let db = Database::open::<()>(
+        StorageConfiguration::new(&path)
+            .key_value_persistence(KeyValuePersistence::immediate().with_max_namespaces(2)),
+    )?;
+    db.with_key_namespace("a")
+        .set_key("key", &0_u32)
+        .execute()?;
+    db.with_key_namespace("b")
+        .set_key("key", &0_u32)
+        .execute()?;
+
+    let new_namespace = db.with_key_namespace("c");
+    assert!(matches!(
+        new_namespace.set_key("key", &0_u32).execute(),
+        Err(bonsaidb_core::Error::TooManyKeyValueNamespaces { limit: 2 })
+    ));
+    assert!(matches!(
+        new_namespace.increment_key_by("counter", 1_u64).execute(),
+        Err(bonsaidb_core::Error::TooManyKeyValueNamespaces { limit: 2 })
+    ));
+    // Operations that can't create keys are still allowed.
+    assert!(new_namespace.get_key("key").query()?.is_none());
+    // Existing namespaces and keys without a namespace aren't limited.
+    db.with_key_namespace("a")
+        .set_key("other", &0_u32)
+        .execute()?;
+    db.set_key("key", &0_u32).execute()?;
+
+    Ok(())
+}
+
+#[test]
+fn relative_expiration() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::{
+        Command, Expiration, KeyOperation, KeyValue, SetCommand, Timestamp, Value,
+    };
+
+    let path = TestDirectory::new("relative-expiration");
+    let db = Database::open::<()>(StorageConfiguration::new(&path))?;
+    let before = Timestamp::now();
+    db.execute_key_operation(KeyOperation {
+        namespace: None,
+        key: String::from("a"),
+        command: Command::Set(SetCommand {
+            value: Value::Bytes(Default::default()),
+            expiration: Some(Expiration::After(Duration::from_secs(1))),
+            keep_existing_expiration: false,
+            check: None,
+            return_previous_value: false,
+        }),
+    })?;
+    let after = Timestamp::now();
+
+    // The expiration is computed from the database's clock when the operation
+    // is executed.
+    let expiration = db
+        .get_key("a")
+        .query_with_metadata()?
+        .and_then(|entry| entry.expiration)
+        .expect("key should have an expiration");
+    assert!(expiration >= before + Duration::from_secs(1));
+    assert!(expiration <= after + Duration::from_secs(1));
+
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(db.get_key("a").query()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn key_expiration_operations() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::{KeyStatus, KeyValue, Timestamp};
+
+    let path = TestDirectory::new("key-expiration-operations");
+    let db = Database::open::<()>(StorageConfiguration::new(&path))?;
+    let expiration_of = |key: &str| -> anyhow::Result<Option<Timestamp>> {
+        Ok(db
+            .get_key(key)
+            .query_with_metadata()?
+            .and_then(|entry| entry.expiration))
+    };
+    db.set_numeric_key("a", 1_u64).execute()?;
+
+    // Setting an absolute expiration.
+    let expiration = Timestamp::now() + Duration::from_secs(60);
+    assert_eq!(db.expire_at("a", expiration)?, KeyStatus::Updated);
+    assert_eq!(expiration_of("a")?, Some(expiration));
+
+    // Setting a relative expiration, computed when the operation is executed.
+    let before = Timestamp::now();
+    assert_eq!(
+        db.expire_after("a", Duration::from_secs(30))?,
+        KeyStatus::Updated
+    );
+    let after = Timestamp::now();
+    let expiration = expiration_of("a")?.expect("key should have an expiration");
+    assert!(expiration >= before + Duration::from_secs(30));
+    assert!(expiration <= after + Duration::from_secs(30));
+
+    // Clearing the expiration leaves the value intact.
+    assert_eq!(db.persist("a")?, KeyStatus::Updated);
+    assert_eq!(expiration_of("a")?, None);
+    assert_eq!(db.get_key("a").into_u64()?, Some(1));
+
+    // Missing keys are reported as unchanged and aren't created.
+    assert_eq!(
+        db.expire_at("missing", Timestamp::now() + Duration::from_secs(60))?,
+        KeyStatus::NotChanged
+    );
+    assert_eq!(
+        db.expire_after("missing", Duration::from_secs(60))?,
+        KeyStatus::NotChanged
+    );
+    assert_eq!(db.persist("missing")?, KeyStatus::NotChanged);
+    assert!(db.get_key("missing").query()?.is_none());
+
+    // The value is kept until the new expiration.
+    assert_eq!(
+        db.expire_after("a", Duration::from_millis(100))?,
+        KeyStatus::Updated
+    );
+    assert_eq!(db.get_key("a").into_u64()?, Some(1));
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(db.get_key("a").query()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn scan_key_value_namespace() -> anyhow::Result<()> {
+    use std::ops::ControlFlow;
+
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    use crate::config::{KeyValuePersistence, PersistenceThreshold};
+
+    let path = TestDirectory::new("scan-key-value-namespace");
+    let storage = Storage::open(StorageConfiguration::new(&path).key_value_persistence(
+        KeyValuePersistence::lazy([PersistenceThreshold::after_changes(4)]),
+    ))?;
+    let db = storage.create_database::<()>("scan", false)?;
+    let scores = db.with_key_namespace("scores");
+    // Enough changes to cause some keys to be persisted while others remain
+    // dirty, ensuring both sources are merged by the scan.
+    for (key, value) in ["a", "b", "c", "d", "e", "f"].into_iter().zip(1_u64..) {
+        scores.set_numeric_key(key, value).execute()?;
+    }
+    scores.delete_key("c")?;
+    db.set_numeric_key("a", 100_u64).execute()?;
+
+    let mut visited = Vec::new();
+    let mut total = 0;
+    db.scan_key_value_namespace(Some("scores"), |key, value| {
+        visited.push(key.to_string());
+        total += value.as_u64().unwrap();
+        ControlFlow::Continue(())
+    })?;
+    assert_eq!(visited, ["a", "b", "d", "e", "f"]);
+    assert_eq!(total, 18);
+
+    let mut visited = 0;
+    db.scan_key_value_namespace(Some("scores"), |_, _| {
+        visited += 1;
+        if visited == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })?;
+    assert_eq!(visited, 2);
+
+    Ok(())
+}
+
+#[test]
+fn rename_key_value_namespace() -> anyhow::Result<()> {
+    use std::ops::ControlFlow;
+
+    use bonsaidb_core::keyvalue::{KeyValue, Timestamp};
+
+    use crate::config::{KeyValuePersistence, PersistenceThreshold};
+
+    let path = TestDirectory::new("rename-key-value-namespace");
+    let open = || {
+        Database::open::<()>(StorageConfiguration::new(&path).key_value_persistence(
+            KeyValuePersistence::lazy([PersistenceThreshold::after_changes(2)]),
+        ))
+    };
+    let db = open()?;
+    let old = db.with_key_namespace("old");
+    // Enough changes to persist some keys while others remain dirty.
+    for (key, value) in ["a", "b", "c"].into_iter().zip(1_u64..) {
+        old.set_numeric_key(key, value).execute()?;
+    }
+    let expiration = Timestamp::now() + Duration::from_secs(60);
+    old.expire_at("c", expiration)?;
+    db.with_key_namespace("new")
+        .set_numeric_key("b", 0_u64)
+        .execute()?;
+
+    // A conflicting key prevents any keys from being moved.
+    assert!(matches!(
+        db.rename_namespace(Some("old"), Some("new")),
+        Err(bonsaidb_core::Error::KeyValueNamespaceConflict { namespace, key })
+            if namespace == "new" && key == "b"
+    ));
+    assert_eq!(old.get_key("a").into_u64()?, Some(1));
+
+    db.with_key_namespace("new").delete_key("b")?;
+    assert_eq!(db.rename_namespace(Some("old"), Some("new"))?, 3);
+    assert_eq!(db.rename_namespace(Some("old"), Some("new"))?, 0);
+    drop(db);
+
+    let db = open()?;
+    let new = db.with_key_namespace("new");
+    assert_eq!(new.get_key("a").into_u64()?, Some(1));
+    assert_eq!(new.get_key("b").into_u64()?, Some(2));
+    assert_eq!(new.get_key("c").into_u64()?, Some(3));
+    assert_eq!(
+        new.get_key("c")
+            .query_with_metadata()?
+            .and_then(|entry| entry.expiration),
+        Some(expiration)
+    );
+    let expiring = db.keys_expiring_between(Timestamp::MIN, Timestamp::MAX)?;
+    assert_eq!(expiring.len(), 1);
+    assert_eq!(expiring[0].namespace.as_deref(), Some("new"));
+    assert_eq!(expiring[0].key, "c");
+
+    let mut remaining = 0;
+    db.scan_key_value_namespace(Some("old"), |_, _| {
+        remaining += 1;
+        ControlFlow::Continue(())
+    })?;
+    assert_eq!(remaining, 0);
+    assert!(db.with_key_namespace("old").get_key("a").query()?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn shared_key_value_worker() -> anyhow::Result<()> {
+    use std::ops::ControlFlow;
+
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    use crate::config::{KeyValuePersistence, KeyValueWorker, PersistenceThreshold};
+
+    let path = TestDirectory::new("shared-key-value-worker");
+    let configuration = || {
+        StorageConfiguration::new(&path)
+            .key_value_worker(KeyValueWorker::Shared)
+            .key_value_persistence(KeyValuePersistence::lazy([
+                PersistenceThreshold::after_changes(1).and_duration(Duration::from_millis(250)),
+            ]))
+            .lock_wait(Duration::from_secs(5))
+    };
+    let storage = Storage::open(configuration())?;
+    let databases = (0..4)
+        .map(|index| storage.create_database::<()>(&format!("db{index}"), false))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (index, db) in (0_u64..).zip(&databases) {
+        db.set_numeric_key("persistent", index).execute()?;
+        // Each database's key expires at a different time, giving the worker
+        // a target for each database.
+        db.set_numeric_key("expiring", index)
+            .expire_in(Duration::from_millis(100 * (index + 1)))
+            .execute()?;
+    }
+
+    std::thread::sleep(Duration::from_secs(1));
+
+    for (index, db) in (0_u64..).zip(&databases) {
+        assert_eq!(
+            db.get_key("persistent").committed_only().into_u64()?,
+            Some(index)
+        );
+        // Scanning doesn't remove expired keys, so the expired key must have
+        // been removed by the worker.
+        let mut keys = Vec::new();
+        db.scan_key_value_namespace(None, |key, _| {
+            keys.push(key.to_string());
+            ControlFlow::Continue(())
+        })?;
+        assert_eq!(keys, ["persistent"]);
+    }
+
+    // Once every database is closed, the storage can be reopened.
+    drop(databases);
+    drop(storage);
+    let storage = Storage::open(configuration())?;
+    for index in 0..4 {
+        let db = storage.database::<()>(&format!("db{index}"))?;
+        assert_eq!(db.get_key("persistent").into_u64()?, Some(index));
+        assert!(db.get_key("expiring").query()?.is_none());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn case_insensitive_view_keys() -> anyhow::Result<()> {
+    use bonsaidb_core::document::{CollectionDocument, Emit};
+    use bonsaidb_core::key::CaseInsensitive;
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, SerializedCollection, View, ViewMapResult, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "users", views = [UsersByName], core = bonsaidb_core)]
+    struct User {
+        name: String,
+    }
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = User, key = CaseInsensitive, value = String, core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct UsersByName;
+
+    impl CollectionMapReduce for UsersByName {
+        fn map<'doc>(&self, document: CollectionDocument<User>) -> ViewMapResult<'doc, Self> {
+            let name = document.contents.name;
+            document
+                .header
+                .emit_key_and_value(CaseInsensitive::from(name.clone()), name)
+        }
+    }
+
+    let path = TestDirectory::new("case-insensitive-view-keys");
+    let db = Database::open::<User>(StorageConfiguration::new(&path))?;
+    User {
+        name: String::from("EctonDev"),
+    }
+    .push_into(&db)?;
+    User {
+        name: String::from("someone"),
+    }
+    .push_into(&db)?;
+
+    let mappings = db
+        .view::<UsersByName>()
+        .with_key(&CaseInsensitive::from("ectondev"))
+        .query()?;
+    assert_eq!(mappings.len(), 1);
+    assert_eq!(mappings[0].key, CaseInsensitive::from("ECTONDEV"));
+    assert_eq!(mappings[0].key.0, "ectondev");
+    assert_eq!(mappings[0].value, "EctonDev");
+
+    Ok(())
+}
+
+#[test]
+fn ip_addr_view_keys() -> anyhow::Result<()> {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    use bonsaidb_core::connection::ViewMappings;
+    use bonsaidb_core::document::{CollectionDocument, Emit};
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, SerializedCollection, View, ViewMapResult, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "requests", views = [RequestsByAddress], core = bonsaidb_core)]
+    struct Request {
+        address: IpAddr,
+    }
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Request, key = IpAddr, value = (), core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct RequestsByAddress;
+
+    impl CollectionMapReduce for RequestsByAddress {
+        fn map<'doc>(&self, document: CollectionDocument<Request>) -> ViewMapResult<'doc, Self> {
+            document.header.emit_key(document.contents.address)
+        }
+    }
+
+    let path = TestDirectory::new("ip-addr-view-keys");
+    let db = Database::open::<Request>(StorageConfiguration::new(&path))?;
+    let v4 = |last| IpAddr::V4(Ipv4Addr::new(10, 0, 0, last));
+    let v6 = |last| IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, last));
+    // Insert the addresses out of order, mixing both versions.
+    for address in [v6(2), v4(3), v6(1), v4(1), v6(3), v4(2)] {
+        Request { address }.push_into(&db)?;
+    }
+
+    let keys = |mappings: ViewMappings<RequestsByAddress>| {
+        mappings
+            .into_iter()
+            .map(|mapping| mapping.key)
+            .collect::<Vec<_>>()
+    };
+
+    // All IPv4 addresses sort before all IPv6 addresses.
+    assert_eq!(
+        keys(db.view::<RequestsByAddress>().query()?),
+        vec![v4(1), v4(2), v4(3), v6(1), v6(2), v6(3)]
+    );
+    assert_eq!(
+        keys(
+            db.view::<RequestsByAddress>()
+                .with_key_range(v4(2)..=v4(3))
+                .query()?
+        ),
+        vec![v4(2), v4(3)]
+    );
+    // Ranges can span both versions.
+    assert_eq!(
+        keys(
+            db.view::<RequestsByAddress>()
+                .with_key_range(v4(3)..v6(2))
+                .query()?
+        ),
+        vec![v4(3), v6(1)]
+    );
+    // Ranges covering each version only include that version.
+    let all_v4 = IpAddr::V4(Ipv4Addr::UNSPECIFIED)..=IpAddr::V4(Ipv4Addr::BROADCAST);
+    assert_eq!(
+        keys(
+            db.view::<RequestsByAddress>()
+                .with_key_range(all_v4)
+                .query()?
+        ),
+        vec![v4(1), v4(2), v4(3)]
+    );
+    assert_eq!(
+        keys(
+            db.view::<RequestsByAddress>()
+                .with_key_range(IpAddr::V6(Ipv6Addr::UNSPECIFIED)..)
+                .query()?
+        ),
+        vec![v6(1), v6(2), v6(3)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn deletion_history_view() -> anyhow::Result<()> {
+    use bonsaidb_core::document::{CollectionDocument, DocumentId, Emit, Header};
+    use bonsaidb_core::keyvalue::Timestamp;
+    use bonsaidb_core::schema::view::map::Mappings;
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, SerializedCollection, View, ViewMapResult, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "audited", views = [Deletions], core = bonsaidb_core)]
+    struct Audited {
+        value: u32,
+    }
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Audited, key = Timestamp, value = (), core = bonsaidb_core)]
+    #[view_schema(deletion_history, core = bonsaidb_core)]
+    struct Deletions;
+
+    impl CollectionMapReduce for Deletions {
+        fn map<'doc>(&self, _document: CollectionDocument<Audited>) -> ViewMapResult<'doc, Self> {
+            Ok(Mappings::none())
+        }
+
+        fn map_deleted<'doc>(
+            &self,
+            header: &'doc Header,
+            deleted_at: Timestamp,
+        ) -> ViewMapResult<'doc, Self> {
+            header.emit_key(deleted_at)
+        }
+    }
+
+    let path = TestDirectory::new("deletion-history-view");
+    let db = Database::open::<Audited>(StorageConfiguration::new(&path))?;
+
+    let first = Audited { value: 1 }.insert_into(&1, &db)?;
+    let kept = Audited { value: 2 }.insert_into(&2, &db)?;
+    // Existing documents aren't included in the view.
+    assert!(db.view::<Deletions>().query()?.is_empty());
+
+    let before_delete = Timestamp::now();
+    first.delete(&db)?;
+    let after_delete = Timestamp::now();
+
+    let history = db.view::<Deletions>().query()?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].source.id, DocumentId::from_u64(first.header.id));
+    assert!(before_delete <= history[0].key && history[0].key <= after_delete);
+    let first_deleted_at = history[0].key;
+
+    // Reusing the id and deleting it again adds another entry rather than
+    // replacing the first one.
+    let reinserted = Audited { value: 3 }.insert_into(&1, &db)?;
+    assert_eq!(db.view::<Deletions>().query()?.len(), 1);
+    reinserted.delete(&db)?;
+
+    let history = db.view::<Deletions>().query()?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].key, first_deleted_at);
+    assert!(history[1].key >= first_deleted_at);
+    assert!(history
+        .iter()
+        .all(|mapping| mapping.source.id == DocumentId::from_u64(first.header.id)));
+    assert!(!history
+        .iter()
+        .any(|mapping| mapping.source.id == DocumentId::from_u64(kept.header.id)));
+
+    // Entries for deleted documents aren't integrity problems in a deletion
+    // history view.
+    assert!(db.verify_integrity()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn view_order_by_source() -> anyhow::Result<()> {
+    use bonsaidb_core::document::{CollectionDocument, Emit};
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, SerializedCollection, View, ViewMapResult, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "events", views = [EventsByCategory], core = bonsaidb_core)]
+    struct Event {
+        category: String,
+    }
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Event, key = String, value = (), core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct EventsByCategory;
+
+    impl CollectionMapReduce for EventsByCategory {
+        fn map<'doc>(&self, document: CollectionDocument<Event>) -> ViewMapResult<'doc, Self> {
+            document.header.emit_key(document.contents.category)
+        }
+    }
+
+    let path = TestDirectory::new("view-order-by-source");
+    let db = Database::open::<Event>(StorageConfiguration::new(&path))?;
+    let mut inserted = Vec::new();
+    for category in ["b", "a", "b", "a", "b"] {
+        let doc = Event {
+            category: String::from(category),
+        }
+        .push_into(&db)?;
+        inserted.push((category, doc.header.id));
+    }
+    let inserted_ids = |category: &str| {
+        inserted
+            .iter()
+            .filter(|(inserted_category, _)| *inserted_category == category)
+            .map(|(_, id)| *id)
+            .collect::<Vec<_>>()
+    };
+
+    // Within a single key, mappings are returned in insertion order.
+    let mappings = db
+        .view::<EventsByCategory>()
+        .with_key("b")
+        .order_by_source()
+        .query()?;
+    let ids = mappings
+        .iter()
+        .map(|mapping| mapping.source.id.deserialize::<u64>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, inserted_ids("b"));
+
+    // Descending queries reverse the order within each key.
+    let mappings = db
+        .view::<EventsByCategory>()
+        .descending()
+        .order_by_source()
+        .query()?;
+    let results = mappings
+        .iter()
+        .map(|mapping| {
+            (
+                mapping.key.as_str(),
+                mapping.source.id.deserialize::<u64>().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut expected = Vec::new();
+    for category in ["b", "a"] {
+        let mut ids = inserted_ids(category);
+        ids.reverse();
+        expected.extend(ids.into_iter().map(|id| (category, id)));
+    }
+    assert_eq!(results, expected);
+
+    // The ordering also applies to the other query kinds.
+    let keys = db
+        .view::<EventsByCategory>()
+        .with_key("a")
+        .order_by_source()
+        .keys_only()?;
+    assert_eq!(
+        keys.iter()
+            .map(|mapping| mapping.source.id.deserialize::<u64>().unwrap())
+            .collect::<Vec<_>>(),
+        inserted_ids("a")
+    );
+    let docs = db
+        .view::<EventsByCategory>()
+        .with_key("a")
+        .descending()
+        .order_by_source()
+        .query_with_collection_docs()?;
+    let mut expected = inserted_ids("a");
+    expected.reverse();
+    assert_eq!(
+        docs.mappings
+            .iter()
+            .map(|mapping| mapping.source.id.deserialize::<u64>().unwrap())
+            .collect::<Vec<_>>(),
+        expected
+    );
+
+    Ok(())
+}
+
+#[test]
+fn storage_lock_wait() -> anyhow::Result<()> {
+    let path = TestDirectory::new("storage-lock-wait");
+    let storage = Storage::open(StorageConfiguration::new(&path))?;
+
+    // While the first instance is open, a short wait times out.
+    assert!(matches!(
+        Storage::open(StorageConfiguration::new(&path).lock_wait(Duration::from_millis(50))),
+        Err(crate::Error::StorageLocked)
+    ));
+
+    // Release the lock while the second instance is waiting for it.
+    let releaser = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(250));
+        drop(storage);
+    });
+    let storage =
+        Storage::open(StorageConfiguration::new(&path).lock_wait(Duration::from_secs(30)))?;
+    releaser.join().unwrap();
+    drop(storage);
+
+    Ok(())
+}
+
+#[test]
+fn write_ahead_log_configuration() {
+    use crate::config::KeyValuePersistence;
+
+    let path = TestDirectory::new("write-ahead-log-configuration");
+    let persistence = KeyValuePersistence::default().with_write_ahead_log();
+    assert!(Storage::open(
+        StorageConfiguration::new(&path)
+            .memory_only()
+            .key_value_persistence(persistence.clone())
+    )
+    .is_err());
+    #[cfg(feature = "encryption")]
+    assert!(Storage::open(
+        StorageConfiguration::new(&path)
+            .default_encryption_key(bonsaidb_core::document::KeyId::Master)
+            .key_value_persistence(persistence)
+    )
+    .is_err());
+}
+
+#[test]
+fn transaction_log_pruning() -> anyhow::Result<()> {
+    use nebari::tree::{Root, Unversioned};
+
+    use crate::database::transaction_log::EXECUTED_TRANSACTIONS_TREE;
+
+    let path = TestDirectory::new("transaction-log-pruning");
+    let db = Database::open::<BasicCollectionWithNoViews>(StorageConfiguration::new(&path))?;
+    for _ in 0..10 {
+        db.collection::<BasicCollectionWithNoViews>()
+            .push(&Basic::new("test"))?;
+    }
+    let ids = db
+        .list_executed_transactions(None, None)?
+        .into_iter()
+        .map(|transaction| transaction.id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids.len(), 10);
+
+    assert_eq!(db.prune_transaction_log(ids[6])?, 6);
+    // The pruned transactions are removed rather than hidden.
+    let executed_transactions = db
+        .roots()
+        .tree(Unversioned::tree(EXECUTED_TRANSACTIONS_TREE))?;
+    assert!(executed_transactions.get(&ids[5].to_be_bytes())?.is_none());
+    assert!(executed_transactions.get(&ids[6].to_be_bytes())?.is_some());
+    let listed_ids = |starting_id: Option<u64>, limit: Option<u32>| -> anyhow::Result<Vec<u64>> {
+        Ok(db
+            .list_executed_transactions(starting_id, limit)?
+            .into_iter()
+            .map(|transaction| transaction.id)
+            .collect())
+    };
+    assert_eq!(listed_ids(None, None)?, &ids[6..]);
+    // Listing from a pruned id starts with the oldest remaining transaction.
+    assert_eq!(listed_ids(Some(ids[0]), None)?, &ids[6..]);
+    // Paging through the remaining transactions is unaffected.
+    let first_page = listed_ids(None, Some(2))?;
+    assert_eq!(first_page, &ids[6..8]);
+    let second_page = listed_ids(Some(first_page[1] + 1), Some(2))?;
+    assert_eq!(second_page, &ids[8..10]);
+
+    // Pruning an earlier id doesn't restore pruned transactions.
+    assert_eq!(db.prune_transaction_log(ids[0])?, 0);
+    assert_eq!(listed_ids(None, None)?, &ids[6..]);
+
+    // The pruning is persisted.
+    drop(db);
+    let db = Database::open::<BasicCollectionWithNoViews>(StorageConfiguration::new(&path))?;
+    let reopened_ids = db
+        .list_executed_transactions(None, None)?
+        .into_iter()
+        .map(|transaction| transaction.id)
+        .collect::<Vec<_>>();
+    assert_eq!(reopened_ids, &ids[6..]);
+
+    Ok(())
+}
+
+#[test]
+fn transaction_log_retention() -> anyhow::Result<()> {
+    use nebari::tree::{Root, Unversioned};
+
+    use crate::database::transaction_log::EXECUTED_TRANSACTIONS_TREE;
+
+    let path = TestDirectory::new("transaction-log-retention");
+    let db = Database::open::<BasicCollectionWithNoViews>(
+        StorageConfiguration::new(&path).transaction_log_retention(3),
+    )?;
+    for _ in 0..10 {
+        db.collection::<BasicCollectionWithNoViews>()
+            .push(&Basic::new("test"))?;
+    }
+
+    let transactions = db.list_executed_transactions(None, None)?;
+    assert_eq!(transactions.len(), 3);
+    assert_eq!(
+        transactions.last().map(|transaction| transaction.id),
+        db.last_transaction_id()?
+    );
+    // Transactions outside of the retention are removed as documents are
+    // written.
+    let start = 0_u64.to_be_bytes();
+    assert_eq!(
+        db.roots()
+            .tree(Unversioned::tree(EXECUTED_TRANSACTIONS_TREE))?
+            .get_range(&(start.as_slice()..))?
+            .len(),
+        3
+    );
+
+    // Transactions older than the maximum age are removed.
+    let path = TestDirectory::new("transaction-log-max-age");
+    let db = Database::open::<BasicCollectionWithNoViews>(
+        StorageConfiguration::new(&path).transaction_log_max_age(Duration::ZERO),
+    )?;
+    db.collection::<BasicCollectionWithNoViews>()
+        .push(&Basic::new("test"))?;
+    assert!(db.list_executed_transactions(None, None)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn transaction_log_pruning_by_age() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::keyvalue::Timestamp;
+
+    let path = TestDirectory::new("transaction-log-pruning-by-age");
+    let storage = Storage::open(
+        StorageConfiguration::new(&path).with_schema::<BasicCollectionWithNoViews>()?,
+    )?;
+    let db = storage.create_database::<BasicCollectionWithNoViews>("db", false)?;
+    for _ in 0..3 {
+        db.collection::<BasicCollectionWithNoViews>()
+            .push(&Basic::new("old"))?;
+    }
+    std::thread::sleep(Duration::from_millis(10));
+    let cutoff = Timestamp::now();
+    std::thread::sleep(Duration::from_millis(10));
+    for _ in 0..2 {
+        db.collection::<BasicCollectionWithNoViews>()
+            .push(&Basic::new("new"))?;
+    }
+
+    let removed = storage.prune_transaction_logs_executed_before(cutoff)?;
+    assert_eq!(removed.get("db"), Some(&3));
+    assert_eq!(db.list_executed_transactions(None, None)?.len(), 2);
+    assert_eq!(db.prune_transaction_log_executed_before(cutoff)?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn list_transactions_max_results() -> anyhow::Result<()> {
+    use bonsaidb_core::limits::LIST_TRANSACTIONS_MAX_RESULTS;
+
+    let path = TestDirectory::new("list-transactions-max-results");
+    let db = Database::open::<BasicCollectionWithNoViews>(
+        StorageConfiguration::new(&path)
+            .list_transactions_max_results(LIST_TRANSACTIONS_MAX_RESULTS * 2),
+    )?;
+    let transaction_count = LIST_TRANSACTIONS_MAX_RESULTS + 10;
+    for _ in 0..transaction_count {
+        db.collection::<BasicCollectionWithNoViews>()
+            .push(&Basic::new("test"))?;
+    }
+
+    let transactions = db.list_executed_transactions(None, Some(u32::MAX))?;
+    assert_eq!(transactions.len(), usize::try_from(transaction_count)?);
+    assert_eq!(
+        transactions.last().map(|transaction| transaction.id),
+        db.last_transaction_id()?
+    );
+
+    Ok(())
+}
+
+#[test]
+fn max_document_size() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::SerializedCollection;
+
+    let path = TestDirectory::new("max-document-size");
+    let db = Database::open::<BasicCollectionWithNoViews>(
+        StorageConfiguration::new(&path).max_document_size(256),
+    )?;
+    let mut doc = BasicCollectionWithNoViews::push(Basic::new("small"), &db)?;
+    let last_transaction_id = db.last_transaction_id()?;
+
+    let large = "x".repeat(512);
+    match BasicCollectionWithNoViews::push(Basic::new(&large), &db).map_err(|err| err.error) {
+        Err(bonsaidb_core::Error::DocumentTooLarge { size, limit }) => {
+            assert!(size > 512);
+            assert_eq!(limit, 256);
+        }
+        other => unreachable!("unexpected result: {other:?}"),
+    }
+    doc.contents.value = large;
+    assert!(matches!(
+        doc.update(&db),
+        Err(bonsaidb_core::Error::DocumentTooLarge { .. })
+    ));
+
+    // Nothing was written by the failed operations.
+    assert_eq!(db.last_transaction_id()?, last_transaction_id);
+    let stored = BasicCollectionWithNoViews::all(&db).query()?;
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].contents.value, "small");
+
+    Ok(())
+}
+
+#[test]
+fn collection_transaction() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::SerializedCollection;
+    use bonsaidb_core::transaction::OperationResult;
+
+    let path = TestDirectory::new("collection-transaction");
+    let db = Database::open::<BasicCollectionWithNoViews>(StorageConfiguration::new(&path))?;
+    let mut updated = BasicCollectionWithNoViews::push(Basic::new("original"), &db)?;
+    let deleted = BasicCollectionWithNoViews::push(Basic::new("deleted"), &db)?;
+
+    updated.contents.value = String::from("updated");
+    let mut tx = db.collection::<BasicCollectionWithNoViews>().transaction();
+    tx.push(&Basic::new("pushed"))?
+        .insert(&100, &Basic::new("inserted"))?
+        .update(&mut updated)?
+        .delete(&deleted)?;
+    assert_eq!(tx.len(), 4);
+    let results = tx.commit()?;
+    assert_eq!(results.len(), 4);
+    let OperationResult::DocumentDeleted { id, .. } = &results[3] else {
+        unreachable!("unexpected tx result")
+    };
+    assert_eq!(id.deserialize::<u64>()?, deleted.header.id);
+
+    let mut values = BasicCollectionWithNoViews::all(&db)
+        .query()?
+        .into_iter()
+        .map(|doc| doc.contents.value)
+        .collect::<Vec<_>>();
+    values.sort();
+    assert_eq!(values, ["inserted", "pushed", "updated"]);
+    assert!(BasicCollectionWithNoViews::get(&100, &db)?.is_some());
+
+    // Committing stores the new revision in the updated document, so it can
+    // be updated again.
+    assert_eq!(updated.header.revision.id, 1);
+    let mut stale = updated.clone();
+    updated.contents.value = String::from("updated again");
+    let mut tx = db.collection::<BasicCollectionWithNoViews>().transaction();
+    tx.update(&mut updated)?;
+    tx.commit()?;
+    assert_eq!(updated.header.revision.id, 2);
+
+    // Operations are applied atomically: a stale update aborts the entire
+    // transaction.
+    let mut tx = db.collection::<BasicCollectionWithNoViews>().transaction();
+    tx.push(&Basic::new("aborted"))?.update(&mut stale)?;
+    assert!(matches!(
+        tx.commit(),
+        Err(bonsaidb_core::Error::DocumentConflict(..))
+    ));
+    assert_eq!(BasicCollectionWithNoViews::all(&db).count()?, 3);
+
+    Ok(())
+}
+
+#[test]
+fn document_patch() -> anyhow::Result<()> {
+    use bonsaidb_core::document::HasHeader;
+    use bonsaidb_core::schema::{Collection, SerializedCollection};
+    use bonsaidb_core::transaction::{Operation, OperationResult, Transaction};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[collection(name = "reports", core = bonsaidb_core)]
+    struct Report {
+        title: String,
+        views: u64,
+        note: Option<String>,
+        body: Vec<u8>,
+    }
+
+    #[derive(Serialize)]
+    struct ReportPatch {
+        views: u64,
+        note: Option<String>,
+    }
+
+    let path = TestDirectory::new("document-patch");
+    let db = Database::open::<Report>(StorageConfiguration::new(&path))?;
+    let original = Report {
+        title: String::from("quarterly"),
+        views: 1,
+        note: Some(String::from("draft")),
+        body: vec![42; 64 * 1024],
+    }
+    .push_into(&db)?;
+
+    let patch = Operation::patch_serialized::<Report, _, _>(
+        &original,
+        &ReportPatch {
+            views: 2,
+            note: None,
+        },
+    )?;
+    let results = Transaction::from(patch).apply(&db)?;
+    let OperationResult::DocumentUpdated { header, .. } = &results[0] else {
+        unreachable!("unexpected tx result")
+    };
+    assert_ne!(header.revision, original.header()?.revision);
+
+    // Only the patched fields changed.
+    let patched = Report::get(&original.header.id, &db)?.expect("document missing");
+    assert_eq!(
+        patched.contents,
+        Report {
+            views: 2,
+            note: None,
+            ..original.contents.clone()
+        }
+    );
+    assert_eq!(patched.header.revision, header.revision);
+
+    // Patching a stale revision conflicts without modifying the document.
+    let stale = Operation::patch_serialized::<Report, _, _>(
+        &original,
+        &ReportPatch {
+            views: 3,
+            note: None,
+        },
+    )?;
+    assert!(matches!(
+        Transaction::from(stale).apply(&db),
+        Err(bonsaidb_core::Error::DocumentConflict(..))
+    ));
+    assert_eq!(
+        Report::get(&original.header.id, &db)?
+            .expect("document missing")
+            .contents
+            .views,
+        2
+    );
+
+    // Patching a missing document fails.
+    patched.delete(&db)?;
+    let missing = Operation::patch_serialized::<Report, _, _>(
+        &patched,
+        &ReportPatch {
+            views: 4,
+            note: None,
+        },
+    )?;
+    assert!(matches!(
+        Transaction::from(missing).apply(&db),
+        Err(bonsaidb_core::Error::DocumentNotFound(..))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn time_ordered_ids() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::{Collection, IdStrategy, SerializedCollection};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "events", id_strategy = IdStrategy::TimeOrdered, core = bonsaidb_core)]
+    struct Event {
+        value: u32,
+    }
+
+    let path = TestDirectory::new("time-ordered-ids");
+    let db = Database::open::<Event>(StorageConfiguration::new(&path))?;
+    let mut ids = Vec::new();
+    for value in 0..100 {
+        ids.push(Event { value }.push_into(&db)?.header.id);
+    }
+
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    // The upper 48 bits of each id contain the milliseconds since the Unix
+    // epoch, which are far larger than the sequential ids.
+    assert!(ids[0] > u64::from(u32::MAX));
+
+    let last = Event::get(&ids[99], &db)?.expect("document not found");
+    assert_eq!(last.contents.value, 99);
+
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn blocking_thread_pool() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::AsyncConnection;
+    use bonsaidb_core::keyvalue::AsyncKeyValue;
+
+    use crate::AsyncDatabase;
+
+    let path = TestDirectory::new("blocking-thread-pool");
+    let db = AsyncDatabase::open::<BasicCollectionWithNoViews>(
+        StorageConfiguration::new(&path).blocking_threads(1),
+    )
+    .await?;
+
+    // Every request is executed by the pool's only thread, so these
+    // concurrent requests are serialized, but they should all complete.
+    futures::future::try_join_all((0..20_u64).map(|index| {
+        let db = db.clone();
+        async move {
+            db.set_numeric_key(format!("key{index}"), index).await?;
+            db.collection::<BasicCollectionWithNoViews>()
+                .push(&Basic::new("test"))
+                .await?;
+            anyhow::Ok(())
+        }
+    }))
+    .await?;
+
+    assert_eq!(
+        db.collection::<BasicCollectionWithNoViews>()
+            .all()
+            .count()
+            .await?,
+        20
+    );
+    for index in 0..20_u64 {
+        assert_eq!(
+            db.get_key(format!("key{index}")).into_u64().await?,
+            Some(index)
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn key_value_entry_batches() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::AsyncKeyValue;
+    use futures::TryStreamExt;
+
+    use crate::AsyncDatabase;
+
+    let path = TestDirectory::new("key-value-entry-batches");
+    let db =
+        AsyncDatabase::open::<BasicCollectionWithNoViews>(StorageConfiguration::new(&path)).await?;
+    for index in 0..7_u64 {
+        db.set_numeric_key(format!("key{index}"), index).await?;
+        db.with_key_namespace("namespace")
+            .set_numeric_key(format!("key{index}"), index)
+            .await?;
+    }
+
+    let batches = db
+        .key_value_entry_batches(3)
+        .try_collect::<Vec<_>>()
+        .await?;
+    assert!(batches.iter().all(|batch| batch.len() <= 3));
+    let mut entries = batches
+        .into_iter()
+        .flatten()
+        .map(|entry| ((entry.namespace, entry.key), entry.value))
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let expected = db
+        .as_blocking()
+        .all_key_value_entries()?
+        .into_iter()
+        .map(|(key, entry)| (key, entry.value))
+        .collect::<Vec<_>>();
+    assert_eq!(entries.len(), 14);
+    assert_eq!(entries, expected);
+
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn transaction_stream() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::AsyncConnection;
+    use futures::StreamExt;
+
+    use crate::AsyncDatabase;
+
+    let path = TestDirectory::new("transaction-stream");
+    let db =
+        AsyncDatabase::open::<BasicCollectionWithNoViews>(StorageConfiguration::new(&path)).await?;
+    for _ in 0..3 {
+        db.collection::<BasicCollectionWithNoViews>()
+            .push(&Basic::new("before"))
+            .await?;
+    }
+
+    let mut stream = db
+        .transaction_stream(0)
+        .follow(|| tokio::time::sleep(Duration::from_millis(10)));
+    let writer = tokio::spawn({
+        let db = db.clone();
+        async move {
+            for _ in 0..10 {
+                db.collection::<BasicCollectionWithNoViews>()
+                    .push(&Basic::new("during"))
+                    .await?;
+            }
+            anyhow::Ok(())
+        }
+    });
+
+    let mut streamed = Vec::new();
+    while streamed.len() < 13 {
+        let transaction = tokio::time::timeout(Duration::from_secs(10), stream.next())
+            .await?
+            .expect("followed streams don't end")?;
+        streamed.push(transaction.id);
+        assert_eq!(stream.position(), transaction.id + 1);
+    }
+    writer.await??;
+
+    let listed = db
+        .list_executed_transactions(None, None)
+        .await?
+        .into_iter()
+        .map(|transaction| transaction.id)
+        .collect::<Vec<_>>();
+    assert_eq!(streamed, listed);
+
+    // A stream that isn't followed ends once it has caught up, and can be
+    // resumed from a stored position.
+    let remaining = db
+        .transaction_stream(streamed[10])
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .map(|transaction| transaction.map(|transaction| transaction.id))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(remaining, &streamed[10..]);
+
+    Ok(())
+}
+
+#[test]
+fn soft_delete() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::LowLevelConnection;
+    use bonsaidb_core::keyvalue::Timestamp;
+    use bonsaidb_core::schema::SerializedCollection;
+    use bonsaidb_core::test_util::{BasicByParentIdEager, BasicCount};
+
+    let path = TestDirectory::new("soft-delete");
+    let db = Database::open::<Basic>(StorageConfiguration::new(&path))?;
+    let header = db.collection::<Basic>().push(&Basic::new("retained"))?;
+    let doc = db.collection::<Basic>().get(&header.id)?.unwrap();
+    assert_eq!(db.view::<BasicCount>().query()?.len(), 1);
+    assert_eq!(db.view::<BasicByParentIdEager>().query()?.len(), 1);
+
+    db.soft_delete::<Basic, _>(&doc)?;
+    // The document is hidden from retrieval and views.
+    assert!(db.collection::<Basic>().get(&header.id)?.is_none());
+    assert!(db.view::<BasicCount>().query()?.is_empty());
+    assert!(db.view::<BasicByParentIdEager>().query()?.is_empty());
+
+    // The contents are retained until the tombstone is purged.
+    let tombstone = db
+        .get_tombstone::<Basic, _>(&header.id)?
+        .expect("tombstone not retained");
+    assert_eq!(tombstone.header, doc.header);
+    assert_eq!(Basic::document_contents(&tombstone)?.value, "retained");
+    assert_eq!(db.purge_tombstones(Timestamp::MIN)?, 0);
+    assert!(db.get_tombstone::<Basic, _>(&header.id)?.is_some());
+
+    assert_eq!(
+        db.purge_tombstones(Timestamp::now() + Duration::from_secs(1))?,
+        1
+    );
+    assert!(db.get_tombstone::<Basic, _>(&header.id)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn incremental_reduce() -> anyhow::Result<()> {
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use bonsaidb_core::document::{CollectionDocument, Emit};
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, ReduceResult, SerializedCollection, SerializedView, View,
+        ViewMapResult, ViewMappedValue, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    // The number of mapped values that have been passed to `reduce()` without
+    // `rereduce` being set.
+    static VALUES_REDUCED: AtomicUsize = AtomicUsize::new(0);
+    // The number of partials that have been passed to `ValueTotals::reduce()`
+    // with `rereduce` set.
+    static VALUE_PARTIALS_REREDUCED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "measurements", views = [LazyTotals, EagerTotals, ValueTotals], core = bonsaidb_core)]
+    struct Measurement {
+        category: String,
+        value: u64,
+    }
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Measurement, key = String, value = u64, core = bonsaidb_core)]
+    #[view_schema(incremental_reduce, core = bonsaidb_core)]
+    struct LazyTotals;
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Measurement, key = String, value = u64, core = bonsaidb_core)]
+    #[view_schema(policy = Eager, incremental_reduce, core = bonsaidb_core)]
+    struct EagerTotals;
+
+    // Integer keys share their leading bytes, unlike the categories above.
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Measurement, key = u64, value = u64, core = bonsaidb_core)]
+    #[view_schema(policy = Eager, incremental_reduce, core = bonsaidb_core)]
+    struct ValueTotals;
+
+    fn sum<'a>(values: impl ExactSizeIterator<Item = &'a u64>, rereduce: bool) -> u64 {
+        if !rereduce {
+            VALUES_REDUCED.fetch_add(values.len(), Ordering::SeqCst);
+        }
+        values.sum()
+    }
+
+    impl CollectionMapReduce for LazyTotals {
+        fn map<'doc>(
+            &self,
+            document: CollectionDocument<Measurement>,
+        ) -> ViewMapResult<'doc, Self> {
+            let category = document.contents.category;
+            document
+                .header
+                .emit_key_and_value(category, document.contents.value)
+        }
+
+        fn reduce(
+            &self,
+            mappings: &[ViewMappedValue<'_, Self>],
+            rereduce: bool,
+        ) -> ReduceResult<Self> {
+            Ok(sum(mappings.iter().map(|mapping| &mapping.value), rereduce))
+        }
+    }
+
+    impl CollectionMapReduce for EagerTotals {
+        fn map<'doc>(
+            &self,
+            document: CollectionDocument<Measurement>,
+        ) -> ViewMapResult<'doc, Self> {
+            let category = document.contents.category;
+            document
+                .header
+                .emit_key_and_value(category, document.contents.value)
+        }
+
+        fn reduce(
+            &self,
+            mappings: &[ViewMappedValue<'_, Self>],
+            rereduce: bool,
+        ) -> ReduceResult<Self> {
+            Ok(sum(mappings.iter().map(|mapping| &mapping.value), rereduce))
+        }
+    }
+
+    impl CollectionMapReduce for ValueTotals {
+        fn map<'doc>(
+            &self,
+            document: CollectionDocument<Measurement>,
+        ) -> ViewMapResult<'doc, Self> {
+            let value = document.contents.value;
+            document.header.emit_key_and_value(value, value)
+        }
+
+        fn reduce(
+            &self,
+            mappings: &[ViewMappedValue<'_, Self>],
+            rereduce: bool,
+        ) -> ReduceResult<Self> {
+            if rereduce {
+                VALUE_PARTIALS_REREDUCED.fetch_add(mappings.len(), Ordering::SeqCst);
+            }
+            Ok(mappings.iter().map(|mapping| mapping.value).sum())
+        }
+    }
+
+    fn assert_totals<V: SerializedView<Key = String, Value = u64>>(
+        db: &Database,
+        documents: &BTreeMap<&str, Vec<CollectionDocument<Measurement>>>,
+    ) -> anyhow::Result<()> {
+        let expected = documents
+            .iter()
+            .filter(|(_, documents)| !documents.is_empty())
+            .map(|(category, documents)| {
+                (
+                    (*category).to_string(),
+                    documents.iter().map(|doc| doc.contents.value).sum::<u64>(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let grouped = db
+            .view::<V>()
+            .reduce_grouped()?
+            .into_iter()
+            .map(|mapping| (mapping.key, mapping.value))
+            .collect::<Vec<_>>();
+        assert_eq!(grouped, expected);
+        assert_eq!(
+            db.view::<V>().reduce()?,
+            expected.iter().map(|(_, total)| total).sum::<u64>()
+        );
+        // Categories starting with "a" or "b".
+        assert_eq!(
+            db.view::<V>()
+                .with_key_range(String::from("a")..String::from("c"))
+                .reduce()?,
+            expected
+                .iter()
+                .filter(|(category, _)| category.starts_with(['a', 'b']))
+                .map(|(_, total)| total)
+                .sum::<u64>()
+        );
+        Ok(())
+    }
+
+    let path = TestDirectory::new("incremental-reduce");
+    let db = Database::open::<Measurement>(StorageConfiguration::new(&path))?;
+
+    // Keys are spread across several buckets, including the empty key which
+    // is in a bucket of its own.
+    let mut documents = BTreeMap::<&str, Vec<CollectionDocument<Measurement>>>::new();
+    for (index, category) in ["apple", "avocado", "banana", "cherry", ""]
+        .into_iter()
+        .cycle()
+        .take(50)
+        .enumerate()
+    {
+        let doc = Measurement {
+            category: category.to_string(),
+            value: index as u64,
+        }
+        .push_into(&db)?;
+        documents.entry(category).or_default().push(doc);
+    }
+    assert_totals::<LazyTotals>(&db, &documents)?;
+    assert_totals::<EagerTotals>(&db, &documents)?;
+
+    // Inserting a document only reduces the values of the key it emitted.
+    let reduced_before = VALUES_REDUCED.load(Ordering::SeqCst);
+    let doc = Measurement {
+        category: String::from("banana"),
+        value: 1_000,
+    }
+    .push_into(&db)?;
+    documents.entry("banana").or_default().push(doc);
+    assert_totals::<LazyTotals>(&db, &documents)?;
+    assert_totals::<EagerTotals>(&db, &documents)?;
+    assert_eq!(
+        VALUES_REDUCED.load(Ordering::SeqCst) - reduced_before,
+        documents["banana"].len() * 2
+    );
+
+    // Updates and deletes, including removing every entry of a key.
+    let doc = documents.get_mut("apple").unwrap().pop().unwrap();
+    doc.delete(&db)?;
+    let doc = documents.get_mut("avocado").unwrap().first_mut().unwrap();
+    doc.contents.value = 42;
+    doc.update(&db)?;
+    for doc in documents.get_mut("cherry").unwrap().drain(..) {
+        doc.delete(&db)?;
+    }
+    assert_totals::<LazyTotals>(&db, &documents)?;
+    assert_totals::<EagerTotals>(&db, &documents)?;
+
+    // Changing the category moves the value between keys.
+    let mut doc = documents.get_mut("").unwrap().pop().unwrap();
+    doc.contents.category = String::from("cherry");
+    doc.update(&db)?;
+    documents.entry("cherry").or_default().push(doc);
+    assert_totals::<LazyTotals>(&db, &documents)?;
+    assert_totals::<EagerTotals>(&db, &documents)?;
+
+    for doc in documents
+        .values_mut()
+        .flat_map(|documents| documents.drain(..))
+    {
+        doc.delete(&db)?;
+    }
+    assert_totals::<LazyTotals>(&db, &documents)?;
+    assert_totals::<EagerTotals>(&db, &documents)?;
+
+    let measurements = (0..2_000)
+        .map(|value| Measurement {
+            category: String::from("integer"),
+            value,
+        })
+        .collect::<Vec<_>>();
+    let inserted = Measurement::push_all(measurements, &db)?;
+    let total = (0..2_000_u64).sum::<u64>();
+    assert_eq!(db.view::<ValueTotals>().reduce()?, total);
+    assert_eq!(
+        db.view::<ValueTotals>()
+            .with_key_range(500_u64..1_500)
+            .reduce()?,
+        (500..1_500_u64).sum::<u64>()
+    );
+
+    // Inserting a key only re-reduces the groups containing it, rather than
+    // every key with the same first byte.
+    let rereduced_before = VALUE_PARTIALS_REREDUCED.load(Ordering::SeqCst);
+    let doc = Measurement {
+        category: String::from("integer"),
+        value: 10_000,
+    }
+    .push_into(&db)?;
+    let rereduced = VALUE_PARTIALS_REREDUCED.load(Ordering::SeqCst) - rereduced_before;
+    assert!(rereduced < 1_000, "{rereduced} partials were re-reduced");
+    assert_eq!(db.view::<ValueTotals>().reduce()?, total + 10_000);
+
+    doc.delete(&db)?;
+    assert_eq!(db.view::<ValueTotals>().reduce()?, total);
+    inserted[1].delete(&db)?;
+    assert_eq!(db.view::<ValueTotals>().reduce()?, total - 1);
+
+    Ok(())
+}
+
+#[test]
+fn view_rows_reported_by_transactions() -> anyhow::Result<()> {
+    use bonsaidb_core::document::Header;
+    use bonsaidb_core::schema::{Collection, SerializedCollection, View};
+    use bonsaidb_core::test_util::BasicByParentIdEager;
+    use bonsaidb_core::transaction::{Operation, OperationResult, Transaction, ViewRowChanges};
+
+    fn apply(
+        db: &Database,
+        operation: Operation,
+    ) -> anyhow::Result<(OperationResult, ViewRowChanges)> {
+        let mut results = db.apply_transaction_with_view_rows(Transaction::from(operation))?;
+        assert_eq!(results.len(), 1);
+        let (result, mut view_rows) = results.pop().unwrap();
+        // Only the eager view is mapped during the transaction.
+        assert_eq!(view_rows.len(), 1);
+        let view_rows = view_rows.pop().unwrap();
+        assert_eq!(view_rows.view, BasicByParentIdEager.view_name());
+        Ok((result, view_rows))
+    }
+
+    fn updated_header(result: OperationResult) -> Header {
+        match result {
+            OperationResult::DocumentUpdated { header, .. } => header,
+            other => unreachable!("expected an updated document, got {other:?}"),
+        }
+    }
+
+    let path = TestDirectory::new("view-rows-reported-by-transactions");
+    let db = Database::open::<Basic>(StorageConfiguration::new(&path))?;
+
+    let (result, view_rows) = apply(
+        &db,
+        Operation::push_serialized::<Basic>(&Basic::new("a").with_parent_id(1))?,
+    )?;
+    let header = updated_header(result);
+    assert!(view_rows.removed.is_empty());
+    let added = view_rows
+        .added
+        .iter()
+        .map(|row| row.deserialized::<BasicByParentIdEager>())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(added, db.view::<BasicByParentIdEager>().query()?);
+    assert_eq!(added[0].source, header);
+
+    // Changing the emitted key replaces the document's row.
+    let (result, view_rows) = apply(
+        &db,
+        Operation::update(
+            Basic::collection_name(),
+            header.clone(),
+            Basic::serialize(&Basic::new("a").with_parent_id(2))?,
+        ),
+    )?;
+    let updated = updated_header(result);
+    let removed = view_rows.removed[0].deserialized::<BasicByParentIdEager>()?;
+    assert_eq!((removed.key, removed.source), (Some(1), header));
+    let added = view_rows
+        .added
+        .iter()
+        .map(|row| row.deserialized::<BasicByParentIdEager>())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(added, db.view::<BasicByParentIdEager>().query()?);
+    assert_eq!(added[0].key, Some(2));
+
+    // Deleting the document only removes rows.
+    let (_, view_rows) = apply(
+        &db,
+        Operation::delete(Basic::collection_name(), updated.clone()),
+    )?;
+    assert!(view_rows.added.is_empty());
+    let removed = view_rows.removed[0].deserialized::<BasicByParentIdEager>()?;
+    assert_eq!((removed.key, removed.source), (Some(2), updated));
+    assert!(db.view::<BasicByParentIdEager>().query()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn view_changes_returned_by_transactions() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::LowLevelConnection;
+    use bonsaidb_core::schema::View;
+    use bonsaidb_core::test_util::BasicByParentIdEager;
+    use bonsaidb_core::transaction::{Operation, Transaction};
+
+    let path = TestDirectory::new("view-changes-returned-by-transactions");
+    let db = Database::open::<Basic>(StorageConfiguration::new(&path))?;
+
+    let mut tx = Transaction::new();
+    tx.push(Operation::push_serialized::<Basic>(
+        &Basic::new("a").with_parent_id(1),
+    )?);
+    tx.push(Operation::push_serialized::<Basic>(
+        &Basic::new("b").with_parent_id(2),
+    )?);
+    let (results, changes) = db.apply_transaction_with_views(tx)?;
+    assert_eq!(results.len(), 2);
+    // Only the eager view is mapped during the transaction, and the rows of
+    // both operations are combined.
+    assert_eq!(changes.views.len(), 1);
+    let view_rows = changes
+        .get(&BasicByParentIdEager.view_name())
+        .expect("view changes missing");
+    assert!(view_rows.removed.is_empty());
+    let added = view_rows
+        .added
+        .iter()
+        .map(|row| row.deserialized::<BasicByParentIdEager>())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(added, db.view::<BasicByParentIdEager>().query()?);
+
+    Ok(())
+}
+
+#[test]
+fn get_del_is_atomic() -> anyhow::Result<()> {
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    const WRITERS: u64 = 4;
+    const SETS_PER_WRITER: u64 = 1_000;
+
+    let path = TestDirectory::new("get-del-is-atomic");
+    let db = Database::open::<()>(StorageConfiguration::new(&path))?;
+    let writing = Arc::new(AtomicBool::new(true));
+
+    let readers = (0..2)
+        .map(|_| {
+            let db = db.clone();
+            let writing = writing.clone();
+            std::thread::spawn(move || -> anyhow::Result<Vec<u64>> {
+                let mut taken = Vec::new();
+                while writing.load(Ordering::SeqCst) {
+                    if let Some(value) = db.get_del("queue")? {
+                        taken.push(value.as_u64().unwrap());
+                    }
+                }
+                Ok(taken)
+            })
+        })
+        .collect::<Vec<_>>();
+    let writers = (0..WRITERS)
+        .map(|writer| {
+            let db = db.clone();
+            std::thread::spawn(move || -> anyhow::Result<()> {
+                for value in 0..SETS_PER_WRITER {
+                    db.set_numeric_key("queue", writer * SETS_PER_WRITER + value)
+                        .execute()?;
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for writer in writers {
+        writer.join().unwrap()?;
+    }
+    writing.store(false, Ordering::SeqCst);
+
+    let mut taken = HashSet::new();
+    for reader in readers {
+        for value in reader.join().unwrap()? {
+            assert!(taken.insert(value), "{value} was returned twice");
+        }
+    }
+    if let Some(remaining) = db.get_del("queue")? {
+        assert!(taken.insert(remaining.as_u64().unwrap()));
+    }
+    assert!(!taken.is_empty());
+    assert!(taken.iter().all(|value| *value < WRITERS * SETS_PER_WRITER));
+    assert!(db.get_del("queue")?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn increment_field_is_atomic() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::{KeyValue, Numeric};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Counter {
+        label: String,
+        stats: Stats,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Stats {
+        visits: u64,
+        ratio: f32,
+    }
+
+    const THREADS: u64 = 4;
+    const INCREMENTS_PER_THREAD: u64 = 250;
+
+    let path = TestDirectory::new("increment-field-is-atomic");
+    let db = Database::open::<()>(StorageConfiguration::new(&path))?;
+    db.set_key(
+        "counter",
+        &Counter {
+            label: String::from("home"),
+            stats: Stats {
+                visits: 0,
+                ratio: 0.5,
+            },
+        },
+    )
+    .execute()?;
+
+    let threads = (0..THREADS)
+        .map(|_| {
+            let db = db.clone();
+            std::thread::spawn(move || -> anyhow::Result<()> {
+                for _ in 0..INCREMENTS_PER_THREAD {
+                    db.increment_field("counter", &["stats", "visits"], 1_u64)?;
+                }
+                Ok(())
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+
+    assert_eq!(
+        db.increment_field("counter", &["stats", "ratio"], 0.25_f64)?,
+        Some(Numeric::Float(0.75))
+    );
+    assert_eq!(
+        db.get_key("counter").into::<Counter>()?,
+        Some(Counter {
+            label: String::from("home"),
+            stats: Stats {
+                visits: THREADS * INCREMENTS_PER_THREAD,
+                ratio: 0.75,
+            },
+        })
+    );
+
+    // Missing keys aren't created.
+    assert_eq!(db.increment_field("missing", &["visits"], 1_u64)?, None);
+    assert!(db.get_key("missing").query()?.is_none());
+    // Missing fields, non-numeric fields, and numeric values are errors.
+    assert!(db
+        .increment_field("counter", &["stats", "missing"], 1_u64)
+        .is_err());
+    assert!(db.increment_field("counter", &["label"], 1_u64).is_err());
+    assert!(db
+        .increment_field("counter", &["label", "visits"], 1_u64)
+        .is_err());
+    db.set_numeric_key("numeric", 1_u64).execute()?;
+    assert!(db.increment_field("numeric", &["visits"], 1_u64).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn key_value_sets() -> anyhow::Result<()> {
+    use bonsaidb_core::keyvalue::KeyValue;
+
+    let path = TestDirectory::new("key-value-sets");
+    let db = Database::open::<()>(StorageConfiguration::new(&path))?;
+
+    assert_eq!(db.set_len("tags")?, 0);
+    assert!(db.add_to_set("tags", &"red")?);
+    assert!(db.add_to_set("tags", &"blue")?);
+    // Adding an existing member doesn't change the set.
+    assert!(!db.add_to_set("tags", &"red")?);
+    assert_eq!(db.set_len("tags")?, 2);
+    assert!(db.set_contains("tags", &"blue")?);
+    assert!(!db.set_contains("tags", &"green")?);
+    assert!(!db.set_contains("missing", &"blue")?);
+
+    assert!(db.remove_from_set("tags", &"red")?);
+    assert!(!db.remove_from_set("tags", &"red")?);
+    assert_eq!(db.set_len("tags")?, 1);
+    // Removing the last member removes the key.
+    assert!(db.remove_from_set("tags", &"blue")?);
+    assert!(db.get_key("tags").query()?.is_none());
+
+    // Set operations on keys storing other values are errors.
+    db.set_numeric_key("numeric", 1_u64).execute()?;
+    assert!(db.add_to_set("numeric", &"red").is_err());
+    db.set_key("bytes", &"red").execute()?;
+    assert!(db.set_len("bytes").is_err());
+    db.add_to_set("set", &"red")?;
+    assert!(db.get_key("set").into::<String>().is_err());
+
+    Ok(())
+}
+
+#[test]
+fn read_your_writes() -> anyhow::Result<()> {
+    let path = TestDirectory::new("read-your-writes");
+    let db = Database::open::<Basic>(StorageConfiguration::new(&path))?;
+
+    // Without any writes from this session, the policy behaves like
+    // UpdateAfter.
+    assert!(db
+        .view::<BasicByParentId>()
+        .with_access_policy(AccessPolicy::ReadYourWrites)
+        .query()?
+        .is_empty());
+
+    for expected in 1..=5 {
+        db.collection::<Basic>()
+            .push(&Basic::new("test").with_parent_id(1))?;
+        let mappings = db
+            .view::<BasicByParentId>()
+            .with_key(&Some(1))
+            .with_access_policy(AccessPolicy::ReadYourWrites)
+            .query()?;
+        assert_eq!(mappings.len(), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn verify_integrity() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::StorageConnection;
+    use bonsaidb_core::document::DocumentId;
+    use bonsaidb_core::schema::Collection;
+    use nebari::tree::{Root, Unversioned, Versioned};
+
+    use crate::database::document_tree_name;
+    use crate::database::keyvalue::KEY_TREE;
+    use crate::IntegrityProblem;
+
+    let path = TestDirectory::new("verify-integrity");
+    let storage = Storage::open(StorageConfiguration::new(&path).with_schema::<Basic>()?)?;
+    let db = storage.create_database::<Basic>("db", false)?;
+    let corrupted = db
+        .collection::<Basic>()
+        .push(&Basic::new("corrupted").with_parent_id(1))?;
+    let removed = db
+        .collection::<Basic>()
+        .push(&Basic::new("removed").with_parent_id(1))?;
+    assert_eq!(db.view::<BasicByParentId>().query()?.len(), 2);
+    assert!(storage.verify()?.is_empty());
+
+    // Corrupt one document, remove another without updating the views, and
+    // store an entry that isn't able to be deserialized in the key-value store.
+    let collection = Basic::collection_name();
+    let documents = db
+        .roots()
+        .tree(db.collection_tree::<Versioned, _>(&collection, document_tree_name(&collection))?)?;
+    let corrupted = DocumentId::from_u64(corrupted.id);
+    let removed = DocumentId::from_u64(removed.id);
+    documents.set(corrupted.to_vec(), b"corrupt".to_vec())?;
+    documents.remove(removed.as_ref())?;
+    db.roots()
+        .tree(Unversioned::tree(KEY_TREE))?
+        .set(b"\x00corrupt".to_vec(), b"corrupt".to_vec())?;
+
+    let problems = storage.verify()?;
+    let problems = problems.get("db").expect("problems not reported");
+    assert_eq!(problems.len(), 3);
+    assert!(problems.iter().any(|problem| matches!(
+        problem,
+        IntegrityProblem::CorruptDocument { key, .. } if key == corrupted.as_ref()
+    )));
+    assert!(problems.iter().any(|problem| matches!(
+        problem,
+        IntegrityProblem::MissingViewSource { source, .. } if source.id == removed
+    )));
+    assert!(problems.iter().any(|problem| matches!(
+        problem,
+        IntegrityProblem::CorruptKeyValueEntry { key, .. } if key == "\0corrupt"
+    )));
+
+    // Verification doesn't modify data.
+    assert_eq!(storage.verify()?.get("db"), Some(problems));
+
+    Ok(())
+}
+
+#[test]
+fn key_changes_round_trip() -> anyhow::Result<()> {
+    use bonsaidb_core::transaction::{ChangedKey, Changes};
+
+    use crate::database::compat::serialize_executed_transaction_changes;
+    use crate::decode_executed_transaction;
+
+    let data = serialize_executed_transaction_changes(&Changes::Keys(vec![
+        ChangedKey {
+            namespace: None,
+            key: String::from("a"),
+            deleted: false,
+        },
+        ChangedKey {
+            namespace: Some(String::from("namespace")),
+            key: String::from("b"),
+            deleted: true,
+        },
+    ]))?;
+    // The payload is a transmog-versions header followed by a Pot document.
+    let (_, payload) = transmog_versions::unwrap_version(&data);
+    assert!(pot::from_slice::<Changes>(payload).is_ok());
+
+    let (changes, metadata) = decode_executed_transaction(&data)?;
+    assert!(metadata.is_none());
+    let Changes::Keys(keys) = changes else {
+        unreachable!("key changes decoded as document changes")
+    };
+    assert_eq!(keys.len(), 2);
+    assert_eq!(keys[0].namespace, None);
+    assert_eq!(keys[0].key, "a");
+    assert!(!keys[0].deleted);
+    assert_eq!(keys[1].namespace.as_deref(), Some("namespace"));
+    assert_eq!(keys[1].key, "b");
+    assert!(keys[1].deleted);
+
+    Ok(())
+}
+
+#[test]
+fn view_result_cache() -> anyhow::Result<()> {
+    let path = TestDirectory::new("view-result-cache");
+    let db = Database::open::<Basic>(StorageConfiguration::new(&path).view_result_cache_size(8))?;
+    let cache_hits = || {
+        db.storage
+            .instance
+            .view_result_cache()
+            .expect("cache not enabled")
+            .hits()
+    };
+    db.collection::<Basic>()
+        .push(&Basic::new("a").with_parent_id(1))?;
+
+    let query = || db.view::<BasicByParentId>().with_key(&Some(1)).query();
+    assert_eq!(query()?.len(), 1);
+    assert_eq!(cache_hits(), 0);
+    // The index hasn't changed, so the second query is served from the cache.
+    assert_eq!(query()?.len(), 1);
+    assert_eq!(cache_hits(), 1);
+    // Different parameters are cached separately.
+    assert!(db
+        .view::<BasicByParentId>()
+        .with_key(&Some(2))
+        .query()?
+        .is_empty());
+    assert_eq!(cache_hits(), 1);
+
+    // Writing a document advances the index, invalidating the cached results.
+    db.collection::<Basic>()
+        .push(&Basic::new("b").with_parent_id(1))?;
+    assert_eq!(query()?.len(), 2);
+    assert_eq!(cache_hits(), 1);
+    assert_eq!(query()?.len(), 2);
+    assert_eq!(cache_hits(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn partitioned_view_rebuilds() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::SerializedView;
+    use bonsaidb_core::test_util::{BasicByCategory, BasicByTag};
+    use bonsaidb_core::transaction::{Operation, Transaction};
+
+    type ViewContents = (Vec<(String, u64, usize)>, Vec<(String, usize)>, usize);
+
+    fn view_contents(workers: usize) -> anyhow::Result<ViewContents> {
+        let path = TestDirectory::new(format!("partitioned-view-rebuilds-{workers}"));
+        let db = Database::open::<Basic>(
+            StorageConfiguration::new(&path)
+                .tasks_parallelization(4)
+                .view_rebuild_workers(workers),
+        )?;
+        let mut tx = Transaction::new();
+        for i in 0..3_000 {
+            tx.push(Operation::push_serialized::<Basic>(
+                &Basic::new(i.to_string())
+                    .with_category(format!("category-{}", i % 7))
+                    .with_tag(format!("tag-{}", i % 3)),
+            )?);
+        }
+        tx.apply(&db)?;
+
+        // Every document is mapped when the views are first queried.
+        let mappings = BasicByCategory::entries(&db)
+            .query()?
+            .into_iter()
+            .map(|mapping| {
+                (
+                    mapping.key,
+                    mapping.source.id.deserialize::<u64>().unwrap(),
+                    mapping.value,
+                )
+            })
+            .collect();
+        let grouped = BasicByTag::entries(&db)
+            .reduce_grouped()?
+            .into_iter()
+            .map(|mapping| (mapping.key, mapping.value))
+            .collect();
+        let total = BasicByCategory::entries(&db).reduce()?;
+        Ok((mappings, grouped, total))
+    }
+
+    let single = view_contents(1)?;
+    assert_eq!(single.0.len(), 3_000);
+    assert_eq!(single.2, 3_000);
+    // Two workers each map more than one batch, so the documents are
+    // partitioned.
+    assert_eq!(view_contents(2)?, single);
+
+    Ok(())
+}
+
+#[test]
+fn bucketed_view_keys() -> anyhow::Result<()> {
+    use bonsaidb_core::document::{CollectionDocument, Emit};
+    use bonsaidb_core::schema::view::bucket::Buckets;
+    use bonsaidb_core::schema::{
+        Collection, CollectionMapReduce, ReduceResult, SerializedCollection, View, ViewMapResult,
+        ViewMappedValue, ViewSchema,
+    };
+    use serde::{Deserialize, Serialize};
+
+    const BY_TENS: Buckets = Buckets::fixed_width(10);
+    const BY_POWERS_OF_TEN: Buckets = Buckets::exponential(10);
+
+    #[derive(Collection, Debug, Serialize, Deserialize)]
+    #[collection(name = "samples", views = [SamplesByTens, SamplesByPowersOfTen], core = bonsaidb_core)]
+    struct Sample {
+        value: u64,
+    }
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Sample, key = u64, value = u64, core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct SamplesByTens;
+
+    #[derive(View, ViewSchema, Debug, Clone)]
+    #[view(collection = Sample, key = u64, value = u64, core = bonsaidb_core)]
+    #[view_schema(core = bonsaidb_core)]
+    struct SamplesByPowersOfTen;
+
+    impl CollectionMapReduce for SamplesByTens {
+        fn map<'doc>(&self, document: CollectionDocument<Sample>) -> ViewMapResult<'doc, Self> {
+            let bucket = BY_TENS.bucketize(document.contents.value);
+            document.header.emit_key_and_value(bucket, 1)
+        }
+
+        fn reduce(
+            &self,
+            mappings: &[ViewMappedValue<'_, Self>],
+            _rereduce: bool,
+        ) -> ReduceResult<Self> {
+            Ok(mappings.iter().map(|mapping| mapping.value).sum())
+        }
+    }
+
+    impl CollectionMapReduce for SamplesByPowersOfTen {
+        fn map<'doc>(&self, document: CollectionDocument<Sample>) -> ViewMapResult<'doc, Self> {
+            let bucket = BY_POWERS_OF_TEN.bucketize(document.contents.value);
+            document.header.emit_key_and_value(bucket, 1)
+        }
+
+        fn reduce(
+            &self,
+            mappings: &[ViewMappedValue<'_, Self>],
+            _rereduce: bool,
+        ) -> ReduceResult<Self> {
+            Ok(mappings.iter().map(|mapping| mapping.value).sum())
+        }
+    }
+
+    assert_eq!(BY_TENS.bucket_range(15), 10..=19);
+    assert_eq!(BY_POWERS_OF_TEN.bucket_range(0), 0..=0);
+    assert_eq!(BY_POWERS_OF_TEN.bucket_range(250), 100..=999);
+    assert_eq!(
+        BY_POWERS_OF_TEN.bucket_range(u64::MAX),
+        10_000_000_000_000_000_000..=u64::MAX
+    );
+
+    let path = TestDirectory::new("bucketed-view-keys");
+    let db = Database::open::<Sample>(StorageConfiguration::new(&path))?;
+    for value in [0, 3, 9, 10, 15, 42, 99, 100, 250, 1_000] {
+        Sample { value }.push_into(&db)?;
+    }
+
+    let by_tens = db
+        .view::<SamplesByTens>()
+        .reduce_grouped()?
+        .into_iter()
+        .map(|mapping| (mapping.key, mapping.value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        by_tens,
+        vec![
+            (0, 3),
+            (10, 2),
+            (40, 1),
+            (90, 1),
+            (100, 1),
+            (250, 1),
+            (1_000, 1)
+        ]
+    );
+
+    let by_powers_of_ten = db
+        .view::<SamplesByPowersOfTen>()
+        .reduce_grouped()?
+        .into_iter()
+        .map(|mapping| (mapping.key, mapping.value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        by_powers_of_ten,
+        vec![(0, 1), (1, 2), (10, 4), (100, 2), (1_000, 1)]
+    );
+
+    // Querying a single bucket by its range.
+    assert_eq!(
+        db.view::<SamplesByPowersOfTen>()
+            .with_key_range(BY_POWERS_OF_TEN.bucket_range(42))
+            .reduce()?,
+        4
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "instrument")]
+fn slow_operations_are_logged() -> anyhow::Result<()> {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use bonsaidb_core::keyvalue::KeyValue;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Records the `operation` field of every event.
+    #[derive(Clone, Default)]
+    struct RecordOperations(Arc<Mutex<Vec<String>>>);
+
+    impl RecordOperations {
+        fn logged(&self, operation: &str) -> bool {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|logged| logged == operation)
+        }
+    }
+
+    impl Subscriber for RecordOperations {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            struct Operation(Option<String>);
+
+            impl Visit for Operation {
+                fn record_str(&mut self, field: &Field, value: &str) {
+                    if field.name() == "operation" {
+                        self.0 = Some(value.to_string());
+                    }
+                }
+
+                fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+            }
+
+            let mut operation = Operation(None);
+            event.record(&mut operation);
+            if let Some(operation) = operation.0 {
+                self.0.lock().unwrap().push(operation);
+            }
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    let operations = RecordOperations::default();
+    let path = TestDirectory::new("slow-operations-are-logged");
+    tracing::subscriber::with_default(operations.clone(), || {
+        let db = Database::open::<Basic>(
+            StorageConfiguration::new(&path)
+                .slow_operation_threshold(Duration::ZERO)
+                .slow_operation_level(log::Level::Info),
+        )?;
+        db.collection::<Basic>().push(&Basic::new("slow"))?;
+        db.view::<BasicByParentId>().query()?;
+        db.set_key("key", &0_u32).execute()?;
+
+        for _ in 0_u8..100 {
+            if operations.logged("persist_keys") {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        anyhow::Ok(())
+    })?;
+
+    assert!(operations.logged("apply_transaction"));
+    assert!(operations.logged("query_view"));
+    assert!(operations.logged("persist_keys"));
+
+    Ok(())
+}
+
+#[test]
+fn schema_version_gate() -> anyhow::Result<()> {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    use bonsaidb_core::schema::Schema;
+
+    #[derive(Schema, Debug)]
+    #[schema(name = "versioned", version = 1, collections = [Basic], core = bonsaidb_core)]
+    struct VersionOne;
+
+    #[derive(Schema, Debug)]
+    #[schema(name = "versioned", version = 2, collections = [Basic], core = bonsaidb_core)]
+    struct VersionTwo;
+
+    let path = TestDirectory::new("schema-version-gate");
+    let db = Database::open::<VersionOne>(StorageConfiguration::new(&path))?;
+    db.collection::<Basic>().push(&Basic::new("one"))?;
+    drop(db);
+
+    // Upgrading runs the registered migration with the stored version.
+    let migrated_from = Arc::new(AtomicU64::new(u64::MAX));
+    let db = Database::open::<VersionTwo>(
+        StorageConfiguration::new(&path).with_schema_migration::<VersionTwo, _>({
+            let migrated_from = migrated_from.clone();
+            move |db, stored_version| {
+                assert_eq!(db.collection::<Basic>().all().count()?, 1);
+                migrated_from.store(stored_version, Ordering::SeqCst);
+                Ok(())
+            }
+        })?,
+    )?;
+    assert_eq!(migrated_from.load(Ordering::SeqCst), 1);
+    drop(db);
+
+    // Downgrading is refused when the storage is opened, before the database
+    // is requested.
+    match Storage::open(StorageConfiguration::new(&path).with_schema::<VersionOne>()?) {
+        Err(crate::Error::Core(bonsaidb_core::Error::SchemaVersionMismatch {
+            database_name,
+            schema_version,
+            stored_version,
+            ..
+        })) => {
+            assert_eq!(database_name, "default");
+            assert_eq!(schema_version, 1);
+            assert_eq!(stored_version, 2);
+        }
+        other => unreachable!("expected a schema version mismatch, got {other:?}"),
+    }
+
+    // Opening with the matching version succeeds without migrating again.
+    let db = Database::open::<VersionTwo>(StorageConfiguration::new(&path))?;
+    assert_eq!(db.collection::<Basic>().all().count()?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn idempotency_keys_survive_reopening() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::SerializedCollection;
+    use bonsaidb_core::transaction::Transaction;
+
+    let path = TestDirectory::new("idempotency-keys-survive-reopening");
+    let apply = |db: &Database| {
+        let mut tx = Transaction::new().with_idempotency_key("key");
+        Basic::new("idempotent").push_in_transaction(&mut tx)?;
+        tx.apply(db)
+    };
+    let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+    let first = apply(&db)?;
+    drop(db);
+
+    // The record is committed with the transaction, so a retry after
+    // reopening returns the original results.
+    let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+    assert_eq!(format!("{:?}", apply(&db)?), format!("{first:?}"));
+    assert_eq!(Basic::all(&db).count()?, 1);
+
+    Ok(())
+}
+
+#[test]
+fn query_in_chunks_reads_incrementally() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::Sort;
+    use bonsaidb_core::document::DocumentId;
+    use bonsaidb_core::schema::{SerializedCollection, View};
+    use bonsaidb_core::test_util::BasicByParentIdEager;
+
+    let path = TestDirectory::new("query-in-chunks-reads-incrementally");
+    let db = Database::open::<BasicSchema>(StorageConfiguration::new(&path))?;
+    for parent_id in 0..10 {
+        Basic::new("initial")
+            .with_parent_id(parent_id)
+            .push_into(&db)?;
+    }
+
+    let mut added = None;
+    let mut chunks = Vec::new();
+    db.query_by_name_in_chunks(
+        &BasicByParentIdEager.view_name(),
+        None,
+        Sort::Ascending,
+        None,
+        AccessPolicy::NoUpdate,
+        4,
+        |chunk| {
+            if added.is_none() {
+                // The view is read one chunk at a time, so an entry added
+                // after the first chunk is delivered is included in a later
+                // chunk.
+                let doc = Basic::new("added")
+                    .with_parent_id(100)
+                    .push_into(&db)
+                    .map_err(|err| err.error)?;
+                added = Some(doc.header.id);
+            }
+            chunks.push(chunk);
+            Ok(())
+        },
+    )?;
+    assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [4, 4, 3]);
+    let last = chunks.last().and_then(|chunk| chunk.last()).unwrap();
+    assert_eq!(last.source.id, DocumentId::new(&added.unwrap())?);
+
+    Ok(())
+}
+
+#[test]
+fn counting_view_entries_skips_mappings() -> anyhow::Result<()> {
+    use bonsaidb_core::schema::{Collection, View};
+    use nebari::tree::Unversioned;
+
+    use crate::views::{view_entries_tree_name, ViewEntry};
+
+    let path = TestDirectory::new("counting-view-entries-skips-mappings");
+    let db = Database::open::<Basic>(StorageConfiguration::new(&path))?;
+    db.collection::<Basic>()
+        .push(&Basic::new("a").with_parent_id(1))?;
+    db.collection::<Basic>()
+        .push(&Basic::new("b").with_parent_id(1))?;
+    assert_eq!(db.view::<BasicByParentId>().query()?.len(), 2);
+
+    // Truncate the stored entry immediately after the number of mappings, so
+    // that the entry can only be counted if the mappings are never read.
+    let view_entries = db.roots().tree(db.collection_tree::<Unversioned, _>(
+        &Basic::collection_name(),
+        view_entries_tree_name(&BasicByParentId.view_name()),
+    )?)?;
+    let (key, value) = view_entries
+        .get_range(&(..))?
+        .into_iter()
+        .next()
+        .expect("view entry missing");
+    let entry = bincode::deserialize::<ViewEntry>(&value)?;
+    let truncated_length = 8 + 8 + entry.key.len() + 8;
+    view_entries.set(key, value[..truncated_length].to_vec())?;
+
+    let counts = db
+        .view::<BasicByParentId>()
+        .with_access_policy(AccessPolicy::NoUpdate)
+        .count()?;
+    assert_eq!(counts.len(), 1);
+    assert_eq!(counts[0].key, Some(1));
+    assert_eq!(counts[0].value, 2);
+    assert!(db
+        .view::<BasicByParentId>()
+        .with_access_policy(AccessPolicy::NoUpdate)
+        .query()
+        .is_err());
+
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use std::fmt::Display;
 
 use bonsaidb_core::arc_bytes::serde::Bytes;
-use bonsaidb_core::document::Header;
+use bonsaidb_core::document::{DocumentId, Header};
+use bonsaidb_core::schema::view::map;
 use bonsaidb_core::schema::CollectionName;
 use serde::{Deserialize, Serialize};
 
@@ -14,14 +15,37 @@ pub struct ViewEntry {
     pub reduced_value: Bytes,
 }
 
+/// The beginning of a serialized [`ViewEntry`], used to count an entry's
+/// mappings without deserializing them. Bincode encodes a sequence's length
+/// before its elements and ignores trailing bytes, so deserializing a
+/// [`ViewEntry`] as this type reads the number of mappings and stops.
+#[derive(Debug, Deserialize)]
+pub struct ViewEntryCount {
+    _view_version: u64,
+    pub key: Bytes,
+    pub mappings: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EntryMapping {
     pub source: Header,
     pub value: Bytes,
 }
 
+impl EntryMapping {
+    pub fn to_serialized(&self, key: &[u8]) -> map::Serialized {
+        map::Serialized {
+            source: self.source.clone(),
+            key: Bytes::from(key.to_vec()),
+            value: self.value.clone(),
+        }
+    }
+}
+
 pub mod integrity_scanner;
 pub mod mapper;
+pub mod reductions;
+pub mod result_cache;
 
 pub fn view_entries_tree_name(view_name: &impl Display) -> String {
     format!("view.{view_name:#}")
@@ -36,6 +60,24 @@ pub fn view_invalidated_docs_tree_name(view_name: &impl Display) -> String {
     format!("view.{view_name:#}.invalidated")
 }
 
+/// Used to store related document -> Document IDs mappings, so that when a
+/// related document is updated, the documents that read it can be invalidated.
+pub fn view_dependencies_tree_name(view_name: &impl Display) -> String {
+    format!("view.{view_name:#}.dependencies")
+}
+
+/// Used to store the reduced values of views that are reduced incrementally.
+pub fn view_reductions_tree_name(view_name: &impl Display) -> String {
+    format!("view.{view_name:#}.reductions")
+}
+
+pub fn view_dependency_key(collection: &CollectionName, id: &DocumentId) -> Vec<u8> {
+    let mut key = collection.to_string().into_bytes();
+    key.push(0);
+    key.extend_from_slice(id.as_ref());
+    key
+}
+
 pub fn view_versions_tree_name(collection: &CollectionName) -> String {
     format!("view-versions.{collection:#}")
 }
@@ -13,7 +13,10 @@ use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 
 use super::mapper::{Map, Mapper};
-use super::{view_invalidated_docs_tree_name, view_versions_tree_name};
+use super::{
+    view_dependencies_tree_name, view_invalidated_docs_tree_name, view_reductions_tree_name,
+    view_versions_tree_name,
+};
 use crate::database::{document_tree_name, Database};
 use crate::tasks::handle::Handle;
 use crate::tasks::{Job, Keyed, Task};
@@ -29,6 +32,8 @@ pub struct IntegrityScanner {
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub struct IntegrityScan {
     pub view_version: u64,
+    pub incremental_reduce: bool,
+    pub deletion_history: bool,
     pub database: Arc<Cow<'static, str>>,
     pub collection: CollectionName,
     pub view_name: ViewName,
@@ -59,6 +64,8 @@ impl Job for IntegrityScanner {
 
         let view_name = self.scan.view_name.clone();
         let view_version = self.scan.view_version;
+        let incremental_reduce = self.scan.incremental_reduce;
+        let deletion_history = self.scan.deletion_history;
         let roots = self.database.roots().clone();
         let version = view_versions
             .get(view_name.to_string().as_bytes())?
@@ -68,17 +75,24 @@ impl Job for IntegrityScanner {
         // Remove any old files that are no longer used.
         version.cleanup(&roots, &view_name)?;
 
-        let task = if version.is_current(view_version) {
+        let task = if version.is_current(view_version, incremental_reduce, deletion_history) {
             None
         } else {
             // The view isn't the current version, queue up all documents.
-            let missing_entries = tree_keys::<Versioned>(&documents)?;
+            // Deletion history views don't map existing documents.
+            let missing_entries = if deletion_history {
+                HashSet::new()
+            } else {
+                tree_keys::<Versioned>(&documents)?
+            };
             // When a version is updated, we can make no guarantees about
             // existing keys. The best we can do is delete the existing files so
             // that the view starts fresh.
             roots.delete_tree(view_invalidated_docs_tree_name(&self.scan.view_name))?;
             roots.delete_tree(view_entries_tree_name(&self.scan.view_name))?;
             roots.delete_tree(view_document_map_tree_name(&self.scan.view_name))?;
+            roots.delete_tree(view_dependencies_tree_name(&self.scan.view_name))?;
+            roots.delete_tree(view_reductions_tree_name(&self.scan.view_name))?;
             // Add all missing entries to the invalidated list. The view
             // mapping job will update them on the next pass.
             let invalidated_entries_tree = self.database.collection_tree::<Unversioned, _>(
@@ -91,7 +105,8 @@ impl Job for IntegrityScanner {
                 let mut view_versions = transaction.tree::<Unversioned>(1).unwrap();
                 view_versions.set(
                     view_name.to_string().as_bytes().to_vec(),
-                    ViewVersion::current_for(view_version).to_vec()?,
+                    ViewVersion::current_for(view_version, incremental_reduce, deletion_history)
+                        .to_vec()?,
                 )?;
                 let mut invalidated_entries = transaction.tree::<Unversioned>(0).unwrap();
                 let mut missing_entries = missing_entries
@@ -138,6 +153,10 @@ impl Job for IntegrityScanner {
 pub struct ViewVersion {
     internal_version: u8,
     schema_version: u64,
+    #[serde(default)]
+    incremental_reduce: bool,
+    #[serde(default)]
+    deletion_history: bool,
 }
 
 impl ViewVersion {
@@ -153,6 +172,8 @@ impl ViewVersion {
                 Ok(Self {
                     internal_version: 0,
                     schema_version,
+                    incremental_reduce: false,
+                    deletion_history: false,
                 })
             }
             Err(err) => Err(crate::Error::from(err)),
@@ -163,15 +184,29 @@ impl ViewVersion {
         pot::to_vec(self).map_err(crate::Error::from)
     }
 
-    pub fn current_for(schema_version: u64) -> Self {
+    pub fn current_for(
+        schema_version: u64,
+        incremental_reduce: bool,
+        deletion_history: bool,
+    ) -> Self {
         Self {
             internal_version: Self::CURRENT_VERSION,
             schema_version,
+            incremental_reduce,
+            deletion_history,
         }
     }
 
-    pub fn is_current(&self, schema_version: u64) -> bool {
-        self.internal_version == Self::CURRENT_VERSION && self.schema_version == schema_version
+    pub fn is_current(
+        &self,
+        schema_version: u64,
+        incremental_reduce: bool,
+        deletion_history: bool,
+    ) -> bool {
+        self.internal_version == Self::CURRENT_VERSION
+            && self.schema_version == schema_version
+            && self.incremental_reduce == incremental_reduce
+            && self.deletion_history == deletion_history
     }
 
     pub fn cleanup(&self, roots: &Roots<AnyFile>, view: &ViewName) -> Result<(), crate::Error> {
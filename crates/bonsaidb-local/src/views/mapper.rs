@@ -6,8 +6,12 @@ use std::sync::Arc;
 use bonsaidb_core::arc_bytes::serde::Bytes;
 use bonsaidb_core::arc_bytes::{ArcBytes, OwnedBytes};
 use bonsaidb_core::connection::Connection;
+use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
+use bonsaidb_core::keyvalue::Timestamp;
+use bonsaidb_core::schema::view::map::MapContext;
 use bonsaidb_core::schema::view::{self, map, Serialized, ViewUpdatePolicy};
 use bonsaidb_core::schema::{CollectionName, ViewName};
+use bonsaidb_core::transaction::ViewRowChanges;
 use easy_parallel::Parallel;
 use nebari::io::any::AnyFile;
 use nebari::tree::{AnyTreeRoot, CompareSwap, KeyOperation, Operation, Unversioned, Versioned};
@@ -16,7 +20,8 @@ use nebari::{LockedTransactionTree, Tree, UnlockedTransactionTree};
 use crate::database::{deserialize_document, document_tree_name, Database};
 use crate::tasks::{Job, Keyed, Task};
 use crate::views::{
-    view_document_map_tree_name, view_entries_tree_name, view_invalidated_docs_tree_name,
+    reductions, view_dependencies_tree_name, view_dependency_key, view_document_map_tree_name,
+    view_entries_tree_name, view_invalidated_docs_tree_name, view_reductions_tree_name,
     EntryMapping, ViewEntry,
 };
 use crate::Error;
@@ -73,6 +78,35 @@ impl Job for Mapper {
                     view_invalidated_docs_tree_name(&self.map.view_name),
                 )?)?;
 
+        let view = self
+            .database
+            .data
+            .schema
+            .view_by_name(&self.map.view_name)?;
+        let has_related_collections = !view.related_collections().is_empty();
+        let dependencies =
+            if has_related_collections {
+                Some(self.database.roots().tree(
+                    self.database.collection_tree::<Unversioned, _>(
+                        &self.map.collection,
+                        view_dependencies_tree_name(&self.map.view_name),
+                    )?,
+                )?)
+            } else {
+                None
+            };
+        let reductions =
+            if view.incremental_reduce() {
+                Some(self.database.roots().tree(
+                    self.database.collection_tree::<Unversioned, _>(
+                        &self.map.collection,
+                        view_reductions_tree_name(&self.map.view_name),
+                    )?,
+                )?)
+            } else {
+                None
+            };
+
         let transaction_id = self
             .database
             .last_transaction_id()?
@@ -86,6 +120,8 @@ impl Job for Mapper {
             &document_map,
             &documents,
             &view_entries,
+            dependencies.as_ref(),
+            reductions.as_ref(),
             &storage,
             &map_request,
         )?;
@@ -96,6 +132,9 @@ impl Job for Mapper {
             self.map.view_name.clone(),
             transaction_id,
         );
+        if let Some(cache) = self.database.storage.instance.view_result_cache() {
+            cache.invalidate_view(&self.map.database, &self.map.view_name);
+        }
 
         Ok(transaction_id)
     }
@@ -106,6 +145,8 @@ fn map_view(
     document_map: &Tree<Unversioned, AnyFile>,
     documents: &Tree<Versioned, AnyFile>,
     view_entries: &Tree<Unversioned, AnyFile>,
+    dependencies: Option<&Tree<Unversioned, AnyFile>>,
+    reductions: Option<&Tree<Unversioned, AnyFile>>,
     database: &Database,
     map_request: &Map,
 ) -> Result<(), Error> {
@@ -117,14 +158,23 @@ fn map_view(
         .map(|(key, _)| key)
         .collect::<Vec<_>>();
     while !invalidated_ids.is_empty() {
+        let mut trees = vec![
+            Box::new(invalidated_entries.clone()) as Box<dyn AnyTreeRoot<AnyFile>>,
+            Box::new(document_map.clone()),
+            Box::new(documents.clone()),
+            Box::new(view_entries.clone()),
+        ];
+        let dependencies_index = dependencies.map(|dependencies| {
+            trees.push(Box::new(dependencies.clone()));
+            trees.len() - 1
+        });
+        let reductions_index = reductions.map(|reductions| {
+            trees.push(Box::new(reductions.clone()));
+            trees.len() - 1
+        });
         let transaction = database
             .roots()
-            .transaction::<_, dyn AnyTreeRoot<AnyFile>>(&[
-                Box::new(invalidated_entries.clone()) as Box<dyn AnyTreeRoot<AnyFile>>,
-                Box::new(document_map.clone()),
-                Box::new(documents.clone()),
-                Box::new(view_entries.clone()),
-            ])?;
+            .transaction::<_, dyn AnyTreeRoot<AnyFile>>(&trees)?;
         {
             let view = database
                 .data
@@ -138,16 +188,29 @@ fn map_view(
             let document_map = transaction.unlocked_tree(1).unwrap();
             let documents = transaction.unlocked_tree(2).unwrap();
             let view_entries = transaction.unlocked_tree(3).unwrap();
-            DocumentRequest {
+            let dependencies =
+                dependencies_index.and_then(|index| transaction.unlocked_tree(index));
+            let reductions = reductions_index.and_then(|index| transaction.unlocked_tree(index));
+            let mut request = DocumentRequest {
                 document_ids: document_ids.clone(),
                 map_request,
                 database,
                 document_map,
                 documents,
                 view_entries,
+                dependencies,
+                reductions,
                 view,
+                changes: None,
+            };
+            // Partitioning only pays off when every worker has more than one
+            // batch to map.
+            let workers = database.storage().view_rebuild_workers();
+            if workers > 1 && document_ids.len() > workers * BATCH_SIZE {
+                request.map_partitioned(workers)?;
+            } else {
+                request.map()?;
             }
-            .map()?;
 
             let mut invalidated_entries = transaction.tree::<Unversioned>(0).unwrap();
             invalidated_entries.modify(document_ids, nebari::tree::Operation::Remove)?;
@@ -166,9 +229,19 @@ pub struct DocumentRequest<'a> {
     pub document_map: &'a UnlockedTransactionTree<AnyFile>,
     pub documents: &'a UnlockedTransactionTree<AnyFile>,
     pub view_entries: &'a UnlockedTransactionTree<AnyFile>,
+    /// Present when the view reads related documents while mapping.
+    pub dependencies: Option<&'a UnlockedTransactionTree<AnyFile>>,
+    /// Present when the view's reduced values are stored incrementally.
+    pub reductions: Option<&'a UnlockedTransactionTree<AnyFile>>,
     pub view: &'a dyn Serialized,
+    /// Present when the rows added to and removed from the view should be
+    /// reported.
+    pub changes: Option<&'a mut ViewRowChanges>,
 }
 
+/// The number of documents read and mapped together before being saved.
+const BATCH_SIZE: usize = 1024;
+
 type DocumentIdPayload = (ArcBytes<'static>, Option<ArcBytes<'static>>);
 type BatchPayload = (Vec<ArcBytes<'static>>, flume::Receiver<DocumentIdPayload>);
 
@@ -178,14 +251,16 @@ impl<'a> DocumentRequest<'a> {
         document_ids: &[ArcBytes<'static>],
         documents: &UnlockedTransactionTree<AnyFile>,
     ) -> Result<(), Error> {
-        // Generate batches
-        let mut documents = documents.lock::<Versioned>();
-        for chunk in document_ids.chunks(1024) {
+        // Generate batches. The tree is only locked while reading each chunk
+        // so that partitioned mapping can read from multiple workers.
+        for chunk in document_ids.chunks(BATCH_SIZE) {
             let (document_id_sender, document_id_receiver) = flume::bounded(chunk.len());
             batch_sender
                 .send((chunk.to_vec(), document_id_receiver))
                 .unwrap();
-            let mut documents = documents.get_multiple(chunk.iter().map(ArcBytes::as_slice))?;
+            let mut documents = documents
+                .lock::<Versioned>()
+                .get_multiple(chunk.iter().map(ArcBytes::as_slice))?;
             documents.sort_by(|a, b| a.0.cmp(&b.0));
 
             for document_id in chunk.iter().rev() {
@@ -209,6 +284,8 @@ impl<'a> DocumentRequest<'a> {
         batch_receiver: &flume::Receiver<BatchPayload>,
         mapped_sender: flume::Sender<Batch>,
         view: &dyn Serialized,
+        related_collections: &[CollectionName],
+        database: &Database,
         parallelization: usize,
     ) -> Result<(), Error> {
         // Process batches
@@ -219,34 +296,47 @@ impl<'a> DocumentRequest<'a> {
             };
             for result in Parallel::new()
                 .each(1..=parallelization, |_| -> Result<_, Error> {
+                    let loader = |collection: &CollectionName, id: &DocumentId| {
+                        load_related_document(database, collection, id)
+                    };
                     let mut results = Vec::new();
                     while let Ok((document_id, document)) = document_id_receiver.recv() {
+                        let context = MapContext::new(related_collections, &loader);
                         let map_result = if let Some(document) = document {
                             let document = deserialize_document(&document)?;
 
                             // Call the schema map function
-                            view.map(&document).map_err(bonsaidb_core::Error::from)?
+                            view.map(&document, &context)
+                                .map_err(bonsaidb_core::Error::from)?
                         } else {
                             // Get multiple didn't return this document ID.
                             Vec::new()
                         };
+                        let dependencies = context.into_dependencies();
                         let keys: HashSet<OwnedBytes> = map_result
                             .iter()
                             .map(|map| OwnedBytes::from(map.key.as_slice()))
                             .collect();
                         let new_keys = ArcBytes::from(bincode::serialize(&keys)?);
 
-                        results.push((document_id, new_keys, keys, map_result));
+                        results.push((document_id, new_keys, keys, map_result, dependencies));
                     }
 
                     Ok(results)
                 })
                 .run()
             {
-                for (document_id, new_keys, keys, map_result) in result? {
+                for (document_id, new_keys, keys, map_result, dependencies) in result? {
                     for key in &keys {
                         batch.all_keys.insert(key.0.clone());
                     }
+                    for (collection, id) in dependencies {
+                        batch
+                            .dependencies
+                            .entry(ArcBytes::from(view_dependency_key(&collection, &id)))
+                            .or_insert_with(HashSet::default)
+                            .insert(OwnedBytes(document_id.clone()));
+                    }
                     batch.document_maps.insert(document_id.clone(), new_keys);
                     batch.document_keys.insert(document_id.clone(), keys);
                     for mapping in map_result {
@@ -298,6 +388,7 @@ impl<'a> DocumentRequest<'a> {
         Ok(view_entries_to_clean)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn update_view_entries(
         view: &dyn Serialized,
         map_request: &Map,
@@ -305,6 +396,8 @@ impl<'a> DocumentRequest<'a> {
         all_keys: BTreeSet<ArcBytes<'static>>,
         view_entries_to_clean: BTreeMap<ArcBytes<'static>, HashSet<ArcBytes<'static>>>,
         new_mappings: BTreeMap<ArcBytes<'static>, Vec<map::Serialized>>,
+        reductions: Option<&mut LockedTransactionTree<'_, Unversioned, AnyFile>>,
+        changes: Option<&mut ViewRowChanges>,
     ) -> Result<(), Error> {
         let mut updater = ViewEntryUpdater {
             view,
@@ -313,6 +406,8 @@ impl<'a> DocumentRequest<'a> {
             new_mappings,
             result: Ok(()),
             has_reduce: true,
+            reduced_values: BTreeMap::new(),
+            changes,
         };
         view_entries
             .modify(
@@ -322,15 +417,53 @@ impl<'a> DocumentRequest<'a> {
                 })),
             )
             .map_err(Error::from)
-            .and(updater.result)
+            .and(updater.result)?;
+
+        if let Some(reductions) = reductions {
+            if updater.has_reduce {
+                reductions::update(view, reductions, updater.reduced_values)?;
+            }
+        }
+
+        Ok(())
     }
 
+    fn update_dependencies(
+        dependencies: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+        new_dependencies: &BTreeMap<ArcBytes<'static>, HashSet<OwnedBytes>>,
+    ) -> Result<(), Error> {
+        let mut result = Ok(());
+        dependencies.modify(
+            new_dependencies.keys().cloned().collect(),
+            Operation::CompareSwap(CompareSwap::new(&mut |key, existing| {
+                let mut dependents = existing
+                    .and_then(|existing| {
+                        bincode::deserialize::<HashSet<OwnedBytes>>(&existing).ok()
+                    })
+                    .unwrap_or_default();
+                dependents.extend(new_dependencies[&key[..]].iter().cloned());
+                match bincode::serialize(&dependents) {
+                    Ok(dependents) => KeyOperation::Set(ArcBytes::from(dependents)),
+                    Err(err) => {
+                        result = Err(Error::from(err));
+                        KeyOperation::Skip
+                    }
+                }
+            })),
+        )?;
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn save_mappings(
         mapped_receiver: &flume::Receiver<Batch>,
         view: &dyn Serialized,
         map_request: &Map,
         document_map: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
         view_entries: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+        mut dependencies: Option<LockedTransactionTree<'_, Unversioned, AnyFile>>,
+        mut reductions: Option<LockedTransactionTree<'_, Unversioned, AnyFile>>,
+        mut changes: Option<&mut ViewRowChanges>,
     ) -> Result<(), Error> {
         while let Ok(Batch {
             document_ids,
@@ -338,8 +471,15 @@ impl<'a> DocumentRequest<'a> {
             document_keys,
             new_mappings,
             mut all_keys,
+            dependencies: new_dependencies,
         }) = mapped_receiver.recv()
         {
+            if let Some(dependencies) = &mut dependencies {
+                if !new_dependencies.is_empty() {
+                    Self::update_dependencies(dependencies, &new_dependencies)?;
+                }
+            }
+
             let view_entries_to_clean = Self::update_document_map(
                 document_ids,
                 document_map,
@@ -355,6 +495,8 @@ impl<'a> DocumentRequest<'a> {
                 all_keys,
                 view_entries_to_clean,
                 new_mappings,
+                reductions.as_mut(),
+                changes.as_deref_mut(),
             )?;
         }
         Ok(())
@@ -363,6 +505,14 @@ impl<'a> DocumentRequest<'a> {
     pub fn map(&mut self) -> Result<(), Error> {
         let (batch_sender, batch_receiver) = flume::bounded(1);
         let (mapped_sender, mapped_receiver) = flume::bounded(1);
+        // Related documents can only be read when the dependencies can be
+        // recorded.
+        let related_collections = if self.dependencies.is_some() {
+            self.view.related_collections()
+        } else {
+            Vec::new()
+        };
+        let changes = self.changes.as_deref_mut();
 
         for result in Parallel::new()
             .add(|| Self::generate_batches(batch_sender, &self.document_ids, self.documents))
@@ -371,18 +521,104 @@ impl<'a> DocumentRequest<'a> {
                     &batch_receiver,
                     mapped_sender,
                     self.view,
+                    &related_collections,
+                    self.database,
                     self.database.storage().parallelization(),
                 )
             })
             .add(|| {
                 let mut document_map = self.document_map.lock();
                 let mut view_entries = self.view_entries.lock();
+                let dependencies = self.dependencies.map(|tree| tree.lock());
+                let reductions = self.reductions.map(|tree| tree.lock());
                 Self::save_mappings(
                     &mapped_receiver,
                     self.view,
                     self.map_request,
                     &mut document_map,
                     &mut view_entries,
+                    dependencies,
+                    reductions,
+                    changes,
+                )
+            })
+            .run()
+        {
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Maps the documents by splitting the document ids into `workers`
+    /// contiguous ranges, each of which is read and mapped on its own thread.
+    /// Each worker's batches are saved as soon as they are mapped, so no more
+    /// than a few batches are held in memory at once.
+    pub fn map_partitioned(&mut self, workers: usize) -> Result<(), Error> {
+        let (mapped_sender, mapped_receiver) = flume::bounded(workers);
+        // Related documents can only be read when the dependencies can be
+        // recorded.
+        let related_collections = if self.dependencies.is_some() {
+            self.view.related_collections()
+        } else {
+            Vec::new()
+        };
+        let partition_size = ((self.document_ids.len() + workers - 1) / workers).max(1);
+        let parallelization = (self.database.storage().parallelization() / workers).max(1);
+        let documents = self.documents;
+        let view = self.view;
+        let database = self.database;
+        let changes = self.changes.as_deref_mut();
+
+        for result in Parallel::new()
+            .add(|| {
+                for result in Parallel::new()
+                    .each(
+                        self.document_ids.chunks(partition_size),
+                        |document_ids| -> Result<(), Error> {
+                            let (batch_sender, batch_receiver) = flume::bounded(1);
+                            for result in Parallel::new()
+                                .add(|| {
+                                    Self::generate_batches(batch_sender, document_ids, documents)
+                                })
+                                .add(|| {
+                                    Self::map_batches(
+                                        &batch_receiver,
+                                        mapped_sender.clone(),
+                                        view,
+                                        &related_collections,
+                                        database,
+                                        parallelization,
+                                    )
+                                })
+                                .run()
+                            {
+                                result?;
+                            }
+                            Ok(())
+                        },
+                    )
+                    .run()
+                {
+                    result?;
+                }
+                drop(mapped_sender);
+                Ok(())
+            })
+            .add(|| {
+                let mut document_map = self.document_map.lock();
+                let mut view_entries = self.view_entries.lock();
+                let dependencies = self.dependencies.map(|tree| tree.lock());
+                let reductions = self.reductions.map(|tree| tree.lock());
+                Self::save_mappings(
+                    &mapped_receiver,
+                    self.view,
+                    self.map_request,
+                    &mut document_map,
+                    &mut view_entries,
+                    dependencies,
+                    reductions,
+                    changes,
                 )
             })
             .run()
@@ -401,6 +637,64 @@ struct Batch {
     document_keys: BTreeMap<ArcBytes<'static>, HashSet<OwnedBytes>>,
     new_mappings: BTreeMap<ArcBytes<'static>, Vec<map::Serialized>>,
     all_keys: BTreeSet<ArcBytes<'static>>,
+    dependencies: BTreeMap<ArcBytes<'static>, HashSet<OwnedBytes>>,
+}
+
+/// Adds the entries a deletion history view emits for the deleted document
+/// `header`. Unlike [`DocumentRequest::map()`], existing entries are never
+/// removed.
+pub fn map_deleted_document(
+    view: &dyn Serialized,
+    map_request: &Map,
+    header: &Header,
+    deleted_at: Timestamp,
+    view_entries: &UnlockedTransactionTree<AnyFile>,
+    reductions: Option<&UnlockedTransactionTree<AnyFile>>,
+    changes: Option<&mut ViewRowChanges>,
+) -> Result<(), Error> {
+    let mappings = view
+        .map_deleted(header, deleted_at)
+        .map_err(bonsaidb_core::Error::from)?;
+    if mappings.is_empty() {
+        return Ok(());
+    }
+
+    let mut new_mappings = BTreeMap::<ArcBytes<'static>, Vec<map::Serialized>>::new();
+    for mapping in mappings {
+        new_mappings
+            .entry(ArcBytes::from(mapping.key.to_vec()))
+            .or_default()
+            .push(mapping);
+    }
+    let all_keys = new_mappings.keys().cloned().collect();
+
+    let mut view_entries = view_entries.lock();
+    let mut reductions = reductions.map(|tree| tree.lock());
+    DocumentRequest::update_view_entries(
+        view,
+        map_request,
+        &mut view_entries,
+        all_keys,
+        BTreeMap::new(),
+        new_mappings,
+        reductions.as_mut(),
+        changes,
+    )
+}
+
+fn load_related_document(
+    database: &Database,
+    collection: &CollectionName,
+    id: &DocumentId,
+) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+    let documents = database
+        .roots()
+        .tree(database.collection_tree::<Versioned, _>(collection, document_tree_name(collection))?)
+        .map_err(Error::from)?;
+    match documents.get(id.as_ref()).map_err(Error::from)? {
+        Some(document) => Ok(Some(deserialize_document(&document)?.into_owned())),
+        None => Ok(None),
+    }
 }
 
 impl Keyed<Task> for Mapper {
@@ -416,6 +710,10 @@ struct ViewEntryUpdater<'a> {
     new_mappings: BTreeMap<ArcBytes<'static>, Vec<map::Serialized>>,
     result: Result<(), Error>,
     has_reduce: bool,
+    /// The reduced value of each key that was updated, or `None` if the key
+    /// was removed.
+    reduced_values: BTreeMap<ArcBytes<'static>, Option<ArcBytes<'static>>>,
+    changes: Option<&'a mut ViewRowChanges>,
 }
 
 impl<'a> ViewEntryUpdater<'a> {
@@ -434,11 +732,21 @@ impl<'a> ViewEntryUpdater<'a> {
             });
         let key = key.to_owned();
         if let Some(document_ids) = self.view_entries_to_clean.remove(&key) {
+            if let Some(changes) = &mut self.changes {
+                changes.removed.extend(
+                    view_entry
+                        .mappings
+                        .iter()
+                        .filter(|m| document_ids.contains(m.source.id.as_ref()))
+                        .map(|m| m.to_serialized(&key)),
+                );
+            }
             view_entry
                 .mappings
                 .retain(|m| !document_ids.contains(m.source.id.as_ref()));
 
             if view_entry.mappings.is_empty() && !self.new_mappings.contains_key(&key[..]) {
+                self.reduced_values.insert(key, None);
                 return KeyOperation::Remove;
             } else if self.has_reduce {
                 let mappings = view_entry
@@ -477,6 +785,9 @@ impl<'a> ViewEntryUpdater<'a> {
                     return KeyOperation::Skip;
                 }
                 let entry_mapping = EntryMapping { source, value };
+                if let Some(changes) = &mut self.changes {
+                    changes.added.push(entry_mapping.to_serialized(&key));
+                }
 
                 // attempt to update an existing
                 // entry for this document, if
@@ -485,6 +796,9 @@ impl<'a> ViewEntryUpdater<'a> {
                 for mapping in &mut view_entry.mappings {
                     if mapping.source.id == entry_mapping.source.id {
                         found = true;
+                        if let Some(changes) = &mut self.changes {
+                            changes.removed.push(mapping.to_serialized(&key));
+                        }
                         mapping.source.revision = entry_mapping.source.revision;
                         mapping.value = entry_mapping.value.clone();
                         break;
@@ -531,6 +845,11 @@ impl<'a> ViewEntryUpdater<'a> {
             }
         }
 
+        if self.has_reduce {
+            self.reduced_values
+                .insert(key, Some(ArcBytes::from(view_entry.reduced_value.to_vec())));
+        }
+
         let value = bincode::serialize(&view_entry).unwrap();
         KeyOperation::Set(ArcBytes::from(value))
     }
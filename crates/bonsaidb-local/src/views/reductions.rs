@@ -0,0 +1,370 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+
+use bonsaidb_core::arc_bytes::serde::Bytes;
+use bonsaidb_core::arc_bytes::ArcBytes;
+use bonsaidb_core::connection::{Bound, Range, SerializedQueryKey};
+use bonsaidb_core::schema::view::map::MappedSerializedValue;
+use bonsaidb_core::schema::view::Serialized;
+use nebari::io::any::AnyFile;
+use nebari::tree::{CompareSwap, KeyOperation, Operation, ScanEvaluation, Unversioned};
+use nebari::{LockedTransactionTree, Tree};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+// The reductions tree contains two kinds of entries:
+//
+// - The reduced value of each key in the view, stored under the key prefixed
+//   with `KEY_PREFIX`. These are the entries of level 0.
+// - For each level from 1 through `MAX_LEVEL`, the re-reduced value of each
+//   group of consecutive entries from the level below, stored under
+//   `GROUP_PREFIX`, the level, and the first key of the group.
+//
+// Each key is assigned a level by hashing it. A group at a level starts at
+// each key whose level is at least as high, and contains every entry of the
+// level below up to the next group. Keys before the first such key are in the
+// level's head group. Because a key's level doesn't depend on any other keys,
+// inserting or removing a key only changes the groups containing it, and the
+// groups are the same regardless of the order keys were inserted in.
+//
+// On average, a group contains `2^LEVEL_BITS` entries from the level below, so
+// updating a key re-reduces a bounded number of entries on each level, no
+// matter how the view's keys are distributed. Reducing the entire view only
+// requires re-reducing the groups of the highest level.
+const GROUP_PREFIX: u8 = 0;
+const KEY_PREFIX: u8 = 1;
+
+/// The key of a level's head group, following the level.
+const HEAD_GROUP: u8 = 0;
+/// The prefix of a level's group that starts at a key, following the level.
+const KEY_GROUP: u8 = 1;
+
+/// The number of bits of a key's hash that must be zero for each level the
+/// key is assigned.
+const LEVEL_BITS: u32 = 6;
+const MAX_LEVEL: u8 = 4;
+
+#[derive(Serialize, Deserialize)]
+struct GroupPartial {
+    /// The first key in the group, which is provided as the key of this
+    /// partial when re-reducing.
+    first_key: Bytes,
+    value: Bytes,
+}
+
+fn key_partial_key(key: &[u8]) -> Vec<u8> {
+    let mut partial_key = Vec::with_capacity(key.len() + 1);
+    partial_key.push(KEY_PREFIX);
+    partial_key.extend_from_slice(key);
+    partial_key
+}
+
+/// Returns the tree key of the entry on `level` that starts at `start`, or of
+/// the level's head group if `start` is `None`. On level 0, `None` is the
+/// first possible key partial.
+fn entry_key(level: u8, start: Option<&[u8]>) -> Vec<u8> {
+    if level == 0 {
+        return key_partial_key(start.unwrap_or_default());
+    }
+
+    let mut key = vec![GROUP_PREFIX, level];
+    match start {
+        Some(start) => {
+            key.reserve(start.len() + 1);
+            key.push(KEY_GROUP);
+            key.extend_from_slice(start);
+        }
+        None => key.push(HEAD_GROUP),
+    }
+    key
+}
+
+/// Returns the tree key after every entry on `level`.
+fn level_end(level: u8) -> Bound<Vec<u8>> {
+    if level == 0 {
+        Bound::Unbounded
+    } else {
+        Bound::Excluded(vec![GROUP_PREFIX, level, KEY_GROUP + 1])
+    }
+}
+
+/// Returns the start of the group on `level` stored under `entry_key`.
+fn group_start(entry_key: &[u8]) -> Option<Vec<u8>> {
+    (entry_key[2] == KEY_GROUP).then(|| entry_key[3..].to_vec())
+}
+
+/// Returns the highest level of the groups that start at `key`, or 0 if no
+/// group starts at `key`.
+fn key_level(key: &[u8]) -> u8 {
+    // A 64-bit FNV-1a hash, followed by the MurmurHash3 finalizer to
+    // distribute the bits of similar keys, such as sequential integers.
+    let mut hash = key.iter().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^= hash >> 33;
+    let level = hash.leading_zeros() / LEVEL_BITS;
+    u8::try_from(level).map_or(MAX_LEVEL, |level| level.min(MAX_LEVEL))
+}
+
+/// Returns the key of the first entry in `range`, scanning in the direction
+/// given by `forwards`.
+fn first_entry_key(
+    reductions: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+    range: Range<Vec<u8>>,
+    forwards: bool,
+) -> Result<Option<ArcBytes<'static>>, Error> {
+    let mut keys_read = 0;
+    let mut first_key = None;
+    reductions.scan::<Infallible, _, _, _, _>(
+        &range.map_ref(|key| &key[..]),
+        forwards,
+        |_, _, _| ScanEvaluation::ReadData,
+        |_, _| {
+            if keys_read == 1 {
+                ScanEvaluation::Stop
+            } else {
+                keys_read += 1;
+                ScanEvaluation::ReadData
+            }
+        },
+        |key, _, _| {
+            first_key = Some(key);
+            Ok(())
+        },
+    )?;
+    Ok(first_key)
+}
+
+/// Returns the start of the group on `level` containing `key`. If `exclusive`
+/// is true, a group starting at `key` is skipped, returning the group before
+/// it.
+fn containing_group(
+    reductions: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+    level: u8,
+    key: &[u8],
+    exclusive: bool,
+) -> Result<Option<Vec<u8>>, Error> {
+    let key = entry_key(level, Some(key));
+    let range = Range {
+        start: Bound::Included(entry_key(level, None)),
+        end: if exclusive {
+            Bound::Excluded(key)
+        } else {
+            Bound::Included(key)
+        },
+    };
+    Ok(first_entry_key(reductions, range, false)?.and_then(|entry_key| group_start(&entry_key)))
+}
+
+/// Stores the reduced values in `changes`, removing the keys whose value is
+/// `None`, and then re-reduces each group that contains a changed key.
+pub fn update(
+    view: &dyn Serialized,
+    reductions: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+    changes: BTreeMap<ArcBytes<'static>, Option<ArcBytes<'static>>>,
+) -> Result<(), Error> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let changed_keys = changes
+        .iter()
+        .map(|(key, reduced_value)| (key.to_vec(), key_level(key), reduced_value.is_some()))
+        .collect::<Vec<_>>();
+    let changes = changes
+        .into_iter()
+        .map(|(key, reduced_value)| (ArcBytes::from(key_partial_key(&key)), reduced_value))
+        .collect::<BTreeMap<_, _>>();
+    reductions.modify(
+        changes.keys().cloned().collect(),
+        Operation::CompareSwap(CompareSwap::new(&mut |key, _| match &changes[&key[..]] {
+            Some(reduced_value) => KeyOperation::Set(reduced_value.clone()),
+            None => KeyOperation::Remove,
+        })),
+    )?;
+
+    // Each level's groups are built from the level below, so the levels are
+    // updated from the bottom up.
+    for level in 1..=MAX_LEVEL {
+        // Add and remove the groups starting at changed keys first, so that
+        // the groups containing the changed keys can be found.
+        for (key, highest_level, exists) in &changed_keys {
+            if *highest_level >= level {
+                let group = entry_key(level, Some(key.as_slice()));
+                if *exists {
+                    if reductions.get(&group)?.is_none() {
+                        // The group's value is computed below.
+                        reductions.set(group, b"")?;
+                    }
+                } else {
+                    reductions.remove(&group)?;
+                }
+            }
+        }
+
+        let mut groups = BTreeSet::new();
+        for (key, highest_level, _) in &changed_keys {
+            groups.insert(containing_group(reductions, level, key, false)?);
+            if *highest_level >= level {
+                // The group before this key gained or lost the entries after
+                // this key.
+                groups.insert(containing_group(reductions, level, key, true)?);
+            }
+        }
+
+        for start in groups {
+            update_group(view, reductions, level, start)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-reduces the group on `level` that starts at `start` from the entries of
+/// the level below, removing the group if it doesn't contain any entries.
+fn update_group(
+    view: &dyn Serialized,
+    reductions: &mut LockedTransactionTree<'_, Unversioned, AnyFile>,
+    level: u8,
+    start: Option<Vec<u8>>,
+) -> Result<(), Error> {
+    let group = entry_key(level, start.as_deref());
+    let next_group = first_entry_key(
+        reductions,
+        Range {
+            start: Bound::Excluded(group.clone()),
+            end: level_end(level),
+        },
+        true,
+    )?;
+    let entries = reductions.get_range(
+        &Range {
+            start: Bound::Included(entry_key(level - 1, start.as_deref())),
+            end: match next_group.as_deref().and_then(group_start) {
+                Some(next_start) => Bound::Excluded(entry_key(level - 1, Some(&next_start))),
+                None => level_end(level - 1),
+            },
+        }
+        .map_ref(|key| &key[..]),
+    )?;
+    if entries.is_empty() {
+        reductions.remove(&group)?;
+        return Ok(());
+    }
+
+    let entries = if level == 1 {
+        entries
+            .into_iter()
+            .map(|(key, value)| (key[1..].to_vec(), value.to_vec()))
+            .collect::<Vec<_>>()
+    } else {
+        entries
+            .into_iter()
+            .map(|(_, partial)| {
+                bincode::deserialize::<GroupPartial>(&partial)
+                    .map(|partial| (partial.first_key.into_vec(), partial.value.into_vec()))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let value = if entries.len() == 1 {
+        entries[0].1.clone()
+    } else {
+        view.reduce(
+            &entries
+                .iter()
+                .map(|(key, value)| (&key[..], &value[..]))
+                .collect::<Vec<_>>(),
+            true,
+        )?
+    };
+    let partial = GroupPartial {
+        first_key: Bytes::from(entries[0].0.clone()),
+        value: Bytes::from(value),
+    };
+    reductions.set(group, bincode::serialize(&partial)?)?;
+    Ok(())
+}
+
+/// Returns the reduced value of every entry in the view.
+pub fn reduce(
+    view: &dyn Serialized,
+    reductions: &Tree<Unversioned, AnyFile>,
+) -> Result<Vec<u8>, Error> {
+    let mut groups = reductions
+        .get_range(
+            &Range {
+                start: Bound::Included(entry_key(MAX_LEVEL, None)),
+                end: level_end(MAX_LEVEL),
+            }
+            .map_ref(|key| &key[..]),
+        )?
+        .into_iter()
+        .map(|(_, partial)| bincode::deserialize::<GroupPartial>(&partial))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if groups.len() == 1 {
+        Ok(groups.pop().unwrap().value.to_vec())
+    } else {
+        view.reduce(
+            &groups
+                .iter()
+                .map(|partial| (&partial.first_key[..], &partial.value[..]))
+                .collect::<Vec<_>>(),
+            true,
+        )
+        .map_err(Error::from)
+    }
+}
+
+/// Converts a range of view keys into the range of their key partials.
+fn key_partial_range(range: Range<Bytes>) -> Range<Vec<u8>> {
+    Range {
+        start: match range.start {
+            Bound::Unbounded => Bound::Included(vec![KEY_PREFIX]),
+            start => start.map(|key| key_partial_key(&key)),
+        },
+        end: range.end.map(|key| key_partial_key(&key)),
+    }
+}
+
+/// Returns the reduced value of each key matching `key`, in ascending order.
+pub fn reduce_grouped(
+    reductions: &Tree<Unversioned, AnyFile>,
+    key: Option<SerializedQueryKey>,
+) -> Result<Vec<MappedSerializedValue>, Error> {
+    let partials = match key {
+        None => reductions.get_range(&([KEY_PREFIX].as_slice()..))?,
+        Some(SerializedQueryKey::Range(range)) => {
+            reductions.get_range(&key_partial_range(range).map_ref(|key| &key[..]))?
+        }
+        Some(SerializedQueryKey::Matches(key)) => {
+            let key = key_partial_key(&key);
+            reductions
+                .get(&key)?
+                .map(|value| (ArcBytes::from(key), value))
+                .into_iter()
+                .collect()
+        }
+        Some(SerializedQueryKey::Multiple(list)) => {
+            let mut keys = list
+                .iter()
+                .map(|key| key_partial_key(key))
+                .collect::<Vec<_>>();
+            keys.sort();
+            reductions.get_multiple(keys.iter().map(Vec::as_slice))?
+        }
+    };
+
+    Ok(partials
+        .into_iter()
+        .map(|(key, value)| MappedSerializedValue {
+            key: Bytes::from(key[1..].to_vec()),
+            value: Bytes::from(value.to_vec()),
+        })
+        .collect())
+}
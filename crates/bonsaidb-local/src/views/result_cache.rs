@@ -0,0 +1,126 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use bonsaidb_core::schema::view::map;
+use bonsaidb_core::schema::ViewName;
+use parking_lot::Mutex;
+
+/// The state of a view's index when a query was evaluated. Cached results are
+/// only returned when the view's index is at the same position.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IndexPosition {
+    /// The last transaction id the view's mapper indexed.
+    pub indexed: u64,
+    /// The database's last transaction id. Eager views are updated as part of
+    /// each transaction, so any new transaction may have changed the index.
+    pub database: Option<u64>,
+}
+
+type CacheKey = (Arc<Cow<'static, str>>, ViewName, Vec<u8>);
+
+/// A bounded cache of view query results. Entries are evicted
+/// least-recently-used first once `capacity` entries are cached.
+#[derive(Debug)]
+pub struct ViewResultCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, CachedResults>,
+    access_counter: u64,
+}
+
+#[derive(Debug)]
+struct CachedResults {
+    position: IndexPosition,
+    results: Vec<map::Serialized>,
+    last_access: u64,
+}
+
+impl ViewResultCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::default(),
+            hits: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the results cached for `parameters`, if they were cached while
+    /// the view's index was at `position`.
+    pub fn get(
+        &self,
+        database: &Arc<Cow<'static, str>>,
+        view: &ViewName,
+        parameters: &[u8],
+        position: IndexPosition,
+    ) -> Option<Vec<map::Serialized>> {
+        let key = (database.clone(), view.clone(), parameters.to_vec());
+        let mut state = self.state.lock();
+        state.access_counter += 1;
+        let access = state.access_counter;
+        let cached = state.entries.get_mut(&key)?;
+        if cached.position == position {
+            cached.last_access = access;
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            Some(cached.results.clone())
+        } else {
+            state.entries.remove(&key);
+            None
+        }
+    }
+
+    /// Caches `results` for `parameters`, evicting the least recently used
+    /// entry if the cache is full.
+    pub fn insert(
+        &self,
+        database: &Arc<Cow<'static, str>>,
+        view: &ViewName,
+        parameters: Vec<u8>,
+        position: IndexPosition,
+        results: Vec<map::Serialized>,
+    ) {
+        let key = (database.clone(), view.clone(), parameters);
+        let mut state = self.state.lock();
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.capacity {
+            let least_recently_used = state
+                .entries
+                .iter()
+                .min_by_key(|(_, cached)| cached.last_access)
+                .map(|(key, _)| key.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                state.entries.remove(&least_recently_used);
+            }
+        }
+        state.access_counter += 1;
+        let last_access = state.access_counter;
+        state.entries.insert(
+            key,
+            CachedResults {
+                position,
+                results,
+                last_access,
+            },
+        );
+    }
+
+    /// Removes all cached results for `view`.
+    pub fn invalidate_view(&self, database: &Arc<Cow<'static, str>>, view: &ViewName) {
+        let mut state = self.state.lock();
+        state
+            .entries
+            .retain(|(cached_database, cached_view, _), _| {
+                cached_database != database || cached_view != view
+            });
+    }
+
+    /// Returns the number of queries that have been answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+}
@@ -78,6 +78,8 @@ struct CollectionAttribute {
     primary_key: Option<Type>,
     #[attribute(example = "self.0 or something(self)")]
     natural_id: Option<Expr>,
+    #[attribute(example = "IdStrategy::TimeOrdered")]
+    id_strategy: Option<Expr>,
     #[attribute(example = "bosaidb::core")]
     core: Option<Path>,
 }
@@ -102,6 +104,7 @@ pub fn collection_derive(input: proc_macro::TokenStream) -> Result {
         serialization,
         mut primary_key,
         mut natural_id,
+        id_strategy,
         core,
         encryption_key,
         encryption_required,
@@ -229,6 +232,14 @@ pub fn collection_derive(input: proc_macro::TokenStream) -> Result {
         }
     });
 
+    let id_strategy = id_strategy.map(|id_strategy| {
+        quote! {
+            fn id_strategy() -> #core::schema::IdStrategy {
+                #id_strategy
+            }
+        }
+    });
+
     Ok(quote! {
         impl #impl_generics #core::schema::Collection for #ident #ty_generics #where_clause {
             type PrimaryKey = #primary_key;
@@ -241,6 +252,7 @@ pub fn collection_derive(input: proc_macro::TokenStream) -> Result {
                 Ok(())
             }
             #encryption
+            #id_strategy
         }
         #serialization
     })
@@ -256,7 +268,7 @@ pub fn view_derive(input: proc_macro::TokenStream) -> Result {
 }
 /// Derives the `bonsaidb::core::schema::ViewSchema` trait.
 #[manyhow]
-/// `#[view_schema(version = 1, policy = Unique, view=ViewType, mapped_key=KeyType<'doc>)]`
+/// `#[view_schema(version = 1, policy = Unique, view=ViewType, mapped_key=KeyType<'doc>, related_collections = [OtherCollection], incremental_reduce, deletion_history)]`
 ///
 /// All attributes are optional.
 #[proc_macro_derive(ViewSchema, attributes(view_schema))]
@@ -271,6 +283,8 @@ struct SchemaAttribute {
     name: String,
     #[attribute(example = "\"authority\"")]
     authority: Option<Expr>,
+    #[attribute(example = "1")]
+    version: Option<u64>,
     #[attribute(optional, example = "[SomeCollection, AnotherCollection]")]
     collections: Vec<Type>,
     #[attribute(optional, example = "[SomeSchema, AnotherSchema]")]
@@ -281,8 +295,8 @@ struct SchemaAttribute {
 
 /// Derives the `bonsaidb::core::schema::Schema` trait.
 ///
-/// `#[schema(name = "Name", authority = "Authority", collections = [A, B, C]), core = bonsaidb::core]`
-/// `authority`, `collections` and `core` are optional
+/// `#[schema(name = "Name", authority = "Authority", version = 1, collections = [A, B, C]), core = bonsaidb::core]`
+/// `authority`, `version`, `collections` and `core` are optional
 #[manyhow]
 #[proc_macro_derive(Schema, attributes(schema))]
 pub fn schema_derive(input: proc_macro::TokenStream) -> Result {
@@ -296,6 +310,7 @@ pub fn schema_derive(input: proc_macro::TokenStream) -> Result {
     let SchemaAttribute {
         name,
         authority,
+        version,
         collections,
         include,
         core,
@@ -308,6 +323,11 @@ pub fn schema_derive(input: proc_macro::TokenStream) -> Result {
         || quote!(#core::schema::Qualified::private(#name)),
         |authority| quote!(#core::schema::Qualified::new(#authority, #name)),
     );
+    let version = version.map(|version| {
+        quote!(fn schema_version() -> u64 {
+            #version
+        })
+    });
 
     Ok(quote! {
         impl #impl_generics #core::schema::Schema for #ident #ty_generics #where_clause {
@@ -315,6 +335,8 @@ pub fn schema_derive(input: proc_macro::TokenStream) -> Result {
                 #name
             }
 
+            #version
+
             fn define_collections(
                 schema: &mut #core::schema::Schematic
             ) -> Result<(), #core::Error> {
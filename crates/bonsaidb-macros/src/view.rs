@@ -98,6 +98,10 @@ struct ViewSchemaAttribute {
     version: Option<u64>,
     #[attribute(example = "Lazy")]
     policy: Option<Ident>,
+    #[attribute(optional, example = "[SomeCollection, AnotherCollection]")]
+    related_collections: Vec<Type>,
+    incremental_reduce: bool,
+    deletion_history: bool,
     #[attribute(example = "bosaidb::core")]
     core: Option<Path>,
 }
@@ -115,6 +119,9 @@ pub fn derive_schema(
         mapped_key,
         version,
         policy,
+        related_collections,
+        incremental_reduce,
+        deletion_history,
         core,
     } = ViewSchemaAttribute::from_attributes(&attrs)?;
 
@@ -139,6 +146,28 @@ pub fn derive_schema(
         })
     });
 
+    let related_collections = (!related_collections.is_empty()).then(|| {
+        quote!(fn related_collections(&self) -> Vec<#core::schema::CollectionName> {
+            vec![#( <#related_collections as #core::schema::Collection>::collection_name() ),*]
+        })
+    });
+
+    let incremental_reduce = incremental_reduce.then(|| {
+        quote!(
+            fn incremental_reduce(&self) -> bool {
+                true
+            }
+        )
+    });
+
+    let deletion_history = deletion_history.then(|| {
+        quote!(
+            fn deletion_history(&self) -> bool {
+                true
+            }
+        )
+    });
+
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     Ok(quote! {
@@ -148,6 +177,9 @@ pub fn derive_schema(
 
             #version
             #policy
+            #related_collections
+            #incremental_reduce
+            #deletion_history
         }
     })
 }
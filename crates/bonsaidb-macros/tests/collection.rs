@@ -2,8 +2,8 @@ use core::fmt::Debug;
 
 use bonsaidb::core::document::{CollectionDocument, Emit, KeyId};
 use bonsaidb::core::schema::{
-    Collection, CollectionMapReduce, DefaultSerialization, DefaultViewSerialization, Name,
-    Qualified, Schematic, SerializedCollection, View, ViewMapResult, ViewSchema,
+    Collection, CollectionMapReduce, DefaultSerialization, DefaultViewSerialization, IdStrategy,
+    Name, Qualified, Schematic, SerializedCollection, View, ViewMapResult, ViewSchema,
 };
 use serde::{Deserialize, Serialize};
 
@@ -141,6 +141,16 @@ fn encryption_key() {
     struct Test;
 }
 
+#[test]
+fn id_strategy() {
+    #[derive(Collection, Debug, Deserialize, Serialize)]
+    #[collection(name = "Name")]
+    #[collection(id_strategy = IdStrategy::TimeOrdered)]
+    struct Test;
+
+    assert_eq!(Test::id_strategy(), IdStrategy::TimeOrdered);
+}
+
 #[test]
 fn primary_key() {
     #[derive(Collection, Debug, Deserialize, Serialize)]
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
-use bonsaidb::core::key::{Key, KeyEncoding};
+use bonsaidb::core::key::{ByteSource, Key, KeyEncoding};
 
 #[test]
 fn tuple_struct() {
@@ -141,3 +142,46 @@ fn lifetime() {
             .as_ref()
     )
 }
+
+#[test]
+fn fieldless_enum_ordering() {
+    #[derive(Clone, Copy, Debug, Key, PartialEq, Eq)]
+    enum Priority {
+        Low,
+        Normal,
+        High,
+        Urgent,
+    }
+
+    let variants = [
+        Priority::Low,
+        Priority::Normal,
+        Priority::High,
+        Priority::Urgent,
+    ];
+    let encoded = variants
+        .iter()
+        .map(|variant| (variant.as_ord_bytes().unwrap().to_vec(), *variant))
+        .collect::<BTreeMap<_, _>>();
+
+    // Round-trip each variant, and ensure the encoded order matches the
+    // declaration order.
+    for (bytes, variant) in &encoded {
+        assert_eq!(
+            Priority::from_ord_bytes(ByteSource::Borrowed(bytes)).unwrap(),
+            *variant
+        );
+    }
+    assert_eq!(encoded.values().copied().collect::<Vec<_>>(), variants);
+
+    // A range over the encoded keys selects the variants in between.
+    let start = Priority::Normal.as_ord_bytes().unwrap().to_vec();
+    let end = Priority::Urgent.as_ord_bytes().unwrap().to_vec();
+    assert_eq!(
+        encoded
+            .range(start..end)
+            .map(|(_, variant)| *variant)
+            .collect::<Vec<_>>(),
+        [Priority::Normal, Priority::High]
+    );
+}
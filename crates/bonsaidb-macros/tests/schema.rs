@@ -37,6 +37,15 @@ fn name_and_authority() {
     );
 }
 #[test]
+fn version() {
+    #[derive(Schema, Debug)]
+    #[schema(name = "name", version = 3)]
+    struct Test;
+
+    assert_eq!(Test::schema_version(), 3);
+    assert_eq!(Schematic::from_schema::<Test>().unwrap().version, 3);
+}
+#[test]
 fn collections() {
     #[derive(Schema, Debug)]
     #[schema(name = "name", authority = "authority", collections = [TestCollection])]
@@ -4,10 +4,16 @@ use std::marker::PhantomData;
 use async_trait::async_trait;
 use bonsaidb_core::api::{self, Api, ApiError, Infallible};
 use bonsaidb_core::arc_bytes::serde::Bytes;
+use bonsaidb_core::keyvalue::Timestamp;
 use bonsaidb_core::permissions::PermissionDenied;
 use bonsaidb_core::schema::{InsertError, InvalidNameError};
 
-use crate::{Backend, ConnectedClient, CustomServer, Error, NoBackend};
+use crate::{Backend, BackendError, ConnectedClient, CustomServer, Error, NoBackend};
+
+mod procedure;
+
+pub(crate) use self::procedure::ProcedureHandler;
+pub use self::procedure::{Procedure, ProcedureTransaction};
 
 /// A trait that can dispatch requests for a [`Api`].
 #[async_trait]
@@ -30,6 +36,28 @@ pub struct HandlerSession<'a, B: Backend = NoBackend> {
     pub as_client: CustomServer<B>,
     /// The connected client making the API request.
     pub client: &'a ConnectedClient<B>,
+    /// The time after which the client no longer needs a response to this
+    /// request, if the client provided one.
+    pub deadline: Option<Timestamp>,
+}
+
+impl<B: Backend> HandlerSession<'_, B> {
+    /// Returns [`bonsaidb_core::Error::DeadlineExceeded`] if this request's
+    /// deadline has passed. Handlers should call this before starting
+    /// expensive work, allowing the server to abandon requests whose response
+    /// would no longer be used.
+    pub fn check_deadline(&self) -> Result<(), bonsaidb_core::Error> {
+        check_deadline(self.deadline)
+    }
+}
+
+pub(crate) fn check_deadline(deadline: Option<Timestamp>) -> Result<(), bonsaidb_core::Error> {
+    match deadline {
+        Some(deadline) if deadline <= Timestamp::now() => {
+            Err(bonsaidb_core::Error::DeadlineExceeded)
+        }
+        _ => Ok(()),
+    }
 }
 
 #[async_trait]
@@ -60,13 +88,72 @@ where
     A: Api,
 {
     async fn handle(&self, client: HandlerSession<'_, B>, request: &[u8]) -> Result<Bytes, Error> {
-        let request = pot::from_slice(request)?;
+        let request = pot::from_slice::<A>(request)?;
+        match client
+            .server
+            .backend()
+            .authorize_request(&client, &A::name(), &request)
+            .await
+        {
+            Ok(()) => {}
+            Err(BackendError::Server(err)) => return Err(err),
+            Err(BackendError::Backend(err)) => return Err(Error::other("backend", err)),
+        }
         let response = match T::handle(client, request).await {
             Ok(response) => Ok(response),
             Err(HandlerError::Api(err)) => Err(err),
             Err(HandlerError::Server(err)) => return Err(err),
         };
-        Ok(Bytes::from(pot::to_vec(&response)?))
+        serialize_response(&response, client.server.response_size_limit())
+    }
+}
+
+/// Serializes `response`, stopping as soon as more than `limit` bytes have
+/// been written.
+fn serialize_response<T: serde::Serialize>(
+    response: &T,
+    limit: Option<usize>,
+) -> Result<Bytes, Error> {
+    let Some(limit) = limit else {
+        return Ok(Bytes::from(pot::to_vec(response)?));
+    };
+
+    let mut writer = LimitedWriter {
+        bytes: Vec::new(),
+        limit,
+        exceeded: false,
+    };
+    match pot::to_writer(response, &mut writer) {
+        Ok(()) => Ok(Bytes::from(writer.bytes)),
+        Err(_) if writer.exceeded => Err(Error::from(bonsaidb_core::Error::ResponseTooLarge {
+            limit,
+        })),
+        Err(err) => Err(Error::from(err)),
+    }
+}
+
+/// A writer that fails once more than `limit` bytes are written to it.
+struct LimitedWriter {
+    bytes: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl std::io::Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.bytes.len() + buf.len() > self.limit {
+            self.exceeded = true;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "response size limit exceeded",
+            ));
+        }
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
     }
 }
 
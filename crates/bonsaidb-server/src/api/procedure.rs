@@ -0,0 +1,177 @@
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use bonsaidb_core::api::Api;
+use bonsaidb_core::document::CollectionDocument;
+use bonsaidb_core::key::KeyEncoding;
+use bonsaidb_core::schema::SerializedCollection;
+use bonsaidb_core::transaction::{Command, Operation, Transaction};
+
+use super::{Handler, HandlerError, HandlerResult, HandlerSession};
+use crate::{Backend, NoBackend, ServerDatabase};
+
+/// A server-side procedure that reads and writes a database atomically while
+/// handling a single [`Api`] request.
+///
+/// Procedures are registered using
+/// [`ServerConfiguration::with_procedure()`](crate::ServerConfiguration::with_procedure).
+/// Each time a request is received, [`execute()`](Self::execute) is invoked
+/// with a new [`ProcedureTransaction`]. Documents read through the transaction
+/// are checked to still be current when the transaction's writes are applied.
+/// If another client modified or deleted any of them in the meantime, none of
+/// the writes are applied and the procedure is executed again, up to
+/// [`MAX_ATTEMPTS`](Self::MAX_ATTEMPTS) times.
+///
+/// Because the procedure may be executed more than once for a single request,
+/// it should not have side effects outside of the transaction.
+#[async_trait]
+pub trait Procedure<A: Api, B: Backend = NoBackend>: Send + Sync {
+    /// The maximum number of times the procedure is executed for a single
+    /// request before the conflict is returned to the client.
+    const MAX_ATTEMPTS: usize = 5;
+
+    /// Returns the name of the database the procedure operates on.
+    fn database_name(request: &A) -> &str;
+
+    /// Executes the procedure for `request`. The writes pushed to
+    /// `transaction` are applied after this function returns successfully,
+    /// and the returned response is sent to the client once they have been
+    /// applied.
+    async fn execute(
+        session: &HandlerSession<'_, B>,
+        transaction: &mut ProcedureTransaction<B>,
+        request: &A,
+    ) -> HandlerResult<A>;
+}
+
+/// A transaction being built by a [`Procedure`].
+///
+/// Writes are not applied until the procedure returns, and are not visible to
+/// reads made through this type.
+#[derive(Debug)]
+pub struct ProcedureTransaction<B: Backend = NoBackend> {
+    database: ServerDatabase<B>,
+    transaction: Transaction,
+}
+
+impl<B: Backend> ProcedureTransaction<B> {
+    fn new(database: ServerDatabase<B>) -> Self {
+        Self {
+            database,
+            transaction: Transaction::new(),
+        }
+    }
+
+    /// Returns the database this transaction will be applied to. Reads made
+    /// directly through the database are not checked when the transaction is
+    /// applied.
+    #[must_use]
+    pub const fn database(&self) -> &ServerDatabase<B> {
+        &self.database
+    }
+
+    /// Retrieves the document with `id` from the collection `C`. If the
+    /// document is found, the transaction will fail with a conflict if the
+    /// document is modified or deleted before the transaction is applied.
+    ///
+    /// A document that isn't found is not checked: the transaction does not
+    /// fail if the document is inserted before the transaction is applied.
+    pub async fn get<C, PrimaryKey>(
+        &mut self,
+        id: &PrimaryKey,
+    ) -> Result<Option<CollectionDocument<C>>, bonsaidb_core::Error>
+    where
+        C: SerializedCollection,
+        PrimaryKey: KeyEncoding<C::PrimaryKey>,
+    {
+        let document = C::get_async(id, &self.database).await?;
+        if let Some(document) = &document {
+            self.transaction
+                .push(Operation::check_document_is_current::<C, _>(document)?);
+        }
+        Ok(document)
+    }
+
+    /// Pushes `operation` to be applied with this transaction.
+    pub fn push(&mut self, operation: Operation) {
+        self.transaction.push(operation);
+    }
+
+    /// Pushes an operation that updates `document` with its current contents.
+    /// The update fails with a conflict if the document has been modified
+    /// since it was retrieved.
+    pub fn update<C: SerializedCollection>(
+        &mut self,
+        document: &CollectionDocument<C>,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.transaction.push(Operation::update_serialized::<C>(
+            document.header.clone(),
+            &document.contents,
+        )?);
+        Ok(())
+    }
+
+    /// Pushes an operation that inserts a new document into `C` containing
+    /// `contents`. If `id` is `None`, a unique id will be generated.
+    pub fn insert<C: SerializedCollection>(
+        &mut self,
+        id: Option<&C::PrimaryKey>,
+        contents: &C::Contents,
+    ) -> Result<(), bonsaidb_core::Error> {
+        self.transaction
+            .push(Operation::insert_serialized::<C>(id, contents)?);
+        Ok(())
+    }
+
+    /// Applies the transaction if it contains any writes. A transaction that
+    /// only contains checks has nothing to apply.
+    async fn apply(self) -> Result<(), bonsaidb_core::Error> {
+        let has_writes = self
+            .transaction
+            .operations
+            .iter()
+            .any(|operation| !matches!(operation.command, Command::Check { .. }));
+        if has_writes {
+            self.transaction.apply_async(&self.database).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Handles an [`Api`] by executing the [`Procedure`] `P`.
+pub(crate) struct ProcedureHandler<P>(PhantomData<P>);
+
+#[async_trait]
+impl<P, A, B> Handler<A, B> for ProcedureHandler<P>
+where
+    P: Procedure<A, B>,
+    A: Api,
+    B: Backend,
+{
+    async fn handle(session: HandlerSession<'_, B>, request: A) -> HandlerResult<A> {
+        let database = ServerDatabase {
+            server: session.as_client.clone(),
+            db: session
+                .as_client
+                .database_without_schema(P::database_name(&request))
+                .await?,
+        };
+
+        let mut attempt = 1;
+        loop {
+            session.check_deadline()?;
+            let mut transaction = ProcedureTransaction::new(database.clone());
+            let response = P::execute(&session, &mut transaction, &request).await?;
+            match transaction.apply().await {
+                Ok(()) => return Ok(response),
+                Err(
+                    bonsaidb_core::Error::DocumentConflict(..)
+                    | bonsaidb_core::Error::DocumentNotFound(..),
+                ) if attempt < P::MAX_ATTEMPTS => {
+                    attempt += 1;
+                }
+                Err(err) => return Err(HandlerError::from(err)),
+            }
+        }
+    }
+}
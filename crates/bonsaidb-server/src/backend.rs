@@ -1,13 +1,17 @@
+use std::any::Any;
 use std::convert::Infallible;
 use std::fmt::Debug;
 
 use async_trait::async_trait;
+use bonsaidb_core::api::ApiName;
 use bonsaidb_core::connection::Session;
 use bonsaidb_core::permissions::PermissionDenied;
-use bonsaidb_core::schema::{InsertError, InvalidNameError};
+use bonsaidb_core::schema::{CollectionName, InsertError, InvalidNameError};
+use bonsaidb_core::transaction::Transaction;
 
+use crate::api::HandlerSession;
 use crate::server::ConnectedClient;
-use crate::{CustomServer, Error, ServerConfiguration};
+use crate::{CustomServer, Error, ServerConfiguration, ServerDatabase};
 
 /// Tailors the behavior of a server to your needs.
 #[async_trait]
@@ -105,6 +109,35 @@ pub trait Backend: Debug + Send + Sync + Sized + 'static {
         );
         Ok(())
     }
+
+    /// Invoked before every request received from a client is executed,
+    /// including the requests BonsaiDb uses to implement its own APIs such as
+    /// [`ExecuteKeyOperation`](bonsaidb_core::networking::ExecuteKeyOperation)
+    /// and [`ApplyTransaction`](bonsaidb_core::networking::ApplyTransaction).
+    ///
+    /// `request` is the deserialized request of the api named `api`. It can be
+    /// inspected using [`downcast_ref()`](Any::downcast_ref). For example,
+    /// `request.downcast_ref::<ExecuteKeyOperation>()` will return the
+    /// key-value operation being requested, allowing a policy to permit reads
+    /// while denying writes.
+    ///
+    /// Returning an error prevents the request from being executed, and the
+    /// error is returned to the client. To signal that the client isn't
+    /// allowed to perform the request, return a
+    /// [`PermissionDenied`](bonsaidb_core::Error::PermissionDenied) error.
+    ///
+    /// This check is performed in addition to the permission checks performed
+    /// against the session's effective permissions. The default implementation
+    /// allows all requests.
+    #[allow(unused_variables)]
+    async fn authorize_request(
+        &self,
+        session: &HandlerSession<'_, Self>,
+        api: &ApiName,
+        request: &(dyn Any + Send + Sync),
+    ) -> Result<(), BackendError<Self::Error>> {
+        Ok(())
+    }
 }
 
 /// A [`Backend`] with no custom functionality.
@@ -116,6 +149,62 @@ impl Backend for NoBackend {
     type Error = Infallible;
 }
 
+/// Validates transactions before they are applied to a [`ServerDatabase`].
+///
+/// Validators are registered for a collection using
+/// [`ServerConfiguration::with_transaction_validator()`], and are invoked for
+/// each transaction that contains at least one operation on that collection.
+/// Deleting every document in the collection using `delete_all()` is
+/// validated by [`validate_delete_all()`](Self::validate_delete_all) instead.
+/// Documents deleted by a view query using `delete_docs()` are removed without
+/// invoking validators.
+#[async_trait]
+pub trait TransactionValidator<B: Backend>: Debug + Send + Sync + 'static {
+    /// Validates `transaction` before it is applied to `database`.
+    ///
+    /// `database` can be used to read the current state of the database. The
+    /// transaction is applied after all validators return, so changes made
+    /// by other connections in the meantime are not seen by the validator.
+    /// Invariants that must hold even when transactions race should also be
+    /// enforced by the transaction itself, such as by including
+    /// [`Command::Check`](bonsaidb_core::transaction::Command::Check)
+    /// operations.
+    ///
+    /// Returning an error prevents the transaction from being applied, and the
+    /// error is returned to the caller. Errors returned as
+    /// [`BackendError::Backend`] are converted to
+    /// [`bonsaidb_core::Error::Validation`] for the collection this validator
+    /// was registered for, allowing remote clients to identify the rejected
+    /// collection. A validator can also return
+    /// [`bonsaidb_core::Error::validation()`] directly.
+    async fn validate(
+        &self,
+        transaction: &Transaction,
+        database: &ServerDatabase<B>,
+    ) -> Result<(), BackendError<B::Error>>;
+
+    /// Validates deleting every document in `collection` using
+    /// `delete_all()`, before the collection is cleared.
+    ///
+    /// The collection is cleared without building a transaction, so the
+    /// deleted documents aren't passed to [`validate()`](Self::validate). By
+    /// default, deleting every document is rejected. Validators that allow
+    /// it should override this function.
+    #[allow(unused_variables)]
+    async fn validate_delete_all(
+        &self,
+        collection: &CollectionName,
+        database: &ServerDatabase<B>,
+    ) -> Result<(), BackendError<B::Error>> {
+        Err(BackendError::Server(Error::Core(
+            bonsaidb_core::Error::validation(
+                collection.clone(),
+                "deleting every document is not allowed",
+            ),
+        )))
+    }
+}
+
 /// Controls how a server should handle a connection.
 pub enum ConnectionHandling {
     /// The server should accept this connection.
@@ -3,21 +3,24 @@ use std::marker::PhantomData;
 use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bonsaidb_core::api;
 use bonsaidb_core::api::ApiName;
 #[cfg(feature = "encryption")]
 use bonsaidb_core::document::KeyId;
 use bonsaidb_core::permissions::{Permissions, Statement};
-use bonsaidb_core::schema::Schema;
+use bonsaidb_core::schema::{Collection, CollectionName, Schema};
 #[cfg(feature = "compression")]
 use bonsaidb_local::config::Compression;
-use bonsaidb_local::config::{Builder, KeyValuePersistence, StorageConfiguration};
+use bonsaidb_local::config::{
+    Builder, CorruptKeyValuePolicy, KeyValuePersistence, KeyValueWorker, StorageConfiguration,
+};
 #[cfg(feature = "encryption")]
 use bonsaidb_local::vault::AnyVaultKeyStorage;
 
-use crate::api::{AnyHandler, AnyWrapper, Handler};
-use crate::{Backend, Error, NoBackend};
+use crate::api::{AnyHandler, AnyWrapper, Handler, Procedure, ProcedureHandler};
+use crate::{Backend, Error, NoBackend, TransactionValidator};
 
 /// Configuration options for [`Server`](crate::Server)
 #[derive(Debug, Clone)]
@@ -33,8 +36,23 @@ pub struct ServerConfiguration<B: Backend = NoBackend> {
     /// relative to `request_workers` such that one client cannot overwhelm the
     /// entire queue.
     pub client_simultaneous_request_limit: usize,
+    /// Number of key-value watchers a single client can have registered at a
+    /// time across all of its sessions. Default value is 16.
+    pub client_key_watcher_limit: usize,
     /// Number of simultaneous requests to be processed. Default value is 16.
     pub request_workers: usize,
+    /// The maximum number of bytes a single response may serialize to. When a
+    /// response exceeds this limit, serialization is stopped and
+    /// [`bonsaidb_core::Error::ResponseTooLarge`] is returned to the client
+    /// instead. Default value is `None`, which does not limit responses.
+    pub response_size_limit: Option<usize>,
+    /// When set, a request received from a client that already has
+    /// `client_simultaneous_request_limit` requests in flight is rejected with
+    /// [`bonsaidb_core::Error::Busy`] instead of waiting for one of the
+    /// client's requests to complete. This duration is returned to the client
+    /// as the suggested delay before retrying. Default value is `None`, which
+    /// queues requests instead of rejecting them.
+    pub busy_retry_after: Option<Duration>,
     /// Configuration options for individual databases.
     pub storage: StorageConfiguration,
     /// The permissions granted to all connections to this server.
@@ -44,6 +62,8 @@ pub struct ServerConfiguration<B: Backend = NoBackend> {
     pub acme: AcmeConfiguration,
 
     pub(crate) custom_apis: HashMap<ApiName, Arc<dyn AnyHandler<B>>>,
+    pub(crate) transaction_validators:
+        HashMap<CollectionName, Vec<Arc<dyn TransactionValidator<B>>>>,
 }
 
 impl<B: Backend> ServerConfiguration<B> {
@@ -53,12 +73,16 @@ impl<B: Backend> ServerConfiguration<B> {
             backend,
             server_name: String::from("bonsaidb"),
             client_simultaneous_request_limit: 16,
+            client_key_watcher_limit: 16,
             // TODO this was arbitrarily picked, it probably should be higher,
             // but it also should probably be based on the cpu's capabilities
             request_workers: 16,
+            response_size_limit: None,
+            busy_retry_after: None,
             storage: bonsaidb_local::config::StorageConfiguration::default(),
             default_permissions: DefaultPermissions::Permissions(Permissions::default()),
             custom_apis: HashMap::default(),
+            transaction_validators: HashMap::default(),
             #[cfg(feature = "acme")]
             acme: AcmeConfiguration::default(),
         }
@@ -81,12 +105,30 @@ impl<B: Backend> ServerConfiguration<B> {
         self
     }
 
+    /// Sets [`Self::client_key_watcher_limit`](Self#structfield.client_key_watcher_limit) to `watcher_limit` and returns self.
+    pub const fn client_key_watcher_limit(mut self, watcher_limit: usize) -> Self {
+        self.client_key_watcher_limit = watcher_limit;
+        self
+    }
+
     /// Sets [`Self::request_workers`](Self#structfield.request_workers) to `workers` and returns self.
     pub const fn request_workers(mut self, workers: usize) -> Self {
         self.request_workers = workers;
         self
     }
 
+    /// Sets [`Self::response_size_limit`](Self#structfield.response_size_limit) to `limit` and returns self.
+    pub const fn response_size_limit(mut self, limit: usize) -> Self {
+        self.response_size_limit = Some(limit);
+        self
+    }
+
+    /// Sets [`Self::busy_retry_after`](Self#structfield.busy_retry_after) to `retry_after` and returns self.
+    pub const fn busy_retry_after(mut self, retry_after: Duration) -> Self {
+        self.busy_retry_after = Some(retry_after);
+        self
+    }
+
     /// Sets [`Self::default_permissions`](Self#structfield.default_permissions) to `default_permissions` and returns self.
     pub fn default_permissions<P: Into<DefaultPermissions>>(
         mut self,
@@ -130,6 +172,45 @@ impl<B: Backend> ServerConfiguration<B> {
         self.register_custom_api::<Dispatcher, Api>()?;
         Ok(self)
     }
+
+    /// Registers `P` to execute each [`Api`][api::Api] request it receives
+    /// within a transaction. See [`Procedure`] for more information.
+    pub fn register_procedure<P: Procedure<Api, B> + 'static, Api: api::Api>(
+        &mut self,
+    ) -> Result<(), Error> {
+        self.register_custom_api::<ProcedureHandler<P>, Api>()
+    }
+
+    /// Registers the procedure `P` and returns self.
+    pub fn with_procedure<P: Procedure<Api, B> + 'static, Api: api::Api>(
+        mut self,
+    ) -> Result<Self, Error> {
+        self.register_procedure::<P, Api>()?;
+        Ok(self)
+    }
+
+    /// Registers `validator` to be invoked before applying each transaction
+    /// that contains an operation on the collection `C`. Multiple validators
+    /// can be registered for the same collection, and they are invoked in the
+    /// order they were registered.
+    pub fn register_transaction_validator<C: Collection, V: TransactionValidator<B>>(
+        &mut self,
+        validator: V,
+    ) {
+        self.transaction_validators
+            .entry(C::collection_name())
+            .or_default()
+            .push(Arc::new(validator));
+    }
+
+    /// Registers `validator` for the collection `C` and returns self.
+    pub fn with_transaction_validator<C: Collection, V: TransactionValidator<B>>(
+        mut self,
+        validator: V,
+    ) -> Self {
+        self.register_transaction_validator::<C, V>(validator);
+        self
+    }
 }
 
 impl<B> Default for ServerConfiguration<B>
@@ -210,6 +291,19 @@ impl<B: Backend> Builder for ServerConfiguration<B> {
         Ok(self)
     }
 
+    fn with_schema_migration<S, F>(mut self, migration: F) -> Result<Self, bonsaidb_local::Error>
+    where
+        S: Schema,
+        F: Fn(&bonsaidb_local::Database, u64) -> Result<(), bonsaidb_core::Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.storage
+            .register_schema_with_migration::<S, F>(migration)?;
+        Ok(self)
+    }
+
     fn memory_only(mut self) -> Self {
         self.storage.memory_only = true;
         self
@@ -220,6 +314,11 @@ impl<B: Backend> Builder for ServerConfiguration<B> {
         self
     }
 
+    fn shard_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.storage.shard_paths.push(path.as_ref().to_owned());
+        self
+    }
+
     fn unique_id(mut self, unique_id: u64) -> Self {
         self.storage.unique_id = Some(unique_id);
         self
@@ -255,6 +354,16 @@ impl<B: Backend> Builder for ServerConfiguration<B> {
         self
     }
 
+    fn view_result_cache_size(mut self, entries: usize) -> Self {
+        self.storage.views.result_cache_size = entries;
+        self
+    }
+
+    fn view_rebuild_workers(mut self, workers: usize) -> Self {
+        self.storage.views.rebuild_workers = workers;
+        self
+    }
+
     #[cfg(feature = "compression")]
     fn default_compression(mut self, compression: Compression) -> Self {
         self.storage.default_compression = Some(compression);
@@ -266,6 +375,56 @@ impl<B: Backend> Builder for ServerConfiguration<B> {
         self
     }
 
+    fn corrupt_key_value_policy(mut self, policy: CorruptKeyValuePolicy) -> Self {
+        self.storage.corrupt_key_value_policy = policy;
+        self
+    }
+
+    fn key_value_worker(mut self, worker: KeyValueWorker) -> Self {
+        self.storage.key_value_worker = worker;
+        self
+    }
+
+    fn lock_wait(mut self, wait: Duration) -> Self {
+        self.storage.lock_wait = Some(wait);
+        self
+    }
+
+    fn transaction_log_retention(mut self, transactions: u64) -> Self {
+        self.storage.transaction_log_retention.transactions = Some(transactions);
+        self
+    }
+
+    fn transaction_log_max_age(mut self, age: Duration) -> Self {
+        self.storage.transaction_log_retention.max_age = Some(age);
+        self
+    }
+
+    fn list_transactions_max_results(mut self, max_results: u32) -> Self {
+        self.storage.list_transactions_max_results = max_results;
+        self
+    }
+
+    fn max_document_size(mut self, bytes: usize) -> Self {
+        self.storage.max_document_size = bytes;
+        self
+    }
+
+    fn blocking_threads(mut self, threads: usize) -> Self {
+        self.storage.blocking_threads = Some(threads);
+        self
+    }
+
+    fn slow_operation_threshold(mut self, threshold: Duration) -> Self {
+        self.storage.slow_operation_threshold = Some(threshold);
+        self
+    }
+
+    fn slow_operation_level(mut self, level: log::Level) -> Self {
+        self.storage.slow_operation_level = level;
+        self
+    }
+
     fn authenticated_permissions<P: Into<Permissions>>(
         mut self,
         authenticated_permissions: P,
@@ -6,18 +6,22 @@ use bonsaidb_core::connection::{
 };
 use bonsaidb_core::keyvalue::AsyncKeyValue;
 use bonsaidb_core::networking::{
-    AlterUserPermissionGroupMembership, AlterUserRoleMembership, ApplyTransaction, AssumeIdentity,
-    Compact, CompactCollection, CompactKeyValueStore, Count, CreateDatabase, CreateSubscriber,
-    CreateUser, DeleteDatabase, DeleteDocs, DeleteUser, ExecuteKeyOperation, Get, GetMultiple,
+    AlterUserPermissionGroupMembership, AlterUserRoleMembership, ApplyTransaction,
+    ApplyTransactionWithViews, AssumeIdentity, Compact, CompactCollection, CompactKeyValueStore,
+    Count, CountGrouped, CreateDatabase, CreateSubscriber, CreateUser, DeleteAll, DeleteDatabase,
+    DeleteDocs, DeleteUser, ExecuteKeyOperation, ExecuteKeyPipeline, Exists, Get,
+    GetKeyExpirations, GetMultiple, GetTombstone, GetViewIndexState, KeysExpiringBetween,
     LastTransactionId, List, ListAvailableSchemas, ListDatabases, ListExecutedTransactions,
-    ListHeaders, LogOutSession, Publish, PublishToAll, Query, QueryWithDocs, Reduce, ReduceGrouped,
-    SubscribeTo, UnregisterSubscriber, UnsubscribeFrom,
+    ListHeaders, LogOutSession, Publish, PublishToAll, PurgeTombstones, Query, QueryChunk,
+    QueryKeys, QueryWithDocs, RebuildView, Reduce, ReduceGrouped, RenameKeyNamespace,
+    RestoreKeyNamespace, ScanKeyNamespace, StreamQuery, SubscribeTo, UnregisterSubscriber,
+    UnsubscribeFrom, UnwatchKeys, WatchKeys,
 };
 #[cfg(feature = "password-hashing")]
 use bonsaidb_core::networking::{Authenticate, SetUserPassword};
 use bonsaidb_core::pubsub::AsyncPubSub;
 
-use crate::api::{Handler, HandlerError, HandlerResult, HandlerSession};
+use crate::api::{check_deadline, Handler, HandlerError, HandlerResult, HandlerSession};
 use crate::{Backend, Error, ServerConfiguration};
 
 #[cfg_attr(not(feature = "password-hashing"), allow(unused_mut))]
@@ -28,6 +32,7 @@ pub fn register_api_handlers<B: Backend>(
         .with_api::<ServerDispatcher, AlterUserPermissionGroupMembership>()?
         .with_api::<ServerDispatcher, AlterUserRoleMembership>()?
         .with_api::<ServerDispatcher, ApplyTransaction>()?
+        .with_api::<ServerDispatcher, ApplyTransactionWithViews>()?
         .with_api::<ServerDispatcher, AssumeIdentity>()?
         .with_api::<ServerDispatcher, Compact>()?
         .with_api::<ServerDispatcher, CompactCollection>()?
@@ -36,12 +41,22 @@ pub fn register_api_handlers<B: Backend>(
         .with_api::<ServerDispatcher, CreateDatabase>()?
         .with_api::<ServerDispatcher, CreateSubscriber>()?
         .with_api::<ServerDispatcher, CreateUser>()?
+        .with_api::<ServerDispatcher, DeleteAll>()?
         .with_api::<ServerDispatcher, DeleteDatabase>()?
         .with_api::<ServerDispatcher, DeleteDocs>()?
         .with_api::<ServerDispatcher, DeleteUser>()?
         .with_api::<ServerDispatcher, ExecuteKeyOperation>()?
+        .with_api::<ServerDispatcher, ExecuteKeyPipeline>()?
         .with_api::<ServerDispatcher, Get>()?
+        .with_api::<ServerDispatcher, Exists>()?
+        .with_api::<ServerDispatcher, GetTombstone>()?
+        .with_api::<ServerDispatcher, PurgeTombstones>()?
         .with_api::<ServerDispatcher, GetMultiple>()?
+        .with_api::<ServerDispatcher, KeysExpiringBetween>()?
+        .with_api::<ServerDispatcher, GetKeyExpirations>()?
+        .with_api::<ServerDispatcher, ScanKeyNamespace>()?
+        .with_api::<ServerDispatcher, RenameKeyNamespace>()?
+        .with_api::<ServerDispatcher, RestoreKeyNamespace>()?
         .with_api::<ServerDispatcher, LastTransactionId>()?
         .with_api::<ServerDispatcher, List>()?
         .with_api::<ServerDispatcher, ListHeaders>()?
@@ -53,11 +68,18 @@ pub fn register_api_handlers<B: Backend>(
         .with_api::<ServerDispatcher, PublishToAll>()?
         .with_api::<ServerDispatcher, Query>()?
         .with_api::<ServerDispatcher, QueryWithDocs>()?
+        .with_api::<ServerDispatcher, QueryKeys>()?
         .with_api::<ServerDispatcher, Reduce>()?
         .with_api::<ServerDispatcher, ReduceGrouped>()?
+        .with_api::<ServerDispatcher, CountGrouped>()?
+        .with_api::<ServerDispatcher, GetViewIndexState>()?
+        .with_api::<ServerDispatcher, RebuildView>()?
+        .with_api::<ServerDispatcher, StreamQuery>()?
         .with_api::<ServerDispatcher, SubscribeTo>()?
         .with_api::<ServerDispatcher, UnregisterSubscriber>()?
-        .with_api::<ServerDispatcher, UnsubscribeFrom>()?;
+        .with_api::<ServerDispatcher, UnsubscribeFrom>()?
+        .with_api::<ServerDispatcher, UnwatchKeys>()?
+        .with_api::<ServerDispatcher, WatchKeys>()?;
 
     #[cfg(feature = "password-hashing")]
     {
@@ -298,6 +320,54 @@ impl<B: Backend> Handler<Get, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<Exists, B> for ServerDispatcher {
+    async fn handle(session: HandlerSession<'_, B>, command: Exists) -> HandlerResult<Exists> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .exists_in_collection(command.id, &command.collection)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<GetTombstone, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: GetTombstone,
+    ) -> HandlerResult<GetTombstone> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .get_tombstone_from_collection(command.id, &command.collection)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<PurgeTombstones, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: PurgeTombstones,
+    ) -> HandlerResult<PurgeTombstones> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .purge_tombstones(command.before)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<GetMultiple, B> for ServerDispatcher {
     async fn handle(
@@ -308,6 +378,7 @@ impl<B: Backend> Handler<GetMultiple, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.database)
             .await?;
+        session.check_deadline()?;
         database
             .get_multiple_from_collection(&command.ids, &command.collection)
             .await
@@ -322,6 +393,7 @@ impl<B: Backend> Handler<List, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.database)
             .await?;
+        session.check_deadline()?;
         database
             .list_from_collection(
                 command.ids,
@@ -344,6 +416,7 @@ impl<B: Backend> Handler<ListHeaders, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.0.database)
             .await?;
+        session.check_deadline()?;
         database
             .list_headers_from_collection(
                 command.0.ids,
@@ -370,6 +443,23 @@ impl<B: Backend> Handler<Count, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<DeleteAll, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: DeleteAll,
+    ) -> HandlerResult<DeleteAll> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .delete_all_from_collection(&command.collection)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<Query, B> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Query) -> HandlerResult<Query> {
@@ -377,6 +467,7 @@ impl<B: Backend> Handler<Query, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.database)
             .await?;
+        session.check_deadline()?;
         database
             .query_by_name(
                 &command.view,
@@ -390,6 +481,59 @@ impl<B: Backend> Handler<Query, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<StreamQuery, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: StreamQuery,
+    ) -> HandlerResult<StreamQuery> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.query.database)
+            .await?
+            .as_blocking()
+            .clone();
+        session.check_deadline()?;
+        let client = session.client.clone();
+        let client_session = session.as_client.session().cloned();
+        let deadline = session.deadline;
+        let StreamQuery {
+            stream_id,
+            chunk_size,
+            query,
+        } = command;
+        tokio::task::spawn_blocking(move || {
+            let mut chunks_sent = 0;
+            database.query_by_name_in_chunks(
+                &query.view,
+                query.key,
+                query.order,
+                query.limit,
+                query.access_policy,
+                usize::try_from(chunk_size).unwrap_or(usize::MAX),
+                |mappings| {
+                    check_deadline(deadline)?;
+                    client
+                        .send::<QueryChunk>(
+                            client_session.as_ref(),
+                            &QueryChunk {
+                                stream_id,
+                                mappings,
+                            },
+                        )
+                        .map_err(|err| bonsaidb_core::Error::other("bonsaidb-server", err))?;
+                    chunks_sent += 1;
+                    Ok(())
+                },
+            )?;
+            Ok::<_, bonsaidb_core::Error>(chunks_sent)
+        })
+        .await
+        .map_err(bonsaidb_local::Error::from)?
+        .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<QueryWithDocs, B> for ServerDispatcher {
     async fn handle(
@@ -400,6 +544,7 @@ impl<B: Backend> Handler<QueryWithDocs, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.0.database)
             .await?;
+        session.check_deadline()?;
         database
             .query_by_name_with_docs(
                 &command.0.view,
@@ -413,6 +558,30 @@ impl<B: Backend> Handler<QueryWithDocs, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<QueryKeys, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: QueryKeys,
+    ) -> HandlerResult<QueryKeys> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.0.database)
+            .await?;
+        session.check_deadline()?;
+        database
+            .query_keys_by_name(
+                &command.0.view,
+                command.0.key,
+                command.0.order,
+                command.0.limit,
+                command.0.access_policy,
+            )
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<Reduce, B> for ServerDispatcher {
     async fn handle(session: HandlerSession<'_, B>, command: Reduce) -> HandlerResult<Reduce> {
@@ -420,6 +589,7 @@ impl<B: Backend> Handler<Reduce, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.database)
             .await?;
+        session.check_deadline()?;
         database
             .reduce_by_name(&command.view, command.key, command.access_policy)
             .await
@@ -438,6 +608,7 @@ impl<B: Backend> Handler<ReduceGrouped, B> for ServerDispatcher {
             .as_client
             .database_without_schema(&command.0.database)
             .await?;
+        session.check_deadline()?;
         database
             .reduce_grouped_by_name(&command.0.view, command.0.key, command.0.access_policy)
             .await
@@ -445,6 +616,58 @@ impl<B: Backend> Handler<ReduceGrouped, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<CountGrouped, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: CountGrouped,
+    ) -> HandlerResult<CountGrouped> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.0.database)
+            .await?;
+        session.check_deadline()?;
+        database
+            .count_grouped_by_name(&command.0.view, command.0.key, command.0.access_policy)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<GetViewIndexState, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: GetViewIndexState,
+    ) -> HandlerResult<GetViewIndexState> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .view_index_state_by_name(&command.view)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<RebuildView, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: RebuildView,
+    ) -> HandlerResult<RebuildView> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .rebuild_view_by_name(&command.view)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<ApplyTransaction, B> for ServerDispatcher {
     async fn handle(
@@ -462,6 +685,23 @@ impl<B: Backend> Handler<ApplyTransaction, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<ApplyTransactionWithViews, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: ApplyTransactionWithViews,
+    ) -> HandlerResult<ApplyTransactionWithViews> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .apply_transaction_with_views(command.transaction)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<DeleteDocs, B> for ServerDispatcher {
     async fn handle(
@@ -619,6 +859,46 @@ impl<B: Backend> Handler<UnregisterSubscriber, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<WatchKeys, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: WatchKeys,
+    ) -> HandlerResult<WatchKeys> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        session
+            .client
+            .watch_keys(
+                session.server,
+                database.as_blocking(),
+                command.watch_id,
+                command.namespace,
+                command.prefix,
+                session.as_client.session().cloned(),
+            )
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<UnwatchKeys, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: UnwatchKeys,
+    ) -> HandlerResult<UnwatchKeys> {
+        session
+            .client
+            .unwatch_keys(
+                command.watch_id,
+                session.as_client.session().and_then(|session| session.id),
+            )
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<ExecuteKeyOperation, B> for ServerDispatcher {
     async fn handle(
@@ -636,6 +916,112 @@ impl<B: Backend> Handler<ExecuteKeyOperation, B> for ServerDispatcher {
     }
 }
 
+#[async_trait]
+impl<B: Backend> Handler<ExecuteKeyPipeline, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: ExecuteKeyPipeline,
+    ) -> HandlerResult<ExecuteKeyPipeline> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .execute_key_pipeline(command.pipeline)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<KeysExpiringBetween, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: KeysExpiringBetween,
+    ) -> HandlerResult<KeysExpiringBetween> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .keys_expiring_between(command.start, command.end)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<GetKeyExpirations, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: GetKeyExpirations,
+    ) -> HandlerResult<GetKeyExpirations> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .execute_key_expirations(command.namespace, command.keys)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<ScanKeyNamespace, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: ScanKeyNamespace,
+    ) -> HandlerResult<ScanKeyNamespace> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .execute_namespace_scan(command.namespace, command.after, command.limit)
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<RenameKeyNamespace, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: RenameKeyNamespace,
+    ) -> HandlerResult<RenameKeyNamespace> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .rename_namespace(command.from.as_deref(), command.to.as_deref())
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
+#[async_trait]
+impl<B: Backend> Handler<RestoreKeyNamespace, B> for ServerDispatcher {
+    async fn handle(
+        session: HandlerSession<'_, B>,
+        command: RestoreKeyNamespace,
+    ) -> HandlerResult<RestoreKeyNamespace> {
+        let database = session
+            .as_client
+            .database_without_schema(&command.database)
+            .await?;
+        database
+            .restore_to(
+                command.namespace.as_deref(),
+                command.timestamp,
+                command.target.as_deref(),
+            )
+            .await
+            .map_err(HandlerError::from)
+    }
+}
+
 #[async_trait]
 impl<B: Backend> Handler<CompactCollection, B> for ServerDispatcher {
     async fn handle(
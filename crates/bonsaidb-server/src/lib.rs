@@ -33,7 +33,9 @@ pub use config::{
     AcmeConfiguration, LETS_ENCRYPT_PRODUCTION_DIRECTORY, LETS_ENCRYPT_STAGING_DIRECTORY,
 };
 
-pub use self::backend::{Backend, BackendError, ConnectionHandling, NoBackend};
+pub use self::backend::{
+    Backend, BackendError, ConnectionHandling, NoBackend, TransactionValidator,
+};
 pub use self::config::{BonsaiListenConfig, DefaultPermissions, ServerConfiguration};
 pub use self::error::Error;
 pub use self::server::{
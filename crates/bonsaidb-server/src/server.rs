@@ -19,9 +19,11 @@ use bonsaidb_core::connection::{
 use bonsaidb_core::networking::{self, Payload, CURRENT_PROTOCOL_VERSION};
 use bonsaidb_core::permissions::bonsai::{bonsaidb_resource_name, BonsaiAction, ServerAction};
 use bonsaidb_core::permissions::Permissions;
-use bonsaidb_core::schema::{self, Nameable, NamedCollection, Schema, SchemaSummary};
+use bonsaidb_core::schema::{
+    self, CollectionName, Nameable, NamedCollection, Schema, SchemaSummary,
+};
 use bonsaidb_local::config::Builder;
-use bonsaidb_local::{AsyncStorage, Storage, StorageNonBlocking};
+use bonsaidb_local::{AsyncStorage, Database, DatabaseNonBlocking, Storage, StorageNonBlocking};
 use bonsaidb_utils::fast_async_lock;
 use derive_where::derive_where;
 use fabruic::{self, CertificateChain, Endpoint, KeyPair, PrivateKey};
@@ -33,7 +35,7 @@ use schema::SchemaName;
 #[cfg(not(windows))]
 use signal_hook::consts::SIGQUIT;
 use signal_hook::consts::{SIGINT, SIGTERM};
-use tokio::sync::{oneshot, Notify};
+use tokio::sync::{oneshot, watch, Notify};
 
 use crate::api::{AnyHandler, HandlerSession};
 use crate::backend::ConnectionHandling;
@@ -43,7 +45,9 @@ use crate::dispatch::{register_api_handlers, ServerDispatcher};
 use crate::error::Error;
 use crate::hosted::{Hosted, SerializablePrivateKey, TlsCertificate, TlsCertificatesByDomain};
 use crate::server::shutdown::{Shutdown, ShutdownState, ShutdownStateWatcher};
-use crate::{Backend, BackendError, BonsaiListenConfig, NoBackend, ServerConfiguration};
+use crate::{
+    Backend, BackendError, BonsaiListenConfig, NoBackend, ServerConfiguration, TransactionValidator,
+};
 
 #[cfg(feature = "acme")]
 pub mod acme;
@@ -55,8 +59,8 @@ mod tcp;
 #[cfg(feature = "websockets")]
 mod websockets;
 
-use self::connected_client::OwnedClient;
 pub use self::connected_client::{ConnectedClient, LockedClientDataGuard, Transport};
+use self::connected_client::{KeyWatchHub, KeyWatcher, OwnedClient};
 pub use self::database::ServerDatabase;
 pub use self::tcp::{ApplicationProtocols, HttpService, Peer, StandardTcpProtocols, TcpService};
 
@@ -92,9 +96,16 @@ struct Data<B: Backend = NoBackend> {
     request_processor: flume::Sender<ClientRequest<B>>,
     default_session: Session,
     client_simultaneous_request_limit: usize,
+    client_key_watcher_limit: usize,
+    response_size_limit: Option<usize>,
+    busy_retry_after: Option<Duration>,
+    key_watch_hubs: Mutex<HashMap<String, KeyWatchHub>>,
     primary_tls_key: CachedCertifiedKey,
+    /// Notified each time `primary_tls_key` is replaced.
+    certificate_changes: watch::Sender<()>,
     primary_domain: String,
     custom_apis: RwLock<HashMap<ApiName, Arc<dyn AnyHandler<B>>>>,
+    transaction_validators: HashMap<CollectionName, Vec<Arc<dyn TransactionValidator<B>>>>,
     #[cfg(feature = "acme")]
     acme: AcmeConfiguration,
     #[cfg(feature = "acme")]
@@ -102,6 +113,31 @@ struct Data<B: Backend = NoBackend> {
     shutdown: Shutdown,
 }
 
+/// Validates `certificate_chain` and `private_key`, returning the key used to
+/// serve TLS connections.
+fn certified_key(
+    certificate_chain: &CertificateChain,
+    private_key: &PrivateKey,
+) -> Result<Arc<CertifiedKey>, Error> {
+    if certificate_chain.iter().next().is_none() {
+        return Err(Error::Core(bonsaidb_core::Error::other(
+            "bonsaidb-server config",
+            "certificate chain is empty",
+        )));
+    }
+    KeyPair::from_parts(certificate_chain.clone(), private_key.clone())?;
+
+    let certificates = certificate_chain
+        .iter()
+        .map(|cert| rustls::Certificate(cert.as_ref().to_vec()))
+        .collect::<Vec<_>>();
+    let private_key =
+        rustls::PrivateKey(fabruic::dangerous::PrivateKey::as_ref(private_key).to_vec());
+    let private_key = rustls::sign::any_ecdsa_type(&Arc::new(private_key))?;
+
+    Ok(Arc::new(CertifiedKey::new(certificates, private_key)))
+}
+
 #[derive(Default)]
 struct CachedCertifiedKey(Mutex<Option<Arc<CertifiedKey>>>);
 
@@ -143,14 +179,20 @@ impl<B: Backend> CustomServer<B> {
                                     data: client_request.server.data.clone(),
                                     storage,
                                 },
+                                deadline: request.deadline,
                             };
-                            ServerDispatcher::dispatch_api_request(
-                                client,
-                                &request.name,
-                                request.value.unwrap(),
-                            )
-                            .await
-                            .map_err(bonsaidb_core::Error::from)
+                            // The request may have waited in the queue past
+                            // its deadline.
+                            match client.check_deadline() {
+                                Ok(()) => ServerDispatcher::dispatch_api_request(
+                                    client,
+                                    &request.name,
+                                    request.value.unwrap(),
+                                )
+                                .await
+                                .map_err(bonsaidb_core::Error::from),
+                                Err(err) => Err(err),
+                            }
                         }
                         Err(err) => Err(err),
                     };
@@ -176,9 +218,15 @@ impl<B: Backend> CustomServer<B> {
                     ..Session::default()
                 },
                 client_simultaneous_request_limit: configuration.client_simultaneous_request_limit,
+                client_key_watcher_limit: configuration.client_key_watcher_limit,
+                response_size_limit: configuration.response_size_limit,
+                busy_retry_after: configuration.busy_retry_after,
+                key_watch_hubs: Mutex::default(),
                 primary_tls_key: CachedCertifiedKey::default(),
+                certificate_changes: watch::channel(()).0,
                 primary_domain: configuration.server_name,
                 custom_apis: parking_lot::RwLock::new(configuration.custom_apis),
+                transaction_validators: configuration.transaction_validators,
                 #[cfg(feature = "acme")]
                 acme: configuration.acme,
                 #[cfg(feature = "acme")]
@@ -228,11 +276,51 @@ impl<B: Backend> CustomServer<B> {
         }
     }
 
+    pub(crate) fn response_size_limit(&self) -> Option<usize> {
+        self.data.response_size_limit
+    }
+
+    pub(crate) fn client_key_watcher_limit(&self) -> usize {
+        self.data.client_key_watcher_limit
+    }
+
+    /// Adds `watcher` to the [`KeyWatchHub`] of `database`, registering a new
+    /// hub as the database's replica if needed. Returns the hub and the
+    /// watcher's id within it.
+    pub(crate) fn register_key_watcher(
+        &self,
+        database: &Database,
+        watcher: KeyWatcher,
+    ) -> Result<(KeyWatchHub, u64), bonsaidb_core::Error> {
+        let mut hubs = self.data.key_watch_hubs.lock();
+        let watcher = match hubs.get(database.name()) {
+            Some(hub) => match hub.register(watcher) {
+                Ok(id) => return Ok((hub.clone(), id)),
+                Err(watcher) => watcher,
+            },
+            None => watcher,
+        };
+        let (hub, id) = KeyWatchHub::new(watcher);
+        database.register_key_value_replica(hub.clone())?;
+        hubs.insert(database.name().to_string(), hub.clone());
+        Ok((hub, id))
+    }
+
     pub(crate) fn custom_api_dispatcher(&self, name: &ApiName) -> Option<Arc<dyn AnyHandler<B>>> {
         let dispatchers = self.data.custom_apis.read();
         dispatchers.get(name).cloned()
     }
 
+    pub(crate) fn transaction_validators(
+        &self,
+        collection: &CollectionName,
+    ) -> &[Arc<dyn TransactionValidator<B>>] {
+        self.data
+            .transaction_validators
+            .get(collection)
+            .map_or(&[], Vec::as_slice)
+    }
+
     /// Installs an X.509 certificate used for general purpose connections.
     pub async fn install_self_signed_certificate(&self, overwrite: bool) -> Result<(), Error> {
         let keypair = KeyPair::new_self_signed(&self.data.primary_domain);
@@ -277,11 +365,22 @@ impl<B: Backend> CustomServer<B> {
     }
 
     /// Installs a certificate chain and private key used for TLS connections.
+    ///
+    /// The certificate is validated before it is stored. If validation fails,
+    /// an error is returned and the previously installed certificate remains
+    /// active. Once stored, the new certificate is used for all new TLS
+    /// connections accepted by
+    /// [`listen_for_secure_tcp_on()`](Self::listen_for_secure_tcp_on) without
+    /// interrupting existing connections. QUIC listeners started with
+    /// [`listen_on()`](Self::listen_on) restart their endpoint to use the new
+    /// certificate, which disconnects their clients.
     pub async fn install_certificate(
         &self,
         certificate_chain: &CertificateChain,
         private_key: &PrivateKey,
     ) -> Result<(), Error> {
+        let certified_key = certified_key(certificate_chain, private_key)?;
+
         let db = self.hosted().await;
 
         TlsCertificate::entry_async(&self.data.primary_domain, &db)
@@ -296,7 +395,7 @@ impl<B: Backend> CustomServer<B> {
             })
             .await?;
 
-        self.refresh_certified_key().await?;
+        self.activate_certificate(certified_key);
 
         let pinned_certificate_path = self.pinned_certificate_path();
         if pinned_certificate_path.exists() {
@@ -306,24 +405,32 @@ impl<B: Backend> CustomServer<B> {
         Ok(())
     }
 
-    async fn refresh_certified_key(&self) -> Result<(), Error> {
+    /// Reloads the installed certificate from storage, making it active for
+    /// all new TLS connections without interrupting existing connections.
+    /// QUIC listeners restart their endpoint to use the reloaded certificate.
+    ///
+    /// The stored certificate is validated before it replaces the active
+    /// certificate. If validation fails, an error is returned and the active
+    /// certificate is left unchanged.
+    pub async fn reload_certificate(&self) -> Result<(), Error> {
         let certificate = self.tls_certificate().await?;
+        let certified_key =
+            certified_key(&certificate.certificate_chain, &certificate.private_key.0)?;
+        self.activate_certificate(certified_key);
+        Ok(())
+    }
 
-        let mut cached_key = self.data.primary_tls_key.lock();
-        let private_key = rustls::PrivateKey(
-            fabruic::dangerous::PrivateKey::as_ref(&certificate.private_key.0).to_vec(),
-        );
-        let private_key = rustls::sign::any_ecdsa_type(&Arc::new(private_key))?;
-
-        let certificates = certificate
-            .certificate_chain
-            .iter()
-            .map(|cert| rustls::Certificate(cert.as_ref().to_vec()))
-            .collect::<Vec<_>>();
+    /// Makes `certified_key` the certificate used for new connections. QUIC
+    /// listeners are notified so that they can restart their endpoints.
+    fn activate_certificate(&self, certified_key: Arc<CertifiedKey>) {
+        *self.data.primary_tls_key.lock() = Some(certified_key);
+        // Sending only fails if no QUIC listeners are running.
+        let _: Result<_, _> = self.data.certificate_changes.send(());
+    }
 
-        let certified_key = Arc::new(CertifiedKey::new(certificates, private_key));
-        *cached_key = Some(certified_key);
-        Ok(())
+    #[cfg(test)]
+    pub(crate) fn active_certificate(&self) -> Option<Arc<CertifiedKey>> {
+        self.data.primary_tls_key.lock().clone()
     }
 
     async fn tls_certificate(&self) -> Result<TlsCertificate, Error> {
@@ -377,18 +484,18 @@ impl<B: Backend> CustomServer<B> {
     ///
     /// To ensure that the server only listens for local traffic, specify a
     /// local IP or localhost in addition to the port number.
+    ///
+    /// ## Certificate changes
+    ///
+    /// The endpoint is restarted whenever a certificate is installed or
+    /// reloaded, closing its existing connections. Clients automatically
+    /// reconnect using the new certificate.
     pub async fn listen_on(&self, config: impl Into<BonsaiListenConfig>) -> Result<(), Error> {
         let config = config.into();
-        let certificate = self.tls_certificate().await?;
-        let keypair =
-            KeyPair::from_parts(certificate.certificate_chain, certificate.private_key.0)?;
-        let mut builder = Endpoint::builder();
-        builder.set_protocols([CURRENT_PROTOCOL_VERSION.as_bytes().to_vec()]);
-        builder.set_address(config.address);
-        builder.set_max_idle_timeout(None)?;
-        builder.set_server_key_pair(Some(keypair));
-        builder.set_reuse_address(config.reuse_address);
-        let mut server = builder.build()?;
+        // Subscribe before the certificate is loaded so that a certificate
+        // installed in the meantime restarts the endpoint.
+        let mut certificate_changes = self.data.certificate_changes.subscribe();
+        let mut server = self.bonsai_endpoint(&config).await?;
 
         let mut shutdown_watcher = self
             .data
@@ -397,16 +504,31 @@ impl<B: Backend> CustomServer<B> {
             .await
             .expect("server already shut down");
 
-        while let Some(incoming) = tokio::select! {
-            shutdown_state = shutdown_watcher.wait_for_shutdown() => {
-                drop(server.close_incoming());
-                if matches!(shutdown_state, ShutdownState::GracefulShutdown) {
-                    server.wait_idle().await;
-                }
-                None
-            },
-            msg = server.next() => msg
-        } {
+        loop {
+            let incoming = tokio::select! {
+                shutdown_state = shutdown_watcher.wait_for_shutdown() => {
+                    drop(server.close_incoming());
+                    if matches!(shutdown_state, ShutdownState::GracefulShutdown) {
+                        server.wait_idle().await;
+                    }
+                    None
+                },
+                Ok(()) = certificate_changes.changed() => {
+                    // An endpoint's certificate can't be replaced, so the
+                    // endpoint is restarted with the new certificate. Its
+                    // clients reconnect to the new endpoint.
+                    server.close().await;
+                    drop(server);
+                    server = self.rebuild_bonsai_endpoint(&config).await?;
+                    continue;
+                },
+                msg = server.next() => msg
+            };
+            let incoming = match incoming {
+                Some(incoming) => incoming,
+                None => break,
+            };
+
             let address = incoming.remote_address();
             let connection = match incoming.accept::<()>().await {
                 Ok(connection) => connection,
@@ -426,6 +548,39 @@ impl<B: Backend> CustomServer<B> {
         Ok(())
     }
 
+    /// Builds the endpoint for `config` after its previous endpoint has been
+    /// closed. The previous endpoint's socket is released in the background,
+    /// so binding the address is retried briefly.
+    async fn rebuild_bonsai_endpoint(
+        &self,
+        config: &BonsaiListenConfig,
+    ) -> Result<Endpoint, Error> {
+        let mut attempts = 0;
+        loop {
+            match self.bonsai_endpoint(config).await {
+                Err(_) if attempts < 10 => {
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Builds a QUIC endpoint for `config` using the installed certificate.
+    async fn bonsai_endpoint(&self, config: &BonsaiListenConfig) -> Result<Endpoint, Error> {
+        let certificate = self.tls_certificate().await?;
+        let keypair =
+            KeyPair::from_parts(certificate.certificate_chain, certificate.private_key.0)?;
+        let mut builder = Endpoint::builder();
+        builder.set_protocols([CURRENT_PROTOCOL_VERSION.as_bytes().to_vec()]);
+        builder.set_address(config.address);
+        builder.set_max_idle_timeout(None)?;
+        builder.set_server_key_pair(Some(keypair));
+        builder.set_reuse_address(config.reuse_address);
+        Ok(builder.build()?)
+    }
+
     /// Returns all of the currently connected clients.
     #[must_use]
     pub fn connected_clients(&self) -> Vec<ConnectedClient<B>> {
@@ -433,6 +588,25 @@ impl<B: Backend> CustomServer<B> {
         clients.values().cloned().collect()
     }
 
+    /// Returns the connected client with `client_id`, if it is still
+    /// connected.
+    #[must_use]
+    pub fn connected_client(&self, client_id: u32) -> Option<ConnectedClient<B>> {
+        let clients = self.data.clients.read();
+        clients.get(&client_id).cloned()
+    }
+
+    /// Disconnects the client with `client_id` by closing its transport.
+    /// Returns true if a connected client was found.
+    ///
+    /// The client is removed from [`connected_clients()`](Self::connected_clients)
+    /// once its connection has been torn down, after which
+    /// [`Backend::client_disconnected`] is invoked as usual.
+    pub fn disconnect(&self, client_id: u32) -> bool {
+        self.connected_client(client_id)
+            .map_or(false, |client| client.disconnect())
+    }
+
     /// Sends a custom API response to all connected clients.
     pub fn broadcast<Api: api::Api>(&self, response: &Api::Response) {
         let clients = self.data.clients.read();
@@ -546,6 +720,7 @@ impl<B: Backend> CustomServer<B> {
                                         session_id,
                                         name,
                                         value: Ok(bytes),
+                                        deadline: None,
                                     })
                                     .is_err()
                                 {
@@ -586,12 +761,38 @@ impl<B: Backend> CustomServer<B> {
         mut shutdown: ShutdownStateWatcher,
     ) {
         let notify = Arc::new(Notify::new());
-        let requests_in_queue = Arc::new(AtomicUsize::new(0));
+        let requests_in_queue = client.requests_in_flight_counter().clone();
         loop {
             let current_requests = requests_in_queue.load(Ordering::SeqCst);
             if current_requests == self.data.client_simultaneous_request_limit {
-                // Wait for requests to finish.
-                notify.notified().await;
+                if let Some(retry_after) = self.data.busy_retry_after {
+                    // Reject new requests until one of the client's requests
+                    // finishes.
+                    tokio::select! {
+                        payload = request_receiver.recv_async() => {
+                            if let Ok(payload) = payload {
+                                drop(response_sender.send(Payload {
+                                    session_id: payload.session_id,
+                                    id: payload.id,
+                                    name: payload.name,
+                                    value: Err(bonsaidb_core::Error::Busy {
+                                        retry_after: Some(retry_after),
+                                    }),
+                                    deadline: None,
+                                }));
+                            } else {
+                                return
+                            }
+                        },
+                        _ = notify.notified() => {}
+                        _ = client.wait_for_disconnect() => {
+                            return
+                        }
+                    }
+                } else {
+                    // Wait for requests to finish.
+                    notify.notified().await;
+                }
             } else if requests_in_queue
                 .compare_exchange(
                     current_requests,
@@ -615,6 +816,9 @@ impl<B: Backend> CustomServer<B> {
                                 return
                             }
                         }
+                        _ = client.wait_for_disconnect() => {
+                            return
+                        }
                     }
                 };
                 let session_id = payload.session_id;
@@ -631,6 +835,7 @@ impl<B: Backend> CustomServer<B> {
                             id,
                             name,
                             value,
+                            deadline: None,
                         }));
 
                         requests_in_queue.fetch_sub(1, Ordering::SeqCst);
@@ -720,6 +925,7 @@ impl<B: Backend> CustomServer<B> {
 
         let (request_sender, request_receiver) =
             flume::bounded::<Payload>(self.data.client_simultaneous_request_limit);
+        let connected_client = client.clone();
         let task_self = self.clone();
         tokio::spawn({
             let shutdown = shutdown.clone();
@@ -752,6 +958,9 @@ impl<B: Backend> CustomServer<B> {
                             return Ok(());
                         }
                     }
+                    _ = connected_client.wait_for_disconnect() => {
+                        return Ok(());
+                    }
                 }
             };
             drop(request_sender.send_async(payload?).await);
@@ -920,7 +1129,11 @@ impl<B: Backend> AsyncStorageConnection for CustomServer<B> {
     }
 
     async fn delete_database(&self, name: &str) -> Result<(), bonsaidb_core::Error> {
-        self.storage.delete_database(name).await
+        self.storage.delete_database(name).await?;
+        if let Some(hub) = self.data.key_watch_hubs.lock().remove(name) {
+            hub.retire();
+        }
+        Ok(())
     }
 
     async fn list_databases(&self) -> Result<Vec<connection::Database>, bonsaidb_core::Error> {
@@ -1,20 +1,27 @@
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use async_lock::{Mutex, MutexGuard};
 use bonsaidb_core::api;
 use bonsaidb_core::api::ApiName;
 use bonsaidb_core::arc_bytes::serde::Bytes;
-use bonsaidb_core::connection::{Session, SessionId};
-use bonsaidb_core::networking::MessageReceived;
+use bonsaidb_core::connection::{HasSession, Session, SessionId};
+use bonsaidb_core::keyvalue::{KeyChange, KeyWatchEvent, Timestamp};
+use bonsaidb_core::networking::{KeyChanged, MessageReceived};
+use bonsaidb_core::permissions::bonsai::{
+    kv_resource_name, BonsaiAction, DatabaseAction, KeyValueAction,
+};
 use bonsaidb_core::pubsub::{Receiver, Subscriber as _};
-use bonsaidb_local::Subscriber;
+use bonsaidb_local::{
+    Database, DatabaseNonBlocking, KvReplicationError, KvReplicationSink, ReplicatedKey, Subscriber,
+};
 use bonsaidb_utils::fast_async_lock;
 use derive_where::derive_where;
 use flume::Sender;
-use parking_lot::RwLock;
+use parking_lot::{Mutex as SyncMutex, RwLock};
 
 use crate::{Backend, CustomServer, Error, NoBackend};
 
@@ -41,17 +48,55 @@ struct Data<B: Backend = NoBackend> {
     sessions: RwLock<HashMap<Option<SessionId>, ClientSession>>,
     address: SocketAddr,
     transport: Transport,
+    connected_at: Timestamp,
+    requests_in_flight: Arc<AtomicUsize>,
     response_sender: Sender<(Option<SessionId>, ApiName, Bytes)>,
     client_data: Mutex<Option<B::ClientData>>,
+    /// Dropped to signal the transport that this client should be
+    /// disconnected.
+    disconnect_sender: SyncMutex<Option<Sender<()>>>,
+    disconnected: flume::Receiver<()>,
 }
 
 #[derive(Debug)]
 struct ClientSession {
     session: Session,
     subscribers: HashMap<u64, Subscriber>,
+    key_watchers: KeyWatchers,
+}
+
+/// The key-value watchers of a session, keyed by the client's watch id. All
+/// watchers are unregistered when the session ends.
+#[derive(Debug, Default)]
+struct KeyWatchers(HashMap<u64, (KeyWatchHub, u64)>);
+
+impl KeyWatchers {
+    fn remove(&mut self, watch_id: u64) -> bool {
+        if let Some((hub, id)) = self.0.remove(&watch_id) {
+            hub.unregister(id);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Drop for KeyWatchers {
+    fn drop(&mut self) {
+        for (hub, id) in self.0.values() {
+            hub.unregister(*id);
+        }
+    }
 }
 
 impl<B: Backend> ConnectedClient<B> {
+    /// Returns the unique id of this client. This id is unique among all
+    /// clients currently connected to the server.
+    #[must_use]
+    pub fn id(&self) -> u32 {
+        self.data.id
+    }
+
     /// Returns the address of the connected client.
     #[must_use]
     pub fn address(&self) -> &SocketAddr {
@@ -64,6 +109,47 @@ impl<B: Backend> ConnectedClient<B> {
         &self.data.transport
     }
 
+    /// Returns the time this client connected.
+    #[must_use]
+    pub fn connected_at(&self) -> Timestamp {
+        self.data.connected_at
+    }
+
+    /// Returns the number of requests from this client that are currently
+    /// being processed.
+    #[must_use]
+    pub fn requests_in_flight(&self) -> usize {
+        self.data.requests_in_flight.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn requests_in_flight_counter(&self) -> &Arc<AtomicUsize> {
+        &self.data.requests_in_flight
+    }
+
+    /// Closes this client's connection. Requests that are currently being
+    /// processed will not have their responses delivered, and the client must
+    /// reconnect before it can make further requests.
+    ///
+    /// Returns true if the client was still connected.
+    pub fn disconnect(&self) -> bool {
+        self.data.disconnect_sender.lock().take().is_some()
+    }
+
+    /// Returns true if [`disconnect()`](Self::disconnect) has been called for
+    /// this client.
+    #[must_use]
+    pub fn is_disconnected(&self) -> bool {
+        self.data.disconnect_sender.lock().is_none()
+    }
+
+    /// Waits until [`disconnect()`](Self::disconnect) is called for this
+    /// client.
+    pub(crate) async fn wait_for_disconnect(&self) {
+        // No messages are ever sent, so this only returns once the sender has
+        // been dropped.
+        let _: Result<_, _> = self.data.disconnected.recv_async().await;
+    }
+
     pub(crate) fn logged_in_as(&self, session: Session) {
         let mut sessions = self.data.sessions.write();
         sessions.insert(
@@ -71,6 +157,7 @@ impl<B: Backend> ConnectedClient<B> {
             ClientSession {
                 session,
                 subscribers: HashMap::default(),
+                key_watchers: KeyWatchers::default(),
             },
         );
     }
@@ -235,6 +322,205 @@ impl<B: Backend> ConnectedClient<B> {
             Err(Error::other("bonsaidb-server auth", "invalid session id"))
         }
     }
+
+    pub(crate) fn watch_keys(
+        &self,
+        server: &CustomServer<B>,
+        database: &Database,
+        watch_id: u64,
+        namespace: Option<String>,
+        prefix: Option<String>,
+        session: Option<Session>,
+    ) -> Result<(), crate::Error> {
+        database.check_permission(
+            kv_resource_name(database.name()),
+            &BonsaiAction::Database(DatabaseAction::KeyValue(KeyValueAction::ExecuteOperation)),
+        )?;
+
+        let session_id = session.as_ref().and_then(|session| session.id);
+        let mut sessions = self.data.sessions.write();
+        let watchers = sessions
+            .values()
+            .map(|client_session| client_session.key_watchers.0.len())
+            .sum::<usize>();
+        let client_session = sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| Error::other("bonsaidb-server auth", "invalid session id"))?;
+        if client_session.key_watchers.0.contains_key(&watch_id) {
+            return Err(Error::other(
+                "bonsaidb-server keyvalue",
+                "watch id already in use",
+            ));
+        } else if watchers >= server.client_key_watcher_limit() {
+            return Err(Error::other(
+                "bonsaidb-server keyvalue",
+                "too many key watchers",
+            ));
+        }
+
+        let registration = server.register_key_watcher(
+            database,
+            KeyWatcher {
+                watch_id,
+                response_sender: self.data.response_sender.clone(),
+                session_id,
+                namespace,
+                prefix,
+            },
+        )?;
+        client_session.key_watchers.0.insert(watch_id, registration);
+
+        Ok(())
+    }
+
+    pub(crate) fn unwatch_keys(
+        &self,
+        watch_id: u64,
+        check_session_id: Option<SessionId>,
+    ) -> Result<(), crate::Error> {
+        let mut sessions = self.data.sessions.write();
+        if let Some(client_session) = sessions.get_mut(&check_session_id) {
+            if client_session.key_watchers.remove(watch_id) {
+                Ok(())
+            } else {
+                Err(Error::other("bonsaidb-server keyvalue", "invalid watch id"))
+            }
+        } else {
+            Err(Error::other("bonsaidb-server auth", "invalid session id"))
+        }
+    }
+
+    /// Unregisters the key-value watchers of every session.
+    fn unwatch_all_keys(&self) {
+        let mut sessions = self.data.sessions.write();
+        for client_session in sessions.values_mut() {
+            client_session.key_watchers = KeyWatchers::default();
+        }
+    }
+}
+
+/// Forwards a database's persisted key-value changes to the clients watching
+/// it. Each database has a single hub registered as its replica, no matter
+/// how many clients are watching it.
+#[derive(Debug, Clone)]
+pub(crate) struct KeyWatchHub {
+    /// The registered watchers, or `None` once the hub has been retired.
+    watchers: Arc<SyncMutex<Option<HashMap<u64, KeyWatcher>>>>,
+    last_id: Arc<AtomicU64>,
+}
+
+impl KeyWatchHub {
+    /// Returns a new hub containing `watcher`, along with the watcher's id.
+    pub(crate) fn new(watcher: KeyWatcher) -> (Self, u64) {
+        let mut watchers = HashMap::new();
+        watchers.insert(0, watcher);
+        let hub = Self {
+            watchers: Arc::new(SyncMutex::new(Some(watchers))),
+            last_id: Arc::new(AtomicU64::new(1)),
+        };
+        (hub, 0)
+    }
+
+    /// Adds `watcher` to this hub, returning its id. If the hub has been
+    /// retired, the watcher is returned instead.
+    pub(crate) fn register(&self, watcher: KeyWatcher) -> Result<u64, KeyWatcher> {
+        let mut watchers = self.watchers.lock();
+        if let Some(watchers) = &mut *watchers {
+            let id = self.last_id.fetch_add(1, Ordering::Relaxed);
+            watchers.insert(id, watcher);
+            Ok(id)
+        } else {
+            Err(watcher)
+        }
+    }
+
+    fn unregister(&self, id: u64) {
+        let mut watchers = self.watchers.lock();
+        if let Some(watchers) = &mut *watchers {
+            watchers.remove(&id);
+        }
+    }
+
+    /// Stops forwarding changes. The hub is unregistered from its database
+    /// the next time changes are replicated.
+    pub(crate) fn retire(&self) {
+        self.watchers.lock().take();
+    }
+}
+
+impl KvReplicationSink for KeyWatchHub {
+    fn replicate(&self, changes: &[ReplicatedKey]) -> Result<(), bonsaidb_core::Error> {
+        let watchers = self.watchers.lock();
+        for watcher in watchers.iter().flat_map(HashMap::values) {
+            for change in changes.iter().filter(|change| watcher.matches(change)) {
+                watcher.send(KeyWatchEvent::Changed(KeyChange {
+                    namespace: change.namespace.clone(),
+                    key: change.key.clone(),
+                    value: change.value.clone(),
+                    expiration: change.expiration,
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    fn replication_failed(&self, error: KvReplicationError) {
+        match error {
+            KvReplicationError::BufferFull { dropped_keys } => {
+                let dropped_keys = u64::try_from(dropped_keys).unwrap_or(u64::MAX);
+                let watchers = self.watchers.lock();
+                for watcher in watchers.iter().flat_map(HashMap::values) {
+                    watcher.send(KeyWatchEvent::Lagged { dropped_keys });
+                }
+            }
+            KvReplicationError::Sink(err) => {
+                log::error!("error forwarding key-value changes to clients: {err}");
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.watchers.lock().is_none()
+    }
+}
+
+/// Forwards the persisted key-value changes of a [`KeyWatchHub`] to a
+/// connected client as [`KeyChanged`] events.
+#[derive(Debug)]
+pub(crate) struct KeyWatcher {
+    watch_id: u64,
+    response_sender: Sender<(Option<SessionId>, ApiName, Bytes)>,
+    session_id: Option<SessionId>,
+    namespace: Option<String>,
+    prefix: Option<String>,
+}
+
+impl KeyWatcher {
+    fn matches(&self, change: &ReplicatedKey) -> bool {
+        change.namespace == self.namespace
+            && self
+                .prefix
+                .as_ref()
+                .map_or(true, |prefix| change.key.starts_with(prefix))
+    }
+
+    fn send(&self, event: KeyWatchEvent) {
+        let response = Result::<_, bonsaidb_core::Error>::Ok(KeyChanged {
+            watch_id: self.watch_id,
+            event,
+        });
+        match pot::to_vec(&response) {
+            Ok(encoded) => {
+                // The client unregisters its watchers when it disconnects.
+                let _: Result<_, _> = self.response_sender.send((
+                    self.session_id,
+                    <KeyChanged as api::Api>::name(),
+                    Bytes::from(encoded),
+                ));
+            }
+            Err(err) => log::error!("error encoding a key-value change: {err}"),
+        }
+    }
 }
 
 /// A locked reference to associated client data.
@@ -276,17 +562,23 @@ impl<B: Backend> OwnedClient<B> {
             ClientSession {
                 session: default_session,
                 subscribers: HashMap::default(),
+                key_watchers: KeyWatchers::default(),
             },
         );
+        let (disconnect_sender, disconnected) = flume::bounded(1);
         Self {
             client: ConnectedClient {
                 data: Arc::new(Data {
                     id,
                     address,
                     transport,
+                    connected_at: Timestamp::now(),
+                    requests_in_flight: Arc::default(),
                     response_sender,
                     sessions: RwLock::new(session),
                     client_data: Mutex::default(),
+                    disconnect_sender: SyncMutex::new(Some(disconnect_sender)),
+                    disconnected,
                 }),
             },
             runtime: Arc::new(tokio::runtime::Handle::current()),
@@ -301,6 +593,7 @@ impl<B: Backend> OwnedClient<B> {
 
 impl<B: Backend> Drop for OwnedClient<B> {
     fn drop(&mut self) {
+        self.client.unwatch_all_keys();
         let id = self.client.data.id;
         let server = self.server.take().unwrap();
         self.runtime
@@ -1,20 +1,22 @@
+use std::collections::HashSet;
 use std::ops::Deref;
 
 use async_trait::async_trait;
 use bonsaidb_core::connection::{
     AccessPolicy, AsyncLowLevelConnection, HasSchema, HasSession, Range, SerializedQueryKey, Sort,
+    ViewIndexState,
 };
 use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
-use bonsaidb_core::keyvalue::AsyncKeyValue;
+use bonsaidb_core::keyvalue::{AsyncKeyValue, ExpiringKey, Output, Pipeline, Timestamp, Value};
 use bonsaidb_core::permissions::Permissions;
 use bonsaidb_core::pubsub::AsyncPubSub;
-use bonsaidb_core::schema::view::map::MappedSerializedValue;
+use bonsaidb_core::schema::view::map::{MappedSerializedCount, MappedSerializedValue};
 use bonsaidb_core::schema::{self, CollectionName, Schematic, ViewName};
-use bonsaidb_core::transaction::{OperationResult, Transaction};
+use bonsaidb_core::transaction::{OperationResult, Transaction, ViewChanges};
 use bonsaidb_local::{AsyncDatabase, Database};
 use derive_where::derive_where;
 
-use crate::{Backend, CustomServer, NoBackend};
+use crate::{Backend, BackendError, CustomServer, NoBackend};
 
 /// A database belonging to a [`CustomServer`].
 #[derive_where(Debug, Clone)]
@@ -47,6 +49,40 @@ impl<B: Backend> ServerDatabase<B> {
                 server: self.server.clone(),
             })
     }
+
+    async fn validate_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<(), bonsaidb_core::Error> {
+        let mut validated_collections = HashSet::new();
+        for operation in &transaction.operations {
+            if !validated_collections.insert(&operation.collection) {
+                continue;
+            }
+            for validator in self.server.transaction_validators(&operation.collection) {
+                validation_result(
+                    &operation.collection,
+                    validator.validate(transaction, self).await,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts the result of a [`TransactionValidator`](crate::TransactionValidator)
+/// registered for `collection`.
+fn validation_result<E: std::error::Error>(
+    collection: &CollectionName,
+    result: Result<(), BackendError<E>>,
+) -> Result<(), bonsaidb_core::Error> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(BackendError::Server(err)) => Err(err.into()),
+        Err(BackendError::Backend(err)) => {
+            Err(bonsaidb_core::Error::validation(collection.clone(), err))
+        }
+    }
 }
 
 impl<B: Backend> Deref for ServerDatabase<B> {
@@ -135,6 +171,57 @@ impl<B: Backend> AsyncKeyValue for ServerDatabase<B> {
     ) -> Result<bonsaidb_core::keyvalue::Output, bonsaidb_core::Error> {
         self.db.execute_key_operation(op).await
     }
+
+    async fn keys_expiring_between(
+        &self,
+        start: Timestamp,
+        end: Timestamp,
+    ) -> Result<Vec<ExpiringKey>, bonsaidb_core::Error> {
+        self.db.keys_expiring_between(start, end).await
+    }
+
+    async fn execute_key_expirations(
+        &self,
+        namespace: Option<String>,
+        keys: Vec<String>,
+    ) -> Result<Vec<(String, Option<Timestamp>)>, bonsaidb_core::Error> {
+        self.db.execute_key_expirations(namespace, keys).await
+    }
+
+    async fn execute_namespace_scan(
+        &self,
+        namespace: Option<String>,
+        after: Option<String>,
+        limit: usize,
+    ) -> Result<Vec<(String, Value)>, bonsaidb_core::Error> {
+        self.db
+            .execute_namespace_scan(namespace, after, limit)
+            .await
+    }
+
+    async fn rename_namespace(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.db.rename_namespace(from, to).await
+    }
+
+    async fn restore_to(
+        &self,
+        namespace: Option<&str>,
+        timestamp: Timestamp,
+        target: Option<&str>,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        self.db.restore_to(namespace, timestamp, target).await
+    }
+
+    async fn execute_key_pipeline(
+        &self,
+        pipeline: Pipeline,
+    ) -> Result<Vec<Option<Output>>, bonsaidb_core::Error> {
+        self.db.execute_key_pipeline(pipeline).await
+    }
 }
 
 #[async_trait]
@@ -147,6 +234,26 @@ impl<B: Backend> AsyncLowLevelConnection for ServerDatabase<B> {
         self.db.get_from_collection(id, collection).await
     }
 
+    async fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, bonsaidb_core::Error> {
+        self.db.exists_in_collection(id, collection).await
+    }
+
+    async fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        self.db.get_tombstone_from_collection(id, collection).await
+    }
+
+    async fn purge_tombstones(&self, before: Timestamp) -> Result<u64, bonsaidb_core::Error> {
+        self.db.purge_tombstones(before).await
+    }
+
     async fn list_from_collection(
         &self,
         ids: Range<DocumentId>,
@@ -179,6 +286,19 @@ impl<B: Backend> AsyncLowLevelConnection for ServerDatabase<B> {
         self.db.count_from_collection(ids, collection).await
     }
 
+    async fn delete_all_from_collection(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        for validator in self.server.transaction_validators(collection) {
+            validation_result(
+                collection,
+                validator.validate_delete_all(collection, self).await,
+            )?;
+        }
+        self.db.delete_all_from_collection(collection).await
+    }
+
     async fn get_multiple_from_collection(
         &self,
         ids: &[DocumentId],
@@ -207,6 +327,19 @@ impl<B: Backend> AsyncLowLevelConnection for ServerDatabase<B> {
             .await
     }
 
+    async fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<schema::view::map::MappedSerializedKey>, bonsaidb_core::Error> {
+        self.db
+            .query_keys_by_name(view, key, order, limit, access_policy)
+            .await
+    }
+
     async fn query_by_name_with_docs(
         &self,
         view: &ViewName,
@@ -240,6 +373,28 @@ impl<B: Backend> AsyncLowLevelConnection for ServerDatabase<B> {
             .await
     }
 
+    async fn count_grouped_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, bonsaidb_core::Error> {
+        self.db
+            .count_grouped_by_name(view, key, access_policy)
+            .await
+    }
+
+    async fn view_index_state_by_name(
+        &self,
+        view: &ViewName,
+    ) -> Result<ViewIndexState, bonsaidb_core::Error> {
+        self.db.view_index_state_by_name(view).await
+    }
+
+    async fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), bonsaidb_core::Error> {
+        self.db.rebuild_view_by_name(view).await
+    }
+
     async fn delete_docs_by_name(
         &self,
         view: &ViewName,
@@ -253,8 +408,17 @@ impl<B: Backend> AsyncLowLevelConnection for ServerDatabase<B> {
         &self,
         transaction: Transaction,
     ) -> Result<Vec<OperationResult>, bonsaidb_core::Error> {
+        self.validate_transaction(&transaction).await?;
         self.db.apply_transaction(transaction).await
     }
+
+    async fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), bonsaidb_core::Error> {
+        self.validate_transaction(&transaction).await?;
+        self.db.apply_transaction_with_views(transaction).await
+    }
 }
 
 impl<B: Backend> HasSchema for ServerDatabase<B> {
@@ -70,7 +70,7 @@ impl<B: Backend> CustomServer<B> {
         service: S,
     ) -> Result<(), Error> {
         // We may not have a certificate yet, so we ignore any errors.
-        drop(self.refresh_certified_key().await);
+        drop(self.reload_certificate().await);
 
         #[cfg(feature = "acme")]
         {
@@ -126,6 +126,7 @@ impl<B: Backend> CustomServer<B> {
                         session_id,
                         name,
                         value: Ok(value),
+                        deadline: None,
                     })
                     .is_err()
                 {
@@ -136,7 +137,8 @@ impl<B: Backend> CustomServer<B> {
 
         tokio::spawn(async move {
             while let Ok(response) = message_receiver.recv_async().await {
-                if sender.send(response).await.is_err() {
+                let closing = matches!(response, Message::Close(_));
+                if sender.send(response).await.is_err() || closing {
                     break;
                 }
             }
@@ -161,6 +163,7 @@ impl<B: Backend> CustomServer<B> {
         let (request_sender, request_receiver) =
             flume::bounded::<Payload>(self.data.client_simultaneous_request_limit);
 
+        let connected_client = client.clone();
         self.spawn_client_request_handler(client, request_receiver, response_sender, &shutdown);
 
         loop {
@@ -193,6 +196,10 @@ impl<B: Backend> CustomServer<B> {
                         return;
                     }
                 }
+                _ = connected_client.wait_for_disconnect() => {
+                    drop(message_sender.send(Message::Close(None)));
+                    return;
+                }
             }
         }
     }
@@ -1,10 +1,15 @@
+use async_trait::async_trait;
 use bonsaidb_core::actionable::{Permissions, Statement};
-use bonsaidb_core::connection::AsyncStorageConnection;
-use bonsaidb_core::test_util::{self, BasicSchema, HarnessTest, TestDirectory};
+use bonsaidb_core::connection::{AsyncLowLevelConnection, AsyncStorageConnection};
+use bonsaidb_core::schema::{Collection, SerializedCollection};
+use bonsaidb_core::test_util::{self, Basic, BasicSchema, HarnessTest, TestDirectory};
+use bonsaidb_core::transaction::{Command, Transaction};
+use bonsaidb_local::config::Builder;
+use fabruic::{KeyPair, PrivateKey};
 
 use crate::server::ServerDatabase;
 use crate::test_util::initialize_basic_server;
-use crate::Server;
+use crate::{BackendError, DefaultPermissions, Server, ServerConfiguration, TransactionValidator};
 
 #[tokio::test]
 async fn simple_test() -> anyhow::Result<()> {
@@ -35,6 +40,399 @@ async fn install_self_signed_certificate_tests() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn certificate_reload() -> anyhow::Result<()> {
+    let test_dir = TestDirectory::new("certificate-reload");
+    let server = initialize_basic_server(test_dir.as_ref()).await?;
+    let active_end_entity = || {
+        server
+            .active_certificate()
+            .expect("no active certificate")
+            .cert[0]
+            .0
+            .clone()
+    };
+    let original = active_end_entity();
+
+    // Installing a new certificate makes it active immediately.
+    let keypair = KeyPair::new_self_signed("localhost");
+    server
+        .install_certificate(keypair.certificate_chain(), keypair.private_key())
+        .await?;
+    let installed = active_end_entity();
+    assert_ne!(installed, original);
+    assert_eq!(installed, keypair.end_entity_certificate().as_ref());
+
+    // An invalid certificate is rejected without replacing the active one.
+    let replacement = KeyPair::new_self_signed("localhost");
+    assert!(server
+        .install_certificate(
+            replacement.certificate_chain(),
+            &PrivateKey::unchecked_from_der(vec![0; 8]),
+        )
+        .await
+        .is_err());
+    assert_eq!(active_end_entity(), installed);
+    assert_eq!(
+        server
+            .certificate_chain()
+            .await?
+            .into_end_entity_certificate()
+            .as_ref(),
+        installed
+    );
+
+    // Reloading restores the stored certificate.
+    server.reload_certificate().await?;
+    assert_eq!(active_end_entity(), installed);
+
+    // Installing a certificate while listening applies to new connections.
+    tokio::spawn({
+        let server = server.clone();
+        async move {
+            server
+                .listen_for_secure_tcp_on(CERTIFICATE_RELOAD_ADDRESS, ())
+                .await
+        }
+    });
+    connect_trusting(keypair.end_entity_certificate()).await?;
+    let rotated = KeyPair::new_self_signed("localhost");
+    server
+        .install_certificate(rotated.certificate_chain(), rotated.private_key())
+        .await?;
+    connect_trusting(rotated.end_entity_certificate()).await?;
+    assert!(connect_trusting(keypair.end_entity_certificate())
+        .await
+        .is_err());
+
+    // QUIC listeners restart their endpoint with the reloaded certificate.
+    tokio::spawn({
+        let server = server.clone();
+        async move { server.listen_on(CERTIFICATE_RELOAD_QUIC_PORT).await }
+    });
+    connect_quic_pinned(rotated.end_entity_certificate()).await?;
+    let reloaded = KeyPair::new_self_signed("localhost");
+    server
+        .install_certificate(reloaded.certificate_chain(), reloaded.private_key())
+        .await?;
+    server.reload_certificate().await?;
+    connect_quic_pinned(reloaded.end_entity_certificate()).await?;
+    assert!(connect_quic_pinned(rotated.end_entity_certificate())
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+const CERTIFICATE_RELOAD_ADDRESS: &str = "127.0.0.1:12351";
+const CERTIFICATE_RELOAD_QUIC_PORT: u16 = 12352;
+
+/// Opens a QUIC connection to the server listening on
+/// [`CERTIFICATE_RELOAD_QUIC_PORT`], pinned to `certificate`.
+async fn connect_quic_pinned(certificate: &fabruic::Certificate) -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    use bonsaidb_core::networking::CURRENT_PROTOCOL_VERSION;
+    use fabruic::Endpoint;
+
+    let mut builder = Endpoint::builder();
+    builder.set_protocols([CURRENT_PROTOCOL_VERSION.as_bytes().to_vec()]);
+    let endpoint = builder.build()?;
+    let url = format!("quic://localhost:{CERTIFICATE_RELOAD_QUIC_PORT}");
+
+    // The endpoint is started or restarted in the background, so retry until
+    // it accepts connections.
+    let mut attempts = 0;
+    loop {
+        let connection = async {
+            endpoint
+                .connect_pinned(&url, certificate, None)
+                .await?
+                .accept::<()>()
+                .await
+                .map_err(anyhow::Error::from)
+        };
+        match tokio::time::timeout(Duration::from_secs(1), connection).await {
+            Ok(Ok(_)) => return Ok(()),
+            Ok(Err(_)) | Err(_) if attempts < 10 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Ok(Err(err)) => return Err(err),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Completes a TLS handshake with the server listening on
+/// [`CERTIFICATE_RELOAD_ADDRESS`], trusting only `certificate`.
+async fn connect_trusting(certificate: &fabruic::Certificate) -> anyhow::Result<()> {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio::net::TcpStream;
+    use tokio_rustls::rustls::{self, ClientConfig, RootCertStore, ServerName};
+    use tokio_rustls::TlsConnector;
+
+    let mut roots = RootCertStore::empty();
+    roots.add(&rustls::Certificate(certificate.as_ref().to_vec()))?;
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    // The listener is started in the background, so retry until it accepts
+    // connections.
+    let mut attempts = 0;
+    let stream = loop {
+        match TcpStream::connect(CERTIFICATE_RELOAD_ADDRESS).await {
+            Ok(stream) => break stream,
+            Err(_) if attempts < 50 => {
+                attempts += 1;
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost")?, stream)
+        .await?;
+    Ok(())
+}
+
+/// Rejects updates to [`Basic`] documents whose stored category is "locked".
+#[derive(Debug)]
+struct LockedCategoryValidator;
+
+#[async_trait]
+impl TransactionValidator<crate::NoBackend> for LockedCategoryValidator {
+    async fn validate(
+        &self,
+        transaction: &Transaction,
+        database: &ServerDatabase,
+    ) -> Result<(), BackendError> {
+        for operation in &transaction.operations {
+            let Command::Update { header, .. } = &operation.command else { continue };
+            if operation.collection != Basic::collection_name() {
+                continue;
+            }
+            if let Some(current) = database
+                .get_from_collection(header.id.clone(), &operation.collection)
+                .await?
+            {
+                let current = Basic::deserialize(&current.contents)?;
+                if current.category.as_deref() == Some("locked") {
+                    return Err(BackendError::Server(crate::Error::other(
+                        "validator",
+                        "document is locked",
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn transaction_validators() -> anyhow::Result<()> {
+    let test_dir = TestDirectory::new("transaction-validators");
+    let server = Server::open(
+        ServerConfiguration::new(&test_dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .with_schema::<BasicSchema>()?
+            .with_transaction_validator::<Basic, _>(LockedCategoryValidator),
+    )
+    .await?;
+    let db = server
+        .create_database::<BasicSchema>("tests", false)
+        .await?;
+
+    let mut locked = Basic::new("initial")
+        .with_category("locked")
+        .push_into_async(&db)
+        .await?;
+    let mut unlocked = Basic::new("initial").push_into_async(&db).await?;
+
+    locked.contents.value = String::from("changed");
+    assert!(matches!(
+        locked.update_async(&db).await,
+        Err(bonsaidb_core::Error::Other { .. })
+    ));
+    unlocked.contents.value = String::from("changed");
+    unlocked.update_async(&db).await?;
+
+    let locked = Basic::get_async(&locked.header.id, &db).await?.unwrap();
+    assert_eq!(locked.contents.value, "initial");
+    assert_eq!(locked.header.revision.id, 0);
+    let unlocked = Basic::get_async(&unlocked.header.id, &db).await?.unwrap();
+    assert_eq!(unlocked.contents.value, "changed");
+
+    // Validators reject deleting every document unless they allow it.
+    assert!(matches!(
+        db.delete_all_from_collection(&Basic::collection_name()).await,
+        Err(bonsaidb_core::Error::Validation { .. })
+    ));
+    assert_eq!(Basic::all_async(&db).count().await?, 2);
+
+    Ok(())
+}
+
+/// Connects a mock client to `server` over an in-memory websocket, returning
+/// the client's end of the websocket and the address the server sees the
+/// client connecting from.
+#[cfg(feature = "websockets")]
+async fn connect_mock_websocket(
+    server: &Server,
+) -> anyhow::Result<(
+    tokio_tungstenite::WebSocketStream<tokio::io::DuplexStream>,
+    std::net::SocketAddr,
+)> {
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::WebSocketStream;
+
+    let (client_io, server_io) = tokio::io::duplex(4096);
+    let address = "127.0.0.1:4242".parse()?;
+    tokio::spawn({
+        let server = server.clone();
+        async move {
+            let ws = WebSocketStream::from_raw_socket(server_io, Role::Server, None).await;
+            server.handle_websocket(ws, address).await;
+        }
+    });
+    let client = WebSocketStream::from_raw_socket(client_io, Role::Client, None).await;
+    Ok((client, address))
+}
+
+#[tokio::test]
+#[cfg(feature = "websockets")]
+async fn list_and_disconnect_clients() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    use bonsaidb_core::api::Api;
+    use bonsaidb_core::arc_bytes::serde::Bytes;
+    use bonsaidb_core::networking::{ListDatabases, Payload};
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::Transport;
+
+    let test_dir = TestDirectory::new("list-and-disconnect-clients");
+    let server = initialize_basic_server(test_dir.as_ref()).await?;
+
+    let (mut client, address) = connect_mock_websocket(&server).await?;
+    let list_databases = |id| {
+        Message::Binary(
+            bincode::serialize(&Payload {
+                session_id: None,
+                id: Some(id),
+                name: ListDatabases::name(),
+                value: Ok(Bytes::from(pot::to_vec(&ListDatabases).unwrap())),
+                deadline: None,
+            })
+            .unwrap(),
+        )
+    };
+
+    client.send(list_databases(1)).await?;
+    let response = match client.next().await {
+        Some(Ok(Message::Binary(response))) => response,
+        other => unreachable!("expected a response, got {other:?}"),
+    };
+    let response = bincode::deserialize::<Payload>(&response)?;
+    assert_eq!(response.id, Some(1));
+    assert!(response.value.is_ok());
+
+    let clients = server.connected_clients();
+    assert_eq!(clients.len(), 1);
+    let connected = &clients[0];
+    assert_eq!(connected.address(), &address);
+    assert_eq!(connected.transport(), &Transport::WebSocket);
+    assert_eq!(connected.requests_in_flight(), 0);
+    assert!(connected.connected_at() <= bonsaidb_core::keyvalue::Timestamp::now());
+
+    assert!(server.disconnect(connected.id()));
+    assert!(connected.is_disconnected());
+    // Disconnecting a second time reports that the client is already gone.
+    assert!(!server.disconnect(connected.id()));
+
+    // Any further requests on the connection must not be answered.
+    let response = match client.send(list_databases(2)).await {
+        Ok(()) => loop {
+            match client.next().await {
+                Some(Ok(Message::Close(_))) => continue,
+                other => break other,
+            }
+        },
+        Err(_) => None,
+    };
+    assert!(!matches!(response, Some(Ok(Message::Binary(_)))));
+
+    // The client is removed from the registry once the transport is closed.
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !server.connected_clients().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+#[cfg(feature = "websockets")]
+async fn request_deadlines() -> anyhow::Result<()> {
+    use std::time::Duration;
+
+    use bonsaidb_core::api::Api;
+    use bonsaidb_core::arc_bytes::serde::Bytes;
+    use bonsaidb_core::keyvalue::Timestamp;
+    use bonsaidb_core::networking::{ListDatabases, Payload};
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+    use tokio_tungstenite::WebSocketStream;
+
+    async fn list_databases(
+        client: &mut WebSocketStream<tokio::io::DuplexStream>,
+        id: u32,
+        deadline: Option<Timestamp>,
+    ) -> anyhow::Result<Result<Bytes, bonsaidb_core::Error>> {
+        client
+            .send(Message::Binary(bincode::serialize(&Payload {
+                session_id: None,
+                id: Some(id),
+                name: ListDatabases::name(),
+                value: Ok(Bytes::from(pot::to_vec(&ListDatabases)?)),
+                deadline,
+            })?))
+            .await?;
+        let response = match client.next().await {
+            Some(Ok(Message::Binary(response))) => response,
+            other => unreachable!("expected a response, got {other:?}"),
+        };
+        let response = bincode::deserialize::<Payload>(&response)?;
+        assert_eq!(response.id, Some(id));
+        Ok(response.value)
+    }
+
+    let test_dir = TestDirectory::new("request-deadlines");
+    let server = initialize_basic_server(test_dir.as_ref()).await?;
+    let (mut client, _) = connect_mock_websocket(&server).await?;
+
+    // A request whose deadline has already passed is not executed.
+    let past = Timestamp::from_rfc3339("2000-01-01T00:00:00Z")?;
+    assert!(matches!(
+        list_databases(&mut client, 1, Some(past)).await?,
+        Err(bonsaidb_core::Error::DeadlineExceeded)
+    ));
+
+    // Requests with a future deadline or no deadline are executed normally.
+    let future = Timestamp::now() + Duration::from_secs(60);
+    assert!(list_databases(&mut client, 2, Some(future)).await?.is_ok());
+    assert!(list_databases(&mut client, 3, None).await?.is_ok());
+
+    Ok(())
+}
+
 struct TestHarness {
     _directory: TestDirectory,
     server: Server,
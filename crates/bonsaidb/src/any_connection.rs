@@ -3,14 +3,16 @@ use bonsaidb_core::async_trait::async_trait;
 use bonsaidb_core::connection::{
     self, AccessPolicy, AsyncConnection, AsyncLowLevelConnection, AsyncStorageConnection,
     HasSchema, HasSession, IdentityReference, Range, SerializedQueryKey, Session, Sort,
+    ViewIndexState,
 };
 use bonsaidb_core::document::{DocumentId, Header, OwnedDocument};
-use bonsaidb_core::schema::view::map::MappedSerializedValue;
+use bonsaidb_core::keyvalue::Timestamp;
+use bonsaidb_core::schema::view::map::{MappedSerializedCount, MappedSerializedValue};
 use bonsaidb_core::schema::{
     self, Collection, CollectionName, Nameable, Schema, SchemaName, SchemaSummary, Schematic,
     ViewName,
 };
-use bonsaidb_core::transaction::{Executed, OperationResult, Transaction};
+use bonsaidb_core::transaction::{Executed, OperationResult, Transaction, ViewChanges};
 use bonsaidb_server::{Backend, CustomServer, NoBackend, ServerDatabase};
 use derive_where::derive_where;
 
@@ -322,6 +324,16 @@ impl<B: Backend> AsyncLowLevelConnection for AnyDatabase<B> {
         }
     }
 
+    async fn apply_transaction_with_views(
+        &self,
+        transaction: Transaction,
+    ) -> Result<(Vec<OperationResult>, ViewChanges), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.apply_transaction_with_views(transaction).await,
+            Self::Networked(client) => client.apply_transaction_with_views(transaction).await,
+        }
+    }
+
     async fn get_from_collection(
         &self,
         id: DocumentId,
@@ -333,6 +345,35 @@ impl<B: Backend> AsyncLowLevelConnection for AnyDatabase<B> {
         }
     }
 
+    async fn exists_in_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<bool, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.exists_in_collection(id, collection).await,
+            Self::Networked(client) => client.exists_in_collection(id, collection).await,
+        }
+    }
+
+    async fn get_tombstone_from_collection(
+        &self,
+        id: DocumentId,
+        collection: &CollectionName,
+    ) -> Result<Option<OwnedDocument>, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.get_tombstone_from_collection(id, collection).await,
+            Self::Networked(client) => client.get_tombstone_from_collection(id, collection).await,
+        }
+    }
+
+    async fn purge_tombstones(&self, before: Timestamp) -> Result<u64, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.purge_tombstones(before).await,
+            Self::Networked(client) => client.purge_tombstones(before).await,
+        }
+    }
+
     async fn list_from_collection(
         &self,
         ids: Range<DocumentId>,
@@ -386,6 +427,16 @@ impl<B: Backend> AsyncLowLevelConnection for AnyDatabase<B> {
         }
     }
 
+    async fn delete_all_from_collection(
+        &self,
+        collection: &CollectionName,
+    ) -> Result<u64, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.delete_all_from_collection(collection).await,
+            Self::Networked(client) => client.delete_all_from_collection(collection).await,
+        }
+    }
+
     async fn get_multiple_from_collection(
         &self,
         ids: &[DocumentId],
@@ -429,6 +480,28 @@ impl<B: Backend> AsyncLowLevelConnection for AnyDatabase<B> {
         }
     }
 
+    async fn query_keys_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        order: Sort,
+        limit: Option<u32>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<schema::view::map::MappedSerializedKey>, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => {
+                server
+                    .query_keys_by_name(view, key, order, limit, access_policy)
+                    .await
+            }
+            Self::Networked(client) => {
+                client
+                    .query_keys_by_name(view, key, order, limit, access_policy)
+                    .await
+            }
+        }
+    }
+
     async fn query_by_name_with_docs(
         &self,
         view: &ViewName,
@@ -483,6 +556,35 @@ impl<B: Backend> AsyncLowLevelConnection for AnyDatabase<B> {
         }
     }
 
+    async fn count_grouped_by_name(
+        &self,
+        view: &ViewName,
+        key: Option<SerializedQueryKey>,
+        access_policy: AccessPolicy,
+    ) -> Result<Vec<MappedSerializedCount>, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.count_grouped_by_name(view, key, access_policy).await,
+            Self::Networked(client) => client.count_grouped_by_name(view, key, access_policy).await,
+        }
+    }
+
+    async fn view_index_state_by_name(
+        &self,
+        view: &ViewName,
+    ) -> Result<ViewIndexState, bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.view_index_state_by_name(view).await,
+            Self::Networked(client) => client.view_index_state_by_name(view).await,
+        }
+    }
+
+    async fn rebuild_view_by_name(&self, view: &ViewName) -> Result<(), bonsaidb_core::Error> {
+        match self {
+            Self::Local(server) => server.rebuild_view_by_name(view).await,
+            Self::Networked(client) => client.rebuild_view_by_name(view).await,
+        }
+    }
+
     async fn delete_docs_by_name(
         &self,
         view: &ViewName,
@@ -1,15 +1,26 @@
 //! Tests invoking an API defined in a custom backend.
 
+use std::any::Any;
+
 use bonsaidb::client::url::Url;
 use bonsaidb::client::AsyncClient;
 use bonsaidb::core::api::{Api, Infallible};
 use bonsaidb::core::async_trait::async_trait;
 use bonsaidb::core::test_util::{Basic, TestDirectory};
 use bonsaidb::local::config::Builder;
-use bonsaidb::server::api::Handler;
-use bonsaidb::server::{Backend, CustomServer, DefaultPermissions, ServerConfiguration};
+use bonsaidb::server::api::{Handler, Procedure, ProcedureTransaction};
+use bonsaidb::server::{
+    Backend, BackendError, CustomServer, DefaultPermissions, ServerConfiguration,
+};
 use bonsaidb_core::api::ApiName;
-use bonsaidb_core::schema::Qualified;
+use bonsaidb_core::connection::AsyncStorageConnection;
+use bonsaidb_core::keyvalue::{AsyncKeyValue, Command};
+use bonsaidb_core::networking::ExecuteKeyOperation;
+use bonsaidb_core::permissions::bonsai::{
+    keyvalue_key_resource_name, BonsaiAction, DatabaseAction, KeyValueAction,
+};
+use bonsaidb_core::permissions::Permissions;
+use bonsaidb_core::schema::{Qualified, SerializedCollection};
 use bonsaidb_server::api::{HandlerResult, HandlerSession};
 use serde::{Deserialize, Serialize};
 
@@ -79,3 +90,186 @@ impl Handler<SetValue, CustomBackend> for SetValueHandler {
         Ok(existing_value)
     }
 }
+
+/// A backend that only allows key-value writes that are permitted by
+/// `writers`.
+#[derive(Debug)]
+struct KeyValueWritePolicy {
+    writers: Permissions,
+}
+
+#[async_trait]
+impl Backend for KeyValueWritePolicy {
+    type ClientData = ();
+    type Error = Infallible;
+
+    async fn authorize_request(
+        &self,
+        _session: &HandlerSession<'_, Self>,
+        _api: &ApiName,
+        request: &(dyn Any + Send + Sync),
+    ) -> Result<(), BackendError<Self::Error>> {
+        if let Some(request) = request.downcast_ref::<ExecuteKeyOperation>() {
+            if !matches!(request.op.command, Command::Get { delete: false, .. }) {
+                self.writers.check(
+                    keyvalue_key_resource_name(
+                        &request.database,
+                        request.op.namespace.as_deref(),
+                        &request.op.key,
+                    ),
+                    &BonsaiAction::Database(DatabaseAction::KeyValue(
+                        KeyValueAction::ExecuteOperation,
+                    )),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn authorize_request() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("authorize_request.bonsaidb");
+    let server = CustomServer::<KeyValueWritePolicy>::open(
+        ServerConfiguration::new_with_backend(
+            &dir,
+            KeyValueWritePolicy {
+                writers: Permissions::default(),
+            },
+        )
+        .default_permissions(DefaultPermissions::AllowAll)
+        .with_schema::<Basic>()?,
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    server.create_database::<Basic>("tests", false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    tokio::spawn(async move { server.listen_on(12347).await });
+
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:12347")?)
+        .with_certificate(certificate)
+        .build()?;
+    let db = client.database::<Basic>("tests").await?;
+
+    // Reads are allowed by the policy.
+    assert_eq!(db.get_key("key").into_u64().await?, None);
+
+    // Writes are rejected before being executed.
+    assert!(matches!(
+        db.set_numeric_key("key", 1_u64).await,
+        Err(bonsaidb_core::Error::PermissionDenied(_))
+    ));
+    assert_eq!(db.get_key("key").into_u64().await?, None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn procedure() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("procedure.bonsaidb");
+    let server = CustomServer::<CustomBackend>::open(
+        ServerConfiguration::new(&dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .with_procedure::<ReplaceValueProcedure, _>()?
+            .with_schema::<Basic>()?,
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    server.create_database::<Basic>("tests", false).await?;
+    let id = Basic::new("initial")
+        .push_into_async(&server.database::<Basic>("tests").await?)
+        .await?
+        .header
+        .id;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    tokio::spawn(async move { server.listen_on(12350).await });
+
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:12350")?)
+        .with_api::<ReplaceValue>()
+        .with_certificate(certificate)
+        .build()?;
+
+    // The value is only replaced if it matches the expected value.
+    assert!(
+        client
+            .send_api_request(&ReplaceValue {
+                id,
+                expected: String::from("initial"),
+                new_value: String::from("updated"),
+            })
+            .await?
+    );
+    assert!(
+        !client
+            .send_api_request(&ReplaceValue {
+                id,
+                expected: String::from("initial"),
+                new_value: String::from("ignored"),
+            })
+            .await?
+    );
+    assert!(
+        !client
+            .send_api_request(&ReplaceValue {
+                id: id + 1,
+                expected: String::from("updated"),
+                new_value: String::from("ignored"),
+            })
+            .await?
+    );
+
+    let db = client.database::<Basic>("tests").await?;
+    let doc = Basic::get_async(&id, &db).await?.unwrap();
+    assert_eq!(doc.contents.value, "updated");
+
+    Ok(())
+}
+
+/// Replaces the value of a [`Basic`] document if it currently contains
+/// `expected`, returning whether the value was replaced.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReplaceValue {
+    id: u64,
+    expected: String,
+    new_value: String,
+}
+
+impl Api for ReplaceValue {
+    type Error = Infallible;
+    type Response = bool;
+
+    fn name() -> ApiName {
+        ApiName::private("replace-value")
+    }
+}
+
+#[derive(Debug)]
+struct ReplaceValueProcedure;
+
+#[async_trait]
+impl Procedure<ReplaceValue, CustomBackend> for ReplaceValueProcedure {
+    fn database_name(_request: &ReplaceValue) -> &str {
+        "tests"
+    }
+
+    async fn execute(
+        _session: &HandlerSession<'_, CustomBackend>,
+        transaction: &mut ProcedureTransaction<CustomBackend>,
+        request: &ReplaceValue,
+    ) -> HandlerResult<ReplaceValue> {
+        match transaction.get::<Basic, _>(&request.id).await? {
+            Some(mut doc) if doc.contents.value == request.expected => {
+                doc.contents.value = request.new_value.clone();
+                transaction.update(&doc)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Tests rejecting requests when a client has too many requests in flight.
+
+use std::time::Duration;
+
+use bonsaidb::client::url::Url;
+use bonsaidb::client::AsyncClient;
+use bonsaidb::core::api::{Api, Infallible};
+use bonsaidb::core::async_trait::async_trait;
+use bonsaidb::core::connection::AsyncStorageConnection;
+use bonsaidb::core::test_util::{Basic, TestDirectory};
+use bonsaidb::local::config::Builder;
+use bonsaidb::server::api::{Handler, HandlerResult, HandlerSession};
+use bonsaidb::server::{DefaultPermissions, NoBackend, Server, ServerConfiguration};
+use bonsaidb_core::api::ApiName;
+use serde::{Deserialize, Serialize};
+
+const RETRY_AFTER: Duration = Duration::from_millis(100);
+
+#[tokio::test]
+async fn busy_requests_are_rejected() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("busy.bonsaidb");
+    let server = Server::open(
+        ServerConfiguration::new(&dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .client_simultaneous_request_limit(1)
+            .busy_retry_after(RETRY_AFTER)
+            .with_api::<SlowCall, SlowCall>()?
+            .with_schema::<Basic>()?,
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    tokio::spawn(async move { server.listen_on(12349).await });
+
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:12349")?)
+        .with_api::<SlowCall>()
+        .with_certificate(certificate)
+        .build()?;
+    // Ensure the connection is established before starting the slow request.
+    client.list_databases().await?;
+
+    let slow_call = tokio::spawn({
+        let client = client.clone();
+        async move { client.send_api_request(&SlowCall).await }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The only available request slot is occupied by the slow call.
+    match client.list_databases().await {
+        Err(bonsaidb::core::Error::Busy { retry_after }) => {
+            assert_eq!(retry_after, Some(RETRY_AFTER));
+        }
+        other => unreachable!("expected busy, got {other:?}"),
+    }
+
+    // Once the slow call completes, requests are accepted again.
+    slow_call.await??;
+    client.list_databases().await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SlowCall;
+
+impl Api for SlowCall {
+    type Error = Infallible;
+    type Response = ();
+
+    fn name() -> ApiName {
+        ApiName::private("slow-call")
+    }
+}
+
+#[async_trait]
+impl Handler<SlowCall, NoBackend> for SlowCall {
+    async fn handle(
+        _session: HandlerSession<'_, NoBackend>,
+        _request: SlowCall,
+    ) -> HandlerResult<SlowCall> {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        Ok(())
+    }
+}
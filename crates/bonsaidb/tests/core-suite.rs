@@ -4,16 +4,21 @@ use bonsaidb::client::url::Url;
 use bonsaidb::client::{AsyncClient, AsyncRemoteDatabase};
 use bonsaidb::core::actionable::Permissions;
 use bonsaidb::core::admin::{Admin, PermissionGroup, ADMIN_DATABASE_NAME};
+use bonsaidb::core::async_trait::async_trait;
 use bonsaidb::core::circulate::flume;
 use bonsaidb::core::keyvalue::AsyncKeyValue;
 use bonsaidb::core::permissions::bonsai::{BonsaiAction, ServerAction};
 use bonsaidb::core::permissions::Statement;
-use bonsaidb::core::schema::{InsertError, SerializedCollection};
-use bonsaidb::core::test_util::{BasicSchema, HarnessTest, TestDirectory};
+use bonsaidb::core::schema::{Collection, InsertError, SerializedCollection};
+use bonsaidb::core::test_util::{Basic, BasicSchema, HarnessTest, TestDirectory};
+use bonsaidb::core::transaction::{Command, Transaction};
 use bonsaidb::local::config::Builder;
 use bonsaidb::server::fabruic::Certificate;
 use bonsaidb::server::test_util::{initialize_basic_server, BASIC_SERVER_NAME};
-use bonsaidb::server::{DefaultPermissions, Server, ServerConfiguration};
+use bonsaidb::server::{
+    Backend, BackendError, CustomServer, DefaultPermissions, Server, ServerConfiguration,
+    ServerDatabase, TransactionValidator,
+};
 use bonsaidb_core::connection::{Authentication, AuthenticationMethod, SensitiveString};
 use once_cell::sync::Lazy;
 use rand::distributions::Alphanumeric;
@@ -278,6 +283,157 @@ mod bonsai {
         check_incompatible_client(client).await
     }
 
+    #[tokio::test]
+    async fn streamed_query() -> anyhow::Result<()> {
+        use bonsaidb_core::connection::{
+            AccessPolicy, AsyncLowLevelConnection, AsyncStorageConnection, Sort,
+        };
+        use bonsaidb_core::schema::View;
+        use bonsaidb_core::test_util::{Basic, BasicByParentId};
+        use futures::TryStreamExt;
+
+        let certificate = initialize_shared_server().await;
+        let url = Url::parse(&format!(
+            "bonsaidb://localhost:6000?server={BASIC_SERVER_NAME}"
+        ))?;
+        let client = AsyncClient::build(url)
+            .with_certificate(certificate)
+            .build()?;
+        client
+            .create_database::<BasicSchema>("bonsai-streamed-query", false)
+            .await?;
+        let db = client
+            .database::<BasicSchema>("bonsai-streamed-query")
+            .await?;
+
+        for i in 0..25 {
+            Basic::new(i.to_string())
+                .with_parent_id(i % 3)
+                .push_into_async(&db)
+                .await?;
+        }
+
+        let view = BasicByParentId.view_name();
+        let expected = db
+            .query_by_name(
+                &view,
+                None,
+                Sort::Descending,
+                None,
+                AccessPolicy::UpdateBefore,
+            )
+            .await?;
+        assert_eq!(expected.len(), 25);
+
+        let chunks = db
+            .query_by_name_streamed(
+                &view,
+                None,
+                Sort::Descending,
+                None,
+                AccessPolicy::UpdateBefore,
+                4,
+            )
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(chunks.len(), 7);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 4));
+        let streamed = chunks.into_iter().flatten().collect::<Vec<_>>();
+        assert_eq!(streamed.len(), expected.len());
+        for (streamed, expected) in streamed.iter().zip(&expected) {
+            assert_eq!(streamed.source, expected.source);
+            assert_eq!(streamed.key, expected.key);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watch_keys() -> anyhow::Result<()> {
+        use std::time::Duration;
+
+        use bonsaidb_core::connection::AsyncStorageConnection;
+        use bonsaidb_core::keyvalue::{AsyncKeyValue, KeyWatchEvent, Numeric, Value};
+        use futures::StreamExt;
+
+        let certificate = initialize_shared_server().await;
+        let url = Url::parse(&format!(
+            "bonsaidb://localhost:6000?server={BASIC_SERVER_NAME}"
+        ))?;
+        let client = AsyncClient::build(url)
+            .with_certificate(certificate)
+            .build()?;
+        client
+            .create_database::<BasicSchema>("bonsai-watch-keys", false)
+            .await?;
+        let db = client.database::<BasicSchema>("bonsai-watch-keys").await?;
+
+        let mut watcher = db
+            .watch_keys(Some(String::from("watched")), Some(String::from("a")))
+            .await?;
+
+        // Neither of these changes match the watcher.
+        db.set_numeric_key("abc", 1_u64).await?;
+        db.with_key_namespace("watched")
+            .set_numeric_key("bcd", 2_u64)
+            .await?;
+        db.with_key_namespace("watched")
+            .set_numeric_key("abc", 3_u64)
+            .await?;
+
+        let event = tokio::time::timeout(Duration::from_secs(10), watcher.next())
+            .await?
+            .expect("watcher stream ended");
+        match event {
+            KeyWatchEvent::Changed(changed) => {
+                assert_eq!(changed.namespace.as_deref(), Some("watched"));
+                assert_eq!(changed.key, "abc");
+                assert_eq!(
+                    changed.value,
+                    Some(Value::Numeric(Numeric::UnsignedInteger(3)))
+                );
+                assert_eq!(changed.expiration, None);
+            }
+            other => unreachable!("unexpected event: {other:?}"),
+        }
+
+        db.with_key_namespace("watched").delete_key("abc").await?;
+        let event = tokio::time::timeout(Duration::from_secs(10), watcher.next())
+            .await?
+            .expect("watcher stream ended");
+        match event {
+            KeyWatchEvent::Changed(changed) => {
+                assert_eq!(changed.key, "abc");
+                assert_eq!(changed.value, None);
+            }
+            other => unreachable!("unexpected event: {other:?}"),
+        }
+
+        // Every watcher of the database receives the changes it matches.
+        let mut second_watcher = db.watch_keys(Some(String::from("watched")), None).await?;
+        db.with_key_namespace("watched")
+            .set_numeric_key("ab", 4_u64)
+            .await?;
+        for watcher in [&mut watcher, &mut second_watcher] {
+            let event = tokio::time::timeout(Duration::from_secs(10), watcher.next())
+                .await?
+                .expect("watcher stream ended");
+            match event {
+                KeyWatchEvent::Changed(changed) => assert_eq!(changed.key, "ab"),
+                other => unreachable!("unexpected event: {other:?}"),
+            }
+        }
+
+        // Each client is limited to 16 watchers by default.
+        let mut watchers = Vec::new();
+        for _ in 2..16 {
+            watchers.push(db.watch_keys(None, None).await?);
+        }
+        assert!(db.watch_keys(None, None).await.is_err());
+
+        Ok(())
+    }
+
     bonsaidb_core::define_async_connection_test_suite!(BonsaiTestHarness);
     bonsaidb_core::define_async_pubsub_test_suite!(BonsaiTestHarness);
     bonsaidb_core::define_async_kv_test_suite!(BonsaiTestHarness);
@@ -425,3 +581,94 @@ async fn authenticated_permissions_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[derive(Debug)]
+struct EmptyValueError;
+
+impl std::fmt::Display for EmptyValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("value must not be empty")
+    }
+}
+
+impl std::error::Error for EmptyValueError {}
+
+#[derive(Debug, Default)]
+struct ValidatingBackend;
+
+impl Backend for ValidatingBackend {
+    type ClientData = ();
+    type Error = EmptyValueError;
+}
+
+/// Rejects inserting [`Basic`] documents with an empty value.
+#[derive(Debug)]
+struct NonEmptyValueValidator;
+
+#[async_trait]
+impl TransactionValidator<ValidatingBackend> for NonEmptyValueValidator {
+    async fn validate(
+        &self,
+        transaction: &Transaction,
+        _database: &ServerDatabase<ValidatingBackend>,
+    ) -> Result<(), BackendError<EmptyValueError>> {
+        for operation in &transaction.operations {
+            if let Command::Insert { contents, .. } | Command::InsertWithId { contents, .. } =
+                &operation.command
+            {
+                if Basic::deserialize(contents)?.value.is_empty() {
+                    return Err(BackendError::Backend(EmptyValueError));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn transaction_validation_errors() -> anyhow::Result<()> {
+    use bonsaidb_core::connection::AsyncStorageConnection;
+    let directory = TestDirectory::new("transaction-validation-errors");
+    let server = CustomServer::<ValidatingBackend>::open(
+        ServerConfiguration::new(&directory)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .with_schema::<BasicSchema>()?
+            .with_transaction_validator::<Basic, _>(NonEmptyValueValidator),
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+    server
+        .create_database::<BasicSchema>("tests", false)
+        .await?;
+    tokio::spawn(async move {
+        server.listen_on(6003).await?;
+        Result::<(), anyhow::Error>::Ok(())
+    });
+    // Give the server time to listen
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:6003")?)
+        .with_certificate(certificate)
+        .build()?;
+    let db = client.database::<BasicSchema>("tests").await?;
+    Basic::new("valid").push_into_async(&db).await?;
+
+    let mut tx = Transaction::new();
+    Basic::new("").push_in_transaction(&mut tx)?;
+    match tx.apply_async(&db).await {
+        Err(bonsaidb_core::Error::Validation {
+            collection,
+            message,
+        }) => {
+            assert_eq!(collection, Basic::collection_name());
+            assert_eq!(message, "value must not be empty");
+        }
+        other => unreachable!("expected a validation error: {other:?}"),
+    }
+
+    Ok(())
+}
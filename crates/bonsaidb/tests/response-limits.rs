@@ -0,0 +1,55 @@
+//! Tests the server's response size limit.
+
+use bonsaidb::client::url::Url;
+use bonsaidb::client::AsyncClient;
+use bonsaidb::core::connection::AsyncStorageConnection;
+use bonsaidb::core::schema::SerializedCollection;
+use bonsaidb::core::test_util::{Basic, TestDirectory};
+use bonsaidb::local::config::Builder;
+use bonsaidb::server::{DefaultPermissions, Server, ServerConfiguration};
+
+const RESPONSE_SIZE_LIMIT: usize = 16 * 1024;
+
+#[tokio::test]
+async fn oversized_responses_are_rejected() -> anyhow::Result<()> {
+    let dir = TestDirectory::new("response-limits.bonsaidb");
+    let server = Server::open(
+        ServerConfiguration::new(&dir)
+            .default_permissions(DefaultPermissions::AllowAll)
+            .response_size_limit(RESPONSE_SIZE_LIMIT)
+            .with_schema::<Basic>()?,
+    )
+    .await?;
+    server.install_self_signed_certificate(false).await?;
+    let certificate = server
+        .certificate_chain()
+        .await?
+        .into_end_entity_certificate();
+
+    // Store roughly four times as much data as a single response may contain.
+    let db = server.create_database::<Basic>("large", false).await?;
+    let value = "a".repeat(1024);
+    for _ in 0..64 {
+        Basic::new(&value).push_into_async(&db).await?;
+    }
+
+    tokio::spawn(async move { server.listen_on(12348).await });
+
+    let client = AsyncClient::build(Url::parse("bonsaidb://localhost:12348")?)
+        .with_certificate(certificate)
+        .build()?;
+    let db = client.database::<Basic>("large").await?;
+
+    match db.collection::<Basic>().all().await {
+        Err(bonsaidb::core::Error::ResponseTooLarge { limit }) => {
+            assert_eq!(limit, RESPONSE_SIZE_LIMIT);
+        }
+        other => unreachable!("expected response too large, got {other:?}"),
+    }
+
+    // Paginating the same data keeps each response within the limit.
+    let page = db.collection::<Basic>().all().limit(8).await?;
+    assert_eq!(page.len(), 8);
+
+    Ok(())
+}